@@ -1,5 +1,15 @@
+import type { LatencySummary } from "./types.ts";
+import {
+  FRAME_HEADER_LEN,
+  FRAME_RECORD_LEN,
+  readFrameExtensions,
+  readFrameHeader,
+} from "../tools/native_frame.ts";
+
 export const MAGIC = 0x4d494452; // "MIDR"
-export const VERSION = 1;
+export const VERSION = 2;
+/** Version 1 is version 2 without the flag-gated blocks, so both decode. */
+const SUPPORTED_VERSIONS = [1, VERSION];
 
 export const KIND_CC = 1;
 export const KIND_PB = 2;
@@ -7,6 +17,39 @@ export const KIND_CH_PRESS = 3;
 export const KIND_POLY_PRESS = 4;
 export const KIND_PROG = 5;
 export const KIND_NOTE = 6;
+export const KIND_NOTE_COMPLETE = 15;
+export const KIND_CONNECTED = 16;
+export const KIND_MSC = 17;
+export const KIND_OSC = 18;
+export const KIND_NOTE_EXP = 19;
+export const KIND_MMC = 20;
+
+export const FLAG_LATENCY_SUMMARY = 0x01;
+export const FLAG_MONOTONIC_RAW = 0x20;
+export const FLAG_NOTE_DURATION = 0x40;
+export const FLAG_PARSE_MSC = 0x80;
+export const FLAG_DECODE_OSC_TUNNEL = 0x100;
+export const FLAG_NORMALISE_CC = 0x200;
+export const FLAG_PARSE_MMC = 0x400;
+export const PACKET_FLAG_LATENCY = 0x1;
+
+/** MIDI Machine Control transport commands (`MidiOutput.sendMmc`, `MmcEvent.command`). */
+export const MMC_STOP = 0x01;
+export const MMC_PLAY = 0x02;
+export const MMC_DEFERRED_PLAY = 0x03;
+export const MMC_FAST_FORWARD = 0x04;
+export const MMC_REWIND = 0x05;
+export const MMC_RECORD_STROBE = 0x06;
+export const MMC_RECORD_EXIT = 0x07;
+export const MMC_PAUSE = 0x09;
+export const MMC_EJECT = 0x0a;
+
+/** TLV extension tag: the n-th KIND_OSC record's packet is the n-th TLV_OSC. */
+export const TLV_OSC = 1;
+
+// CC record `extra`: bit 8 set => remapped, low byte = original controller
+// (under FLAG_NORMALISE_CC it holds the raw value instead).
+export const CC_REMAPPED = 0x100;
 
 export type PacketHeader = {
   flags: number;
@@ -14,6 +57,7 @@ export type PacketHeader = {
   droppedRaw: number;
   droppedNote: number;
   recordCount: number;
+  latency?: LatencySummary;
 };
 
 export type Record = {
@@ -26,39 +70,56 @@ export type Record = {
   extra: number;
 };
 
+export function decodeLatency(
+  view: DataView,
+  flags: number,
+  recordCount: number,
+): LatencySummary | undefined {
+  if ((flags & PACKET_FLAG_LATENCY) === 0) return undefined;
+  const offset = FRAME_HEADER_LEN + recordCount * FRAME_RECORD_LEN;
+  if (view.byteLength < offset + 16) return undefined;
+  return {
+    minUs: view.getUint32(offset, true),
+    maxUs: view.getUint32(offset + 4, true),
+    meanUs: view.getUint32(offset + 8, true),
+    count: view.getUint32(offset + 12, true),
+  };
+}
+
+/** The OSC packets of a frame's KIND_OSC records, in record order. */
+export function decodeOscPackets(bytes: Uint8Array, flags: number, recordCount: number): Uint8Array[] {
+  const blockCount = (flags & PACKET_FLAG_LATENCY) ? 1 : 0;
+  return readFrameExtensions(bytes, recordCount, blockCount)
+    .filter((ext) => ext.tag === TLV_OSC)
+    .map((ext) => ext.payload);
+}
+
 export function decodePacket(bytes: Uint8Array): {
   header: PacketHeader;
   records: Record[];
 } {
-  if (bytes.length < 32) {
-    throw new Error("Packet too small");
-  }
   const view = new DataView(bytes.buffer, bytes.byteOffset, bytes.byteLength);
-  const magic = view.getUint32(0, true);
-  if (magic !== MAGIC) {
+  const frame = readFrameHeader(view);
+  if (frame.magic !== MAGIC) {
     throw new Error("Bad magic");
   }
-  const version = view.getUint16(4, true);
-  if (version !== VERSION) {
-    throw new Error(`Unsupported version ${version}`);
+  if (!SUPPORTED_VERSIONS.includes(frame.version)) {
+    throw new Error(`Unsupported version ${frame.version}`);
   }
-  const flags = view.getUint16(6, true);
-  const dispatchTsUs = Number(view.getBigUint64(8, true));
-  const droppedRaw = view.getUint32(16, true);
-  const droppedNote = view.getUint32(20, true);
-  const recordCount = view.getUint32(24, true);
+  const { flags, count: recordCount } = frame;
   const header: PacketHeader = {
     flags,
-    dispatchTsUs,
-    droppedRaw,
-    droppedNote,
+    dispatchTsUs: frame.tsUs,
+    droppedRaw: frame.aux[0],
+    droppedNote: frame.aux[1],
     recordCount,
+    latency: decodeLatency(view, flags, recordCount),
   };
 
-  const available = Math.floor((bytes.length - 32) / 16);
+  const available = Math.floor((bytes.length - FRAME_HEADER_LEN) / FRAME_RECORD_LEN);
   const count = Math.min(recordCount, available);
   const records: Record[] = new Array(count);
-  let offset = 32;
+  let offset = FRAME_HEADER_LEN;
   for (let i = 0; i < count; i++) {
     const tsUs = Number(view.getBigUint64(offset, true));
     const kind = view.getUint8(offset + 8);
@@ -68,7 +129,7 @@ export function decodePacket(bytes: Uint8Array): {
     const v16 = view.getInt16(offset + 12, true);
     const extra = view.getUint16(offset + 14, true);
     records[i] = { tsUs, kind, channel, a, b, v16, extra };
-    offset += 16;
+    offset += FRAME_RECORD_LEN;
   }
 
   return { header, records };
@@ -1,4 +1,5 @@
-import type { PortInfo } from "./types.ts";
+import type { InputStats, PortInfo } from "./types.ts";
+import { combinedLibraryHint, findCombinedLibrary } from "../tools/native_library.ts";
 
 export const CALLBACK_DEF = {
   parameters: ["pointer", "u32"],
@@ -14,10 +15,94 @@ export const FFI_SYMBOLS = {
     parameters: ["pointer", "u32", "u32", "u32", "function"],
     result: "u32",
   },
+  midi_open_input_ex: {
+    parameters: ["pointer", "u32", "pointer", "u32", "function"],
+    result: "u32",
+  },
+  midi_open_input_with_retry: {
+    parameters: ["pointer", "u32", "u32", "u32", "function", "u32", "u32"],
+    result: "u32",
+  },
+  midi_open_mock_input: { parameters: ["buffer", "u32", "function"], result: "u32" },
+  midi_mock_input_send: { parameters: ["u32", "buffer", "u32"], result: "i32" },
+  midi_is_handle_connected: { parameters: ["u32"], result: "u8" },
   midi_close_input: { parameters: ["u32"], result: "void" },
+  midi_input_stats: { parameters: ["u32", "pointer", "u32"], result: "u32" },
+  midi_get_bandwidth_bps: { parameters: ["u32"], result: "u32" },
+  midi_set_program_name: { parameters: ["u8", "pointer", "u32"], result: "void" },
+  midi_get_program_name: { parameters: ["u32", "u8", "pointer", "u32"], result: "u32" },
+  midi_set_cc_display_format: { parameters: ["u8", "pointer", "u32", "pointer", "u32"], result: "void" },
+  midi_get_cc_display_format: { parameters: ["u8", "pointer", "u32"], result: "u32" },
+  midi_get_cc14: { parameters: ["u32", "u8", "u8"], result: "f32" },
+  midi_add_cc_remap: { parameters: ["u32", "u8", "u8", "u8", "u8"], result: "i32" },
+  midi_remove_cc_remap: { parameters: ["u32", "u8", "u8"], result: "i32" },
+  midi_clear_cc_remaps: { parameters: ["u32"], result: "i32" },
+  midi_set_cc_lock_mode: { parameters: ["u32", "u8", "u8", "u8", "u8"], result: "i32" },
+  midi_release_cc_lock: { parameters: ["u32", "u8", "u8"], result: "i32" },
+  midi_get_epoch_us: { parameters: ["u32"], result: "u64" },
+  midi_set_quantise_grid: { parameters: ["u32", "u32", "u32", "u8"], result: "i32" },
+  midi_clear_quantise_grid: { parameters: ["u32"], result: "i32" },
+  midi_set_note_quantise_scale: { parameters: ["u32", "u8", "u8", "u16"], result: "i32" },
+  midi_clear_note_quantise_scale: { parameters: ["u32", "u8"], result: "i32" },
+  midi_set_msc_device_id: { parameters: ["u32", "u8"], result: "i32" },
+  midi_register_note_expression: {
+    parameters: ["u32", "u8", "u8", "u32", "buffer", "u32", "u8"],
+    result: "i32",
+  },
+  midi_clear_note_expressions: { parameters: ["u32"], result: "i32" },
+  midi_set_latency_compensation: { parameters: ["u32", "i64"], result: "i32" },
+  midi_set_note_timeout_ms: { parameters: ["u32", "u32"], result: "i32" },
+  midi_set_beat_dispatch: { parameters: ["u32", "u32"], result: "i32" },
+  midi_set_dispatch_clocks_per_tick: { parameters: ["u32", "u32"], result: "i32" },
+  midi_start_cc_record: { parameters: ["u32", "u8", "u8"], result: "u32" },
+  midi_stop_cc_record: { parameters: ["u32"], result: "i32" },
+  midi_delete_cc_record: { parameters: ["u32"], result: "i32" },
+  midi_playback_cc: { parameters: ["u32", "u32", "u8", "u8", "u32", "u32"], result: "u32" },
+  midi_stop_cc_playback: { parameters: ["u32"], result: "i32" },
+  midi_clock_start: { parameters: ["u32", "f32"], result: "u32" },
+  midi_clock_set_bpm: { parameters: ["u32", "f32"], result: "i32" },
+  midi_clock_set_cc_sync: { parameters: ["u32", "u32", "u8", "u8", "f32", "f32"], result: "i32" },
+  midi_clock_get_bpm: { parameters: ["u32"], result: "f32" },
+  midi_clock_stop: { parameters: ["u32"], result: "i32" },
+  midi_create_pipeline: { parameters: [], result: "u32" },
+  midi_pipeline_add_transpose: { parameters: ["u32", "u8", "i8"], result: "i32" },
+  midi_pipeline_add_channel_remap: { parameters: ["u32", "u8", "u8"], result: "i32" },
+  midi_pipeline_add_cc_remap: { parameters: ["u32", "u8", "u8", "u8", "u8"], result: "i32" },
+  midi_pipeline_add_velocity_scale: { parameters: ["u32", "u8", "u16"], result: "i32" },
+  midi_pipeline_clear: { parameters: ["u32"], result: "i32" },
+  midi_pipeline_attach_input: { parameters: ["u32", "u32"], result: "i32" },
+  midi_pipeline_attach_output: { parameters: ["u32", "u32"], result: "i32" },
+  midi_pipeline_destroy: { parameters: ["u32"], result: "i32" },
   midi_open_output: { parameters: ["pointer", "u32"], result: "u32" },
+  midi_open_output_pool: { parameters: ["pointer", "u32"], result: "u32" },
+  midi_get_pool_member_handles: { parameters: ["u32", "pointer", "u32"], result: "u32" },
   midi_close_output: { parameters: ["u32"], result: "void" },
   midi_send: { parameters: ["u32", "pointer", "u32"], result: "i32" },
+  midi_send_osc_tunnelled: { parameters: ["u32", "buffer", "u32", "u8"], result: "i32" },
+  midi_set_cc14: { parameters: ["u32", "u8", "u8", "f32"], result: "i32" },
+  midi_send_mmc: { parameters: ["u32", "u8", "u8"], result: "i32" },
+  midi_mpe_negotiate: { parameters: ["u32", "u32", "u32"], result: "i32", nonblocking: true },
+  midi_sysex_request_response: {
+    parameters: ["u32", "u32", "buffer", "u32", "buffer", "u32", "u32", "buffer", "u32"],
+    result: "i32",
+    nonblocking: true,
+  },
+  avtools_clock_now_us: { parameters: [], result: "u64" },
+  avtools_clock_offset: { parameters: ["u32", "u32"], result: "i64" },
+  avtools_tracing_start: { parameters: ["buffer", "u32", "u32"], result: "i32" },
+  avtools_tracing_stop: { parameters: [], result: "i32" },
+  av_alloc: { parameters: ["u32"], result: "pointer" },
+  av_free: { parameters: ["pointer", "u32"], result: "void" },
+  midi_abi_version: { parameters: [], result: "u32" },
+  midi_capabilities: { parameters: ["pointer", "u32"], result: "u32" },
+  midi_decode_frame_json: { parameters: ["pointer", "u32", "pointer", "u32"], result: "u32" },
+  midi_last_error_code: { parameters: [], result: "i32" },
+  midi_last_error_message: { parameters: ["buffer", "u32"], result: "u32" },
+  midi_get_last_panic: { parameters: ["buffer", "u32"], result: "u32" },
+  midi_metrics_json: { parameters: ["buffer", "u32"], result: "u32" },
+  midi_set_log_callback: { parameters: ["function"], result: "void", nonblocking: true },
+  midi_set_log_level: { parameters: ["u32"], result: "i32" },
+  avtools_shutdown_all: { parameters: ["buffer", "u32"], result: "u32" },
 } as const;
 
 export type MidiBridgeSymbols = typeof FFI_SYMBOLS;
@@ -27,7 +112,7 @@ const textDecoder = new TextDecoder();
 const textEncoder = new TextEncoder();
 
 function defaultLibUrl(): URL {
-  const base = new URL("../native/midi_bridge/target/release/", import.meta.url);
+  const base = new URL("../native/target/release/", import.meta.url);
   const os = Deno.build.os;
   const candidates =
     os === "windows"
@@ -46,9 +131,12 @@ function defaultLibUrl(): URL {
       // try next
     }
   }
+  const combined = findCombinedLibrary(FFI_SYMBOLS);
+  if (combined) return combined;
 
   throw new Error(
-    `Could not find native midi_bridge library in ${base.toString()} (tried ${candidates.join(", ")})`,
+    `Could not find native midi_bridge library in ${base.toString()} (tried ${candidates.join(", ")}), ` +
+      combinedLibraryHint(),
   );
 }
 
@@ -72,6 +160,18 @@ export function readPortList(
   return JSON.parse(text) as PortInfo[];
 }
 
+export function readInputStats(lib: MidiBridgeLibrary, handle: number): InputStats | null {
+  const required = lib.symbols.midi_input_stats(handle, null, 0);
+  if (required === 0) {
+    return null;
+  }
+  const buf = new Uint8Array(required);
+  const ptr = Deno.UnsafePointer.of(buf);
+  const written = lib.symbols.midi_input_stats(handle, ptr, buf.length);
+  const text = textDecoder.decode(buf.subarray(0, written));
+  return JSON.parse(text) as InputStats;
+}
+
 export function withPortId<T>(portId: string, fn: (ptr: Deno.PointerValue, len: number) => T): T {
   const bytes = textEncoder.encode(portId);
   const ptr = Deno.UnsafePointer.of(bytes);
@@ -4,26 +4,137 @@ import type { PortInfo } from "./types.ts";
 import type { MidiInputOptions } from "./midi_input.ts";
 import { MidiInput } from "./midi_input.ts";
 import { MidiOutput } from "./midi_output.ts";
+import { MidiPipeline } from "./pipeline.ts";
+import { lastNativePanic, nativeError } from "../tools/native_error.ts";
+import { describeFrame } from "../tools/native_frame.ts";
+import { readSizedBuffer } from "../tools/native_buffer.ts";
+import { onUnload, type ShutdownReport, shutdownAll } from "../tools/native_shutdown.ts";
+import { readMetrics, type RuntimeMetrics } from "../tools/native_metrics.ts";
+import { type LogHandler, type LogLevel, logLevelId, NativeLogSink } from "../tools/native_log.ts";
+import { startTracing, type TraceLevel } from "../tools/native_tracing.ts";
 
 export type MidiAccessOptions = {
   libPath?: string;
 };
 
+function inputOptionsJson(options: MidiInputOptions) {
+  return new TextEncoder().encode(JSON.stringify({
+    version: 1,
+    rate_hz: options.rateHz ?? 250,
+    flags: options.flags ?? 0,
+    raw_queue_cap: options.rawQueueCap ?? 0,
+    note_queue_cap: options.noteQueueCap ?? 0,
+  }));
+}
+
 export class MidiAccess {
   #lib: MidiBridgeLibrary;
+  #log: NativeLogSink;
+  #cancelUnload: () => void;
 
   private constructor(lib: MidiBridgeLibrary) {
     this.#lib = lib;
+    this.#log = new NativeLogSink((cb) => lib.symbols.midi_set_log_callback(cb));
+    this.#cancelUnload = onUnload(() => this.shutdownAll());
   }
 
   static open(options: MidiAccessOptions = {}) {
     return new MidiAccess(openLibrary(options.libPath));
   }
 
+  /** Closes anything still open first (see shutdownAll). */
   close() {
+    this.#cancelUnload();
+    this.shutdownAll();
     this.#lib.close();
   }
 
+  /**
+   * Close every input (joining its threads), stop CC playbacks, send
+   * note-offs for held notes plus All Notes Off, and close every output.
+   * Idempotent; also runs on unload. Returns what has been closed so far.
+   */
+  shutdownAll(): ShutdownReport {
+    return shutdownAll((buf, cap) => this.#lib.symbols.avtools_shutdown_all(buf, cap));
+  }
+
+  /**
+   * Runtime metrics of every open input (lifetime drop counts, dispatch
+   * latency) and how many outputs and playbacks are open. Cheap enough to
+   * call every frame; see tools/native_metrics.ts.
+   */
+  metrics(): RuntimeMetrics {
+    return readMetrics((buf, cap) => this.#lib.symbols.midi_metrics_json(buf, cap));
+  }
+
+  /**
+   * Write timing spans of the coalescer, dispatch ticks and CC playback sends
+   * to `path` until stopTracing (see tools/native_tracing.ts). Needs a
+   * library built with the `tracing` feature; "trace" includes every
+   * dispatch tick.
+   */
+  startTracing(path: string, level: TraceLevel = "trace") {
+    if (!startTracing(this.#lib.symbols.avtools_tracing_start, path, level)) {
+      throw this.#error("Failed to start tracing");
+    }
+  }
+
+  /** Finish the trace file. */
+  stopTracing() {
+    if (this.#lib.symbols.avtools_tracing_stop() !== 0) {
+      throw this.#error("Failed to stop tracing");
+    }
+  }
+
+  /** Message of the last midi_bridge export that panicked, if any. */
+  lastPanic(): string | null {
+    return lastNativePanic((buf, cap) => this.#lib.symbols.midi_get_last_panic(buf, cap));
+  }
+
+  #error(fallback: string) {
+    const symbols = this.#lib.symbols;
+    return nativeError({
+      code: () => symbols.midi_last_error_code(),
+      message: (buf, cap) => symbols.midi_last_error_message(buf, cap),
+    }, fallback);
+  }
+
+  /**
+   * Receive midi_bridge's log lines (port errors, retry failures, queue
+   * overflow) instead of stderr; null restores stderr. Clear it before close().
+   */
+  setLogHandler(fn: LogHandler | null): Promise<void> {
+    return this.#log.setHandler(fn);
+  }
+
+  /** Most verbose level midi_bridge logs (default "info"). */
+  setLogLevel(level: LogLevel) {
+    this.#lib.symbols.midi_set_log_level(logLevelId(level));
+  }
+
+  /** ABI version of the loaded midi_bridge library. */
+  get abiVersion(): number {
+    return this.#lib.symbols.midi_abi_version();
+  }
+
+  /** Feature flags of the loaded library, e.g. `{ sysex: false, pipelines: true }`. */
+  capabilities(): Record<string, boolean> {
+    const buf = readSizedBuffer((out, cap) =>
+      this.#lib.symbols.midi_capabilities(out && Deno.UnsafePointer.of(out), cap)
+    );
+    return JSON.parse(new TextDecoder().decode(buf));
+  }
+
+  /**
+   * Pretty-printed JSON for a binary packet (as received by input callbacks),
+   * for debugging. Throws if the bytes are not a frame.
+   */
+  describePacket(bytes: Uint8Array): string {
+    const text = describeFrame(this.#lib.symbols.midi_decode_frame_json, bytes);
+    if (text === null) throw this.#error("Failed to decode packet");
+    return text;
+  }
+
   listInputs(): PortInfo[] {
     return readPortList(this.#lib, "midi_list_inputs");
   }
@@ -32,14 +143,14 @@ export class MidiAccess {
     return readPortList(this.#lib, "midi_list_outputs");
   }
 
-  openInput(portId: string, options: MidiInputOptions = {}) {
-    let handler: (bytes: Uint8Array) => void = () => {};
+  #inputCallback(options: MidiInputOptions) {
+    const target: { input: MidiInput | null } = { input: null };
     const callback: MidiCallback = Deno.UnsafeCallback.threadSafe(CALLBACK_DEF, (ptr, len) => {
       try {
-        if (ptr === null) return;
+        if (ptr === null || !target.input) return;
         const bytes = new Uint8Array(Number(len));
         Deno.UnsafePointerView.copyInto(ptr, bytes);
-        handler(bytes);
+        target.input._handlePacket(bytes);
       } catch (err) {
         console.error("midi input callback error", err);
       }
@@ -48,25 +159,82 @@ export class MidiAccess {
     if (options.keepAlive === false) {
       callback.unref();
     }
+    return { callback, target };
+  }
+
+  openInput(portId: string, options: MidiInputOptions = {}) {
+    const { callback, target } = this.#inputCallback(options);
+
+    const optionsJson = inputOptionsJson(options);
+    const handle = withPortId(portId, (ptr, len) =>
+      this.#lib.symbols.midi_open_input_ex(
+        ptr,
+        len,
+        Deno.UnsafePointer.of(optionsJson),
+        optionsJson.length,
+        callback.pointer,
+      )
+    );
+
+    if (handle === 0) {
+      callback.close();
+      throw this.#error("Failed to open MIDI input");
+    }
+
+    target.input = new MidiInput(this.#lib, handle, callback, options.flags);
+    return target.input;
+  }
 
+  /**
+   * Open an input with no port, fed only by `input.mockSend` (or a native
+   * replay): for tests and for replaying captured sessions.
+   */
+  openMockInput(options: MidiInputOptions = {}) {
+    const { callback, target } = this.#inputCallback(options);
+    const optionsJson = inputOptionsJson(options);
+    const handle = this.#lib.symbols.midi_open_mock_input(optionsJson, optionsJson.length, callback.pointer);
+
+    if (handle === 0) {
+      callback.close();
+      throw this.#error("Failed to open mock MIDI input");
+    }
+
+    target.input = new MidiInput(this.#lib, handle, callback, options.flags);
+    return target.input;
+  }
+
+  /**
+   * Open an input that may not be plugged in yet. The native side retries in
+   * the background with exponential backoff starting at `initialBackoffMs`
+   * (`maxAttempts` 0 = forever); `input.connected` and `onConnected` report
+   * when the port opens, before any of its messages arrive. If the port later
+   * disappears (checked every second), `input.connected` turns false and the
+   * same backoff reopens it, firing `onConnected` again.
+   */
+  openInputWithRetry(
+    portId: string,
+    options: MidiInputOptions & { maxAttempts?: number; initialBackoffMs?: number } = {},
+  ) {
+    const { callback, target } = this.#inputCallback(options);
     const handle = withPortId(portId, (ptr, len) =>
-      this.#lib.symbols.midi_open_input(
+      this.#lib.symbols.midi_open_input_with_retry(
         ptr,
         len,
         options.rateHz ?? 250,
         options.flags ?? 0,
         callback.pointer,
+        options.maxAttempts ?? 0,
+        options.initialBackoffMs ?? 250,
       )
     );
 
     if (handle === 0) {
       callback.close();
-      throw new Error("Failed to open MIDI input");
+      throw this.#error("Failed to open MIDI input");
     }
 
-    const input = new MidiInput(this.#lib, handle, callback);
-    handler = (bytes) => input._handlePacket(bytes);
-    return input;
+    target.input = new MidiInput(this.#lib, handle, callback, options.flags);
+    return target.input;
   }
 
   openOutput(portId: string) {
@@ -74,7 +242,22 @@ export class MidiAccess {
       this.#lib.symbols.midi_open_output(ptr, len)
     );
     if (handle === 0) {
-      throw new Error("Failed to open MIDI output");
+      throw this.#error("Failed to open MIDI output");
+    }
+    return new MidiOutput(this.#lib, handle);
+  }
+
+  /** Empty transform pipeline; see MidiPipeline. */
+  createPipeline() {
+    return new MidiPipeline(this.#lib, this.#lib.symbols.midi_create_pipeline());
+  }
+
+  /** One output over several ports; each send goes to the next port in turn. */
+  openOutputPool(portIds: string[]) {
+    const json = new TextEncoder().encode(JSON.stringify(portIds));
+    const handle = this.#lib.symbols.midi_open_output_pool(Deno.UnsafePointer.of(json), json.length);
+    if (handle === 0) {
+      throw this.#error("Failed to open MIDI output pool");
     }
     return new MidiOutput(this.#lib, handle);
   }
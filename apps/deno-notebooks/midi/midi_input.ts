@@ -1,20 +1,36 @@
 import {
   KIND_CC,
   KIND_CH_PRESS,
+  KIND_CONNECTED,
+  KIND_MMC,
+  KIND_MSC,
   KIND_NOTE,
+  KIND_NOTE_COMPLETE,
+  KIND_NOTE_EXP,
+  KIND_OSC,
   KIND_PB,
   KIND_POLY_PRESS,
   KIND_PROG,
+  decodeLatency,
+  decodeOscPackets,
+  FLAG_NORMALISE_CC,
   MAGIC,
   VERSION,
 } from "./decode.ts";
+import { readInputStats } from "./ffi.ts";
 import type { MidiBridgeLibrary, MidiCallback } from "./ffi.ts";
 import type {
   CCEvent,
   ChannelPressureEvent,
+  InputStats,
+  MmcEvent,
+  MscEvent,
+  NoteCompleteEvent,
   NoteEvent,
+  NoteExpressionEvent,
   NoteOffEvent,
   NoteOnEvent,
+  OscEvent,
   PitchBendEvent,
   PolyPressureEvent,
   ProgramChangeEvent,
@@ -22,11 +38,16 @@ import type {
 } from "./types.ts";
 import type { MPEConfig } from "./mpe.ts";
 import { MPEInput } from "./mpe.ts";
+import type { MidiOutput } from "./midi_output.ts";
 
 export type MidiInputOptions = {
   rateHz?: number;
   flags?: number;
   keepAlive?: boolean;
+  /** Capacity of the callback → coalescer queue (default 4096 messages). */
+  rawQueueCap?: number;
+  /** Capacity of the pending note queue (default 4096 notes). */
+  noteQueueCap?: number;
 };
 
 type Listener<T> = (event: T) => void;
@@ -36,6 +57,7 @@ export class MidiInput {
   #handle: number;
   #callback: MidiCallback;
   #closed = false;
+  #normalised: boolean;
 
   #ccListeners = new Set<Listener<CCEvent>>();
   #pbListeners = new Set<Listener<PitchBendEvent>>();
@@ -45,12 +67,23 @@ export class MidiInput {
   #noteOnListeners = new Set<Listener<NoteOnEvent>>();
   #noteOffListeners = new Set<Listener<NoteOffEvent>>();
   #noteListeners = new Set<Listener<NoteEvent>>();
+  #noteCompleteListeners = new Set<Listener<NoteCompleteEvent>>();
   #tickListeners = new Set<Listener<TickPayload>>();
+  #connectedListeners = new Set<Listener<{ attempts: number }>>();
+  #mscListeners = new Set<Listener<MscEvent>>();
+  #mmcListeners = new Set<Listener<MmcEvent>>();
+  #oscListeners = new Set<Listener<OscEvent>>();
+  #noteExpressionListeners = new Set<Listener<NoteExpressionEvent>>();
 
-  constructor(lib: MidiBridgeLibrary, handle: number, callback: MidiCallback) {
+  constructor(lib: MidiBridgeLibrary, handle: number, callback: MidiCallback, flags = 0) {
     this.#lib = lib;
     this.#handle = handle;
     this.#callback = callback;
+    this.#normalised = (flags & FLAG_NORMALISE_CC) !== 0;
+  }
+
+  get handle(): number {
+    return this.#handle;
   }
 
   close() {
@@ -60,6 +93,131 @@ export class MidiInput {
     this.#closed = true;
   }
 
+  stats(): InputStats | null {
+    if (this.#closed) return null;
+    return readInputStats(this.#lib, this.#handle);
+  }
+
+  /**
+   * The 14-bit controller pair `msbCC` (0–31) + `msbCC + 32` on `channel`, as
+   * of the last dispatch, scaled to 0–1.
+   */
+  cc14(channel: number, msbCC: number): number {
+    const value = this.#lib.symbols.midi_get_cc14(this.#handle, channel, msbCC);
+    if (value < 0) throw new Error("midi_get_cc14 failed");
+    return value;
+  }
+
+  /** Bytes received per second, averaged over the last second; 0 once closed. */
+  bandwidthBps(): number {
+    if (this.#closed) return 0;
+    return this.#lib.symbols.midi_get_bandwidth_bps(this.#handle);
+  }
+
+  /**
+   * Snap incoming notes to the nearest note of a scale (ties snap down).
+   * `degrees` are semitones above `rootNote`, e.g. [0, 2, 4, 5, 7, 9, 11] for
+   * major. `channel` defaults to every channel.
+   */
+  setNoteScale(rootNote: number, degrees: number[], channel = 16) {
+    const mask = degrees.reduce((m, d) => m | (1 << (((d % 12) + 12) % 12)), 0);
+    if (this.#lib.symbols.midi_set_note_quantise_scale(this.#handle, channel, rootNote, mask) !== 0) {
+      throw new Error("midi_set_note_quantise_scale failed");
+    }
+  }
+
+  clearNoteScale(channel = 16) {
+    this.#lib.symbols.midi_clear_note_quantise_scale(this.#handle, channel);
+  }
+
+  /**
+   * Ignore `cc` on `channel` until the incoming value comes within
+   * `threshold` of `currentValue` (the software's value), then follow it:
+   * a knob "picks up" the parameter instead of jumping it.
+   */
+  setCcLockMode(channel: number, cc: number, currentValue: number, threshold = 2) {
+    if (this.#lib.symbols.midi_set_cc_lock_mode(this.#handle, channel, cc, currentValue, threshold) !== 0) {
+      throw new Error("midi_set_cc_lock_mode failed");
+    }
+  }
+
+  /** Follow `cc` again without waiting for the lock; false if it was not locked. */
+  releaseCcLock(channel: number, cc: number): boolean {
+    return this.#lib.symbols.midi_release_cc_lock(this.#handle, channel, cc) === 0;
+  }
+
+  /** MSC device id to answer to (0–127); 127 (the default) accepts every device. */
+  setMscDeviceId(deviceId: number) {
+    if (this.#lib.symbols.midi_set_msc_device_id(this.#handle, deviceId) !== 0) {
+      throw new Error("midi_set_msc_device_id failed");
+    }
+  }
+
+  /**
+   * Report SysEx messages that equal `template` (the whole message, F0 to F7)
+   * in every byte but the one at `valueOffset` as note expression events for
+   * `channel`/`note`, with that byte as the value. `kind` is any id (0–65535)
+   * to tell parameters apart; registering the same channel, note and kind
+   * again replaces the template.
+   */
+  registerNoteExpression(channel: number, note: number, kind: number, template: Uint8Array, valueOffset: number) {
+    const ok = this.#lib.symbols.midi_register_note_expression(
+      this.#handle,
+      channel,
+      note,
+      kind,
+      template,
+      template.length,
+      valueOffset,
+    );
+    if (ok !== 0) {
+      throw new Error("midi_register_note_expression failed");
+    }
+  }
+
+  clearNoteExpressions() {
+    this.#lib.symbols.midi_clear_note_expressions(this.#handle);
+  }
+
+  /**
+   * Set pitch bend range 48 on every member channel (2–16) of an MPE
+   * instrument via `output` and wait up to `timeoutMs` for it to echo the
+   * setting back on this input. Resolves to the number of channels that
+   * acknowledged; rejects if none did.
+   */
+  async negotiateMpe(output: MidiOutput, timeoutMs = 1000): Promise<number> {
+    const acked = await this.#lib.symbols.midi_mpe_negotiate(this.#handle, output.handle, timeoutMs);
+    if (acked < 0) throw new Error("midi_mpe_negotiate: no channel acknowledged the pitch bend range");
+    return acked;
+  }
+
+  /**
+   * Send the SysEx `request` via `output` and resolve to the first SysEx
+   * this input receives that starts with `responsePrefix` (F0 included),
+   * F0 to F7. Rejects after `timeoutMs` or for a response over `maxBytes`.
+   */
+  async requestSysex(
+    output: MidiOutput,
+    request: Uint8Array,
+    responsePrefix: Uint8Array,
+    options: { timeoutMs?: number; maxBytes?: number } = {},
+  ): Promise<Uint8Array> {
+    const buf = new Uint8Array(options.maxBytes ?? 65536);
+    const len = await this.#lib.symbols.midi_sysex_request_response(
+      output.handle,
+      this.#handle,
+      request,
+      request.length,
+      responsePrefix,
+      responsePrefix.length,
+      options.timeoutMs ?? 1000,
+      buf,
+      buf.length,
+    );
+    if (len < 0) throw new Error("midi_sysex_request_response: no matching response, or it did not fit in maxBytes");
+    return buf.slice(0, len);
+  }
+
   onCC(fn: Listener<CCEvent>) {
     this.#ccListeners.add(fn);
     return () => this.#ccListeners.delete(fn);
@@ -100,6 +258,62 @@ export class MidiInput {
     return () => this.#noteListeners.delete(fn);
   }
 
+  /** Paired notes; requires opening the input with FLAG_NOTE_DURATION. */
+  onNoteComplete(fn: Listener<NoteCompleteEvent>) {
+    this.#noteCompleteListeners.add(fn);
+    return () => this.#noteCompleteListeners.delete(fn);
+  }
+
+  /**
+   * False while an input opened with openInputWithRetry is still retrying,
+   * including while it reconnects after its port disappeared.
+   */
+  get connected(): boolean {
+    if (this.#closed) return false;
+    return this.#lib.symbols.midi_is_handle_connected(this.#handle) !== 0;
+  }
+
+  /**
+   * Fires each time a retrying input connects (first open and every
+   * reconnect), with the attempts it took.
+   */
+  onConnected(fn: Listener<{ attempts: number }>) {
+    this.#connectedListeners.add(fn);
+    return () => this.#connectedListeners.delete(fn);
+  }
+
+  /** MIDI Show Control messages; requires opening the input with FLAG_PARSE_MSC. */
+  onMsc(fn: Listener<MscEvent>) {
+    this.#mscListeners.add(fn);
+    return () => this.#mscListeners.delete(fn);
+  }
+
+  /** MIDI Machine Control commands; requires opening the input with FLAG_PARSE_MMC. */
+  onMmc(fn: Listener<MmcEvent>) {
+    this.#mmcListeners.add(fn);
+    return () => this.#mmcListeners.delete(fn);
+  }
+
+  /** OSC tunnelled over SysEx; requires opening the input with FLAG_DECODE_OSC_TUNNEL. */
+  onOsc(fn: Listener<OscEvent>) {
+    this.#oscListeners.add(fn);
+    return () => this.#oscListeners.delete(fn);
+  }
+
+  /** Feed one message to an input from `MidiAccess.openMockInput`, as if it had arrived now. */
+  mockSend(message: Uint8Array | number[]) {
+    const bytes = message instanceof Uint8Array ? message : new Uint8Array(message);
+    if (this.#lib.symbols.midi_mock_input_send(this.#handle, bytes, bytes.length) !== 0) {
+      throw new Error("midi_mock_input_send failed");
+    }
+  }
+
+  /** Per-note parameters registered with `registerNoteExpression`. */
+  onNoteExpression(fn: Listener<NoteExpressionEvent>) {
+    this.#noteExpressionListeners.add(fn);
+    return () => this.#noteExpressionListeners.delete(fn);
+  }
+
   onTick(fn: Listener<TickPayload>) {
     this.#tickListeners.add(fn);
     return () => this.#tickListeners.delete(fn);
@@ -117,6 +331,7 @@ export class MidiInput {
     const version = view.getUint16(4, true);
     if (version !== VERSION) return;
 
+    const flags = view.getUint16(6, true);
     const dispatchTsUs = Number(view.getBigUint64(8, true));
     const droppedRaw = view.getUint32(16, true);
     const droppedNote = view.getUint32(20, true);
@@ -131,15 +346,19 @@ export class MidiInput {
         tsUs: dispatchTsUs,
         droppedRaw,
         droppedNote,
+        latency: decodeLatency(view, flags, recordCount),
         ccChanges: [],
         pbChanges: [],
         chPressureChanges: [],
         polyPressureChanges: [],
         programChanges: [],
         noteEvents: [],
+        noteCompletes: [],
       }
       : null;
 
+    let oscPackets: Uint8Array[] | null = null;
+    let oscIndex = 0;
     let offset = 32;
     for (let i = 0; i < count; i++) {
       const tsUs = Number(view.getBigUint64(offset, true));
@@ -160,6 +379,8 @@ export class MidiInput {
             ctrlVal: b,
             tsUs,
           };
+          // Normalised records carry the value scaled to 0–65535 in v16.
+          if (this.#normalised) payload.normalised = (v16 & 0xffff) / 65535;
           if (tick) tick.ccChanges.push(payload);
           if (this.#ccListeners.size) {
             for (const fn of this.#ccListeners) fn(payload);
@@ -168,7 +389,9 @@ export class MidiInput {
         }
         case KIND_PB: {
           if (this.#pbListeners.size === 0 && !tick) break;
-          const payload: PitchBendEvent = { channel, bend: v16, tsUs };
+          const payload: PitchBendEvent = this.#normalised
+            ? { channel, bend: extra - 8192, normalised: (v16 & 0xffff) / 65535, tsUs }
+            : { channel, bend: v16, tsUs };
           if (tick) tick.pbChanges.push(payload);
           if (this.#pbListeners.size) {
             for (const fn of this.#pbListeners) fn(payload);
@@ -247,6 +470,55 @@ export class MidiInput {
           }
           break;
         }
+        case KIND_NOTE_COMPLETE: {
+          if (this.#noteCompleteListeners.size === 0 && !tick) break;
+          const payload: NoteCompleteEvent = {
+            channel,
+            noteNum: a,
+            velocity: b,
+            offVelocity: v16,
+            durationMs: extra,
+            tsUs,
+          };
+          if (tick) tick.noteCompletes.push(payload);
+          if (this.#noteCompleteListeners.size) {
+            for (const fn of this.#noteCompleteListeners) fn(payload);
+          }
+          break;
+        }
+        case KIND_CONNECTED: {
+          for (const fn of this.#connectedListeners) fn({ attempts: extra });
+          break;
+        }
+        case KIND_MSC: {
+          const payload: MscEvent = {
+            deviceId: channel,
+            commandFormat: a,
+            command: b,
+            dataLength: extra,
+            tsUs,
+          };
+          for (const fn of this.#mscListeners) fn(payload);
+          break;
+        }
+        case KIND_MMC: {
+          const payload: MmcEvent = { deviceId: channel, command: a, dataLength: extra, tsUs };
+          for (const fn of this.#mmcListeners) fn(payload);
+          break;
+        }
+        case KIND_OSC: {
+          oscPackets ??= decodeOscPackets(bytes, flags, recordCount);
+          const packet = oscPackets[oscIndex++];
+          if (!packet || this.#oscListeners.size === 0) break;
+          const payload: OscEvent = { deviceId: channel, packet, tsUs };
+          for (const fn of this.#oscListeners) fn(payload);
+          break;
+        }
+        case KIND_NOTE_EXP: {
+          const payload: NoteExpressionEvent = { channel, noteNum: a, kind: extra, value: b, tsUs };
+          for (const fn of this.#noteExpressionListeners) fn(payload);
+          break;
+        }
         default:
           break;
       }
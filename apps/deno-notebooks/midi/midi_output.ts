@@ -1,4 +1,5 @@
 import type { MidiBridgeLibrary } from "./ffi.ts";
+import type { MidiInput } from "./midi_input.ts";
 
 export class MidiOutput {
   #lib: MidiBridgeLibrary;
@@ -10,6 +11,10 @@ export class MidiOutput {
     this.#handle = handle;
   }
 
+  get handle(): number {
+    return this.#handle;
+  }
+
   close() {
     if (this.#closed) return;
     this.#lib.symbols.midi_close_output(this.#handle);
@@ -22,6 +27,37 @@ export class MidiOutput {
     this.#lib.symbols.midi_send(this.#handle, ptr, buf.length);
   }
 
+  /**
+   * Send an OSC packet (message or bundle bytes) tunnelled in SysEx, for an
+   * input opened with FLAG_DECODE_OSC_TUNNEL. Throws if it is not valid OSC.
+   */
+  sendOscTunnelled(packet: Uint8Array, deviceId = 0x7f) {
+    if (this.#lib.symbols.midi_send_osc_tunnelled(this.#handle, packet, packet.length, deviceId) !== 0) {
+      throw new Error("midi_send_osc_tunnelled failed");
+    }
+  }
+
+  /** Send an MMC transport command (MMC_STOP, MMC_PLAY, ...); 0x7f addresses every device. */
+  sendMmc(command: number, deviceId = 0x7f) {
+    if (this.#lib.symbols.midi_send_mmc(this.#handle, deviceId, command) !== 0) {
+      throw new Error("midi_send_mmc failed");
+    }
+  }
+
+  /** Send `value` (0–1) as a 14-bit controller pair: MSB on `msbCC` (0–31), LSB on `msbCC + 32`. */
+  cc14(channel: number, msbCC: number, value: number) {
+    if (this.#lib.symbols.midi_set_cc14(this.#handle, channel, msbCC, value) !== 0) {
+      throw new Error("midi_set_cc14 failed");
+    }
+  }
+
+  /** Send MIDI timing clock (24 per quarter note) at `bpm` (1–999) until stopped. */
+  startClock(bpm: number): MidiClock {
+    const id = this.#lib.symbols.midi_clock_start(this.#handle, bpm);
+    if (id === 0) throw new Error("midi_clock_start failed");
+    return new MidiClock(this.#lib, id);
+  }
+
   cc(channel: number, ctrlNum: number, ctrlVal: number) {
     const status = 0xB0 | (channel & 0x0f);
     this.send([status, ctrlNum & 0x7f, ctrlVal & 0x7f]);
@@ -56,6 +92,43 @@ export class MidiOutput {
   }
 }
 
+/** A running clock generator from `MidiOutput.startClock`. */
+export class MidiClock {
+  #lib: MidiBridgeLibrary;
+  #id: number;
+
+  constructor(lib: MidiBridgeLibrary, id: number) {
+    this.#lib = lib;
+    this.#id = id;
+  }
+
+  /** Current tempo, including changes made by a CC sync. */
+  get bpm(): number {
+    return this.#lib.symbols.midi_clock_get_bpm(this.#id);
+  }
+
+  /** Set the tempo; replaces any CC sync. */
+  set bpm(bpm: number) {
+    if (this.#lib.symbols.midi_clock_set_bpm(this.#id, bpm) !== 0) {
+      throw new Error("midi_clock_set_bpm failed");
+    }
+  }
+
+  /**
+   * Follow CC `cc` on `channel` of `input`, 0–127 mapped linearly to
+   * `minBpm`–`maxBpm`, natively and without JS on the path. Null stops
+   * following and keeps the current tempo.
+   */
+  syncToCc(input: MidiInput | null, channel = 0, cc = 0, minBpm = 60, maxBpm = 180) {
+    const ok = this.#lib.symbols.midi_clock_set_cc_sync(this.#id, input?.handle ?? 0, channel, cc, minBpm, maxBpm);
+    if (ok !== 0) throw new Error("midi_clock_set_cc_sync failed");
+  }
+
+  stop() {
+    this.#lib.symbols.midi_clock_stop(this.#id);
+  }
+}
+
 function clamp(value: number, min: number, max: number) {
   return Math.max(min, Math.min(max, value));
 }
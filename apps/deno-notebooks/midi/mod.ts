@@ -1,11 +1,18 @@
 export { MidiAccess } from "./midi_access.ts";
 export { MidiInput } from "./midi_input.ts";
 export { MidiOutput } from "./midi_output.ts";
+export { ALL_CHANNELS, MidiPipeline } from "./pipeline.ts";
 export { MPEInput } from "./mpe.ts";
 export { MPEDevice, MPENoteRef } from "./mpe_device.ts";
 export type {
   CCEvent,
   ChannelPressureEvent,
+  InputStats,
+  LatencySummary,
+  MmcEvent,
+  MscEvent,
+  OscEvent,
+  NoteCompleteEvent,
   NoteEvent,
   NoteOffEvent,
   NoteOnEvent,
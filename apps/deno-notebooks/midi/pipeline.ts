@@ -0,0 +1,72 @@
+import type { MidiBridgeLibrary } from "./ffi.ts";
+import type { MidiInput } from "./midi_input.ts";
+import type { MidiOutput } from "./midi_output.ts";
+
+/** Channel value for transforms that apply to every channel. */
+export const ALL_CHANNELS = 16;
+
+/**
+ * Ordered native transforms applied to attached inputs before coalescing,
+ * with optional MIDI thru to outputs. Methods return `this` for chaining.
+ */
+export class MidiPipeline {
+  #lib: MidiBridgeLibrary;
+  #handle: number;
+  #destroyed = false;
+
+  constructor(lib: MidiBridgeLibrary, handle: number) {
+    this.#lib = lib;
+    this.#handle = handle;
+  }
+
+  #check(rc: number, name: string) {
+    if (rc !== 0) throw new Error(`${name} failed`);
+    return this;
+  }
+
+  transpose(channel: number, semitones: number) {
+    const rc = this.#lib.symbols.midi_pipeline_add_transpose(this.#handle, channel, semitones);
+    return this.#check(rc, "midi_pipeline_add_transpose");
+  }
+
+  remapChannel(from: number, to: number) {
+    const rc = this.#lib.symbols.midi_pipeline_add_channel_remap(this.#handle, from, to);
+    return this.#check(rc, "midi_pipeline_add_channel_remap");
+  }
+
+  remapCC(fromChannel: number, fromCC: number, toChannel: number, toCC: number) {
+    const rc = this.#lib.symbols.midi_pipeline_add_cc_remap(
+      this.#handle,
+      fromChannel,
+      fromCC,
+      toChannel,
+      toCC,
+    );
+    return this.#check(rc, "midi_pipeline_add_cc_remap");
+  }
+
+  scaleVelocity(channel: number, percent: number) {
+    const rc = this.#lib.symbols.midi_pipeline_add_velocity_scale(this.#handle, channel, percent);
+    return this.#check(rc, "midi_pipeline_add_velocity_scale");
+  }
+
+  clear() {
+    return this.#check(this.#lib.symbols.midi_pipeline_clear(this.#handle), "midi_pipeline_clear");
+  }
+
+  attachInput(input: MidiInput) {
+    const rc = this.#lib.symbols.midi_pipeline_attach_input(this.#handle, input.handle);
+    return this.#check(rc, "midi_pipeline_attach_input");
+  }
+
+  attachOutput(output: MidiOutput) {
+    const rc = this.#lib.symbols.midi_pipeline_attach_output(this.#handle, output.handle);
+    return this.#check(rc, "midi_pipeline_attach_output");
+  }
+
+  destroy() {
+    if (this.#destroyed) return;
+    this.#lib.symbols.midi_pipeline_destroy(this.#handle);
+    this.#destroyed = true;
+  }
+}
@@ -1,3 +1,4 @@
+import { FLAG_NORMALISE_CC } from "../decode.ts";
 import { openMidi, pickLoopback, sleep } from "./_helpers.ts";
 
 const { midi } = openMidi(Deno.args[0]);
@@ -8,7 +9,8 @@ const pair = pickLoopback(inputs, outputs);
 console.log(`Using input: ${pair.input.name}`);
 console.log(`Using output: ${pair.output.name}`);
 
-const input = midi.openInput(pair.input.id, { rateHz: 20, keepAlive: false });
+// Normalised values ride along in the same records, so check them too.
+const input = midi.openInput(pair.input.id, { rateHz: 20, keepAlive: false, flags: FLAG_NORMALISE_CC });
 const output = midi.openOutput(pair.output.id);
 
 const targetCc = 74;
@@ -35,6 +37,11 @@ input.onTick((tick) => {
     rejectDone(new Error(`Expected ${values[values.length - 1]}, got ${cc.ctrlVal}`));
     return;
   }
+  const expected = values[values.length - 1] / 127;
+  if (cc.normalised === undefined || Math.abs(cc.normalised - expected) > 1 / 65535) {
+    rejectDone(new Error(`Expected normalised ${expected}, got ${cc.normalised}`));
+    return;
+  }
   if (!resolved) {
     resolved = true;
     clearTimeout(timeout);
@@ -0,0 +1,33 @@
+import { openLibrary as openMidiLibrary } from "../ffi.ts";
+import { openLibrary as openWindowLibrary } from "../../window/ffi.ts";
+import { avtoolsClockNowUs, fastNowUs } from "../../tools/fast_sleep.ts";
+
+// Every native library exports avtools_clock_now_us() backed by the same OS
+// clock; readings taken back to back must be ordered and close together.
+const TOLERANCE_US = 1000;
+const ROUNDS = 100;
+
+const midiLib = openMidiLibrary(Deno.args[0]);
+const windowLib = openWindowLibrary(Deno.args[1]);
+
+let worstSpreadUs = 0;
+for (let i = 0; i < ROUNDS; i++) {
+  const a = avtoolsClockNowUs();
+  const b = Number(midiLib.symbols.avtools_clock_now_us());
+  const c = Number(windowLib.symbols.avtools_clock_now_us());
+  const d = fastNowUs();
+  if (!(a <= b && b <= c && c <= d)) {
+    throw new Error(`Clock readings out of order: fastsleep=${a} midi=${b} window=${c} fast_now_us=${d}`);
+  }
+  worstSpreadUs = Math.max(worstSpreadUs, d - a);
+}
+
+console.log(`Worst spread over ${ROUNDS} rounds: ${worstSpreadUs}µs`);
+if (worstSpreadUs > TOLERANCE_US) {
+  throw new Error(`Clocks disagree by ${worstSpreadUs}µs (tolerance ${TOLERANCE_US}µs)`);
+}
+
+midiLib.close();
+windowLib.close();
+console.log("Shared clock OK");
+Deno.exit(0);
@@ -0,0 +1,87 @@
+// Checks each native library's exported symbols against its committed
+// `abi.txt` snapshot. Adding, removing or renaming an export must come with an
+// ABI_VERSION bump; rerun with `--update` after bumping to refresh snapshots.
+//
+//   deno run --allow-read --allow-write midi/tests/06_abi_snapshot.ts [--update]
+
+const NATIVE = new URL("../../native/", import.meta.url);
+const LIBRARIES = ["deno_window", "midi_bridge", "fastsleep"];
+const update = Deno.args.includes("--update");
+
+function rustSources(dir: URL): string[] {
+  const out: string[] = [];
+  for (const entry of Deno.readDirSync(dir)) {
+    if (entry.isDirectory) out.push(...rustSources(new URL(`${entry.name}/`, dir)));
+    else if (entry.name.endsWith(".rs")) out.push(Deno.readTextFileSync(new URL(entry.name, dir)));
+  }
+  return out;
+}
+
+/** `#[no_mangle]` functions of the crate and of its path dependencies. */
+function exportedSymbols(crate: string): string[] {
+  const crateDir = new URL(`${crate}/`, NATIVE);
+  const manifest = Deno.readTextFileSync(new URL("Cargo.toml", crateDir));
+  const deps = [...manifest.matchAll(/path\s*=\s*"\.\.\/([\w-]+)"/g)].map((m) => m[1]);
+  const symbols = new Set<string>();
+  for (const dir of [crate, ...deps]) {
+    for (const source of rustSources(new URL(`${dir}/src/`, NATIVE))) {
+      for (const m of source.matchAll(/#\[no_mangle\]\s*(?:#\[[^\]]*\]\s*)*pub\s+(?:unsafe\s+)?extern\s+"C"\s+fn\s+(\w+)/g)) {
+        symbols.add(m[1]);
+      }
+    }
+  }
+  return [...symbols].sort();
+}
+
+function abiVersion(crate: string): number {
+  const lib = Deno.readTextFileSync(new URL(`${crate}/src/lib.rs`, NATIVE));
+  const m = lib.match(/pub const ABI_VERSION: u32 = (\d+);/);
+  if (!m) throw new Error(`${crate}: ABI_VERSION not found`);
+  return Number(m[1]);
+}
+
+function readSnapshot(path: URL): { version: number; symbols: string[] } | null {
+  let text: string;
+  try {
+    text = Deno.readTextFileSync(path);
+  } catch {
+    return null;
+  }
+  const lines = text.split("\n").map((l) => l.trim()).filter((l) => l && !l.startsWith("#"));
+  const version = Number(lines.shift()?.replace(/^version\s+/, ""));
+  return { version, symbols: lines };
+}
+
+let failed = false;
+for (const crate of LIBRARIES) {
+  const path = new URL(`${crate}/abi.txt`, NATIVE);
+  const symbols = exportedSymbols(crate);
+  const version = abiVersion(crate);
+  const snapshot = readSnapshot(path);
+  const added = symbols.filter((s) => !snapshot?.symbols.includes(s));
+  const removed = snapshot?.symbols.filter((s) => !symbols.includes(s)) ?? [];
+  const changed = added.length > 0 || removed.length > 0;
+
+  if (snapshot && changed && version === snapshot.version) {
+    console.error(`${crate}: exports changed without an ABI_VERSION bump (still ${version})`);
+    for (const s of added) console.error(`  + ${s}`);
+    for (const s of removed) console.error(`  - ${s}`);
+    failed = true;
+    continue;
+  }
+  if (!snapshot || changed || version !== snapshot.version) {
+    if (!update) {
+      console.error(`${crate}: abi.txt is out of date (ABI ${version}); rerun with --update`);
+      failed = true;
+      continue;
+    }
+    const header = `# Exported symbols of ${crate}; checked by midi/tests/06_abi_snapshot.ts.\n`;
+    Deno.writeTextFileSync(path, `${header}version ${version}\n${symbols.join("\n")}\n`);
+    console.log(`${crate}: wrote abi.txt (ABI ${version}, ${symbols.length} symbols)`);
+    continue;
+  }
+  console.log(`${crate}: ABI ${version}, ${symbols.length} symbols OK`);
+}
+
+if (failed) Deno.exit(1);
+Deno.exit(0);
@@ -0,0 +1,140 @@
+import { openLibrary as openMidiLibrary } from "../ffi.ts";
+import { openLibrary as openWindowLibrary } from "../../window/ffi.ts";
+import { decodePacket, MAGIC, PACKET_FLAG_LATENCY, VERSION } from "../decode.ts";
+import { describeFrame, FRAME_FLAG_TLV } from "../../tools/native_frame.ts";
+
+// Builds random MIDR frames and checks that the TS decoder and the native
+// avtools_frame decoder (via midi_decode_frame_json) agree on every field.
+// window_decode_frame_json has no MIDR schema, so only its header is checked.
+const ROUNDS = 500;
+
+const midiLib = openMidiLibrary(Deno.args[0]);
+const windowLib = openWindowLibrary(Deno.args[1]);
+
+const randInt = (max: number) => Math.floor(Math.random() * max);
+
+function randomFrame() {
+  const records = Array.from({ length: randInt(20) }, () => ({
+    tsUs: randInt(2 ** 48),
+    kind: randInt(256),
+    channel: randInt(16),
+    a: randInt(128),
+    b: randInt(128),
+    v16: randInt(65536) - 32768,
+    extra: randInt(65536),
+  }));
+  const latency = Math.random() < 0.5
+    ? { minUs: randInt(2 ** 32), maxUs: randInt(2 ** 32), meanUs: randInt(2 ** 32), count: randInt(2 ** 32) }
+    : undefined;
+  const tlvs = Math.random() < 0.3
+    ? Array.from({ length: 1 + randInt(3) }, () => ({
+      tag: randInt(65536),
+      payload: Uint8Array.from({ length: randInt(24) }, () => randInt(256)),
+    }))
+    : [];
+  const flags = (latency ? PACKET_FLAG_LATENCY : 0) | (tlvs.length ? FRAME_FLAG_TLV : 0);
+  const size = 32 + records.length * 16 + (latency ? 16 : 0) +
+    tlvs.reduce((n, t) => n + 4 + t.payload.length, 0);
+  const bytes = new Uint8Array(size);
+  const view = new DataView(bytes.buffer);
+  const header = {
+    flags,
+    dispatchTsUs: randInt(2 ** 48),
+    droppedRaw: randInt(2 ** 32),
+    droppedNote: randInt(2 ** 32),
+    recordCount: records.length,
+  };
+  view.setUint32(0, MAGIC, true);
+  view.setUint16(4, VERSION, true);
+  view.setUint16(6, flags, true);
+  view.setBigUint64(8, BigInt(header.dispatchTsUs), true);
+  view.setUint32(16, header.droppedRaw, true);
+  view.setUint32(20, header.droppedNote, true);
+  view.setUint32(24, records.length, true);
+  let at = 32;
+  for (const r of records) {
+    view.setBigUint64(at, BigInt(r.tsUs), true);
+    view.setUint8(at + 8, r.kind);
+    view.setUint8(at + 9, r.channel);
+    view.setUint8(at + 10, r.a);
+    view.setUint8(at + 11, r.b);
+    view.setInt16(at + 12, r.v16, true);
+    view.setUint16(at + 14, r.extra, true);
+    at += 16;
+  }
+  if (latency) {
+    view.setUint32(at, latency.minUs, true);
+    view.setUint32(at + 4, latency.maxUs, true);
+    view.setUint32(at + 8, latency.meanUs, true);
+    view.setUint32(at + 12, latency.count, true);
+    at += 16;
+  }
+  for (const t of tlvs) {
+    view.setUint16(at, t.tag, true);
+    view.setUint16(at + 2, t.payload.length, true);
+    bytes.set(t.payload, at + 4);
+    at += 4 + t.payload.length;
+  }
+  return { bytes, header: { ...header, latency }, records, tlvs };
+}
+
+function assertEqual(actual: unknown, expected: unknown, what: string) {
+  if (JSON.stringify(actual) !== JSON.stringify(expected)) {
+    throw new Error(`${what}: expected ${JSON.stringify(expected)}, got ${JSON.stringify(actual)}`);
+  }
+}
+
+const hex = (bytes: Uint8Array) => [...bytes].map((b) => b.toString(16).padStart(2, "0")).join("");
+
+for (let i = 0; i < ROUNDS; i++) {
+  const frame = randomFrame();
+
+  const ts = decodePacket(frame.bytes);
+  assertEqual(ts.header, frame.header, "TS header");
+  assertEqual(ts.records, frame.records, "TS records");
+
+  const text = describeFrame(midiLib.symbols.midi_decode_frame_json, frame.bytes);
+  if (text === null) throw new Error("midi_decode_frame_json rejected a valid frame");
+  const native = JSON.parse(text);
+  assertEqual(native.header.magic, "MIDR", "native magic");
+  assertEqual(native.header.ts_us, frame.header.dispatchTsUs, "native ts_us");
+  assertEqual(native.header.dropped_raw, frame.header.droppedRaw, "native dropped_raw");
+  assertEqual(native.header.dropped_note, frame.header.droppedNote, "native dropped_note");
+  assertEqual(
+    native.records.map((r: Record<string, number>) => ({
+      tsUs: r.ts_us,
+      kind: r.kind,
+      channel: r.channel,
+      a: r.a,
+      b: r.b,
+      v16: r.v16,
+      extra: r.extra,
+    })),
+    frame.records,
+    "native records",
+  );
+  const latency = native.blocks.find((b: { name?: string }) => b.name === "latency")?.fields;
+  assertEqual(
+    latency && { minUs: latency.min_us, maxUs: latency.max_us, meanUs: latency.mean_us, count: latency.count },
+    frame.header.latency,
+    "native latency",
+  );
+  assertEqual(
+    native.extensions,
+    frame.tlvs.map((t) => ({ tag: t.tag, hex: hex(t.payload) })),
+    "native extensions",
+  );
+  assertEqual(native.trailing_bytes, 0, "native trailing bytes");
+
+  const generic = describeFrame(windowLib.symbols.window_decode_frame_json, frame.bytes);
+  if (generic === null) throw new Error("window_decode_frame_json rejected a valid frame");
+  assertEqual(JSON.parse(generic).records.length, frame.records.length, "generic record count");
+}
+
+if (describeFrame(midiLib.symbols.midi_decode_frame_json, new Uint8Array(31)) !== null) {
+  throw new Error("A truncated frame should be rejected");
+}
+
+console.log(`${ROUNDS} random frames decoded identically`);
+midiLib.close();
+windowLib.close();
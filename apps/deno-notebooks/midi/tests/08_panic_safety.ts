@@ -0,0 +1,57 @@
+import { lastNativeError, lastNativePanic, NativeErrorCode } from "../../tools/native_error.ts";
+
+// Makes one export of each library panic (via the `panic-hook` test feature)
+// and checks the process survives, the export returns its failure value and
+// the panic is reported as a PANIC last error, and that `<prefix>_get_last_panic`
+// still returns it after later calls succeed. Build the libraries with
+// `cargo build --features panic-hook` first, then:
+//
+//   deno run --allow-ffi --allow-env midi/tests/08_panic_safety.ts \
+//     <libmidi_bridge> <libdeno_window> <libfastsleep>
+
+const [midiPath, windowPath, fastSleepPath] = Deno.args;
+if (!midiPath || !windowPath || !fastSleepPath) {
+  throw new Error("usage: 08_panic_safety.ts <midi_bridge> <deno_window> <fastsleep>");
+}
+
+const LIBRARIES = [
+  { path: midiPath, prefix: "midi" },
+  { path: windowPath, prefix: "window" },
+  { path: fastSleepPath, prefix: "fast_sleep" },
+];
+
+for (const { path, prefix } of LIBRARIES) {
+  const lib = Deno.dlopen(path, {
+    [`${prefix}_capabilities`]: { parameters: ["buffer", "u32"], result: "u32" },
+    [`${prefix}_abi_version`]: { parameters: [], result: "u32" },
+    [`${prefix}_last_error_code`]: { parameters: [], result: "i32" },
+    [`${prefix}_last_error_message`]: { parameters: ["buffer", "u32"], result: "u32" },
+    [`${prefix}_get_last_panic`]: { parameters: ["buffer", "u32"], result: "u32" },
+  } as const);
+  const call = (name: string, ...args: unknown[]) =>
+    (lib.symbols as Record<string, (...a: unknown[]) => number>)[`${prefix}_${name}`](...args);
+
+  Deno.env.set("AVTOOLS_FFI_PANIC", `${prefix}_capabilities`);
+  const len = call("capabilities", null, 0);
+  Deno.env.delete("AVTOOLS_FFI_PANIC");
+
+  if (len !== 0) throw new Error(`${prefix}: expected 0 from a panicking export, got ${len}`);
+  const err = lastNativeError({
+    code: () => call("last_error_code"),
+    message: (buf, cap) => call("last_error_message", buf, cap),
+  });
+  if (err?.code !== NativeErrorCode.panic) {
+    throw new Error(`${prefix}: expected a PANIC last error, got ${JSON.stringify(err)}`);
+  }
+  if (call("capabilities", null, 0) === 0 || call("abi_version") === 0) {
+    throw new Error(`${prefix}: library unusable after a caught panic`);
+  }
+  const panic = lastNativePanic((buf, cap) => call("get_last_panic", buf, cap));
+  if (panic !== err.message) {
+    throw new Error(`${prefix}: last panic ${JSON.stringify(panic)} was not kept after later calls`);
+  }
+  console.log(`${prefix}: ${err.message}`);
+  lib.close();
+}
+
+console.log("All libraries survived an injected panic");
@@ -0,0 +1,86 @@
+// Checks the generated bindings: reruns native/avtools_bindgen in `--check`
+// mode (the committed headers and symbols.ts must match the Rust exports),
+// compiles each C header, and verifies the hand-written dlopen tables agree
+// with the generated ones, including the combined avtools_native table.
+// "buffer" and "function" count as "pointer".
+//
+//   deno run --allow-run --allow-read --allow-ffi --allow-env midi/tests/09_generated_bindings.ts
+//
+// Importing tools/fast_sleep.ts opens the fastsleep library, so build it first.
+
+import { FFI_SYMBOLS as MIDI_SYMBOLS } from "../ffi.ts";
+import { FFI_SYMBOLS as WINDOW_SYMBOLS } from "../../window/ffi.ts";
+import { FFI_SYMBOLS as FAST_SLEEP_SYMBOLS } from "../../tools/fast_sleep.ts";
+import { MIDI_BRIDGE_SYMBOLS } from "../../native/midi_bridge/symbols.ts";
+import { DENO_WINDOW_SYMBOLS } from "../../native/deno_window/symbols.ts";
+import { FASTSLEEP_SYMBOLS } from "../../native/fastsleep/symbols.ts";
+import { AVTOOLS_NATIVE_SYMBOLS } from "../../native/avtools_native/symbols.ts";
+
+const NATIVE = new URL("../../native/", import.meta.url);
+
+type Table = Record<string, { parameters: readonly string[]; result: string }>;
+
+async function run(cmd: string, args: string[]): Promise<boolean> {
+  const { success, stdout, stderr } = await new Deno.Command(cmd, { args }).output();
+  const out = new TextDecoder().decode(stdout) + new TextDecoder().decode(stderr);
+  if (out.trim()) console.log(out.trimEnd());
+  return success;
+}
+
+function pointerLike(t: string) {
+  return t === "buffer" || t === "function" ? "pointer" : t;
+}
+
+function signature(def: { parameters: readonly string[]; result: string }) {
+  return `(${def.parameters.map(pointerLike).join(", ")}) -> ${pointerLike(def.result)}`;
+}
+
+function compare(name: string, hand: Table, generated: Table, reportUnbound = true): boolean {
+  let ok = true;
+  for (const [symbol, def] of Object.entries(hand)) {
+    const native = generated[symbol];
+    if (!native) {
+      console.error(`${name}: ${symbol} is not exported by the library`);
+      ok = false;
+    } else if (signature(def) !== signature(native)) {
+      console.error(`${name}: ${symbol} is ${signature(def)}, library has ${signature(native)}`);
+      ok = false;
+    }
+  }
+  const unbound = Object.keys(generated).filter((s) => !(s in hand));
+  if (reportUnbound && unbound.length > 0) console.log(`${name}: not bound from TS: ${unbound.join(", ")}`);
+  return ok;
+}
+
+let failed = false;
+
+const manifest = new URL("avtools_bindgen/Cargo.toml", NATIVE).pathname;
+if (!(await run("cargo", ["run", "-q", "--manifest-path", manifest, "--", "--check"]))) {
+  console.error("generated bindings are out of date");
+  failed = true;
+}
+
+for (const crate of ["deno_window", "midi_bridge", "fastsleep", "avtools_native"]) {
+  const header = new URL(`${crate}/include/${crate}.h`, NATIVE).pathname;
+  const cc = Deno.env.get("CC") ?? "cc";
+  if (!(await run(cc, ["-fsyntax-only", "-Wall", "-Werror", "-std=c11", "-x", "c", header]))) {
+    console.error(`${crate}: header does not compile`);
+    failed = true;
+  }
+}
+
+failed = !compare("midi/ffi.ts", MIDI_SYMBOLS, MIDI_BRIDGE_SYMBOLS) || failed;
+failed = !compare("window/ffi.ts", WINDOW_SYMBOLS, DENO_WINDOW_SYMBOLS) || failed;
+failed = !compare("tools/fast_sleep.ts", FAST_SLEEP_SYMBOLS, FASTSLEEP_SYMBOLS) || failed;
+// The combined library must serve every wrapper's table unchanged.
+for (const [name, hand] of [
+  ["midi/ffi.ts", MIDI_SYMBOLS],
+  ["window/ffi.ts", WINDOW_SYMBOLS],
+  ["tools/fast_sleep.ts", FAST_SLEEP_SYMBOLS],
+] as const) {
+  failed = !compare(`${name} (avtools_native)`, hand, AVTOOLS_NATIVE_SYMBOLS, false) || failed;
+}
+
+if (failed) Deno.exit(1);
+console.log("generated bindings OK");
+Deno.exit(0);
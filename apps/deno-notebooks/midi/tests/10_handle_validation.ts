@@ -0,0 +1,89 @@
+import { openLibrary as openMidiLibrary } from "../ffi.ts";
+import { openLibrary as openWindowLibrary } from "../../window/ffi.ts";
+import { lastNativeError, NativeErrorCode } from "../../tools/native_error.ts";
+
+// Hammers handle creation and destruction and checks that stale handles are
+// rejected with INVALID_HANDLE instead of crashing or reaching a new object.
+// MIDI pipelines need no devices; WORKERS threads churn them concurrently
+// while the main thread checks stale ids. Pass `--window` to also destroy a
+// window and reuse its pointer (needs a display).
+//
+//   deno run --allow-ffi --allow-read midi/tests/10_handle_validation.ts [midi_lib] [--window]
+
+const ROUNDS = 20_000;
+const WORKERS = 4;
+
+const args = Deno.args.filter((a) => a !== "--window");
+const midi = openMidiLibrary(args[0]);
+const midiError = () =>
+  lastNativeError({
+    code: () => midi.symbols.midi_last_error_code(),
+    message: (buf, cap) => midi.symbols.midi_last_error_message(buf, cap),
+  });
+
+function assert(cond: unknown, message: string): asserts cond {
+  if (!cond) throw new Error(message);
+}
+
+/** Create, use and destroy pipelines; every closed id must stay dead. */
+function churn(rounds: number) {
+  for (let i = 0; i < rounds; i++) {
+    const pipeline = midi.symbols.midi_create_pipeline();
+    assert(pipeline !== 0, "midi_create_pipeline failed");
+    assert(midi.symbols.midi_pipeline_add_transpose(pipeline, 16, 1) === 0, "live pipeline rejected");
+    assert(midi.symbols.midi_pipeline_destroy(pipeline) === 0, "destroy failed");
+    assert(midi.symbols.midi_pipeline_clear(pipeline) === -1, `stale pipeline ${pipeline} accepted`);
+    assert(midi.symbols.midi_pipeline_destroy(pipeline) === -1, `double destroy of ${pipeline} accepted`);
+  }
+}
+
+if (new URL(import.meta.url).search === "?worker") {
+  churn(ROUNDS);
+  self.postMessage("done");
+  self.close();
+} else {
+  const workers = Array.from({ length: WORKERS }, () => {
+    const worker = new Worker(new URL("?worker", import.meta.url), { type: "module" });
+    return new Promise<void>((resolve, reject) => {
+      worker.onmessage = () => resolve();
+      worker.onerror = (e) => reject(e.error ?? new Error(e.message));
+    });
+  });
+
+  // A closed id must not validate even once its slot has been reissued.
+  const first = midi.symbols.midi_create_pipeline();
+  midi.symbols.midi_pipeline_destroy(first);
+  churn(ROUNDS);
+  assert(midi.symbols.midi_pipeline_clear(first) === -1, "reissued slot accepted a stale id");
+  assert(midiError()?.code === NativeErrorCode.invalidHandle, "stale id did not report INVALID_HANDLE");
+
+  // Handles of the wrong kind are rejected too.
+  const pipeline = midi.symbols.midi_create_pipeline();
+  assert(midi.symbols.midi_set_note_timeout_ms(pipeline, 100) === -1, "pipeline accepted as an input");
+  console.log(`wrong kind: ${midiError()?.message}`);
+  midi.symbols.midi_pipeline_destroy(pipeline);
+  const noteOn = new Uint8Array([0x90, 60, 1]);
+  assert(midi.symbols.midi_send(0xdeadbeef, Deno.UnsafePointer.of(noteOn), 3) === -1, "bogus output accepted");
+
+  await Promise.all(workers);
+  console.log(`midi: ${ROUNDS * (WORKERS + 1)} pipelines churned, stale ids rejected`);
+
+  if (Deno.args.includes("--window")) {
+    const win = openWindowLibrary();
+    const state = win.symbols.create_window(64, 64, null, 0);
+    assert(state !== null, "create_window failed");
+    win.symbols.destroy_window(state);
+    assert(win.symbols.get_frame_count(state) === 0n, "destroyed window answered");
+    const error = lastNativeError({
+      code: () => win.symbols.window_last_error_code(),
+      message: (buf, cap) => win.symbols.window_last_error_message(buf, cap),
+    });
+    assert(error?.code === NativeErrorCode.invalidHandle, "destroyed window did not report INVALID_HANDLE");
+    win.symbols.destroy_window(state);
+    console.log(`window: ${error.message}`);
+    win.close();
+  }
+
+  midi.close();
+  console.log("handle validation OK");
+}
@@ -0,0 +1,60 @@
+import { readMetrics } from "../../tools/native_metrics.ts";
+
+// Native worker threads: a tick loop's thread carries its name (checked in
+// /proc on Linux), and a panic injected into it (via the `panic-hook` test
+// feature) is contained, with the process still running and the loop
+// reported as failed in the metrics. Build fastsleep with
+// `cargo build --features panic-hook` first, then:
+//
+//   deno run --allow-ffi --allow-env --allow-read midi/tests/11_worker_threads.ts <libfastsleep>
+
+const [fastSleepPath] = Deno.args;
+if (!fastSleepPath) throw new Error("usage: 11_worker_threads.ts <fastsleep>");
+
+const lib = Deno.dlopen(fastSleepPath, {
+  fast_loop_start: { parameters: ["u32", "function", "u32"], result: "u32" },
+  fast_loop_stop: { parameters: ["u32"], result: "i32", nonblocking: true },
+  fast_sleep_metrics_json: { parameters: ["buffer", "u32"], result: "u32" },
+} as const);
+
+const onTick = new Deno.UnsafeCallback(
+  { parameters: ["u64", "i64"], result: "void" } as const,
+  () => {},
+);
+onTick.unref();
+
+const fastsleepMetrics = () =>
+  readMetrics((buf, cap) => lib.symbols.fast_sleep_metrics_json(buf, cap)).fastsleep!;
+
+function threadNames(): string[] {
+  return [...Deno.readDirSync("/proc/self/task")].map((task) =>
+    Deno.readTextFileSync(`/proc/self/task/${task.name}/comm`).trim()
+  );
+}
+
+const loop = lib.symbols.fast_loop_start(1000, onTick.pointer, 0);
+if (loop === 0) throw new Error("fast_loop_start failed");
+if (Deno.build.os === "linux") {
+  const names = threadNames();
+  if (!names.includes("fastsleep-loop")) {
+    throw new Error(`no fastsleep-loop thread among ${JSON.stringify(names)}`);
+  }
+} else {
+  console.log(`thread names not checked on ${Deno.build.os}`);
+}
+await lib.symbols.fast_loop_stop(loop);
+
+Deno.env.set("AVTOOLS_FFI_PANIC", "fastsleep-loop");
+const failing = lib.symbols.fast_loop_start(1000, onTick.pointer, 0);
+Deno.env.delete("AVTOOLS_FFI_PANIC");
+if (failing === 0) throw new Error("fast_loop_start failed");
+await new Promise((resolve) => setTimeout(resolve, 50));
+const { failed_loops } = fastsleepMetrics();
+if (failed_loops !== 1) throw new Error(`expected 1 failed loop, metrics report ${failed_loops}`);
+if (await lib.symbols.fast_loop_stop(failing) !== 0) {
+  throw new Error("stopping a failed loop should still succeed");
+}
+
+onTick.close();
+lib.close();
+console.log("Worker threads are named and contain panics");
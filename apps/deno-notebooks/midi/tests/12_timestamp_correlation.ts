@@ -0,0 +1,94 @@
+import { openLibrary as openMidiLibrary } from "../ffi.ts";
+import { openLibrary as openWindowLibrary } from "../../window/ffi.ts";
+import { ClockDomain, clockOffsetUs, fastNowUs } from "../../tools/fast_sleep.ts";
+import type { WindowEvent } from "../../window/events.ts";
+import { openMidi, pickLoopback, sleep } from "./_helpers.ts";
+
+// Timestamps from different libraries must be comparable. Every library
+// reports the residual offset between the timestamp domains
+// (avtools_clock_offset), which must be ~0 for every domain on the shared
+// clock and agree between libraries. With `--events`, a window user event
+// and a MIDI note sent through a loopback port at the same moment must carry
+// timestamps within EVENT_TOLERANCE_US (needs a display and a loopback port).
+//
+//   deno run --allow-ffi --allow-read --allow-env midi/tests/12_timestamp_correlation.ts \
+//     [midi_lib] [window_lib] [--events]
+
+const OFFSET_TOLERANCE_US = 50;
+const EVENT_TOLERANCE_US = 5000;
+
+const args = Deno.args.filter((a) => a !== "--events");
+const midiLib = openMidiLibrary(args[0]);
+const windowLib = openWindowLibrary(args[1]);
+
+function assert(cond: unknown, message: string): asserts cond {
+  if (!cond) throw new Error(message);
+}
+
+const SHARED_DOMAINS = [ClockDomain.midi, ClockDomain.window, ClockDomain.fastsleep];
+const readers: [string, (a: number, b: number) => number][] = [
+  ["fastsleep", (a, b) => clockOffsetUs(a as ClockDomain, b as ClockDomain)],
+  ["midi", (a, b) => Number(midiLib.symbols.avtools_clock_offset(a, b))],
+  ["window", (a, b) => Number(windowLib.symbols.avtools_clock_offset(a, b))],
+];
+
+for (const [name, offset] of readers) {
+  for (const domain of SHARED_DOMAINS) {
+    const us = offset(ClockDomain.shared, domain);
+    assert(
+      Math.abs(us) <= OFFSET_TOLERANCE_US,
+      `${name}: domain ${domain} is ${us}µs off the shared clock (tolerance ${OFFSET_TOLERANCE_US}µs)`,
+    );
+  }
+}
+if (Deno.build.os === "linux") {
+  // The raw clock is not slewed, so it may legitimately sit apart; report it.
+  console.log(`raw MIDI clock offset: ${clockOffsetUs(ClockDomain.shared, ClockDomain.midiRaw)}µs`);
+}
+console.log("clock domains agree");
+
+if (Deno.args.includes("--events")) {
+  const state = windowLib.symbols.create_window(64, 64, null, 0);
+  assert(state !== null, "create_window failed");
+  assert(windowLib.symbols.wait_for_window_ready(state, 2000) === 0, "window did not appear");
+
+  const { midi } = openMidi(args[0]);
+  const pair = pickLoopback(midi.listInputs(), midi.listOutputs());
+  const input = midi.openInput(pair.input.id, { rateHz: 1000, keepAlive: false });
+  const output = midi.openOutput(pair.output.id);
+  const note = new Promise<number>((resolve) => input.onNoteOn((evt) => resolve(evt.tsUs)));
+  // Let the input settle so its clock mapping is in place before the note.
+  await sleep(50);
+
+  const marker = new Uint8Array([1]);
+  const sentUs = fastNowUs();
+  output.noteOn(0, 60, 100);
+  windowLib.symbols.send_window_user_event(state, Deno.UnsafePointer.of(marker), marker.length);
+  const buf = new Uint8Array(65536);
+  const written = windowLib.symbols.poll_events(state, Deno.UnsafePointer.of(buf), buf.length);
+  const events = JSON.parse(new TextDecoder().decode(buf.subarray(0, written))) as WindowEvent[];
+  const userEvent = events.find((ev) => ev.type === "user");
+  assert(userEvent, "window user event was not delivered");
+
+  const noteUs = await Promise.race([note, sleep(2000).then(() => null)]);
+  assert(noteUs !== null, `no note arrived on ${pair.input.name}`);
+  output.noteOff(0, 60, 0);
+
+  const skewUs = noteUs - userEvent.ts_us;
+  console.log(`sent at ${sentUs}µs: window +${userEvent.ts_us - sentUs}µs, midi +${noteUs - sentUs}µs`);
+  assert(
+    Math.abs(skewUs) <= EVENT_TOLERANCE_US,
+    `window and MIDI timestamps ${skewUs}µs apart (tolerance ${EVENT_TOLERANCE_US}µs)`,
+  );
+  assert(userEvent.ts_us >= sentUs && noteUs >= sentUs, "event stamped before it was sent");
+
+  input.close();
+  output.close();
+  midi.close();
+  windowLib.symbols.destroy_window(state);
+}
+
+midiLib.close();
+windowLib.close();
+console.log("timestamp correlation OK");
+Deno.exit(0);
@@ -0,0 +1,132 @@
+import { lastNativeError, NativeErrorCode } from "../../tools/native_error.ts";
+
+// Every library's JSON options export (midi_open_input_ex, create_window_ex,
+// fast_ticker_create_ex) rejects malformed options the same way: failure
+// value, INVALID_ARGUMENT, and a message naming the field. No devices or
+// display needed; the MIDI cases fail before any port is opened.
+//
+//   deno run --allow-ffi midi/tests/13_options_validation.ts \
+//     <libmidi_bridge> <libdeno_window> <libfastsleep>
+
+const [midiPath, windowPath, fastSleepPath] = Deno.args;
+if (!midiPath || !windowPath || !fastSleepPath) {
+  throw new Error("usage: 13_options_validation.ts <midi_bridge> <deno_window> <fastsleep>");
+}
+
+/** Options JSON and the start of the expected error message; null = accepted. */
+type Case = [json: string, error: string | null];
+
+// Shared behaviour, checked against every library.
+const COMMON: Case[] = [
+  ["[]", ": expected a JSON object"],
+  ["{", ": EOF while parsing"],
+  ['{"version":99}', ".version: 99 is newer than"],
+  ['{"version":0}', ".version: expected an integer"],
+  ['{"version":"1"}', ".version: expected an integer"],
+  ['{"no_such_field":1}', ".no_such_field: unknown field"],
+];
+
+const encoder = new TextEncoder();
+
+function run(
+  name: string,
+  open: (json: Uint8Array) => boolean,
+  error: () => ReturnType<typeof lastNativeError>,
+  cases: Case[],
+) {
+  for (const [json, expected] of cases) {
+    const ok = open(encoder.encode(json));
+    const err = error();
+    if (expected === null) {
+      if (err?.code === NativeErrorCode.invalidArgument) {
+        throw new Error(`${name}: ${json} was rejected: ${err.message}`);
+      }
+      continue;
+    }
+    const message = err?.message ?? "";
+    if (ok || err?.code !== NativeErrorCode.invalidArgument || !message.includes(expected)) {
+      throw new Error(`${name}: ${json} should fail with "${expected}", got ${JSON.stringify(err)}`);
+    }
+    console.log(`${name}: ${json} -> ${message}`);
+  }
+}
+
+const midi = Deno.dlopen(midiPath, {
+  midi_open_input_ex: { parameters: ["buffer", "u32", "buffer", "u32", "function"], result: "u32" },
+  midi_last_error_code: { parameters: [], result: "i32" },
+  midi_last_error_message: { parameters: ["buffer", "u32"], result: "u32" },
+} as const);
+const callback = new Deno.UnsafeCallback({ parameters: ["pointer", "u32"], result: "void" } as const, () => {});
+const portId = encoder.encode("no-such-port");
+run(
+  "midi",
+  (json) => midi.symbols.midi_open_input_ex(portId, portId.length, json, json.length, callback.pointer) !== 0,
+  () =>
+    lastNativeError({
+      code: () => midi.symbols.midi_last_error_code(),
+      message: (buf, cap) => midi.symbols.midi_last_error_message(buf, cap),
+    }),
+  [
+    ...COMMON,
+    ['{"rate_hz":20000}', "input options.rate_hz: must be at most"],
+    ['{"rate_hz":-1}', "input options.rate_hz: invalid value"],
+    ['{"flags":4096}', "input options.flags: unknown flag bits 0x1000"],
+    ['{"note_queue_cap":"big"}', "input options.note_queue_cap: invalid type"],
+    // Valid: fails later, opening the port.
+    ['{"version":1,"rate_hz":100,"flags":512}', null],
+  ],
+);
+callback.close();
+midi.close();
+
+const window = Deno.dlopen(windowPath, {
+  create_window_ex: { parameters: ["buffer", "u32"], result: "pointer" },
+  window_last_error_code: { parameters: [], result: "i32" },
+  window_last_error_message: { parameters: ["buffer", "u32"], result: "u32" },
+} as const);
+run(
+  "window",
+  (json) => window.symbols.create_window_ex(json, json.length) !== null,
+  () =>
+    lastNativeError({
+      code: () => window.symbols.window_last_error_code(),
+      message: (buf, cap) => window.symbols.window_last_error_message(buf, cap),
+    }),
+  [
+    ...COMMON,
+    ['{"width":0}', "window options.width: must be from 1"],
+    ['{"height":100000}', "window options.height: must be from 1"],
+    ['{"title":5}', "window options.title: invalid type"],
+  ],
+);
+window.close();
+
+const fastSleep = Deno.dlopen(fastSleepPath, {
+  fast_ticker_create_ex: { parameters: ["buffer", "u32"], result: "u32" },
+  fast_ticker_destroy: { parameters: ["u32"], result: "i32" },
+  fast_sleep_last_error_code: { parameters: [], result: "i32" },
+  fast_sleep_last_error_message: { parameters: ["buffer", "u32"], result: "u32" },
+} as const);
+run(
+  "fastsleep",
+  (json) => {
+    const handle = fastSleep.symbols.fast_ticker_create_ex(json, json.length);
+    if (handle !== 0) fastSleep.symbols.fast_ticker_destroy(handle);
+    return handle !== 0;
+  },
+  () =>
+    lastNativeError({
+      code: () => fastSleep.symbols.fast_sleep_last_error_code(),
+      message: (buf, cap) => fastSleep.symbols.fast_sleep_last_error_message(buf, cap),
+    }),
+  [
+    ...COMMON,
+    ["{}", "ticker options.period_us: required"],
+    ['{"period_us":1000,"catch_up":1}', "ticker options.catch_up: invalid type"],
+    ['{"period_us":1000,"catchUp":true}', "ticker options.catchUp: unknown field"],
+    ['{"version":1,"period_us":1000,"catch_up":true}', null],
+  ],
+);
+fastSleep.close();
+
+console.log("options validation OK");
@@ -7,12 +7,16 @@ export type CCEvent = {
   channel: number;
   ctrlNum: number;
   ctrlVal: number;
+  /** `ctrlVal` as 0–1; only from inputs opened with FLAG_NORMALISE_CC. */
+  normalised?: number;
   tsUs: number;
 };
 
 export type PitchBendEvent = {
   channel: number;
   bend: number;
+  /** `bend` as 0 (full down) to 1 (full up); only with FLAG_NORMALISE_CC. */
+  normalised?: number;
   tsUs: number;
 };
 
@@ -57,14 +61,75 @@ export type NoteEvent = {
   tsUs: number;
 };
 
+export type NoteCompleteEvent = {
+  channel: number;
+  noteNum: number;
+  velocity: number;
+  offVelocity: number;
+  durationMs: number;
+  tsUs: number;
+};
+
+/** MIDI Show Control message (FLAG_PARSE_MSC); `dataLength` counts the bytes after the command. */
+export type MscEvent = {
+  deviceId: number;
+  commandFormat: number;
+  command: number;
+  dataLength: number;
+  tsUs: number;
+};
+
+/** MIDI Machine Control command (FLAG_PARSE_MMC), e.g. MMC_PLAY; `dataLength` counts the bytes after the command. */
+export type MmcEvent = {
+  deviceId: number;
+  command: number;
+  dataLength: number;
+  tsUs: number;
+};
+
+/** OSC tunnelled over MIDI SysEx (FLAG_DECODE_OSC_TUNNEL); `packet` is the raw OSC message or bundle. */
+export type OscEvent = {
+  deviceId: number;
+  packet: Uint8Array;
+  tsUs: number;
+};
+
+/** Per-note parameter matched by `registerNoteExpression`; `kind` is the caller's id for it. */
+export type NoteExpressionEvent = {
+  channel: number;
+  noteNum: number;
+  kind: number;
+  value: number;
+  tsUs: number;
+};
+
+export type LatencySummary = {
+  minUs: number;
+  maxUs: number;
+  meanUs: number;
+  count: number;
+};
+
+export type InputStats = {
+  latency: {
+    count: number;
+    min_us: number;
+    median_us: number;
+    p95_us: number;
+    max_us: number;
+  };
+};
+
 export type TickPayload = {
   tsUs: number;
   droppedRaw: number;
   droppedNote: number;
+  latency?: LatencySummary;
   ccChanges: CCEvent[];
   pbChanges: PitchBendEvent[];
   chPressureChanges: ChannelPressureEvent[];
   polyPressureChanges: PolyPressureEvent[];
   programChanges: ProgramChangeEvent[];
   noteEvents: NoteEvent[];
+  noteCompletes: NoteCompleteEvent[];
 };
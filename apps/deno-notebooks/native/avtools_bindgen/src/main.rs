@@ -0,0 +1,287 @@
+//! Generates C headers (via cbindgen) and Deno `dlopen` symbol tables for the
+//! native libraries from their exported `extern "C"` signatures.
+//!
+//!   cargo run --manifest-path native/avtools_bindgen/Cargo.toml [-- --check]
+//!
+//! Writes `<crate>/include/<crate>.h` and `<crate>/symbols.ts` for every
+//! library; `--check` writes nothing and fails if either is out of date.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use cbindgen::ItemType;
+use syn::{FnArg, GenericArgument, Item, PathArguments, ReturnType, Type};
+
+const LIBRARIES: &[&str] = &["deno_window", "midi_bridge", "fastsleep", "avtools_native"];
+
+fn native_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .parent()
+        .expect("bindgen crate lives under native/")
+        .to_path_buf()
+}
+
+/// Path dependencies (`path = "../name"`) of `krate`, transitively.
+fn path_deps(native: &Path, krate: &str) -> Vec<String> {
+    let mut seen = BTreeSet::new();
+    let mut pending = vec![krate.to_string()];
+    while let Some(name) = pending.pop() {
+        let manifest =
+            fs::read_to_string(native.join(&name).join("Cargo.toml")).unwrap_or_default();
+        for line in manifest.lines() {
+            let Some(rest) = line.split("path = \"../").nth(1) else {
+                continue;
+            };
+            let dep = rest.split('"').next().unwrap_or_default().to_string();
+            if seen.insert(dep.clone()) {
+                pending.push(dep);
+            }
+        }
+    }
+    seen.into_iter().collect()
+}
+
+fn rust_sources(dir: &Path, out: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            rust_sources(&path, out);
+        } else if path.extension().is_some_and(|e| e == "rs") {
+            out.push(path);
+        }
+    }
+}
+
+#[derive(Default)]
+struct Exports {
+    fns: BTreeMap<String, syn::Signature>,
+    aliases: HashMap<String, Type>,
+}
+
+impl Exports {
+    fn collect(&mut self, items: &[Item]) {
+        for item in items {
+            match item {
+                Item::Fn(f) if is_export(&f.attrs, &f.sig) => {
+                    self.fns.insert(f.sig.ident.to_string(), f.sig.clone());
+                }
+                Item::Type(t) => {
+                    self.aliases.insert(t.ident.to_string(), (*t.ty).clone());
+                }
+                Item::Mod(m) => {
+                    if let Some((_, items)) = &m.content {
+                        self.collect(items);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Deno FFI type name for `ty`, following type aliases.
+    fn deno_type(&self, ty: &Type) -> Result<&'static str, String> {
+        match ty {
+            Type::Ptr(_) => Ok("pointer"),
+            Type::BareFn(_) => Ok("function"),
+            Type::Paren(p) => self.deno_type(&p.elem),
+            Type::Group(g) => self.deno_type(&g.elem),
+            Type::Tuple(t) if t.elems.is_empty() => Ok("void"),
+            Type::Path(p) => {
+                let seg = p.path.segments.last().ok_or("empty type path")?;
+                let name = seg.ident.to_string();
+                match name.as_str() {
+                    "u8" => Ok("u8"),
+                    "i8" => Ok("i8"),
+                    "u16" => Ok("u16"),
+                    "i16" => Ok("i16"),
+                    "u32" => Ok("u32"),
+                    "i32" => Ok("i32"),
+                    "u64" => Ok("u64"),
+                    "i64" => Ok("i64"),
+                    "usize" => Ok("usize"),
+                    "isize" => Ok("isize"),
+                    "f32" => Ok("f32"),
+                    "f64" => Ok("f64"),
+                    "bool" => Ok("bool"),
+                    "NonNull" => Ok("pointer"),
+                    // Nullable function pointers and NonNull keep their representation.
+                    "Option" => match &seg.arguments {
+                        PathArguments::AngleBracketed(args) => match args.args.first() {
+                            Some(GenericArgument::Type(inner)) => match self.deno_type(inner)? {
+                                t @ ("function" | "pointer") => Ok(t),
+                                t => Err(format!("Option<{t}> has no C representation")),
+                            },
+                            _ => Err("Option without a type argument".into()),
+                        },
+                        _ => Err("Option without a type argument".into()),
+                    },
+                    _ => match self.aliases.get(&name) {
+                        Some(aliased) => self.deno_type(aliased),
+                        None => Err(format!("unsupported FFI type `{name}`")),
+                    },
+                }
+            }
+            _ => Err("unsupported FFI type".into()),
+        }
+    }
+}
+
+fn is_export(attrs: &[syn::Attribute], sig: &syn::Signature) -> bool {
+    let no_mangle = attrs.iter().any(|a| a.path().is_ident("no_mangle"));
+    let extern_c = sig
+        .abi
+        .as_ref()
+        .and_then(|abi| abi.name.as_ref())
+        .is_some_and(|name| name.value() == "C");
+    no_mangle && extern_c
+}
+
+fn symbols_ts(native: &Path, krate: &str, deps: &[String]) -> Result<String, String> {
+    let mut exports = Exports::default();
+    for dir in std::iter::once(krate).chain(deps.iter().map(String::as_str)) {
+        let mut files = Vec::new();
+        rust_sources(&native.join(dir).join("src"), &mut files);
+        for path in files {
+            let source =
+                fs::read_to_string(&path).map_err(|e| format!("{}: {e}", path.display()))?;
+            let file = syn::parse_file(&source).map_err(|e| format!("{}: {e}", path.display()))?;
+            exports.collect(&file.items);
+        }
+    }
+
+    let table = krate.to_uppercase();
+    let mut out = format!(
+        "// Generated by native/avtools_bindgen from the exports of {krate}; do not edit.\n\
+         // Hand-written tables may narrow \"pointer\" to \"buffer\"/\"function\" and add `nonblocking`.\n\n\
+         export const {table}_SYMBOLS = {{\n"
+    );
+    for (name, sig) in &exports.fns {
+        let params = sig
+            .inputs
+            .iter()
+            .map(|arg| match arg {
+                FnArg::Typed(pat) => exports.deno_type(&pat.ty).map(|t| format!("\"{t}\"")),
+                FnArg::Receiver(_) => Err("receiver in extern fn".into()),
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("{krate}::{name}: {e}"))?;
+        let result = match &sig.output {
+            ReturnType::Default => "void",
+            ReturnType::Type(_, ty) => exports
+                .deno_type(ty)
+                .map_err(|e| format!("{krate}::{name}: {e}"))?,
+        };
+        out.push_str(&format!(
+            "  {name}: {{ parameters: [{}], result: \"{result}\" }},\n",
+            params.join(", ")
+        ));
+    }
+    out.push_str("} as const satisfies Deno.ForeignLibraryInterface;\n");
+    Ok(out)
+}
+
+fn header(native: &Path, krate: &str, deps: &[String]) -> Result<String, String> {
+    let config = cbindgen::Config {
+        language: cbindgen::Language::C,
+        header: Some(format!(
+            "/* Generated by native/avtools_bindgen from the exports of {krate}; do not edit. */"
+        )),
+        include_guard: Some(format!("AVTOOLS_{}_H", krate.to_uppercase())),
+        usize_is_size_t: true,
+        documentation: false,
+        // Constants are left out: unprefixed names like OK and UNSUPPORTED
+        // clash between a library and its dependencies.
+        export: cbindgen::ExportConfig {
+            item_types: vec![
+                ItemType::Structs,
+                ItemType::Enums,
+                ItemType::Typedefs,
+                ItemType::OpaqueItems,
+                ItemType::Functions,
+            ],
+            ..Default::default()
+        },
+        parse: cbindgen::ParseConfig {
+            parse_deps: true,
+            include: Some(deps.to_vec()),
+            extra_bindings: deps.to_vec(),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let bindings = cbindgen::Builder::new()
+        .with_config(config)
+        .with_crate(native.join(krate))
+        .generate()
+        .map_err(|e| format!("{krate}: {e}"))?;
+    let mut out = Vec::new();
+    bindings.write(&mut out);
+    String::from_utf8(out).map_err(|e| format!("{krate}: {e}"))
+}
+
+/// Write `contents` to `path` unless it already matches. Returns false if the
+/// file was stale (or, with `check`, would have been rewritten).
+fn sync(path: &Path, contents: &str, check: bool) -> Result<bool, String> {
+    if fs::read_to_string(path).is_ok_and(|old| old == contents) {
+        return Ok(true);
+    }
+    if !check {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("{}: {e}", parent.display()))?;
+        }
+        fs::write(path, contents).map_err(|e| format!("{}: {e}", path.display()))?;
+    }
+    Ok(false)
+}
+
+fn run(check: bool) -> Result<bool, String> {
+    let native = native_dir();
+    let mut fresh = true;
+    for krate in LIBRARIES {
+        let deps = path_deps(&native, krate);
+        let outputs = [
+            (
+                native
+                    .join(krate)
+                    .join("include")
+                    .join(format!("{krate}.h")),
+                header(&native, krate, &deps)?,
+            ),
+            (
+                native.join(krate).join("symbols.ts"),
+                symbols_ts(&native, krate, &deps)?,
+            ),
+        ];
+        for (path, contents) in outputs {
+            if !sync(&path, &contents, check)? {
+                fresh = false;
+                let verb = if check { "out of date" } else { "wrote" };
+                println!(
+                    "{verb}: {}",
+                    path.strip_prefix(&native).unwrap_or(&path).display()
+                );
+            }
+        }
+    }
+    Ok(fresh)
+}
+
+fn main() -> ExitCode {
+    let check = std::env::args().any(|a| a == "--check");
+    match run(check) {
+        Ok(fresh) if fresh || !check => ExitCode::SUCCESS,
+        Ok(_) => {
+            eprintln!("generated bindings are stale; rerun without --check and commit the result");
+            ExitCode::FAILURE
+        }
+        Err(e) => {
+            eprintln!("avtools_bindgen: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
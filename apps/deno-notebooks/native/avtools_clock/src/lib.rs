@@ -0,0 +1,219 @@
+//! Process-wide monotonic clock shared by the native libraries.
+//!
+//! Each cdylib links its own copy of this crate, so the origin cannot live in
+//! a static. Instead `now_us` reads the OS clock that backs `Instant`
+//! (CLOCK_MONOTONIC on Linux, mach_absolute_time on macOS,
+//! QueryPerformanceCounter on Windows), whose origin (typically boot) is the
+//! same for every library in the process.
+
+use avtools_ffi::{catch_panic, error, Error};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+/// Microseconds on the shared clock.
+pub fn now_us() -> u64 {
+    now_ns() / 1_000
+}
+
+/// Nanoseconds on the shared clock.
+pub fn now_ns() -> u64 {
+    platform_now_ns()
+}
+
+/// The `Instant` at which the shared clock reads zero, so that
+/// `epoch() + Duration::from_micros(now_us())` is (within a microsecond) now.
+/// Falls back to the first call if the platform cannot represent it.
+pub fn epoch() -> Instant {
+    static EPOCH: OnceLock<Instant> = OnceLock::new();
+    *EPOCH.get_or_init(|| {
+        let now = Instant::now();
+        now.checked_sub(Duration::from_nanos(platform_now_ns()))
+            .unwrap_or(now)
+    })
+}
+
+/// Nanoseconds since `epoch()` measured through `Instant`, the clock behind
+/// fastsleep's deadlines. Agrees with `now_ns` up to the error in `epoch`.
+pub fn instant_ns() -> u64 {
+    epoch().elapsed().as_nanos().min(u64::MAX as u128) as u64
+}
+
+/// CLOCK_MONOTONIC_RAW in µs, which MIDI inputs opened with
+/// FLAG_MONOTONIC_RAW stamp with. It is not slewed by NTP, so it drifts
+/// against the shared clock. None off Linux.
+#[cfg(target_os = "linux")]
+pub fn monotonic_raw_us() -> Option<u64> {
+    monotonic_raw_ns().map(|ns| ns / 1_000)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn monotonic_raw_us() -> Option<u64> {
+    None
+}
+
+/// Raw platform monotonic clock in ns, read without going through `Instant`:
+/// CLOCK_MONOTONIC_RAW on Linux, otherwise the clock behind `now_ns`
+/// (mach_absolute_time on macOS, QueryPerformanceCounter on Windows). The
+/// origin is platform-defined, so use it for intervals.
+#[cfg(target_os = "linux")]
+pub fn raw_ns() -> u64 {
+    monotonic_raw_ns().unwrap_or_else(platform_now_ns)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn raw_ns() -> u64 {
+    platform_now_ns()
+}
+
+/// Timestamp domains for avtools_clock_offset.
+pub const DOMAIN_SHARED: u32 = 0;
+/// MIDI events, from inputs opened without FLAG_MONOTONIC_RAW.
+pub const DOMAIN_MIDI: u32 = 1;
+/// Window events.
+pub const DOMAIN_WINDOW: u32 = 2;
+/// fast_now_us and fastsleep deadlines.
+pub const DOMAIN_FASTSLEEP: u32 = 3;
+/// MIDI events from inputs opened with FLAG_MONOTONIC_RAW.
+pub const DOMAIN_MIDI_RAW: u32 = 4;
+
+/// Samples per avtools_clock_offset; the one read in the shortest window wins.
+const OFFSET_ROUNDS: usize = 32;
+
+/// The clock behind `domain`'s timestamps, in ns.
+fn domain_now_ns(domain: u32) -> Result<u64, Error> {
+    match domain {
+        DOMAIN_SHARED | DOMAIN_MIDI | DOMAIN_WINDOW => Ok(now_ns()),
+        DOMAIN_FASTSLEEP => Ok(instant_ns()),
+        DOMAIN_MIDI_RAW => monotonic_raw_us()
+            .map(|us| us.saturating_mul(1_000))
+            .ok_or_else(|| Error::new(error::UNSUPPORTED, "no raw monotonic clock on this platform")),
+        _ => Err(Error::new(error::INVALID_ARGUMENT, format!("unknown clock domain {domain}"))),
+    }
+}
+
+/// How far `b`'s clock reads ahead of `a`'s, in ns: `b` is read between two
+/// reads of `a` and compared with their midpoint, keeping the tightest pair.
+pub fn offset_ns(a: u32, b: u32) -> Result<i64, Error> {
+    let mut best: Option<(u64, i64)> = None;
+    for _ in 0..OFFSET_ROUNDS {
+        let before = domain_now_ns(a)?;
+        let reading = domain_now_ns(b)?;
+        let after = domain_now_ns(a)?;
+        let window = after.saturating_sub(before);
+        let midpoint = before + window / 2;
+        let offset = reading as i64 - midpoint as i64;
+        if best.is_none_or(|(w, _)| window < w) {
+            best = Some((window, offset));
+        }
+    }
+    Ok(best.map_or(0, |(_, offset)| offset))
+}
+
+/// Residual offset in µs between two timestamp domains (DOMAIN_*): how far
+/// `domain_b`'s timestamps run ahead of `domain_a`'s for the same instant.
+/// The shared clock makes this 0 apart from rounding, except for the raw MIDI
+/// clock; it is how a library that still stamps with its own clock shows up.
+/// Every library computes the same domains, so offsets from different
+/// libraries can be compared. Returns 0 with INVALID_ARGUMENT for an unknown
+/// domain, UNSUPPORTED where a clock does not exist.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn avtools_clock_offset(domain_a: u32, domain_b: u32) -> i64 {
+    match error::record(offset_ns(domain_a, domain_b)) {
+        Some(ns) => ns / 1_000,
+        None => 0,
+    }
+}
+
+/// Convert an `Instant` to microseconds on the shared clock.
+pub fn instant_to_us(t: Instant) -> u64 {
+    t.saturating_duration_since(epoch())
+        .as_micros()
+        .min(u64::MAX as u128) as u64
+}
+
+/// Shared clock in µs. Every library exports this same symbol, so the Deno
+/// side can check that they agree.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn avtools_clock_now_us() -> u64 {
+    now_us()
+}
+
+#[cfg(target_os = "linux")]
+fn monotonic_raw_ns() -> Option<u64> {
+    let mut ts = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    let rc = unsafe { libc::clock_gettime(libc::CLOCK_MONOTONIC_RAW, &mut ts) };
+    if rc != 0 {
+        return None;
+    }
+    Some(
+        (ts.tv_sec as u64)
+            .saturating_mul(1_000_000_000)
+            .saturating_add(ts.tv_nsec as u64),
+    )
+}
+
+#[cfg(target_os = "linux")]
+fn platform_now_ns() -> u64 {
+    let mut ts = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    unsafe { libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut ts) };
+    (ts.tv_sec as u64)
+        .saturating_mul(1_000_000_000)
+        .saturating_add(ts.tv_nsec as u64)
+}
+
+#[cfg(target_os = "macos")]
+fn platform_now_ns() -> u64 {
+    #[repr(C)]
+    struct MachTimebaseInfo {
+        numer: u32,
+        denom: u32,
+    }
+
+    extern "C" {
+        fn mach_absolute_time() -> u64;
+        fn mach_timebase_info(info: *mut MachTimebaseInfo) -> i32;
+    }
+
+    static TIMEBASE: OnceLock<(u32, u32)> = OnceLock::new();
+    let &(numer, denom) = TIMEBASE.get_or_init(|| {
+        let mut info = MachTimebaseInfo { numer: 1, denom: 1 };
+        unsafe { mach_timebase_info(&mut info) };
+        (info.numer, info.denom.max(1))
+    });
+    let ticks = unsafe { mach_absolute_time() };
+    (ticks as u128 * numer as u128 / denom as u128).min(u64::MAX as u128) as u64
+}
+
+#[cfg(target_os = "windows")]
+fn platform_now_ns() -> u64 {
+    use windows::Win32::System::Performance::{QueryPerformanceCounter, QueryPerformanceFrequency};
+
+    static FREQUENCY: OnceLock<i64> = OnceLock::new();
+    let frequency = *FREQUENCY.get_or_init(|| {
+        let mut frequency = 0i64;
+        let _ = unsafe { QueryPerformanceFrequency(&mut frequency) };
+        frequency.max(1)
+    });
+    let mut counter = 0i64;
+    let _ = unsafe { QueryPerformanceCounter(&mut counter) };
+    (counter.max(0) as u128 * 1_000_000_000 / frequency as u128).min(u64::MAX as u128) as u64
+}
+
+// No shared OS clock wired up: each library counts from its first call.
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn platform_now_ns() -> u64 {
+    static START: OnceLock<Instant> = OnceLock::new();
+    START
+        .get_or_init(Instant::now)
+        .elapsed()
+        .as_nanos()
+        .min(u64::MAX as u128) as u64
+}
@@ -0,0 +1,83 @@
+//! The two ways an export hands variable-length data to the host.
+//!
+//! Sized buffers: the host calls once with a null buffer to learn the length,
+//! then again with a buffer at least that large. `write` returns the length
+//! either way and copies only when the buffer is big enough.
+//!
+//! Owned buffers: the export allocates, returns the pointer and writes the
+//! length to an out-parameter, and the host releases it with `av_free(ptr,
+//! len)`. One call instead of two, which matters for large payloads that are
+//! expensive to produce twice.
+
+use crate::catch_panic;
+use std::ptr;
+
+/// Copy `bytes` into `out` if it is non-null and at least `bytes.len()` long.
+///
+/// # Safety
+/// `out` must be null or valid for `out_cap` bytes of writes.
+pub unsafe fn copy_if_fits(bytes: &[u8], out: *mut u8, out_cap: u32) -> bool {
+    if out.is_null() || bytes.len() > out_cap as usize {
+        return false;
+    }
+    ptr::copy_nonoverlapping(bytes.as_ptr(), out, bytes.len());
+    true
+}
+
+/// The sized-buffer contract: copy `bytes` if they fit and return their
+/// length regardless.
+///
+/// # Safety
+/// `out` must be null or valid for `out_cap` bytes of writes.
+pub unsafe fn write(bytes: &[u8], out: *mut u8, out_cap: u32) -> u32 {
+    copy_if_fits(bytes, out, out_cap);
+    bytes.len().min(u32::MAX as usize) as u32
+}
+
+/// Hand `bytes` to the host as an owned buffer: writes the length to
+/// `out_len` and returns a pointer to release with `av_free`. Empty payloads
+/// return null with length 0.
+///
+/// # Safety
+/// `out_len` must be null or valid for a u32 write.
+pub unsafe fn into_owned(bytes: Vec<u8>, out_len: *mut u32) -> *mut u8 {
+    let len = bytes.len().min(u32::MAX as usize);
+    if !out_len.is_null() {
+        *out_len = len as u32;
+    }
+    if len == 0 {
+        return ptr::null_mut();
+    }
+    let mut bytes = bytes;
+    bytes.truncate(len);
+    Box::into_raw(bytes.into_boxed_slice()) as *mut u8
+}
+
+/// Allocate `len` zeroed bytes owned by this library, e.g. for the host to
+/// fill before passing to an export that takes ownership. Release with
+/// `av_free(ptr, len)`. Returns null for `len` 0.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn av_alloc(len: u32) -> *mut u8 {
+    if len == 0 {
+        return ptr::null_mut();
+    }
+    Box::into_raw(vec![0u8; len as usize].into_boxed_slice()) as *mut u8
+}
+
+/// Release a buffer from `av_alloc` or from an export returning an owned
+/// buffer. `len` must be the length it was returned with; null is ignored.
+/// Each library has its own allocator, so free with the same library's
+/// `av_free`.
+///
+/// # Safety
+/// `ptr` must be null or a buffer of exactly `len` bytes from this library
+/// that has not been freed yet.
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn av_free(ptr: *mut u8, len: u32) {
+    if ptr.is_null() {
+        return;
+    }
+    drop(Box::from_raw(ptr::slice_from_raw_parts_mut(ptr, len as usize)));
+}
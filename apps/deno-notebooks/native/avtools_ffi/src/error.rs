@@ -0,0 +1,101 @@
+//! Thread-local last-error record behind each library's
+//! `<prefix>_last_error_code` / `<prefix>_last_error_message` exports.
+//!
+//! A fallible export sets the record when it fails and clears it when it
+//! succeeds, so the record describes the most recent fallible call made on the
+//! calling thread. Deno `nonblocking` calls run on another thread and cannot
+//! be inspected this way.
+//!
+//! Code space (0 = no error):
+//!   1–99     shared by every library (the constants below)
+//!   100–199  deno_window
+//!   200–299  midi_bridge
+//!   300–399  fastsleep
+
+use std::cell::RefCell;
+use std::fmt;
+
+pub const OK: i32 = 0;
+pub const INVALID_ARGUMENT: i32 = 1;
+pub const INVALID_HANDLE: i32 = 2;
+pub const NOT_FOUND: i32 = 3;
+pub const OS_ERROR: i32 = 4;
+pub const UNSUPPORTED: i32 = 5;
+pub const BUSY: i32 = 6;
+/// The export panicked; see avtools_ffi::panic.
+pub const PANIC: i32 = 7;
+
+#[derive(Debug, Clone)]
+pub struct Error {
+    pub code: i32,
+    pub message: String,
+}
+
+impl Error {
+    pub fn new(code: i32, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for Error {}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<Error>> = const { RefCell::new(None) };
+}
+
+pub fn set(err: Error) {
+    LAST_ERROR.with(|e| *e.borrow_mut() = Some(err));
+}
+
+/// Record a failure; shorthand for `set(Error::new(code, message))`.
+pub fn fail(code: i32, message: impl Into<String>) {
+    set(Error::new(code, message));
+}
+
+pub fn clear() {
+    LAST_ERROR.with(|e| *e.borrow_mut() = None);
+}
+
+/// Record `result`'s error, or clear the record on success.
+pub fn record<T>(result: Result<T, Error>) -> Option<T> {
+    match result {
+        Ok(value) => {
+            clear();
+            Some(value)
+        }
+        Err(err) => {
+            set(err);
+            None
+        }
+    }
+}
+
+/// Code of the calling thread's last error, or OK.
+pub fn code() -> i32 {
+    LAST_ERROR.with(|e| e.borrow().as_ref().map_or(OK, |err| err.code))
+}
+
+/// Copy the last error message (UTF-8, no terminator) into `out`. Returns the
+/// message length, 0 if there is none; nothing is written if `out_cap` is too
+/// small, so call once with a null buffer to size it.
+///
+/// # Safety
+/// `out` must be null or valid for `out_cap` bytes of writes.
+pub unsafe fn message(out: *mut u8, out_cap: u32) -> u32 {
+    LAST_ERROR.with(|e| {
+        let e = e.borrow();
+        let Some(err) = e.as_ref() else {
+            return 0;
+        };
+        crate::buffer::write(err.message.as_bytes(), out, out_cap)
+    })
+}
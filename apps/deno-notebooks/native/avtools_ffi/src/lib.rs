@@ -0,0 +1,16 @@
+//! FFI conventions shared by the native libraries.
+
+// Lets #[catch_panic] expand to ::avtools_ffi paths inside this crate too.
+extern crate self as avtools_ffi;
+
+pub mod buffer;
+pub mod error;
+pub mod metrics;
+pub mod options;
+pub mod panic;
+pub mod shutdown;
+pub mod thread_priority;
+pub mod worker;
+
+pub use error::Error;
+pub use panic::catch_panic;
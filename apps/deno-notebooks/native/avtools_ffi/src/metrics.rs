@@ -0,0 +1,35 @@
+//! Runtime metrics documents for a performance HUD. Each library serialises
+//! one section (`*_metrics_json`); the combined library puts all of them in
+//! one document. Sections are sampled from atomics and `try_lock`s only, so a
+//! reading never holds up a realtime thread: whatever is locked at that
+//! moment is reported as `null` instead of waited for.
+
+use std::sync::{Mutex, TryLockError};
+
+/// Bumped when a field changes meaning or is removed; new fields don't bump it.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// `{"schema":N,"<name>":<section>,...}` from already-serialised sections.
+pub fn document(sections: &[(&str, String)]) -> String {
+    let mut json = format!("{{\"schema\":{SCHEMA_VERSION}");
+    for (name, section) in sections {
+        json.push_str(&format!(",\"{name}\":{section}"));
+    }
+    json.push('}');
+    json
+}
+
+/// `f` applied to the locked value, or None if the lock is held right now.
+/// A poisoned lock is read anyway, as everywhere else.
+pub fn sample<T, R>(lock: &Mutex<T>, f: impl FnOnce(&T) -> R) -> Option<R> {
+    match lock.try_lock() {
+        Ok(guard) => Some(f(&guard)),
+        Err(TryLockError::Poisoned(e)) => Some(f(&e.into_inner())),
+        Err(TryLockError::WouldBlock) => None,
+    }
+}
+
+/// JSON for an optional, already-serialised value.
+pub fn or_null(value: Option<String>) -> String {
+    value.unwrap_or_else(|| "null".to_string())
+}
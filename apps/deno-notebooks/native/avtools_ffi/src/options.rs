@@ -0,0 +1,112 @@
+//! Options objects that `_ex` exports take as JSON. Each options struct
+//! implements `VersionedOptions`, and `parse` treats them all the same way:
+//!
+//! - every field is optional and falls back to the struct's `Default`;
+//! - `version` may be given. Versions only ever add fields, so older ones
+//!   are read as they are, and one newer than the library's is rejected;
+//! - unknown fields are rejected rather than ignored, so a typo cannot
+//!   silently leave a default in place;
+//! - `validate` then checks values against each other and their limits.
+//!
+//! Errors are INVALID_ARGUMENT and name the field, e.g.
+//! `input options.rate_hz: must be at most 10000`.
+
+use crate::{error, Error};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+pub trait VersionedOptions: Serialize + DeserializeOwned + Default {
+    /// Latest layout this library understands.
+    const VERSION: u32;
+    /// What the options configure, as error messages call it.
+    const NAME: &'static str;
+
+    /// Check field values after parsing.
+    fn validate(&self) -> Result<(), Invalid> {
+        Ok(())
+    }
+}
+
+/// A field that failed validation, and why.
+pub struct Invalid {
+    pub field: &'static str,
+    pub reason: String,
+}
+
+impl Invalid {
+    pub fn new(field: &'static str, reason: impl Into<String>) -> Self {
+        Self {
+            field,
+            reason: reason.into(),
+        }
+    }
+}
+
+/// Parse and validate an options object.
+pub fn parse<T: VersionedOptions>(json: &[u8]) -> Result<T, Error> {
+    let value: Value = serde_json::from_slice(json).map_err(|e| invalid::<T>("", e))?;
+    let Value::Object(mut fields) = value else {
+        return Err(invalid::<T>("", "expected a JSON object"));
+    };
+    if let Some(version) = fields.remove("version") {
+        match version.as_u64() {
+            Some(v) if v > T::VERSION as u64 => {
+                return Err(invalid::<T>(
+                    "version",
+                    format!("{v} is newer than this library's {}", T::VERSION),
+                ));
+            }
+            Some(v) if v >= 1 => {}
+            _ => {
+                return Err(invalid::<T>(
+                    "version",
+                    format!("expected an integer from 1 to {}", T::VERSION),
+                ));
+            }
+        }
+    }
+    let known = serde_json::to_value(T::default()).unwrap_or(Value::Null);
+    if let Some(unknown) = fields.keys().find(|key| known.get(key.as_str()).is_none()) {
+        return Err(invalid::<T>(unknown, "unknown field"));
+    }
+    let options =
+        T::deserialize(Value::Object(fields.clone())).map_err(|err| locate::<T>(&fields, err))?;
+    options
+        .validate()
+        .map_err(|bad| invalid::<T>(bad.field, bad.reason))?;
+    Ok(options)
+}
+
+/// `parse` for an export's pointer and length; null or empty means all
+/// defaults.
+///
+/// # Safety
+/// `ptr` must be null or valid for `len` bytes of reads.
+pub unsafe fn parse_raw<T: VersionedOptions>(ptr: *const u8, len: u32) -> Result<T, Error> {
+    if ptr.is_null() || len == 0 {
+        return Ok(T::default());
+    }
+    parse(std::slice::from_raw_parts(ptr, len as usize))
+}
+
+/// serde's error does not say which field it came from, so deserialize each
+/// field alone (the rest default) until one fails.
+fn locate<T: VersionedOptions>(fields: &Map<String, Value>, err: serde_json::Error) -> Error {
+    for (key, value) in fields {
+        let single = Map::from_iter([(key.clone(), value.clone())]);
+        if let Err(err) = T::deserialize(Value::Object(single)) {
+            return invalid::<T>(key, err);
+        }
+    }
+    invalid::<T>("", err)
+}
+
+fn invalid<T: VersionedOptions>(field: &str, reason: impl std::fmt::Display) -> Error {
+    let path = if field.is_empty() {
+        T::NAME.to_string()
+    } else {
+        format!("{}.{field}", T::NAME)
+    };
+    Error::new(error::INVALID_ARGUMENT, format!("{path}: {reason}"))
+}
@@ -0,0 +1,106 @@
+//! Keeps panics from unwinding out of `extern "C"` exports, which would abort
+//! the host process. Every export is marked `#[catch_panic]`, which runs its
+//! body through `call`: a panic is recorded as a PANIC last error and the
+//! export returns its type's `PanicValue`.
+//!
+//! The libraries build with `panic = "unwind"` for this to work, and take
+//! locks with `unwrap_or_else(|e| e.into_inner())` so a mutex poisoned by an
+//! earlier panic keeps working.
+
+use crate::{buffer, error};
+use std::any::Any;
+use std::cell::RefCell;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+pub use avtools_ffi_macros::catch_panic;
+
+/// What an export returns when its body panicked.
+pub trait PanicValue {
+    fn panic_value() -> Self;
+}
+
+impl PanicValue for () {
+    fn panic_value() -> Self {}
+}
+
+/// Signed results use the libraries' usual -1 failure.
+macro_rules! panic_value {
+    ($value:expr => $($ty:ty),*) => {
+        $(impl PanicValue for $ty {
+            fn panic_value() -> Self {
+                $value
+            }
+        })*
+    };
+}
+
+panic_value!(-1 => i8, i16, i32, i64, isize);
+// Unsigned results are handles and lengths, where 0 means none.
+panic_value!(0 => u8, u16, u32, u64, usize);
+panic_value!(f32::NAN => f32);
+panic_value!(f64::NAN => f64);
+panic_value!(false => bool);
+
+impl<T> PanicValue for *mut T {
+    fn panic_value() -> Self {
+        std::ptr::null_mut()
+    }
+}
+
+impl<T> PanicValue for *const T {
+    fn panic_value() -> Self {
+        std::ptr::null()
+    }
+}
+
+thread_local! {
+    /// The most recent panic caught on this thread. Unlike the last error, it
+    /// is not cleared by later successful calls.
+    static LAST_PANIC: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Copy the last panic caught on this thread (`"<export> panicked: <msg>"`)
+/// using the sized-buffer contract; 0 if no export has panicked on it. Backs
+/// each library's `<prefix>_get_last_panic`.
+///
+/// # Safety
+/// `out` must be null or valid for `out_cap` bytes of writes.
+pub unsafe fn last_panic(out: *mut u8, out_cap: u32) -> u32 {
+    LAST_PANIC.with(|p| match p.borrow().as_deref() {
+        Some(message) => buffer::write(message.as_bytes(), out, out_cap),
+        None => 0,
+    })
+}
+
+/// Run an export's body, turning a panic into a PANIC last error and
+/// `T::panic_value()`.
+///
+/// With the `panic-hook` feature, setting `AVTOOLS_FFI_PANIC` to an export's
+/// name makes that export panic on entry, for tests.
+pub fn call<T: PanicValue, F: FnOnce() -> T>(name: &str, body: F) -> T {
+    match catch_unwind(AssertUnwindSafe(|| {
+        #[cfg(feature = "panic-hook")]
+        if std::env::var("AVTOOLS_FFI_PANIC").is_ok_and(|target| target == name) {
+            panic!("injected panic");
+        }
+        body()
+    })) {
+        Ok(value) => value,
+        Err(payload) => {
+            let message = format!("{name} panicked: {}", panic_message(&*payload));
+            LAST_PANIC.with(|p| *p.borrow_mut() = Some(message.clone()));
+            error::fail(error::PANIC, message);
+            T::panic_value()
+        }
+    }
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        s
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s
+    } else {
+        "unknown panic payload"
+    }
+}
@@ -0,0 +1,110 @@
+//! Cleanup of whatever the host left open. Each library registers one hook
+//! per kind of resource the first time it creates one; `avtools_shutdown_all`
+//! runs them in stage order (inputs, then outputs, then windows, then any
+//! worker threads still running) and reports how many of each it closed. The same hooks run from `atexit` (which also
+//! covers unloading the library) as a best-effort fallback for scripts that
+//! exit without calling it.
+//!
+//! Hooks close only what is still open, so running them again is harmless;
+//! the report is cumulative over every run.
+
+use crate::{buffer, catch_panic};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::{Mutex, Once};
+
+/// When a hook runs relative to the others.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Stage {
+    /// Sources of events and sends (MIDI inputs and playbacks, tick loops).
+    Inputs,
+    /// Anything that sends, so held notes can be released before closing.
+    Outputs,
+    /// Windows, once nothing else can touch them.
+    Windows,
+    /// Joining worker threads their owners' hooks have told to stop.
+    Workers,
+}
+
+/// Closes every open resource of one kind; returns how many it closed.
+pub type Hook = fn() -> u32;
+
+struct Registered {
+    stage: Stage,
+    name: &'static str,
+    hook: Hook,
+    closed: u32,
+}
+
+static HOOKS: Mutex<Vec<Registered>> = Mutex::new(Vec::new());
+static ATEXIT: Once = Once::new();
+
+extern "C" {
+    fn atexit(cb: extern "C" fn()) -> i32;
+}
+
+extern "C" fn shutdown_at_exit() {
+    run();
+}
+
+/// Register `hook` under `name` (the key in the report). Registering a name
+/// again is a no-op. Hooks of one stage run in registration order.
+pub fn register(stage: Stage, name: &'static str, hook: Hook) {
+    let mut hooks = HOOKS.lock().unwrap_or_else(|e| e.into_inner());
+    if !hooks.iter().any(|h| h.name == name) {
+        hooks.push(Registered {
+            stage,
+            name,
+            hook,
+            closed: 0,
+        });
+        hooks.sort_by_key(|h| h.stage);
+    }
+    drop(hooks);
+    ATEXIT.call_once(|| unsafe {
+        atexit(shutdown_at_exit);
+    });
+}
+
+/// Run every hook and return the cumulative report as a JSON object of
+/// counts, e.g. `{"inputs":1,"outputs":0}`. A panicking hook is logged and
+/// skipped so the rest still run.
+pub fn run() -> String {
+    let hooks: Vec<(&'static str, Hook)> = HOOKS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .map(|h| (h.name, h.hook))
+        .collect();
+    for (name, hook) in hooks {
+        let closed = match catch_unwind(AssertUnwindSafe(hook)) {
+            Ok(closed) => closed,
+            Err(_) => {
+                avtools_log::error!("shutdown hook {name} panicked");
+                0
+            }
+        };
+        let mut hooks = HOOKS.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(h) = hooks.iter_mut().find(|h| h.name == name) {
+            h.closed = h.closed.saturating_add(closed);
+        }
+    }
+    let hooks = HOOKS.lock().unwrap_or_else(|e| e.into_inner());
+    let fields: Vec<String> = hooks
+        .iter()
+        .map(|h| format!("\"{}\":{}", h.name, h.closed))
+        .collect();
+    format!("{{{}}}", fields.join(","))
+}
+
+/// Close everything this library still has open: MIDI inputs (joining their
+/// threads), held notes and outputs, windows. Idempotent; returns the
+/// cumulative report as a sized JSON buffer, so the usual length query call
+/// is itself a (harmless) shutdown.
+///
+/// # Safety
+/// `out_ptr` must be null or valid for `out_cap` bytes of writes.
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn avtools_shutdown_all(out_ptr: *mut u8, out_cap: u32) -> u32 {
+    buffer::write(run().as_bytes(), out_ptr, out_cap)
+}
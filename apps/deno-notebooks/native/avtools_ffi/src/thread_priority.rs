@@ -0,0 +1,195 @@
+//! Priority and naming for the calling thread. With nonblocking FFI the
+//! caller is one of Deno's blocking-pool threads, so fastsleep's exports use
+//! this to elevate whichever thread runs the timing-critical calls;
+//! `worker::spawn_worker` uses it for the libraries' own threads.
+
+/// Success.
+pub const OK: i32 = 0;
+/// Unknown level.
+pub const INVALID: i32 = -1;
+/// The OS refused the change (e.g. realtime scheduling without the privilege).
+pub const DENIED: i32 = -2;
+/// Not implemented on this platform.
+pub const UNSUPPORTED: i32 = -3;
+
+/// Highest level: 0 normal, 1 above normal, 2 time-critical/realtime.
+pub const MAX_LEVEL: u32 = 2;
+
+#[cfg(target_os = "linux")]
+mod imp {
+    use super::{DENIED, OK};
+
+    /// SCHED_FIFO priority for level 2; mid-range so it stays below kernel threads.
+    const REALTIME_PRIORITY: libc::c_int = 50;
+    const ABOVE_NORMAL_NICE: libc::c_int = -5;
+
+    fn tid() -> libc::id_t {
+        unsafe { libc::gettid() as libc::id_t }
+    }
+
+    pub fn set(level: u32) -> i32 {
+        unsafe {
+            let (policy, priority) = if level == 2 {
+                (libc::SCHED_FIFO, REALTIME_PRIORITY)
+            } else {
+                (libc::SCHED_OTHER, 0)
+            };
+            let param = libc::sched_param {
+                sched_priority: priority,
+            };
+            if libc::pthread_setschedparam(libc::pthread_self(), policy, &param) != 0 {
+                return DENIED;
+            }
+            let nice = if level == 1 { ABOVE_NORMAL_NICE } else { 0 };
+            // On Linux the nice value of a tid applies to that thread only.
+            if level != 2 && libc::setpriority(libc::PRIO_PROCESS, tid(), nice) != 0 {
+                return DENIED;
+            }
+        }
+        OK
+    }
+
+    pub fn get() -> u32 {
+        unsafe {
+            let mut policy = 0;
+            let mut param = libc::sched_param { sched_priority: 0 };
+            if libc::pthread_getschedparam(libc::pthread_self(), &mut policy, &mut param) == 0
+                && (policy == libc::SCHED_FIFO || policy == libc::SCHED_RR)
+            {
+                return 2;
+            }
+            if libc::getpriority(libc::PRIO_PROCESS, tid()) < 0 {
+                1
+            } else {
+                0
+            }
+        }
+    }
+
+    pub fn set_name(name: &std::ffi::CStr) -> i32 {
+        // Linux limits thread names to 15 bytes plus the terminator.
+        let bytes = name.to_bytes();
+        let mut end = bytes.len().min(15);
+        while end > 0 && std::str::from_utf8(&bytes[..end]).is_err() {
+            end -= 1;
+        }
+        let truncated = std::ffi::CString::new(&bytes[..end]).unwrap_or_default();
+        if unsafe { libc::pthread_setname_np(libc::pthread_self(), truncated.as_ptr()) } == 0 {
+            OK
+        } else {
+            DENIED
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod imp {
+    use super::{DENIED, OK};
+    use libc::qos_class_t::{
+        QOS_CLASS_DEFAULT, QOS_CLASS_USER_INITIATED, QOS_CLASS_USER_INTERACTIVE,
+    };
+
+    /// macOS maps levels onto QoS classes; USER_INTERACTIVE is the highest
+    /// class available without a realtime time-constraint policy.
+    pub fn set(level: u32) -> i32 {
+        let class = match level {
+            0 => QOS_CLASS_DEFAULT,
+            1 => QOS_CLASS_USER_INITIATED,
+            _ => QOS_CLASS_USER_INTERACTIVE,
+        };
+        if unsafe { libc::pthread_set_qos_class_self_np(class, 0) } == 0 {
+            OK
+        } else {
+            DENIED
+        }
+    }
+
+    pub fn get() -> u32 {
+        let mut class = QOS_CLASS_DEFAULT;
+        let mut relative = 0;
+        unsafe { libc::pthread_get_qos_class_np(libc::pthread_self(), &mut class, &mut relative) };
+        match class {
+            QOS_CLASS_USER_INTERACTIVE => 2,
+            QOS_CLASS_USER_INITIATED => 1,
+            _ => 0,
+        }
+    }
+
+    pub fn set_name(name: &std::ffi::CStr) -> i32 {
+        if unsafe { libc::pthread_setname_np(name.as_ptr()) } == 0 {
+            OK
+        } else {
+            DENIED
+        }
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod imp {
+    use super::{DENIED, OK};
+    use windows::core::HSTRING;
+    use windows::Win32::System::Threading::{
+        GetCurrentThread, GetThreadPriority, SetThreadDescription, SetThreadPriority,
+        THREAD_PRIORITY_ABOVE_NORMAL, THREAD_PRIORITY_NORMAL, THREAD_PRIORITY_TIME_CRITICAL,
+    };
+
+    pub fn set(level: u32) -> i32 {
+        let priority = match level {
+            0 => THREAD_PRIORITY_NORMAL,
+            1 => THREAD_PRIORITY_ABOVE_NORMAL,
+            _ => THREAD_PRIORITY_TIME_CRITICAL,
+        };
+        match unsafe { SetThreadPriority(GetCurrentThread(), priority) } {
+            Ok(()) => OK,
+            Err(_) => DENIED,
+        }
+    }
+
+    pub fn get() -> u32 {
+        let priority = unsafe { GetThreadPriority(GetCurrentThread()) };
+        if priority >= THREAD_PRIORITY_TIME_CRITICAL.0 {
+            2
+        } else if priority >= THREAD_PRIORITY_ABOVE_NORMAL.0 {
+            1
+        } else {
+            0
+        }
+    }
+
+    pub fn set_name(name: &std::ffi::CStr) -> i32 {
+        let name = HSTRING::from(name.to_string_lossy().as_ref());
+        match unsafe { SetThreadDescription(GetCurrentThread(), &name) } {
+            Ok(()) => OK,
+            Err(_) => DENIED,
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+mod imp {
+    use super::UNSUPPORTED;
+
+    pub fn set(_level: u32) -> i32 {
+        UNSUPPORTED
+    }
+
+    pub fn get() -> u32 {
+        0
+    }
+
+    pub fn set_name(_name: &std::ffi::CStr) -> i32 {
+        UNSUPPORTED
+    }
+}
+
+pub fn set(level: u32) -> i32 {
+    if level > MAX_LEVEL {
+        return INVALID;
+    }
+    imp::set(level)
+}
+
+pub use imp::{get, set_name};
+
+/// Whether priority changes are implemented on this platform.
+pub const SUPPORTED: bool = cfg!(any(target_os = "linux", target_os = "macos", target_os = "windows"));
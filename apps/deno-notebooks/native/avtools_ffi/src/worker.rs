@@ -0,0 +1,221 @@
+//! Background threads of the native libraries. `spawn_worker` names the
+//! thread (visible to debuggers and `top -H`), applies a priority level from
+//! `thread_priority`, stepping down a level at a time if the OS refuses,
+//! reports a panic through avtools_log instead of stderr and marks the worker
+//! failed, and tracks the thread so `avtools_shutdown_all` joins whatever its
+//! owner left running.
+//!
+//! With the `panic-hook` feature, setting `AVTOOLS_FFI_PANIC` to a worker's
+//! name makes that worker panic on start, for tests.
+
+use crate::shutdown::{self, Stage};
+use crate::thread_priority;
+use std::cell::Cell;
+use std::io;
+use std::panic::{self, catch_unwind, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, Once};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// How long avtools_shutdown_all waits for the remaining workers, in total.
+const SHUTDOWN_JOIN_TIMEOUT: Duration = Duration::from_millis(500);
+
+struct Shared {
+    name: String,
+    failed: AtomicBool,
+    join: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl Shared {
+    fn take_join(&self) -> Option<JoinHandle<()>> {
+        self.join.lock().unwrap_or_else(|e| e.into_inner()).take()
+    }
+
+    fn is_finished(&self) -> bool {
+        self.join
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .as_ref()
+            .is_none_or(|join| join.is_finished())
+    }
+}
+
+/// Workers spawned and not yet joined, by their owner or by shutdown.
+static WORKERS: Mutex<Vec<Arc<Shared>>> = Mutex::new(Vec::new());
+static SETUP: Once = Once::new();
+
+thread_local! {
+    static IS_WORKER: Cell<bool> = const { Cell::new(false) };
+}
+
+/// A thread started by `spawn_worker`. Dropping it detaches the thread,
+/// which shutdown still joins.
+pub struct Worker {
+    shared: Arc<Shared>,
+}
+
+impl Worker {
+    /// True once the thread has panicked; the panic was logged.
+    pub fn failed(&self) -> bool {
+        self.shared.failed.load(Ordering::Relaxed)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.shared.is_finished()
+    }
+
+    /// Wait up to `timeout` for the thread to exit; false if it is still
+    /// running (it stays tracked for shutdown). Never waits on the calling
+    /// thread itself.
+    pub fn join_within(self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        while !self.shared.is_finished() {
+            if self.is_current() || Instant::now() >= deadline {
+                return false;
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+        self.join();
+        true
+    }
+
+    /// Wait for the thread to exit, unless it is the calling thread.
+    pub fn join(self) {
+        if self.is_current() {
+            return;
+        }
+        if let Some(join) = self.shared.take_join() {
+            let _ = join.join();
+        }
+        untrack(&self.shared);
+    }
+
+    fn is_current(&self) -> bool {
+        let join = self.shared.join.lock().unwrap_or_else(|e| e.into_inner());
+        join.as_ref()
+            .is_some_and(|join| join.thread().id() == thread::current().id())
+    }
+}
+
+/// Start `f` on a new thread called `name` at `priority` (0 normal, 1 above
+/// normal, 2 time-critical; see `thread_priority`). Errors only if the OS
+/// cannot create the thread.
+pub fn spawn_worker<F>(name: &str, priority: u32, f: F) -> io::Result<Worker>
+where
+    F: FnOnce() + Send + 'static,
+{
+    SETUP.call_once(|| {
+        install_panic_hook();
+        shutdown::register(Stage::Workers, "workers", join_remaining);
+    });
+    prune_finished();
+    let shared = Arc::new(Shared {
+        name: name.to_string(),
+        failed: AtomicBool::new(false),
+        join: Mutex::new(None),
+    });
+    let thread_shared = shared.clone();
+    let join = thread::Builder::new()
+        .name(name.to_string())
+        .spawn(move || run(&thread_shared, priority, f))?;
+    *shared.join.lock().unwrap_or_else(|e| e.into_inner()) = Some(join);
+    WORKERS.lock().unwrap_or_else(|e| e.into_inner()).push(shared.clone());
+    Ok(Worker { shared })
+}
+
+fn run<F: FnOnce()>(shared: &Shared, priority: u32, f: F) {
+    IS_WORKER.with(|w| w.set(true));
+    apply_priority(&shared.name, priority);
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        #[cfg(feature = "panic-hook")]
+        if std::env::var("AVTOOLS_FFI_PANIC").is_ok_and(|target| target == shared.name) {
+            panic!("injected panic");
+        }
+        f()
+    }));
+    if result.is_err() {
+        shared.failed.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Try `priority`, then each lower level, until the OS accepts one.
+fn apply_priority(name: &str, priority: u32) {
+    if priority == 0 {
+        return;
+    }
+    let requested = priority.min(thread_priority::MAX_LEVEL);
+    for level in (1..=requested).rev() {
+        if thread_priority::set(level) == thread_priority::OK {
+            if level != requested {
+                avtools_log::warn!("worker {name}: priority {requested} refused, running at {level}");
+            }
+            return;
+        }
+    }
+    avtools_log::warn!("worker {name}: priority {requested} refused, running at normal priority");
+}
+
+/// Worker panics go to the log as errors; everything else keeps the
+/// previous hook (stderr by default).
+fn install_panic_hook() {
+    let previous = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        if IS_WORKER.with(Cell::get) {
+            let current = thread::current();
+            let name = current.name().unwrap_or("unnamed");
+            avtools_log::error!("worker {name} {info}");
+        } else {
+            previous(info);
+        }
+    }));
+}
+
+fn untrack(shared: &Arc<Shared>) {
+    WORKERS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .retain(|w| !Arc::ptr_eq(w, shared));
+}
+
+/// Join detached workers that have exited, so the table only holds live ones.
+fn prune_finished() {
+    let finished: Vec<Arc<Shared>> = {
+        let mut workers = WORKERS.lock().unwrap_or_else(|e| e.into_inner());
+        let (finished, running) = workers.drain(..).partition(|w| w.is_finished());
+        *workers = running;
+        finished
+    };
+    for worker in finished {
+        if let Some(join) = worker.take_join() {
+            let _ = join.join();
+        }
+    }
+}
+
+/// avtools_shutdown_all: join every worker still tracked once the owners'
+/// hooks have told them to stop. Returns how many were joined; any still
+/// running after the timeout are logged and left alone.
+fn join_remaining() -> u32 {
+    let deadline = Instant::now() + SHUTDOWN_JOIN_TIMEOUT;
+    let workers: Vec<Arc<Shared>> = WORKERS.lock().unwrap_or_else(|e| e.into_inner()).clone();
+    let current = thread::current().id();
+    let mut joined = 0;
+    for worker in workers {
+        while !worker.is_finished() && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(1));
+        }
+        if !worker.is_finished() {
+            avtools_log::warn!("worker {} still running after shutdown", worker.name);
+            continue;
+        }
+        if let Some(join) = worker.take_join() {
+            if join.thread().id() != current {
+                let _ = join.join();
+            }
+        }
+        untrack(&worker);
+        joined += 1;
+    }
+    joined
+}
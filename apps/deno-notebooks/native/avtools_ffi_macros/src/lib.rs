@@ -0,0 +1,30 @@
+//! `#[catch_panic]` for the native libraries' exports; see avtools_ffi::panic.
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{parse_macro_input, parse_quote, ItemFn, ReturnType};
+
+/// Run the function body under `avtools_ffi::panic::call`, so a panic becomes
+/// a PANIC last error and the return type's `PanicValue` instead of unwinding
+/// into the host. Put it after `#[no_mangle]` on every export.
+#[proc_macro_attribute]
+pub fn catch_panic(attr: TokenStream, item: TokenStream) -> TokenStream {
+    if !attr.is_empty() {
+        let err = syn::Error::new(
+            proc_macro2::Span::call_site(),
+            "catch_panic takes no arguments",
+        );
+        return err.to_compile_error().into();
+    }
+    let mut func = parse_macro_input!(item as ItemFn);
+    let name = func.sig.ident.to_string();
+    let ret = match &func.sig.output {
+        ReturnType::Default => quote!(()),
+        ReturnType::Type(_, ty) => quote!(#ty),
+    };
+    let body = func.block.clone();
+    *func.block = parse_quote!({
+        ::avtools_ffi::panic::call::<#ret, _>(#name, || #body)
+    });
+    quote!(#func).into()
+}
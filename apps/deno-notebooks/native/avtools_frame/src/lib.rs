@@ -0,0 +1,406 @@
+//! Binary frame layout shared by the native libraries' packet formats.
+//!
+//! All integers are little-endian. A frame is:
+//!
+//!   header   32 bytes: magic u32, version u16, flags u16, ts_us u64,
+//!            aux0 u32, aux1 u32, record count u32, reserved u32
+//!   records  `count` fixed 16-byte records
+//!   blocks   one fixed 16-byte block per set bit in the schema's
+//!            `blocks`, in bit order (sized like a record so older decoders
+//!            skip them cleanly)
+//!   TLVs     when FLAG_TLV is set: tag u16, len u16, `len` payload bytes,
+//!            repeated to the end of the frame
+//!
+//! Each library picks its own magic and describes its records with a
+//! `Schema`; `decode_json` pretty-prints any frame for debugging.
+
+use avtools_ffi::{buffer, error};
+use serde_json::{json, Map, Value};
+use std::fmt;
+
+pub const HEADER_LEN: usize = 32;
+pub const RECORD_LEN: usize = 16;
+pub const BLOCK_LEN: usize = 16;
+
+/// Header flag: TLV extensions follow the records and fixed blocks. The top
+/// flag bit is reserved for this in every library's flag space.
+pub const FLAG_TLV: u16 = 0x8000;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Header {
+    pub magic: u32,
+    pub version: u16,
+    pub flags: u16,
+    pub ts_us: u64,
+    pub aux: [u32; 2],
+    pub count: u32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParseError {
+    /// Shorter than the header.
+    Truncated,
+    /// A TLV extension runs past the end of the frame.
+    BadExtension,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::Truncated => write!(f, "frame shorter than its {HEADER_LEN}-byte header"),
+            ParseError::BadExtension => f.write_str("TLV extension runs past the end of the frame"),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Builds one frame. The record count in the header is filled in by
+/// `finish`, so `Header::count` is ignored.
+pub struct FrameWriter {
+    buf: Vec<u8>,
+    count: u32,
+}
+
+impl FrameWriter {
+    pub fn new(header: Header, record_capacity: usize) -> Self {
+        let mut buf = Vec::with_capacity(HEADER_LEN + (record_capacity + 1) * RECORD_LEN);
+        push_u32(&mut buf, header.magic);
+        push_u16(&mut buf, header.version);
+        push_u16(&mut buf, header.flags);
+        push_u64(&mut buf, header.ts_us);
+        push_u32(&mut buf, header.aux[0]);
+        push_u32(&mut buf, header.aux[1]);
+        push_u32(&mut buf, 0);
+        push_u32(&mut buf, 0);
+        Self { buf, count: 0 }
+    }
+
+    /// Append a record. Records must all be pushed before any block or TLV.
+    pub fn record(&mut self, record: [u8; RECORD_LEN]) {
+        self.buf.extend_from_slice(&record);
+        self.count += 1;
+    }
+
+    /// Append a fixed block and set its flag bit in the header. Blocks must be
+    /// pushed in ascending flag-bit order, after the records.
+    pub fn block(&mut self, flag: u16, block: [u8; BLOCK_LEN]) {
+        self.or_flags(flag);
+        self.buf.extend_from_slice(&block);
+    }
+
+    /// Append a TLV extension and set FLAG_TLV. Payloads longer than
+    /// u16::MAX are truncated.
+    pub fn extension(&mut self, tag: u16, payload: &[u8]) {
+        self.or_flags(FLAG_TLV);
+        let payload = &payload[..payload.len().min(u16::MAX as usize)];
+        push_u16(&mut self.buf, tag);
+        push_u16(&mut self.buf, payload.len() as u16);
+        self.buf.extend_from_slice(payload);
+    }
+
+    pub fn finish(mut self) -> Vec<u8> {
+        self.buf[24..28].copy_from_slice(&self.count.to_le_bytes());
+        self.buf
+    }
+
+    fn or_flags(&mut self, flag: u16) {
+        let flags = u16::from_le_bytes([self.buf[6], self.buf[7]]) | flag;
+        self.buf[6..8].copy_from_slice(&flags.to_le_bytes());
+    }
+}
+
+/// Packs record and block fields in order.
+#[derive(Default)]
+pub struct Fields {
+    bytes: [u8; RECORD_LEN],
+    len: usize,
+}
+
+impl Fields {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn u8(mut self, v: u8) -> Self {
+        self.put(&[v]);
+        self
+    }
+
+    pub fn u16(mut self, v: u16) -> Self {
+        self.put(&v.to_le_bytes());
+        self
+    }
+
+    pub fn i16(mut self, v: i16) -> Self {
+        self.put(&v.to_le_bytes());
+        self
+    }
+
+    pub fn u32(mut self, v: u32) -> Self {
+        self.put(&v.to_le_bytes());
+        self
+    }
+
+    pub fn u64(mut self, v: u64) -> Self {
+        self.put(&v.to_le_bytes());
+        self
+    }
+
+    /// The packed bytes, zero-padded to RECORD_LEN.
+    pub fn build(self) -> [u8; RECORD_LEN] {
+        self.bytes
+    }
+
+    fn put(&mut self, v: &[u8]) {
+        self.bytes[self.len..self.len + v.len()].copy_from_slice(v);
+        self.len += v.len();
+    }
+}
+
+/// A parsed frame borrowing the input bytes.
+pub struct Frame<'a> {
+    pub header: Header,
+    /// Whole records only; a trailing partial record is dropped.
+    pub records: Vec<&'a [u8]>,
+    /// Everything after the records: fixed blocks, then TLVs.
+    pub tail: &'a [u8],
+}
+
+impl<'a> Frame<'a> {
+    /// Split the tail into the fixed blocks for the set bits of `block_flags`
+    /// (missing ones are omitted) and the TLV extensions after them.
+    #[allow(clippy::type_complexity)]
+    pub fn split_tail(
+        &self,
+        block_flags: u16,
+    ) -> Result<(Vec<(u16, &'a [u8])>, Vec<(u16, &'a [u8])>), ParseError> {
+        let mut rest = self.tail;
+        let mut blocks = Vec::new();
+        for bit in 0..16 {
+            let flag = 1u16 << bit;
+            if block_flags & flag == 0 || self.header.flags & flag == 0 {
+                continue;
+            }
+            if rest.len() < BLOCK_LEN {
+                break;
+            }
+            blocks.push((flag, &rest[..BLOCK_LEN]));
+            rest = &rest[BLOCK_LEN..];
+        }
+        let mut tlvs = Vec::new();
+        if self.header.flags & FLAG_TLV != 0 {
+            while !rest.is_empty() {
+                if rest.len() < 4 {
+                    return Err(ParseError::BadExtension);
+                }
+                let tag = u16::from_le_bytes([rest[0], rest[1]]);
+                let len = u16::from_le_bytes([rest[2], rest[3]]) as usize;
+                if rest.len() < 4 + len {
+                    return Err(ParseError::BadExtension);
+                }
+                tlvs.push((tag, &rest[4..4 + len]));
+                rest = &rest[4 + len..];
+            }
+        }
+        Ok((blocks, tlvs))
+    }
+}
+
+pub fn parse(bytes: &[u8]) -> Result<Frame<'_>, ParseError> {
+    if bytes.len() < HEADER_LEN {
+        return Err(ParseError::Truncated);
+    }
+    let header = Header {
+        magic: read_u32(bytes, 0),
+        version: u16::from_le_bytes([bytes[4], bytes[5]]),
+        flags: u16::from_le_bytes([bytes[6], bytes[7]]),
+        ts_us: u64::from_le_bytes(bytes[8..16].try_into().unwrap()),
+        aux: [read_u32(bytes, 16), read_u32(bytes, 20)],
+        count: read_u32(bytes, 24),
+    };
+    let body = &bytes[HEADER_LEN..];
+    let whole = (header.count as usize).min(body.len() / RECORD_LEN);
+    let records = body[..whole * RECORD_LEN]
+        .chunks_exact(RECORD_LEN)
+        .collect();
+    Ok(Frame {
+        header,
+        records,
+        tail: &body[whole * RECORD_LEN..],
+    })
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum FieldType {
+    U8,
+    U16,
+    I16,
+    U32,
+    U64,
+}
+
+impl FieldType {
+    fn len(self) -> usize {
+        match self {
+            FieldType::U8 => 1,
+            FieldType::U16 | FieldType::I16 => 2,
+            FieldType::U32 => 4,
+            FieldType::U64 => 8,
+        }
+    }
+}
+
+/// A fixed block present when `flag` is set in the header.
+pub struct BlockSchema {
+    pub flag: u16,
+    pub name: &'static str,
+    pub fields: &'static [(&'static str, FieldType)],
+}
+
+/// How one library's frames are laid out, for `decode_json`.
+pub struct Schema {
+    pub name: &'static str,
+    pub magic: u32,
+    /// Names of the two aux header words.
+    pub aux: [&'static str; 2],
+    pub record: &'static [(&'static str, FieldType)],
+    pub blocks: &'static [BlockSchema],
+}
+
+/// Pretty-print a frame as JSON. Frames whose magic matches none of
+/// `schemas` are still decoded, with records and blocks shown as hex.
+pub fn decode_json(bytes: &[u8], schemas: &[&Schema]) -> Result<String, ParseError> {
+    let frame = parse(bytes)?;
+    let h = frame.header;
+    let schema = schemas.iter().find(|s| s.magic == h.magic);
+    let block_flags = schema.map_or(0, |s| s.blocks.iter().fold(0, |acc, b| acc | b.flag));
+    let (blocks, tlvs) = frame.split_tail(block_flags)?;
+
+    let aux_names = schema.map_or(["aux0", "aux1"], |s| s.aux);
+    let mut header = Map::new();
+    header.insert("magic".into(), json!(magic_string(h.magic)));
+    header.insert("schema".into(), json!(schema.map(|s| s.name)));
+    header.insert("version".into(), json!(h.version));
+    header.insert("flags".into(), json!(format!("{:#06x}", h.flags)));
+    header.insert("ts_us".into(), json!(h.ts_us));
+    header.insert(aux_names[0].into(), json!(h.aux[0]));
+    header.insert(aux_names[1].into(), json!(h.aux[1]));
+    header.insert("count".into(), json!(h.count));
+
+    let records: Vec<Value> = frame
+        .records
+        .iter()
+        .map(|r| match schema {
+            Some(s) => decode_fields(r, s.record),
+            None => json!(hex(r)),
+        })
+        .collect();
+    let blocks: Vec<Value> = blocks
+        .iter()
+        .map(
+            |(flag, bytes)| match schema.and_then(|s| s.blocks.iter().find(|b| b.flag == *flag)) {
+                Some(b) => json!({ "name": b.name, "fields": decode_fields(bytes, b.fields) }),
+                None => json!({ "flag": flag, "hex": hex(bytes) }),
+            },
+        )
+        .collect();
+    let extensions: Vec<Value> = tlvs
+        .iter()
+        .map(|(tag, payload)| json!({ "tag": tag, "hex": hex(payload) }))
+        .collect();
+
+    let value = json!({
+        "header": header,
+        "records": records,
+        "blocks": blocks,
+        "extensions": extensions,
+        "trailing_bytes": frame.tail.len()
+            - blocks.len() * BLOCK_LEN
+            - tlvs.iter().map(|(_, p)| 4 + p.len()).sum::<usize>(),
+    });
+    Ok(serde_json::to_string_pretty(&value).unwrap_or_default())
+}
+
+/// Body of each library's `<prefix>_decode_frame_json` export: decode the
+/// `len` bytes at `ptr` and copy the JSON into `out`. Returns the JSON length,
+/// 0 on failure (with the last error set); nothing is written if `out_cap`
+/// is too small.
+///
+/// # Safety
+/// `ptr` must be valid for `len` bytes of reads, and `out` null or valid for
+/// `out_cap` bytes of writes.
+pub unsafe fn decode_json_export(
+    ptr: *const u8,
+    len: u32,
+    out: *mut u8,
+    out_cap: u32,
+    schemas: &[&Schema],
+) -> u32 {
+    if ptr.is_null() {
+        error::fail(error::INVALID_ARGUMENT, "frame pointer is null");
+        return 0;
+    }
+    let bytes = std::slice::from_raw_parts(ptr, len as usize);
+    let text = match decode_json(bytes, schemas) {
+        Ok(text) => text,
+        Err(err) => {
+            error::fail(error::INVALID_ARGUMENT, err.to_string());
+            return 0;
+        }
+    };
+    error::clear();
+    buffer::write(text.as_bytes(), out, out_cap)
+}
+
+fn decode_fields(bytes: &[u8], fields: &[(&str, FieldType)]) -> Value {
+    let mut out = Map::new();
+    let mut at = 0;
+    for &(name, ty) in fields {
+        let end = at + ty.len();
+        if end > bytes.len() {
+            break;
+        }
+        let b = &bytes[at..end];
+        let v = match ty {
+            FieldType::U8 => json!(b[0]),
+            FieldType::U16 => json!(u16::from_le_bytes([b[0], b[1]])),
+            FieldType::I16 => json!(i16::from_le_bytes([b[0], b[1]])),
+            FieldType::U32 => json!(read_u32(b, 0)),
+            FieldType::U64 => json!(u64::from_le_bytes(b.try_into().unwrap())),
+        };
+        out.insert(name.into(), v);
+        at = end;
+    }
+    Value::Object(out)
+}
+
+/// "MIDR" for the four-character magics, hex otherwise.
+fn magic_string(magic: u32) -> String {
+    let bytes = magic.to_be_bytes();
+    if bytes.iter().all(|b| b.is_ascii_graphic()) {
+        String::from_utf8_lossy(&bytes).into_owned()
+    } else {
+        format!("{magic:#010x}")
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn read_u32(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap())
+}
+
+fn push_u16(buf: &mut Vec<u8>, v: u16) {
+    buf.extend_from_slice(&v.to_le_bytes());
+}
+
+fn push_u32(buf: &mut Vec<u8>, v: u32) {
+    buf.extend_from_slice(&v.to_le_bytes());
+}
+
+fn push_u64(buf: &mut Vec<u8>, v: u64) {
+    buf.extend_from_slice(&v.to_le_bytes());
+}
@@ -0,0 +1,99 @@
+//! Levelled logging shared by the native libraries.
+//!
+//! Each cdylib links its own copy, so every library has its own callback and
+//! level (the combined avtools_native has one for all components); each one
+//! exports `<prefix>_set_log_callback` / `<prefix>_set_log_level`
+//! wrappers around `set_callback` / `set_level`. Without a callback, messages
+//! go to stderr as `[<crate>] message`.
+
+use std::fmt;
+use std::io::Write;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::RwLock;
+
+pub const LEVEL_OFF: u32 = 0;
+pub const LEVEL_ERROR: u32 = 1;
+pub const LEVEL_WARN: u32 = 2;
+pub const LEVEL_INFO: u32 = 3;
+pub const LEVEL_DEBUG: u32 = 4;
+
+/// `(level, msg_ptr, msg_len)`; the message is UTF-8 and only valid for the
+/// duration of the call. Exports spell it out as `Option<extern "C" fn(..)>`
+/// because cbindgen does not see through the alias to a nullable pointer.
+pub type LogCallback = extern "C" fn(u32, *const u8, u32);
+
+static LEVEL: AtomicU32 = AtomicU32::new(LEVEL_INFO);
+
+/// Held for reading for the whole of each callback invocation, so once
+/// `set_callback` returns the previous callback is no longer running and will
+/// not be called again.
+static CALLBACK: RwLock<Option<LogCallback>> = RwLock::new(None);
+
+/// Install (or with `None`, clear) the callback. Blocks until any in-flight
+/// call of the previous callback has returned, so it must not be called from
+/// inside the callback.
+pub fn set_callback(cb: Option<LogCallback>) {
+    *CALLBACK.write().unwrap_or_else(|e| e.into_inner()) = cb;
+}
+
+/// Most verbose level delivered; LEVEL_OFF silences everything. Returns 0, or
+/// -1 for an unknown level.
+pub fn set_level(level: u32) -> i32 {
+    if level > LEVEL_DEBUG {
+        return -1;
+    }
+    LEVEL.store(level, Ordering::Relaxed);
+    0
+}
+
+pub fn enabled(level: u32) -> bool {
+    level != LEVEL_OFF && level <= LEVEL.load(Ordering::Relaxed)
+}
+
+/// Deliver `args` at `level`. `target` is the stderr prefix, normally the
+/// crate name taken from `module_path!()` by the macros.
+pub fn log(level: u32, target: &str, args: fmt::Arguments) {
+    if !enabled(level) {
+        return;
+    }
+    let callback = CALLBACK.read().unwrap_or_else(|e| e.into_inner());
+    match *callback {
+        Some(cb) => {
+            let msg = args.to_string();
+            cb(level, msg.as_ptr(), msg.len().min(u32::MAX as usize) as u32);
+        }
+        None => {
+            drop(callback);
+            let target = target.split("::").next().unwrap_or(target);
+            let _ = writeln!(std::io::stderr(), "[{target}] {args}");
+        }
+    }
+}
+
+#[macro_export]
+macro_rules! error {
+    ($($arg:tt)+) => {
+        $crate::log($crate::LEVEL_ERROR, module_path!(), format_args!($($arg)+))
+    };
+}
+
+#[macro_export]
+macro_rules! warn {
+    ($($arg:tt)+) => {
+        $crate::log($crate::LEVEL_WARN, module_path!(), format_args!($($arg)+))
+    };
+}
+
+#[macro_export]
+macro_rules! info {
+    ($($arg:tt)+) => {
+        $crate::log($crate::LEVEL_INFO, module_path!(), format_args!($($arg)+))
+    };
+}
+
+#[macro_export]
+macro_rules! debug {
+    ($($arg:tt)+) => {
+        $crate::log($crate::LEVEL_DEBUG, module_path!(), format_args!($($arg)+))
+    };
+}
@@ -0,0 +1,581 @@
+/* Generated by native/avtools_bindgen from the exports of avtools_native; do not edit. */
+
+#ifndef AVTOOLS_AVTOOLS_NATIVE_H
+#define AVTOOLS_AVTOOLS_NATIVE_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+typedef struct WindowState WindowState;
+
+typedef void (*TickCallback)(uint64_t, int64_t);
+
+typedef void (*TimerCallback)(uint32_t, int64_t);
+
+typedef void (*BatchCallback)(uint32_t, int64_t);
+
+typedef void (*Callback)(const uint8_t*, uint32_t);
+
+uint32_t avtools_metrics_json(uint8_t *out_ptr, uint32_t out_cap);
+
+uint32_t avtools_map_window_to_midi(struct WindowState *window_state,
+                                    uint32_t output_handle,
+                                    const uint8_t *mapping_ptr,
+                                    uint32_t mapping_len);
+
+uint32_t avtools_map_midi_to_window(uint32_t input_handle,
+                                    struct WindowState *window_state,
+                                    const uint8_t *mapping_ptr,
+                                    uint32_t mapping_len);
+
+int32_t avtools_map_destroy(uint32_t mapping);
+
+int32_t avtools_capture_start(struct WindowState *window_state,
+                              uint32_t input_handle,
+                              const uint8_t *path_ptr,
+                              uint32_t path_len);
+
+int32_t avtools_capture_stop(void);
+
+uint32_t avtools_replay(const uint8_t *path_ptr, uint32_t path_len, double speed, uint32_t flags);
+
+int32_t avtools_replay_set_targets(uint32_t replay,
+                                   struct WindowState *window_state,
+                                   uint32_t input_handle);
+
+int32_t avtools_replay_pause(uint32_t replay, uint8_t paused);
+
+int32_t avtools_replay_seek(uint32_t replay, uint64_t position_us);
+
+uint32_t avtools_replay_status(uint32_t replay, uint8_t *out_ptr, uint32_t out_cap);
+
+int32_t avtools_replay_destroy(uint32_t replay);
+
+int64_t avtools_clock_offset(uint32_t domain_a, uint32_t domain_b);
+
+uint64_t avtools_clock_now_us(void);
+
+uint8_t *av_alloc(uint32_t len);
+
+void av_free(uint8_t *ptr, uint32_t len);
+
+extern int32_t atexit(void (*cb)(void));
+
+uint32_t avtools_shutdown_all(uint8_t *out_ptr, uint32_t out_cap);
+
+int32_t avtools_tracing_start(const uint8_t *path_ptr, uint32_t path_len, uint32_t level);
+
+int32_t avtools_tracing_stop(void);
+
+struct WindowState *create_window(uint32_t width,
+                                  uint32_t height,
+                                  const uint8_t *title_ptr,
+                                  uint32_t title_len);
+
+struct WindowState *create_window_ex(const uint8_t *options_json_ptr, uint32_t options_json_len);
+
+struct WindowState *create_child_window(struct WindowState *parent_state,
+                                        uint32_t x,
+                                        uint32_t y,
+                                        uint32_t width,
+                                        uint32_t height);
+
+uint32_t create_additional_window(struct WindowState *state,
+                                  uint32_t width,
+                                  uint32_t height,
+                                  const uint8_t *title_ptr,
+                                  uint32_t title_len);
+
+int32_t set_parent_window_handle(struct WindowState *state,
+                                 size_t handle,
+                                 size_t display,
+                                 uint32_t system);
+
+int32_t wait_for_window_ready(struct WindowState *state, uint32_t timeout_ms);
+
+size_t get_raw_window_handle(struct WindowState *state);
+
+size_t get_raw_display_handle(struct WindowState *state);
+
+uint32_t get_window_system(struct WindowState *state);
+
+uint32_t poll_events(struct WindowState *state, uint8_t *buf_ptr, uint32_t buf_cap);
+
+uint32_t poll_events_filtered(struct WindowState *state,
+                              uint64_t event_mask,
+                              uint8_t *buf_ptr,
+                              uint32_t buf_cap);
+
+int32_t subscribe_events(struct WindowState *state, uint64_t event_mask, void (*cb)(const uint8_t*,
+                                                                                    uint32_t));
+
+int32_t unsubscribe_events(struct WindowState *state);
+
+int32_t send_window_user_event(struct WindowState *state,
+                               const uint8_t *data_ptr,
+                               uint32_t data_len);
+
+uint32_t poll_events_msgpack(struct WindowState *state, uint8_t *buf_ptr, uint32_t buf_cap);
+
+int32_t set_event_format(struct WindowState *state, uint8_t format);
+
+void resize_window(struct WindowState *state, uint32_t width, uint32_t height);
+
+void get_window_size(struct WindowState *state, uint32_t *out_w, uint32_t *out_h);
+
+int32_t set_window_position(struct WindowState *state, int32_t x, int32_t y);
+
+int32_t get_window_position(struct WindowState *state, int32_t *out_x, int32_t *out_y);
+
+size_t get_raw_window_handle_by_token(struct WindowState *state, uint32_t token);
+
+int32_t resize_window_by_token(struct WindowState *state,
+                               uint32_t token,
+                               uint32_t width,
+                               uint32_t height);
+
+int32_t destroy_window_by_token(struct WindowState *state, uint32_t token);
+
+int32_t get_window_size_by_token(struct WindowState *state,
+                                 uint32_t token,
+                                 uint32_t *out_w,
+                                 uint32_t *out_h);
+
+uint64_t get_frame_count(struct WindowState *state);
+
+double get_frames_per_second(struct WindowState *state);
+
+double get_display_refresh_rate(struct WindowState *state);
+
+uint64_t get_vsync_interval_us(struct WindowState *state);
+
+int32_t set_window_corner_preference(struct WindowState *state, uint32_t pref);
+
+int32_t set_taskbar_thumbnail_clip(struct WindowState *state,
+                                   uint32_t x,
+                                   uint32_t y,
+                                   uint32_t width,
+                                   uint32_t height);
+
+int32_t clear_taskbar_thumbnail_clip(struct WindowState *state);
+
+int32_t capture_window_pixels(struct WindowState *state,
+                              uint8_t *buf_ptr,
+                              uint32_t buf_cap,
+                              uint32_t *out_width,
+                              uint32_t *out_height);
+
+int32_t set_content_protection(struct WindowState *state, uint8_t enabled);
+
+int32_t set_pointer_capture(struct WindowState *state, uint8_t captured);
+
+int32_t set_auto_capture_on_drag(struct WindowState *state, uint8_t enabled);
+
+int32_t begin_window_drag_move(struct WindowState *state);
+
+int32_t begin_window_drag_resize(struct WindowState *state, uint32_t direction);
+
+int32_t set_hit_test_callback(struct WindowState *state, uint8_t (*cb)(double x, double y));
+
+int32_t set_accessibility_label(struct WindowState *state,
+                                const uint8_t *label_ptr,
+                                uint32_t label_len);
+
+int32_t set_accessibility_role(struct WindowState *state, uint32_t role);
+
+int32_t set_hdr_hint(struct WindowState *state, uint8_t enabled);
+
+int32_t set_gpu_preference(struct WindowState *state, uint32_t pref);
+
+uint32_t get_gpu_preference(struct WindowState *state);
+
+uint32_t get_display_color_space(struct WindowState *state, uint8_t *buf_ptr, uint32_t buf_cap);
+
+uint32_t list_monitors(struct WindowState *state, uint8_t *buf_ptr, uint32_t buf_cap);
+
+uint32_t window_abi_version(void);
+
+uint32_t window_capabilities(uint8_t *buf_ptr, uint32_t buf_cap);
+
+uint32_t window_decode_frame_json(const uint8_t *ptr,
+                                  uint32_t len,
+                                  uint8_t *buf_ptr,
+                                  uint32_t buf_cap);
+
+int32_t window_last_error_code(void);
+
+uint32_t window_metrics_json(uint8_t *buf_ptr, uint32_t buf_cap);
+
+uint32_t window_last_error_message(uint8_t *buf_ptr, uint32_t buf_cap);
+
+uint32_t window_get_last_panic(uint8_t *buf_ptr, uint32_t buf_cap);
+
+void window_set_log_callback(void (*cb)(uint32_t, const uint8_t*, uint32_t));
+
+int32_t window_set_log_level(uint32_t level);
+
+int32_t set_before_destroy_callback(struct WindowState *state, void (*cb)(void));
+
+void destroy_window(struct WindowState *state);
+
+int32_t fast_sleep_configure(uint32_t native_accuracy_us, uint32_t strategy);
+
+int32_t fast_sleep_init(uint32_t native_accuracy_us, uint32_t strategy);
+
+void fast_sleep_get_config(uint32_t *out_accuracy_us,
+                           uint32_t *out_strategy,
+                           uint32_t *out_power_mode);
+
+void fast_sleep_us(uint32_t us);
+
+void fast_sleep_ns(uint64_t ns);
+
+int32_t fast_sleep_enable_mock(double scale_factor);
+
+uint64_t fast_sleep_get_virtual_time_ns(void);
+
+uint64_t fast_sleep_us_measured(uint32_t us);
+
+uint64_t fast_sleep_ns_measured(uint64_t ns);
+
+uint64_t fast_sleep_clock_ns(void);
+
+uint32_t fast_clock_info(uint8_t *out, uint32_t out_cap);
+
+uint32_t fast_sleep_abi_version(void);
+
+uint32_t fast_sleep_capabilities(uint8_t *out, uint32_t cap);
+
+int32_t fast_sleep_last_error_code(void);
+
+uint32_t fast_sleep_last_error_message(uint8_t *out, uint32_t cap);
+
+uint32_t fast_sleep_get_last_panic(uint8_t *out, uint32_t cap);
+
+void fast_sleep_set_log_callback(void (*cb)(uint32_t, const uint8_t*, uint32_t));
+
+int32_t fast_sleep_set_log_level(uint32_t level);
+
+uint64_t fast_now_us(void);
+
+int64_t fast_sleep_until_us(uint64_t deadline_us);
+
+int64_t fast_sleep_until_ns(uint64_t deadline_ns);
+
+int32_t fast_sleep_us_with(uint32_t us, uint32_t native_accuracy_us, uint32_t strategy);
+
+int32_t fast_sleep_ns_with(uint64_t ns, uint32_t native_accuracy_us, uint32_t strategy);
+
+int32_t fast_sleep_until_us_with(uint64_t deadline_us,
+                                 uint32_t native_accuracy_us,
+                                 uint32_t strategy);
+
+uint32_t fast_sleeper_create(uint32_t native_accuracy_us, uint32_t strategy);
+
+int32_t fast_sleeper_sleep_us(uint32_t handle, uint32_t us);
+
+int64_t fast_sleeper_sleep_until_us(uint32_t handle, uint64_t deadline_us);
+
+int32_t fast_sleeper_destroy(uint32_t handle);
+
+uint32_t fast_sleep_token_create(void);
+
+int32_t fast_sleep_cancellable_us(uint32_t token_handle, uint32_t us);
+
+int32_t fast_sleep_until_any(const uint64_t *deadlines_ptr, uint32_t count, uint32_t token_handle);
+
+int32_t fast_wait_flag_until_us(const uint32_t *flag_ptr,
+                                uint32_t expected_nonzero,
+                                uint64_t deadline_us);
+
+uint8_t fast_sleep_until_signal(const uint8_t *signal_ptr, uint64_t timeout_us);
+
+int32_t fast_set_wake_flag(uint32_t *flag_ptr, uint32_t value);
+
+int32_t fast_sleep_cancel(uint32_t token_handle);
+
+int32_t fast_sleep_token_reset(uint32_t token_handle);
+
+int32_t fast_sleep_token_destroy(uint32_t token_handle);
+
+uint32_t fast_ticker_create(uint32_t period_us, uint32_t flags);
+
+uint32_t fast_ticker_create_ex(const uint8_t *options_json_ptr, uint32_t options_json_len);
+
+int64_t fast_ticker_wait(uint32_t handle);
+
+int64_t fast_ticker_skipped(uint32_t handle);
+
+int32_t fast_ticker_set_period(uint32_t handle, uint32_t period_us);
+
+int32_t fast_ticker_align(uint32_t handle, uint64_t anchor_us);
+
+uint32_t fast_ticker_info(uint32_t handle, uint8_t *out, uint32_t out_cap);
+
+int32_t fast_ticker_destroy(uint32_t handle);
+
+uint32_t fast_loop_start(uint32_t period_us, TickCallback cb, uint32_t flags);
+
+int32_t fast_loop_stop(uint32_t handle);
+
+uint32_t fast_timer_schedule(uint32_t delay_us, TimerCallback cb);
+
+int32_t fast_timer_cancel(uint32_t timer_id);
+
+uint32_t fast_schedule_batch(const uint64_t *deadlines_ptr, uint32_t count, BatchCallback cb);
+
+int32_t fast_schedule_batch_cancel(uint32_t handle);
+
+int32_t fast_sleep_set_windows_timer_resolution(uint32_t period_ms);
+
+int32_t fast_sleep_set_timer_resolution(uint32_t ms);
+
+int32_t fast_sleep_clear_timer_resolution(uint32_t ms);
+
+int32_t fast_sleep_reset_windows_timer_resolution(void);
+
+uint32_t fast_sleep_calibrate(uint32_t sample_count, uint32_t target_percentile);
+
+uint32_t fast_sleep_calibration_report(uint8_t *out, uint32_t out_cap);
+
+uint32_t fast_sleep_selftest(uint8_t *out, uint32_t out_cap);
+
+void fast_sleep_stats_enable(uint8_t enabled);
+
+void fast_sleep_stats_reset(void);
+
+uint32_t fast_sleep_stats(uint8_t *out, uint32_t out_cap);
+
+uint32_t fast_sleep_metrics_json(uint8_t *out, uint32_t out_cap);
+
+int32_t fast_set_current_thread_priority(uint32_t level);
+
+int32_t fast_get_current_thread_priority(void);
+
+int32_t fast_set_current_thread_name(const uint8_t *ptr, uint32_t len);
+
+void fast_set_miss_handler(uint32_t threshold_us, void (*cb)(uint64_t, uint64_t));
+
+int32_t fast_sleep_set_power_mode(uint32_t mode);
+
+uint32_t fast_sleep_get_power_mode(void);
+
+extern int32_t atexit(void (*cb)(void));
+
+uint32_t midi_list_inputs(uint8_t *out_ptr, uint32_t out_cap);
+
+uint32_t midi_list_outputs(uint8_t *out_ptr, uint32_t out_cap);
+
+uint32_t midi_open_input(const uint8_t *port_id_ptr,
+                         uint32_t port_id_len,
+                         uint32_t rate_hz,
+                         uint32_t flags,
+                         Callback cb);
+
+uint32_t midi_open_input_ex(const uint8_t *port_id_ptr,
+                            uint32_t port_id_len,
+                            const uint8_t *options_json_ptr,
+                            uint32_t options_json_len,
+                            Callback cb);
+
+uint32_t midi_open_mock_input(const uint8_t *options_json_ptr,
+                              uint32_t options_json_len,
+                              Callback cb);
+
+int32_t midi_mock_input_send(uint32_t handle, const uint8_t *msg_ptr, uint32_t msg_len);
+
+uint32_t midi_open_input_with_retry(const uint8_t *port_id_ptr,
+                                    uint32_t port_id_len,
+                                    uint32_t rate_hz,
+                                    uint32_t flags,
+                                    Callback cb,
+                                    uint32_t max_attempts,
+                                    uint32_t initial_backoff_ms);
+
+uint8_t midi_is_handle_connected(uint32_t handle);
+
+void midi_close_input(uint32_t handle);
+
+uint32_t midi_input_stats(uint32_t handle, uint8_t *out_ptr, uint32_t out_cap);
+
+uint32_t midi_metrics_json(uint8_t *out_ptr, uint32_t out_cap);
+
+void midi_set_program_name(uint8_t program, const uint8_t *name_ptr, uint32_t name_len);
+
+void midi_set_cc_display_format(uint8_t cc,
+                                const uint8_t *name_ptr,
+                                uint32_t name_len,
+                                const uint8_t *format_ptr,
+                                uint32_t format_len);
+
+uint32_t midi_get_cc_display_format(uint8_t cc, uint8_t *buf_ptr, uint32_t buf_cap);
+
+uint32_t midi_get_program_name(uint32_t handle,
+                               uint8_t channel,
+                               uint8_t *buf_ptr,
+                               uint32_t buf_cap);
+
+float midi_get_cc14(uint32_t handle, uint8_t channel, uint8_t msb_cc);
+
+int32_t midi_add_cc_remap(uint32_t handle,
+                          uint8_t from_channel,
+                          uint8_t from_cc,
+                          uint8_t to_channel,
+                          uint8_t to_cc);
+
+int32_t midi_remove_cc_remap(uint32_t handle, uint8_t from_channel, uint8_t from_cc);
+
+int32_t midi_clear_cc_remaps(uint32_t handle);
+
+uint64_t midi_get_epoch_us(uint32_t handle);
+
+int32_t midi_set_quantise_grid(uint32_t handle,
+                               uint32_t ppqn,
+                               uint32_t bpm_x100,
+                               uint8_t strength_percent);
+
+int32_t midi_clear_quantise_grid(uint32_t handle);
+
+int32_t midi_set_note_quantise_scale(uint32_t handle,
+                                     uint8_t channel,
+                                     uint8_t root_note,
+                                     uint16_t scale_mask);
+
+int32_t midi_clear_note_quantise_scale(uint32_t handle, uint8_t channel);
+
+int32_t midi_set_latency_compensation(uint32_t handle, int64_t offset_us);
+
+int32_t midi_set_cc_lock_mode(uint32_t handle,
+                              uint8_t channel,
+                              uint8_t cc,
+                              uint8_t current_value,
+                              uint8_t lock_threshold);
+
+int32_t midi_release_cc_lock(uint32_t handle, uint8_t channel, uint8_t cc);
+
+uint32_t midi_get_bandwidth_bps(uint32_t handle);
+
+int32_t midi_set_msc_device_id(uint32_t handle, uint8_t device_id);
+
+int32_t midi_register_note_expression(uint32_t handle,
+                                      uint8_t channel,
+                                      uint8_t note,
+                                      uint32_t kind,
+                                      const uint8_t *template_ptr,
+                                      uint32_t template_len,
+                                      uint8_t value_offset);
+
+int32_t midi_clear_note_expressions(uint32_t handle);
+
+int32_t midi_set_note_timeout_ms(uint32_t handle, uint32_t timeout_ms);
+
+int32_t midi_set_beat_dispatch(uint32_t handle, uint32_t source_clock_handle);
+
+int32_t midi_set_dispatch_clocks_per_tick(uint32_t handle, uint32_t clocks);
+
+uint32_t midi_open_output(const uint8_t *port_id_ptr, uint32_t port_id_len);
+
+uint32_t midi_open_output_pool(const uint8_t *port_ids_json_ptr, uint32_t port_ids_json_len);
+
+uint32_t midi_get_pool_member_handles(uint32_t pool_handle, uint8_t *out_ptr, uint32_t out_cap);
+
+void midi_close_output(uint32_t handle);
+
+int32_t midi_send(uint32_t handle, const uint8_t *bytes_ptr, uint32_t len);
+
+int32_t midi_send_osc_tunnelled(uint32_t output_handle,
+                                const uint8_t *osc_bytes_ptr,
+                                uint32_t osc_bytes_len,
+                                uint8_t device_id);
+
+int32_t midi_send_mmc(uint32_t output_handle, uint8_t device_id, uint8_t command);
+
+int32_t midi_set_cc14(uint32_t output_handle, uint8_t channel, uint8_t msb_cc, float value_f32);
+
+int32_t midi_mpe_negotiate(uint32_t input_handle, uint32_t output_handle, uint32_t timeout_ms);
+
+int32_t midi_sysex_request_response(uint32_t output_handle,
+                                    uint32_t input_handle,
+                                    const uint8_t *request_ptr,
+                                    uint32_t request_len,
+                                    const uint8_t *response_prefix_ptr,
+                                    uint32_t response_prefix_len,
+                                    uint32_t timeout_ms,
+                                    uint8_t *buf_ptr,
+                                    uint32_t buf_cap);
+
+uint32_t midi_create_pipeline(void);
+
+int32_t midi_pipeline_add_transpose(uint32_t pipeline, uint8_t channel, int8_t semitones);
+
+int32_t midi_pipeline_add_channel_remap(uint32_t pipeline, uint8_t from, uint8_t to);
+
+int32_t midi_pipeline_add_cc_remap(uint32_t pipeline,
+                                   uint8_t from_channel,
+                                   uint8_t from_cc,
+                                   uint8_t to_channel,
+                                   uint8_t to_cc);
+
+int32_t midi_pipeline_add_velocity_scale(uint32_t pipeline, uint8_t channel, uint16_t percent);
+
+int32_t midi_pipeline_clear(uint32_t pipeline);
+
+int32_t midi_pipeline_attach_input(uint32_t pipeline, uint32_t input_handle);
+
+int32_t midi_pipeline_attach_output(uint32_t pipeline, uint32_t output_handle);
+
+int32_t midi_pipeline_destroy(uint32_t pipeline);
+
+uint32_t midi_start_cc_record(uint32_t handle, uint8_t channel, uint8_t cc);
+
+int32_t midi_stop_cc_record(uint32_t rec_id);
+
+int32_t midi_delete_cc_record(uint32_t rec_id);
+
+uint32_t midi_playback_cc(uint32_t output_handle,
+                          uint32_t rec_id,
+                          uint8_t channel,
+                          uint8_t cc,
+                          uint32_t speed_x1000,
+                          uint32_t loop_count);
+
+int32_t midi_stop_cc_playback(uint32_t pb_id);
+
+uint32_t midi_clock_start(uint32_t output_handle, float bpm);
+
+int32_t midi_clock_set_bpm(uint32_t clock_handle, float bpm);
+
+int32_t midi_clock_set_cc_sync(uint32_t clock_handle,
+                               uint32_t input_handle,
+                               uint8_t channel,
+                               uint8_t cc,
+                               float min_bpm,
+                               float max_bpm);
+
+float midi_clock_get_bpm(uint32_t clock_handle);
+
+int32_t midi_clock_stop(uint32_t clock_handle);
+
+uint32_t midi_abi_version(void);
+
+uint32_t midi_capabilities(uint8_t *out_ptr, uint32_t out_cap);
+
+uint32_t midi_decode_frame_json(const uint8_t *ptr,
+                                uint32_t len,
+                                uint8_t *out_ptr,
+                                uint32_t out_cap);
+
+int32_t midi_last_error_code(void);
+
+uint32_t midi_last_error_message(uint8_t *out_ptr, uint32_t out_cap);
+
+uint32_t midi_get_last_panic(uint8_t *buf_ptr, uint32_t buf_cap);
+
+void midi_set_log_callback(void (*cb)(uint32_t, const uint8_t*, uint32_t));
+
+int32_t midi_set_log_level(uint32_t level);
+
+#endif  /* AVTOOLS_AVTOOLS_NATIVE_H */
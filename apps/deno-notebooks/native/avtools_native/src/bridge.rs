@@ -0,0 +1,429 @@
+//! Window input forwarded as MIDI, and MIDI forwarded as window input,
+//! without a round trip through Deno. A mapping is a JSON list of rules
+//! between one window and one MIDI output or input:
+//!
+//! - window to MIDI, e.g. `{"event":"mouse_x","channel":0,"cc":1}` (CC 1
+//!   follows the cursor from 0 at the left edge to 127 at the right) or
+//!   `{"event":"key","key":"a","note":60}` (note on while `a` is held). The
+//!   rules run in the window pump, as each event is queued.
+//! - MIDI to window, e.g. `{"cc":1,"event":"mouse_x"}` (cursor moves across
+//!   the window with CC 1) or `{"note":60,"event":"key","key":"a"}`. The rules
+//!   run on the input's dispatch thread and the records they make arrive with
+//!   the window's next pump. Note rules see note on/off edges, so they do
+//!   nothing on an input opened with FLAG_NOTE_DURATION.
+//!
+//! Mapping the same pair again replaces its rules and keeps its id.
+
+use avtools_ffi::options::{self, Invalid, VersionedOptions};
+use avtools_ffi::shutdown::{self, Stage};
+use avtools_ffi::{catch_panic, error, Error};
+use deno_window::{Injector, TapEvent, WindowState};
+use midi_bridge::{Record, KIND_CC, KIND_NOTE};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Mutex, Once};
+
+static MAPPINGS: Mutex<Option<HashMap<u32, Mapping>>> = Mutex::new(None);
+static NEXT_ID: AtomicU32 = AtomicU32::new(1);
+static SHUTDOWN_HOOK: Once = Once::new();
+
+/// Source and target of a mapping; mapping the same pair again reloads it.
+#[derive(Clone, Copy, PartialEq)]
+enum Link {
+    WindowToMidi { state: usize, output: u32 },
+    MidiToWindow { input: u32, state: usize },
+}
+
+struct Mapping {
+    link: Link,
+    /// Token of the tap on the source that runs the rules.
+    tap: u64,
+}
+
+impl Mapping {
+    /// Stop the rules. The source may already be gone, taking the tap with it.
+    fn remove_tap(&self) {
+        match self.link {
+            Link::WindowToMidi { state, .. } => unsafe {
+                deno_window::remove_event_tap(state as *mut WindowState, self.tap);
+            },
+            Link::MidiToWindow { input, .. } => {
+                midi_bridge::remove_record_tap(input, self.tap);
+            }
+        };
+    }
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum WindowInput {
+    MouseX,
+    MouseY,
+    Key,
+    MouseButton,
+}
+
+/// Checks shared by both directions: one of `cc` and `note`, and the key or
+/// button that `event` needs and no other.
+fn check_rule(
+    index: usize,
+    event: WindowInput,
+    key: &Option<String>,
+    button: Option<u32>,
+    cc: Option<u8>,
+    note: Option<u8>,
+) -> Result<(), Invalid> {
+    let fail = |reason: &str| Err(Invalid::new("mappings", format!("rule {index}: {reason}")));
+    match (cc, note) {
+        (Some(_), Some(_)) | (None, None) => return fail("needs exactly one of cc and note"),
+        (Some(n), None) | (None, Some(n)) if n > 127 => return fail("cc or note must be at most 127"),
+        _ => {}
+    }
+    if matches!(event, WindowInput::MouseX | WindowInput::MouseY) && note.is_some() {
+        return fail("mouse positions map to a cc, not a note");
+    }
+    if (event == WindowInput::Key) != key.is_some() {
+        return fail("key is required for, and only for, event \"key\"");
+    }
+    if (event == WindowInput::MouseButton) != button.is_some() {
+        return fail("button is required for, and only for, event \"mouse_button\"");
+    }
+    Ok(())
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct WindowRule {
+    event: WindowInput,
+    /// Key name as the window reports it, e.g. "a" or "Space".
+    #[serde(default)]
+    key: Option<String>,
+    /// 0 left, 1 right, 2 middle.
+    #[serde(default)]
+    button: Option<u32>,
+    #[serde(default)]
+    channel: u8,
+    #[serde(default)]
+    cc: Option<u8>,
+    #[serde(default)]
+    note: Option<u8>,
+    /// Note-on velocity.
+    #[serde(default = "default_velocity")]
+    velocity: u8,
+    /// CC value at the left or top edge, or while released. May exceed `max`
+    /// to invert the axis.
+    #[serde(default)]
+    min: u8,
+    /// CC value at the right or bottom edge, or while held.
+    #[serde(default = "default_max")]
+    max: u8,
+}
+
+fn default_velocity() -> u8 {
+    100
+}
+
+fn default_max() -> u8 {
+    127
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct WindowToMidi {
+    mappings: Vec<WindowRule>,
+}
+
+impl VersionedOptions for WindowToMidi {
+    const VERSION: u32 = 1;
+    const NAME: &'static str = "window to MIDI mapping";
+
+    fn validate(&self) -> Result<(), Invalid> {
+        for (index, rule) in self.mappings.iter().enumerate() {
+            check_rule(index, rule.event, &rule.key, rule.button, rule.cc, rule.note)?;
+            let fail = |reason: &str| Err(Invalid::new("mappings", format!("rule {index}: {reason}")));
+            if rule.channel > 15 {
+                return fail("channel must be at most 15");
+            }
+            if rule.velocity == 0 || rule.velocity > 127 {
+                return fail("velocity must be from 1 to 127");
+            }
+            if rule.min > 127 || rule.max > 127 {
+                return fail("min and max must be at most 127");
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Window-to-MIDI rules with what each last sent, evaluated in the pump.
+struct WindowRules {
+    output: u32,
+    rules: Vec<(WindowRule, Option<u8>)>,
+}
+
+impl WindowRules {
+    fn on_event(&mut self, event: &TapEvent) {
+        for (rule, last) in &mut self.rules {
+            let value = match (*event, rule.event) {
+                (TapEvent::MouseMove { x, width, .. }, WindowInput::MouseX) => scale(rule, x, width),
+                (TapEvent::MouseMove { y, height, .. }, WindowInput::MouseY) => scale(rule, y, height),
+                (TapEvent::Key { key, down, .. }, WindowInput::Key) if rule.key.as_deref() == Some(key) => {
+                    Some(if down { rule.max } else { rule.min })
+                }
+                (TapEvent::MouseButton { button, down }, WindowInput::MouseButton) if rule.button == Some(button) => {
+                    Some(if down { rule.max } else { rule.min })
+                }
+                _ => None,
+            };
+            // Key repeat and moves within one CC step send nothing.
+            let Some(value) = value.filter(|&v| *last != Some(v)) else {
+                continue;
+            };
+            *last = Some(value);
+            let channel = rule.channel & 0x0F;
+            let message = match (rule.cc, rule.note) {
+                (Some(cc), _) => [0xB0 | channel, cc, value],
+                (None, Some(note)) if value == rule.max => [0x90 | channel, note, rule.velocity],
+                (None, Some(note)) => [0x80 | channel, note, 0],
+                (None, None) => continue,
+            };
+            if let Err(err) = midi_bridge::send(self.output, &message) {
+                avtools_log::debug!("window to MIDI mapping: {}", err.message);
+            }
+        }
+    }
+}
+
+/// `pos` across `extent` pixels as a CC value from `rule.min` to `rule.max`.
+fn scale(rule: &WindowRule, pos: f64, extent: u32) -> Option<u8> {
+    if extent == 0 {
+        return None;
+    }
+    let t = (pos / extent as f64).clamp(0.0, 1.0);
+    let value = rule.min as f64 + (rule.max as f64 - rule.min as f64) * t;
+    Some(value.round() as u8)
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MidiRule {
+    /// 0–15; any channel if omitted.
+    #[serde(default)]
+    channel: Option<u8>,
+    #[serde(default)]
+    cc: Option<u8>,
+    #[serde(default)]
+    note: Option<u8>,
+    event: WindowInput,
+    #[serde(default)]
+    key: Option<String>,
+    #[serde(default)]
+    button: Option<u32>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct MidiToWindow {
+    mappings: Vec<MidiRule>,
+}
+
+impl VersionedOptions for MidiToWindow {
+    const VERSION: u32 = 1;
+    const NAME: &'static str = "MIDI to window mapping";
+
+    fn validate(&self) -> Result<(), Invalid> {
+        for (index, rule) in self.mappings.iter().enumerate() {
+            check_rule(index, rule.event, &rule.key, rule.button, rule.cc, rule.note)?;
+            if rule.channel.is_some_and(|c| c > 15) {
+                return Err(Invalid::new("mappings", format!("rule {index}: channel must be at most 15")));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// MIDI-to-window rules, evaluated on the input's dispatch thread. A CC moves
+/// one axis of a synthetic cursor; a CC of 64 or more holds a key or button.
+struct MidiRules {
+    injector: Injector,
+    rules: Vec<(MidiRule, bool)>,
+    cursor: (f64, f64),
+}
+
+impl MidiRules {
+    fn on_records(&mut self, records: &[Record]) {
+        for record in records {
+            for (rule, held) in &mut self.rules {
+                if rule.channel.is_some_and(|c| c != record.channel) {
+                    continue;
+                }
+                let value = match record.kind {
+                    KIND_CC if rule.cc == Some(record.a) => record.b,
+                    KIND_NOTE if rule.note == Some(record.a) => {
+                        if record.extra == 1 && record.b > 0 {
+                            127
+                        } else {
+                            0
+                        }
+                    }
+                    _ => continue,
+                };
+                let (width, height) = self.injector.size();
+                match rule.event {
+                    WindowInput::MouseX => {
+                        self.cursor.0 = value as f64 / 127.0 * width as f64;
+                        self.injector.mouse_move(self.cursor.0, self.cursor.1);
+                    }
+                    WindowInput::MouseY => {
+                        self.cursor.1 = value as f64 / 127.0 * height as f64;
+                        self.injector.mouse_move(self.cursor.0, self.cursor.1);
+                    }
+                    WindowInput::Key | WindowInput::MouseButton => {
+                        let down = value >= 64;
+                        if down == *held {
+                            continue;
+                        }
+                        *held = down;
+                        if let Some(key) = rule.key.as_deref() {
+                            self.injector.key(key, None, down);
+                        }
+                        if let Some(button) = rule.button {
+                            let (x, y) = self.cursor;
+                            self.injector.mouse_button(button, down, x, y);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Register `add`'s tap as the mapping for `link`, or reload the one there
+/// is. Returns the mapping id, or 0 with the error recorded.
+fn install(link: Link, add: impl FnOnce() -> Result<u64, Error>) -> u32 {
+    SHUTDOWN_HOOK.call_once(|| shutdown::register(Stage::Inputs, "mappings", destroy_all_mappings));
+    let mut mappings = MAPPINGS.lock().unwrap_or_else(|e| e.into_inner());
+    let mappings = mappings.get_or_insert_with(HashMap::new);
+    let existing = mappings.iter().find(|(_, m)| m.link == link).map(|(&id, _)| id);
+    if let Some(id) = existing {
+        mappings[&id].remove_tap();
+    }
+    let tap = match add() {
+        Ok(tap) => tap,
+        Err(err) => {
+            if let Some(id) = existing {
+                mappings.remove(&id);
+            }
+            error::set(err);
+            return 0;
+        }
+    };
+    let id = existing.unwrap_or_else(|| NEXT_ID.fetch_add(1, Ordering::Relaxed));
+    mappings.insert(id, Mapping { link, tap });
+    error::clear();
+    id
+}
+
+/// avtools_shutdown_all: stop every mapping.
+fn destroy_all_mappings() -> u32 {
+    let mappings = MAPPINGS.lock().unwrap_or_else(|e| e.into_inner()).take().unwrap_or_default();
+    for mapping in mappings.values() {
+        mapping.remove_tap();
+    }
+    mappings.len() as u32
+}
+
+/// Send MIDI to `output_handle` (an output or pool) for the window's mouse,
+/// key and button events, by the rules in the JSON at `mapping_ptr` (see the
+/// module docs). Mapping the same window and output again replaces the rules.
+/// Returns the mapping id, or 0 with INVALID_ARGUMENT for bad JSON and
+/// INVALID_HANDLE for a bad window or output. Call on the thread that pumps
+/// the window.
+///
+/// # Safety
+/// `mapping_ptr` must be null or valid for `mapping_len` bytes of reads.
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn avtools_map_window_to_midi(
+    window_state: *mut WindowState,
+    output_handle: u32,
+    mapping_ptr: *const u8,
+    mapping_len: u32,
+) -> u32 {
+    let Some(mapping) = error::record(options::parse_raw::<WindowToMidi>(mapping_ptr, mapping_len)) else {
+        return 0;
+    };
+    if !midi_bridge::is_output(output_handle) {
+        error::fail(error::INVALID_HANDLE, format!("unknown output handle {output_handle}"));
+        return 0;
+    }
+    let link = Link::WindowToMidi {
+        state: window_state as usize,
+        output: output_handle,
+    };
+    install(link, || {
+        let mut rules = WindowRules {
+            output: output_handle,
+            rules: mapping.mappings.into_iter().map(|rule| (rule, None)).collect(),
+        };
+        deno_window::add_event_tap(window_state, Box::new(move |event| rules.on_event(event)))
+    })
+}
+
+/// Queue window records (mouse moves, keys, buttons) for the MIDI that
+/// `input_handle` receives, by the rules in the JSON at `mapping_ptr` (see
+/// the module docs). Mapping the same input and window again replaces the
+/// rules. Returns the mapping id, or 0 with INVALID_ARGUMENT for bad JSON
+/// and INVALID_HANDLE for a bad input or window. Call on the thread that
+/// pumps the window.
+///
+/// # Safety
+/// `mapping_ptr` must be null or valid for `mapping_len` bytes of reads.
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn avtools_map_midi_to_window(
+    input_handle: u32,
+    window_state: *mut WindowState,
+    mapping_ptr: *const u8,
+    mapping_len: u32,
+) -> u32 {
+    let Some(mapping) = error::record(options::parse_raw::<MidiToWindow>(mapping_ptr, mapping_len)) else {
+        return 0;
+    };
+    let Some(injector) = error::record(deno_window::injector(window_state)) else {
+        return 0;
+    };
+    let link = Link::MidiToWindow {
+        input: input_handle,
+        state: window_state as usize,
+    };
+    install(link, || {
+        let mut rules = MidiRules {
+            injector,
+            rules: mapping.mappings.into_iter().map(|rule| (rule, false)).collect(),
+            cursor: (0.0, 0.0),
+        };
+        midi_bridge::add_record_tap(input_handle, Box::new(move |records| rules.on_records(records)))
+    })
+}
+
+/// Stop a mapping from either direction. Returns 0, or -1 with NOT_FOUND
+/// for an unknown id. Call on the thread that pumps the mapping's window.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn avtools_map_destroy(mapping: u32) -> i32 {
+    let removed = MAPPINGS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .as_mut()
+        .and_then(|mappings| mappings.remove(&mapping));
+    match removed {
+        Some(removed) => {
+            removed.remove_tap();
+            0
+        }
+        None => {
+            error::fail(error::NOT_FOUND, format!("no mapping {mapping}"));
+            -1
+        }
+    }
+}
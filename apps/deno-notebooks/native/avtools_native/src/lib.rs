@@ -0,0 +1,57 @@
+//! Every avTools native library in one cdylib. Each enabled component is
+//! linked as an rlib and its `#[no_mangle]` exports are re-exported unchanged,
+//! so the Deno wrappers can dlopen this file in place of the separate
+//! libraries.
+//!
+//! The shared crates are linked once, so what they hold is shared by all
+//! components rather than kept per library:
+//! - `avtools_clock_now_us`, `av_alloc` / `av_free` and `avtools_shutdown_all`
+//!   are exported once; the shutdown report covers every component.
+//! - The last error is one per-thread record, so each `*_last_error_*` export
+//!   reports the last failure of any component on that thread.
+//! - Logging has one callback and level; each `*_set_log_callback` /
+//!   `*_set_log_level` export sets them for all components.
+//! - `avtools_tracing_start` traces every component into one file (with the
+//!   `tracing` feature).
+//!
+//! Exports of its own combine the components; `avtools_metrics_json` reports
+//! every component's runtime metrics in one document, and with both window
+//! and midi enabled `avtools_map_*` forward events between them (see
+//! `bridge`), and `avtools_capture_*` / `avtools_replay*` record both kinds of
+//! input to a file and play it back (see `replay`).
+
+#[cfg(all(feature = "window", feature = "midi"))]
+mod bridge;
+#[cfg(all(feature = "window", feature = "midi"))]
+mod replay;
+
+use avtools_ffi::{buffer, catch_panic, metrics};
+
+#[cfg(feature = "window")]
+pub use deno_window;
+#[cfg(feature = "fastsleep")]
+pub use fastsleep;
+#[cfg(feature = "midi")]
+pub use midi_bridge;
+
+/// Runtime metrics of every enabled component in one document,
+/// `{"schema":1,"window":{...},"midi":{...},"fastsleep":{...}}`; each section
+/// is the one its library's `*_metrics_json` reports. Nothing waits on a
+/// realtime thread: values that were locked when sampled are null. Returns
+/// the byte length; nothing is written if `out_cap` is too small.
+///
+/// # Safety
+/// `out_ptr` must be null or valid for `out_cap` bytes of writes.
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn avtools_metrics_json(out_ptr: *mut u8, out_cap: u32) -> u32 {
+    let sections = [
+        #[cfg(feature = "window")]
+        ("window", deno_window::metrics_section()),
+        #[cfg(feature = "midi")]
+        ("midi", midi_bridge::metrics_section()),
+        #[cfg(feature = "fastsleep")]
+        ("fastsleep", fastsleep::metrics_section()),
+    ];
+    buffer::write(metrics::document(&sections).as_bytes(), out_ptr, out_cap)
+}
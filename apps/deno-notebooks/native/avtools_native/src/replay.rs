@@ -0,0 +1,614 @@
+//! Capture of MIDI and window input into one file, and replay of it with the
+//! original relative timing, so a bug that depends on how the two streams
+//! interleave can be reproduced.
+//!
+//! `avtools_capture_start` records every message an input receives (before
+//! any filtering, stamped on arrival) and the key, mouse move and mouse
+//! button events of a window (stamped when queued), both on the shared clock,
+//! as JSON lines:
+//!
+//! ```text
+//! {"format":"avtools_capture","version":1,"start_us":...}
+//! {"t_us":1520,"midi":[144,60,100]}
+//! {"t_us":1733,"window":{"type":"key","key":"a","down":true}}
+//! ```
+//!
+//! `avtools_replay` plays a capture back on a thread of its own: MIDI into an
+//! input (usually a mock input, see midi_open_mock_input), which processes it
+//! like a port's, and window records into a window's queue, where the next
+//! pump delivers them. Every injection is timed against the capture; how late
+//! each one was is reported as drift.
+
+use avtools_ffi::shutdown::{self, Stage};
+use avtools_ffi::worker::{spawn_worker, Worker};
+use avtools_ffi::{buffer, catch_panic, error, Error};
+use deno_window::{Injector, TapEvent, WindowState};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, Once};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const FORMAT: &str = "avtools_capture";
+const FORMAT_VERSION: u32 = 1;
+
+/// avtools_replay: start paused, e.g. to set targets before anything plays.
+const REPLAY_PAUSED: u32 = 0x1;
+const KNOWN_REPLAY_FLAGS: u32 = REPLAY_PAUSED;
+
+/// The replay thread waits on its condvar until this close to an entry's
+/// time, then yields until it is due.
+const SPIN_WINDOW: Duration = Duration::from_millis(1);
+
+static CAPTURE: Mutex<Option<Capture>> = Mutex::new(None);
+static REPLAYS: Mutex<Option<HashMap<u32, Replay>>> = Mutex::new(None);
+static NEXT_REPLAY: AtomicU32 = AtomicU32::new(1);
+static SHUTDOWN_HOOK: Once = Once::new();
+
+#[derive(Serialize, Deserialize)]
+struct Header {
+    format: String,
+    version: u32,
+    start_us: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    /// µs since the capture started.
+    t_us: u64,
+    #[serde(flatten)]
+    input: Input,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Input {
+    Midi(Vec<u8>),
+    Window(WindowInput),
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum WindowInput {
+    Key {
+        key: String,
+        #[serde(default)]
+        text: Option<String>,
+        down: bool,
+    },
+    MouseMove { x: f64, y: f64 },
+    MouseButton { button: u32, down: bool },
+}
+
+fn register_shutdown_hook() {
+    SHUTDOWN_HOOK.call_once(|| {
+        shutdown::register(Stage::Inputs, "replays", || {
+            let replays = REPLAYS.lock().unwrap_or_else(|e| e.into_inner()).take().unwrap_or_default();
+            let count = replays.len() as u32;
+            replays.into_values().for_each(Replay::stop);
+            count + u32::from(stop_capture().is_ok())
+        })
+    });
+}
+
+struct Capture {
+    window: Option<(usize, u64)>,
+    input: Option<(u32, u64)>,
+    /// Entries for the writer thread; None finishes the file.
+    tx: Sender<Option<Entry>>,
+    writer: Worker,
+}
+
+/// Stop the taps and wait for the file to be written out.
+fn stop_capture() -> Result<(), Error> {
+    let Some(capture) = CAPTURE.lock().unwrap_or_else(|e| e.into_inner()).take() else {
+        return Err(Error::new(error::NOT_FOUND, "no capture is running"));
+    };
+    if let Some((state, tap)) = capture.window {
+        unsafe { deno_window::remove_event_tap(state as *mut WindowState, tap) };
+    }
+    if let Some((input, tap)) = capture.input {
+        midi_bridge::remove_raw_tap(input, tap);
+    }
+    let _ = capture.tx.send(None);
+    capture.writer.join();
+    Ok(())
+}
+
+fn write_capture(path: &str, start_us: u64, rx: mpsc::Receiver<Option<Entry>>) -> Result<Worker, Error> {
+    let file = File::create(path).map_err(|err| Error::new(error::OS_ERROR, format!("cannot create {path}: {err}")))?;
+    let mut out = BufWriter::new(file);
+    let header = Header {
+        format: FORMAT.to_string(),
+        version: FORMAT_VERSION,
+        start_us,
+    };
+    let path = path.to_string();
+    spawn_worker("avtools-capture", 0, move || {
+        let lines = std::iter::once(serde_json::to_string(&header))
+            .chain(rx.iter().map_while(|entry| entry).map(|entry| serde_json::to_string(&entry)));
+        let mut failed = false;
+        for line in lines.flatten() {
+            if !failed && writeln!(out, "{line}").is_err() {
+                avtools_log::error!("capture {path}: write failed, dropping the rest");
+                failed = true;
+            }
+        }
+        let _ = out.flush();
+    })
+    .map_err(|err| Error::new(error::OS_ERROR, format!("cannot start capture thread: {err}")))
+}
+
+/// Record the input of `window_state` (null to skip) and `input_handle` (0
+/// to skip) into a new file at the UTF-8 path `path_ptr`/`path_len` until
+/// avtools_capture_stop. Returns 0, or -1 with BUSY if a capture is running,
+/// INVALID_HANDLE for a bad window or input and OS_ERROR if the file cannot
+/// be created. Call on the thread that pumps the window.
+///
+/// # Safety
+/// `path_ptr` must be null or valid for `path_len` bytes of reads.
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn avtools_capture_start(
+    window_state: *mut WindowState,
+    input_handle: u32,
+    path_ptr: *const u8,
+    path_len: u32,
+) -> i32 {
+    match error::record(start_capture(window_state, input_handle, path_arg(path_ptr, path_len))) {
+        Some(()) => 0,
+        None => -1,
+    }
+}
+
+unsafe fn start_capture(
+    window_state: *mut WindowState,
+    input_handle: u32,
+    path: Result<&str, Error>,
+) -> Result<(), Error> {
+    let path = path?;
+    if window_state.is_null() && input_handle == 0 {
+        return Err(Error::new(error::INVALID_ARGUMENT, "nothing to capture"));
+    }
+    register_shutdown_hook();
+    let mut capture = CAPTURE.lock().unwrap_or_else(|e| e.into_inner());
+    if capture.is_some() {
+        return Err(Error::new(error::BUSY, "a capture is already running"));
+    }
+    let start_us = avtools_clock::now_us();
+    let (tx, rx) = mpsc::channel();
+    let writer = write_capture(path, start_us, rx)?;
+    let finish = |err: Error| {
+        let _ = tx.send(None);
+        err
+    };
+
+    let mut window = None;
+    if !window_state.is_null() {
+        let window_tx = tx.clone();
+        let tap = deno_window::add_event_tap(
+            window_state,
+            Box::new(move |event| {
+                let input = match *event {
+                    TapEvent::Key { key, text, down } => WindowInput::Key {
+                        key: key.to_string(),
+                        text: text.map(str::to_string),
+                        down,
+                    },
+                    TapEvent::MouseMove { x, y, .. } => WindowInput::MouseMove { x, y },
+                    TapEvent::MouseButton { button, down } => WindowInput::MouseButton { button, down },
+                };
+                let t_us = avtools_clock::now_us().saturating_sub(start_us);
+                let _ = window_tx.send(Some(Entry {
+                    t_us,
+                    input: Input::Window(input),
+                }));
+            }),
+        )
+        .map_err(finish)?;
+        window = Some((window_state as usize, tap));
+    }
+    let mut input = None;
+    if input_handle != 0 {
+        let midi_tx = Mutex::new(tx.clone());
+        let tap = midi_bridge::add_raw_tap(
+            input_handle,
+            Box::new(move |arrival_us, msg| {
+                let entry = Entry {
+                    t_us: arrival_us.saturating_sub(start_us),
+                    input: Input::Midi(msg.to_vec()),
+                };
+                let _ = midi_tx.lock().unwrap_or_else(|e| e.into_inner()).send(Some(entry));
+            }),
+        );
+        match tap {
+            Ok(tap) => input = Some((input_handle, tap)),
+            Err(err) => {
+                if let Some((state, tap)) = window {
+                    deno_window::remove_event_tap(state as *mut WindowState, tap);
+                }
+                return Err(finish(err));
+            }
+        }
+    }
+    *capture = Some(Capture {
+        window,
+        input,
+        tx,
+        writer,
+    });
+    Ok(())
+}
+
+/// Stop the capture and finish its file. Returns 0, or -1 with NOT_FOUND if
+/// none is running. Call on the thread that pumps the captured window.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn avtools_capture_stop() -> i32 {
+    match error::record(stop_capture()) {
+        Some(()) => 0,
+        None => -1,
+    }
+}
+
+unsafe fn path_arg<'a>(ptr: *const u8, len: u32) -> Result<&'a str, Error> {
+    if ptr.is_null() || len == 0 {
+        return Err(Error::new(error::INVALID_ARGUMENT, "path is empty"));
+    }
+    std::str::from_utf8(std::slice::from_raw_parts(ptr, len as usize))
+        .map_err(|_| Error::new(error::INVALID_ARGUMENT, "path is not UTF-8"))
+}
+
+/// Read a capture, entries in time order.
+fn load(path: &str) -> Result<Vec<Entry>, Error> {
+    let file = File::open(path).map_err(|err| Error::new(error::OS_ERROR, format!("cannot open {path}: {err}")))?;
+    let invalid = |line: usize, reason: String| Error::new(error::INVALID_ARGUMENT, format!("{path}:{line}: {reason}"));
+    let mut lines = BufReader::new(file).lines();
+    let header = lines
+        .next()
+        .transpose()
+        .map_err(|err| Error::new(error::OS_ERROR, format!("cannot read {path}: {err}")))?
+        .ok_or_else(|| invalid(1, "empty file".to_string()))?;
+    let header: Header = serde_json::from_str(&header).map_err(|err| invalid(1, err.to_string()))?;
+    if header.format != FORMAT || header.version > FORMAT_VERSION {
+        return Err(invalid(1, format!("not a version {FORMAT_VERSION} capture")));
+    }
+    let mut entries = Vec::new();
+    for (index, line) in lines.enumerate() {
+        let line = line.map_err(|err| Error::new(error::OS_ERROR, format!("cannot read {path}: {err}")))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        entries.push(serde_json::from_str::<Entry>(&line).map_err(|err| invalid(index + 2, err.to_string()))?);
+    }
+    // The two streams are written from different threads, so may interleave
+    // slightly out of order.
+    entries.sort_by_key(|entry| entry.t_us);
+    Ok(entries)
+}
+
+#[derive(Clone, Default)]
+struct Targets {
+    window: Option<Injector>,
+    input: u32,
+}
+
+#[derive(Default, Serialize)]
+struct Drift {
+    /// Latest injection minus its intended time, in µs.
+    last_us: i64,
+    mean_us: f64,
+    max_us: i64,
+    #[serde(skip)]
+    total_us: i64,
+}
+
+struct Playback {
+    paused: bool,
+    stopped: bool,
+    /// Index of the next entry to inject.
+    next: usize,
+    /// Capture time at `anchor`; while paused, the current capture time.
+    position_us: u64,
+    anchor: Instant,
+    targets: Targets,
+    injected: u64,
+    /// Entries that had no target set when due.
+    skipped: u64,
+    drift: Drift,
+}
+
+struct Control {
+    playback: Mutex<Playback>,
+    changed: Condvar,
+    entries: Vec<Entry>,
+    speed: f64,
+}
+
+impl Control {
+    fn lock(&self) -> MutexGuard<'_, Playback> {
+        self.playback.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Current capture time.
+    fn position_us(&self, playback: &Playback) -> u64 {
+        if playback.paused || playback.next >= self.entries.len() {
+            return playback.position_us;
+        }
+        let elapsed = playback.anchor.elapsed().as_secs_f64() * 1e6 * self.speed;
+        playback.position_us + elapsed as u64
+    }
+
+    /// When entry `t_us` is due on the wall clock.
+    fn due(&self, playback: &Playback, t_us: u64) -> Instant {
+        let offset = t_us.saturating_sub(playback.position_us) as f64 / self.speed;
+        playback.anchor + Duration::from_micros(offset as u64)
+    }
+
+    fn run(&self) {
+        let mut playback = self.lock();
+        let mut cursor = (0.0, 0.0);
+        loop {
+            if playback.stopped {
+                return;
+            }
+            let Some(entry) = self.entries.get(playback.next).filter(|_| !playback.paused) else {
+                playback = self.changed.wait(playback).unwrap_or_else(|e| e.into_inner());
+                continue;
+            };
+            let due = self.due(&playback, entry.t_us);
+            let now = Instant::now();
+            if now + SPIN_WINDOW < due {
+                let wait = due - now - SPIN_WINDOW;
+                playback = self.changed.wait_timeout(playback, wait).unwrap_or_else(|e| e.into_inner()).0;
+                continue;
+            }
+            if now < due {
+                drop(playback);
+                thread::yield_now();
+                playback = self.lock();
+                continue;
+            }
+            let late = now.duration_since(due).as_micros() as i64;
+            playback.next += 1;
+            if playback.next == self.entries.len() {
+                playback.position_us = entry.t_us;
+            }
+            let targets = playback.targets.clone();
+            drop(playback);
+            let delivered = deliver(entry, &targets, &mut cursor);
+            playback = self.lock();
+            if delivered {
+                playback.injected += 1;
+                let injected = playback.injected;
+                let drift = &mut playback.drift;
+                drift.last_us = late;
+                drift.max_us = drift.max_us.max(late);
+                drift.total_us += late;
+                drift.mean_us = drift.total_us as f64 / injected as f64;
+            } else {
+                playback.skipped += 1;
+            }
+        }
+    }
+}
+
+/// Inject one entry; false if it has no target.
+fn deliver(entry: &Entry, targets: &Targets, cursor: &mut (f64, f64)) -> bool {
+    match &entry.input {
+        Input::Midi(msg) => targets.input != 0 && midi_bridge::inject(targets.input, msg).is_ok(),
+        Input::Window(input) => {
+            let Some(window) = targets.window.as_ref() else {
+                return false;
+            };
+            match *input {
+                WindowInput::Key { ref key, ref text, down } => window.key(key, text.as_deref(), down),
+                WindowInput::MouseMove { x, y } => {
+                    *cursor = (x, y);
+                    window.mouse_move(x, y);
+                }
+                WindowInput::MouseButton { button, down } => window.mouse_button(button, down, cursor.0, cursor.1),
+            }
+            true
+        }
+    }
+}
+
+struct Replay {
+    control: Arc<Control>,
+    worker: Worker,
+}
+
+impl Replay {
+    fn stop(self) {
+        self.control.lock().stopped = true;
+        self.control.changed.notify_all();
+        self.worker.join();
+    }
+}
+
+fn with_replay<T>(replay: u32, f: impl FnOnce(&Control) -> T) -> Result<T, Error> {
+    let replays = REPLAYS.lock().unwrap_or_else(|e| e.into_inner());
+    match replays.as_ref().and_then(|replays| replays.get(&replay)) {
+        Some(r) => Ok(f(&r.control)),
+        None => Err(Error::new(error::INVALID_HANDLE, format!("unknown replay {replay}"))),
+    }
+}
+
+fn status_code(result: Result<(), Error>) -> i32 {
+    match error::record(result) {
+        Some(()) => 0,
+        None => -1,
+    }
+}
+
+/// Load the capture at the UTF-8 path `path_ptr`/`path_len` and play it at
+/// `speed` times the original pace. Nothing is injected until targets are
+/// set with avtools_replay_set_targets; entries due before then are skipped,
+/// so pass REPLAY_PAUSED (0x1) to start paused. Returns the replay handle, or
+/// 0 with INVALID_ARGUMENT for a bad speed, flag or file and OS_ERROR if the
+/// file cannot be read.
+///
+/// # Safety
+/// `path_ptr` must be null or valid for `path_len` bytes of reads.
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn avtools_replay(path_ptr: *const u8, path_len: u32, speed: f64, flags: u32) -> u32 {
+    error::record(start_replay(path_arg(path_ptr, path_len), speed, flags)).unwrap_or(0)
+}
+
+fn start_replay(path: Result<&str, Error>, speed: f64, flags: u32) -> Result<u32, Error> {
+    if !(speed.is_finite() && speed > 0.0) {
+        return Err(Error::new(error::INVALID_ARGUMENT, format!("speed {speed} is not positive")));
+    }
+    if flags & !KNOWN_REPLAY_FLAGS != 0 {
+        return Err(Error::new(
+            error::INVALID_ARGUMENT,
+            format!("unknown flag bits {:#x}", flags & !KNOWN_REPLAY_FLAGS),
+        ));
+    }
+    let entries = load(path?)?;
+    register_shutdown_hook();
+    let control = Arc::new(Control {
+        playback: Mutex::new(Playback {
+            paused: flags & REPLAY_PAUSED != 0,
+            stopped: false,
+            next: 0,
+            position_us: 0,
+            anchor: Instant::now(),
+            targets: Targets::default(),
+            injected: 0,
+            skipped: 0,
+            drift: Drift::default(),
+        }),
+        changed: Condvar::new(),
+        entries,
+        speed,
+    });
+    let thread_control = control.clone();
+    let worker = spawn_worker("avtools-replay", 1, move || thread_control.run())
+        .map_err(|err| Error::new(error::OS_ERROR, format!("cannot start replay thread: {err}")))?;
+    let id = NEXT_REPLAY.fetch_add(1, Ordering::Relaxed);
+    REPLAYS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get_or_insert_with(HashMap::new)
+        .insert(id, Replay { control, worker });
+    Ok(id)
+}
+
+/// Where a replay injects: MIDI into `input_handle` (0 for none) and window
+/// records into `window_state` (null for none). Returns 0, or -1 with
+/// INVALID_HANDLE for a bad replay, window or input. Call on the thread that
+/// pumps the window.
+///
+/// # Safety
+/// `window_state` may be stale or bogus; it is checked.
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn avtools_replay_set_targets(
+    replay: u32,
+    window_state: *mut WindowState,
+    input_handle: u32,
+) -> i32 {
+    let targets = (|| {
+        let window = match window_state.is_null() {
+            true => None,
+            false => Some(deno_window::injector(window_state)?),
+        };
+        if input_handle != 0 && !midi_bridge::is_input(input_handle) {
+            return Err(Error::new(error::INVALID_HANDLE, format!("unknown input handle {input_handle}")));
+        }
+        Ok(Targets {
+            window,
+            input: input_handle,
+        })
+    })();
+    status_code(targets.and_then(|targets| with_replay(replay, |control| control.lock().targets = targets)))
+}
+
+/// Pause (`paused` nonzero) or resume. Returns 0, or -1 with INVALID_HANDLE.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn avtools_replay_pause(replay: u32, paused: u8) -> i32 {
+    status_code(with_replay(replay, |control| {
+        let mut playback = control.lock();
+        let paused = paused != 0;
+        if paused && !playback.paused {
+            playback.position_us = control.position_us(&playback);
+        }
+        playback.paused = paused;
+        playback.anchor = Instant::now();
+        control.changed.notify_all();
+    }))
+}
+
+/// Continue from `position_us` into the capture; entries before it are
+/// skipped, not replayed. Seeking after the end has been reached plays again.
+/// Returns 0, or -1 with INVALID_HANDLE.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn avtools_replay_seek(replay: u32, position_us: u64) -> i32 {
+    status_code(with_replay(replay, |control| {
+        let mut playback = control.lock();
+        playback.next = control.entries.partition_point(|entry| entry.t_us < position_us);
+        playback.position_us = position_us;
+        playback.anchor = Instant::now();
+        control.changed.notify_all();
+    }))
+}
+
+/// Progress of a replay as JSON: `{"position_us","duration_us","paused",
+/// "finished","injected","skipped","drift":{"last_us","mean_us","max_us"}}`,
+/// where drift is how late injections were against the capture's timing.
+/// Returns the byte length (0 for an unknown replay); nothing is written if
+/// `out_cap` is too small.
+///
+/// # Safety
+/// `out_ptr` must be null or valid for `out_cap` bytes of writes.
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn avtools_replay_status(replay: u32, out_ptr: *mut u8, out_cap: u32) -> u32 {
+    let json = with_replay(replay, |control| {
+        let playback = control.lock();
+        serde_json::json!({
+            "position_us": control.position_us(&playback),
+            "duration_us": control.entries.last().map_or(0, |entry| entry.t_us),
+            "paused": playback.paused,
+            "finished": playback.next >= control.entries.len(),
+            "injected": playback.injected,
+            "skipped": playback.skipped,
+            "drift": playback.drift,
+        })
+        .to_string()
+    });
+    match error::record(json) {
+        Some(json) => buffer::write(json.as_bytes(), out_ptr, out_cap),
+        None => 0,
+    }
+}
+
+/// Stop a replay and join its thread. Returns 0, or -1 with INVALID_HANDLE.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn avtools_replay_destroy(replay: u32) -> i32 {
+    let removed = REPLAYS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .as_mut()
+        .and_then(|replays| replays.remove(&replay));
+    match removed {
+        Some(removed) => {
+            removed.stop();
+            0
+        }
+        None => {
+            error::fail(error::INVALID_HANDLE, format!("unknown replay {replay}"));
+            -1
+        }
+    }
+}
@@ -0,0 +1,219 @@
+// Generated by native/avtools_bindgen from the exports of avtools_native; do not edit.
+// Hand-written tables may narrow "pointer" to "buffer"/"function" and add `nonblocking`.
+
+export const AVTOOLS_NATIVE_SYMBOLS = {
+  av_alloc: { parameters: ["u32"], result: "pointer" },
+  av_free: { parameters: ["pointer", "u32"], result: "void" },
+  avtools_capture_start: { parameters: ["pointer", "u32", "pointer", "u32"], result: "i32" },
+  avtools_capture_stop: { parameters: [], result: "i32" },
+  avtools_clock_now_us: { parameters: [], result: "u64" },
+  avtools_clock_offset: { parameters: ["u32", "u32"], result: "i64" },
+  avtools_map_destroy: { parameters: ["u32"], result: "i32" },
+  avtools_map_midi_to_window: { parameters: ["u32", "pointer", "pointer", "u32"], result: "u32" },
+  avtools_map_window_to_midi: { parameters: ["pointer", "u32", "pointer", "u32"], result: "u32" },
+  avtools_metrics_json: { parameters: ["pointer", "u32"], result: "u32" },
+  avtools_replay: { parameters: ["pointer", "u32", "f64", "u32"], result: "u32" },
+  avtools_replay_destroy: { parameters: ["u32"], result: "i32" },
+  avtools_replay_pause: { parameters: ["u32", "u8"], result: "i32" },
+  avtools_replay_seek: { parameters: ["u32", "u64"], result: "i32" },
+  avtools_replay_set_targets: { parameters: ["u32", "pointer", "u32"], result: "i32" },
+  avtools_replay_status: { parameters: ["u32", "pointer", "u32"], result: "u32" },
+  avtools_shutdown_all: { parameters: ["pointer", "u32"], result: "u32" },
+  avtools_tracing_start: { parameters: ["pointer", "u32", "u32"], result: "i32" },
+  avtools_tracing_stop: { parameters: [], result: "i32" },
+  begin_window_drag_move: { parameters: ["pointer"], result: "i32" },
+  begin_window_drag_resize: { parameters: ["pointer", "u32"], result: "i32" },
+  capture_window_pixels: { parameters: ["pointer", "pointer", "u32", "pointer", "pointer"], result: "i32" },
+  clear_taskbar_thumbnail_clip: { parameters: ["pointer"], result: "i32" },
+  create_additional_window: { parameters: ["pointer", "u32", "u32", "pointer", "u32"], result: "u32" },
+  create_child_window: { parameters: ["pointer", "u32", "u32", "u32", "u32"], result: "pointer" },
+  create_window: { parameters: ["u32", "u32", "pointer", "u32"], result: "pointer" },
+  create_window_ex: { parameters: ["pointer", "u32"], result: "pointer" },
+  destroy_window: { parameters: ["pointer"], result: "void" },
+  destroy_window_by_token: { parameters: ["pointer", "u32"], result: "i32" },
+  fast_clock_info: { parameters: ["pointer", "u32"], result: "u32" },
+  fast_get_current_thread_priority: { parameters: [], result: "i32" },
+  fast_loop_start: { parameters: ["u32", "function", "u32"], result: "u32" },
+  fast_loop_stop: { parameters: ["u32"], result: "i32" },
+  fast_now_us: { parameters: [], result: "u64" },
+  fast_schedule_batch: { parameters: ["pointer", "u32", "function"], result: "u32" },
+  fast_schedule_batch_cancel: { parameters: ["u32"], result: "i32" },
+  fast_set_current_thread_name: { parameters: ["pointer", "u32"], result: "i32" },
+  fast_set_current_thread_priority: { parameters: ["u32"], result: "i32" },
+  fast_set_miss_handler: { parameters: ["u32", "function"], result: "void" },
+  fast_set_wake_flag: { parameters: ["pointer", "u32"], result: "i32" },
+  fast_sleep_abi_version: { parameters: [], result: "u32" },
+  fast_sleep_calibrate: { parameters: ["u32", "u32"], result: "u32" },
+  fast_sleep_calibration_report: { parameters: ["pointer", "u32"], result: "u32" },
+  fast_sleep_cancel: { parameters: ["u32"], result: "i32" },
+  fast_sleep_cancellable_us: { parameters: ["u32", "u32"], result: "i32" },
+  fast_sleep_capabilities: { parameters: ["pointer", "u32"], result: "u32" },
+  fast_sleep_clear_timer_resolution: { parameters: ["u32"], result: "i32" },
+  fast_sleep_clock_ns: { parameters: [], result: "u64" },
+  fast_sleep_configure: { parameters: ["u32", "u32"], result: "i32" },
+  fast_sleep_enable_mock: { parameters: ["f64"], result: "i32" },
+  fast_sleep_get_config: { parameters: ["pointer", "pointer", "pointer"], result: "void" },
+  fast_sleep_get_last_panic: { parameters: ["pointer", "u32"], result: "u32" },
+  fast_sleep_get_power_mode: { parameters: [], result: "u32" },
+  fast_sleep_get_virtual_time_ns: { parameters: [], result: "u64" },
+  fast_sleep_init: { parameters: ["u32", "u32"], result: "i32" },
+  fast_sleep_last_error_code: { parameters: [], result: "i32" },
+  fast_sleep_last_error_message: { parameters: ["pointer", "u32"], result: "u32" },
+  fast_sleep_metrics_json: { parameters: ["pointer", "u32"], result: "u32" },
+  fast_sleep_ns: { parameters: ["u64"], result: "void" },
+  fast_sleep_ns_measured: { parameters: ["u64"], result: "u64" },
+  fast_sleep_ns_with: { parameters: ["u64", "u32", "u32"], result: "i32" },
+  fast_sleep_reset_windows_timer_resolution: { parameters: [], result: "i32" },
+  fast_sleep_selftest: { parameters: ["pointer", "u32"], result: "u32" },
+  fast_sleep_set_log_callback: { parameters: ["function"], result: "void" },
+  fast_sleep_set_log_level: { parameters: ["u32"], result: "i32" },
+  fast_sleep_set_power_mode: { parameters: ["u32"], result: "i32" },
+  fast_sleep_set_timer_resolution: { parameters: ["u32"], result: "i32" },
+  fast_sleep_set_windows_timer_resolution: { parameters: ["u32"], result: "i32" },
+  fast_sleep_stats: { parameters: ["pointer", "u32"], result: "u32" },
+  fast_sleep_stats_enable: { parameters: ["u8"], result: "void" },
+  fast_sleep_stats_reset: { parameters: [], result: "void" },
+  fast_sleep_token_create: { parameters: [], result: "u32" },
+  fast_sleep_token_destroy: { parameters: ["u32"], result: "i32" },
+  fast_sleep_token_reset: { parameters: ["u32"], result: "i32" },
+  fast_sleep_until_any: { parameters: ["pointer", "u32", "u32"], result: "i32" },
+  fast_sleep_until_ns: { parameters: ["u64"], result: "i64" },
+  fast_sleep_until_signal: { parameters: ["pointer", "u64"], result: "u8" },
+  fast_sleep_until_us: { parameters: ["u64"], result: "i64" },
+  fast_sleep_until_us_with: { parameters: ["u64", "u32", "u32"], result: "i32" },
+  fast_sleep_us: { parameters: ["u32"], result: "void" },
+  fast_sleep_us_measured: { parameters: ["u32"], result: "u64" },
+  fast_sleep_us_with: { parameters: ["u32", "u32", "u32"], result: "i32" },
+  fast_sleeper_create: { parameters: ["u32", "u32"], result: "u32" },
+  fast_sleeper_destroy: { parameters: ["u32"], result: "i32" },
+  fast_sleeper_sleep_until_us: { parameters: ["u32", "u64"], result: "i64" },
+  fast_sleeper_sleep_us: { parameters: ["u32", "u32"], result: "i32" },
+  fast_ticker_align: { parameters: ["u32", "u64"], result: "i32" },
+  fast_ticker_create: { parameters: ["u32", "u32"], result: "u32" },
+  fast_ticker_create_ex: { parameters: ["pointer", "u32"], result: "u32" },
+  fast_ticker_destroy: { parameters: ["u32"], result: "i32" },
+  fast_ticker_info: { parameters: ["u32", "pointer", "u32"], result: "u32" },
+  fast_ticker_set_period: { parameters: ["u32", "u32"], result: "i32" },
+  fast_ticker_skipped: { parameters: ["u32"], result: "i64" },
+  fast_ticker_wait: { parameters: ["u32"], result: "i64" },
+  fast_timer_cancel: { parameters: ["u32"], result: "i32" },
+  fast_timer_schedule: { parameters: ["u32", "function"], result: "u32" },
+  fast_wait_flag_until_us: { parameters: ["pointer", "u32", "u64"], result: "i32" },
+  get_display_color_space: { parameters: ["pointer", "pointer", "u32"], result: "u32" },
+  get_display_refresh_rate: { parameters: ["pointer"], result: "f64" },
+  get_frame_count: { parameters: ["pointer"], result: "u64" },
+  get_frames_per_second: { parameters: ["pointer"], result: "f64" },
+  get_gpu_preference: { parameters: ["pointer"], result: "u32" },
+  get_raw_display_handle: { parameters: ["pointer"], result: "usize" },
+  get_raw_window_handle: { parameters: ["pointer"], result: "usize" },
+  get_raw_window_handle_by_token: { parameters: ["pointer", "u32"], result: "usize" },
+  get_vsync_interval_us: { parameters: ["pointer"], result: "u64" },
+  get_window_position: { parameters: ["pointer", "pointer", "pointer"], result: "i32" },
+  get_window_size: { parameters: ["pointer", "pointer", "pointer"], result: "void" },
+  get_window_size_by_token: { parameters: ["pointer", "u32", "pointer", "pointer"], result: "i32" },
+  get_window_system: { parameters: ["pointer"], result: "u32" },
+  list_monitors: { parameters: ["pointer", "pointer", "u32"], result: "u32" },
+  midi_abi_version: { parameters: [], result: "u32" },
+  midi_add_cc_remap: { parameters: ["u32", "u8", "u8", "u8", "u8"], result: "i32" },
+  midi_capabilities: { parameters: ["pointer", "u32"], result: "u32" },
+  midi_clear_cc_remaps: { parameters: ["u32"], result: "i32" },
+  midi_clear_note_expressions: { parameters: ["u32"], result: "i32" },
+  midi_clear_note_quantise_scale: { parameters: ["u32", "u8"], result: "i32" },
+  midi_clear_quantise_grid: { parameters: ["u32"], result: "i32" },
+  midi_clock_get_bpm: { parameters: ["u32"], result: "f32" },
+  midi_clock_set_bpm: { parameters: ["u32", "f32"], result: "i32" },
+  midi_clock_set_cc_sync: { parameters: ["u32", "u32", "u8", "u8", "f32", "f32"], result: "i32" },
+  midi_clock_start: { parameters: ["u32", "f32"], result: "u32" },
+  midi_clock_stop: { parameters: ["u32"], result: "i32" },
+  midi_close_input: { parameters: ["u32"], result: "void" },
+  midi_close_output: { parameters: ["u32"], result: "void" },
+  midi_create_pipeline: { parameters: [], result: "u32" },
+  midi_decode_frame_json: { parameters: ["pointer", "u32", "pointer", "u32"], result: "u32" },
+  midi_delete_cc_record: { parameters: ["u32"], result: "i32" },
+  midi_get_bandwidth_bps: { parameters: ["u32"], result: "u32" },
+  midi_get_cc14: { parameters: ["u32", "u8", "u8"], result: "f32" },
+  midi_get_cc_display_format: { parameters: ["u8", "pointer", "u32"], result: "u32" },
+  midi_get_epoch_us: { parameters: ["u32"], result: "u64" },
+  midi_get_last_panic: { parameters: ["pointer", "u32"], result: "u32" },
+  midi_get_pool_member_handles: { parameters: ["u32", "pointer", "u32"], result: "u32" },
+  midi_get_program_name: { parameters: ["u32", "u8", "pointer", "u32"], result: "u32" },
+  midi_input_stats: { parameters: ["u32", "pointer", "u32"], result: "u32" },
+  midi_is_handle_connected: { parameters: ["u32"], result: "u8" },
+  midi_last_error_code: { parameters: [], result: "i32" },
+  midi_last_error_message: { parameters: ["pointer", "u32"], result: "u32" },
+  midi_list_inputs: { parameters: ["pointer", "u32"], result: "u32" },
+  midi_list_outputs: { parameters: ["pointer", "u32"], result: "u32" },
+  midi_metrics_json: { parameters: ["pointer", "u32"], result: "u32" },
+  midi_mock_input_send: { parameters: ["u32", "pointer", "u32"], result: "i32" },
+  midi_mpe_negotiate: { parameters: ["u32", "u32", "u32"], result: "i32" },
+  midi_open_input: { parameters: ["pointer", "u32", "u32", "u32", "function"], result: "u32" },
+  midi_open_input_ex: { parameters: ["pointer", "u32", "pointer", "u32", "function"], result: "u32" },
+  midi_open_input_with_retry: { parameters: ["pointer", "u32", "u32", "u32", "function", "u32", "u32"], result: "u32" },
+  midi_open_mock_input: { parameters: ["pointer", "u32", "function"], result: "u32" },
+  midi_open_output: { parameters: ["pointer", "u32"], result: "u32" },
+  midi_open_output_pool: { parameters: ["pointer", "u32"], result: "u32" },
+  midi_pipeline_add_cc_remap: { parameters: ["u32", "u8", "u8", "u8", "u8"], result: "i32" },
+  midi_pipeline_add_channel_remap: { parameters: ["u32", "u8", "u8"], result: "i32" },
+  midi_pipeline_add_transpose: { parameters: ["u32", "u8", "i8"], result: "i32" },
+  midi_pipeline_add_velocity_scale: { parameters: ["u32", "u8", "u16"], result: "i32" },
+  midi_pipeline_attach_input: { parameters: ["u32", "u32"], result: "i32" },
+  midi_pipeline_attach_output: { parameters: ["u32", "u32"], result: "i32" },
+  midi_pipeline_clear: { parameters: ["u32"], result: "i32" },
+  midi_pipeline_destroy: { parameters: ["u32"], result: "i32" },
+  midi_playback_cc: { parameters: ["u32", "u32", "u8", "u8", "u32", "u32"], result: "u32" },
+  midi_register_note_expression: { parameters: ["u32", "u8", "u8", "u32", "pointer", "u32", "u8"], result: "i32" },
+  midi_release_cc_lock: { parameters: ["u32", "u8", "u8"], result: "i32" },
+  midi_remove_cc_remap: { parameters: ["u32", "u8", "u8"], result: "i32" },
+  midi_send: { parameters: ["u32", "pointer", "u32"], result: "i32" },
+  midi_send_mmc: { parameters: ["u32", "u8", "u8"], result: "i32" },
+  midi_send_osc_tunnelled: { parameters: ["u32", "pointer", "u32", "u8"], result: "i32" },
+  midi_set_beat_dispatch: { parameters: ["u32", "u32"], result: "i32" },
+  midi_set_cc14: { parameters: ["u32", "u8", "u8", "f32"], result: "i32" },
+  midi_set_cc_display_format: { parameters: ["u8", "pointer", "u32", "pointer", "u32"], result: "void" },
+  midi_set_cc_lock_mode: { parameters: ["u32", "u8", "u8", "u8", "u8"], result: "i32" },
+  midi_set_dispatch_clocks_per_tick: { parameters: ["u32", "u32"], result: "i32" },
+  midi_set_latency_compensation: { parameters: ["u32", "i64"], result: "i32" },
+  midi_set_log_callback: { parameters: ["function"], result: "void" },
+  midi_set_log_level: { parameters: ["u32"], result: "i32" },
+  midi_set_msc_device_id: { parameters: ["u32", "u8"], result: "i32" },
+  midi_set_note_quantise_scale: { parameters: ["u32", "u8", "u8", "u16"], result: "i32" },
+  midi_set_note_timeout_ms: { parameters: ["u32", "u32"], result: "i32" },
+  midi_set_program_name: { parameters: ["u8", "pointer", "u32"], result: "void" },
+  midi_set_quantise_grid: { parameters: ["u32", "u32", "u32", "u8"], result: "i32" },
+  midi_start_cc_record: { parameters: ["u32", "u8", "u8"], result: "u32" },
+  midi_stop_cc_playback: { parameters: ["u32"], result: "i32" },
+  midi_stop_cc_record: { parameters: ["u32"], result: "i32" },
+  midi_sysex_request_response: { parameters: ["u32", "u32", "pointer", "u32", "pointer", "u32", "u32", "pointer", "u32"], result: "i32" },
+  poll_events: { parameters: ["pointer", "pointer", "u32"], result: "u32" },
+  poll_events_filtered: { parameters: ["pointer", "u64", "pointer", "u32"], result: "u32" },
+  poll_events_msgpack: { parameters: ["pointer", "pointer", "u32"], result: "u32" },
+  resize_window: { parameters: ["pointer", "u32", "u32"], result: "void" },
+  resize_window_by_token: { parameters: ["pointer", "u32", "u32", "u32"], result: "i32" },
+  send_window_user_event: { parameters: ["pointer", "pointer", "u32"], result: "i32" },
+  set_accessibility_label: { parameters: ["pointer", "pointer", "u32"], result: "i32" },
+  set_accessibility_role: { parameters: ["pointer", "u32"], result: "i32" },
+  set_auto_capture_on_drag: { parameters: ["pointer", "u8"], result: "i32" },
+  set_before_destroy_callback: { parameters: ["pointer", "function"], result: "i32" },
+  set_content_protection: { parameters: ["pointer", "u8"], result: "i32" },
+  set_event_format: { parameters: ["pointer", "u8"], result: "i32" },
+  set_gpu_preference: { parameters: ["pointer", "u32"], result: "i32" },
+  set_hdr_hint: { parameters: ["pointer", "u8"], result: "i32" },
+  set_hit_test_callback: { parameters: ["pointer", "function"], result: "i32" },
+  set_parent_window_handle: { parameters: ["pointer", "usize", "usize", "u32"], result: "i32" },
+  set_pointer_capture: { parameters: ["pointer", "u8"], result: "i32" },
+  set_taskbar_thumbnail_clip: { parameters: ["pointer", "u32", "u32", "u32", "u32"], result: "i32" },
+  set_window_corner_preference: { parameters: ["pointer", "u32"], result: "i32" },
+  set_window_position: { parameters: ["pointer", "i32", "i32"], result: "i32" },
+  subscribe_events: { parameters: ["pointer", "u64", "function"], result: "i32" },
+  unsubscribe_events: { parameters: ["pointer"], result: "i32" },
+  wait_for_window_ready: { parameters: ["pointer", "u32"], result: "i32" },
+  window_abi_version: { parameters: [], result: "u32" },
+  window_capabilities: { parameters: ["pointer", "u32"], result: "u32" },
+  window_decode_frame_json: { parameters: ["pointer", "u32", "pointer", "u32"], result: "u32" },
+  window_get_last_panic: { parameters: ["pointer", "u32"], result: "u32" },
+  window_last_error_code: { parameters: [], result: "i32" },
+  window_last_error_message: { parameters: ["pointer", "u32"], result: "u32" },
+  window_metrics_json: { parameters: ["pointer", "u32"], result: "u32" },
+  window_set_log_callback: { parameters: ["function"], result: "void" },
+  window_set_log_level: { parameters: ["u32"], result: "i32" },
+} as const satisfies Deno.ForeignLibraryInterface;
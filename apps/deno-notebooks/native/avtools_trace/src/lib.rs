@@ -0,0 +1,133 @@
+//! Timing spans around the libraries' hot paths (window pump, MIDI coalescer
+//! and dispatch, timer and playback sends), written to a file that loads into
+//! a flamegraph viewer.
+//!
+//! The spans are `tracing` spans, compiled in only with the `enabled` feature
+//! (each library's `tracing` feature turns it on). Without it `span!` expands
+//! to a zero-sized value and costs nothing. With it, a stopped trace costs the
+//! one level comparison `tracing` does at every span.
+//!
+//! `avtools_tracing_start` writes the Chrome trace format (load it in
+//! chrome://tracing or Perfetto), or JSON lines when the path ends in
+//! `.jsonl`. Timestamps are µs on the shared clock, as in every other
+//! timestamp the libraries report. Like logging, each cdylib has its own
+//! trace; the combined avtools_native has one for all components.
+
+use avtools_ffi::{catch_panic, error, Error};
+
+#[cfg(feature = "enabled")]
+mod recorder;
+
+#[cfg(feature = "enabled")]
+#[doc(hidden)]
+pub use tracing;
+
+/// Whether this build can trace at all.
+pub const AVAILABLE: bool = cfg!(feature = "enabled");
+
+/// Most verbose level recorded, numbered like avtools_log's levels.
+pub const LEVEL_ERROR: u32 = 1;
+pub const LEVEL_WARN: u32 = 2;
+pub const LEVEL_INFO: u32 = 3;
+pub const LEVEL_DEBUG: u32 = 4;
+pub const LEVEL_TRACE: u32 = 5;
+
+/// Stands in for a span when tracing is compiled out.
+#[doc(hidden)]
+pub struct NoSpan;
+
+/// Enter a span until the returned guard is dropped:
+/// `let _span = avtools_trace::span!(DEBUG, "pump_app_events");`, optionally
+/// with `tracing` fields after the name. Field expressions are not evaluated
+/// when tracing is compiled out.
+#[cfg(feature = "enabled")]
+#[macro_export]
+macro_rules! span {
+    ($level:ident, $name:expr $(, $($fields:tt)+)?) => {
+        $crate::tracing::span!($crate::tracing::Level::$level, $name $(, $($fields)+)?).entered()
+    };
+}
+
+#[cfg(not(feature = "enabled"))]
+#[macro_export]
+macro_rules! span {
+    ($level:ident, $name:expr $(, $($fields:tt)+)?) => {
+        $crate::NoSpan
+    };
+}
+
+/// Start writing spans at `level` (LEVEL_*) or more important to a new file
+/// at `path`, truncating it. Fails if a trace is already running.
+pub fn start(path: &str, level: u32) -> Result<(), Error> {
+    if !(LEVEL_ERROR..=LEVEL_TRACE).contains(&level) {
+        return Err(Error::new(
+            error::INVALID_ARGUMENT,
+            format!("unknown trace level {level}"),
+        ));
+    }
+    if path.is_empty() {
+        return Err(Error::new(error::INVALID_ARGUMENT, "trace path is empty"));
+    }
+    #[cfg(feature = "enabled")]
+    return recorder::start(path, level);
+    #[cfg(not(feature = "enabled"))]
+    Err(Error::new(
+        error::UNSUPPORTED,
+        "built without the tracing feature",
+    ))
+}
+
+/// Finish the file of the running trace. False if none was running.
+pub fn stop() -> Result<bool, Error> {
+    #[cfg(feature = "enabled")]
+    return recorder::stop();
+    #[cfg(not(feature = "enabled"))]
+    Ok(false)
+}
+
+/// Start tracing to the UTF-8 path `path_ptr`/`path_len` (see `start`).
+/// Returns 0, or -1 with INVALID_ARGUMENT for a bad path or level, BUSY if a
+/// trace is already running, OS_ERROR if the file cannot be created and
+/// UNSUPPORTED in builds without tracing.
+///
+/// # Safety
+/// `path_ptr` must be null or valid for `path_len` bytes of reads.
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn avtools_tracing_start(
+    path_ptr: *const u8,
+    path_len: u32,
+    level: u32,
+) -> i32 {
+    let path = if path_ptr.is_null() {
+        Ok("")
+    } else {
+        std::str::from_utf8(std::slice::from_raw_parts(path_ptr, path_len as usize))
+    };
+    let result = match path {
+        Ok(path) => start(path, level),
+        Err(_) => Err(Error::new(
+            error::INVALID_ARGUMENT,
+            "trace path is not UTF-8",
+        )),
+    };
+    match error::record(result) {
+        Some(()) => 0,
+        None => -1,
+    }
+}
+
+/// Stop tracing and finish the file. Returns 0, or -1 with NOT_FOUND if no
+/// trace was running or OS_ERROR if the file could not be written out.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn avtools_tracing_stop() -> i32 {
+    match error::record(stop()) {
+        Some(true) => 0,
+        Some(false) => {
+            error::fail(error::NOT_FOUND, "no trace is running");
+            -1
+        }
+        None => -1,
+    }
+}
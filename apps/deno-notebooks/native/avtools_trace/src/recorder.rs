@@ -0,0 +1,364 @@
+//! The `tracing` subscriber behind avtools_tracing_start. It is installed as
+//! the global default on the first start and stays installed; while no trace
+//! runs its max level hint is OFF, so every span is rejected by the level
+//! check before reaching it.
+
+use crate::{LEVEL_DEBUG, LEVEL_ERROR, LEVEL_INFO, LEVEL_TRACE, LEVEL_WARN};
+use avtools_ffi::shutdown::{self, Stage};
+use avtools_ffi::{error, Error};
+use serde_json::{json, Map, Value};
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::{Mutex, MutexGuard, OnceLock};
+use std::thread;
+use tracing::field::{Field, Visit};
+use tracing::level_filters::LevelFilter;
+use tracing::span::{Attributes, Id, Record};
+use tracing::subscriber::{Interest, Subscriber};
+use tracing::{Event, Metadata};
+
+/// LEVEL_* being recorded; 0 while stopped.
+static LEVEL: AtomicU32 = AtomicU32::new(0);
+static SINK: Mutex<Option<Sink>> = Mutex::new(None);
+/// Whether the recorder became the global subscriber; decided on first start.
+static INSTALLED: OnceLock<bool> = OnceLock::new();
+static NEXT_SPAN: AtomicU64 = AtomicU64::new(1);
+static NEXT_THREAD: AtomicU64 = AtomicU64::new(1);
+
+thread_local! {
+    /// Small per-thread id for the trace's `tid`; 0 until first used.
+    static THREAD_ID: Cell<u64> = const { Cell::new(0) };
+    /// Spans entered on this thread, innermost last, with their start in ns.
+    static ENTERED: RefCell<Vec<(u64, u64)>> = const { RefCell::new(Vec::new()) };
+}
+
+enum Format {
+    /// Chrome trace event format: one JSON array of events.
+    Chrome,
+    /// One JSON object per line.
+    JsonLines,
+}
+
+struct Sink {
+    out: BufWriter<File>,
+    format: Format,
+    /// Chrome only: no event written yet, so no separating comma.
+    first: bool,
+    /// Threads whose name was written as Chrome metadata.
+    named: HashSet<u64>,
+    /// First write error; later records are dropped.
+    failed: Option<io::Error>,
+}
+
+impl Sink {
+    fn write(&mut self, record: &Value) {
+        if self.failed.is_some() {
+            return;
+        }
+        let result = match self.format {
+            Format::Chrome => {
+                let separator: &[u8] = if self.first { b"[\n" } else { b",\n" };
+                self.first = false;
+                self.out.write_all(separator).and_then(|()| {
+                    serde_json::to_writer(&mut self.out, record).map_err(io::Error::from)
+                })
+            }
+            Format::JsonLines => serde_json::to_writer(&mut self.out, record)
+                .map_err(io::Error::from)
+                .and_then(|()| self.out.write_all(b"\n")),
+        };
+        if let Err(err) = result {
+            avtools_log::warn!("trace write failed, dropping the rest: {err}");
+            self.failed = Some(err);
+        }
+    }
+
+    /// Chrome viewers label threads by a metadata event, once per thread.
+    fn name_thread(&mut self, tid: u64) {
+        if !matches!(self.format, Format::Chrome) || !self.named.insert(tid) {
+            return;
+        }
+        let current = thread::current();
+        let name = current.name().unwrap_or("unnamed");
+        self.write(&json!({
+            "name": "thread_name",
+            "ph": "M",
+            "pid": std::process::id(),
+            "tid": tid,
+            "args": { "name": name },
+        }));
+    }
+
+    fn finish(mut self) -> io::Result<()> {
+        if let Some(err) = self.failed {
+            return Err(err);
+        }
+        if matches!(self.format, Format::Chrome) {
+            let tail: &[u8] = if self.first { b"[]\n" } else { b"\n]\n" };
+            self.out.write_all(tail)?;
+        }
+        self.out.flush()
+    }
+}
+
+struct SpanData {
+    metadata: &'static Metadata<'static>,
+    fields: Map<String, Value>,
+    refs: usize,
+}
+
+static SPANS: Mutex<Option<HashMap<u64, SpanData>>> = Mutex::new(None);
+
+fn spans() -> MutexGuard<'static, Option<HashMap<u64, SpanData>>> {
+    SPANS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn thread_id() -> u64 {
+    THREAD_ID.with(|id| {
+        if id.get() == 0 {
+            id.set(NEXT_THREAD.fetch_add(1, Ordering::Relaxed));
+        }
+        id.get()
+    })
+}
+
+fn level_filter(level: u32) -> LevelFilter {
+    match level {
+        LEVEL_ERROR => LevelFilter::ERROR,
+        LEVEL_WARN => LevelFilter::WARN,
+        LEVEL_INFO => LevelFilter::INFO,
+        LEVEL_DEBUG => LevelFilter::DEBUG,
+        LEVEL_TRACE => LevelFilter::TRACE,
+        _ => LevelFilter::OFF,
+    }
+}
+
+/// Fields as JSON values, numbers and booleans kept as such.
+struct Fields<'a>(&'a mut Map<String, Value>);
+
+impl Visit for Fields<'_> {
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0
+            .insert(field.name().to_string(), format!("{value:?}").into());
+    }
+}
+
+struct Recorder;
+
+impl Recorder {
+    /// Write a finished span (`dur_ns` Some) or an instant event.
+    fn emit(metadata: &Metadata, fields: Map<String, Value>, start_ns: u64, dur_ns: Option<u64>) {
+        let mut sink = SINK.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(sink) = sink.as_mut() else {
+            return;
+        };
+        let tid = thread_id();
+        let record = match sink.format {
+            Format::Chrome => {
+                let mut event = json!({
+                    "name": metadata.name(),
+                    "cat": metadata.target(),
+                    "ts": start_ns as f64 / 1000.0,
+                    "pid": std::process::id(),
+                    "tid": tid,
+                    "args": fields,
+                });
+                match dur_ns {
+                    Some(dur) => {
+                        event["ph"] = "X".into();
+                        event["dur"] = (dur as f64 / 1000.0).into();
+                    }
+                    None => {
+                        event["ph"] = "i".into();
+                        event["s"] = "t".into();
+                    }
+                }
+                sink.name_thread(tid);
+                event
+            }
+            Format::JsonLines => json!({
+                "name": metadata.name(),
+                "target": metadata.target(),
+                "level": metadata.level().as_str(),
+                "ts_us": start_ns as f64 / 1000.0,
+                "dur_us": dur_ns.map(|dur| dur as f64 / 1000.0),
+                "thread": thread::current().name().unwrap_or("unnamed"),
+                "fields": fields,
+            }),
+        };
+        sink.write(&record);
+    }
+}
+
+impl Subscriber for Recorder {
+    fn register_callsite(&self, _metadata: &'static Metadata<'static>) -> Interest {
+        // Whether a callsite is wanted changes with every start and stop.
+        Interest::sometimes()
+    }
+
+    fn max_level_hint(&self) -> Option<LevelFilter> {
+        Some(level_filter(LEVEL.load(Ordering::Relaxed)))
+    }
+
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        *metadata.level() <= level_filter(LEVEL.load(Ordering::Relaxed))
+    }
+
+    fn new_span(&self, attrs: &Attributes) -> Id {
+        let id = NEXT_SPAN.fetch_add(1, Ordering::Relaxed);
+        let mut fields = Map::new();
+        attrs.record(&mut Fields(&mut fields));
+        spans().get_or_insert_with(HashMap::new).insert(
+            id,
+            SpanData {
+                metadata: attrs.metadata(),
+                fields,
+                refs: 1,
+            },
+        );
+        Id::from_u64(id)
+    }
+
+    fn record(&self, span: &Id, values: &Record) {
+        if let Some(data) = spans().as_mut().and_then(|s| s.get_mut(&span.into_u64())) {
+            values.record(&mut Fields(&mut data.fields));
+        }
+    }
+
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+    fn event(&self, event: &Event) {
+        let mut fields = Map::new();
+        event.record(&mut Fields(&mut fields));
+        Self::emit(event.metadata(), fields, avtools_clock::now_ns(), None);
+    }
+
+    fn enter(&self, span: &Id) {
+        let start = avtools_clock::now_ns();
+        ENTERED.with(|entered| entered.borrow_mut().push((span.into_u64(), start)));
+    }
+
+    fn exit(&self, span: &Id) {
+        let id = span.into_u64();
+        let Some(start) = ENTERED.with(|entered| {
+            let mut entered = entered.borrow_mut();
+            let index = entered
+                .iter()
+                .rposition(|&(entered_id, _)| entered_id == id)?;
+            Some(entered.remove(index).1)
+        }) else {
+            return;
+        };
+        let end = avtools_clock::now_ns();
+        let Some((metadata, fields)) = spans()
+            .as_ref()
+            .and_then(|s| s.get(&id))
+            .map(|data| (data.metadata, data.fields.clone()))
+        else {
+            return;
+        };
+        Self::emit(metadata, fields, start, Some(end.saturating_sub(start)));
+    }
+
+    fn clone_span(&self, span: &Id) -> Id {
+        if let Some(data) = spans().as_mut().and_then(|s| s.get_mut(&span.into_u64())) {
+            data.refs += 1;
+        }
+        span.clone()
+    }
+
+    fn try_close(&self, span: Id) -> bool {
+        let mut spans = spans();
+        let Some(map) = spans.as_mut() else {
+            return false;
+        };
+        let id = span.into_u64();
+        let Some(data) = map.get_mut(&id) else {
+            return false;
+        };
+        data.refs -= 1;
+        if data.refs > 0 {
+            return false;
+        }
+        map.remove(&id);
+        true
+    }
+}
+
+pub fn start(path: &str, level: u32) -> Result<(), Error> {
+    let mut sink = SINK.lock().unwrap_or_else(|e| e.into_inner());
+    if sink.is_some() {
+        return Err(Error::new(error::BUSY, "a trace is already running"));
+    }
+    let file = File::create(path)
+        .map_err(|err| Error::new(error::OS_ERROR, format!("cannot create {path}: {err}")))?;
+    let format = if path.ends_with(".jsonl") {
+        Format::JsonLines
+    } else {
+        Format::Chrome
+    };
+    *sink = Some(Sink {
+        out: BufWriter::new(file),
+        format,
+        first: true,
+        named: HashSet::new(),
+        failed: None,
+    });
+    drop(sink);
+
+    let installed = *INSTALLED.get_or_init(|| {
+        let installed =
+            tracing::dispatcher::set_global_default(tracing::Dispatch::new(Recorder)).is_ok();
+        if installed {
+            shutdown::register(Stage::Workers, "tracing", || {
+                u32::from(stop().unwrap_or(false))
+            });
+        }
+        installed
+    });
+    if !installed {
+        SINK.lock().unwrap_or_else(|e| e.into_inner()).take();
+        return Err(Error::new(
+            error::BUSY,
+            "another tracing subscriber is installed in this library",
+        ));
+    }
+    LEVEL.store(level, Ordering::Relaxed);
+    tracing::callsite::rebuild_interest_cache();
+    Ok(())
+}
+
+pub fn stop() -> Result<bool, Error> {
+    LEVEL.store(0, Ordering::Relaxed);
+    tracing::callsite::rebuild_interest_cache();
+    let Some(sink) = SINK.lock().unwrap_or_else(|e| e.into_inner()).take() else {
+        return Ok(false);
+    };
+    sink.finish()
+        .map_err(|err| Error::new(error::OS_ERROR, format!("cannot finish the trace: {err}")))?;
+    Ok(true)
+}
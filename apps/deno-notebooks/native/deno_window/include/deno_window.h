@@ -0,0 +1,181 @@
+/* Generated by native/avtools_bindgen from the exports of deno_window; do not edit. */
+
+#ifndef AVTOOLS_DENO_WINDOW_H
+#define AVTOOLS_DENO_WINDOW_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+typedef struct WindowState WindowState;
+
+struct WindowState *create_window(uint32_t width,
+                                  uint32_t height,
+                                  const uint8_t *title_ptr,
+                                  uint32_t title_len);
+
+struct WindowState *create_window_ex(const uint8_t *options_json_ptr, uint32_t options_json_len);
+
+struct WindowState *create_child_window(struct WindowState *parent_state,
+                                        uint32_t x,
+                                        uint32_t y,
+                                        uint32_t width,
+                                        uint32_t height);
+
+uint32_t create_additional_window(struct WindowState *state,
+                                  uint32_t width,
+                                  uint32_t height,
+                                  const uint8_t *title_ptr,
+                                  uint32_t title_len);
+
+int32_t set_parent_window_handle(struct WindowState *state,
+                                 size_t handle,
+                                 size_t display,
+                                 uint32_t system);
+
+int32_t wait_for_window_ready(struct WindowState *state, uint32_t timeout_ms);
+
+size_t get_raw_window_handle(struct WindowState *state);
+
+size_t get_raw_display_handle(struct WindowState *state);
+
+uint32_t get_window_system(struct WindowState *state);
+
+uint32_t poll_events(struct WindowState *state, uint8_t *buf_ptr, uint32_t buf_cap);
+
+uint32_t poll_events_filtered(struct WindowState *state,
+                              uint64_t event_mask,
+                              uint8_t *buf_ptr,
+                              uint32_t buf_cap);
+
+int32_t subscribe_events(struct WindowState *state, uint64_t event_mask, void (*cb)(const uint8_t*,
+                                                                                    uint32_t));
+
+int32_t unsubscribe_events(struct WindowState *state);
+
+int32_t send_window_user_event(struct WindowState *state,
+                               const uint8_t *data_ptr,
+                               uint32_t data_len);
+
+uint32_t poll_events_msgpack(struct WindowState *state, uint8_t *buf_ptr, uint32_t buf_cap);
+
+int32_t set_event_format(struct WindowState *state, uint8_t format);
+
+void resize_window(struct WindowState *state, uint32_t width, uint32_t height);
+
+void get_window_size(struct WindowState *state, uint32_t *out_w, uint32_t *out_h);
+
+int32_t set_window_position(struct WindowState *state, int32_t x, int32_t y);
+
+int32_t get_window_position(struct WindowState *state, int32_t *out_x, int32_t *out_y);
+
+size_t get_raw_window_handle_by_token(struct WindowState *state, uint32_t token);
+
+int32_t resize_window_by_token(struct WindowState *state,
+                               uint32_t token,
+                               uint32_t width,
+                               uint32_t height);
+
+int32_t destroy_window_by_token(struct WindowState *state, uint32_t token);
+
+int32_t get_window_size_by_token(struct WindowState *state,
+                                 uint32_t token,
+                                 uint32_t *out_w,
+                                 uint32_t *out_h);
+
+uint64_t get_frame_count(struct WindowState *state);
+
+double get_frames_per_second(struct WindowState *state);
+
+double get_display_refresh_rate(struct WindowState *state);
+
+uint64_t get_vsync_interval_us(struct WindowState *state);
+
+int32_t set_window_corner_preference(struct WindowState *state, uint32_t pref);
+
+int32_t set_taskbar_thumbnail_clip(struct WindowState *state,
+                                   uint32_t x,
+                                   uint32_t y,
+                                   uint32_t width,
+                                   uint32_t height);
+
+int32_t clear_taskbar_thumbnail_clip(struct WindowState *state);
+
+int32_t capture_window_pixels(struct WindowState *state,
+                              uint8_t *buf_ptr,
+                              uint32_t buf_cap,
+                              uint32_t *out_width,
+                              uint32_t *out_height);
+
+int32_t set_content_protection(struct WindowState *state, uint8_t enabled);
+
+int32_t set_pointer_capture(struct WindowState *state, uint8_t captured);
+
+int32_t set_auto_capture_on_drag(struct WindowState *state, uint8_t enabled);
+
+int32_t begin_window_drag_move(struct WindowState *state);
+
+int32_t begin_window_drag_resize(struct WindowState *state, uint32_t direction);
+
+int32_t set_hit_test_callback(struct WindowState *state, uint8_t (*cb)(double x, double y));
+
+int32_t set_accessibility_label(struct WindowState *state,
+                                const uint8_t *label_ptr,
+                                uint32_t label_len);
+
+int32_t set_accessibility_role(struct WindowState *state, uint32_t role);
+
+int32_t set_hdr_hint(struct WindowState *state, uint8_t enabled);
+
+int32_t set_gpu_preference(struct WindowState *state, uint32_t pref);
+
+uint32_t get_gpu_preference(struct WindowState *state);
+
+uint32_t get_display_color_space(struct WindowState *state, uint8_t *buf_ptr, uint32_t buf_cap);
+
+uint32_t list_monitors(struct WindowState *state, uint8_t *buf_ptr, uint32_t buf_cap);
+
+uint32_t window_abi_version(void);
+
+uint32_t window_capabilities(uint8_t *buf_ptr, uint32_t buf_cap);
+
+uint32_t window_decode_frame_json(const uint8_t *ptr,
+                                  uint32_t len,
+                                  uint8_t *buf_ptr,
+                                  uint32_t buf_cap);
+
+int32_t window_last_error_code(void);
+
+uint32_t window_metrics_json(uint8_t *buf_ptr, uint32_t buf_cap);
+
+uint32_t window_last_error_message(uint8_t *buf_ptr, uint32_t buf_cap);
+
+uint32_t window_get_last_panic(uint8_t *buf_ptr, uint32_t buf_cap);
+
+void window_set_log_callback(void (*cb)(uint32_t, const uint8_t*, uint32_t));
+
+int32_t window_set_log_level(uint32_t level);
+
+int32_t set_before_destroy_callback(struct WindowState *state, void (*cb)(void));
+
+void destroy_window(struct WindowState *state);
+
+int64_t avtools_clock_offset(uint32_t domain_a, uint32_t domain_b);
+
+uint64_t avtools_clock_now_us(void);
+
+uint8_t *av_alloc(uint32_t len);
+
+void av_free(uint8_t *ptr, uint32_t len);
+
+extern int32_t atexit(void (*cb)(void));
+
+uint32_t avtools_shutdown_all(uint8_t *out_ptr, uint32_t out_cap);
+
+int32_t avtools_tracing_start(const uint8_t *path_ptr, uint32_t path_len, uint32_t level);
+
+int32_t avtools_tracing_stop(void);
+
+#endif  /* AVTOOLS_DENO_WINDOW_H */
@@ -1,23 +1,73 @@
+#[cfg(target_os = "macos")]
+mod macos;
+mod metrics;
+mod taps;
+#[cfg(target_os = "windows")]
+mod win32;
+#[cfg(all(unix, not(target_os = "macos")))]
+mod x11;
+
 use winit::raw_window_handle_05::{
     HasRawDisplayHandle, HasRawWindowHandle, RawDisplayHandle, RawWindowHandle,
 };
-use serde::Serialize;
+use avtools_ffi::options::{self, Invalid, VersionedOptions};
+use avtools_ffi::shutdown::{self, Stage};
+use avtools_ffi::{buffer, catch_panic, error, Error};
+use serde::{Deserialize, Serialize};
 use std::ptr;
+use std::collections::{BTreeMap, VecDeque};
 use std::slice;
-use std::time::Duration;
+use std::sync::{Arc, Mutex, Once};
+use std::thread;
+use std::time::{Duration, Instant};
 use winit::application::ApplicationHandler;
-use winit::dpi::{LogicalSize, PhysicalSize};
+use winit::dpi::{LogicalPosition, LogicalSize, PhysicalPosition, PhysicalSize};
 use winit::event::{ElementState, MouseButton, MouseScrollDelta, WindowEvent};
-use winit::event_loop::{ActiveEventLoop, ControlFlow, EventLoop};
+use winit::event_loop::{ActiveEventLoop, ControlFlow, EventLoop, EventLoopProxy};
 use winit::keyboard::Key;
 use winit::platform::pump_events::EventLoopExtPumpEvents;
-use winit::window::{Window, WindowId};
+use winit::raw_window_handle::{HasWindowHandle, RawWindowHandle as RawWindowHandle06};
+use winit::monitor::MonitorHandle;
+use winit::error::ExternalError;
+use winit::window::{CursorGrabMode, ResizeDirection, Window, WindowId};
+
+use metrics::WindowMetrics;
+use taps::Inbox;
+
+pub use taps::{EventTap, Injector, TapEvent};
+
+/// deno_window's last-error codes (see avtools_ffi::error for the shared ones).
+const ERR_EVENT_LOOP: i32 = 100;
+const ERR_WINDOW_CREATE: i32 = 101;
+const ERR_NO_WINDOW: i32 = 102;
+const ERR_CAPTURE: i32 = 103;
+const ERR_EVENTS_TOO_LARGE: i32 = 104;
+
+const GPU_PREFERENCE_DEFAULT: u32 = 0;
+const GPU_PREFERENCE_HIGH_PERFORMANCE: u32 = 2;
+
+/// begin_window_drag_resize directions: the edge or corner dragged, or
+/// DRAG_CENTER to move the window instead.
+const DRAG_CENTER: u32 = 0;
+const DRAG_NORTH: u32 = 1;
+const DRAG_NORTH_EAST: u32 = 2;
+const DRAG_EAST: u32 = 3;
+const DRAG_SOUTH_EAST: u32 = 4;
+const DRAG_SOUTH: u32 = 5;
+const DRAG_SOUTH_WEST: u32 = 6;
+const DRAG_WEST: u32 = 7;
+const DRAG_NORTH_WEST: u32 = 8;
+
+const EVENT_FORMAT_JSON: u8 = 0;
+const EVENT_FORMAT_MSGPACK: u8 = 1;
 
 #[derive(Serialize)]
 #[serde(tag = "type")]
 enum WindowEventRecord {
+    /// `key` names the logical key ("a", "Space", "ArrowLeft"); `text` is
+    /// what the press types, if anything, with modifiers and layout applied.
     #[serde(rename = "key")]
-    Key { key: String, down: bool },
+    Key { key: String, text: Option<String>, down: bool },
     #[serde(rename = "mouse_move")]
     MouseMove { x: f64, y: f64 },
     #[serde(rename = "mouse_button")]
@@ -28,20 +78,212 @@ enum WindowEventRecord {
     Resize { width: u32, height: u32 },
     #[serde(rename = "close")]
     Close,
+    /// Keyboard focus gained or lost, e.g. on alt-tab.
+    #[serde(rename = "focus")]
+    Focus { gained: bool },
+    /// A file dropped on the window; one record per file. Paths that are not
+    /// valid UTF-8 have the invalid bytes replaced with U+FFFD.
+    #[serde(rename = "file_drop")]
+    FileDrop { path: String },
+    /// A file dragged over the window (`active`), or the drag leaving without
+    /// a drop (`active` false, same path). A drop also ends the hover.
+    #[serde(rename = "file_hover")]
+    FileHover { path: String, active: bool },
+    #[serde(rename = "frame_stats")]
+    FrameStats { count: u64, fps: f64 },
+    /// Bytes from send_window_user_event; base64 in JSON, raw bin in msgpack.
+    #[serde(rename = "user")]
+    UserEvent {
+        #[serde(serialize_with = "serialize_user_data")]
+        data: UserEvent,
+    },
+}
+
+/// Payload of the event loop's user events.
+type UserEvent = Vec<u8>;
+
+/// set_hit_test_callback: 1 captures clicks at `(x, y)`, 0 lets them through.
+type HitTestCallback = extern "C" fn(x: f64, y: f64) -> u8;
+
+/// Receives one event record at a time (subscribe_events).
+type EventCallback = extern "C" fn(*const u8, u32);
+
+/// Called while the native window is still valid, just before it goes away
+/// (set_before_destroy_callback).
+type BeforeDestroyCallback = extern "C" fn();
+
+fn serialize_user_data<S: serde::Serializer>(data: &UserEvent, s: S) -> Result<S::Ok, S::Error> {
+    if s.is_human_readable() {
+        s.serialize_str(&base64_encode(data))
+    } else {
+        s.serialize_bytes(data)
+    }
+}
+
+/// Standard padded base64.
+fn base64_encode(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 0x3F) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// Bits for poll_events_filtered, one per WindowEventRecord variant.
+const EVENT_MASK_KEY: u64 = 0x1;
+const EVENT_MASK_MOUSE_MOVE: u64 = 0x2;
+const EVENT_MASK_MOUSE_BUTTON: u64 = 0x4;
+const EVENT_MASK_SCROLL: u64 = 0x8;
+const EVENT_MASK_RESIZE: u64 = 0x10;
+const EVENT_MASK_CLOSE: u64 = 0x20;
+const EVENT_MASK_FRAME_STATS: u64 = 0x40;
+const EVENT_MASK_USER: u64 = 0x80;
+const EVENT_MASK_FOCUS: u64 = 0x100;
+const EVENT_MASK_FILE_DROP: u64 = 0x200;
+const EVENT_MASK_FILE_HOVER: u64 = 0x400;
+
+impl WindowEventRecord {
+    fn mask_bit(&self) -> u64 {
+        match self {
+            WindowEventRecord::Key { .. } => EVENT_MASK_KEY,
+            WindowEventRecord::MouseMove { .. } => EVENT_MASK_MOUSE_MOVE,
+            WindowEventRecord::MouseButton { .. } => EVENT_MASK_MOUSE_BUTTON,
+            WindowEventRecord::Scroll { .. } => EVENT_MASK_SCROLL,
+            WindowEventRecord::Resize { .. } => EVENT_MASK_RESIZE,
+            WindowEventRecord::Close => EVENT_MASK_CLOSE,
+            WindowEventRecord::FrameStats { .. } => EVENT_MASK_FRAME_STATS,
+            WindowEventRecord::UserEvent { .. } => EVENT_MASK_USER,
+            WindowEventRecord::Focus { .. } => EVENT_MASK_FOCUS,
+            WindowEventRecord::FileDrop { .. } => EVENT_MASK_FILE_DROP,
+            WindowEventRecord::FileHover { .. } => EVENT_MASK_FILE_HOVER,
+        }
+    }
+}
+
+/// A record stamped with `avtools_clock::now_us()` when it was queued, so it
+/// can be ordered against MIDI and fastsleep timestamps.
+#[derive(Serialize)]
+struct TimedEvent {
+    ts_us: u64,
+    /// Which of the state's windows it came from: 0 for its own, otherwise
+    /// the token create_additional_window returned.
+    window_token: u32,
+    #[serde(flatten)]
+    event: WindowEventRecord,
+}
+
+/// Timestamps kept for the rolling frame rate.
+const FRAME_TIMES_CAP: usize = 60;
+
+/// Placement of a child window inside its parent.
+struct ChildConfig {
+    parent_handle: RawWindowHandle06,
+    x: u32,
+    y: u32,
+}
+
+/// A foreign window to embed in (set_parent_window_handle).
+#[derive(Clone, Copy)]
+struct ParentWindow {
+    handle: usize,
+    /// Xlib Display*; the other platforms have no display connection.
+    #[cfg_attr(not(all(unix, not(target_os = "macos"))), allow(dead_code))]
+    display: usize,
+    system: u32,
+}
+
+#[derive(Serialize)]
+struct DisplayColorSpace {
+    hdr_supported: bool,
+    max_luminance_nits: Option<f32>,
+    edr_headroom: Option<f32>,
+    color_gamut: &'static str,
+}
+
+impl DisplayColorSpace {
+    fn unknown() -> Self {
+        Self {
+            hdr_supported: false,
+            max_luminance_nits: None,
+            edr_headroom: None,
+            color_gamut: "unknown",
+        }
+    }
 }
 
 struct WindowApp {
     window: Option<Window>,
+    hdr_hint: bool,
+    /// GPU_PREFERENCE_* for whoever creates the surface's adapter.
+    gpu_preference: u32,
+    /// Keep the window out of screen captures; reapplied when it is recreated.
+    content_protected: bool,
+    /// Capture the pointer while the left button is held.
+    auto_capture_on_drag: bool,
+    /// Decides per cursor position (physical client pixels) whether clicks
+    /// are captured (1) or pass through to whatever is below (0).
+    hit_test: Option<HitTestCallback>,
+    /// What was last passed to set_cursor_hittest; None before the first call.
+    cursor_hittest: Option<bool>,
+    event_taps: Vec<(u64, EventTap)>,
+    /// Callback taking the event types in its mask as they happen, instead of
+    /// queueing them for poll_events.
+    subscription: Option<(u64, EventCallback)>,
+    before_destroy: Option<BeforeDestroyCallback>,
+    /// Records from Injectors, moved into `events` on each pump.
+    inbox: Arc<Inbox>,
+    child: Option<ChildConfig>,
+    /// Host window to embed in (set_parent_window_handle), applied as soon as
+    /// the native window exists.
+    parent: Option<ParentWindow>,
+    /// Centre the window on the primary monitor when it is created.
+    center: bool,
+    /// Where the window is placed when created, in physical screen pixels.
+    initial_position: Option<(i32, i32)>,
+    children: Vec<*mut WindowState>,
+    /// 0 for the window a state was created with, else its
+    /// create_additional_window token.
+    token: u32,
+    /// Top-level windows sharing this window's event loop
+    /// (create_additional_window); their events are queued here.
+    additional: Vec<WindowApp>,
+    next_token: u32,
     window_id: Option<WindowId>,
     width: u32,
     height: u32,
     title: String,
-    events: Vec<WindowEventRecord>,
+    events: Vec<TimedEvent>,
     last_cursor: (f64, f64),
+    /// Last focus state recorded; platforms may repeat Focused events.
+    focused: Option<bool>,
+    /// Path of the file being dragged over the window, for the record when
+    /// the drag leaves.
+    hovered_file: Option<String>,
     should_close: bool,
+    /// Close was requested while additional windows were open; the window is
+    /// hidden and the loop stops once the last of them closes.
+    close_deferred: bool,
     cached_window_handle: usize,
     cached_display_handle: usize,
     cached_window_system: u32,
+    /// Monitor the window was last seen on, with its refresh rate (0 = unknown).
+    monitor: Option<MonitorHandle>,
+    refresh_rate_hz: f64,
+    vsync_interval_us: u64,
+    event_format: u8,
+    /// Number of about_to_wait calls, i.e. event loop iterations.
+    frame_counter: u64,
+    frame_times: VecDeque<Instant>,
+    last_frame_stats: Instant,
+    metrics: Arc<WindowMetrics>,
 }
 
 impl WindowApp {
@@ -51,8 +293,8 @@ impl WindowApp {
 
     fn debug_log_handles(&self, label: &str) {
         if Self::debug_enabled() {
-            eprintln!(
-                "[deno_window] {label} window_handle=0x{:x} display_handle=0x{:x} system={}",
+            avtools_log::info!(
+                "{label} window_handle=0x{:x} display_handle=0x{:x} system={}",
                 self.cached_window_handle, self.cached_display_handle, self.cached_window_system
             );
         }
@@ -61,26 +303,88 @@ impl WindowApp {
     fn new(width: u32, height: u32, title: String) -> Self {
         Self {
             window: None,
+            hdr_hint: false,
+            gpu_preference: GPU_PREFERENCE_DEFAULT,
+            content_protected: false,
+            auto_capture_on_drag: false,
+            hit_test: None,
+            cursor_hittest: None,
+            event_taps: Vec::new(),
+            subscription: None,
+            before_destroy: None,
+            inbox: Arc::new(Inbox::with_size(width, height)),
+            child: None,
+            parent: None,
+            center: false,
+            initial_position: None,
+            children: Vec::new(),
+            token: 0,
+            additional: Vec::new(),
+            next_token: 1,
             window_id: None,
             width,
             height,
             title,
             events: Vec::new(),
             last_cursor: (0.0, 0.0),
+            focused: None,
+            hovered_file: None,
             should_close: false,
+            close_deferred: false,
             cached_window_handle: 0,
             cached_display_handle: 0,
             cached_window_system: 0,
+            monitor: None,
+            refresh_rate_hz: 0.0,
+            vsync_interval_us: 0,
+            event_format: EVENT_FORMAT_JSON,
+            frame_counter: 0,
+            frame_times: VecDeque::with_capacity(FRAME_TIMES_CAP),
+            last_frame_stats: Instant::now(),
+            metrics: Arc::new(WindowMetrics::default()),
+        }
+    }
+
+    /// Refresh the cached refresh rate if the window moved to another monitor.
+    /// winit has no monitor-changed event, so this runs on move/scale changes.
+    fn update_monitor(&mut self) {
+        let Some(monitor) = self.window.as_ref().and_then(|w| w.current_monitor()) else {
+            return;
+        };
+        if self.monitor.as_ref() == Some(&monitor) {
+            return;
         }
+        self.refresh_rate_hz = monitor
+            .refresh_rate_millihertz()
+            .map_or(0.0, |mhz| mhz as f64 / 1000.0);
+        self.vsync_interval_us = if self.refresh_rate_hz > 0.0 {
+            (1_000_000.0 / self.refresh_rate_hz).round() as u64
+        } else {
+            0
+        };
+        self.monitor = Some(monitor);
     }
 
     fn ensure_window(&mut self, event_loop: &ActiveEventLoop) {
         if self.window.is_some() {
             return;
         }
-        let attrs = Window::default_attributes()
+        let mut attrs = Window::default_attributes()
             .with_title(self.title.clone())
-            .with_inner_size(LogicalSize::new(self.width as f64, self.height as f64));
+            .with_inner_size(LogicalSize::new(self.width as f64, self.height as f64))
+            .with_content_protected(self.content_protected);
+        if self.center && self.child.is_none() {
+            self.initial_position = self.centered_position(event_loop);
+        }
+        if let Some((x, y)) = self.initial_position {
+            attrs = attrs.with_position(PhysicalPosition::new(x, y));
+        }
+        if let Some(child) = self.child.as_ref() {
+            attrs = attrs.with_position(LogicalPosition::new(child.x as f64, child.y as f64));
+            // The parent handle stays valid while the parent WindowState is alive,
+            // and children are closed before their parent is destroyed.
+            attrs = unsafe { attrs.with_parent_window(Some(child.parent_handle)) };
+        }
         match event_loop.create_window(attrs) {
             Ok(window) => {
                 self.window_id = Some(window.id());
@@ -90,42 +394,290 @@ impl WindowApp {
                 self.cached_display_handle = handle_from_raw_display(display_handle);
                 self.cached_window_system = window_system_id(win_handle, display_handle);
                 self.debug_log_handles("ensure_window");
+                let size = window.inner_size();
+                self.inbox.set_size(size.width, size.height);
                 self.window = Some(window);
+                self.update_monitor();
+                if let Some(parent) = self.parent {
+                    if let Err(err) = self.apply_parent(parent) {
+                        avtools_log::error!("Failed to embed window: {}", err.message);
+                    }
+                }
             }
             Err(err) => {
-                eprintln!("Failed to create window: {err}");
+                avtools_log::error!("Failed to create window: {err}");
+                error::fail(ERR_WINDOW_CREATE, format!("failed to create window: {err}"));
+            }
+        }
+    }
+
+    /// Top left corner that centres a window of the requested size on the
+    /// primary monitor (the first one where there is no primary, as on
+    /// Wayland, which ignores positions anyway).
+    fn centered_position(&self, event_loop: &ActiveEventLoop) -> Option<(i32, i32)> {
+        let monitor = event_loop.primary_monitor().or_else(|| event_loop.available_monitors().next())?;
+        let scale = monitor.scale_factor();
+        let (origin, size) = (monitor.position(), monitor.size());
+        let width = (self.width as f64 * scale).round() as i32;
+        let height = (self.height as f64 * scale).round() as i32;
+        Some((
+            origin.x + (size.width as i32 - width) / 2,
+            origin.y + (size.height as i32 - height) / 2,
+        ))
+    }
+
+    /// Reparent the native window into `parent` with the platform's API.
+    fn apply_parent(&self, parent: ParentWindow) -> Result<(), Error> {
+        if parent.system != self.cached_window_system {
+            return Err(Error::new(
+                error::INVALID_ARGUMENT,
+                format!("parent is window system {}, this window {}", parent.system, self.cached_window_system),
+            ));
+        }
+        let handle = self.cached_window_handle;
+        #[cfg(target_os = "macos")]
+        let embedded = macos::reparent(handle, parent.handle);
+        #[cfg(target_os = "windows")]
+        let embedded = win32::reparent(handle, parent.handle);
+        #[cfg(all(unix, not(target_os = "macos")))]
+        let embedded = match parent.system {
+            1 => {
+                let display = if parent.display != 0 { parent.display } else { self.cached_display_handle };
+                x11::reparent(display, handle, parent.handle)
             }
+            _ => return Err(Error::new(error::UNSUPPORTED, "embedding is not available on Wayland")),
+        };
+        match embedded {
+            true => Ok(()),
+            false => Err(Error::new(error::OS_ERROR, "reparenting the window failed")),
+        }
+    }
+
+    fn record_frame(&mut self) {
+        let now = Instant::now();
+        self.frame_counter += 1;
+        self.metrics.set_frames(self.frame_counter);
+        if self.frame_times.len() >= FRAME_TIMES_CAP {
+            self.frame_times.pop_front();
+        }
+        self.frame_times.push_back(now);
+        if now.duration_since(self.last_frame_stats) >= Duration::from_secs(1) {
+            self.last_frame_stats = now;
+            self.push_event(WindowEventRecord::FrameStats {
+                count: self.frame_counter,
+                fps: self.frames_per_second(),
+            });
         }
     }
 
-    fn record_key(&mut self, key: Key, down: bool) {
+    /// Frame rate over the stored timestamps from the last second.
+    fn frames_per_second(&self) -> f64 {
+        let Some(&newest) = self.frame_times.back() else {
+            return 0.0;
+        };
+        let mut recent = self
+            .frame_times
+            .iter()
+            .filter(|&&t| newest.duration_since(t) <= Duration::from_secs(1));
+        let Some(&oldest) = recent.next() else {
+            return 0.0;
+        };
+        let intervals = recent.count();
+        let span = newest.duration_since(oldest).as_secs_f64();
+        if intervals == 0 || span <= 0.0 {
+            return 0.0;
+        }
+        intervals as f64 / span
+    }
+
+    fn record_key(&mut self, key: Key, text: Option<&str>, down: bool) {
         let key_str = match key {
             Key::Character(text) => text.to_string(),
-            other => format!("{:?}", other),
+            Key::Named(named) => format!("{:?}", named),
+            Key::Dead(_) => "Dead".to_string(),
+            Key::Unidentified(_) => "Unidentified".to_string(),
         };
-        self.events.push(WindowEventRecord::Key { key: key_str, down });
+        self.push_event(WindowEventRecord::Key {
+            key: key_str,
+            text: text.map(str::to_string),
+            down,
+        });
     }
 
     fn record_resize(&mut self, size: PhysicalSize<u32>) {
         self.width = size.width;
         self.height = size.height;
-        self.events.push(WindowEventRecord::Resize {
+        self.inbox.set_size(size.width, size.height);
+        self.push_event(WindowEventRecord::Resize {
             width: size.width,
             height: size.height,
         });
     }
 
-    fn take_events_json(&mut self) -> Vec<u8> {
+    /// Confine the cursor to the window (or release it). macOS has no
+    /// confined mode, but AppKit keeps delivering drag events outside the
+    /// window anyway, so NotSupported counts as success.
+    fn set_pointer_capture(&self, captured: bool) -> Result<(), winit::error::ExternalError> {
+        let Some(window) = self.window.as_ref() else {
+            return Ok(());
+        };
+        let mode = if captured {
+            CursorGrabMode::Confined
+        } else {
+            CursorGrabMode::None
+        };
+        match window.set_cursor_grab(mode) {
+            Err(winit::error::ExternalError::NotSupported(_)) => Ok(()),
+            result => result,
+        }
+    }
+
+    /// Ask the hit-test callback about `(x, y)` and switch click-through on
+    /// or off if the answer changed.
+    fn update_hit_test(&mut self, x: f64, y: f64) {
+        let (Some(hit_test), Some(window)) = (self.hit_test, self.window.as_ref()) else {
+            return;
+        };
+        let capture = hit_test(x, y) != 0;
+        if self.cursor_hittest == Some(capture) {
+            return;
+        }
+        // Cached even on failure, so an unsupported platform is not retried
+        // on every cursor move.
+        self.cursor_hittest = Some(capture);
+        if let Err(err) = window.set_cursor_hittest(capture) {
+            avtools_log::debug!("set_cursor_hittest failed: {err}");
+        }
+    }
+
+    /// While clicks pass through, re-run the hit test at the cursor position
+    /// read from the platform.
+    fn poll_hit_test(&mut self) {
+        if self.cursor_hittest != Some(false) {
+            return;
+        }
+        if let Some((x, y)) = self.query_cursor_position() {
+            self.update_hit_test(x, y);
+        }
+    }
+
+    /// Cursor position in physical client pixels, read from the platform.
+    /// While the window lets clicks through it receives no cursor events, so
+    /// this is how the hit test notices the cursor coming back.
+    fn query_cursor_position(&self) -> Option<(f64, f64)> {
+        let handle = self.cached_window_handle;
+        if handle == 0 {
+            return None;
+        }
+        #[cfg(target_os = "macos")]
+        return macos::cursor_position(handle);
+        #[cfg(target_os = "windows")]
+        return win32::cursor_position(handle);
+        #[cfg(all(unix, not(target_os = "macos")))]
+        return if self.cached_window_system == 1 {
+            x11::cursor_position(self.cached_display_handle, handle)
+        } else {
+            None
+        };
+    }
+
+    fn push_event(&mut self, event: WindowEventRecord) {
+        if !self.event_taps.is_empty() {
+            if let Some(tap_event) = event.as_tap_event(self.width, self.height) {
+                for (_, tap) in &mut self.event_taps {
+                    tap(&tap_event);
+                }
+            }
+        }
+        self.queue_event(TimedEvent {
+            ts_us: avtools_clock::now_us(),
+            window_token: self.token,
+            event,
+        });
+    }
+
+    /// Queue a focus record unless it repeats the last one.
+    fn record_focus(&mut self, gained: bool) {
+        if self.focused != Some(gained) {
+            self.focused = Some(gained);
+            self.push_event(WindowEventRecord::Focus { gained });
+        }
+    }
+
+    /// Hand `event` to the subscriber if it wants this type, else queue it.
+    fn queue_event(&mut self, event: TimedEvent) {
+        if let Some((mask, cb)) = self.subscription {
+            if event.event.mask_bit() & mask != 0 {
+                let bytes = match self.event_format {
+                    EVENT_FORMAT_MSGPACK => rmp_serde::to_vec_named(&event).unwrap_or_default(),
+                    _ => serde_json::to_vec(&event).unwrap_or_default(),
+                };
+                cb(bytes.as_ptr(), bytes.len() as u32);
+                return;
+            }
+        }
+        self.events.push(event);
+        self.metrics.set_queue_depth(self.events.len());
+    }
+
+    /// This window for token 0, else the additional window with `token`.
+    fn window_for_token(&mut self, token: u32) -> Option<&mut WindowApp> {
+        if token == 0 {
+            return Some(self);
+        }
+        self.additional.iter_mut().find(|w| w.token == token)
+    }
+
+    /// Queue what Injectors sent since the last pump.
+    fn drain_injected(&mut self) {
+        for event in self.inbox.take() {
+            self.queue_event(event);
+        }
+    }
+
+    fn events_json(&self) -> Vec<u8> {
+        serde_json::to_vec(&self.events).unwrap_or_else(|_| b"[]".to_vec())
+    }
+
+    /// Same records as events_json, as MessagePack maps (field names kept).
+    fn events_msgpack(&self) -> Vec<u8> {
+        rmp_serde::to_vec_named(&self.events).unwrap_or_else(|_| vec![0x90])
+    }
+
+    /// Copy the queued records, encoded as `format`, to `buf_ptr` and clear
+    /// them; returns the byte length, 0 if there are none. Records that do
+    /// not fit in `buf_cap` (a long dropped file path, say) stay queued, and
+    /// ERR_EVENTS_TOO_LARGE gives the length they need.
+    fn take_events(&mut self, format: u8, buf_ptr: *mut u8, buf_cap: u32) -> u32 {
+        error::clear();
         if self.events.is_empty() {
-            return Vec::new();
+            return 0;
+        }
+        let payload = match format {
+            EVENT_FORMAT_MSGPACK => self.events_msgpack(),
+            _ => self.events_json(),
+        };
+        if !unsafe { buffer::copy_if_fits(&payload, buf_ptr, buf_cap) } {
+            error::fail(
+                ERR_EVENTS_TOO_LARGE,
+                format!("events need {} bytes, the buffer has {buf_cap}", payload.len()),
+            );
+            return 0;
         }
-        let json = serde_json::to_string(&self.events).unwrap_or_else(|_| "[]".to_string());
         self.events.clear();
-        json.into_bytes()
+        self.metrics.set_queue_depth(0);
+        payload.len() as u32
+    }
+
+    /// Like take_events, but drops (rather than keeps) records whose type bit
+    /// is not in `mask`.
+    fn take_events_filtered(&mut self, mask: u64, buf_ptr: *mut u8, buf_cap: u32) -> u32 {
+        self.events.retain(|e| e.event.mask_bit() & mask != 0);
+        self.take_events(self.event_format, buf_ptr, buf_cap)
     }
 }
 
-impl ApplicationHandler for WindowApp {
+impl ApplicationHandler<UserEvent> for WindowApp {
     fn resumed(&mut self, event_loop: &ActiveEventLoop) {
         event_loop.set_control_flow(ControlFlow::Poll);
         self.ensure_window(event_loop);
@@ -133,24 +685,69 @@ impl ApplicationHandler for WindowApp {
 
     fn window_event(&mut self, event_loop: &ActiveEventLoop, window_id: WindowId, event: WindowEvent) {
         if Some(window_id) != self.window_id {
+            for &child in &self.children {
+                let child = unsafe { &mut *child };
+                if child.app.window_id == Some(window_id) {
+                    child.app.window_event(event_loop, window_id, event);
+                    return;
+                }
+            }
+            if let Some(window) = self.additional.iter_mut().find(|w| w.window_id == Some(window_id)) {
+                window.window_event(event_loop, window_id, event);
+                for event in std::mem::take(&mut window.events) {
+                    self.queue_event(event);
+                }
+            }
+            self.additional.retain(|w| !w.should_close);
             return;
         }
 
         match event {
             WindowEvent::CloseRequested => {
-                self.events.push(WindowEventRecord::Close);
-                self.should_close = true;
-                event_loop.exit();
+                self.push_event(WindowEventRecord::Close);
+                // Children and additional windows share the loop; only the
+                // window that owns it may stop it, and not while additional
+                // windows still need it.
+                let owns_loop = self.child.is_none() && self.token == 0;
+                if owns_loop && !self.additional.is_empty() {
+                    self.close_deferred = true;
+                    if let Some(window) = self.window.as_ref() {
+                        window.set_visible(false);
+                    }
+                } else {
+                    self.should_close = true;
+                    if owns_loop {
+                        event_loop.exit();
+                    }
+                }
             }
             WindowEvent::Resized(size) => self.record_resize(size),
             WindowEvent::ScaleFactorChanged { .. } => {
                 if let Some(window) = self.window.as_ref() {
                     self.record_resize(window.inner_size());
                 }
+                self.update_monitor();
+            }
+            WindowEvent::Moved(_) => self.update_monitor(),
+            WindowEvent::Focused(gained) => self.record_focus(gained),
+            WindowEvent::HoveredFile(path) => {
+                let path = path.to_string_lossy().into_owned();
+                self.hovered_file = Some(path.clone());
+                self.push_event(WindowEventRecord::FileHover { path, active: true });
+            }
+            WindowEvent::HoveredFileCancelled => {
+                let path = self.hovered_file.take().unwrap_or_default();
+                self.push_event(WindowEventRecord::FileHover { path, active: false });
+            }
+            WindowEvent::DroppedFile(path) => {
+                self.hovered_file = None;
+                let path = path.to_string_lossy().into_owned();
+                self.push_event(WindowEventRecord::FileDrop { path });
             }
             WindowEvent::CursorMoved { position, .. } => {
                 self.last_cursor = (position.x, position.y);
-                self.events.push(WindowEventRecord::MouseMove { x: position.x, y: position.y });
+                self.update_hit_test(position.x, position.y);
+                self.push_event(WindowEventRecord::MouseMove { x: position.x, y: position.y });
             }
             WindowEvent::MouseInput { state, button, .. } => {
                 let button_id = match button {
@@ -161,8 +758,13 @@ impl ApplicationHandler for WindowApp {
                     _ => 0,
                 };
                 let down = matches!(state, ElementState::Pressed);
+                if self.auto_capture_on_drag && button == MouseButton::Left {
+                    if let Err(err) = self.set_pointer_capture(down) {
+                        avtools_log::debug!("pointer capture failed: {err}");
+                    }
+                }
                 let (x, y) = self.last_cursor;
-                self.events.push(WindowEventRecord::MouseButton {
+                self.push_event(WindowEventRecord::MouseButton {
                     button: button_id,
                     down,
                     x,
@@ -174,33 +776,123 @@ impl ApplicationHandler for WindowApp {
                     MouseScrollDelta::LineDelta(x, y) => (x as f64, y as f64),
                     MouseScrollDelta::PixelDelta(pos) => (pos.x, pos.y),
                 };
-                self.events.push(WindowEventRecord::Scroll { dx, dy });
+                self.push_event(WindowEventRecord::Scroll { dx, dy });
             }
             WindowEvent::KeyboardInput { event, .. } => {
                 let down = matches!(event.state, ElementState::Pressed);
-                self.record_key(event.logical_key, down);
+                self.record_key(event.logical_key, event.text.as_deref(), down);
             }
             _ => {}
         }
     }
 
+    fn user_event(&mut self, _event_loop: &ActiveEventLoop, data: UserEvent) {
+        self.push_event(WindowEventRecord::UserEvent { data });
+    }
+
     fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+        self.record_frame();
+        self.poll_hit_test();
+        self.drain_injected();
+        if self.window.is_some() {
+            for &child in &self.children {
+                let child = unsafe { &mut *child };
+                child.app.ensure_window(event_loop);
+                child.app.poll_hit_test();
+                child.app.drain_injected();
+            }
+            for window in &mut self.additional {
+                window.ensure_window(event_loop);
+                window.poll_hit_test();
+            }
+        }
+        if self.close_deferred && self.additional.is_empty() {
+            self.should_close = true;
+        }
         if self.should_close {
             event_loop.exit();
         }
     }
 }
 
-#[repr(C)]
+/// `WindowState::magic` of a live state; zeroed by destroy_window.
+const STATE_MAGIC: u64 = u64::from_be_bytes(*b"AVWINDOW");
+
+/// Addresses of the states handed out and not yet destroyed. A state pointer
+/// from Deno is only dereferenced once it is found here, so a stale or bogus
+/// pointer is rejected without touching freed memory. Each carries the
+/// window's metrics, which can be read without touching the state itself.
+static LIVE_STATES: Mutex<BTreeMap<usize, Arc<WindowMetrics>>> = Mutex::new(BTreeMap::new());
+static SHUTDOWN_HOOK: Once = Once::new();
+
 pub struct WindowState {
-    event_loop: EventLoop<()>,
+    magic: u64,
+    /// None for child windows, which are driven by their parent's loop.
+    event_loop: Option<EventLoop<UserEvent>>,
+    /// Wakes the loop that drives this window (the parent's for a child).
+    proxy: EventLoopProxy<UserEvent>,
     app: WindowApp,
+    /// Owning parent for child windows; cleared when the parent is destroyed.
+    parent: *mut WindowState,
+}
+
+/// Register a new state as live and hand it out.
+fn into_handle(state: WindowState) -> *mut WindowState {
+    let metrics = state.app.metrics.clone();
+    let ptr = Box::into_raw(Box::new(state));
+    LIVE_STATES.lock().unwrap_or_else(|e| e.into_inner()).insert(ptr as usize, metrics);
+    SHUTDOWN_HOOK.call_once(|| shutdown::register(Stage::Windows, "windows", destroy_all_windows));
+    ptr
+}
+
+/// avtools_shutdown_all: destroy every live window, children before their
+/// parents.
+fn destroy_all_windows() -> u32 {
+    let live: Vec<*mut WindowState> = LIVE_STATES
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .keys()
+        .map(|&addr| addr as *mut WindowState)
+        .collect();
+    let (children, parents): (Vec<_>, Vec<_>) =
+        live.iter().partition(|&&state| unsafe { !(*state).parent.is_null() });
+    for &state in children.into_iter().chain(parents) {
+        destroy_window(state);
+    }
+    live.len() as u32
+}
+
+/// True if `state` came from create_window/create_child_window and has not
+/// been destroyed; otherwise records INVALID_HANDLE.
+fn is_live(state: *const WindowState) -> bool {
+    if state.is_null() {
+        error::fail(error::INVALID_HANDLE, "window state is null");
+        return false;
+    }
+    let registered = LIVE_STATES.lock().unwrap_or_else(|e| e.into_inner()).contains_key(&(state as usize));
+    if !registered || unsafe { (*state).magic } != STATE_MAGIC {
+        error::fail(error::INVALID_HANDLE, "window state was destroyed or is not a window");
+        return false;
+    }
+    true
+}
+
+/// Pump the event loop that drives `state`: its own, or its parent's for a
+/// child window (which then receives its events by forwarding).
+fn pump_events(state: &mut WindowState) {
+    if let Some(event_loop) = state.event_loop.as_mut() {
+        let _span = avtools_trace::span!(DEBUG, "pump_app_events");
+        let start = Instant::now();
+        let _ = event_loop.pump_app_events(Some(Duration::ZERO), &mut state.app);
+        state.app.metrics.record_pump(start.elapsed());
+    } else if !state.parent.is_null() {
+        let parent = unsafe { &mut *state.parent };
+        pump_events(parent);
+    }
 }
 
 fn pump_once(state: &mut WindowState) {
-    let _ = state
-        .event_loop
-        .pump_app_events(Some(Duration::ZERO), &mut state.app);
+    pump_events(state);
     if state.app.cached_window_handle == 0 {
         if let Some(window) = state.app.window.as_ref() {
             let win_handle = window.raw_window_handle();
@@ -244,7 +936,11 @@ fn window_system_id(handle: RawWindowHandle, display: RawDisplayHandle) -> u32 {
     }
 }
 
+/// Create a window and its event loop. The native window itself appears once
+/// the loop has been pumped into `resumed`, which may take more than the one
+/// pump done here; call wait_for_window_ready before using its handles.
 #[no_mangle]
+#[catch_panic]
 pub extern "C" fn create_window(
     width: u32,
     height: u32,
@@ -252,35 +948,272 @@ pub extern "C" fn create_window(
     title_len: u32,
 ) -> *mut WindowState {
     let title = if title_ptr.is_null() || title_len == 0 {
-        "Deno Window".to_string()
+        DEFAULT_TITLE.to_string()
     } else {
         let slice = unsafe { slice::from_raw_parts(title_ptr, title_len as usize) };
         String::from_utf8_lossy(slice).to_string()
     };
+    open_window(WindowOptions {
+        width,
+        height,
+        title,
+        center: false,
+    })
+}
+
+/// Like create_window, with the options given as a JSON object:
+/// `{version, width, height, title, center}` (version 1), where `center`
+/// places the window in the middle of the primary monitor. Every field is optional;
+/// unknown fields are rejected. Returns null if the options are invalid (the
+/// error names the field) or the window cannot be created.
+///
+/// # Safety
+/// `options_json_ptr` must be null or valid for `options_json_len` bytes.
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn create_window_ex(options_json_ptr: *const u8, options_json_len: u32) -> *mut WindowState {
+    let Some(options) = error::record(options::parse_raw::<WindowOptions>(options_json_ptr, options_json_len)) else {
+        return ptr::null_mut();
+    };
+    open_window(options)
+}
+
+const DEFAULT_TITLE: &str = "Deno Window";
+/// Largest width or height create_window_ex accepts, in logical pixels.
+const MAX_WINDOW_SIZE: u32 = 16384;
+
+/// Options for create_window_ex.
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+struct WindowOptions {
+    width: u32,
+    height: u32,
+    title: String,
+    center: bool,
+}
+
+impl Default for WindowOptions {
+    fn default() -> Self {
+        Self {
+            width: 800,
+            height: 600,
+            title: DEFAULT_TITLE.to_string(),
+            center: false,
+        }
+    }
+}
 
-    let mut event_loop = match EventLoop::new() {
+impl VersionedOptions for WindowOptions {
+    const VERSION: u32 = 1;
+    const NAME: &'static str = "window options";
+
+    fn validate(&self) -> Result<(), Invalid> {
+        for (field, size) in [("width", self.width), ("height", self.height)] {
+            if !(1..=MAX_WINDOW_SIZE).contains(&size) {
+                return Err(Invalid::new(field, format!("must be from 1 to {MAX_WINDOW_SIZE}")));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn open_window(options: WindowOptions) -> *mut WindowState {
+    let mut event_loop = match EventLoop::with_user_event().build() {
         Ok(loop_handle) => loop_handle,
         Err(err) => {
-            eprintln!("Failed to create event loop: {err}");
+            avtools_log::error!("Failed to create event loop: {err}");
+            error::fail(ERR_EVENT_LOOP, format!("failed to create event loop: {err}"));
             return ptr::null_mut();
         }
     };
-    let mut app = WindowApp::new(width, height, title);
+    error::clear();
+    let mut app = WindowApp::new(options.width, options.height, options.title);
+    app.center = options.center;
+    let _ = event_loop.pump_app_events(Some(Duration::ZERO), &mut app);
+
+    into_handle(WindowState {
+        magic: STATE_MAGIC,
+        proxy: event_loop.create_proxy(),
+        event_loop: Some(event_loop),
+        app,
+        parent: ptr::null_mut(),
+    })
+}
 
-    // Pump a few times to ensure the window is created.
-    for _ in 0..8 {
-        let _ = event_loop.pump_app_events(Some(Duration::ZERO), &mut app);
-        if app.window.is_some() {
-            break;
+/// Create a window embedded in `parent_state` at (`x`, `y`) in logical pixels,
+/// e.g. to host a system WebView or a plugin editor. Uses winit's parent-window
+/// support (Windows, macOS, X11). The child shares the parent's event loop; it
+/// is closed automatically when the parent is destroyed, but its handle must
+/// still be released with destroy_window. As with create_window, call
+/// wait_for_window_ready before using its handles.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn create_child_window(
+    parent_state: *mut WindowState,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+) -> *mut WindowState {
+    if !is_live(parent_state) {
+        return ptr::null_mut();
+    }
+    let parent = unsafe { &mut *parent_state };
+    if parent.app.window.is_none() {
+        pump_once(parent);
+    }
+    let parent_handle = match parent.app.window.as_ref().map(|w| w.window_handle()) {
+        Some(Ok(handle)) => handle.as_raw(),
+        _ => {
+            error::fail(ERR_NO_WINDOW, "parent has no native window yet");
+            return ptr::null_mut();
         }
+    };
+    error::clear();
+
+    let mut app = WindowApp::new(width, height, parent.app.title.clone());
+    app.child = Some(ChildConfig {
+        parent_handle,
+        x,
+        y,
+    });
+    let child = into_handle(WindowState {
+        magic: STATE_MAGIC,
+        event_loop: None,
+        proxy: parent.proxy.clone(),
+        app,
+        parent: parent_state,
+    });
+    parent.app.children.push(child);
+
+    // The child window is created from the parent's about_to_wait.
+    pump_events(parent);
+    child
+}
+
+/// Open another top-level window on `state`'s event loop, e.g. a parameter
+/// panel next to a canvas; winit allows only one event loop per process on
+/// macOS. Its events are queued on `state` with `window_token` set to the
+/// returned token (the state's own window is token 0), and it is closed by
+/// destroy_window_by_token, by the user (after its close record is queued) or
+/// with `state`. While additional windows are open, a close request on
+/// `state`'s own window hides it instead of stopping the loop, which then
+/// stops once the last additional window closes. `state` must
+/// be a top-level window from create_window or create_window_ex.
+///
+/// Returns the token, or 0 for a bad state (INVALID_HANDLE), a child state
+/// (INVALID_ARGUMENT) or if the window cannot be created (ERR_WINDOW_CREATE).
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn create_additional_window(
+    state: *mut WindowState,
+    width: u32,
+    height: u32,
+    title_ptr: *const u8,
+    title_len: u32,
+) -> u32 {
+    if !is_live(state) {
+        return 0;
+    }
+    let state = unsafe { &mut *state };
+    if state.event_loop.is_none() {
+        error::fail(error::INVALID_ARGUMENT, "additional windows need a top-level window's state");
+        return 0;
+    }
+    if state.app.window.is_none() {
+        pump_once(state);
+    }
+    let title = if title_ptr.is_null() || title_len == 0 {
+        state.app.title.clone()
+    } else {
+        let slice = unsafe { slice::from_raw_parts(title_ptr, title_len as usize) };
+        String::from_utf8_lossy(slice).to_string()
+    };
+    let mut window = WindowApp::new(width, height, title);
+    window.token = state.app.next_token;
+    state.app.next_token += 1;
+    let token = window.token;
+    state.app.additional.push(window);
+    error::clear();
+
+    // Created from the owning window's about_to_wait.
+    pump_events(state);
+    if state.app.additional.last().is_some_and(|w| w.window.is_some()) {
+        token
+    } else {
+        state.app.additional.retain(|w| w.token != token);
+        0
     }
+}
+
+/// Embed the window in a window of another application or toolkit, e.g. an
+/// Electron host or a plugin editor, by reparenting it with the platform's
+/// API: XReparentWindow with `handle` an X11 window id, SetParent with an
+/// HWND, or moving the content view into `handle` as an NSView on macOS.
+/// `system` is the parent's window system as get_window_system numbers it and
+/// must match this window's. `display` is the Xlib Display* to reparent
+/// through, 0 for this window's own connection; it is unused elsewhere. If
+/// the native window does not exist yet, the parent is applied when it is
+/// created.
+///
+/// Returns 0, or -1 with INVALID_HANDLE for a bad state, INVALID_ARGUMENT for
+/// a null handle, a mismatched system or a child window, UNSUPPORTED on
+/// Wayland and OS_ERROR if the platform call fails.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn set_parent_window_handle(state: *mut WindowState, handle: usize, display: usize, system: u32) -> i32 {
+    if !is_live(state) {
+        return -1;
+    }
+    let state = unsafe { &mut *state };
+    if handle == 0 {
+        error::fail(error::INVALID_ARGUMENT, "parent handle is null");
+        return -1;
+    }
+    if state.app.child.is_some() {
+        error::fail(error::INVALID_ARGUMENT, "child windows already have a parent");
+        return -1;
+    }
+    let parent = ParentWindow { handle, display, system };
+    state.app.parent = Some(parent);
+    if state.app.cached_window_handle == 0 {
+        return 0;
+    }
+    match error::record(state.app.apply_parent(parent)) {
+        Some(()) => 0,
+        None => -1,
+    }
+}
 
-    Box::into_raw(Box::new(WindowState { event_loop, app }))
+/// Pump the event loop driving `state` (the parent's, for a child window)
+/// until its native window exists or `timeout_ms` passes. Returns 0 once it
+/// exists, -1 on timeout (ERR_NO_WINDOW) or an invalid state.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn wait_for_window_ready(state: *mut WindowState, timeout_ms: u32) -> i32 {
+    if !is_live(state) {
+        return -1;
+    }
+    let state = unsafe { &mut *state };
+    let deadline = Instant::now() + Duration::from_millis(timeout_ms as u64);
+    loop {
+        pump_once(state);
+        if state.app.window.is_some() {
+            error::clear();
+            return 0;
+        }
+        if Instant::now() >= deadline {
+            error::fail(ERR_NO_WINDOW, format!("window not ready after {timeout_ms} ms"));
+            return -1;
+        }
+        thread::sleep(Duration::from_millis(1));
+    }
 }
 
 #[no_mangle]
+#[catch_panic]
 pub extern "C" fn get_raw_window_handle(state: *mut WindowState) -> usize {
-    if state.is_null() {
+    if !is_live(state) {
         return 0;
     }
     let state = unsafe { &mut *state };
@@ -294,8 +1227,9 @@ pub extern "C" fn get_raw_window_handle(state: *mut WindowState) -> usize {
 }
 
 #[no_mangle]
+#[catch_panic]
 pub extern "C" fn get_raw_display_handle(state: *mut WindowState) -> usize {
-    if state.is_null() {
+    if !is_live(state) {
         return 0;
     }
     let state = unsafe { &mut *state };
@@ -309,8 +1243,9 @@ pub extern "C" fn get_raw_display_handle(state: *mut WindowState) -> usize {
 }
 
 #[no_mangle]
+#[catch_panic]
 pub extern "C" fn get_window_system(state: *mut WindowState) -> u32 {
-    if state.is_null() {
+    if !is_live(state) {
         return 0;
     }
     let state = unsafe { &mut *state };
@@ -324,60 +1259,1181 @@ pub extern "C" fn get_window_system(state: *mut WindowState) -> u32 {
     state.app.cached_window_system
 }
 
+/// Pump the window and hand over the events queued since the last poll, as
+/// a JSON array (or MessagePack, see set_event_format). Returns the byte
+/// length, or 0 if there are none or they do not fit in `buf_cap`; events
+/// carry file paths of any length, so in the latter case they stay queued,
+/// window_last_error_code is ERR_EVENTS_TOO_LARGE (104) with the length they
+/// need, and the caller should poll again with a larger buffer.
 #[no_mangle]
+#[catch_panic]
 pub extern "C" fn poll_events(state: *mut WindowState, buf_ptr: *mut u8, buf_cap: u32) -> u32 {
-    if state.is_null() {
+    if !is_live(state) {
         return 0;
     }
     let state = unsafe { &mut *state };
-    let _ = state
-        .event_loop
-        .pump_app_events(Some(Duration::ZERO), &mut state.app);
-    let payload = state.app.take_events_json();
-    if payload.is_empty() || buf_ptr.is_null() || buf_cap == 0 {
-        return 0;
-    }
-    if payload.len() > buf_cap as usize {
-        return 0;
-    }
-    unsafe {
-        ptr::copy_nonoverlapping(payload.as_ptr(), buf_ptr, payload.len());
-    }
-    payload.len() as u32
+    pump_events(state);
+    state.app.take_events(state.app.event_format, buf_ptr, buf_cap)
 }
 
+/// Like poll_events, but only returns events whose EVENT_MASK_* bit is set in
+/// `event_mask`. Everything else is discarded, not left for the next poll.
 #[no_mangle]
-pub extern "C" fn resize_window(state: *mut WindowState, width: u32, height: u32) {
-    if state.is_null() {
-        return;
+#[catch_panic]
+pub extern "C" fn poll_events_filtered(
+    state: *mut WindowState,
+    event_mask: u64,
+    buf_ptr: *mut u8,
+    buf_cap: u32,
+) -> u32 {
+    if !is_live(state) {
+        return 0;
     }
     let state = unsafe { &mut *state };
-    if let Some(window) = state.app.window.as_ref() {
-        let _ = window.request_inner_size(LogicalSize::new(width as f64, height as f64));
-    }
+    pump_events(state);
+    state.app.take_events_filtered(event_mask, buf_ptr, buf_cap)
 }
 
+/// Deliver events whose EVENT_MASK_* bit is set in `event_mask` to `cb` one
+/// at a time, as they happen while the window is pumped (by poll_events and
+/// the other calls that pump it), instead of queueing them: `cb` gets one
+/// record in the set_event_format format, a JSON object or MessagePack map,
+/// valid only during the call. Other events stay queued for poll_events.
+/// Subscribing again replaces the callback and mask.
+///
+/// Returns 0, or -1 for a bad state (INVALID_HANDLE) or a null callback
+/// (INVALID_ARGUMENT).
 #[no_mangle]
-pub extern "C" fn get_window_size(state: *mut WindowState, out_w: *mut u32, out_h: *mut u32) {
-    if state.is_null() || out_w.is_null() || out_h.is_null() {
-        return;
-    }
-    let state = unsafe { &mut *state };
-    if let Some(window) = state.app.window.as_ref() {
-        let size = window.inner_size();
-        unsafe {
-            *out_w = size.width;
-            *out_h = size.height;
-        }
+#[catch_panic]
+pub extern "C" fn subscribe_events(
+    state: *mut WindowState,
+    event_mask: u64,
+    cb: Option<extern "C" fn(*const u8, u32)>,
+) -> i32 {
+    if !is_live(state) {
+        return -1;
     }
+    let Some(cb) = cb else {
+        error::fail(error::INVALID_ARGUMENT, "event callback is null");
+        return -1;
+    };
+    unsafe { (*state).app.subscription = Some((event_mask, cb)) };
+    0
 }
 
+/// Remove the subscribe_events callback; events are queued for poll_events
+/// again. Returns 0, or -1 for a bad state.
 #[no_mangle]
+#[catch_panic]
+pub extern "C" fn unsubscribe_events(state: *mut WindowState) -> i32 {
+    if !is_live(state) {
+        return -1;
+    }
+    unsafe { (*state).app.subscription = None };
+    0
+}
+
+/// Queue `data` as a user event on the event loop driving `state`, waking it
+/// if it is waiting. The bytes come back from poll_events as a "user" record.
+/// Only the proxy is touched, so this may be called from any thread while the
+/// window is alive. A child window shares its parent's loop, so its user
+/// events are delivered to the parent.
+///
+/// Returns 0 on success, -1 for a null state, -2 if the loop has exited.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn send_window_user_event(
+    state: *mut WindowState,
+    data_ptr: *const u8,
+    data_len: u32,
+) -> i32 {
+    if !is_live(state) {
+        return -1;
+    }
+    let data = if data_ptr.is_null() || data_len == 0 {
+        Vec::new()
+    } else {
+        unsafe { slice::from_raw_parts(data_ptr, data_len as usize) }.to_vec()
+    };
+    let proxy = unsafe { &(*state).proxy };
+    match proxy.send_event(data) {
+        Ok(()) => 0,
+        Err(_) => -2,
+    }
+}
+
+/// Like poll_events, but always encodes the events as MessagePack.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn poll_events_msgpack(state: *mut WindowState, buf_ptr: *mut u8, buf_cap: u32) -> u32 {
+    if !is_live(state) {
+        return 0;
+    }
+    let state = unsafe { &mut *state };
+    pump_events(state);
+    state.app.take_events(EVENT_FORMAT_MSGPACK, buf_ptr, buf_cap)
+}
+
+/// Choose the encoding used by poll_events: 0 = JSON (default), 1 = MessagePack.
+/// Returns 0 on success, -1 for a null state or unknown format.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn set_event_format(state: *mut WindowState, format: u8) -> i32 {
+    if !is_live(state) || format > EVENT_FORMAT_MSGPACK {
+        return -1;
+    }
+    unsafe { (*state).app.event_format = format };
+    0
+}
+
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn resize_window(state: *mut WindowState, width: u32, height: u32) {
+    if !is_live(state) {
+        return;
+    }
+    let state = unsafe { &mut *state };
+    if let Some(window) = state.app.window.as_ref() {
+        let _ = window.request_inner_size(LogicalSize::new(width as f64, height as f64));
+    }
+}
+
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn get_window_size(state: *mut WindowState, out_w: *mut u32, out_h: *mut u32) {
+    if !is_live(state) || out_w.is_null() || out_h.is_null() {
+        return;
+    }
+    let state = unsafe { &mut *state };
+    if let Some(window) = state.app.window.as_ref() {
+        let size = window.inner_size();
+        unsafe {
+            *out_w = size.width;
+            *out_h = size.height;
+        }
+    }
+}
+
+/// Move the window so its outer top-left corner (title bar included) is at
+/// (`x`, `y`) in physical screen pixels, e.g. onto a second monitor (see
+/// list_monitors for their positions). Before the window exists the position
+/// is used when it is created, replacing the `center` option.
+///
+/// Returns 0, or -1 for a bad state or on Wayland, where clients cannot place
+/// their windows (UNSUPPORTED).
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn set_window_position(state: *mut WindowState, x: i32, y: i32) -> i32 {
+    if !is_live(state) {
+        return -1;
+    }
+    let state = unsafe { &mut *state };
+    if state.app.window.is_none() {
+        pump_once(state);
+    }
+    if state.app.cached_window_system == 2 {
+        error::fail(error::UNSUPPORTED, "Wayland does not let clients position windows");
+        return -1;
+    }
+    state.app.center = false;
+    state.app.initial_position = Some((x, y));
+    if let Some(window) = state.app.window.as_ref() {
+        window.set_outer_position(PhysicalPosition::new(x, y));
+    }
+    0
+}
+
+/// The window's outer top-left corner in physical screen pixels, written to
+/// `out_x`/`out_y`. Returns 0, or -1 with (-1, -1) written for a window not
+/// created yet (ERR_NO_WINDOW) or on Wayland (UNSUPPORTED).
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn get_window_position(state: *mut WindowState, out_x: *mut i32, out_y: *mut i32) -> i32 {
+    if !is_live(state) || out_x.is_null() || out_y.is_null() {
+        return -1;
+    }
+    let state = unsafe { &mut *state };
+    if state.app.window.is_none() {
+        pump_once(state);
+    }
+    let position = match state.app.window.as_ref().map(|w| w.outer_position()) {
+        Some(Ok(position)) => Some((position.x, position.y)),
+        Some(Err(_)) => {
+            error::fail(error::UNSUPPORTED, "window position is not available on this platform");
+            None
+        }
+        None => {
+            error::fail(ERR_NO_WINDOW, "window not created yet");
+            None
+        }
+    };
+    let (x, y) = position.unwrap_or((-1, -1));
+    unsafe {
+        *out_x = x;
+        *out_y = y;
+    }
+    if position.is_some() {
+        0
+    } else {
+        -1
+    }
+}
+
+/// get_raw_window_handle for one of `state`'s windows: 0 for its own, else a
+/// create_additional_window token. Returns 0 for a bad state or unknown token.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn get_raw_window_handle_by_token(state: *mut WindowState, token: u32) -> usize {
+    if !is_live(state) {
+        return 0;
+    }
+    if token == 0 {
+        return get_raw_window_handle(state);
+    }
+    let state = unsafe { &mut *state };
+    match state.app.window_for_token(token) {
+        Some(app) => app.cached_window_handle,
+        None => {
+            error::fail(error::INVALID_ARGUMENT, format!("unknown window token {token}"));
+            0
+        }
+    }
+}
+
+/// resize_window for one of `state`'s windows (see get_window_size_by_token).
+/// Returns 0, or -1 for a bad state or unknown token.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn resize_window_by_token(state: *mut WindowState, token: u32, width: u32, height: u32) -> i32 {
+    if !is_live(state) {
+        return -1;
+    }
+    let state = unsafe { &mut *state };
+    let Some(app) = state.app.window_for_token(token) else {
+        error::fail(error::INVALID_ARGUMENT, format!("unknown window token {token}"));
+        return -1;
+    };
+    if let Some(window) = app.window.as_ref() {
+        let _ = window.request_inner_size(LogicalSize::new(width as f64, height as f64));
+    }
+    0
+}
+
+/// Close an additional window of `state`; `state`'s own window (token 0)
+/// goes with destroy_window. The token is not reused. Returns 0, or -1 for
+/// a bad state or unknown token.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn destroy_window_by_token(state: *mut WindowState, token: u32) -> i32 {
+    if !is_live(state) {
+        return -1;
+    }
+    let state = unsafe { &mut *state };
+    let before = state.app.additional.len();
+    state.app.additional.retain(|w| token == 0 || w.token != token);
+    if state.app.additional.len() == before {
+        error::fail(error::INVALID_ARGUMENT, format!("unknown additional window token {token}"));
+        return -1;
+    }
+    0
+}
+
+/// get_window_size for one of `state`'s windows: 0 for its own, else a
+/// create_additional_window token. Returns 0, or -1 for a bad state, an
+/// unknown token (INVALID_ARGUMENT) or a window not created yet.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn get_window_size_by_token(
+    state: *mut WindowState,
+    token: u32,
+    out_w: *mut u32,
+    out_h: *mut u32,
+) -> i32 {
+    if !is_live(state) || out_w.is_null() || out_h.is_null() {
+        return -1;
+    }
+    let state = unsafe { &mut *state };
+    let Some(app) = state.app.window_for_token(token) else {
+        error::fail(error::INVALID_ARGUMENT, format!("unknown window token {token}"));
+        return -1;
+    };
+    let Some(window) = app.window.as_ref() else {
+        return -1;
+    };
+    let size = window.inner_size();
+    unsafe {
+        *out_w = size.width;
+        *out_h = size.height;
+    }
+    0
+}
+
+/// Number of event loop iterations (about_to_wait calls) so far.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn get_frame_count(state: *mut WindowState) -> u64 {
+    if !is_live(state) {
+        return 0;
+    }
+    unsafe { (*state).app.frame_counter }
+}
+
+/// Event loop iterations per second over the last second (at most the last
+/// 60 iterations are kept).
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn get_frames_per_second(state: *mut WindowState) -> f64 {
+    if !is_live(state) {
+        return 0.0;
+    }
+    unsafe { (*state).app.frames_per_second() }
+}
+
+/// Refresh rate in Hz of the monitor the window is on, or 0.0 if unknown.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn get_display_refresh_rate(state: *mut WindowState) -> f64 {
+    if !is_live(state) {
+        return 0.0;
+    }
+    let state = unsafe { &mut *state };
+    if state.app.window.is_none() {
+        pump_once(state);
+    }
+    state.app.update_monitor();
+    state.app.refresh_rate_hz
+}
+
+/// Frame interval in µs for the window's monitor (1e6 / refresh rate), or 0 if unknown.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn get_vsync_interval_us(state: *mut WindowState) -> u64 {
+    if !is_live(state) {
+        return 0;
+    }
+    get_display_refresh_rate(state);
+    unsafe { (*state).app.vsync_interval_us }
+}
+
+/// Windows 11 corner rounding: 0=default, 1=donotround, 2=round, 3=roundsmall.
+///
+/// Returns 0 on success, -1 if the window is missing or the OS does not support
+/// the preference (Windows 10 and earlier), -2 on non-Windows platforms, -3 for
+/// an invalid `pref`.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn set_window_corner_preference(state: *mut WindowState, pref: u32) -> i32 {
+    if !is_live(state) {
+        return -1;
+    }
+    if pref > 3 {
+        return -3;
+    }
+    #[cfg(target_os = "windows")]
+    {
+        let state = unsafe { &mut *state };
+        if state.app.cached_window_handle == 0 {
+            pump_once(state);
+        }
+        let hwnd = state.app.cached_window_handle;
+        if hwnd == 0 || !win32::set_corner_preference(hwnd, pref) {
+            return -1;
+        }
+        0
+    }
+    #[cfg(not(target_os = "windows"))]
+    {
+        -2
+    }
+}
+
+/// Show only the given client-area rectangle in the Windows taskbar thumbnail
+/// (ITaskbarList3::SetThumbnailClip).
+///
+/// Returns 0 on success, -1 on non-Windows platforms or without a window, -2
+/// if the COM call fails.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn set_taskbar_thumbnail_clip(
+    state: *mut WindowState,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+) -> i32 {
+    #[cfg(target_os = "windows")]
+    {
+        let rect = windows::Win32::Foundation::RECT {
+            left: x as i32,
+            top: y as i32,
+            right: x.saturating_add(width) as i32,
+            bottom: y.saturating_add(height) as i32,
+        };
+        apply_thumbnail_clip(state, Some(rect))
+    }
+    #[cfg(not(target_os = "windows"))]
+    {
+        let _ = (state, x, y, width, height);
+        -1
+    }
+}
+
+/// Restore the default full-window taskbar thumbnail.
+///
+/// Returns 0 on success, -1 on non-Windows platforms or without a window, -2
+/// if the COM call fails.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn clear_taskbar_thumbnail_clip(state: *mut WindowState) -> i32 {
+    #[cfg(target_os = "windows")]
+    {
+        apply_thumbnail_clip(state, None)
+    }
+    #[cfg(not(target_os = "windows"))]
+    {
+        let _ = state;
+        -1
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn apply_thumbnail_clip(
+    state: *mut WindowState,
+    clip: Option<windows::Win32::Foundation::RECT>,
+) -> i32 {
+    if !is_live(state) {
+        return -1;
+    }
+    let state = unsafe { &mut *state };
+    if state.app.cached_window_handle == 0 {
+        pump_once(state);
+    }
+    let hwnd = state.app.cached_window_handle;
+    if hwnd == 0 {
+        return -1;
+    }
+    match win32::set_thumbnail_clip(hwnd, clip) {
+        Ok(()) => 0,
+        Err(err) => {
+            avtools_log::warn!("SetThumbnailClip failed: {err}");
+            -2
+        }
+    }
+}
+
+/// Read the window's client-area pixels as tightly packed RGBA8 rows, top row
+/// first: CGWindowListCreateImage on macOS (needs Screen Recording
+/// permission), PrintWindow/BitBlt on Windows, XGetImage on X11. Wayland
+/// clients cannot read their surface back, so it always fails there.
+///
+/// Returns the byte count needed (width * height * 4) and copies only when it
+/// fits in `buf_cap`; `out_width`/`out_height` (nullable) are set either way.
+/// Returns -1 without a window or if the capture fails.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn capture_window_pixels(
+    state: *mut WindowState,
+    buf_ptr: *mut u8,
+    buf_cap: u32,
+    out_width: *mut u32,
+    out_height: *mut u32,
+) -> i32 {
+    if !is_live(state) {
+        return -1;
+    }
+    let state = unsafe { &mut *state };
+    if state.app.cached_window_handle == 0 {
+        pump_once(state);
+    }
+    let handle = state.app.cached_window_handle;
+    if handle == 0 {
+        error::fail(ERR_NO_WINDOW, "window not created yet");
+        return -1;
+    }
+    #[cfg(target_os = "macos")]
+    let captured = macos::capture_pixels(handle);
+    #[cfg(target_os = "windows")]
+    let captured = win32::capture_pixels(handle);
+    #[cfg(all(unix, not(target_os = "macos")))]
+    let captured = if state.app.cached_window_system == 1 {
+        x11::capture_pixels(state.app.cached_display_handle, handle)
+    } else {
+        error::fail(error::UNSUPPORTED, "window capture is not available on Wayland");
+        return -1;
+    };
+    let Some((width, height, pixels)) = captured else {
+        error::fail(ERR_CAPTURE, "platform window capture failed");
+        return -1;
+    };
+    if !out_width.is_null() {
+        unsafe { *out_width = width };
+    }
+    if !out_height.is_null() {
+        unsafe { *out_height = height };
+    }
+    unsafe { buffer::write(&pixels, buf_ptr, buf_cap) as i32 }
+}
+
+/// Exclude the window from screen captures and recordings (winit
+/// set_content_protected). The setting survives window recreation.
+///
+/// Returns 0 on success, -1 for a null state or on platforms other than
+/// macOS and Windows.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn set_content_protection(state: *mut WindowState, enabled: u8) -> i32 {
+    if !is_live(state) || !cfg!(any(target_os = "macos", target_os = "windows")) {
+        return -1;
+    }
+    let state = unsafe { &mut *state };
+    state.app.content_protected = enabled != 0;
+    if let Some(window) = state.app.window.as_ref() {
+        window.set_content_protected(state.app.content_protected);
+    }
+    0
+}
+
+/// Keep the pointer inside the window, e.g. for the length of a drag
+/// (winit CursorGrabMode::Confined), or release it with `captured` 0.
+///
+/// Returns 0 on success, -1 for a null state or missing window, -2 if the
+/// platform refused the grab.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn set_pointer_capture(state: *mut WindowState, captured: u8) -> i32 {
+    if !is_live(state) {
+        return -1;
+    }
+    let state = unsafe { &mut *state };
+    if state.app.window.is_none() {
+        return -1;
+    }
+    match state.app.set_pointer_capture(captured != 0) {
+        Ok(()) => 0,
+        Err(err) => {
+            avtools_log::warn!("set_cursor_grab failed: {err}");
+            -2
+        }
+    }
+}
+
+/// Capture the pointer automatically on left-button down and release it on
+/// left-button up (off by default). Returns -1 for a null state.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn set_auto_capture_on_drag(state: *mut WindowState, enabled: u8) -> i32 {
+    if !is_live(state) {
+        return -1;
+    }
+    unsafe { (*state).app.auto_capture_on_drag = enabled != 0 };
+    0
+}
+
+/// Hand a drag that just started over a custom title bar to the window
+/// manager, which moves the window until the button is released (winit
+/// drag_window). Call from the left-button-down handler.
+///
+/// Returns 0 on success, -1 for a null state, a missing window, or a platform
+/// that does not support it (UNSUPPORTED; some Wayland compositors).
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn begin_window_drag_move(state: *mut WindowState) -> i32 {
+    if !is_live(state) {
+        return -1;
+    }
+    let state = unsafe { &mut *state };
+    let Some(window) = state.app.window.as_ref() else {
+        error::fail(ERR_NO_WINDOW, "window not created yet");
+        return -1;
+    };
+    drag_result(window.drag_window())
+}
+
+/// Like begin_window_drag_move, but the window manager resizes the window
+/// from the edge or corner `direction` (DRAG_NORTH 1 clockwise through
+/// DRAG_NORTH_WEST 8; DRAG_CENTER 0 moves it instead), for frameless windows
+/// that draw their own borders (winit drag_resize_window).
+///
+/// Returns 0 on success, -1 for a null state, a missing window, an unknown
+/// direction (INVALID_ARGUMENT) or an unsupported platform (UNSUPPORTED).
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn begin_window_drag_resize(state: *mut WindowState, direction: u32) -> i32 {
+    if !is_live(state) {
+        return -1;
+    }
+    let state = unsafe { &mut *state };
+    let Some(window) = state.app.window.as_ref() else {
+        error::fail(ERR_NO_WINDOW, "window not created yet");
+        return -1;
+    };
+    let direction = match direction {
+        DRAG_CENTER => return drag_result(window.drag_window()),
+        DRAG_NORTH => ResizeDirection::North,
+        DRAG_NORTH_EAST => ResizeDirection::NorthEast,
+        DRAG_EAST => ResizeDirection::East,
+        DRAG_SOUTH_EAST => ResizeDirection::SouthEast,
+        DRAG_SOUTH => ResizeDirection::South,
+        DRAG_SOUTH_WEST => ResizeDirection::SouthWest,
+        DRAG_WEST => ResizeDirection::West,
+        DRAG_NORTH_WEST => ResizeDirection::NorthWest,
+        other => {
+            error::fail(error::INVALID_ARGUMENT, format!("unknown drag direction {other}"));
+            return -1;
+        }
+    };
+    drag_result(window.drag_resize_window(direction))
+}
+
+fn drag_result(result: Result<(), ExternalError>) -> i32 {
+    match result {
+        Ok(()) => 0,
+        Err(ExternalError::NotSupported(_)) => {
+            error::fail(error::UNSUPPORTED, "window drags are not supported on this platform");
+            -1
+        }
+        Err(err) => {
+            error::fail(error::OS_ERROR, format!("window drag failed: {err}"));
+            -1
+        }
+    }
+}
+
+/// Make parts of the window click-through, e.g. the transparent areas of a
+/// frameless HUD. `cb(x, y)` is called with the cursor position in physical
+/// client pixels whenever it moves, and while clicks pass through, on every
+/// event loop iteration; it returns 1 to capture clicks there and 0 to let
+/// them through (winit set_cursor_hittest, only called when the answer
+/// changes). The callback runs on the thread pumping the window, inside
+/// poll_events. A null `cb` removes it and captures clicks everywhere again.
+///
+/// Returns 0 on success, -1 for a null state or on Wayland, which cannot
+/// report the cursor position while clicks pass through.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn set_hit_test_callback(
+    state: *mut WindowState,
+    cb: Option<extern "C" fn(x: f64, y: f64) -> u8>,
+) -> i32 {
+    if !is_live(state) {
+        return -1;
+    }
+    let state = unsafe { &mut *state };
+    if state.app.cached_window_handle == 0 {
+        pump_once(state);
+    }
+    if cb.is_some() && cfg!(all(unix, not(target_os = "macos"))) && state.app.cached_window_system == 2 {
+        error::fail(error::UNSUPPORTED, "hit testing is not available on Wayland");
+        return -1;
+    }
+    state.app.hit_test = cb;
+    match cb {
+        Some(_) => {
+            let (x, y) = state.app.last_cursor;
+            state.app.update_hit_test(x, y);
+        }
+        None => {
+            if state.app.cursor_hittest.take() == Some(false) {
+                if let Some(window) = state.app.window.as_ref() {
+                    let _ = window.set_cursor_hittest(true);
+                }
+            }
+        }
+    }
+    0
+}
+
+/// Set the window's accessible name. The title doubles as the accessible
+/// name on every platform; on macOS the content view's accessibility label
+/// is set as well.
+///
+/// Returns 0 on success, -1 for a null state or missing window.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn set_accessibility_label(
+    state: *mut WindowState,
+    label_ptr: *const u8,
+    label_len: u32,
+) -> i32 {
+    if !is_live(state) {
+        return -1;
+    }
+    let state = unsafe { &mut *state };
+    let label = if label_ptr.is_null() || label_len == 0 {
+        String::new()
+    } else {
+        let slice = unsafe { slice::from_raw_parts(label_ptr, label_len as usize) };
+        String::from_utf8_lossy(slice).to_string()
+    };
+    if state.app.window.is_none() {
+        pump_once(state);
+    }
+    let Some(window) = state.app.window.as_ref() else {
+        return -1;
+    };
+    window.set_title(&label);
+    state.app.title = label;
+    #[cfg(target_os = "macos")]
+    macos::set_accessibility_label(state.app.cached_window_handle, &state.app.title);
+    0
+}
+
+/// Accessibility role hint for the window content: 0 = platform default,
+/// 1 = group, 2 = image, 3 = application, 4 = layout area. Only macOS exposes
+/// this (NSAccessibility); winit has no cross-platform accessibility API.
+///
+/// Returns 0 on success, -1 on other platforms or without a window, -3 for an
+/// invalid role.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn set_accessibility_role(state: *mut WindowState, role: u32) -> i32 {
+    if !is_live(state) {
+        return -1;
+    }
+    if role > 4 {
+        return -3;
+    }
+    #[cfg(target_os = "macos")]
+    {
+        let state = unsafe { &mut *state };
+        if state.app.cached_window_handle == 0 {
+            pump_once(state);
+        }
+        if macos::set_accessibility_role(state.app.cached_window_handle, role) {
+            0
+        } else {
+            -1
+        }
+    }
+    #[cfg(not(target_os = "macos"))]
+    {
+        -1
+    }
+}
+
+fn display_color_space(state: &mut WindowState) -> DisplayColorSpace {
+    if state.app.cached_window_handle == 0 {
+        pump_once(state);
+    }
+    #[cfg(any(target_os = "windows", target_os = "macos"))]
+    {
+        let handle = state.app.cached_window_handle;
+        #[cfg(target_os = "windows")]
+        let info = win32::display_color_space(handle);
+        #[cfg(target_os = "macos")]
+        let info = macos::display_color_space(handle);
+        info.unwrap_or_else(DisplayColorSpace::unknown)
+    }
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    {
+        DisplayColorSpace::unknown()
+    }
+}
+
+/// Record whether the renderer should create an HDR swapchain for this window.
+/// winit has no HDR mode of its own, so this is a hint read back by renderers
+/// through get_display_color_space.
+///
+/// Returns 0 on success, -1 when enabling on a display without HDR/EDR support,
+/// -2 for a null state.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn set_hdr_hint(state: *mut WindowState, enabled: u8) -> i32 {
+    if !is_live(state) {
+        return -2;
+    }
+    let state = unsafe { &mut *state };
+    if enabled != 0 && !display_color_space(state).hdr_supported {
+        return -1;
+    }
+    state.app.hdr_hint = enabled != 0;
+    0
+}
+
+/// Record which GPU the renderer should pick on dual-GPU machines: 0 = default,
+/// 1 = low power (integrated), 2 = high performance (discrete). The surface is
+/// created outside this library, so this is a hint for the adapter selector,
+/// read back with get_gpu_preference.
+///
+/// Returns 0 on success, -1 for an unknown preference, -2 for an invalid state.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn set_gpu_preference(state: *mut WindowState, pref: u32) -> i32 {
+    if !is_live(state) {
+        return -2;
+    }
+    if pref > GPU_PREFERENCE_HIGH_PERFORMANCE {
+        error::fail(error::INVALID_ARGUMENT, format!("unknown GPU preference {pref}"));
+        return -1;
+    }
+    error::clear();
+    unsafe { (*state).app.gpu_preference = pref };
+    0
+}
+
+/// The preference set by set_gpu_preference (0 = default, also for an invalid
+/// state).
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn get_gpu_preference(state: *mut WindowState) -> u32 {
+    if !is_live(state) {
+        return GPU_PREFERENCE_DEFAULT;
+    }
+    unsafe { (*state).app.gpu_preference }
+}
+
+#[derive(Serialize)]
+struct ColorSpaceReport {
+    #[serde(flatten)]
+    display: DisplayColorSpace,
+    hdr_hint: bool,
+}
+
+/// JSON `{hdr_supported, max_luminance_nits, edr_headroom, color_gamut, hdr_hint}`
+/// for the display showing the window. Returns the byte length needed; nothing
+/// is written if `buf_cap` is too small.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn get_display_color_space(
+    state: *mut WindowState,
+    buf_ptr: *mut u8,
+    buf_cap: u32,
+) -> u32 {
+    if !is_live(state) {
+        return 0;
+    }
+    let state = unsafe { &mut *state };
+    let report = ColorSpaceReport {
+        display: display_color_space(state),
+        hdr_hint: state.app.hdr_hint,
+    };
+    let payload = serde_json::to_vec(&report).unwrap_or_default();
+    unsafe { buffer::write(&payload, buf_ptr, buf_cap) }
+}
+
+#[derive(Serialize)]
+struct MonitorReport {
+    name: Option<String>,
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+    scale_factor: f64,
+    bit_depth: u16,
+    refresh_rate_hz: f64,
+    is_primary: bool,
+    color_space: &'static str,
+}
+
+impl MonitorReport {
+    fn new(monitor: &MonitorHandle, primary: Option<&MonitorHandle>) -> Self {
+        let position = monitor.position();
+        let size = monitor.size();
+        // Best mode: highest refresh rate, then deepest colour, then largest.
+        let best = monitor.video_modes().max_by_key(|mode| {
+            (
+                mode.refresh_rate_millihertz(),
+                mode.bit_depth(),
+                mode.size().width as u64 * mode.size().height as u64,
+            )
+        });
+        let bit_depth = best.as_ref().map_or(0, |mode| mode.bit_depth());
+        let refresh_mhz = monitor
+            .refresh_rate_millihertz()
+            .or_else(|| best.as_ref().map(|mode| mode.refresh_rate_millihertz()));
+        Self {
+            name: monitor.name(),
+            x: position.x,
+            y: position.y,
+            width: size.width,
+            height: size.height,
+            scale_factor: monitor.scale_factor(),
+            bit_depth,
+            refresh_rate_hz: refresh_mhz.map_or(0.0, |mhz| mhz as f64 / 1000.0),
+            is_primary: primary == Some(monitor),
+            // winit reports no gamut per monitor; 10+ bits per channel is
+            // taken as an HDR10-capable output.
+            color_space: match bit_depth {
+                0 => "unknown",
+                30.. => "hdr10",
+                _ => "srgb",
+            },
+        }
+    }
+}
+
+/// JSON array of every monitor: `{name, x, y, width, height, scale_factor,
+/// bit_depth, refresh_rate_hz, is_primary, color_space}`. `bit_depth` comes
+/// from the monitor's best video mode and `color_space` is derived from it
+/// ("srgb", "hdr10" or "unknown"). Returns the byte length needed; nothing is
+/// written if `buf_cap` is too small.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn list_monitors(state: *mut WindowState, buf_ptr: *mut u8, buf_cap: u32) -> u32 {
+    if !is_live(state) {
+        return 0;
+    }
+    let state = unsafe { &mut *state };
+    if state.app.window.is_none() {
+        pump_once(state);
+    }
+    let Some(window) = state.app.window.as_ref() else {
+        return 0;
+    };
+    let primary = window.primary_monitor();
+    let monitors: Vec<MonitorReport> = window
+        .available_monitors()
+        .map(|monitor| MonitorReport::new(&monitor, primary.as_ref()))
+        .collect();
+    let payload = serde_json::to_vec(&monitors).unwrap_or_default();
+    unsafe { buffer::write(&payload, buf_ptr, buf_cap) }
+}
+
+/// Version of the exported C ABI. Bump whenever an export is added, removed or
+/// changes signature, and refresh `abi.txt` (midi/tests/06_abi_snapshot.ts).
+pub const ABI_VERSION: u32 = 22;
+
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn window_abi_version() -> u32 {
+    ABI_VERSION
+}
+
+/// Features available in this build as a JSON object of booleans. Returns the
+/// byte length; nothing is written if `buf_cap` is too small.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn window_capabilities(buf_ptr: *mut u8, buf_cap: u32) -> u32 {
+    let payload = serde_json::to_vec(&serde_json::json!({
+        "binary_events": true,
+        "event_mask": true,
+        "focus_events": true,
+        "file_drop": true,
+        "event_subscription": true,
+        "before_destroy": true,
+        "event_timestamps": true,
+        "child_windows": true,
+        "additional_windows": true,
+        "list_monitors": true,
+        "hdr_info": cfg!(any(target_os = "windows", target_os = "macos")),
+        "corner_preference": cfg!(target_os = "windows"),
+        "taskbar_thumbnail_clip": cfg!(target_os = "windows"),
+        "content_protection": cfg!(any(target_os = "windows", target_os = "macos")),
+        "accessibility_role": cfg!(target_os = "macos"),
+        "pointer_capture": true,
+        "hit_test": true,
+        "window_drag": true,
+        "user_events": true,
+        "capture_pixels": true,
+        "gpu_preference": true,
+        "shutdown_all": true,
+        "tracing": avtools_trace::AVAILABLE,
+        "log_callback": true,
+        "last_error": true,
+        "decode_frame_json": true,
+    }))
+    .unwrap_or_default();
+    unsafe { buffer::write(&payload, buf_ptr, buf_cap) }
+}
+
+/// Pretty-print any avtools_frame packet as JSON, for debugging. deno_window
+/// has no frame schema of its own yet, so records are shown as hex. Returns
+/// the byte length, 0 if the bytes are not a frame; nothing is written if
+/// `buf_cap` is too small.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn window_decode_frame_json(
+    ptr: *const u8,
+    len: u32,
+    buf_ptr: *mut u8,
+    buf_cap: u32,
+) -> u32 {
+    unsafe { avtools_frame::decode_json_export(ptr, len, buf_ptr, buf_cap, &[]) }
+}
+
+/// Code of the calling thread's last failed call (0 if the last fallible call
+/// succeeded). deno_window codes are 100–199; see avtools_ffi::error.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn window_last_error_code() -> i32 {
+    error::code()
+}
+
+/// For other components of the combined library: call `tap` with every
+/// key, mouse move and mouse button event of `state`'s window as it is
+/// queued, until remove_event_tap or destroy_window. Returns the tap's token.
+///
+/// # Safety
+/// Call on the thread that pumps `state`; `state` may be stale or bogus.
+pub unsafe fn add_event_tap(state: *mut WindowState, tap: EventTap) -> Result<u64, Error> {
+    if !is_live(state) {
+        return Err(Error::new(error::INVALID_HANDLE, "window state was destroyed or is not a window"));
+    }
+    let token = taps::NEXT_TAP.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    (*state).app.event_taps.push((token, tap));
+    Ok(token)
+}
+
+/// False if the window was destroyed or has no tap `token`.
+///
+/// # Safety
+/// As add_event_tap.
+pub unsafe fn remove_event_tap(state: *mut WindowState, token: u64) -> bool {
+    if !is_live(state) {
+        return false;
+    }
+    let taps = &mut (*state).app.event_taps;
+    let before = taps.len();
+    taps.retain(|(t, _)| *t != token);
+    taps.len() != before
+}
+
+/// An Injector for `state`'s event queue, usable from any thread.
+///
+/// # Safety
+/// As add_event_tap.
+pub unsafe fn injector(state: *mut WindowState) -> Result<Injector, Error> {
+    if !is_live(state) {
+        return Err(Error::new(error::INVALID_HANDLE, "window state was destroyed or is not a window"));
+    }
+    Ok(Injector {
+        inbox: (*state).app.inbox.clone(),
+    })
+}
+
+/// deno_window's section of the runtime metrics: queue depth, frame count and
+/// pump timings of every live window, or null if the window table is locked.
+pub fn metrics_section() -> String {
+    let windows = avtools_ffi::metrics::sample(&LIVE_STATES, |live| {
+        live.iter().map(|(&state, m)| (state, m.clone())).collect::<Vec<_>>()
+    })
+    .map(|live| live.iter().map(|(state, m)| m.to_json(*state)).collect::<Vec<_>>());
+    serde_json::json!({ "windows": windows }).to_string()
+}
+
+/// Runtime metrics as JSON, `{"schema":1,"window":{...}}` (see
+/// `metrics_section`). Safe to call from any thread, including while a
+/// window is being pumped. Returns the byte length; nothing is written if
+/// `buf_cap` is too small.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn window_metrics_json(buf_ptr: *mut u8, buf_cap: u32) -> u32 {
+    let json = avtools_ffi::metrics::document(&[("window", metrics_section())]);
+    unsafe { buffer::write(json.as_bytes(), buf_ptr, buf_cap) }
+}
+
+/// Message for window_last_error_code. Returns the byte length (0 if none);
+/// nothing is written if `buf_cap` is too small.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn window_last_error_message(buf_ptr: *mut u8, buf_cap: u32) -> u32 {
+    unsafe { error::message(buf_ptr, buf_cap) }
+}
+
+/// Message of the last export that panicked on this thread (the library keeps
+/// working; the export returned its failure value). Unlike the last error it
+/// survives later calls. Returns the byte length, 0 if nothing has panicked.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn window_get_last_panic(buf_ptr: *mut u8, buf_cap: u32) -> u32 {
+    unsafe { avtools_ffi::panic::last_panic(buf_ptr, buf_cap) }
+}
+
+/// Route this library's log messages to `cb` instead of stderr; null restores
+/// stderr. `cb` may be called from any thread, and is never called again once
+/// this returns with a different callback.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn window_set_log_callback(cb: Option<extern "C" fn(u32, *const u8, u32)>) {
+    avtools_log::set_callback(cb);
+}
+
+/// Most verbose level logged: 0 off, 1 error, 2 warn, 3 info (default),
+/// 4 debug. Returns -1 for an unknown level.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn window_set_log_level(level: u32) -> i32 {
+    avtools_log::set_level(level)
+}
+
+/// Call `cb` at the start of destroy_window (or when the parent of a child
+/// window is destroyed), while the native window and its handles are still
+/// valid, so GPU resources on its surface can be released first. It runs on
+/// the destroying thread, at most once. A null `cb` removes it.
+///
+/// Returns 0, or -1 for a bad state.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn set_before_destroy_callback(state: *mut WindowState, cb: Option<extern "C" fn()>) -> i32 {
+    if !is_live(state) {
+        return -1;
+    }
+    unsafe { (*state).app.before_destroy = cb };
+    0
+}
+
+/// Close the window and free `state`. Later calls with the same pointer fail
+/// with INVALID_HANDLE instead of touching freed memory.
+#[no_mangle]
+#[catch_panic]
 pub extern "C" fn destroy_window(state: *mut WindowState) {
-    if state.is_null() {
+    if !is_live(state) {
         return;
     }
+    let this = unsafe { &mut *state };
+    if let Some(cb) = this.app.before_destroy.take() {
+        cb();
+    }
+    if !this.parent.is_null() {
+        let parent = unsafe { &mut *this.parent };
+        parent.app.children.retain(|&c| c != state);
+    }
+    for child in this.app.children.drain(..) {
+        let child = unsafe { &mut *child };
+        if let Some(cb) = child.app.before_destroy.take() {
+            cb();
+        }
+        child.app.window = None;
+        child.app.window_id = None;
+        child.app.should_close = true;
+        child.app.push_event(WindowEventRecord::Close);
+        child.parent = ptr::null_mut();
+    }
+    // Additional windows go before the event loop they were created on.
+    this.app.additional.clear();
+    this.magic = 0;
+    LIVE_STATES.lock().unwrap_or_else(|e| e.into_inner()).remove(&(state as usize));
     unsafe {
         drop(Box::from_raw(state));
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn take_json(app: &mut WindowApp) -> Vec<serde_json::Value> {
+        let mut buf = vec![0u8; 4096];
+        let len = app.take_events(EVENT_FORMAT_JSON, buf.as_mut_ptr(), buf.len() as u32);
+        if len == 0 {
+            return Vec::new();
+        }
+        serde_json::from_slice(&buf[..len as usize]).unwrap()
+    }
+
+    #[test]
+    fn focus_loss_is_reported_once() {
+        let mut app = WindowApp::new(64, 64, String::new());
+        app.record_focus(true);
+        take_json(&mut app);
+
+        // Platforms may deliver the same Focused(false) more than once.
+        app.record_focus(false);
+        app.record_focus(false);
+        let events = take_json(&mut app);
+        assert_eq!(events.len(), 1, "{events:?}");
+        assert_eq!(events[0]["type"], "focus");
+        assert_eq!(events[0]["gained"], false);
+        assert!(take_json(&mut app).is_empty());
+    }
+}
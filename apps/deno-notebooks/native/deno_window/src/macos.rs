@@ -0,0 +1,126 @@
+use objc2_app_kit::{
+    NSAccessibility, NSAccessibilityApplicationRole, NSAccessibilityGroupRole,
+    NSAccessibilityImageRole, NSAccessibilityLayoutAreaRole, NSDisplayGamut, NSView,
+};
+use objc2_foundation::NSString;
+use core_graphics::display::CGRectNull;
+use core_graphics::window::{
+    create_image, kCGWindowImageBoundsIgnoreFraming, kCGWindowListOptionIncludingWindow,
+    CGWindowID,
+};
+
+use crate::DisplayColorSpace;
+
+/// winit owns the view for the lifetime of the window.
+fn view<'a>(ns_view: usize) -> Option<&'a NSView> {
+    (ns_view != 0).then(|| unsafe { &*(ns_view as *const NSView) })
+}
+
+/// Accessible name announced by VoiceOver for the window's content view.
+pub fn set_accessibility_label(ns_view: usize, label: &str) -> bool {
+    let Some(view) = view(ns_view) else {
+        return false;
+    };
+    let label = NSString::from_str(label);
+    unsafe { view.setAccessibilityLabel(Some(&label)) };
+    true
+}
+
+/// 0 = AppKit default, 1 = group, 2 = image, 3 = application, 4 = layout area.
+pub fn set_accessibility_role(ns_view: usize, role: u32) -> bool {
+    let Some(view) = view(ns_view) else {
+        return false;
+    };
+    unsafe {
+        let role = match role {
+            1 => Some(NSAccessibilityGroupRole),
+            2 => Some(NSAccessibilityImageRole),
+            3 => Some(NSAccessibilityApplicationRole),
+            4 => Some(NSAccessibilityLayoutAreaRole),
+            _ => None,
+        };
+        view.setAccessibilityRole(role);
+    }
+    true
+}
+
+/// EDR headroom and gamut of the screen hosting `ns_view`.
+pub fn display_color_space(ns_view: usize) -> Option<DisplayColorSpace> {
+    let screen = view(ns_view)?.window()?.screen()?;
+    let headroom = unsafe { screen.maximumPotentialExtendedDynamicRangeColorComponentValue() };
+    let p3 = unsafe { screen.canRepresentDisplayGamut(NSDisplayGamut::P3) };
+    Some(DisplayColorSpace {
+        hdr_supported: headroom > 1.0,
+        // macOS reports EDR headroom relative to SDR white, not absolute nits.
+        max_luminance_nits: None,
+        edr_headroom: Some(headroom as f32),
+        color_gamut: if p3 { "p3" } else { "srgb" },
+    })
+}
+
+/// Content-view pixels of the window hosting `ns_view` as RGBA8, read back
+/// with CGWindowListCreateImage. Needs Screen Recording permission on 10.15+;
+/// without it macOS returns only the desktop wallpaper behind the window.
+pub fn capture_pixels(ns_view: usize) -> Option<(u32, u32, Vec<u8>)> {
+    let window = view(ns_view)?.window()?;
+    let frame = window.frame();
+    let content = window.contentRectForFrameRect(frame);
+    let window_id = unsafe { window.windowNumber() } as CGWindowID;
+    let image = create_image(
+        unsafe { CGRectNull },
+        kCGWindowListOptionIncludingWindow,
+        window_id,
+        kCGWindowImageBoundsIgnoreFraming,
+    )?;
+    if image.bits_per_pixel() != 32 || frame.size.height <= 0.0 {
+        return None;
+    }
+    // The image covers the whole frame; skip the title bar rows at the top.
+    let scale = image.height() as f64 / frame.size.height;
+    let title_rows = ((frame.size.height - content.size.height) * scale).round() as usize;
+    let (width, stride) = (image.width(), image.bytes_per_row());
+    let height = image.height().saturating_sub(title_rows);
+    let data = image.data();
+    let mut pixels = Vec::with_capacity(width * height * 4);
+    // Window images are premultiplied BGRA; the window is opaque, so alpha is 255.
+    for row in data.bytes().chunks_exact(stride).skip(title_rows).take(height) {
+        for px in row[..width * 4].chunks_exact(4) {
+            pixels.extend_from_slice(&[px[2], px[1], px[0], px[3]]);
+        }
+    }
+    Some((width as u32, height as u32, pixels))
+}
+
+/// Mouse position over the content view in backing (physical) pixels, top
+/// left origin, read outside the event stream so it works while the window
+/// ignores mouse events.
+pub fn cursor_position(ns_view: usize) -> Option<(f64, f64)> {
+    let view = view(ns_view)?;
+    let window = view.window()?;
+    let in_window = unsafe { window.mouseLocationOutsideOfEventStream() };
+    let point = view.convertPoint_fromView(in_window, None);
+    let y = if view.isFlipped() {
+        point.y
+    } else {
+        view.bounds().size.height - point.y
+    };
+    let scale = window.backingScaleFactor();
+    Some((point.x * scale, y * scale))
+}
+
+/// Move the content view into `parent` (an NSView of the host), filling it,
+/// and hide the now empty window that held it.
+pub fn reparent(ns_view: usize, parent: usize) -> bool {
+    let (Some(view), Some(parent)) = (view(ns_view), view(parent)) else {
+        return false;
+    };
+    let window = view.window();
+    unsafe {
+        view.setFrame(parent.bounds());
+        parent.addSubview(view);
+    }
+    if let Some(window) = window {
+        window.orderOut(None);
+    }
+    true
+}
@@ -0,0 +1,56 @@
+//! Per-window counters for the runtime metrics. They live in atomics shared
+//! with the live-state table, so window_metrics_json can read them from any
+//! thread while the window's owner is pumping it.
+
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::time::Duration;
+
+#[derive(Default)]
+pub struct WindowMetrics {
+    queue_depth: AtomicU32,
+    max_queue_depth: AtomicU32,
+    frames: AtomicU64,
+    pumps: AtomicU64,
+    last_pump_us: AtomicU64,
+    max_pump_us: AtomicU64,
+    total_pump_us: AtomicU64,
+}
+
+impl WindowMetrics {
+    /// Events queued and not yet taken by poll_events.
+    pub fn set_queue_depth(&self, depth: usize) {
+        let depth = depth.min(u32::MAX as usize) as u32;
+        self.queue_depth.store(depth, Ordering::Relaxed);
+        self.max_queue_depth.fetch_max(depth, Ordering::Relaxed);
+    }
+
+    pub fn set_frames(&self, frames: u64) {
+        self.frames.store(frames, Ordering::Relaxed);
+    }
+
+    /// One pump of the window's own event loop (children are pumped by their
+    /// parent's).
+    pub fn record_pump(&self, elapsed: Duration) {
+        let us = elapsed.as_micros().min(u64::MAX as u128) as u64;
+        self.pumps.fetch_add(1, Ordering::Relaxed);
+        self.last_pump_us.store(us, Ordering::Relaxed);
+        self.max_pump_us.fetch_max(us, Ordering::Relaxed);
+        self.total_pump_us.fetch_add(us, Ordering::Relaxed);
+    }
+
+    /// `state` is the window's state pointer, as the host holds it.
+    pub fn to_json(&self, state: usize) -> serde_json::Value {
+        let pumps = self.pumps.load(Ordering::Relaxed);
+        let total_us = self.total_pump_us.load(Ordering::Relaxed);
+        serde_json::json!({
+            "state": state,
+            "queue_depth": self.queue_depth.load(Ordering::Relaxed),
+            "max_queue_depth": self.max_queue_depth.load(Ordering::Relaxed),
+            "frames": self.frames.load(Ordering::Relaxed),
+            "pumps": pumps,
+            "last_pump_us": self.last_pump_us.load(Ordering::Relaxed),
+            "max_pump_us": self.max_pump_us.load(Ordering::Relaxed),
+            "mean_pump_us": if pumps == 0 { 0.0 } else { total_us as f64 / pumps as f64 },
+        })
+    }
+}
@@ -0,0 +1,107 @@
+//! Rust-side hooks for the combined library's bridges: taps see input events
+//! as they are queued during the pump, and an Injector queues synthetic
+//! records from any thread, which the next pump moves into the event queue.
+
+use crate::{TimedEvent, WindowEventRecord};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+/// Injected records held for the next pump; beyond this they are dropped.
+const INJECTED_CAP: usize = 1024;
+
+/// An input event as a tap sees it. Positions are physical client pixels.
+#[derive(Clone, Copy)]
+pub enum TapEvent<'a> {
+    Key { key: &'a str, text: Option<&'a str>, down: bool },
+    MouseMove { x: f64, y: f64, width: u32, height: u32 },
+    MouseButton { button: u32, down: bool },
+}
+
+/// Called on the pumping thread for every input event of a window (see
+/// add_event_tap). Injected records are not tapped.
+pub type EventTap = Box<dyn FnMut(&TapEvent)>;
+
+/// Source of add_event_tap tokens, unique across windows.
+pub(crate) static NEXT_TAP: AtomicU64 = AtomicU64::new(1);
+
+impl WindowEventRecord {
+    pub(crate) fn as_tap_event(&self, width: u32, height: u32) -> Option<TapEvent<'_>> {
+        match *self {
+            WindowEventRecord::Key { ref key, ref text, down } => Some(TapEvent::Key {
+                key,
+                text: text.as_deref(),
+                down,
+            }),
+            WindowEventRecord::MouseMove { x, y } => Some(TapEvent::MouseMove { x, y, width, height }),
+            WindowEventRecord::MouseButton { button, down, .. } => Some(TapEvent::MouseButton { button, down }),
+            _ => None,
+        }
+    }
+}
+
+/// Shared between a window and its Injectors, which may outlive it.
+#[derive(Default)]
+pub(crate) struct Inbox {
+    events: Mutex<Vec<TimedEvent>>,
+    /// Client size, width in the high half, for scaling injected positions.
+    size: AtomicU64,
+}
+
+impl Inbox {
+    pub(crate) fn with_size(width: u32, height: u32) -> Self {
+        let inbox = Self::default();
+        inbox.set_size(width, height);
+        inbox
+    }
+
+    pub(crate) fn set_size(&self, width: u32, height: u32) {
+        self.size.store((width as u64) << 32 | height as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn take(&self) -> Vec<TimedEvent> {
+        std::mem::take(&mut *self.events.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+}
+
+/// Queues records into a window's event queue from any thread. Records are
+/// stamped when injected and delivered by the window's next pump; injecting
+/// into a destroyed window does nothing.
+#[derive(Clone)]
+pub struct Injector {
+    pub(crate) inbox: std::sync::Arc<Inbox>,
+}
+
+impl Injector {
+    /// Client size in physical pixels as of the last pump.
+    pub fn size(&self) -> (u32, u32) {
+        let size = self.inbox.size.load(Ordering::Relaxed);
+        ((size >> 32) as u32, size as u32)
+    }
+
+    pub fn key(&self, key: &str, text: Option<&str>, down: bool) {
+        self.push(WindowEventRecord::Key {
+            key: key.to_string(),
+            text: text.map(str::to_string),
+            down,
+        });
+    }
+
+    pub fn mouse_move(&self, x: f64, y: f64) {
+        self.push(WindowEventRecord::MouseMove { x, y });
+    }
+
+    pub fn mouse_button(&self, button: u32, down: bool, x: f64, y: f64) {
+        self.push(WindowEventRecord::MouseButton { button, down, x, y });
+    }
+
+    fn push(&self, event: WindowEventRecord) {
+        let mut events = self.inbox.events.lock().unwrap_or_else(|e| e.into_inner());
+        if events.len() < INJECTED_CAP {
+            events.push(TimedEvent {
+                ts_us: avtools_clock::now_us(),
+                window_token: 0,
+                event,
+            });
+        }
+    }
+}
@@ -0,0 +1,199 @@
+use std::ffi::c_void;
+use windows::core::Interface;
+use windows::Win32::Foundation::{HWND, POINT, RECT};
+use windows::Win32::Graphics::Dwm::{
+    DwmSetWindowAttribute, DWMWA_WINDOW_CORNER_PREFERENCE, DWM_WINDOW_CORNER_PREFERENCE,
+};
+use windows::Win32::Graphics::Dxgi::Common::DXGI_COLOR_SPACE_RGB_FULL_G2084_NONE_P2020;
+use windows::Win32::Graphics::Dxgi::{CreateDXGIFactory1, IDXGIFactory1, IDXGIOutput6};
+use windows::Win32::Graphics::Gdi::{
+    BitBlt, CreateCompatibleBitmap, CreateCompatibleDC, DeleteDC, DeleteObject, GetDC, GetDIBits,
+    MonitorFromWindow, ReleaseDC, ScreenToClient, SelectObject, BITMAPINFO, BITMAPINFOHEADER,
+    BI_RGB, DIB_RGB_COLORS, MONITOR_DEFAULTTONEAREST, SRCCOPY,
+};
+use windows::Win32::Storage::Xps::{PrintWindow, PRINT_WINDOW_FLAGS, PW_CLIENTONLY};
+use windows::Win32::System::Com::{
+    CoCreateInstance, CoInitializeEx, CLSCTX_INPROC_SERVER, COINIT_APARTMENTTHREADED,
+};
+use windows::Win32::UI::Shell::{ITaskbarList3, TaskbarList};
+use windows::Win32::UI::WindowsAndMessaging::{
+    GetClientRect, GetCursorPos, GetWindowLongPtrW, SetParent, SetWindowLongPtrW, SetWindowPos, GWL_STYLE,
+    PW_RENDERFULLCONTENT, SWP_FRAMECHANGED, SWP_NOACTIVATE, SWP_NOSIZE, SWP_NOZORDER, WS_CHILD, WS_OVERLAPPEDWINDOW,
+    WS_POPUP,
+};
+
+use crate::DisplayColorSpace;
+
+/// Returns false when DWM rejects the attribute (Windows 10 and earlier).
+pub fn set_corner_preference(hwnd: usize, pref: u32) -> bool {
+    let value = DWM_WINDOW_CORNER_PREFERENCE(pref as i32);
+    unsafe {
+        DwmSetWindowAttribute(
+            HWND(hwnd as *mut c_void),
+            DWMWA_WINDOW_CORNER_PREFERENCE,
+            &value as *const _ as *const c_void,
+            std::mem::size_of::<DWM_WINDOW_CORNER_PREFERENCE>() as u32,
+        )
+        .is_ok()
+    }
+}
+
+/// Make `hwnd` a child window of `parent` (SetParent), dropping its frame and
+/// moving it to the parent's client origin.
+pub fn reparent(hwnd: usize, parent: usize) -> bool {
+    if hwnd == 0 || parent == 0 {
+        return false;
+    }
+    let hwnd = HWND(hwnd as *mut c_void);
+    unsafe {
+        let style = GetWindowLongPtrW(hwnd, GWL_STYLE) as u32;
+        let style = (style & !(WS_POPUP.0 | WS_OVERLAPPEDWINDOW.0)) | WS_CHILD.0;
+        SetWindowLongPtrW(hwnd, GWL_STYLE, style as isize);
+        if SetParent(hwnd, HWND(parent as *mut c_void)).is_err() {
+            return false;
+        }
+        let flags = SWP_NOSIZE | SWP_NOZORDER | SWP_NOACTIVATE | SWP_FRAMECHANGED;
+        SetWindowPos(hwnd, None, 0, 0, 0, 0, flags).is_ok()
+    }
+}
+
+/// Restrict the taskbar thumbnail to `clip` (client coordinates), or show the
+/// whole window again with `None`.
+pub fn set_thumbnail_clip(hwnd: usize, clip: Option<RECT>) -> windows::core::Result<()> {
+    unsafe {
+        // winit normally initialises OLE on the event loop thread already;
+        // S_FALSE / RPC_E_CHANGED_MODE are both fine here.
+        let _ = CoInitializeEx(None, COINIT_APARTMENTTHREADED);
+        let taskbar: ITaskbarList3 = CoCreateInstance(&TaskbarList, None, CLSCTX_INPROC_SERVER)?;
+        taskbar.HrInit()?;
+        let clip_ptr = clip.as_ref().map_or(std::ptr::null(), |rect| rect as *const RECT);
+        taskbar.SetThumbnailClip(HWND(hwnd as *mut c_void), clip_ptr)
+    }
+}
+
+/// HDR state of the DXGI output showing `hwnd`.
+pub fn display_color_space(hwnd: usize) -> Option<DisplayColorSpace> {
+    if hwnd == 0 {
+        return None;
+    }
+    unsafe {
+        let monitor = MonitorFromWindow(HWND(hwnd as *mut c_void), MONITOR_DEFAULTTONEAREST);
+        let factory: IDXGIFactory1 = CreateDXGIFactory1().ok()?;
+        let mut adapter_idx = 0;
+        while let Ok(adapter) = factory.EnumAdapters1(adapter_idx) {
+            let mut output_idx = 0;
+            while let Ok(output) = adapter.EnumOutputs(output_idx) {
+                output_idx += 1;
+                let Ok(output6) = output.cast::<IDXGIOutput6>() else {
+                    continue;
+                };
+                let Ok(desc) = output6.GetDesc1() else {
+                    continue;
+                };
+                if desc.Monitor != monitor {
+                    continue;
+                }
+                // Classify the gamut by the red primary (sRGB 0.64, P3 0.68, BT.2020 0.708).
+                let red_x = desc.RedPrimary[0];
+                let color_gamut = if red_x >= 0.70 {
+                    "rec2020"
+                } else if red_x >= 0.67 {
+                    "p3"
+                } else {
+                    "srgb"
+                };
+                return Some(DisplayColorSpace {
+                    hdr_supported: desc.ColorSpace == DXGI_COLOR_SPACE_RGB_FULL_G2084_NONE_P2020,
+                    max_luminance_nits: Some(desc.MaxLuminance),
+                    edr_headroom: None,
+                    color_gamut,
+                });
+            }
+            adapter_idx += 1;
+        }
+        None
+    }
+}
+
+/// Client-area pixels of `hwnd` as RGBA8. PrintWindow with
+/// PW_RENDERFULLCONTENT also captures DirectX swapchains; BitBlt from the
+/// window DC is the fallback where that flag is unsupported.
+pub fn capture_pixels(hwnd: usize) -> Option<(u32, u32, Vec<u8>)> {
+    if hwnd == 0 {
+        return None;
+    }
+    let hwnd = HWND(hwnd as *mut c_void);
+    unsafe {
+        let mut rect = RECT::default();
+        GetClientRect(hwnd, &mut rect).ok()?;
+        let (width, height) = (rect.right - rect.left, rect.bottom - rect.top);
+        if width <= 0 || height <= 0 {
+            return None;
+        }
+        let window_dc = GetDC(hwnd);
+        if window_dc.is_invalid() {
+            return None;
+        }
+        let mem_dc = CreateCompatibleDC(window_dc);
+        let bitmap = CreateCompatibleBitmap(window_dc, width, height);
+        let previous = SelectObject(mem_dc, bitmap);
+        let flags = PRINT_WINDOW_FLAGS(PW_CLIENTONLY.0 | PW_RENDERFULLCONTENT);
+        let copied = PrintWindow(hwnd, mem_dc, flags).as_bool()
+            || BitBlt(mem_dc, 0, 0, width, height, window_dc, 0, 0, SRCCOPY).is_ok();
+
+        let mut info = BITMAPINFO {
+            bmiHeader: BITMAPINFOHEADER {
+                biSize: std::mem::size_of::<BITMAPINFOHEADER>() as u32,
+                biWidth: width,
+                // Negative height asks for top-down rows.
+                biHeight: -height,
+                biPlanes: 1,
+                biBitCount: 32,
+                biCompression: BI_RGB.0,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut pixels = vec![0u8; width as usize * height as usize * 4];
+        let rows = if copied {
+            GetDIBits(
+                mem_dc,
+                bitmap,
+                0,
+                height as u32,
+                Some(pixels.as_mut_ptr() as *mut c_void),
+                &mut info,
+                DIB_RGB_COLORS,
+            )
+        } else {
+            0
+        };
+
+        SelectObject(mem_dc, previous);
+        let _ = DeleteObject(bitmap);
+        let _ = DeleteDC(mem_dc);
+        ReleaseDC(hwnd, window_dc);
+
+        if rows != height {
+            return None;
+        }
+        // GDI hands back BGRX; the X byte is undefined.
+        for px in pixels.chunks_exact_mut(4) {
+            px.swap(0, 2);
+            px[3] = 255;
+        }
+        Some((width as u32, height as u32, pixels))
+    }
+}
+
+/// Cursor position in `hwnd`'s client coordinates, wherever the cursor is.
+pub fn cursor_position(hwnd: usize) -> Option<(f64, f64)> {
+    let mut point = POINT::default();
+    unsafe {
+        GetCursorPos(&mut point).ok()?;
+        if !ScreenToClient(HWND(hwnd as *mut c_void), &mut point).as_bool() {
+            return None;
+        }
+    }
+    Some((point.x as f64, point.y as f64))
+}
@@ -0,0 +1,103 @@
+use std::slice;
+
+use x11_dl::xlib::{Display, LSBFirst, Window, XWindowAttributes, Xlib, ZPixmap};
+
+/// Client-area pixels of an Xlib window as RGBA8, read back with XGetImage.
+/// Wayland has no counterpart: clients cannot read their surface back.
+pub fn capture_pixels(display: usize, window: usize) -> Option<(u32, u32, Vec<u8>)> {
+    if display == 0 || window == 0 {
+        return None;
+    }
+    let xlib = Xlib::open().ok()?;
+    let display = display as *mut Display;
+    unsafe {
+        let mut attrs: XWindowAttributes = std::mem::zeroed();
+        if (xlib.XGetWindowAttributes)(display, window as _, &mut attrs) == 0 {
+            return None;
+        }
+        let (width, height) = (attrs.width, attrs.height);
+        if width <= 0 || height <= 0 {
+            return None;
+        }
+        let all_planes = !0;
+        let image = (xlib.XGetImage)(
+            display,
+            window as _,
+            0,
+            0,
+            width as u32,
+            height as u32,
+            all_planes,
+            ZPixmap,
+        );
+        if image.is_null() {
+            return None;
+        }
+        let img = &*image;
+        let pixels = (img.bits_per_pixel == 32).then(|| {
+            let stride = img.bytes_per_line as usize;
+            let data = slice::from_raw_parts(img.data as *const u8, stride * height as usize);
+            let masks = [img.red_mask, img.green_mask, img.blue_mask].map(|m| m as u32);
+            let mut out = Vec::with_capacity(width as usize * height as usize * 4);
+            for row in data.chunks_exact(stride) {
+                for px in row[..width as usize * 4].chunks_exact(4) {
+                    let px = [px[0], px[1], px[2], px[3]];
+                    let value = if img.byte_order == LSBFirst {
+                        u32::from_le_bytes(px)
+                    } else {
+                        u32::from_be_bytes(px)
+                    };
+                    for mask in masks {
+                        out.push(((value & mask) >> mask.trailing_zeros()) as u8);
+                    }
+                    out.push(255);
+                }
+            }
+            out
+        });
+        (xlib.XDestroyImage)(image);
+        pixels.map(|p| (width as u32, height as u32, p))
+    }
+}
+
+/// Pointer position relative to an Xlib window (XQueryPointer), wherever the
+/// pointer is on the window's screen.
+pub fn cursor_position(display: usize, window: usize) -> Option<(f64, f64)> {
+    if display == 0 || window == 0 {
+        return None;
+    }
+    let xlib = Xlib::open().ok()?;
+    let (mut root, mut child): (Window, Window) = (0, 0);
+    let (mut root_x, mut root_y, mut win_x, mut win_y) = (0, 0, 0, 0);
+    let mut mask = 0;
+    let same_screen = unsafe {
+        (xlib.XQueryPointer)(
+            display as *mut Display,
+            window as _,
+            &mut root,
+            &mut child,
+            &mut root_x,
+            &mut root_y,
+            &mut win_x,
+            &mut win_y,
+            &mut mask,
+        )
+    };
+    (same_screen != 0).then_some((win_x as f64, win_y as f64))
+}
+
+/// Make `window` a child of `parent` at its top left (XReparentWindow).
+pub fn reparent(display: usize, window: usize, parent: usize) -> bool {
+    if display == 0 || window == 0 || parent == 0 {
+        return false;
+    }
+    let Ok(xlib) = Xlib::open() else {
+        return false;
+    };
+    let display = display as *mut Display;
+    unsafe {
+        (xlib.XReparentWindow)(display, window as _, parent as _, 0, 0);
+        (xlib.XFlush)(display);
+    }
+    true
+}
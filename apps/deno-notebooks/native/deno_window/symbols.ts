@@ -0,0 +1,68 @@
+// Generated by native/avtools_bindgen from the exports of deno_window; do not edit.
+// Hand-written tables may narrow "pointer" to "buffer"/"function" and add `nonblocking`.
+
+export const DENO_WINDOW_SYMBOLS = {
+  av_alloc: { parameters: ["u32"], result: "pointer" },
+  av_free: { parameters: ["pointer", "u32"], result: "void" },
+  avtools_clock_now_us: { parameters: [], result: "u64" },
+  avtools_clock_offset: { parameters: ["u32", "u32"], result: "i64" },
+  avtools_shutdown_all: { parameters: ["pointer", "u32"], result: "u32" },
+  avtools_tracing_start: { parameters: ["pointer", "u32", "u32"], result: "i32" },
+  avtools_tracing_stop: { parameters: [], result: "i32" },
+  begin_window_drag_move: { parameters: ["pointer"], result: "i32" },
+  begin_window_drag_resize: { parameters: ["pointer", "u32"], result: "i32" },
+  capture_window_pixels: { parameters: ["pointer", "pointer", "u32", "pointer", "pointer"], result: "i32" },
+  clear_taskbar_thumbnail_clip: { parameters: ["pointer"], result: "i32" },
+  create_additional_window: { parameters: ["pointer", "u32", "u32", "pointer", "u32"], result: "u32" },
+  create_child_window: { parameters: ["pointer", "u32", "u32", "u32", "u32"], result: "pointer" },
+  create_window: { parameters: ["u32", "u32", "pointer", "u32"], result: "pointer" },
+  create_window_ex: { parameters: ["pointer", "u32"], result: "pointer" },
+  destroy_window: { parameters: ["pointer"], result: "void" },
+  destroy_window_by_token: { parameters: ["pointer", "u32"], result: "i32" },
+  get_display_color_space: { parameters: ["pointer", "pointer", "u32"], result: "u32" },
+  get_display_refresh_rate: { parameters: ["pointer"], result: "f64" },
+  get_frame_count: { parameters: ["pointer"], result: "u64" },
+  get_frames_per_second: { parameters: ["pointer"], result: "f64" },
+  get_gpu_preference: { parameters: ["pointer"], result: "u32" },
+  get_raw_display_handle: { parameters: ["pointer"], result: "usize" },
+  get_raw_window_handle: { parameters: ["pointer"], result: "usize" },
+  get_raw_window_handle_by_token: { parameters: ["pointer", "u32"], result: "usize" },
+  get_vsync_interval_us: { parameters: ["pointer"], result: "u64" },
+  get_window_position: { parameters: ["pointer", "pointer", "pointer"], result: "i32" },
+  get_window_size: { parameters: ["pointer", "pointer", "pointer"], result: "void" },
+  get_window_size_by_token: { parameters: ["pointer", "u32", "pointer", "pointer"], result: "i32" },
+  get_window_system: { parameters: ["pointer"], result: "u32" },
+  list_monitors: { parameters: ["pointer", "pointer", "u32"], result: "u32" },
+  poll_events: { parameters: ["pointer", "pointer", "u32"], result: "u32" },
+  poll_events_filtered: { parameters: ["pointer", "u64", "pointer", "u32"], result: "u32" },
+  poll_events_msgpack: { parameters: ["pointer", "pointer", "u32"], result: "u32" },
+  resize_window: { parameters: ["pointer", "u32", "u32"], result: "void" },
+  resize_window_by_token: { parameters: ["pointer", "u32", "u32", "u32"], result: "i32" },
+  send_window_user_event: { parameters: ["pointer", "pointer", "u32"], result: "i32" },
+  set_accessibility_label: { parameters: ["pointer", "pointer", "u32"], result: "i32" },
+  set_accessibility_role: { parameters: ["pointer", "u32"], result: "i32" },
+  set_auto_capture_on_drag: { parameters: ["pointer", "u8"], result: "i32" },
+  set_before_destroy_callback: { parameters: ["pointer", "function"], result: "i32" },
+  set_content_protection: { parameters: ["pointer", "u8"], result: "i32" },
+  set_event_format: { parameters: ["pointer", "u8"], result: "i32" },
+  set_gpu_preference: { parameters: ["pointer", "u32"], result: "i32" },
+  set_hdr_hint: { parameters: ["pointer", "u8"], result: "i32" },
+  set_hit_test_callback: { parameters: ["pointer", "function"], result: "i32" },
+  set_parent_window_handle: { parameters: ["pointer", "usize", "usize", "u32"], result: "i32" },
+  set_pointer_capture: { parameters: ["pointer", "u8"], result: "i32" },
+  set_taskbar_thumbnail_clip: { parameters: ["pointer", "u32", "u32", "u32", "u32"], result: "i32" },
+  set_window_corner_preference: { parameters: ["pointer", "u32"], result: "i32" },
+  set_window_position: { parameters: ["pointer", "i32", "i32"], result: "i32" },
+  subscribe_events: { parameters: ["pointer", "u64", "function"], result: "i32" },
+  unsubscribe_events: { parameters: ["pointer"], result: "i32" },
+  wait_for_window_ready: { parameters: ["pointer", "u32"], result: "i32" },
+  window_abi_version: { parameters: [], result: "u32" },
+  window_capabilities: { parameters: ["pointer", "u32"], result: "u32" },
+  window_decode_frame_json: { parameters: ["pointer", "u32", "pointer", "u32"], result: "u32" },
+  window_get_last_panic: { parameters: ["pointer", "u32"], result: "u32" },
+  window_last_error_code: { parameters: [], result: "i32" },
+  window_last_error_message: { parameters: ["pointer", "u32"], result: "u32" },
+  window_metrics_json: { parameters: ["pointer", "u32"], result: "u32" },
+  window_set_log_callback: { parameters: ["function"], result: "void" },
+  window_set_log_level: { parameters: ["u32"], result: "i32" },
+} as const satisfies Deno.ForeignLibraryInterface;
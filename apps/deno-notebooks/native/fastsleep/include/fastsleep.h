@@ -0,0 +1,178 @@
+/* Generated by native/avtools_bindgen from the exports of fastsleep; do not edit. */
+
+#ifndef AVTOOLS_FASTSLEEP_H
+#define AVTOOLS_FASTSLEEP_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+typedef void (*TickCallback)(uint64_t, int64_t);
+
+typedef void (*TimerCallback)(uint32_t, int64_t);
+
+typedef void (*BatchCallback)(uint32_t, int64_t);
+
+int32_t fast_sleep_configure(uint32_t native_accuracy_us, uint32_t strategy);
+
+int32_t fast_sleep_init(uint32_t native_accuracy_us, uint32_t strategy);
+
+void fast_sleep_get_config(uint32_t *out_accuracy_us,
+                           uint32_t *out_strategy,
+                           uint32_t *out_power_mode);
+
+void fast_sleep_us(uint32_t us);
+
+void fast_sleep_ns(uint64_t ns);
+
+int32_t fast_sleep_enable_mock(double scale_factor);
+
+uint64_t fast_sleep_get_virtual_time_ns(void);
+
+uint64_t fast_sleep_us_measured(uint32_t us);
+
+uint64_t fast_sleep_ns_measured(uint64_t ns);
+
+uint64_t fast_sleep_clock_ns(void);
+
+uint32_t fast_clock_info(uint8_t *out, uint32_t out_cap);
+
+uint32_t fast_sleep_abi_version(void);
+
+uint32_t fast_sleep_capabilities(uint8_t *out, uint32_t cap);
+
+int32_t fast_sleep_last_error_code(void);
+
+uint32_t fast_sleep_last_error_message(uint8_t *out, uint32_t cap);
+
+uint32_t fast_sleep_get_last_panic(uint8_t *out, uint32_t cap);
+
+void fast_sleep_set_log_callback(void (*cb)(uint32_t, const uint8_t*, uint32_t));
+
+int32_t fast_sleep_set_log_level(uint32_t level);
+
+uint64_t fast_now_us(void);
+
+int64_t fast_sleep_until_us(uint64_t deadline_us);
+
+int64_t fast_sleep_until_ns(uint64_t deadline_ns);
+
+int32_t fast_sleep_us_with(uint32_t us, uint32_t native_accuracy_us, uint32_t strategy);
+
+int32_t fast_sleep_ns_with(uint64_t ns, uint32_t native_accuracy_us, uint32_t strategy);
+
+int32_t fast_sleep_until_us_with(uint64_t deadline_us,
+                                 uint32_t native_accuracy_us,
+                                 uint32_t strategy);
+
+uint32_t fast_sleeper_create(uint32_t native_accuracy_us, uint32_t strategy);
+
+int32_t fast_sleeper_sleep_us(uint32_t handle, uint32_t us);
+
+int64_t fast_sleeper_sleep_until_us(uint32_t handle, uint64_t deadline_us);
+
+int32_t fast_sleeper_destroy(uint32_t handle);
+
+uint32_t fast_sleep_token_create(void);
+
+int32_t fast_sleep_cancellable_us(uint32_t token_handle, uint32_t us);
+
+int32_t fast_sleep_until_any(const uint64_t *deadlines_ptr, uint32_t count, uint32_t token_handle);
+
+int32_t fast_wait_flag_until_us(const uint32_t *flag_ptr,
+                                uint32_t expected_nonzero,
+                                uint64_t deadline_us);
+
+uint8_t fast_sleep_until_signal(const uint8_t *signal_ptr, uint64_t timeout_us);
+
+int32_t fast_set_wake_flag(uint32_t *flag_ptr, uint32_t value);
+
+int32_t fast_sleep_cancel(uint32_t token_handle);
+
+int32_t fast_sleep_token_reset(uint32_t token_handle);
+
+int32_t fast_sleep_token_destroy(uint32_t token_handle);
+
+uint32_t fast_ticker_create(uint32_t period_us, uint32_t flags);
+
+uint32_t fast_ticker_create_ex(const uint8_t *options_json_ptr, uint32_t options_json_len);
+
+int64_t fast_ticker_wait(uint32_t handle);
+
+int64_t fast_ticker_skipped(uint32_t handle);
+
+int32_t fast_ticker_set_period(uint32_t handle, uint32_t period_us);
+
+int32_t fast_ticker_align(uint32_t handle, uint64_t anchor_us);
+
+uint32_t fast_ticker_info(uint32_t handle, uint8_t *out, uint32_t out_cap);
+
+int32_t fast_ticker_destroy(uint32_t handle);
+
+uint32_t fast_loop_start(uint32_t period_us, TickCallback cb, uint32_t flags);
+
+int32_t fast_loop_stop(uint32_t handle);
+
+uint32_t fast_timer_schedule(uint32_t delay_us, TimerCallback cb);
+
+int32_t fast_timer_cancel(uint32_t timer_id);
+
+uint32_t fast_schedule_batch(const uint64_t *deadlines_ptr, uint32_t count, BatchCallback cb);
+
+int32_t fast_schedule_batch_cancel(uint32_t handle);
+
+int32_t fast_sleep_set_windows_timer_resolution(uint32_t period_ms);
+
+int32_t fast_sleep_set_timer_resolution(uint32_t ms);
+
+int32_t fast_sleep_clear_timer_resolution(uint32_t ms);
+
+int32_t fast_sleep_reset_windows_timer_resolution(void);
+
+uint32_t fast_sleep_calibrate(uint32_t sample_count, uint32_t target_percentile);
+
+uint32_t fast_sleep_calibration_report(uint8_t *out, uint32_t out_cap);
+
+uint32_t fast_sleep_selftest(uint8_t *out, uint32_t out_cap);
+
+void fast_sleep_stats_enable(uint8_t enabled);
+
+void fast_sleep_stats_reset(void);
+
+uint32_t fast_sleep_stats(uint8_t *out, uint32_t out_cap);
+
+uint32_t fast_sleep_metrics_json(uint8_t *out, uint32_t out_cap);
+
+int32_t fast_set_current_thread_priority(uint32_t level);
+
+int32_t fast_get_current_thread_priority(void);
+
+int32_t fast_set_current_thread_name(const uint8_t *ptr, uint32_t len);
+
+void fast_set_miss_handler(uint32_t threshold_us, void (*cb)(uint64_t, uint64_t));
+
+int32_t fast_sleep_set_power_mode(uint32_t mode);
+
+uint32_t fast_sleep_get_power_mode(void);
+
+extern int32_t atexit(void (*cb)(void));
+
+int64_t avtools_clock_offset(uint32_t domain_a, uint32_t domain_b);
+
+uint64_t avtools_clock_now_us(void);
+
+uint8_t *av_alloc(uint32_t len);
+
+void av_free(uint8_t *ptr, uint32_t len);
+
+extern int32_t atexit(void (*cb)(void));
+
+uint32_t avtools_shutdown_all(uint8_t *out_ptr, uint32_t out_cap);
+
+int32_t avtools_tracing_start(const uint8_t *path_ptr, uint32_t path_len, uint32_t level);
+
+int32_t avtools_tracing_stop(void);
+
+#endif  /* AVTOOLS_FASTSLEEP_H */
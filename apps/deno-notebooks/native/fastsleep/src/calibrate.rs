@@ -0,0 +1,106 @@
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::timer_resolution;
+
+/// Requested durations cycled through during calibration, in µs.
+const PROBE_US: [u64; 5] = [100, 250, 500, 1_000, 2_000];
+
+/// Summary of the last calibration run, kept for fast_sleep_calibration_report.
+pub struct CalibrationReport {
+    pub sample_count: u32,
+    pub target_percentile: u32,
+    pub accuracy_us: u32,
+    pub min_us: u64,
+    pub median_us: u64,
+    pub p95_us: u64,
+    pub max_us: u64,
+    pub mean_us: u64,
+    /// System timer resolution during the run (Windows only).
+    pub timer_resolution_us: Option<u32>,
+    /// (requested_us, median overshoot µs, max overshoot µs) per probe duration.
+    pub per_duration: Vec<(u64, u64, u64)>,
+}
+
+pub static LAST_REPORT: Mutex<Option<CalibrationReport>> = Mutex::new(None);
+
+/// Measure how far `thread::sleep` overshoots across `PROBE_US` and pick the
+/// overshoot at `target_percentile` as the native accuracy.
+pub fn run(sample_count: u32, target_percentile: u32) -> CalibrationReport {
+    let sample_count = sample_count.max(PROBE_US.len() as u32);
+    let target_percentile = target_percentile.min(100);
+    let mut all = Vec::with_capacity(sample_count as usize);
+    let mut by_probe: Vec<Vec<u64>> = vec![Vec::new(); PROBE_US.len()];
+
+    for i in 0..sample_count as usize {
+        let probe = i % PROBE_US.len();
+        let requested = Duration::from_micros(PROBE_US[probe]);
+        let start = Instant::now();
+        thread::sleep(requested);
+        let overshoot = start.elapsed().saturating_sub(requested).as_micros() as u64;
+        all.push(overshoot);
+        by_probe[probe].push(overshoot);
+    }
+
+    all.sort_unstable();
+    let per_duration = PROBE_US
+        .iter()
+        .zip(by_probe.iter_mut())
+        .map(|(&requested, samples)| {
+            samples.sort_unstable();
+            (requested, percentile(samples, 50), percentile(samples, 100))
+        })
+        .collect();
+    let accuracy_us = percentile(&all, target_percentile).max(1);
+
+    CalibrationReport {
+        sample_count,
+        target_percentile,
+        accuracy_us: accuracy_us.min(u32::MAX as u64) as u32,
+        min_us: percentile(&all, 0),
+        median_us: percentile(&all, 50),
+        p95_us: percentile(&all, 95),
+        max_us: percentile(&all, 100),
+        mean_us: all.iter().sum::<u64>() / all.len() as u64,
+        timer_resolution_us: timer_resolution::current_us(),
+        per_duration,
+    }
+}
+
+impl CalibrationReport {
+    pub fn to_json(&self) -> String {
+        let per_duration: Vec<String> = self
+            .per_duration
+            .iter()
+            .map(|(requested, median, max)| {
+                format!(
+                    "{{\"requested_us\":{requested},\"median_us\":{median},\"max_us\":{max}}}"
+                )
+            })
+            .collect();
+        format!(
+            "{{\"sample_count\":{},\"target_percentile\":{},\"accuracy_us\":{},\
+             \"min_us\":{},\"median_us\":{},\"p95_us\":{},\"max_us\":{},\"mean_us\":{},\
+             \"timer_resolution_us\":{},\"per_duration\":[{}]}}",
+            self.sample_count,
+            self.target_percentile,
+            self.accuracy_us,
+            self.min_us,
+            self.median_us,
+            self.p95_us,
+            self.max_us,
+            self.mean_us,
+            self.timer_resolution_us
+                .map_or_else(|| "null".to_string(), |us| us.to_string()),
+            per_duration.join(",")
+        )
+    }
+}
+
+fn percentile(sorted: &[u64], pct: u32) -> u64 {
+    if sorted.is_empty() {
+        return 0;
+    }
+    sorted[(sorted.len() - 1) * pct as usize / 100]
+}
@@ -0,0 +1,83 @@
+use spin_sleep::{SpinSleeper, SpinStrategy};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Condvar, Mutex};
+use std::time::Instant;
+
+use crate::mock;
+
+/// Cancellation token shared between a sleeping thread and the canceller.
+/// Once cancelled it stays cancelled until reset, so a cancel issued just
+/// before a sleep starts is never lost.
+pub struct CancelToken {
+    cancelled: AtomicBool,
+    lock: Mutex<()>,
+    cv: Condvar,
+}
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self {
+            cancelled: AtomicBool::new(false),
+            lock: Mutex::new(()),
+            cv: Condvar::new(),
+        }
+    }
+
+    pub fn cancel(&self) {
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        self.cancelled.store(true, Ordering::Release);
+        self.cv.notify_all();
+    }
+
+    pub fn reset(&self) {
+        self.cancelled.store(false, Ordering::Release);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Acquire)
+    }
+
+    /// Sleep until `deadline` unless cancelled. The bulk of the wait parks on
+    /// the condvar; the last `native_accuracy_ns` spins like SpinSleeper,
+    /// polling the flag. In mock mode it only
+    /// advances the virtual clock. Returns true if the sleep was cancelled.
+    pub fn sleep_until(&self, sleeper: SpinSleeper, deadline: Instant) -> bool {
+        if mock::enabled() {
+            if self.is_cancelled() {
+                return true;
+            }
+            mock::sleep_until(deadline);
+            return self.is_cancelled();
+        }
+        mock::note_real_sleep();
+        let accuracy = std::time::Duration::from_nanos(sleeper.native_accuracy_ns() as u64);
+        {
+            let mut guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+            loop {
+                if self.is_cancelled() {
+                    return true;
+                }
+                let now = Instant::now();
+                if now + accuracy >= deadline {
+                    break;
+                }
+                let wait = deadline - now - accuracy;
+                guard = self
+                    .cv
+                    .wait_timeout(guard, wait)
+                    .unwrap_or_else(|e| e.into_inner())
+                    .0;
+            }
+        }
+        while Instant::now() < deadline {
+            if self.is_cancelled() {
+                return true;
+            }
+            match sleeper.spin_strategy() {
+                SpinStrategy::YieldThread => std::thread::yield_now(),
+                _ => std::hint::spin_loop(),
+            }
+        }
+        self.is_cancelled()
+    }
+}
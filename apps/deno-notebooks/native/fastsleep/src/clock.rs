@@ -0,0 +1,42 @@
+//! The fixed mapping from the fast_now_us clock to wall-clock time.
+
+use std::sync::OnceLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Interleaved (monotonic, wall, monotonic) samples taken for the mapping.
+const MAPPING_SAMPLES: usize = 16;
+
+/// One monotonic µs value paired with the Unix time sampled alongside it.
+pub struct WallClockAnchor {
+    pub monotonic_us: u64,
+    pub unix_us: u64,
+    /// Half the tightest monotonic bracket around the wall-clock read.
+    pub error_bound_us: u64,
+}
+
+static ANCHOR: OnceLock<WallClockAnchor> = OnceLock::new();
+
+/// Derived once and kept for the life of the process, so the mapping does not
+/// jump when the wall clock is adjusted.
+pub fn wall_clock_anchor() -> &'static WallClockAnchor {
+    ANCHOR.get_or_init(|| {
+        let mut best: Option<(u64, u64, u64)> = None;
+        for _ in 0..MAPPING_SAMPLES {
+            let before = crate::now_us();
+            let wall = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_micros().min(u64::MAX as u128) as u64);
+            let after = crate::now_us();
+            let width = after - before;
+            if best.is_none_or(|(_, _, w)| width < w) {
+                best = Some((before + width / 2, wall, width));
+            }
+        }
+        let (monotonic_us, unix_us, width) = best.unwrap_or((crate::now_us(), 0, 0));
+        WallClockAnchor {
+            monotonic_us,
+            unix_us,
+            error_bound_us: width.div_ceil(2),
+        }
+    })
+}
@@ -0,0 +1,147 @@
+use spin_sleep::{SpinSleeper, SpinStrategy};
+use std::sync::atomic::{AtomicU32, AtomicU8, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::mock;
+
+/// Longest single park. Writers that only store to the flag (e.g. a JS
+/// `Atomics.store`, which does not wake native waiters) are noticed within
+/// this; writers going through `wake` interrupt the park at once.
+const MAX_PARK: Duration = Duration::from_millis(1);
+
+/// Wait until `flag` satisfies `expected` (any nonzero value when `expected`
+/// is 0, otherwise exactly `expected`) or `deadline` passes. The bulk of the
+/// wait parks on the address; the last `native_accuracy_ns` spins. In mock
+/// mode it checks the flag once and advances the virtual clock to `deadline`.
+/// Returns true if the flag was set.
+pub fn wait_until(flag: &AtomicU32, expected: u32, sleeper: SpinSleeper, deadline: Instant) -> bool {
+    let satisfied = |v: u32| if expected == 0 { v != 0 } else { v == expected };
+    if mock::enabled() {
+        if satisfied(flag.load(Ordering::Acquire)) {
+            return true;
+        }
+        mock::sleep_until(deadline);
+        return satisfied(flag.load(Ordering::Acquire));
+    }
+    mock::note_real_sleep();
+    let accuracy = Duration::from_nanos(sleeper.native_accuracy_ns() as u64);
+    loop {
+        let value = flag.load(Ordering::Acquire);
+        if satisfied(value) {
+            return true;
+        }
+        let now = Instant::now();
+        if now + accuracy >= deadline {
+            break;
+        }
+        park(flag, value, (deadline - now - accuracy).min(MAX_PARK));
+    }
+    spin_until(sleeper, deadline, || satisfied(flag.load(Ordering::Acquire)))
+}
+
+/// Wait until `signal` is nonzero or `deadline` passes. A byte cannot be
+/// parked on, so the bulk of the wait sleeps in slices of at most MAX_PARK
+/// and the last `native_accuracy_ns` spins; mock mode behaves as in
+/// `wait_until`. Returns true if it was signalled.
+pub fn wait_signal(signal: &AtomicU8, sleeper: SpinSleeper, deadline: Instant) -> bool {
+    if mock::enabled() {
+        if signal.load(Ordering::Acquire) != 0 {
+            return true;
+        }
+        mock::sleep_until(deadline);
+        return signal.load(Ordering::Acquire) != 0;
+    }
+    mock::note_real_sleep();
+    let accuracy = Duration::from_nanos(sleeper.native_accuracy_ns() as u64);
+    loop {
+        if signal.load(Ordering::Acquire) != 0 {
+            return true;
+        }
+        let now = Instant::now();
+        if now + accuracy >= deadline {
+            break;
+        }
+        std::thread::sleep((deadline - now - accuracy).min(MAX_PARK));
+    }
+    spin_until(sleeper, deadline, || signal.load(Ordering::Acquire) != 0)
+}
+
+/// Spin with `sleeper`'s strategy until `done` or `deadline`; true if done.
+fn spin_until(sleeper: SpinSleeper, deadline: Instant, done: impl Fn() -> bool) -> bool {
+    while Instant::now() < deadline {
+        if done() {
+            return true;
+        }
+        match sleeper.spin_strategy() {
+            SpinStrategy::YieldThread => std::thread::yield_now(),
+            _ => std::hint::spin_loop(),
+        }
+    }
+    done()
+}
+
+/// Store `value` and wake every thread parked in `wait_until` on `flag`.
+pub fn set_and_wake(flag: &AtomicU32, value: u32) {
+    flag.store(value, Ordering::Release);
+    wake_all(flag);
+}
+
+/// Sleep while `*flag == current`, for at most `timeout`. May return early.
+#[cfg(target_os = "linux")]
+fn park(flag: &AtomicU32, current: u32, timeout: Duration) {
+    let ts = libc::timespec {
+        tv_sec: timeout.as_secs() as libc::time_t,
+        tv_nsec: timeout.subsec_nanos() as libc::c_long,
+    };
+    unsafe {
+        libc::syscall(
+            libc::SYS_futex,
+            flag.as_ptr(),
+            libc::FUTEX_WAIT | libc::FUTEX_PRIVATE_FLAG,
+            current,
+            &ts as *const libc::timespec,
+        );
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn wake_all(flag: &AtomicU32) {
+    unsafe {
+        libc::syscall(
+            libc::SYS_futex,
+            flag.as_ptr(),
+            libc::FUTEX_WAKE | libc::FUTEX_PRIVATE_FLAG,
+            i32::MAX,
+        );
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn park(flag: &AtomicU32, current: u32, timeout: Duration) {
+    use windows::Win32::System::Threading::WaitOnAddress;
+    // WaitOnAddress takes whole ms; round up so a short park still yields.
+    let ms = timeout.as_micros().div_ceil(1_000).max(1) as u32;
+    unsafe {
+        let _ = WaitOnAddress(
+            flag.as_ptr() as *const std::ffi::c_void,
+            &current as *const u32 as *const std::ffi::c_void,
+            std::mem::size_of::<u32>(),
+            ms,
+        );
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn wake_all(flag: &AtomicU32) {
+    use windows::Win32::System::Threading::WakeByAddressAll;
+    unsafe { WakeByAddressAll(flag.as_ptr() as *const std::ffi::c_void) };
+}
+
+// macOS has no public address-wait API before 14.4, so park with short sleeps.
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+fn park(_flag: &AtomicU32, _current: u32, timeout: Duration) {
+    std::thread::sleep(timeout.min(Duration::from_micros(250)));
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+fn wake_all(_flag: &AtomicU32) {}
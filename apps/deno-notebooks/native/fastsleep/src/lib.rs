@@ -1,20 +1,183 @@
+mod calibrate;
+mod cancel;
+mod clock;
+mod flag_wait;
+mod stats;
+mod tick_loop;
+mod miss;
+mod mock;
+mod selftest;
+mod ticker;
+mod timer_resolution;
+mod timers;
+
+use avtools_ffi::shutdown::{self, Stage};
+use avtools_ffi::{buffer, catch_panic, error, metrics, options, thread_priority, Error};
+use cancel::CancelToken;
+use once_cell::sync::Lazy;
 use spin_sleep::{SpinSleeper, SpinStrategy};
-use std::sync::OnceLock;
-use std::time::Duration;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, AtomicU8, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, Once, RwLock, TryLockError};
+use std::time::{Duration, Instant};
+use tick_loop::{TickCallback, TickLoop};
+use ticker::{Ticker, TickerOptions};
+use timer_resolution::Owner;
+use timers::{BatchCallback, TimerCallback};
+
+static NEXT_HANDLE: AtomicU32 = AtomicU32::new(1);
+static SLEEPERS: Lazy<Mutex<HashMap<u32, SpinSleeper>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+static TOKENS: Lazy<Mutex<HashMap<u32, Arc<CancelToken>>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+static TICKERS: Lazy<Mutex<HashMap<u32, Arc<Mutex<Ticker>>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+static LOOPS: Lazy<Mutex<HashMap<u32, TickLoop>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+static SHUTDOWN_HOOK: Once = Once::new();
+
+/// How long avtools_shutdown_all waits for a loop's or timer's in-flight callback.
+const SHUTDOWN_JOIN_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Returned by fast_sleeper_sleep_until_us for an unknown handle.
+pub const INVALID_HANDLE_LATENESS: i64 = i64::MIN;
+
+/// Origin of the fast_now_us clock: the shared avtools clock, so deadlines
+/// line up with midi_bridge and deno_window timestamps.
+fn epoch() -> Instant {
+    avtools_clock::epoch()
+}
+
+fn now_ns() -> u64 {
+    avtools_clock::instant_ns()
+}
+
+fn now_us() -> u64 {
+    now_ns() / 1_000
+}
+
+/// Sleep until `deadline` (offset from the epoch) and return the signed
+/// lateness in nanoseconds.
+fn sleep_until(sleeper: SpinSleeper, deadline: Duration) -> i64 {
+    if let Some(target) = epoch().checked_add(deadline) {
+        let now = Instant::now();
+        if target > now {
+            stats::sleep(sleeper, target - now);
+        }
+    }
+    signed_diff(now_ns(), deadline.as_nanos().min(u64::MAX as u128) as u64)
+}
+
+fn signed_diff(a: u64, b: u64) -> i64 {
+    if a >= b {
+        (a - b).min(i64::MAX as u64) as i64
+    } else {
+        -((b - a).min(i64::MAX as u64) as i64)
+    }
+}
+
+#[derive(Clone, Copy)]
+struct SleeperConfig {
+    native_accuracy_us: u32,
+    strategy: u32,
+}
+
+// Default tuned for “good enough” short sleeps:
+// - 200µs native accuracy (thread::sleep bulk, spin tail)
+// - per-OS default spin strategy (YieldThread on non-Windows, SpinLoopHint on Windows)
+// See spin_sleep docs for details. (You can override via fast_sleep_configure.)
+static CONFIG: RwLock<SleeperConfig> = RwLock::new(SleeperConfig {
+    native_accuracy_us: 200,
+    strategy: 0,
+});
+
+/// Held across fast_sleep_configure and fast_sleep_init so overlapping calls
+/// apply one after the other. CONFIG itself is only locked for the store, so
+/// sleeps reading it never make init report contention.
+static CONFIGURE_LOCK: Mutex<()> = Mutex::new(());
+
+/// Strategy 3: strategy 2 (SpinLoopHint) with the native-accuracy window
+/// capped at MAX_BUSY_SPIN_US. SpinLoopHint already never yields or sleeps in
+/// the final approach: the whole window before a deadline is a tight
+/// `spin_loop()` on the clock and only the part of a wait beyond it uses
+/// native sleep, so 3 only adds the cap that keeps that spin affordable.
+const STRATEGY_BUSY_SPIN: u32 = 3;
+
+/// Longest busy-spin window accepted for STRATEGY_BUSY_SPIN. Spinning a core
+/// for longer than this per wait costs far more CPU than it buys in accuracy.
+pub const MAX_BUSY_SPIN_US: u32 = 1_000;
+
+fn busy_spin_too_long(native_accuracy_us: u32, strategy: u32) -> bool {
+    strategy == STRATEGY_BUSY_SPIN && native_accuracy_us > MAX_BUSY_SPIN_US
+}
 
-static SLEEPER: OnceLock<SpinSleeper> = OnceLock::new();
+/// fastsleep's last-error codes (see avtools_ffi::error for the shared ones).
+const ERR_BUSY_SPIN_TOO_LONG: i32 = 300;
 
-fn sleeper() -> &'static SpinSleeper {
-    // Default tuned for “good enough” short sleeps:
-    // - 200µs native accuracy (thread::sleep bulk, spin tail)
-    // - per-OS default spin strategy (YieldThread on non-Windows, SpinLoopHint on Windows)
-    // See spin_sleep docs for details. (You can override via fast_sleep_init.)
-    SLEEPER.get_or_init(|| {
-        SpinSleeper::new(200_000).with_spin_strategy(SpinStrategy::default())
-    })
+/// build_sleeper, recording why a configuration was rejected.
+fn checked_sleeper(native_accuracy_us: u32, strategy: u32) -> Result<SpinSleeper, Error> {
+    if busy_spin_too_long(native_accuracy_us, strategy) {
+        return Err(Error::new(
+            ERR_BUSY_SPIN_TOO_LONG,
+            format!("busy-spin window {native_accuracy_us}us exceeds {MAX_BUSY_SPIN_US}us"),
+        ));
+    }
+    build_sleeper(native_accuracy_us, strategy)
+        .ok_or_else(|| Error::new(error::INVALID_ARGUMENT, format!("unknown strategy {strategy}")))
+}
+
+fn build_sleeper(native_accuracy_us: u32, strategy: u32) -> Option<SpinSleeper> {
+    let strat = match strategy {
+        0 => SpinStrategy::default(),
+        1 => SpinStrategy::YieldThread,
+        2 => SpinStrategy::SpinLoopHint,
+        STRATEGY_BUSY_SPIN if !busy_spin_too_long(native_accuracy_us, strategy) => {
+            SpinStrategy::SpinLoopHint
+        }
+        _ => return None,
+    };
+
+    let acc_ns_u64 = (native_accuracy_us as u64).saturating_mul(1_000);
+    let acc_ns_u32 = acc_ns_u64.min(u32::MAX as u64) as u32;
+
+    Some(SpinSleeper::new(acc_ns_u32).with_spin_strategy(strat))
 }
 
-/// Optional: configure the global sleeper ONCE, before calling fast_sleep_us.
+/// 0 = configured behaviour, 1 = low power (see fast_sleep_set_power_mode).
+static POWER_MODE: AtomicU32 = AtomicU32::new(0);
+const POWER_MODE_LOW: u32 = 1;
+
+pub(crate) fn power_mode() -> u32 {
+    POWER_MODE.load(Ordering::Relaxed)
+}
+
+/// Native sleep only: with zero assumed inaccuracy there is no spin tail.
+fn low_power_sleeper() -> SpinSleeper {
+    SpinSleeper::new(0).with_spin_strategy(SpinStrategy::YieldThread)
+}
+
+/// In low-power mode, round a ticker/timer deadline up to the next whole
+/// millisecond of the fast_now_us clock so wakeups coalesce; otherwise unchanged.
+pub(crate) fn power_adjust(deadline: Instant) -> Instant {
+    if power_mode() != POWER_MODE_LOW {
+        return deadline;
+    }
+    let offset = deadline.saturating_duration_since(epoch());
+    let rounded_ms = offset.as_nanos().div_ceil(1_000_000);
+    let rounded = Duration::from_millis(rounded_ms.min(u64::MAX as u128) as u64);
+    epoch().checked_add(rounded).unwrap_or(deadline).max(deadline)
+}
+
+/// Snapshot of the current global sleeper. The lock is only held for the copy,
+/// so reconfiguring never waits on (or disturbs) sleeps already in progress.
+pub(crate) fn sleeper() -> SpinSleeper {
+    if power_mode() == POWER_MODE_LOW {
+        return low_power_sleeper();
+    }
+    let cfg = *CONFIG.read().unwrap_or_else(|e| e.into_inner());
+    build_sleeper(cfg.native_accuracy_us, cfg.strategy)
+        .unwrap_or_else(|| SpinSleeper::new(200_000).with_spin_strategy(SpinStrategy::default()))
+}
+
+/// Configure the global sleeper. May be called at any time; the new settings
+/// apply to sleeps that start afterwards.
 ///
 /// native_accuracy_us:
 ///   How much "inaccuracy" to assume for native thread::sleep. Bigger => less spinning (less CPU), often more jitter.
@@ -22,34 +185,1247 @@ fn sleeper() -> &'static SpinSleeper {
 ///   0 = per-OS default
 ///   1 = YieldThread
 ///   2 = SpinLoopHint
+///   3 = BusySpin: SpinLoopHint with native_accuracy_us (the busy-spin
+///       window) capped at MAX_BUSY_SPIN_US; waits shorter than it never
+///       make a syscall
 ///
 /// Returns:
 ///   0 = ok
-///   1 = already initialized
 ///  -1 = invalid strategy
+///  -2 = BusySpin window above MAX_BUSY_SPIN_US
 #[no_mangle]
-pub extern "C" fn fast_sleep_init(native_accuracy_us: u32, strategy: u32) -> i32 {
-    let strat = match strategy {
-        0 => SpinStrategy::default(),
-        1 => SpinStrategy::YieldThread,
-        2 => SpinStrategy::SpinLoopHint,
-        _ => return -1,
-    };
+#[catch_panic]
+pub extern "C" fn fast_sleep_configure(native_accuracy_us: u32, strategy: u32) -> i32 {
+    let configuring = CONFIGURE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    reconfigure(&configuring, native_accuracy_us, strategy)
+}
 
-    let acc_ns_u64 = (native_accuracy_us as u64).saturating_mul(1_000);
-    let acc_ns_u32 = acc_ns_u64.min(u32::MAX as u64) as u32;
+/// Validate and store a new configuration, returning fast_sleep_configure's
+/// codes. Every writer of CONFIG goes through here while holding
+/// CONFIGURE_LOCK, which `_configuring` stands for.
+fn reconfigure(_configuring: &MutexGuard<'_, ()>, native_accuracy_us: u32, strategy: u32) -> i32 {
+    if let Err(err) = checked_sleeper(native_accuracy_us, strategy) {
+        let rc = if err.code == ERR_BUSY_SPIN_TOO_LONG { -2 } else { -1 };
+        error::set(err);
+        return rc;
+    }
+    error::clear();
+    *CONFIG.write().unwrap_or_else(|e| e.into_inner()) = SleeperConfig {
+        native_accuracy_us,
+        strategy,
+    };
+    0
+}
 
-    let s = SpinSleeper::new(acc_ns_u32).with_spin_strategy(strat);
+/// Compatibility alias for fast_sleep_configure.
+///
+/// Returns:
+///   0 = ok
+///   1 = another fast_sleep_configure or fast_sleep_init call was in
+///       progress; settings left unchanged (BUSY)
+///  -1 = invalid strategy
+///  -2 = BusySpin window above MAX_BUSY_SPIN_US
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn fast_sleep_init(native_accuracy_us: u32, strategy: u32) -> i32 {
+    let configuring = match CONFIGURE_LOCK.try_lock() {
+        Ok(guard) => guard,
+        Err(TryLockError::Poisoned(e)) => e.into_inner(),
+        Err(TryLockError::WouldBlock) => {
+            error::fail(error::BUSY, "another configure call is in progress");
+            return 1;
+        }
+    };
+    reconfigure(&configuring, native_accuracy_us, strategy)
+}
 
-    match SLEEPER.set(s) {
-        Ok(_) => 0,
-        Err(_) => 1,
+/// Read back the current global configuration (as passed to
+/// fast_sleep_configure) and the power mode (as passed to
+/// fast_sleep_set_power_mode).
+///
+/// # Safety
+/// Each out pointer must be null or valid for a `u32` write.
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn fast_sleep_get_config(
+    out_accuracy_us: *mut u32,
+    out_strategy: *mut u32,
+    out_power_mode: *mut u32,
+) {
+    let cfg = *CONFIG.read().unwrap_or_else(|e| e.into_inner());
+    if !out_accuracy_us.is_null() {
+        *out_accuracy_us = cfg.native_accuracy_us;
+    }
+    if !out_strategy.is_null() {
+        *out_strategy = cfg.strategy;
+    }
+    if !out_power_mode.is_null() {
+        *out_power_mode = power_mode();
     }
 }
 
 /// Sleep for `us` microseconds using spin_sleep.
 /// Intended to be called from Deno via FFI with `nonblocking: true`.
+/// Capped at u32::MAX µs (~71 minutes); use fast_sleep_ns for longer sleeps.
 #[no_mangle]
+#[catch_panic]
 pub extern "C" fn fast_sleep_us(us: u32) {
-    sleeper().sleep(Duration::from_micros(us as u64));
+    fast_sleep_ns((us as u64).saturating_mul(1_000));
+}
+
+/// Sleep for `ns` nanoseconds using the same sleeper as fast_sleep_us.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn fast_sleep_ns(ns: u64) {
+    stats::sleep(sleeper(), Duration::from_nanos(ns));
+}
+
+/// Test mode: from now on every sleep that goes through the global or a
+/// handle sleeper (relative sleeps, deadlines, tickers, cancellable sleeps and
+/// flag waits) returns at once and advances a virtual clock by
+/// `requested / scale_factor`; a scale of 0 advances it by the requested time.
+/// Must be called before the first sleep and cannot be turned off again.
+///
+/// Returns 0 on success, -1 for a negative or non-finite scale, -2 if a real
+/// sleep has already happened.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn fast_sleep_enable_mock(scale_factor: f64) -> i32 {
+    if !scale_factor.is_finite() || scale_factor < 0.0 {
+        error::fail(error::INVALID_ARGUMENT, format!("invalid mock scale factor {scale_factor}"));
+        return -1;
+    }
+    if !mock::enabled() && !mock::enable(scale_factor) {
+        error::fail(error::BUSY, "fast_sleep_enable_mock called after a real sleep");
+        return -2;
+    }
+    error::clear();
+    0
+}
+
+/// Total time slept in mock mode, in ns (0 if the mock is not enabled).
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn fast_sleep_get_virtual_time_ns() -> u64 {
+    mock::virtual_time_ns()
+}
+
+/// fast_sleep_us, returning the actual elapsed time in nanoseconds as measured
+/// natively around the sleep.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn fast_sleep_us_measured(us: u32) -> u64 {
+    fast_sleep_ns_measured((us as u64).saturating_mul(1_000))
+}
+
+/// fast_sleep_ns, returning the actual elapsed time in nanoseconds.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn fast_sleep_ns_measured(ns: u64) -> u64 {
+    let start = Instant::now();
+    fast_sleep_ns(ns);
+    start.elapsed().as_nanos().min(u64::MAX as u128) as u64
+}
+
+/// Raw platform monotonic clock in nanoseconds: CLOCK_MONOTONIC_RAW on Linux,
+/// mach_absolute_time on macOS, QueryPerformanceCounter on Windows. The origin
+/// is platform-defined (typically boot), not the fast_now_us epoch, so use it
+/// for measuring intervals rather than as a sleep deadline.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn fast_sleep_clock_ns() -> u64 {
+    avtools_clock::raw_ns()
+}
+
+/// Mapping from the fast_now_us clock to Unix time, as JSON:
+/// `{monotonic_us, unix_ms, anchor_monotonic_us, anchor_unix_ms, error_bound_us}`.
+/// `unix_ms` is the wall-clock time corresponding to `monotonic_us` (now). The
+/// anchor pair is sampled once per process, taking the tightest of several
+/// interleaved reads, so the mapping is stable even if the system clock moves.
+/// Returns the byte length; nothing is written if `out_cap` is too small.
+///
+/// # Safety
+/// `out` must be null or valid for `out_cap` bytes of writes.
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn fast_clock_info(out: *mut u8, out_cap: u32) -> u32 {
+    let anchor = clock::wall_clock_anchor();
+    let now = now_us();
+    let unix_us = anchor.unix_us as i128 + now as i128 - anchor.monotonic_us as i128;
+    let json = format!(
+        "{{\"monotonic_us\":{},\"unix_ms\":{:.3},\"anchor_monotonic_us\":{},\
+         \"anchor_unix_ms\":{:.3},\"error_bound_us\":{}}}",
+        now,
+        unix_us as f64 / 1_000.0,
+        anchor.monotonic_us,
+        anchor.unix_us as f64 / 1_000.0,
+        anchor.error_bound_us
+    );
+    buffer::write(json.as_bytes(), out, out_cap)
+}
+
+/// Version of the exported C ABI. Bump whenever an export is added, removed or
+/// changes signature, and refresh `abi.txt` (midi/tests/06_abi_snapshot.ts).
+pub const ABI_VERSION: u32 = 12;
+
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn fast_sleep_abi_version() -> u32 {
+    ABI_VERSION
+}
+
+/// Features available in this build as a JSON object of booleans. Returns the
+/// byte length; nothing is written if `cap` is too small.
+///
+/// # Safety
+/// `out` must be null or valid for `cap` bytes of writes.
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn fast_sleep_capabilities(out: *mut u8, cap: u32) -> u32 {
+    let json = format!(
+        "{{\"timer_resolution\":{},\"address_wait\":{},\"thread_priority\":{},\
+         \"busy_spin\":true,\"timers\":true,\"batch_timers\":true,\"tickers\":true,\
+         \"selftest\":true,\"mock_clock\":true,\"log_callback\":true,\"last_error\":true,\
+         \"shutdown_all\":true,\"signal_wait\":true,\"tracing\":{}}}",
+        timer_resolution::SUPPORTED,
+        cfg!(any(target_os = "linux", target_os = "windows")),
+        cfg!(any(target_os = "linux", target_os = "macos", target_os = "windows")),
+        avtools_trace::AVAILABLE,
+    );
+    buffer::write(json.as_bytes(), out, cap)
+}
+
+/// Code of the calling thread's last failed call (0 if the last fallible call
+/// succeeded). fastsleep codes are 300–399; see avtools_ffi::error. Calls
+/// made `nonblocking` from Deno run on other threads and are not visible here.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn fast_sleep_last_error_code() -> i32 {
+    error::code()
+}
+
+/// Message for fast_sleep_last_error_code. Returns the byte length (0 if
+/// none); nothing is written if `cap` is too small.
+///
+/// # Safety
+/// `out` must be null or valid for `cap` bytes of writes.
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn fast_sleep_last_error_message(out: *mut u8, cap: u32) -> u32 {
+    error::message(out, cap)
+}
+
+/// Message of the last export that panicked on this thread (the library keeps
+/// working; the export returned its failure value). Unlike the last error it
+/// survives later calls. Returns the byte length, 0 if nothing has panicked.
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn fast_sleep_get_last_panic(out: *mut u8, cap: u32) -> u32 {
+    avtools_ffi::panic::last_panic(out, cap)
+}
+
+/// Route this library's log messages to `cb` instead of stderr; null restores
+/// stderr. `cb` may be called from any thread, and is never called again once
+/// this returns with a different callback.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn fast_sleep_set_log_callback(cb: Option<extern "C" fn(u32, *const u8, u32)>) {
+    avtools_log::set_callback(cb);
+}
+
+/// Most verbose level logged: 0 off, 1 error, 2 warn, 3 info (default),
+/// 4 debug. Returns -1 for an unknown level.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn fast_sleep_set_log_level(level: u32) -> i32 {
+    avtools_log::set_level(level)
+}
+
+/// Microseconds on the shared avtools monotonic clock (the OS monotonic clock,
+/// typically counting from boot), also used by midi_bridge and deno_window.
+/// This is the reference clock for absolute deadlines.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn fast_now_us() -> u64 {
+    now_us()
+}
+
+/// Sleep until `deadline_us` on the `fast_now_us` clock.
+///
+/// Returns immediately if the deadline has already passed. The result is the
+/// signed lateness in microseconds (wake time minus deadline); it is only
+/// negative if the deadline is too far out to be represented as an `Instant`.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn fast_sleep_until_us(deadline_us: u64) -> i64 {
+    sleep_until(sleeper(), Duration::from_micros(deadline_us));
+    signed_diff(now_us(), deadline_us)
+}
+
+/// Nanosecond variant of fast_sleep_until_us; `deadline_ns` is on the same
+/// epoch and the lateness is returned in nanoseconds.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn fast_sleep_until_ns(deadline_ns: u64) -> i64 {
+    sleep_until(sleeper(), Duration::from_nanos(deadline_ns))
+}
+
+/// Handle 0 is the global sleeper; anything else must come from fast_sleeper_create.
+fn resolve_sleeper(handle: u32) -> Option<SpinSleeper> {
+    if handle == 0 {
+        return Some(sleeper());
+    }
+    let s = SLEEPERS.lock().unwrap_or_else(|e| e.into_inner()).get(&handle).copied()?;
+    if power_mode() == POWER_MODE_LOW {
+        return Some(low_power_sleeper());
+    }
+    Some(s)
+}
+
+/// Most distinct (accuracy, strategy) pairs kept by the fast_sleep_*_with calls.
+const WITH_CACHE_CAP: usize = 16;
+
+/// Sleepers for the fast_sleep_*_with calls, most recently used last.
+static WITH_CACHE: Lazy<Mutex<Vec<(SleeperConfig, SpinSleeper)>>> =
+    Lazy::new(|| Mutex::new(Vec::with_capacity(WITH_CACHE_CAP)));
+
+/// Cached sleeper for a one-off (accuracy, strategy) pair, or the fast_sleep_configure
+/// error code. Low-power mode still applies.
+fn sleeper_with(native_accuracy_us: u32, strategy: u32) -> Result<SpinSleeper, i32> {
+    if busy_spin_too_long(native_accuracy_us, strategy) {
+        return Err(-2);
+    }
+    let mut cache = WITH_CACHE.lock().unwrap_or_else(|e| e.into_inner());
+    let pos = cache.iter().position(|(cfg, _)| {
+        cfg.native_accuracy_us == native_accuracy_us && cfg.strategy == strategy
+    });
+    let s = match pos {
+        Some(i) => {
+            let entry = cache.remove(i);
+            cache.push(entry);
+            entry.1
+        }
+        None => {
+            let s = build_sleeper(native_accuracy_us, strategy).ok_or(-1)?;
+            if cache.len() >= WITH_CACHE_CAP {
+                cache.remove(0);
+            }
+            let cfg = SleeperConfig {
+                native_accuracy_us,
+                strategy,
+            };
+            cache.push((cfg, s));
+            s
+        }
+    };
+    drop(cache);
+    if power_mode() == POWER_MODE_LOW {
+        return Ok(low_power_sleeper());
+    }
+    Ok(s)
+}
+
+/// Sleep for `us` with a one-off accuracy/strategy (same parameters as
+/// fast_sleep_configure), leaving the global configuration untouched.
+///
+/// Returns 0 after sleeping, -1 for an invalid strategy, -2 for a BusySpin
+/// window above MAX_BUSY_SPIN_US.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn fast_sleep_us_with(us: u32, native_accuracy_us: u32, strategy: u32) -> i32 {
+    fast_sleep_ns_with((us as u64).saturating_mul(1_000), native_accuracy_us, strategy)
+}
+
+/// Nanosecond variant of fast_sleep_us_with.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn fast_sleep_ns_with(ns: u64, native_accuracy_us: u32, strategy: u32) -> i32 {
+    match sleeper_with(native_accuracy_us, strategy) {
+        Ok(s) => {
+            stats::sleep(s, Duration::from_nanos(ns));
+            0
+        }
+        Err(rc) => rc,
+    }
+}
+
+/// fast_sleep_until_us with a one-off accuracy/strategy. Same return codes as
+/// fast_sleep_us_with.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn fast_sleep_until_us_with(
+    deadline_us: u64,
+    native_accuracy_us: u32,
+    strategy: u32,
+) -> i32 {
+    match sleeper_with(native_accuracy_us, strategy) {
+        Ok(s) => {
+            sleep_until(s, Duration::from_micros(deadline_us));
+            0
+        }
+        Err(rc) => rc,
+    }
+}
+
+/// Create an independent sleeper with its own accuracy/strategy (same
+/// parameters as fast_sleep_configure). Returns 0 for an invalid strategy or
+/// a BusySpin window above MAX_BUSY_SPIN_US.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn fast_sleeper_create(native_accuracy_us: u32, strategy: u32) -> u32 {
+    let s = match error::record(checked_sleeper(native_accuracy_us, strategy)) {
+        Some(s) => s,
+        None => return 0,
+    };
+    let id = NEXT_HANDLE.fetch_add(1, Ordering::Relaxed);
+    SLEEPERS.lock().unwrap_or_else(|e| e.into_inner()).insert(id, s);
+    id
+}
+
+/// Returns 0 after sleeping, -1 for an unknown handle.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn fast_sleeper_sleep_us(handle: u32, us: u32) -> i32 {
+    match resolve_sleeper(handle) {
+        Some(s) => {
+            stats::sleep(s, Duration::from_micros(us as u64));
+            0
+        }
+        None => -1,
+    }
+}
+
+/// Like fast_sleep_until_us on a specific sleeper. Returns
+/// INVALID_HANDLE_LATENESS (i64::MIN) for an unknown handle.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn fast_sleeper_sleep_until_us(handle: u32, deadline_us: u64) -> i64 {
+    match resolve_sleeper(handle) {
+        Some(s) => {
+            sleep_until(s, Duration::from_micros(deadline_us));
+            signed_diff(now_us(), deadline_us)
+        }
+        None => INVALID_HANDLE_LATENESS,
+    }
+}
+
+/// Returns 0 if the sleeper was removed, -1 for an unknown handle (or 0, the global sleeper).
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn fast_sleeper_destroy(handle: u32) -> i32 {
+    match SLEEPERS.lock().unwrap_or_else(|e| e.into_inner()).remove(&handle) {
+        Some(_) => 0,
+        None => -1,
+    }
+}
+
+fn token(handle: u32) -> Option<Arc<CancelToken>> {
+    TOKENS.lock().unwrap_or_else(|e| e.into_inner()).get(&handle).cloned()
+}
+
+/// Create a cancellation token for fast_sleep_cancellable_us.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn fast_sleep_token_create() -> u32 {
+    let id = NEXT_HANDLE.fetch_add(1, Ordering::Relaxed);
+    TOKENS.lock().unwrap_or_else(|e| e.into_inner()).insert(id, Arc::new(CancelToken::new()));
+    id
+}
+
+/// Sleep for `us` microseconds on the global sleeper unless `token` is cancelled.
+///
+/// Returns:
+///   0 = completed
+///   1 = cancelled (including a token that was already cancelled)
+///  -1 = unknown token
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn fast_sleep_cancellable_us(token_handle: u32, us: u32) -> i32 {
+    let t = match token(token_handle) {
+        Some(t) => t,
+        None => return -1,
+    };
+    let start = Instant::now();
+    let requested = Duration::from_micros(us as u64);
+    if t.sleep_until(sleeper(), start + requested) {
+        1
+    } else {
+        miss::check(requested, start.elapsed());
+        0
+    }
+}
+
+/// Sleep until the earliest of `count` absolute deadlines (µs on the
+/// fast_now_us clock) and return its index; ties go to the lower index. If
+/// any deadline has already passed, returns the smallest such index at once.
+/// `token_handle` (0 = none) cancels the wait early.
+///
+/// Returns:
+///   n = index (n >= 0) of the deadline that expired
+///  -1 = cancelled by the token (including a token that was already cancelled)
+///  -2 = null `deadlines_ptr`
+///  -3 = `count` is 0 or above i32::MAX
+///  -4 = unknown token
+///
+/// # Safety
+/// `deadlines_ptr` must be null or valid for `count` `u64` reads.
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn fast_sleep_until_any(
+    deadlines_ptr: *const u64,
+    count: u32,
+    token_handle: u32,
+) -> i32 {
+    if deadlines_ptr.is_null() {
+        return -2;
+    }
+    if count == 0 || count > i32::MAX as u32 {
+        return -3;
+    }
+    let t = match token_handle {
+        0 => None,
+        handle => match token(handle) {
+            Some(t) => Some(t),
+            None => return -4,
+        },
+    };
+    let deadlines = std::slice::from_raw_parts(deadlines_ptr, count as usize);
+
+    let now = now_us();
+    if let Some(past) = deadlines.iter().position(|&d| d <= now) {
+        return past as i32;
+    }
+    // min_by_key keeps the first of equal minima.
+    let (index, &earliest) = match deadlines.iter().enumerate().min_by_key(|(_, &d)| d) {
+        Some(found) => found,
+        None => return -3,
+    };
+    match t {
+        Some(t) => {
+            let target = epoch().checked_add(Duration::from_micros(earliest));
+            let cancelled = match target {
+                Some(target) => {
+                    let start = Instant::now();
+                    let cancelled = t.sleep_until(sleeper(), target);
+                    if !cancelled {
+                        miss::check(target.saturating_duration_since(start), start.elapsed());
+                    }
+                    cancelled
+                }
+                None => {
+                    sleep_until(sleeper(), Duration::from_micros(earliest));
+                    t.is_cancelled()
+                }
+            };
+            if cancelled {
+                -1
+            } else {
+                index as i32
+            }
+        }
+        None => {
+            sleep_until(sleeper(), Duration::from_micros(earliest));
+            index as i32
+        }
+    }
+}
+
+/// Wait until the caller-owned 32-bit flag at `flag_ptr` is set or the
+/// absolute deadline (µs on the fast_now_us clock) passes. The flag counts as
+/// set when it is nonzero, or, if `expected_nonzero` is nonzero, when it equals
+/// that value. The bulk of the wait parks on the address (futex /
+/// WaitOnAddress); the tail spins like the other sleeps. Stores made through
+/// fast_set_wake_flag wake the wait immediately; plain stores (e.g. JS
+/// `Atomics.store` on a SharedArrayBuffer) are seen within about 1ms, or
+/// exactly if they land in the spin tail.
+///
+/// Returns:
+///   1 = the flag was set
+///   0 = the deadline passed first
+///  -1 = `flag_ptr` is null or not 4-byte aligned
+///
+/// # Safety
+/// `flag_ptr` must point to a 4-byte-aligned `u32` that stays valid for the
+/// whole wait and is only written atomically (or by fast_set_wake_flag).
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn fast_wait_flag_until_us(
+    flag_ptr: *const u32,
+    expected_nonzero: u32,
+    deadline_us: u64,
+) -> i32 {
+    if flag_ptr.is_null() || !flag_ptr.is_aligned() {
+        return -1;
+    }
+    let flag = AtomicU32::from_ptr(flag_ptr as *mut u32);
+    let deadline = epoch()
+        .checked_add(Duration::from_micros(deadline_us))
+        .unwrap_or_else(|| Instant::now() + Duration::from_secs(86_400 * 365));
+    let start = Instant::now();
+    let set = flag_wait::wait_until(flag, expected_nonzero, sleeper(), deadline);
+    if !set {
+        miss::check(deadline.saturating_duration_since(start), start.elapsed());
+    }
+    set as i32
+}
+
+/// Wait until the byte at `signal_ptr` (e.g. in a SharedArrayBuffer that JS
+/// sets with `Atomics.store`) becomes nonzero or `timeout_us` passes, sleeping
+/// and then spinning like fast_sleep_us with the configured sleeper. Plain
+/// stores are seen within about 1ms, or exactly if they land in the spin
+/// tail. Returns 1 if signalled, 0 on timeout or for a null pointer
+/// (INVALID_ARGUMENT).
+///
+/// # Safety
+/// `signal_ptr` must point to a byte that stays valid for the whole wait and
+/// is only written atomically.
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn fast_sleep_until_signal(signal_ptr: *const u8, timeout_us: u64) -> u8 {
+    if signal_ptr.is_null() {
+        error::fail(error::INVALID_ARGUMENT, "signal pointer is null");
+        return 0;
+    }
+    let start = Instant::now();
+    let timeout = Duration::from_micros(timeout_us);
+    let deadline = start
+        .checked_add(timeout)
+        .unwrap_or_else(|| start + Duration::from_secs(86_400 * 365));
+    let signalled = flag_wait::wait_signal(AtomicU8::from_ptr(signal_ptr as *mut u8), sleeper(), deadline);
+    if !signalled {
+        miss::check(timeout, start.elapsed());
+    }
+    signalled as u8
+}
+
+/// Store `value` into the flag at `flag_ptr` and wake any
+/// fast_wait_flag_until_us parked on it. Returns -1 for a null or misaligned
+/// pointer, 0 otherwise.
+///
+/// # Safety
+/// `flag_ptr` must point to a 4-byte-aligned `u32` valid for atomic writes.
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn fast_set_wake_flag(flag_ptr: *mut u32, value: u32) -> i32 {
+    if flag_ptr.is_null() || !flag_ptr.is_aligned() {
+        return -1;
+    }
+    flag_wait::set_and_wake(AtomicU32::from_ptr(flag_ptr), value);
+    0
+}
+
+/// Wake every sleep using `token` and make future sleeps on it return 1
+/// immediately until fast_sleep_token_reset. Returns -1 for an unknown token.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn fast_sleep_cancel(token_handle: u32) -> i32 {
+    match token(token_handle) {
+        Some(t) => {
+            t.cancel();
+            0
+        }
+        None => -1,
+    }
+}
+
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn fast_sleep_token_reset(token_handle: u32) -> i32 {
+    match token(token_handle) {
+        Some(t) => {
+            t.reset();
+            0
+        }
+        None => -1,
+    }
+}
+
+/// Release a token. Sleeps in progress on it are cancelled first.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn fast_sleep_token_destroy(token_handle: u32) -> i32 {
+    match TOKENS.lock().unwrap_or_else(|e| e.into_inner()).remove(&token_handle) {
+        Some(t) => {
+            t.cancel();
+            0
+        }
+        None => -1,
+    }
+}
+
+fn ticker(handle: u32) -> Option<Arc<Mutex<Ticker>>> {
+    TICKERS.lock().unwrap_or_else(|e| e.into_inner()).get(&handle).cloned()
+}
+
+/// Create a drift-free periodic ticker. The first tick is one period from now.
+///
+/// flags:
+///   0x1 = catch up (fire every missed tick) instead of skipping missed ticks
+///
+/// Returns 0 if `period_us` is 0.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn fast_ticker_create(period_us: u32, flags: u32) -> u32 {
+    if period_us == 0 {
+        return 0;
+    }
+    let t = Ticker::new(Duration::from_micros(period_us as u64), flags);
+    let id = NEXT_HANDLE.fetch_add(1, Ordering::Relaxed);
+    TICKERS.lock().unwrap_or_else(|e| e.into_inner()).insert(id, Arc::new(Mutex::new(t)));
+    id
+}
+
+/// Like fast_ticker_create, with the options given as a JSON object:
+/// `{version, period_us, catch_up}` (version 1). `period_us` is required;
+/// unknown fields are rejected. Returns 0 if the options are invalid (the
+/// error names the field).
+///
+/// # Safety
+/// `options_json_ptr` must be null or valid for `options_json_len` bytes.
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn fast_ticker_create_ex(options_json_ptr: *const u8, options_json_len: u32) -> u32 {
+    let Some(options) = error::record(options::parse_raw::<TickerOptions>(options_json_ptr, options_json_len)) else {
+        return 0;
+    };
+    fast_ticker_create(options.period_us, options.flags())
+}
+
+/// Block until the next tick boundary on the global sleeper.
+///
+/// Returns the wakeup lateness in microseconds. If ticks had to be skipped to
+/// get back on schedule the result is negative (-lateness, at most -1) and
+/// fast_ticker_skipped reports how many. Returns INVALID_HANDLE_LATENESS
+/// (i64::MIN) for an unknown handle.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn fast_ticker_wait(handle: u32) -> i64 {
+    let t = match ticker(handle) {
+        Some(t) => t,
+        None => return INVALID_HANDLE_LATENESS,
+    };
+    let plan = t.lock().unwrap_or_else(|e| e.into_inner()).advance();
+    let now = Instant::now();
+    if plan.target > now {
+        stats::sleep(sleeper(), plan.target - now);
+    }
+    let late = Instant::now()
+        .saturating_duration_since(plan.target)
+        .as_micros()
+        .min(i64::MAX as u128) as i64;
+    t.lock().unwrap_or_else(|e| e.into_inner()).record_lateness(late as u64);
+    if plan.skipped > 0 {
+        -late.max(1)
+    } else {
+        late
+    }
+}
+
+/// Ticks skipped by the most recent fast_ticker_wait, or -1 for an unknown handle.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn fast_ticker_skipped(handle: u32) -> i64 {
+    match ticker(handle) {
+        Some(t) => t.lock().unwrap_or_else(|e| e.into_inner()).last_skipped().min(i64::MAX as u64) as i64,
+        None => -1,
+    }
+}
+
+/// Change the period of a running ticker (e.g. a tempo change). The next tick
+/// is one new period after the last boundary, so the phase stays continuous.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn fast_ticker_set_period(handle: u32, period_us: u32) -> i32 {
+    if period_us == 0 {
+        return -1;
+    }
+    match ticker(handle) {
+        Some(t) => {
+            t.lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .set_period(Duration::from_micros(period_us as u64));
+            0
+        }
+        None => -1,
+    }
+}
+
+/// Re-anchor the tick grid so ticks land at `anchor_us + n * period` (µs on
+/// the fast_now_us clock), keeping the period. Takes effect from the next
+/// tick; the one transitional tick is between half and one and a half
+/// periods long. Returns -1 for an unknown handle or unrepresentable anchor.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn fast_ticker_align(handle: u32, anchor_us: u64) -> i32 {
+    let Some(t) = ticker(handle) else {
+        return -1;
+    };
+    let Some(anchor) = epoch().checked_add(Duration::from_micros(anchor_us)) else {
+        return -1;
+    };
+    if t.lock().unwrap_or_else(|e| e.into_inner()).align(anchor) {
+        0
+    } else {
+        -1
+    }
+}
+
+/// Ticker state as JSON: `{period_us, anchor_us, ticks_fired, ticks_skipped,
+/// lateness: {count, min_us, max_us, mean_us, last_us}}`, with `anchor_us` on
+/// the fast_now_us clock. Returns the byte length (0 for an unknown handle);
+/// nothing is written if `out_cap` is too small.
+///
+/// # Safety
+/// `out` must be null or valid for `out_cap` bytes of writes.
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn fast_ticker_info(handle: u32, out: *mut u8, out_cap: u32) -> u32 {
+    let Some(t) = ticker(handle) else {
+        return 0;
+    };
+    let json = {
+        let t = t.lock().unwrap_or_else(|e| e.into_inner());
+        let l = t.lateness();
+        let mean = l.sum_us.checked_div(l.count).unwrap_or(0);
+        format!(
+            "{{\"period_us\":{},\"anchor_us\":{},\"ticks_fired\":{},\"ticks_skipped\":{},\
+             \"lateness\":{{\"count\":{},\"min_us\":{},\"max_us\":{},\"mean_us\":{},\"last_us\":{}}}}}",
+            t.period().as_micros(),
+            t.anchor().saturating_duration_since(epoch()).as_micros(),
+            t.fired(),
+            t.skipped_total(),
+            l.count,
+            l.min_us,
+            l.max_us,
+            mean,
+            l.last_us
+        )
+    };
+    buffer::write(json.as_bytes(), out, out_cap)
+}
+
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn fast_ticker_destroy(handle: u32) -> i32 {
+    match TICKERS.lock().unwrap_or_else(|e| e.into_inner()).remove(&handle) {
+        Some(_) => 0,
+        None => -1,
+    }
+}
+
+/// fast_loop_start flags bits holding the loop thread's priority level.
+pub const LOOP_FLAG_PRIORITY_MASK: u32 = 0x3;
+
+/// Start a native thread that calls `cb(tick, late_us)` every `period_us` on a
+/// drift-corrected schedule, avoiding one FFI round trip per tick. If the
+/// callback overruns, missed ticks are skipped rather than queued and `tick`
+/// advances by the number of boundaries passed. `flags & LOOP_FLAG_PRIORITY_MASK`
+/// is the loop thread's priority level (0–2, as for
+/// fast_set_current_thread_priority; stepped down if the OS refuses); other
+/// bits must be 0.
+///
+/// Returns 0 if `period_us` is 0, `flags` is invalid (INVALID_ARGUMENT) or the
+/// thread cannot be started.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn fast_loop_start(period_us: u32, cb: TickCallback, flags: u32) -> u32 {
+    if period_us == 0 {
+        return 0;
+    }
+    let priority = flags & LOOP_FLAG_PRIORITY_MASK;
+    if flags & !LOOP_FLAG_PRIORITY_MASK != 0 || priority > thread_priority::MAX_LEVEL {
+        error::fail(error::INVALID_ARGUMENT, format!("invalid fast_loop_start flags {flags:#x}"));
+        return 0;
+    }
+    let l = match TickLoop::start(Duration::from_micros(period_us as u64), priority, cb) {
+        Ok(l) => l,
+        Err(e) => {
+            error::fail(error::OS_ERROR, format!("cannot start loop thread: {e}"));
+            return 0;
+        }
+    };
+    let id = NEXT_HANDLE.fetch_add(1, Ordering::Relaxed);
+    LOOPS.lock().unwrap_or_else(|e| e.into_inner()).insert(id, l);
+    SHUTDOWN_HOOK.call_once(|| shutdown::register(Stage::Inputs, "loops", stop_all_loops));
+    id
+}
+
+/// avtools_shutdown_all: stop every tick loop.
+fn stop_all_loops() -> u32 {
+    let loops: Vec<_> = LOOPS.lock().unwrap_or_else(|e| e.into_inner()).drain().collect();
+    let stopped = loops.len() as u32;
+    for (id, l) in loops {
+        if !l.stop_within(SHUTDOWN_JOIN_TIMEOUT) {
+            avtools_log::warn!("tick loop {id}: callback still running after shutdown");
+        }
+    }
+    stopped
+}
+
+/// Stop a loop started by fast_loop_start. Once this returns no callback will
+/// run. Blocks until an in-flight callback finishes, so from Deno call it with
+/// `nonblocking: true` (the callback may itself be waiting on the JS thread).
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn fast_loop_stop(handle: u32) -> i32 {
+    let l = LOOPS.lock().unwrap_or_else(|e| e.into_inner()).remove(&handle);
+    match l {
+        Some(l) => {
+            l.stop();
+            0
+        }
+        None => -1,
+    }
+}
+
+/// Call `cb(timer_id, late_us)` once, `delay_us` from now, from the shared
+/// timer worker thread. Returns the timer id, or 0 if the worker thread
+/// cannot be started.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn fast_timer_schedule(delay_us: u32, cb: TimerCallback) -> u32 {
+    let deadline = Instant::now() + Duration::from_micros(delay_us as u64);
+    let id = NEXT_HANDLE.fetch_add(1, Ordering::Relaxed);
+    if let Err(e) = timers::queue().schedule(id, deadline, cb) {
+        error::fail(error::OS_ERROR, format!("cannot start timer thread: {e}"));
+        return 0;
+    }
+    id
+}
+
+/// Cancel a pending timer. Returns 0 if it was cancelled (its callback will
+/// never run) or -1 if it already fired, is firing, or is unknown.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn fast_timer_cancel(timer_id: u32) -> i32 {
+    if timers::queue().cancel(timer_id) {
+        0
+    } else {
+        -1
+    }
+}
+
+/// Schedule `count` callbacks at absolute deadlines (µs on the fast_now_us
+/// clock) under a single handle, served by the shared timer worker thread.
+/// Each fires `cb(index, late_us)` with its position in the input array.
+/// Input need not be sorted; equal deadlines fire in index order. The
+/// deadlines are copied, so the buffer may be freed as soon as this returns.
+///
+/// Returns the batch handle, or 0 for a null pointer, zero count, a deadline
+/// too far in the future to represent or a worker thread that cannot be
+/// started.
+///
+/// # Safety
+/// `deadlines_ptr` must be null or valid for `count` `u64` reads.
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn fast_schedule_batch(
+    deadlines_ptr: *const u64,
+    count: u32,
+    cb: BatchCallback,
+) -> u32 {
+    if deadlines_ptr.is_null() || count == 0 {
+        return 0;
+    }
+    let base = epoch();
+    let deadlines: Option<Vec<Instant>> = std::slice::from_raw_parts(deadlines_ptr, count as usize)
+        .iter()
+        .map(|&us| base.checked_add(Duration::from_micros(us)))
+        .collect();
+    let Some(deadlines) = deadlines else {
+        return 0;
+    };
+    let id = NEXT_HANDLE.fetch_add(1, Ordering::Relaxed);
+    if let Err(e) = timers::queue().schedule_batch(id, deadlines, cb) {
+        error::fail(error::OS_ERROR, format!("cannot start timer thread: {e}"));
+        return 0;
+    }
+    id
+}
+
+/// Cancel every element of a batch that has not fired yet. Returns 0 if any
+/// were still pending, -1 if the batch finished already or is unknown.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn fast_schedule_batch_cancel(handle: u32) -> i32 {
+    if timers::queue().cancel_batch(handle) {
+        0
+    } else {
+        -1
+    }
+}
+
+/// Raise the Windows system timer rate with `timeBeginPeriod(period_ms)` so
+/// native sleeps wake within about `period_ms`, letting the sleeper spin less.
+/// Periods set here are ended by fast_sleep_reset_windows_timer_resolution or
+/// at process exit. No-op elsewhere.
+///
+/// Returns 0 on success, -1 if Windows rejected the period.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn fast_sleep_set_windows_timer_resolution(period_ms: u32) -> i32 {
+    if timer_resolution::begin(Owner::Legacy, period_ms) {
+        0
+    } else {
+        -1
+    }
+}
+
+/// Refcounted timeBeginPeriod: each call must be balanced by one
+/// fast_sleep_clear_timer_resolution with the same `ms`, so independent users
+/// can nest. fast_sleep_reset_windows_timer_resolution leaves these periods
+/// alone; any still active are ended when the library unloads or the process
+/// exits.
+///
+/// Returns 0 on success, -1 if Windows rejected the period, 1 (no-op) on
+/// other platforms.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn fast_sleep_set_timer_resolution(ms: u32) -> i32 {
+    if !timer_resolution::SUPPORTED {
+        error::clear();
+        1
+    } else if timer_resolution::begin(Owner::Counted, ms) {
+        error::clear();
+        0
+    } else {
+        error::fail(error::OS_ERROR, format!("timeBeginPeriod({ms}) rejected"));
+        -1
+    }
+}
+
+/// Undo one fast_sleep_set_timer_resolution(`ms`).
+///
+/// Returns 0 on success, -1 if no `ms` period set through
+/// fast_sleep_set_timer_resolution is active, 1 (no-op) on other platforms.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn fast_sleep_clear_timer_resolution(ms: u32) -> i32 {
+    if !timer_resolution::SUPPORTED {
+        1
+    } else if timer_resolution::end(Owner::Counted, ms) {
+        0
+    } else {
+        -1
+    }
+}
+
+/// End every period begun by fast_sleep_set_windows_timer_resolution; those
+/// from fast_sleep_set_timer_resolution stay. Always returns 0.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn fast_sleep_reset_windows_timer_resolution() -> i32 {
+    timer_resolution::end_all(Owner::Legacy);
+    0
+}
+
+/// Measure native `thread::sleep` overshoot over `sample_count` sleeps of
+/// 100µs–2ms, take the overshoot at `target_percentile` (0–100) as the native
+/// accuracy, apply it (keeping the current strategy) and return the applied
+/// value in µs. With the BusySpin strategy it is capped at MAX_BUSY_SPIN_US;
+/// the report keeps the measured value. Returns 0 and leaves the
+/// configuration unchanged if the current strategy cannot take it.
+/// Blocks for roughly `sample_count` ms; call with `nonblocking: true`.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn fast_sleep_calibrate(sample_count: u32, target_percentile: u32) -> u32 {
+    let report = calibrate::run(sample_count, target_percentile);
+    let applied = {
+        let configuring = CONFIGURE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let strategy = CONFIG.read().unwrap_or_else(|e| e.into_inner()).strategy;
+        let mut accuracy_us = report.accuracy_us;
+        if busy_spin_too_long(accuracy_us, strategy) {
+            accuracy_us = MAX_BUSY_SPIN_US;
+        }
+        if reconfigure(&configuring, accuracy_us, strategy) == 0 {
+            accuracy_us
+        } else {
+            0
+        }
+    };
+    *calibrate::LAST_REPORT.lock().unwrap_or_else(|e| e.into_inner()) = Some(report);
+    applied
+}
+
+/// JSON summary of the last fast_sleep_calibrate run (`null` if none).
+/// Returns the byte length of the JSON; nothing is written if `out_cap` is too small.
+///
+/// # Safety
+/// `out` must be null or valid for `out_cap` bytes of writes.
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn fast_sleep_calibration_report(out: *mut u8, out_cap: u32) -> u32 {
+    let json = match &*calibrate::LAST_REPORT.lock().unwrap_or_else(|e| e.into_inner()) {
+        Some(report) => report.to_json(),
+        None => "null".to_string(),
+    };
+    buffer::write(json.as_bytes(), out, out_cap)
+}
+
+/// Run a fixed accuracy battery (100µs, 1ms, 5ms and 16.7ms sleeps, up to 200
+/// each, with plain thread::sleep and with the configured sleeper) and write a
+/// JSON report of mean/p95/max overshoot per duration and method, plus the
+/// measured OS timer granularity. Takes about three seconds; call with
+/// `nonblocking: true`. Changes no configuration and does not feed the
+/// jitter stats or miss handler. The report is under 2 KiB.
+///
+/// Returns the byte length of the JSON; nothing is written if `out_cap` is too
+/// small (the battery is not cached, so size the buffer up front).
+///
+/// # Safety
+/// `out` must be null or valid for `out_cap` bytes of writes.
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn fast_sleep_selftest(out: *mut u8, out_cap: u32) -> u32 {
+    let json = selftest::run(sleeper());
+    buffer::write(json.as_bytes(), out, out_cap)
+}
+
+/// Turn sleep jitter recording on (non-zero) or off. Off by default; existing
+/// counts are kept when toggling.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn fast_sleep_stats_enable(enabled: u8) {
+    stats::set_enabled(enabled != 0);
+}
+
+/// Clear all recorded jitter statistics.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn fast_sleep_stats_reset() {
+    stats::reset();
+}
+
+/// Jitter statistics as JSON: count, mean_error_us, p95_us, p99_us, max_us and
+/// per-bucket counts. Returns the byte length; nothing is written if `out_cap`
+/// is too small.
+///
+/// # Safety
+/// `out` must be null or valid for `out_cap` bytes of writes.
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn fast_sleep_stats(out: *mut u8, out_cap: u32) -> u32 {
+    buffer::write(stats::to_json().as_bytes(), out, out_cap)
+}
+
+/// fastsleep's section of the runtime metrics: the jitter stats plus how many
+/// tickers and tick loops are open and how many of those loops' threads have
+/// panicked (null while their table is locked).
+pub fn metrics_section() -> String {
+    format!(
+        "{{\"stats\":{},\"tickers\":{},\"loops\":{},\"failed_loops\":{}}}",
+        stats::to_json(),
+        metrics::or_null(metrics::sample(&TICKERS, |t| t.len().to_string())),
+        metrics::or_null(metrics::sample(&LOOPS, |l| l.len().to_string())),
+        metrics::or_null(metrics::sample(&LOOPS, |l| {
+            l.values().filter(|l| l.failed()).count().to_string()
+        })),
+    )
+}
+
+/// Runtime metrics as JSON, `{"schema":1,"fastsleep":{...}}` (see
+/// `metrics_section`). Never blocks on a running loop. Returns the byte
+/// length; nothing is written if `out_cap` is too small.
+///
+/// # Safety
+/// `out` must be null or valid for `out_cap` bytes of writes.
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn fast_sleep_metrics_json(out: *mut u8, out_cap: u32) -> u32 {
+    let json = metrics::document(&[("fastsleep", metrics_section())]);
+    buffer::write(json.as_bytes(), out, out_cap)
+}
+
+/// Set the calling thread's scheduling priority: 0 = normal, 1 = above
+/// normal, 2 = time-critical (Windows), SCHED_FIFO (Linux) or the
+/// user-interactive QoS class (macOS).
+///
+/// Returns:
+///   0 = ok
+///  -1 = invalid level
+///  -2 = refused by the OS (e.g. realtime without CAP_SYS_NICE / RLIMIT_RTPRIO)
+///  -3 = unsupported platform
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn fast_set_current_thread_priority(level: u32) -> i32 {
+    let rc = thread_priority::set(level);
+    match rc {
+        thread_priority::OK => error::clear(),
+        thread_priority::INVALID => error::fail(error::INVALID_ARGUMENT, format!("unknown priority level {level}")),
+        thread_priority::DENIED => {
+            avtools_log::warn!("OS refused thread priority level {level}");
+            error::fail(error::OS_ERROR, format!("OS refused thread priority level {level}"));
+        }
+        _ => error::fail(error::UNSUPPORTED, "thread priority is not supported on this platform"),
+    }
+    rc
+}
+
+/// Effective priority level (0–2, as for fast_set_current_thread_priority) of
+/// the calling thread, read back from the OS. -3 on unsupported platforms.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn fast_get_current_thread_priority() -> i32 {
+    if !thread_priority::SUPPORTED {
+        return thread_priority::UNSUPPORTED;
+    }
+    thread_priority::get() as i32
+}
+
+/// Name the calling thread for debuggers and profilers (truncated to 15 bytes
+/// on Linux). Returns 0, -1 for invalid UTF-8, -2 if refused, -3 if unsupported.
+///
+/// # Safety
+/// `ptr` must be null or valid for `len` bytes of reads.
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn fast_set_current_thread_name(ptr: *const u8, len: u32) -> i32 {
+    let bytes = if ptr.is_null() {
+        &[][..]
+    } else {
+        std::slice::from_raw_parts(ptr, len as usize)
+    };
+    let name = match std::str::from_utf8(bytes) {
+        Ok(name) => name,
+        Err(_) => return thread_priority::INVALID,
+    };
+    match std::ffi::CString::new(name) {
+        Ok(name) => thread_priority::set_name(&name),
+        Err(_) => thread_priority::INVALID,
+    }
+}
+
+/// Install a deadline-miss handler: every sleep, ticker, loop or timer wakeup
+/// that overshoots its target by more than `threshold_us` calls
+/// `cb(requested_us, actual_us)` on the waking thread. Replaces any previous
+/// handler; a null `cb` clears it. The handler is never re-entered from sleeps
+/// it makes itself, and its run time is not counted against the next deadline
+/// wait on the same thread.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn fast_set_miss_handler(threshold_us: u32, cb: Option<extern "C" fn(u64, u64)>) {
+    miss::set(threshold_us, cb);
+}
+
+/// Globally trade accuracy for power. 0 = configured behaviour; 1 = low power:
+/// every sleep (including handle sleepers) uses native sleep with no spin
+/// tail, and ticker/loop/timer deadlines are rounded up to the next
+/// millisecond. Takes effect for waits that start afterwards.
+///
+/// Returns 0, or -1 for an unknown mode.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn fast_sleep_set_power_mode(mode: u32) -> i32 {
+    if mode > POWER_MODE_LOW {
+        return -1;
+    }
+    POWER_MODE.store(mode, Ordering::Relaxed);
+    0
+}
+
+/// Current power mode as set by fast_sleep_set_power_mode.
+#[no_mangle]
+#[catch_panic]
+pub extern "C" fn fast_sleep_get_power_mode() -> u32 {
+    power_mode()
 }
@@ -0,0 +1,70 @@
+//! Deadline-miss reporting. Every wakeup that overshoots its target by more
+//! than the threshold calls the installed handler on the waking thread.
+
+use std::cell::Cell;
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+
+pub type MissCallback = extern "C" fn(u64, u64);
+
+#[derive(Clone, Copy)]
+struct Handler {
+    threshold: Duration,
+    cb: MissCallback,
+}
+
+static HANDLER: RwLock<Option<Handler>> = RwLock::new(None);
+
+thread_local! {
+    /// Set while this thread runs the handler, so sleeps inside it never recurse.
+    static IN_HANDLER: Cell<bool> = const { Cell::new(false) };
+    /// Time this thread last spent in the handler. It is taken off the next
+    /// deadline wait's overshoot, since that deadline was already scheduled.
+    static HANDLER_DEBT: Cell<Duration> = const { Cell::new(Duration::ZERO) };
+}
+
+pub fn set(threshold_us: u32, cb: Option<MissCallback>) {
+    *HANDLER.write().unwrap_or_else(|e| e.into_inner()) = cb.map(|cb| Handler {
+        threshold: Duration::from_micros(threshold_us as u64),
+        cb,
+    });
+}
+
+fn handler() -> Option<Handler> {
+    *HANDLER.read().unwrap_or_else(|e| e.into_inner())
+}
+
+pub fn installed() -> bool {
+    handler().is_some()
+}
+
+/// A relative sleep (requested duration measured from its own start).
+pub fn check(requested: Duration, actual: Duration) {
+    report(requested, actual, Duration::ZERO);
+}
+
+/// A wait for a deadline scheduled in advance (ticker, loop, timer), which the
+/// previous handler call may have pushed back.
+pub fn check_deadline(requested: Duration, actual: Duration) {
+    let debt = HANDLER_DEBT.with(|d| d.replace(Duration::ZERO));
+    report(requested, actual, debt);
+}
+
+fn report(requested: Duration, actual: Duration, debt: Duration) {
+    let Some(handler) = handler() else {
+        return;
+    };
+    if actual.saturating_sub(requested).saturating_sub(debt) <= handler.threshold {
+        return;
+    }
+    if IN_HANDLER.with(|h| h.replace(true)) {
+        return;
+    }
+    let start = Instant::now();
+    (handler.cb)(
+        requested.as_micros().min(u64::MAX as u128) as u64,
+        actual.as_micros().min(u64::MAX as u128) as u64,
+    );
+    HANDLER_DEBT.with(|d| d.set(start.elapsed()));
+    IN_HANDLER.with(|h| h.set(false));
+}
@@ -0,0 +1,56 @@
+//! Test mode: sleeps advance a virtual clock instead of waiting in real time.
+
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+/// `f64` bits of the speed-up factor.
+static SCALE: AtomicU64 = AtomicU64::new(0);
+static VIRTUAL_NS: AtomicU64 = AtomicU64::new(0);
+/// Set by the first real sleep; the mock can only be enabled before it.
+static REAL_SLEEP_SEEN: AtomicBool = AtomicBool::new(false);
+
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// False if a real sleep has already happened.
+pub fn enable(scale_factor: f64) -> bool {
+    if REAL_SLEEP_SEEN.load(Ordering::Relaxed) {
+        return false;
+    }
+    SCALE.store(scale_factor.to_bits(), Ordering::Relaxed);
+    ENABLED.store(true, Ordering::Release);
+    true
+}
+
+pub fn note_real_sleep() {
+    if !REAL_SLEEP_SEEN.load(Ordering::Relaxed) {
+        REAL_SLEEP_SEEN.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Advance virtual time by `duration / scale` without waiting; a scale of 0
+/// returns at once and advances it by `duration`.
+pub fn sleep(duration: Duration) {
+    let scale = f64::from_bits(SCALE.load(Ordering::Relaxed));
+    let advance = if scale > 0.0 {
+        Duration::try_from_secs_f64(duration.as_secs_f64() / scale).unwrap_or(Duration::MAX)
+    } else {
+        duration
+    };
+    let advance_ns = advance.as_nanos().min(u64::MAX as u128) as u64;
+    // Saturate rather than wrap on absurdly long sleeps.
+    let _ = VIRTUAL_NS.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |ns| {
+        Some(ns.saturating_add(advance_ns))
+    });
+}
+
+/// `sleep` for whatever is left until `deadline`.
+pub fn sleep_until(deadline: Instant) {
+    sleep(deadline.saturating_duration_since(Instant::now()));
+}
+
+pub fn virtual_time_ns() -> u64 {
+    VIRTUAL_NS.load(Ordering::Relaxed)
+}
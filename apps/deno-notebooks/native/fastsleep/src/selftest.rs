@@ -0,0 +1,104 @@
+use spin_sleep::SpinSleeper;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::timer_resolution;
+
+/// Requested durations in the battery, in ns (100µs, 1ms, 5ms, 16.7ms).
+const DURATIONS_NS: [u64; 4] = [100_000, 1_000_000, 5_000_000, 16_700_000];
+const MAX_SAMPLES: u32 = 200;
+const MIN_SAMPLES: u32 = 10;
+/// Sleeping time allowed per duration (both methods together), so the whole
+/// battery stays around three seconds including overshoot.
+const BUDGET_PER_DURATION: Duration = Duration::from_millis(700);
+/// 1µs sleeps used to estimate the OS timer granularity.
+const GRANULARITY_SAMPLES: usize = 50;
+
+struct Summary {
+    samples: usize,
+    mean_us: f64,
+    p95_us: f64,
+    max_us: f64,
+}
+
+fn summarize(overshoot_ns: &mut [u64]) -> Summary {
+    overshoot_ns.sort_unstable();
+    let n = overshoot_ns.len().max(1);
+    let at = |pct: usize| overshoot_ns.get((overshoot_ns.len().saturating_sub(1)) * pct / 100);
+    Summary {
+        samples: overshoot_ns.len(),
+        mean_us: overshoot_ns.iter().sum::<u64>() as f64 / n as f64 / 1_000.0,
+        p95_us: at(95).copied().unwrap_or(0) as f64 / 1_000.0,
+        max_us: at(100).copied().unwrap_or(0) as f64 / 1_000.0,
+    }
+}
+
+fn overshoot_ns(requested: Duration, start: Instant) -> u64 {
+    start.elapsed().saturating_sub(requested).as_nanos().min(u64::MAX as u128) as u64
+}
+
+/// Median time a 1µs `thread::sleep` actually takes: the smallest sleep the
+/// OS timer can deliver.
+fn timer_granularity_us() -> f64 {
+    let mut samples: Vec<u64> = (0..GRANULARITY_SAMPLES)
+        .map(|_| {
+            let start = Instant::now();
+            thread::sleep(Duration::from_micros(1));
+            start.elapsed().as_nanos().min(u64::MAX as u128) as u64
+        })
+        .collect();
+    samples.sort_unstable();
+    samples[samples.len() / 2] as f64 / 1_000.0
+}
+
+/// Run the fixed battery with plain `thread::sleep` and `sleeper`, alternating
+/// the two per sample. Only local state is touched, so this is safe alongside
+/// other sleeps and leaves the configuration as it was.
+pub fn run(sleeper: SpinSleeper) -> String {
+    let started = Instant::now();
+    let granularity_us = timer_granularity_us();
+    let mut results = Vec::with_capacity(DURATIONS_NS.len() * 2);
+    for &ns in &DURATIONS_NS {
+        let requested = Duration::from_nanos(ns);
+        let budget_samples = (BUDGET_PER_DURATION.as_nanos() / (2 * ns as u128)) as u32;
+        let samples = budget_samples.clamp(MIN_SAMPLES, MAX_SAMPLES);
+        let mut native = Vec::with_capacity(samples as usize);
+        let mut spin = Vec::with_capacity(samples as usize);
+        let slot_start = Instant::now();
+        for _ in 0..samples {
+            let start = Instant::now();
+            thread::sleep(requested);
+            native.push(overshoot_ns(requested, start));
+
+            let start = Instant::now();
+            sleeper.sleep(requested);
+            spin.push(overshoot_ns(requested, start));
+
+            // Overshoot can be far worse than expected on a loaded machine.
+            if slot_start.elapsed() > BUDGET_PER_DURATION * 2 {
+                break;
+            }
+        }
+        for (method, samples) in [("thread_sleep", &mut native), ("spin_sleeper", &mut spin)] {
+            let s = summarize(samples);
+            results.push(format!(
+                "{{\"requested_us\":{:.1},\"method\":\"{method}\",\"samples\":{},\
+                 \"mean_overshoot_us\":{:.1},\"p95_overshoot_us\":{:.1},\"max_overshoot_us\":{:.1}}}",
+                ns as f64 / 1_000.0,
+                s.samples,
+                s.mean_us,
+                s.p95_us,
+                s.max_us
+            ));
+        }
+    }
+    format!(
+        "{{\"duration_ms\":{},\"os_timer_granularity_us\":{:.1},\"timer_resolution_us\":{},\
+         \"native_accuracy_us\":{},\"results\":[{}]}}",
+        started.elapsed().as_millis(),
+        granularity_us,
+        timer_resolution::current_us().map_or_else(|| "null".to_string(), |us| us.to_string()),
+        sleeper.native_accuracy_ns() / 1_000,
+        results.join(",")
+    )
+}
@@ -0,0 +1,122 @@
+//! Opt-in sleep jitter histogram. Recording is a pair of `Instant` reads and a
+//! few relaxed atomic adds; when disabled (and no miss handler is installed),
+//! sleeps skip it entirely.
+
+use spin_sleep::SpinSleeper;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::{miss, mock};
+
+/// Upper bounds (exclusive, µs) of every bucket but the last, which is open-ended.
+const BUCKET_BOUNDS_US: [u64; 5] = [1, 10, 100, 1_000, 10_000];
+const BUCKET_LABELS: [&str; 6] = ["<1us", "<10us", "<100us", "<1ms", "<10ms", ">=10ms"];
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static BUCKETS: [AtomicU64; 6] = [
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+];
+static COUNT: AtomicU64 = AtomicU64::new(0);
+static SUM_ERROR_NS: AtomicU64 = AtomicU64::new(0);
+static MAX_ERROR_NS: AtomicU64 = AtomicU64::new(0);
+
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// `sleeper.sleep(duration)`, recording |actual - requested| when enabled and
+/// reporting overshoots to the miss handler.
+pub fn sleep(sleeper: SpinSleeper, duration: Duration) {
+    if mock::enabled() {
+        mock::sleep(duration);
+        return;
+    }
+    mock::note_real_sleep();
+    let enabled = ENABLED.load(Ordering::Relaxed);
+    if !enabled && !miss::installed() {
+        sleeper.sleep(duration);
+        return;
+    }
+    let start = Instant::now();
+    sleeper.sleep(duration);
+    let elapsed = start.elapsed();
+    if enabled {
+        let error = elapsed.abs_diff(duration);
+        record(error.as_nanos().min(u64::MAX as u128) as u64);
+    }
+    miss::check(duration, elapsed);
+}
+
+fn record(error_ns: u64) {
+    let error_us = error_ns / 1_000;
+    let bucket = BUCKET_BOUNDS_US
+        .iter()
+        .position(|&bound| error_us < bound)
+        .unwrap_or(BUCKET_BOUNDS_US.len());
+    BUCKETS[bucket].fetch_add(1, Ordering::Relaxed);
+    COUNT.fetch_add(1, Ordering::Relaxed);
+    SUM_ERROR_NS.fetch_add(error_ns, Ordering::Relaxed);
+    MAX_ERROR_NS.fetch_max(error_ns, Ordering::Relaxed);
+}
+
+pub fn reset() {
+    for bucket in &BUCKETS {
+        bucket.store(0, Ordering::Relaxed);
+    }
+    COUNT.store(0, Ordering::Relaxed);
+    SUM_ERROR_NS.store(0, Ordering::Relaxed);
+    MAX_ERROR_NS.store(0, Ordering::Relaxed);
+}
+
+/// Upper bound (µs) of the bucket holding the `pct` percentile; the last
+/// bucket has no bound, so the observed max stands in for it.
+fn percentile_us(counts: &[u64; 6], total: u64, pct: u64, max_us: f64) -> f64 {
+    if total == 0 {
+        return 0.0;
+    }
+    let rank = (total * pct).div_ceil(100).max(1);
+    let mut seen = 0;
+    for (i, &count) in counts.iter().enumerate() {
+        seen += count;
+        if seen >= rank {
+            return BUCKET_BOUNDS_US.get(i).map_or(max_us, |&bound| bound as f64);
+        }
+    }
+    max_us
+}
+
+/// Snapshot as JSON. Percentiles are bucket upper bounds, so they are
+/// resolution-limited to the decade buckets.
+pub fn to_json() -> String {
+    let counts: [u64; 6] = std::array::from_fn(|i| BUCKETS[i].load(Ordering::Relaxed));
+    let count = COUNT.load(Ordering::Relaxed);
+    let sum_ns = SUM_ERROR_NS.load(Ordering::Relaxed);
+    let max_us = MAX_ERROR_NS.load(Ordering::Relaxed) as f64 / 1_000.0;
+    let mean_us = if count == 0 {
+        0.0
+    } else {
+        sum_ns as f64 / count as f64 / 1_000.0
+    };
+    let buckets: Vec<String> = BUCKET_LABELS
+        .iter()
+        .zip(counts.iter())
+        .map(|(label, n)| format!("\"{label}\":{n}"))
+        .collect();
+    format!(
+        "{{\"enabled\":{},\"power_mode\":{},\"count\":{},\"mean_error_us\":{:.3},\"p95_us\":{},\"p99_us\":{},\
+         \"max_us\":{:.3},\"buckets\":{{{}}}}}",
+        ENABLED.load(Ordering::Relaxed),
+        crate::power_mode(),
+        count,
+        mean_us,
+        percentile_us(&counts, count, 95, max_us),
+        percentile_us(&counts, count, 99, max_us),
+        max_us,
+        buckets.join(",")
+    )
+}
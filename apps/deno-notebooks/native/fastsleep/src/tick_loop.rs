@@ -0,0 +1,74 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::io;
+use std::time::{Duration, Instant};
+
+use avtools_ffi::worker::{spawn_worker, Worker};
+
+use crate::cancel::CancelToken;
+use crate::miss;
+use crate::sleeper;
+use crate::ticker::Ticker;
+
+pub type TickCallback = extern "C" fn(u64, i64);
+
+/// Native thread that waits on a drift-corrected schedule and calls `cb`
+/// every tick. `tick` counts schedule boundaries, so it jumps by more than one
+/// when a slow callback forced ticks to be skipped.
+pub struct TickLoop {
+    stop: Arc<AtomicBool>,
+    wake: Arc<CancelToken>,
+    worker: Worker,
+}
+
+impl TickLoop {
+    pub fn start(period: Duration, priority: u32, cb: TickCallback) -> io::Result<Self> {
+        let stop = Arc::new(AtomicBool::new(false));
+        let wake = Arc::new(CancelToken::new());
+        let loop_stop = stop.clone();
+        let loop_wake = wake.clone();
+        let worker = spawn_worker("fastsleep-loop", priority, move || run(period, cb, loop_stop, loop_wake))?;
+        Ok(Self { stop, wake, worker })
+    }
+
+    /// The loop thread panicked (the panic was logged) and has ended.
+    pub fn failed(&self) -> bool {
+        self.worker.failed()
+    }
+
+    /// After this returns no further callback will start. Called from inside
+    /// the callback itself, the loop exits once that callback returns.
+    pub fn stop(self) {
+        self.stop.store(true, Ordering::Release);
+        self.wake.cancel();
+        self.worker.join();
+    }
+
+    /// Like `stop`, but give up waiting for an in-flight callback after
+    /// `timeout` (it may be blocked on a host that is exiting). False if the
+    /// thread was still running and has been left to finish on its own.
+    pub fn stop_within(self, timeout: Duration) -> bool {
+        self.stop.store(true, Ordering::Release);
+        self.wake.cancel();
+        self.worker.join_within(timeout)
+    }
+}
+
+fn run(period: Duration, cb: TickCallback, stop: Arc<AtomicBool>, wake: Arc<CancelToken>) {
+    let mut ticker = Ticker::new(period, 0);
+    let mut tick: u64 = 0;
+    while !stop.load(Ordering::Acquire) {
+        let plan = ticker.advance();
+        tick = tick.wrapping_add(1 + plan.skipped);
+        let start = Instant::now();
+        if wake.sleep_until(sleeper(), plan.target) || stop.load(Ordering::Acquire) {
+            break;
+        }
+        miss::check_deadline(plan.target.saturating_duration_since(start), start.elapsed());
+        let late_us = Instant::now()
+            .saturating_duration_since(plan.target)
+            .as_micros()
+            .min(i64::MAX as u128) as i64;
+        cb(tick, late_us);
+    }
+}
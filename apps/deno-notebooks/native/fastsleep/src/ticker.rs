@@ -0,0 +1,178 @@
+use avtools_ffi::options::{Invalid, VersionedOptions};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// Fire every missed tick back-to-back instead of skipping to the latest one.
+pub const TICKER_CATCH_UP: u32 = 0x1;
+
+/// Options for fast_ticker_create_ex.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TickerOptions {
+    /// Required: 0 is rejected.
+    pub period_us: u32,
+    /// TICKER_CATCH_UP.
+    pub catch_up: bool,
+}
+
+impl TickerOptions {
+    pub fn flags(&self) -> u32 {
+        if self.catch_up {
+            TICKER_CATCH_UP
+        } else {
+            0
+        }
+    }
+}
+
+impl VersionedOptions for TickerOptions {
+    const VERSION: u32 = 1;
+    const NAME: &'static str = "ticker options";
+
+    fn validate(&self) -> Result<(), Invalid> {
+        if self.period_us == 0 {
+            return Err(Invalid::new("period_us", "required, must be at least 1"));
+        }
+        Ok(())
+    }
+}
+
+/// Periodic schedule on absolute boundaries: `next` only ever advances by
+/// whole periods, so wakeup jitter never accumulates into drift.
+pub struct Ticker {
+    period: Duration,
+    next: Instant,
+    flags: u32,
+    last_skipped: u64,
+    /// A boundary of the current grid; ticks land at `anchor + n * period`.
+    anchor: Instant,
+    fired: u64,
+    skipped_total: u64,
+    lateness: Lateness,
+}
+
+/// Wakeup lateness of the ticks waited on so far, in microseconds.
+#[derive(Default)]
+pub struct Lateness {
+    pub count: u64,
+    pub min_us: u64,
+    pub max_us: u64,
+    pub sum_us: u64,
+    pub last_us: u64,
+}
+
+/// What a caller of `Ticker::advance` should do.
+pub struct TickPlan {
+    /// Boundary to sleep until (may already be in the past).
+    pub target: Instant,
+    /// Ticks dropped to get back on schedule.
+    pub skipped: u64,
+}
+
+impl Ticker {
+    pub fn new(period: Duration, flags: u32) -> Self {
+        let anchor = Instant::now();
+        Self {
+            period,
+            next: anchor + period,
+            flags,
+            last_skipped: 0,
+            anchor,
+            fired: 0,
+            skipped_total: 0,
+            lateness: Lateness::default(),
+        }
+    }
+
+    pub fn last_skipped(&self) -> u64 {
+        self.last_skipped
+    }
+
+    pub fn period(&self) -> Duration {
+        self.period
+    }
+
+    pub fn anchor(&self) -> Instant {
+        self.anchor
+    }
+
+    pub fn fired(&self) -> u64 {
+        self.fired
+    }
+
+    pub fn skipped_total(&self) -> u64 {
+        self.skipped_total
+    }
+
+    pub fn lateness(&self) -> &Lateness {
+        &self.lateness
+    }
+
+    pub fn record_lateness(&mut self, late_us: u64) {
+        let l = &mut self.lateness;
+        l.min_us = if l.count == 0 { late_us } else { l.min_us.min(late_us) };
+        l.max_us = l.max_us.max(late_us);
+        l.sum_us = l.sum_us.saturating_add(late_us);
+        l.last_us = late_us;
+        l.count += 1;
+    }
+
+    /// Claim the next boundary and advance the schedule past it.
+    pub fn advance(&mut self) -> TickPlan {
+        let now = Instant::now();
+        let mut skipped = 0;
+        if self.flags & TICKER_CATCH_UP == 0 && now > self.next + self.period {
+            let behind = (now - self.next).as_nanos() / self.period.as_nanos().max(1);
+            skipped = behind.min(u32::MAX as u128) as u32;
+            self.next += self.period * skipped;
+        }
+        // Low-power rounding only moves the wakeup, not the schedule.
+        let target = crate::power_adjust(self.next);
+        self.next += self.period;
+        self.last_skipped = skipped as u64;
+        self.fired += 1;
+        self.skipped_total += skipped as u64;
+        TickPlan {
+            target,
+            skipped: skipped as u64,
+        }
+    }
+
+    /// Change the period, keeping the phase continuous: the next tick lands
+    /// one new period after the most recent boundary.
+    pub fn set_period(&mut self, period: Duration) {
+        let last = self.next.checked_sub(self.period).unwrap_or(self.next);
+        self.period = period;
+        self.next = last + period;
+        self.anchor = last;
+    }
+
+    /// Move the grid so ticks land on `anchor + n * period`, keeping the
+    /// period. The next tick becomes the first grid point at least half a
+    /// period after the most recent boundary, so the transition is one tick
+    /// between 0.5 and 1.5 periods long.
+    pub fn align(&mut self, anchor: Instant) -> bool {
+        let last = self.next.checked_sub(self.period).unwrap_or(self.next);
+        let earliest = last + self.period / 2;
+        let period_ns = self.period.as_nanos().max(1);
+        let next = if earliest >= anchor {
+            let steps = (earliest - anchor).as_nanos().div_ceil(period_ns);
+            anchor.checked_add(nanos(steps * period_ns))
+        } else {
+            let steps = (anchor - earliest).as_nanos() / period_ns;
+            anchor.checked_sub(nanos(steps * period_ns))
+        };
+        match next {
+            Some(next) => {
+                self.next = next;
+                self.anchor = anchor;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+fn nanos(n: u128) -> Duration {
+    Duration::from_nanos(n.min(u64::MAX as u128) as u64)
+}
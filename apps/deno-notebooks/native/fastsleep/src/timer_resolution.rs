@@ -0,0 +1,143 @@
+//! Windows system timer resolution (`timeBeginPeriod`). Every period we begin
+//! is counted per period length and per API, so nested users compose: each
+//! refcounted `begin` is undone by one `end` of the same length, the legacy
+//! API's `end_all` only ends the periods it began, and everything left is
+//! ended at `atexit` (which the MSVC CRT runs when the DLL unloads as well as
+//! at process exit).
+
+/// Which export began a period.
+#[derive(Clone, Copy)]
+pub enum Owner {
+    /// fast_sleep_set_windows_timer_resolution; ended all at once by reset.
+    Legacy,
+    /// fast_sleep_set_timer_resolution; ended one at a time by clear.
+    Counted,
+}
+
+#[cfg(target_os = "windows")]
+mod imp {
+    use super::Owner;
+    use std::collections::HashMap;
+    use std::sync::{Mutex, Once};
+    use windows::Win32::Media::{timeBeginPeriod, timeEndPeriod, TIMERR_NOERROR};
+
+    /// Periods begun and not yet ended: period_ms -> count, per owner.
+    struct Active {
+        legacy: HashMap<u32, u32>,
+        counted: HashMap<u32, u32>,
+    }
+
+    impl Active {
+        fn of(&mut self, owner: Owner) -> &mut HashMap<u32, u32> {
+            match owner {
+                Owner::Legacy => &mut self.legacy,
+                Owner::Counted => &mut self.counted,
+            }
+        }
+    }
+
+    static ACTIVE: Mutex<Option<Active>> = Mutex::new(None);
+    static ATEXIT: Once = Once::new();
+
+    extern "C" {
+        fn atexit(cb: extern "C" fn()) -> i32;
+    }
+
+    #[link(name = "ntdll")]
+    extern "system" {
+        fn NtQueryTimerResolution(coarsest: *mut u32, finest: *mut u32, current: *mut u32) -> i32;
+    }
+
+    fn with_active<R>(f: impl FnOnce(&mut Active) -> R) -> R {
+        let mut active = ACTIVE.lock().unwrap_or_else(|e| e.into_inner());
+        f(active.get_or_insert_with(|| Active {
+            legacy: HashMap::new(),
+            counted: HashMap::new(),
+        }))
+    }
+
+    fn end_periods(periods: HashMap<u32, u32>) {
+        for (period_ms, count) in periods {
+            for _ in 0..count {
+                unsafe {
+                    timeEndPeriod(period_ms);
+                }
+            }
+        }
+    }
+
+    extern "C" fn end_all_at_exit() {
+        end_all(Owner::Legacy);
+        end_all(Owner::Counted);
+    }
+
+    pub fn begin(owner: Owner, period_ms: u32) -> bool {
+        if unsafe { timeBeginPeriod(period_ms) } != TIMERR_NOERROR {
+            avtools_log::warn!("timeBeginPeriod({period_ms}) rejected");
+            return false;
+        }
+        with_active(|active| *active.of(owner).entry(period_ms).or_insert(0) += 1);
+        ATEXIT.call_once(|| unsafe {
+            atexit(end_all_at_exit);
+        });
+        true
+    }
+
+    /// End one `period_ms` period begun by `owner`. False if it has none.
+    pub fn end(owner: Owner, period_ms: u32) -> bool {
+        let found = with_active(|active| {
+            let periods = active.of(owner);
+            match periods.get_mut(&period_ms) {
+                Some(count) if *count > 1 => {
+                    *count -= 1;
+                    true
+                }
+                Some(_) => periods.remove(&period_ms).is_some(),
+                None => false,
+            }
+        });
+        if found {
+            unsafe {
+                timeEndPeriod(period_ms);
+            }
+        }
+        found
+    }
+
+    /// End every period begun by `owner`.
+    pub fn end_all(owner: Owner) {
+        end_periods(with_active(|active| std::mem::take(active.of(owner))));
+    }
+
+    /// Current system-wide timer resolution in µs (set by any process).
+    pub fn current_us() -> Option<u32> {
+        let (mut coarsest, mut finest, mut current) = (0u32, 0u32, 0u32);
+        let status = unsafe { NtQueryTimerResolution(&mut coarsest, &mut finest, &mut current) };
+        // Reported in 100ns units.
+        (status >= 0).then_some(current / 10)
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+mod imp {
+    use super::Owner;
+
+    pub fn begin(_owner: Owner, _period_ms: u32) -> bool {
+        true
+    }
+
+    pub fn end(_owner: Owner, _period_ms: u32) -> bool {
+        true
+    }
+
+    pub fn end_all(_owner: Owner) {}
+
+    pub fn current_us() -> Option<u32> {
+        None
+    }
+}
+
+pub use imp::{begin, current_us, end, end_all};
+
+/// Whether the timer resolution calls do anything on this platform.
+pub const SUPPORTED: bool = cfg!(target_os = "windows");
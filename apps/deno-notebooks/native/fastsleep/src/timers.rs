@@ -0,0 +1,243 @@
+use spin_sleep::SpinStrategy;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::io;
+use std::sync::{Condvar, Mutex, MutexGuard, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use avtools_ffi::shutdown::{self, Stage};
+use avtools_ffi::worker::{spawn_worker, Worker};
+
+use crate::{miss, power_adjust, sleeper, SHUTDOWN_JOIN_TIMEOUT};
+
+pub type TimerCallback = extern "C" fn(u32, i64);
+
+/// Called with the element index (in the caller's original order) and lateness.
+pub type BatchCallback = extern "C" fn(u32, i64);
+
+/// Deadlines registered under one handle. Only the next pending element sits
+/// in the heap; it is replaced by its successor as each one fires.
+struct Batch {
+    cb: BatchCallback,
+    scheduled_at: Instant,
+    /// (deadline, original index), sorted so equal deadlines keep index order.
+    entries: Vec<(Instant, u32)>,
+    next: usize,
+}
+
+struct Inner {
+    heap: BinaryHeap<Reverse<(Instant, u32)>>,
+    /// Timers that are still pending. Whoever removes an id from here (the
+    /// worker about to fire, or a cancel) owns it, so exactly one of the two wins.
+    live: HashMap<u32, (TimerCallback, Instant)>,
+    /// Batches with elements left to fire, under the same ownership rule.
+    batches: HashMap<u32, Batch>,
+    /// Set by avtools_shutdown_all to end the worker.
+    stopping: bool,
+}
+
+impl Inner {
+    fn pending(&self, id: u32) -> bool {
+        self.live.contains_key(&id) || self.batches.contains_key(&id)
+    }
+}
+
+/// One worker thread serving every one-shot timer from a min-heap of deadlines.
+/// The worker starts with the first timer and ends at avtools_shutdown_all,
+/// which cancels whatever is pending; the next timer starts it again.
+pub struct TimerQueue {
+    inner: Mutex<Inner>,
+    cv: Condvar,
+    worker: Mutex<Option<Worker>>,
+}
+
+static QUEUE: OnceLock<&'static TimerQueue> = OnceLock::new();
+
+pub fn queue() -> &'static TimerQueue {
+    QUEUE.get_or_init(|| {
+        shutdown::register(Stage::Inputs, "timers", stop_timers);
+        Box::leak(Box::new(TimerQueue {
+            inner: Mutex::new(Inner {
+                heap: BinaryHeap::new(),
+                live: HashMap::new(),
+                batches: HashMap::new(),
+                stopping: false,
+            }),
+            cv: Condvar::new(),
+            worker: Mutex::new(None),
+        }))
+    })
+}
+
+/// avtools_shutdown_all: cancel every pending timer and batch and end the
+/// worker. Returns how many were cancelled.
+fn stop_timers() -> u32 {
+    let q = queue();
+    let mut worker = q.worker.lock().unwrap_or_else(|e| e.into_inner());
+    let cancelled = {
+        let mut inner = q.lock();
+        inner.stopping = true;
+        inner.heap.clear();
+        let cancelled = inner.live.len() + inner.batches.len();
+        inner.live.clear();
+        inner.batches.clear();
+        cancelled as u32
+    };
+    q.cv.notify_all();
+    if let Some(w) = worker.take() {
+        if !w.join_within(SHUTDOWN_JOIN_TIMEOUT) {
+            avtools_log::warn!("timer worker: callback still running after shutdown");
+        }
+    }
+    cancelled
+}
+
+impl TimerQueue {
+    fn lock(&self) -> MutexGuard<'_, Inner> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Start the worker unless it is running. The returned guard keeps
+    /// stop_timers out until the caller has queued its timer.
+    fn ensure_worker(&'static self) -> io::Result<MutexGuard<'static, Option<Worker>>> {
+        let mut worker = self.worker.lock().unwrap_or_else(|e| e.into_inner());
+        if worker.as_ref().is_none_or(Worker::is_finished) {
+            self.lock().stopping = false;
+            *worker = Some(spawn_worker("fastsleep-timers", 0, move || self.run())?);
+        }
+        Ok(worker)
+    }
+
+    pub fn schedule(&'static self, id: u32, deadline: Instant, cb: TimerCallback) -> io::Result<()> {
+        let _worker = self.ensure_worker()?;
+        let mut inner = self.lock();
+        inner.live.insert(id, (cb, Instant::now()));
+        inner.heap.push(Reverse((deadline, id)));
+        self.cv.notify_one();
+        Ok(())
+    }
+
+    /// True if the timer was still pending and will now never fire.
+    pub fn cancel(&self, id: u32) -> bool {
+        self.lock().live.remove(&id).is_some()
+    }
+
+    /// Register `deadlines` under `id`, firing `cb(index, late_us)` for each.
+    /// Unsorted input is sorted here; equal deadlines fire in index order.
+    pub fn schedule_batch(&'static self, id: u32, deadlines: Vec<Instant>, cb: BatchCallback) -> io::Result<()> {
+        let mut entries: Vec<(Instant, u32)> = deadlines
+            .into_iter()
+            .enumerate()
+            .map(|(i, d)| (d, i as u32))
+            .collect();
+        entries.sort_unstable();
+        let Some(&(first, _)) = entries.first() else {
+            return Ok(());
+        };
+        let _worker = self.ensure_worker()?;
+        let mut inner = self.lock();
+        inner.batches.insert(
+            id,
+            Batch {
+                cb,
+                scheduled_at: Instant::now(),
+                entries,
+                next: 0,
+            },
+        );
+        inner.heap.push(Reverse((first, id)));
+        self.cv.notify_one();
+        Ok(())
+    }
+
+    /// Drop every element of the batch that has not fired yet. True if any
+    /// were still pending.
+    pub fn cancel_batch(&self, id: u32) -> bool {
+        self.lock().batches.remove(&id).is_some()
+    }
+
+    /// Pop the due element of batch `id`, queueing its successor (or retiring
+    /// the batch when it was the last one).
+    fn take_batch_entry(inner: &mut Inner, id: u32) -> Option<(BatchCallback, Instant, u32)> {
+        let batch = inner.batches.get_mut(&id)?;
+        let (_, index) = batch.entries[batch.next];
+        let (cb, scheduled_at) = (batch.cb, batch.scheduled_at);
+        batch.next += 1;
+        match batch.entries.get(batch.next) {
+            Some(&(next, _)) => inner.heap.push(Reverse((next, id))),
+            None => {
+                inner.batches.remove(&id);
+            }
+        }
+        Some((cb, scheduled_at, index))
+    }
+
+    fn run(&self) {
+        let mut inner = self.lock();
+        while !inner.stopping {
+            while let Some(&Reverse((_, id))) = inner.heap.peek() {
+                if inner.pending(id) {
+                    break;
+                }
+                inner.heap.pop();
+            }
+            let (deadline, id) = match inner.heap.peek() {
+                Some(Reverse(entry)) => *entry,
+                None => {
+                    inner = self.cv.wait(inner).unwrap_or_else(|e| e.into_inner());
+                    continue;
+                }
+            };
+            let deadline = power_adjust(deadline);
+
+            let s = sleeper();
+            let accuracy = Duration::from_nanos(s.native_accuracy_ns() as u64);
+            let now = Instant::now();
+            if deadline > now + accuracy {
+                inner = self
+                    .cv
+                    .wait_timeout(inner, deadline - now - accuracy)
+                    .unwrap_or_else(|e| e.into_inner())
+                    .0;
+                continue;
+            }
+            if deadline > now {
+                // Spin the tail without the lock so schedule/cancel stay responsive,
+                // then re-check: an earlier timer may have arrived meanwhile.
+                drop(inner);
+                while Instant::now() < deadline {
+                    match s.spin_strategy() {
+                        SpinStrategy::YieldThread => thread::yield_now(),
+                        _ => std::hint::spin_loop(),
+                    }
+                }
+                inner = self.lock();
+                continue;
+            }
+
+            inner.heap.pop();
+            let due = match inner.live.remove(&id) {
+                Some((cb, scheduled_at)) => Some((cb, scheduled_at, id)),
+                None => Self::take_batch_entry(&mut inner, id),
+            };
+            if let Some((cb, scheduled_at, arg)) = due {
+                drop(inner);
+                let now = Instant::now();
+                let late_us = now
+                    .saturating_duration_since(deadline)
+                    .as_micros()
+                    .min(i64::MAX as u128) as i64;
+                miss::check_deadline(
+                    deadline.saturating_duration_since(scheduled_at),
+                    now.saturating_duration_since(scheduled_at),
+                );
+                {
+                    let _span = avtools_trace::span!(DEBUG, "timer_fire", late_us);
+                    cb(arg, late_us);
+                }
+                inner = self.lock();
+            }
+        }
+    }
+}
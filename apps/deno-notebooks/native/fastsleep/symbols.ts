@@ -0,0 +1,80 @@
+// Generated by native/avtools_bindgen from the exports of fastsleep; do not edit.
+// Hand-written tables may narrow "pointer" to "buffer"/"function" and add `nonblocking`.
+
+export const FASTSLEEP_SYMBOLS = {
+  av_alloc: { parameters: ["u32"], result: "pointer" },
+  av_free: { parameters: ["pointer", "u32"], result: "void" },
+  avtools_clock_now_us: { parameters: [], result: "u64" },
+  avtools_clock_offset: { parameters: ["u32", "u32"], result: "i64" },
+  avtools_shutdown_all: { parameters: ["pointer", "u32"], result: "u32" },
+  avtools_tracing_start: { parameters: ["pointer", "u32", "u32"], result: "i32" },
+  avtools_tracing_stop: { parameters: [], result: "i32" },
+  fast_clock_info: { parameters: ["pointer", "u32"], result: "u32" },
+  fast_get_current_thread_priority: { parameters: [], result: "i32" },
+  fast_loop_start: { parameters: ["u32", "function", "u32"], result: "u32" },
+  fast_loop_stop: { parameters: ["u32"], result: "i32" },
+  fast_now_us: { parameters: [], result: "u64" },
+  fast_schedule_batch: { parameters: ["pointer", "u32", "function"], result: "u32" },
+  fast_schedule_batch_cancel: { parameters: ["u32"], result: "i32" },
+  fast_set_current_thread_name: { parameters: ["pointer", "u32"], result: "i32" },
+  fast_set_current_thread_priority: { parameters: ["u32"], result: "i32" },
+  fast_set_miss_handler: { parameters: ["u32", "function"], result: "void" },
+  fast_set_wake_flag: { parameters: ["pointer", "u32"], result: "i32" },
+  fast_sleep_abi_version: { parameters: [], result: "u32" },
+  fast_sleep_calibrate: { parameters: ["u32", "u32"], result: "u32" },
+  fast_sleep_calibration_report: { parameters: ["pointer", "u32"], result: "u32" },
+  fast_sleep_cancel: { parameters: ["u32"], result: "i32" },
+  fast_sleep_cancellable_us: { parameters: ["u32", "u32"], result: "i32" },
+  fast_sleep_capabilities: { parameters: ["pointer", "u32"], result: "u32" },
+  fast_sleep_clear_timer_resolution: { parameters: ["u32"], result: "i32" },
+  fast_sleep_clock_ns: { parameters: [], result: "u64" },
+  fast_sleep_configure: { parameters: ["u32", "u32"], result: "i32" },
+  fast_sleep_enable_mock: { parameters: ["f64"], result: "i32" },
+  fast_sleep_get_config: { parameters: ["pointer", "pointer", "pointer"], result: "void" },
+  fast_sleep_get_last_panic: { parameters: ["pointer", "u32"], result: "u32" },
+  fast_sleep_get_power_mode: { parameters: [], result: "u32" },
+  fast_sleep_get_virtual_time_ns: { parameters: [], result: "u64" },
+  fast_sleep_init: { parameters: ["u32", "u32"], result: "i32" },
+  fast_sleep_last_error_code: { parameters: [], result: "i32" },
+  fast_sleep_last_error_message: { parameters: ["pointer", "u32"], result: "u32" },
+  fast_sleep_metrics_json: { parameters: ["pointer", "u32"], result: "u32" },
+  fast_sleep_ns: { parameters: ["u64"], result: "void" },
+  fast_sleep_ns_measured: { parameters: ["u64"], result: "u64" },
+  fast_sleep_ns_with: { parameters: ["u64", "u32", "u32"], result: "i32" },
+  fast_sleep_reset_windows_timer_resolution: { parameters: [], result: "i32" },
+  fast_sleep_selftest: { parameters: ["pointer", "u32"], result: "u32" },
+  fast_sleep_set_log_callback: { parameters: ["function"], result: "void" },
+  fast_sleep_set_log_level: { parameters: ["u32"], result: "i32" },
+  fast_sleep_set_power_mode: { parameters: ["u32"], result: "i32" },
+  fast_sleep_set_timer_resolution: { parameters: ["u32"], result: "i32" },
+  fast_sleep_set_windows_timer_resolution: { parameters: ["u32"], result: "i32" },
+  fast_sleep_stats: { parameters: ["pointer", "u32"], result: "u32" },
+  fast_sleep_stats_enable: { parameters: ["u8"], result: "void" },
+  fast_sleep_stats_reset: { parameters: [], result: "void" },
+  fast_sleep_token_create: { parameters: [], result: "u32" },
+  fast_sleep_token_destroy: { parameters: ["u32"], result: "i32" },
+  fast_sleep_token_reset: { parameters: ["u32"], result: "i32" },
+  fast_sleep_until_any: { parameters: ["pointer", "u32", "u32"], result: "i32" },
+  fast_sleep_until_ns: { parameters: ["u64"], result: "i64" },
+  fast_sleep_until_signal: { parameters: ["pointer", "u64"], result: "u8" },
+  fast_sleep_until_us: { parameters: ["u64"], result: "i64" },
+  fast_sleep_until_us_with: { parameters: ["u64", "u32", "u32"], result: "i32" },
+  fast_sleep_us: { parameters: ["u32"], result: "void" },
+  fast_sleep_us_measured: { parameters: ["u32"], result: "u64" },
+  fast_sleep_us_with: { parameters: ["u32", "u32", "u32"], result: "i32" },
+  fast_sleeper_create: { parameters: ["u32", "u32"], result: "u32" },
+  fast_sleeper_destroy: { parameters: ["u32"], result: "i32" },
+  fast_sleeper_sleep_until_us: { parameters: ["u32", "u64"], result: "i64" },
+  fast_sleeper_sleep_us: { parameters: ["u32", "u32"], result: "i32" },
+  fast_ticker_align: { parameters: ["u32", "u64"], result: "i32" },
+  fast_ticker_create: { parameters: ["u32", "u32"], result: "u32" },
+  fast_ticker_create_ex: { parameters: ["pointer", "u32"], result: "u32" },
+  fast_ticker_destroy: { parameters: ["u32"], result: "i32" },
+  fast_ticker_info: { parameters: ["u32", "pointer", "u32"], result: "u32" },
+  fast_ticker_set_period: { parameters: ["u32", "u32"], result: "i32" },
+  fast_ticker_skipped: { parameters: ["u32"], result: "i64" },
+  fast_ticker_wait: { parameters: ["u32"], result: "i64" },
+  fast_timer_cancel: { parameters: ["u32"], result: "i32" },
+  fast_timer_schedule: { parameters: ["u32", "function"], result: "u32" },
+  fast_wait_flag_until_us: { parameters: ["pointer", "u32", "u64"], result: "i32" },
+} as const satisfies Deno.ForeignLibraryInterface;
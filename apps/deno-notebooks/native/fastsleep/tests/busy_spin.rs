@@ -0,0 +1,19 @@
+use fastsleep::{fast_sleep_configure, fast_sleep_last_error_code, fast_sleep_us_measured};
+
+const STRATEGY_BUSY_SPIN: u32 = 3;
+const ERR_BUSY_SPIN_TOO_LONG: i32 = 300;
+
+#[test]
+fn busy_spin_window_is_capped_and_short_waits_are_accurate() {
+    assert_eq!(fast_sleep_configure(10_000, STRATEGY_BUSY_SPIN), -2);
+    assert_eq!(fast_sleep_last_error_code(), ERR_BUSY_SPIN_TOO_LONG);
+
+    assert_eq!(fast_sleep_configure(1_000, STRATEGY_BUSY_SPIN), 0);
+    assert_eq!(fast_sleep_last_error_code(), 0);
+    let mut elapsed_ns: Vec<u64> = (0..21).map(|_| fast_sleep_us_measured(200)).collect();
+    assert!(elapsed_ns.iter().all(|&ns| ns >= 200_000), "{elapsed_ns:?}");
+    // The whole wait is inside the spin window, so a typical one ends within a
+    // few µs; the median keeps an occasional preemption on CI from failing it.
+    elapsed_ns.sort_unstable();
+    assert!(elapsed_ns[10] < 200_000 + 50_000, "{elapsed_ns:?}");
+}
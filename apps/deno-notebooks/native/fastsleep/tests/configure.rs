@@ -0,0 +1,41 @@
+use fastsleep::{fast_sleep_get_config, fast_sleep_init, fast_sleep_set_power_mode, fast_sleep_us};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::ptr;
+use std::sync::Arc;
+use std::thread;
+
+#[test]
+fn init_is_not_blocked_by_sleeps_in_progress() {
+    let stop = Arc::new(AtomicBool::new(false));
+    let sleepers: Vec<_> = (0..4)
+        .map(|_| {
+            let stop = stop.clone();
+            thread::spawn(move || {
+                while !stop.load(Ordering::Relaxed) {
+                    fast_sleep_us(10);
+                }
+            })
+        })
+        .collect();
+    for i in 0..1_000 {
+        assert_eq!(fast_sleep_init(100 + i % 2, 1), 0);
+    }
+    stop.store(true, Ordering::Relaxed);
+    for sleeper in sleepers {
+        sleeper.join().unwrap();
+    }
+    let (mut accuracy_us, mut strategy) = (0, 0);
+    unsafe { fast_sleep_get_config(&mut accuracy_us, &mut strategy, ptr::null_mut()) };
+    assert_eq!((accuracy_us, strategy), (101, 1));
+}
+
+#[test]
+fn get_config_reports_the_power_mode() {
+    let mut power_mode = u32::MAX;
+    assert_eq!(fast_sleep_set_power_mode(1), 0);
+    unsafe { fast_sleep_get_config(ptr::null_mut(), ptr::null_mut(), &mut power_mode) };
+    assert_eq!(power_mode, 1);
+    assert_eq!(fast_sleep_set_power_mode(0), 0);
+    unsafe { fast_sleep_get_config(ptr::null_mut(), ptr::null_mut(), &mut power_mode) };
+    assert_eq!(power_mode, 0);
+}
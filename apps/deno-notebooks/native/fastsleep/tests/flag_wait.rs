@@ -0,0 +1,67 @@
+use fastsleep::{fast_now_us, fast_set_wake_flag, fast_wait_flag_until_us};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+fn wait(flag: &AtomicU32, expected_nonzero: u32, deadline_us: u64) -> i32 {
+    unsafe { fast_wait_flag_until_us(flag.as_ptr(), expected_nonzero, deadline_us) }
+}
+
+/// Set `flag` to `value` through fast_set_wake_flag after `delay`.
+fn set_after(flag: &Arc<AtomicU32>, value: u32, delay: Duration) -> thread::JoinHandle<()> {
+    let flag = flag.clone();
+    thread::spawn(move || {
+        thread::sleep(delay);
+        assert_eq!(unsafe { fast_set_wake_flag(flag.as_ptr(), value) }, 0);
+    })
+}
+
+#[test]
+fn times_out_when_the_flag_stays_clear() {
+    let flag = AtomicU32::new(0);
+    let deadline_us = fast_now_us() + 5_000;
+    assert_eq!(wait(&flag, 0, deadline_us), 0);
+    assert!(fast_now_us() >= deadline_us);
+}
+
+#[test]
+fn returns_early_when_another_thread_sets_the_flag() {
+    let flag = Arc::new(AtomicU32::new(0));
+    let start = Instant::now();
+    let setter = set_after(&flag, 7, Duration::from_millis(5));
+    assert_eq!(wait(&flag, 0, fast_now_us() + 2_000_000), 1);
+    assert!(start.elapsed() < Duration::from_millis(500), "{:?}", start.elapsed());
+    setter.join().unwrap();
+}
+
+#[test]
+fn waits_for_the_expected_value() {
+    let flag = Arc::new(AtomicU32::new(0));
+    let setter = set_after(&flag, 1, Duration::from_millis(1));
+    let deadline_us = fast_now_us() + 20_000;
+    assert_eq!(wait(&flag, 2, deadline_us), 0);
+    assert!(fast_now_us() >= deadline_us);
+    setter.join().unwrap();
+}
+
+#[test]
+fn flag_set_near_the_deadline_is_either_seen_or_times_out_on_time() {
+    // xorshift; the sequence only needs to vary, not to be good.
+    let mut seed = 0x2545_f491_u32;
+    for _ in 0..50 {
+        seed ^= seed << 13;
+        seed ^= seed >> 17;
+        seed ^= seed << 5;
+        let flag = Arc::new(AtomicU32::new(0));
+        let deadline_us = fast_now_us() + 3_000;
+        // Somewhere between 1ms before and 1ms after the deadline.
+        let setter = set_after(&flag, 1, Duration::from_micros(2_000 + (seed % 2_000) as u64));
+        match wait(&flag, 0, deadline_us) {
+            1 => assert_ne!(flag.load(Ordering::Acquire), 0),
+            0 => assert!(fast_now_us() >= deadline_us),
+            rc => panic!("unexpected result {rc}"),
+        }
+        setter.join().unwrap();
+    }
+}
@@ -0,0 +1,21 @@
+use fastsleep::{fast_sleep_configure, fast_sleep_ns_measured, fast_sleep_us_measured};
+
+const ACCURACY_US: u32 = 200;
+
+fn check(requested_ns: u64, mut elapsed_ns: Vec<u64>) {
+    assert!(elapsed_ns.iter().all(|&ns| ns >= requested_ns), "{elapsed_ns:?}");
+    // Overshoot past the native-accuracy spin tail only comes from preemption,
+    // so the median stays inside it even on a loaded host.
+    elapsed_ns.sort_unstable();
+    let median = elapsed_ns[elapsed_ns.len() / 2];
+    assert!(median < requested_ns + ACCURACY_US as u64 * 1_000, "{elapsed_ns:?}");
+}
+
+#[test]
+fn measured_sleeps_cover_the_request_within_the_configured_accuracy() {
+    assert_eq!(fast_sleep_configure(ACCURACY_US, 1), 0);
+    check(1_000_000, (0..21).map(|_| fast_sleep_us_measured(1_000)).collect());
+    check(50_000, (0..21).map(|_| fast_sleep_us_measured(50)).collect());
+    check(1_500_500, (0..21).map(|_| fast_sleep_ns_measured(1_500_500)).collect());
+    check(750, (0..21).map(|_| fast_sleep_ns_measured(750)).collect());
+}
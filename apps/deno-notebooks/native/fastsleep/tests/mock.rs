@@ -0,0 +1,26 @@
+//! Mock mode is process-wide and one-way, so everything that needs it lives in
+//! this one test.
+
+use fastsleep::{
+    fast_sleep_cancellable_us, fast_sleep_enable_mock, fast_sleep_get_virtual_time_ns,
+    fast_sleep_token_create, fast_sleep_us,
+};
+use std::time::{Duration, Instant};
+
+#[test]
+fn mock_sleeps_advance_virtual_time_only() {
+    assert_eq!(fast_sleep_enable_mock(4.0), 0);
+
+    let start = Instant::now();
+    let before = fast_sleep_get_virtual_time_ns();
+    fast_sleep_us(1_000_000);
+    assert!(start.elapsed() < Duration::from_millis(50), "{:?}", start.elapsed());
+    assert_eq!(fast_sleep_get_virtual_time_ns() - before, 250_000_000);
+
+    let start = Instant::now();
+    let before = fast_sleep_get_virtual_time_ns();
+    assert_eq!(fast_sleep_cancellable_us(fast_sleep_token_create(), 1_000_000), 0);
+    assert!(start.elapsed() < Duration::from_millis(50), "{:?}", start.elapsed());
+    let advanced = fast_sleep_get_virtual_time_ns() - before;
+    assert!((240_000_000..=250_000_000).contains(&advanced), "{advanced}");
+}
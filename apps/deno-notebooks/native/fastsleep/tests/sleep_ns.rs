@@ -0,0 +1,34 @@
+//! Runs on the mock clock (its own test binary, so the mock stays out of the
+//! other tests) to check the exact amounts slept.
+
+use fastsleep::{
+    fast_sleep_enable_mock, fast_sleep_get_virtual_time_ns, fast_sleep_ns, fast_sleep_us,
+};
+
+fn slept_ns(f: impl FnOnce()) -> u64 {
+    let before = fast_sleep_get_virtual_time_ns();
+    f();
+    fast_sleep_get_virtual_time_ns() - before
+}
+
+#[test]
+fn ns_sleeps_keep_sub_microsecond_time_and_do_not_overflow() {
+    assert_eq!(fast_sleep_enable_mock(1.0), 0);
+
+    assert_eq!(slept_ns(|| fast_sleep_ns(1)), 1);
+    assert_eq!(slept_ns(|| fast_sleep_ns(999)), 999);
+    assert_eq!(slept_ns(|| fast_sleep_ns(0)), 0);
+
+    // A chain of 1.5µs sleeps sums exactly with the ns API; the µs API can
+    // only ask for 1µs each and loses a third of it.
+    assert_eq!(slept_ns(|| (0..10_000).for_each(|_| fast_sleep_ns(1_500))), 15_000_000);
+    assert_eq!(slept_ns(|| (0..10_000).for_each(|_| fast_sleep_us(1))), 10_000_000);
+
+    // The µs API converts without wrapping at u32 µs * 1000.
+    assert_eq!(slept_ns(|| fast_sleep_us(u32::MAX)), u32::MAX as u64 * 1_000);
+
+    // Longer than the ~71 minute µs cap, and the largest possible request.
+    assert_eq!(slept_ns(|| fast_sleep_ns(10_000_000_000_000)), 10_000_000_000_000);
+    fast_sleep_ns(u64::MAX);
+    assert_eq!(fast_sleep_get_virtual_time_ns(), u64::MAX);
+}
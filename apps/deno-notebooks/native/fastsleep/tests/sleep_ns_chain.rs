@@ -0,0 +1,38 @@
+use fastsleep::{fast_sleep_configure, fast_sleep_ns, fast_sleep_us};
+use std::time::{Duration, Instant};
+
+const CHAIN: u32 = 10_000;
+const STEP_NS: u64 = 1_500;
+
+/// Median wall time of five chains of `CHAIN` calls to `sleep`.
+fn chain_time(sleep: impl Fn()) -> Duration {
+    let mut totals: Vec<Duration> = (0..5)
+        .map(|_| {
+            let start = Instant::now();
+            (0..CHAIN).for_each(|_| sleep());
+            start.elapsed()
+        })
+        .collect();
+    totals.sort_unstable();
+    totals[2]
+}
+
+#[test]
+fn ns_chain_lands_on_the_sum_and_us_chains_cannot() {
+    // Spin the whole tail so each sleep ends right at its deadline.
+    assert_eq!(fast_sleep_configure(200, 2), 0);
+    // Per-call overhead, measured with zero-length sleeps, is taken out so
+    // only the time actually slept is compared with the sum.
+    let overhead = chain_time(|| fast_sleep_ns(0));
+    let sum = Duration::from_nanos(STEP_NS * CHAIN as u64);
+    let tolerance = sum / 10;
+
+    let ns = chain_time(|| fast_sleep_ns(STEP_NS)).saturating_sub(overhead);
+    assert!(ns.abs_diff(sum) < tolerance, "ns chain slept {ns:?} for a sum of {sum:?}");
+
+    // 1.5µs is not expressible in µs: rounding either way misses by a third.
+    let us_down = chain_time(|| fast_sleep_us(1)).saturating_sub(overhead);
+    let us_up = chain_time(|| fast_sleep_us(2)).saturating_sub(overhead);
+    assert!(us_down + tolerance < sum, "1µs chain slept {us_down:?}");
+    assert!(us_up > sum + tolerance, "2µs chain slept {us_up:?}");
+}
@@ -0,0 +1,27 @@
+use fastsleep::{fast_loop_start, fast_loop_stop, fast_sleep_last_error_code, LOOP_FLAG_PRIORITY_MASK};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread;
+use std::time::Duration;
+
+const INVALID_ARGUMENT: i32 = 1;
+
+static TICKS: AtomicU64 = AtomicU64::new(0);
+
+extern "C" fn on_tick(_tick: u64, _late_us: i64) {
+    TICKS.fetch_add(1, Ordering::Relaxed);
+}
+
+#[test]
+fn loop_flags_carry_a_priority_level_and_reject_unknown_bits() {
+    assert_eq!(fast_loop_start(1_000, on_tick, 0x4), 0);
+    assert_eq!(fast_sleep_last_error_code(), INVALID_ARGUMENT);
+    assert_eq!(fast_loop_start(1_000, on_tick, LOOP_FLAG_PRIORITY_MASK), 0);
+    assert_eq!(fast_sleep_last_error_code(), INVALID_ARGUMENT);
+
+    // An above-normal level the OS refuses is stepped down, not an error.
+    let handle = fast_loop_start(1_000, on_tick, 1);
+    assert_ne!(handle, 0);
+    thread::sleep(Duration::from_millis(20));
+    assert_eq!(fast_loop_stop(handle), 0);
+    assert!(TICKS.load(Ordering::Relaxed) > 0);
+}
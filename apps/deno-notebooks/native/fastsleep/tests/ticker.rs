@@ -0,0 +1,34 @@
+use fastsleep::{fast_ticker_create, fast_ticker_destroy, fast_ticker_wait};
+use std::time::{Duration, Instant};
+
+const TICKER_CATCH_UP: u32 = 0x1;
+
+fn median(values: &mut [Duration]) -> Duration {
+    values.sort_unstable();
+    values[values.len() / 2]
+}
+
+#[test]
+fn ticker_does_not_drift_over_10k_ticks() {
+    const TICKS: u32 = 10_000;
+    let period = Duration::from_micros(100);
+    // Taken before the ticker anchors, so every boundary is at or after
+    // `start + n * period` and each error below is at least the wakeup lateness.
+    let start = Instant::now();
+    let handle = fast_ticker_create(period.as_micros() as u32, TICKER_CATCH_UP);
+    assert_ne!(handle, 0);
+
+    let mut errors = Vec::with_capacity(TICKS as usize);
+    for n in 1..=TICKS {
+        assert!(fast_ticker_wait(handle) >= 0);
+        errors.push(start.elapsed().saturating_sub(period * n));
+    }
+    assert_eq!(fast_ticker_destroy(handle), 0);
+
+    // Per-tick lateness on a loaded host can spike, but it must not add up:
+    // the error late in the run is no larger than early on, and under a period.
+    let first = median(&mut errors[..1_000].to_vec());
+    let last = median(&mut errors[errors.len() - 1_000..].to_vec());
+    assert!(last < period, "median error over the last 1000 ticks is {last:?}");
+    assert!(last < first + period / 2, "error grew from {first:?} to {last:?}");
+}
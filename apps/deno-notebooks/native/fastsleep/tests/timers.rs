@@ -0,0 +1,34 @@
+use avtools_ffi::shutdown;
+use fastsleep::{fast_timer_cancel, fast_timer_schedule};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+static FIRED: AtomicU32 = AtomicU32::new(0);
+
+extern "C" fn on_timer(_timer_id: u32, _late_us: i64) {
+    FIRED.fetch_add(1, Ordering::Relaxed);
+}
+
+fn wait_for_fired(count: u32) {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while FIRED.load(Ordering::Relaxed) < count {
+        assert!(Instant::now() < deadline, "timer did not fire");
+        thread::sleep(Duration::from_millis(1));
+    }
+}
+
+#[test]
+fn shutdown_cancels_pending_timers_and_the_next_one_restarts_the_worker() {
+    assert_ne!(fast_timer_schedule(1_000, on_timer), 0);
+    wait_for_fired(1);
+
+    let pending = fast_timer_schedule(60_000_000, on_timer);
+    assert_ne!(pending, 0);
+    let report = shutdown::run();
+    assert!(report.contains("\"timers\":1"), "{report}");
+    assert_eq!(fast_timer_cancel(pending), -1);
+
+    assert_ne!(fast_timer_schedule(1_000, on_timer), 0);
+    wait_for_fired(2);
+}
@@ -0,0 +1,243 @@
+/* Generated by native/avtools_bindgen from the exports of midi_bridge; do not edit. */
+
+#ifndef AVTOOLS_MIDI_BRIDGE_H
+#define AVTOOLS_MIDI_BRIDGE_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+typedef void (*Callback)(const uint8_t*, uint32_t);
+
+uint32_t midi_list_inputs(uint8_t *out_ptr, uint32_t out_cap);
+
+uint32_t midi_list_outputs(uint8_t *out_ptr, uint32_t out_cap);
+
+uint32_t midi_open_input(const uint8_t *port_id_ptr,
+                         uint32_t port_id_len,
+                         uint32_t rate_hz,
+                         uint32_t flags,
+                         Callback cb);
+
+uint32_t midi_open_input_ex(const uint8_t *port_id_ptr,
+                            uint32_t port_id_len,
+                            const uint8_t *options_json_ptr,
+                            uint32_t options_json_len,
+                            Callback cb);
+
+uint32_t midi_open_mock_input(const uint8_t *options_json_ptr,
+                              uint32_t options_json_len,
+                              Callback cb);
+
+int32_t midi_mock_input_send(uint32_t handle, const uint8_t *msg_ptr, uint32_t msg_len);
+
+uint32_t midi_open_input_with_retry(const uint8_t *port_id_ptr,
+                                    uint32_t port_id_len,
+                                    uint32_t rate_hz,
+                                    uint32_t flags,
+                                    Callback cb,
+                                    uint32_t max_attempts,
+                                    uint32_t initial_backoff_ms);
+
+uint8_t midi_is_handle_connected(uint32_t handle);
+
+void midi_close_input(uint32_t handle);
+
+uint32_t midi_input_stats(uint32_t handle, uint8_t *out_ptr, uint32_t out_cap);
+
+uint32_t midi_metrics_json(uint8_t *out_ptr, uint32_t out_cap);
+
+void midi_set_program_name(uint8_t program, const uint8_t *name_ptr, uint32_t name_len);
+
+void midi_set_cc_display_format(uint8_t cc,
+                                const uint8_t *name_ptr,
+                                uint32_t name_len,
+                                const uint8_t *format_ptr,
+                                uint32_t format_len);
+
+uint32_t midi_get_cc_display_format(uint8_t cc, uint8_t *buf_ptr, uint32_t buf_cap);
+
+uint32_t midi_get_program_name(uint32_t handle,
+                               uint8_t channel,
+                               uint8_t *buf_ptr,
+                               uint32_t buf_cap);
+
+float midi_get_cc14(uint32_t handle, uint8_t channel, uint8_t msb_cc);
+
+int32_t midi_add_cc_remap(uint32_t handle,
+                          uint8_t from_channel,
+                          uint8_t from_cc,
+                          uint8_t to_channel,
+                          uint8_t to_cc);
+
+int32_t midi_remove_cc_remap(uint32_t handle, uint8_t from_channel, uint8_t from_cc);
+
+int32_t midi_clear_cc_remaps(uint32_t handle);
+
+uint64_t midi_get_epoch_us(uint32_t handle);
+
+int32_t midi_set_quantise_grid(uint32_t handle,
+                               uint32_t ppqn,
+                               uint32_t bpm_x100,
+                               uint8_t strength_percent);
+
+int32_t midi_clear_quantise_grid(uint32_t handle);
+
+int32_t midi_set_note_quantise_scale(uint32_t handle,
+                                     uint8_t channel,
+                                     uint8_t root_note,
+                                     uint16_t scale_mask);
+
+int32_t midi_clear_note_quantise_scale(uint32_t handle, uint8_t channel);
+
+int32_t midi_set_latency_compensation(uint32_t handle, int64_t offset_us);
+
+int32_t midi_set_cc_lock_mode(uint32_t handle,
+                              uint8_t channel,
+                              uint8_t cc,
+                              uint8_t current_value,
+                              uint8_t lock_threshold);
+
+int32_t midi_release_cc_lock(uint32_t handle, uint8_t channel, uint8_t cc);
+
+uint32_t midi_get_bandwidth_bps(uint32_t handle);
+
+int32_t midi_set_msc_device_id(uint32_t handle, uint8_t device_id);
+
+int32_t midi_register_note_expression(uint32_t handle,
+                                      uint8_t channel,
+                                      uint8_t note,
+                                      uint32_t kind,
+                                      const uint8_t *template_ptr,
+                                      uint32_t template_len,
+                                      uint8_t value_offset);
+
+int32_t midi_clear_note_expressions(uint32_t handle);
+
+int32_t midi_set_note_timeout_ms(uint32_t handle, uint32_t timeout_ms);
+
+int32_t midi_set_beat_dispatch(uint32_t handle, uint32_t source_clock_handle);
+
+int32_t midi_set_dispatch_clocks_per_tick(uint32_t handle, uint32_t clocks);
+
+uint32_t midi_open_output(const uint8_t *port_id_ptr, uint32_t port_id_len);
+
+uint32_t midi_open_output_pool(const uint8_t *port_ids_json_ptr, uint32_t port_ids_json_len);
+
+uint32_t midi_get_pool_member_handles(uint32_t pool_handle, uint8_t *out_ptr, uint32_t out_cap);
+
+void midi_close_output(uint32_t handle);
+
+int32_t midi_send(uint32_t handle, const uint8_t *bytes_ptr, uint32_t len);
+
+int32_t midi_send_osc_tunnelled(uint32_t output_handle,
+                                const uint8_t *osc_bytes_ptr,
+                                uint32_t osc_bytes_len,
+                                uint8_t device_id);
+
+int32_t midi_send_mmc(uint32_t output_handle, uint8_t device_id, uint8_t command);
+
+int32_t midi_set_cc14(uint32_t output_handle, uint8_t channel, uint8_t msb_cc, float value_f32);
+
+int32_t midi_mpe_negotiate(uint32_t input_handle, uint32_t output_handle, uint32_t timeout_ms);
+
+int32_t midi_sysex_request_response(uint32_t output_handle,
+                                    uint32_t input_handle,
+                                    const uint8_t *request_ptr,
+                                    uint32_t request_len,
+                                    const uint8_t *response_prefix_ptr,
+                                    uint32_t response_prefix_len,
+                                    uint32_t timeout_ms,
+                                    uint8_t *buf_ptr,
+                                    uint32_t buf_cap);
+
+uint32_t midi_create_pipeline(void);
+
+int32_t midi_pipeline_add_transpose(uint32_t pipeline, uint8_t channel, int8_t semitones);
+
+int32_t midi_pipeline_add_channel_remap(uint32_t pipeline, uint8_t from, uint8_t to);
+
+int32_t midi_pipeline_add_cc_remap(uint32_t pipeline,
+                                   uint8_t from_channel,
+                                   uint8_t from_cc,
+                                   uint8_t to_channel,
+                                   uint8_t to_cc);
+
+int32_t midi_pipeline_add_velocity_scale(uint32_t pipeline, uint8_t channel, uint16_t percent);
+
+int32_t midi_pipeline_clear(uint32_t pipeline);
+
+int32_t midi_pipeline_attach_input(uint32_t pipeline, uint32_t input_handle);
+
+int32_t midi_pipeline_attach_output(uint32_t pipeline, uint32_t output_handle);
+
+int32_t midi_pipeline_destroy(uint32_t pipeline);
+
+uint32_t midi_start_cc_record(uint32_t handle, uint8_t channel, uint8_t cc);
+
+int32_t midi_stop_cc_record(uint32_t rec_id);
+
+int32_t midi_delete_cc_record(uint32_t rec_id);
+
+uint32_t midi_playback_cc(uint32_t output_handle,
+                          uint32_t rec_id,
+                          uint8_t channel,
+                          uint8_t cc,
+                          uint32_t speed_x1000,
+                          uint32_t loop_count);
+
+int32_t midi_stop_cc_playback(uint32_t pb_id);
+
+uint32_t midi_clock_start(uint32_t output_handle, float bpm);
+
+int32_t midi_clock_set_bpm(uint32_t clock_handle, float bpm);
+
+int32_t midi_clock_set_cc_sync(uint32_t clock_handle,
+                               uint32_t input_handle,
+                               uint8_t channel,
+                               uint8_t cc,
+                               float min_bpm,
+                               float max_bpm);
+
+float midi_clock_get_bpm(uint32_t clock_handle);
+
+int32_t midi_clock_stop(uint32_t clock_handle);
+
+uint32_t midi_abi_version(void);
+
+uint32_t midi_capabilities(uint8_t *out_ptr, uint32_t out_cap);
+
+uint32_t midi_decode_frame_json(const uint8_t *ptr,
+                                uint32_t len,
+                                uint8_t *out_ptr,
+                                uint32_t out_cap);
+
+int32_t midi_last_error_code(void);
+
+uint32_t midi_last_error_message(uint8_t *out_ptr, uint32_t out_cap);
+
+uint32_t midi_get_last_panic(uint8_t *buf_ptr, uint32_t buf_cap);
+
+void midi_set_log_callback(void (*cb)(uint32_t, const uint8_t*, uint32_t));
+
+int32_t midi_set_log_level(uint32_t level);
+
+int64_t avtools_clock_offset(uint32_t domain_a, uint32_t domain_b);
+
+uint64_t avtools_clock_now_us(void);
+
+uint8_t *av_alloc(uint32_t len);
+
+void av_free(uint8_t *ptr, uint32_t len);
+
+extern int32_t atexit(void (*cb)(void));
+
+uint32_t avtools_shutdown_all(uint8_t *out_ptr, uint32_t out_cap);
+
+int32_t avtools_tracing_start(const uint8_t *path_ptr, uint32_t path_len, uint32_t level);
+
+int32_t avtools_tracing_stop(void);
+
+#endif  /* AVTOOLS_MIDI_BRIDGE_H */
@@ -0,0 +1,148 @@
+use std::collections::VecDeque;
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use avtools_ffi::worker::{spawn_worker, Worker};
+
+use crate::clock::StopSignal;
+
+/// Samples kept per recording; older ones are dropped first.
+const RECORDING_CAP: usize = 65_536;
+
+/// One CC on one channel, recorded as it arrives on an input.
+pub struct CcRecording {
+    pub channel: u8,
+    pub cc: u8,
+    active: AtomicBool,
+    samples: Mutex<VecDeque<(u64, u8)>>,
+}
+
+impl CcRecording {
+    pub fn new(channel: u8, cc: u8) -> Self {
+        Self {
+            channel,
+            cc,
+            active: AtomicBool::new(true),
+            samples: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active.load(Ordering::Relaxed)
+    }
+
+    pub fn stop(&self) {
+        self.active.store(false, Ordering::Relaxed);
+    }
+
+    pub fn push(&self, ts_us: u64, value: u8) {
+        let mut samples = self.samples.lock().unwrap_or_else(|e| e.into_inner());
+        if samples.len() >= RECORDING_CAP {
+            samples.pop_front();
+        }
+        samples.push_back((ts_us, value));
+    }
+
+    pub fn snapshot(&self) -> Vec<(u64, u8)> {
+        self.samples.lock().unwrap_or_else(|e| e.into_inner()).iter().copied().collect()
+    }
+}
+
+/// Replays a recording onto an output on its own worker thread, sending every
+/// intermediate value between consecutive samples at linearly spaced times.
+pub struct CcPlayback {
+    stop: Arc<StopSignal>,
+    worker: Worker,
+}
+
+impl CcPlayback {
+    /// `channel` is 0–15 and `cc` 0–127. `speed_x1000` scales time (1000 = as
+    /// recorded, 2000 = twice as fast).
+    /// `loop_count` 0 repeats until stopped. `send` returns false once the
+    /// output is gone, which ends playback.
+    pub fn start<F>(
+        samples: Vec<(u64, u8)>,
+        channel: u8,
+        cc: u8,
+        speed_x1000: u32,
+        loop_count: u32,
+        send: F,
+    ) -> io::Result<Self>
+    where
+        F: Fn(&[u8]) -> bool + Send + 'static,
+    {
+        let stop = Arc::new(StopSignal::new());
+        let thread_stop = stop.clone();
+        let worker = spawn_worker("midi-cc-playback", 0, move || {
+            let status = 0xB0 | channel;
+            let speed = speed_x1000.max(1) as u64;
+            let mut pass = 0;
+            while loop_count == 0 || pass < loop_count {
+                pass += 1;
+                if !play_once(&samples, speed, &thread_stop, |value| {
+                    send(&[status, cc, value])
+                }) {
+                    return;
+                }
+            }
+        })?;
+        Ok(Self { stop, worker })
+    }
+
+    /// Stop playback; nothing more is sent once this returns.
+    pub fn stop(self) {
+        self.stop.stop();
+        self.worker.join();
+    }
+
+    /// Like `stop`, but give up waiting after `timeout`. False if the thread
+    /// was left to finish.
+    pub fn stop_within(self, timeout: Duration) -> bool {
+        self.stop.stop();
+        self.worker.join_within(timeout)
+    }
+}
+
+/// Returns false if playback should end (stopped, output gone, or nothing to play).
+fn play_once<F>(samples: &[(u64, u8)], speed_x1000: u64, stop: &StopSignal, send: F) -> bool
+where
+    F: Fn(u8) -> bool,
+{
+    let Some(&(origin, first)) = samples.first() else {
+        return false;
+    };
+    let start = Instant::now();
+    let at = |ts_us: u64| {
+        start
+            + Duration::from_micros(ts_us.saturating_sub(origin).saturating_mul(1000) / speed_x1000)
+    };
+    let mut last = first;
+    if !send(first) {
+        return false;
+    }
+    for pair in samples.windows(2) {
+        let ((t0, v0), (t1, v1)) = (pair[0], pair[1]);
+        let steps = (v1 as i32 - v0 as i32).unsigned_abs() as u64;
+        for step in 1..=steps.max(1) {
+            let ts = t0 + t1.saturating_sub(t0) * step / steps.max(1);
+            let value = if steps == 0 {
+                v1
+            } else {
+                (v0 as i64 + (v1 as i64 - v0 as i64) * step as i64 / steps as i64) as u8
+            };
+            if stop.wait_until(at(ts)) {
+                return false;
+            }
+            if value != last {
+                let _span = avtools_trace::span!(DEBUG, "automation_send", value);
+                if !send(value) {
+                    return false;
+                }
+                last = value;
+            }
+        }
+    }
+    !stop.is_stopped()
+}
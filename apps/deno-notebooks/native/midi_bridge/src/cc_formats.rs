@@ -0,0 +1,116 @@
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::sync::Mutex;
+
+/// How a UI should show a controller's value. `format` is a free-form hint;
+/// the built-in table uses "raw" (0–127), "percent" (0–100%), "signed_64"
+/// (value - 64, centred pan/balance), "signed_127" and "on_off" (>= 64 is on),
+/// and callers may add their own such as "hz".
+#[derive(Clone, Serialize)]
+pub struct CcFormat {
+    pub name: String,
+    pub format: String,
+}
+
+/// General MIDI / MIDI 1.0 controller names with a format hint; `None` for
+/// controllers the spec leaves undefined.
+pub fn gm_cc_format(cc: u8) -> Option<CcFormat> {
+    let (name, format) = match cc {
+        0 => ("Bank Select", "raw"),
+        1 => ("Modulation", "percent"),
+        2 => ("Breath Controller", "percent"),
+        4 => ("Foot Controller", "percent"),
+        5 => ("Portamento Time", "raw"),
+        6 => ("Data Entry", "raw"),
+        7 => ("Volume", "percent"),
+        8 => ("Balance", "signed_64"),
+        10 => ("Pan", "signed_64"),
+        11 => ("Expression", "percent"),
+        12 => ("Effect Control 1", "raw"),
+        13 => ("Effect Control 2", "raw"),
+        16..=19 => (
+            [
+                "General Purpose 1",
+                "General Purpose 2",
+                "General Purpose 3",
+                "General Purpose 4",
+            ][cc as usize - 16],
+            "raw",
+        ),
+        32..=63 => {
+            let msb = gm_cc_format(cc - 32);
+            let name = msb.map_or_else(
+                || format!("CC {} LSB", cc - 32),
+                |f| format!("{} LSB", f.name),
+            );
+            return Some(CcFormat {
+                name,
+                format: "raw".to_string(),
+            });
+        }
+        64 => ("Sustain", "on_off"),
+        65 => ("Portamento", "on_off"),
+        66 => ("Sostenuto", "on_off"),
+        67 => ("Soft Pedal", "on_off"),
+        68 => ("Legato Footswitch", "on_off"),
+        69 => ("Hold 2", "on_off"),
+        70 => ("Sound Variation", "raw"),
+        71 => ("Resonance", "raw"),
+        72 => ("Release Time", "raw"),
+        73 => ("Attack Time", "raw"),
+        74 => ("Brightness", "raw"),
+        75 => ("Decay Time", "raw"),
+        76 => ("Vibrato Rate", "raw"),
+        77 => ("Vibrato Depth", "raw"),
+        78 => ("Vibrato Delay", "raw"),
+        79 => ("Sound Controller 10", "raw"),
+        80..=83 => (
+            [
+                "General Purpose 5",
+                "General Purpose 6",
+                "General Purpose 7",
+                "General Purpose 8",
+            ][cc as usize - 80],
+            "raw",
+        ),
+        84 => ("Portamento Control", "raw"),
+        88 => ("High Resolution Velocity Prefix", "raw"),
+        91 => ("Reverb Send", "percent"),
+        92 => ("Tremolo Depth", "percent"),
+        93 => ("Chorus Send", "percent"),
+        94 => ("Detune", "percent"),
+        95 => ("Phaser Depth", "percent"),
+        96 => ("Data Increment", "raw"),
+        97 => ("Data Decrement", "raw"),
+        98 => ("NRPN LSB", "raw"),
+        99 => ("NRPN MSB", "raw"),
+        100 => ("RPN LSB", "raw"),
+        101 => ("RPN MSB", "raw"),
+        120 => ("All Sound Off", "raw"),
+        121 => ("Reset All Controllers", "raw"),
+        122 => ("Local Control", "on_off"),
+        123 => ("All Notes Off", "raw"),
+        124 => ("Omni Mode Off", "raw"),
+        125 => ("Omni Mode On", "raw"),
+        126 => ("Mono Mode On", "raw"),
+        127 => ("Poly Mode On", "raw"),
+        _ => return None,
+    };
+    Some(CcFormat {
+        name: name.to_string(),
+        format: format.to_string(),
+    })
+}
+
+static CC_FORMATS: Lazy<Mutex<[Option<CcFormat>; 128]>> =
+    Lazy::new(|| Mutex::new(std::array::from_fn(|i| gm_cc_format(i as u8))));
+
+/// Override the display hint for `cc`. `None` restores the General MIDI default.
+pub fn set_cc_format(cc: u8, format: Option<CcFormat>) {
+    let idx = (cc & 0x7F) as usize;
+    CC_FORMATS.lock().unwrap_or_else(|e| e.into_inner())[idx] = format.or_else(|| gm_cc_format(idx as u8));
+}
+
+pub fn cc_format(cc: u8) -> Option<CcFormat> {
+    CC_FORMATS.lock().unwrap_or_else(|e| e.into_inner())[(cc & 0x7F) as usize].clone()
+}
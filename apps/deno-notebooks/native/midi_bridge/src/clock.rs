@@ -0,0 +1,181 @@
+use std::io;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use avtools_ffi::worker::{spawn_worker, Worker};
+
+/// MIDI clock resolution: timing clock messages per quarter note.
+const PULSES_PER_QUARTER: f64 = 24.0;
+
+/// Tempo range a clock generator accepts.
+pub const MIN_BPM: f32 = 1.0;
+pub const MAX_BPM: f32 = 999.0;
+
+/// MIDI clock (0xF8) pulse counter for one input, signalled from the midir
+/// callback. Inputs using beat dispatch wait on it instead of a timer.
+pub struct ClockPulses {
+    count: Mutex<u64>,
+    cv: Condvar,
+}
+
+impl ClockPulses {
+    pub fn new() -> Self {
+        Self {
+            count: Mutex::new(0),
+            cv: Condvar::new(),
+        }
+    }
+
+    pub fn pulse(&self) {
+        *self.count.lock().unwrap_or_else(|e| e.into_inner()) += 1;
+        self.cv.notify_all();
+    }
+
+    pub fn count(&self) -> u64 {
+        *self.count.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Block until at least `target` pulses have been counted. Returns the
+    /// count once reached, or None after `timeout` so callers can poll a stop flag.
+    pub fn wait_for(&self, target: u64, timeout: Duration) -> Option<u64> {
+        let deadline = Instant::now() + timeout;
+        let mut count = self.count.lock().unwrap_or_else(|e| e.into_inner());
+        while *count < target {
+            let now = Instant::now();
+            if now >= deadline {
+                return None;
+            }
+            count = self.cv.wait_timeout(count, deadline - now).unwrap_or_else(|e| e.into_inner()).0;
+        }
+        Some(*count)
+    }
+}
+
+/// Tempo from a controller on an input: 0 maps to `min_bpm`, 127 to `max_bpm`.
+#[derive(Clone, Copy)]
+pub struct CcSync {
+    pub input: u32,
+    pub channel: u8,
+    pub cc: u8,
+    pub min_bpm: f32,
+    pub max_bpm: f32,
+}
+
+impl CcSync {
+    fn bpm(&self, value: u8) -> f32 {
+        self.min_bpm + (self.max_bpm - self.min_bpm) * value as f32 / 127.0
+    }
+}
+
+/// Stop request for a clock or playback thread, waking it from the wait for
+/// its next send.
+pub struct StopSignal {
+    stopped: Mutex<bool>,
+    cv: Condvar,
+}
+
+impl StopSignal {
+    pub fn new() -> Self {
+        Self {
+            stopped: Mutex::new(false),
+            cv: Condvar::new(),
+        }
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        *self.stopped.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn stop(&self) {
+        *self.stopped.lock().unwrap_or_else(|e| e.into_inner()) = true;
+        self.cv.notify_all();
+    }
+
+    /// Wait until `due`; true if stopped first.
+    pub fn wait_until(&self, due: Instant) -> bool {
+        let mut stopped = self.stopped.lock().unwrap_or_else(|e| e.into_inner());
+        while !*stopped {
+            let now = Instant::now();
+            if now >= due {
+                return false;
+            }
+            stopped = self.cv.wait_timeout(stopped, due - now).unwrap_or_else(|e| e.into_inner()).0;
+        }
+        true
+    }
+}
+
+/// Sends MIDI timing clock (0xF8, 24 per quarter note) to an output on its
+/// own worker thread. The interval is recomputed before every pulse, from the
+/// CC sync when one is set, so tempo changes take effect on the next pulse.
+pub struct ClockGenerator {
+    bpm: Arc<AtomicU32>,
+    sync: Arc<Mutex<Option<CcSync>>>,
+    stop: Arc<StopSignal>,
+    worker: Worker,
+}
+
+impl ClockGenerator {
+    /// `read_cc` returns the input's current value for a sync, None once the
+    /// input is gone (the last tempo is kept). `send` returns false once the
+    /// output is gone, which stops the clock.
+    pub fn start<R, F>(bpm: f32, read_cc: R, send: F) -> io::Result<Self>
+    where
+        R: Fn(&CcSync) -> Option<u8> + Send + 'static,
+        F: Fn(&[u8]) -> bool + Send + 'static,
+    {
+        let bpm = Arc::new(AtomicU32::new(bpm.to_bits()));
+        let sync = Arc::new(Mutex::new(None));
+        let stop = Arc::new(StopSignal::new());
+        let (thread_bpm, thread_sync, thread_stop) = (bpm.clone(), sync.clone(), stop.clone());
+        let worker = spawn_worker("midi-clock", 0, move || {
+            let mut due = Instant::now();
+            while !thread_stop.wait_until(due) {
+                if !send(&[0xF8]) {
+                    return;
+                }
+                let sync = *thread_sync.lock().unwrap_or_else(|e| e.into_inner());
+                if let Some(bpm) = sync.and_then(|sync| read_cc(&sync).map(|value| sync.bpm(value))) {
+                    thread_bpm.store(bpm.to_bits(), Ordering::Relaxed);
+                }
+                let bpm = f32::from_bits(thread_bpm.load(Ordering::Relaxed)) as f64;
+                due += Duration::from_secs_f64(60.0 / (bpm * PULSES_PER_QUARTER));
+            }
+        })?;
+        Ok(Self {
+            bpm,
+            sync,
+            stop,
+            worker,
+        })
+    }
+
+    /// Current tempo, whether set directly or by the CC sync.
+    pub fn bpm(&self) -> f32 {
+        f32::from_bits(self.bpm.load(Ordering::Relaxed))
+    }
+
+    /// Set the tempo directly, replacing any CC sync.
+    pub fn set_bpm(&self, bpm: f32) {
+        *self.sync.lock().unwrap_or_else(|e| e.into_inner()) = None;
+        self.bpm.store(bpm.to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn set_cc_sync(&self, sync: Option<CcSync>) {
+        *self.sync.lock().unwrap_or_else(|e| e.into_inner()) = sync;
+    }
+
+    /// Stop the clock; no pulse is sent once this returns.
+    pub fn stop(self) {
+        self.stop.stop();
+        self.worker.join();
+    }
+
+    /// Like `stop`, but give up waiting after `timeout` (a send blocked on a
+    /// backend that is exiting). False if the thread was left to finish.
+    pub fn stop_within(self, timeout: Duration) -> bool {
+        self.stop.stop();
+        self.worker.join_within(timeout)
+    }
+}
@@ -0,0 +1,108 @@
+use std::collections::VecDeque;
+use std::sync::{Mutex, Once};
+
+use avtools_ffi::error;
+use once_cell::sync::Lazy;
+
+/// What a handle refers to. Output and pool handles share the output tables'
+/// id space, so both are accepted where an output is expected.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Kind {
+    Input,
+    Output,
+    Pool,
+    Pipeline,
+    Recording,
+    Playback,
+    Clock,
+}
+
+const SLOT_BITS: u32 = 16;
+const SLOT_MASK: u32 = (1 << SLOT_BITS) - 1;
+
+struct Slot {
+    /// Bumped every time the slot is reissued; never 0, so no id is 0.
+    generation: u16,
+    kind: Option<Kind>,
+}
+
+/// Ids handed out by every u32 handle table: the slot index in the low 16
+/// bits and the slot's generation in the high 16. Released slots go to the
+/// back of a FIFO, so a closed handle's slot is reused as late as possible and
+/// under a new generation, and the old id never matches the new object.
+struct Slots {
+    slots: Vec<Slot>,
+    free: VecDeque<u32>,
+}
+
+static SHUTDOWN_HOOKS: Once = Once::new();
+
+static SLOTS: Lazy<Mutex<Slots>> = Lazy::new(|| {
+    Mutex::new(Slots {
+        slots: Vec::new(),
+        free: VecDeque::new(),
+    })
+});
+
+fn split(id: u32) -> (usize, u16) {
+    ((id & SLOT_MASK) as usize, (id >> SLOT_BITS) as u16)
+}
+
+/// New id for a `kind` object, or 0 (with BUSY recorded) once all 65536 slots
+/// are in use.
+pub fn issue(kind: Kind) -> u32 {
+    // Whatever gets a handle is something avtools_shutdown_all must close.
+    SHUTDOWN_HOOKS.call_once(crate::register_shutdown_hooks);
+    let mut slots = SLOTS.lock().unwrap_or_else(|e| e.into_inner());
+    let index = match slots.free.pop_front() {
+        Some(index) => index,
+        None if slots.slots.len() <= SLOT_MASK as usize => {
+            slots.slots.push(Slot {
+                generation: 0,
+                kind: None,
+            });
+            (slots.slots.len() - 1) as u32
+        }
+        None => {
+            error::fail(error::BUSY, "out of MIDI handles");
+            return 0;
+        }
+    };
+    let slot = &mut slots.slots[index as usize];
+    slot.generation = slot.generation.checked_add(1).unwrap_or(1);
+    slot.kind = Some(kind);
+    ((slot.generation as u32) << SLOT_BITS) | index
+}
+
+/// Retire `id`. Stale or unknown ids are ignored.
+pub fn release(id: u32) {
+    let (index, generation) = split(id);
+    let mut slots = SLOTS.lock().unwrap_or_else(|e| e.into_inner());
+    let Some(slot) = slots.slots.get_mut(index) else {
+        return;
+    };
+    if slot.generation == generation && slot.kind.is_some() {
+        slot.kind = None;
+        slots.free.push_back(index as u32);
+    }
+}
+
+/// True if `id` is live and refers to one of `kinds`; otherwise records
+/// INVALID_HANDLE saying whether it was closed, reissued or never valid.
+pub fn check(id: u32, kinds: &[Kind]) -> bool {
+    let (index, generation) = split(id);
+    let slots = SLOTS.lock().unwrap_or_else(|e| e.into_inner());
+    let message = match slots.slots.get(index) {
+        Some(slot) if slot.generation == generation => match slot.kind {
+            Some(kind) if kinds.contains(&kind) => return true,
+            Some(kind) => format!("handle {id} is a {kind:?} handle, expected {kinds:?}"),
+            None => format!("handle {id} was closed"),
+        },
+        Some(slot) if generation != 0 && generation < slot.generation => {
+            format!("handle {id} was closed and its slot reused")
+        }
+        _ => format!("unknown handle {id}"),
+    };
+    error::fail(error::INVALID_HANDLE, message);
+    false
+}
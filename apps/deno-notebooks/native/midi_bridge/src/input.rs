@@ -1,25 +1,159 @@
-use crossbeam_channel::{bounded, Receiver};
+use crossbeam_channel::{bounded, Receiver as ChannelReceiver, Sender};
 use midir::{Ignore, MidiInput, MidiInputConnection};
+use serde::{Deserialize, Serialize};
 use std::collections::VecDeque;
-use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
-use std::sync::{Arc, Mutex};
-use std::thread::{self, JoinHandle};
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU32, AtomicU64, AtomicU8, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
 use std::time::{Duration, Instant};
 
+use crate::automation::CcRecording;
+use crate::clock::ClockPulses;
 use crate::packet::{
-    encode_packet, Record, KIND_CC, KIND_CH_PRESS, KIND_NOTE, KIND_PB, KIND_POLY_PRESS, KIND_PROG,
+    encode_packet, Record, KIND_CC, KIND_CH_PRESS, KIND_NOTE, KIND_NOTE_COMPLETE, KIND_PB,
+    KIND_OSC, KIND_POLY_PRESS, KIND_PROG,
 };
-use crate::Callback;
+use crate::pipeline::Pipeline;
+use crate::rpn::{self, MpeNegotiation};
+use crate::stats::{summarize, LatencySnapshot, LatencyStats};
+use crate::sysex::{self, NoteExpression};
+use crate::{Callback, ERR_MIDI_INIT, ERR_PORT_CONNECT};
+use avtools_ffi::worker::{spawn_worker, Worker};
+use avtools_ffi::options::{Invalid, VersionedOptions};
+use avtools_ffi::{error, metrics, Error};
 
 const RAW_QUEUE_CAP: usize = 4096;
 const NOTE_QUEUE_CAP: usize = 4096;
 
+/// Append a per-packet latency summary after the records.
+pub const FLAG_LATENCY_SUMMARY: u32 = 0x01;
+
+/// Set in a CC record's `extra` when the CC was remapped; the low byte then
+/// holds the original controller number.
+pub const CC_REMAPPED: u16 = 0x100;
+
+/// Linux only: stamp `ts_us` from CLOCK_MONOTONIC_RAW in the input callback
+/// instead of using midir's (NTP-slewed) timestamp. Ignored elsewhere.
+pub const FLAG_MONOTONIC_RAW: u32 = 0x20;
+
+/// Pair note-ons with their note-offs and emit one `KIND_NOTE_COMPLETE`
+/// record per note (a = note, b = on velocity, v16 = off velocity,
+/// extra = duration in ms). Note-ons unmatched after the note timeout are
+/// delivered as plain `KIND_NOTE` records.
+pub const FLAG_NOTE_DURATION: u32 = 0x40;
+
+/// Parse MIDI Show Control SysEx addressed to this input's MSC device id
+/// (midi_set_msc_device_id; all-call by default) into `KIND_MSC` records.
+pub const FLAG_PARSE_MSC: u32 = 0x80;
+
+/// Unwrap OSC tunnelled in SysEx (midi_send_osc_tunnelled) from any device
+/// into `KIND_OSC` records. Packets that are not valid OSC are dropped.
+pub const FLAG_DECODE_OSC_TUNNEL: u32 = 0x100;
+
+/// Deliver CC and pitch bend values scaled to the full u16 range in `v16`
+/// (read it as unsigned): a CC's value * 65535 / 127 with the raw 0–127 value
+/// in `extra` (which then no longer reports CC_REMAPPED), pitch bend from 0
+/// (full down) to 65535 (full up) with the raw 14-bit value in `extra`.
+pub const FLAG_NORMALISE_CC: u32 = 0x200;
+
+/// Parse MIDI Machine Control SysEx from any device into `KIND_MMC` records.
+pub const FLAG_PARSE_MMC: u32 = 0x400;
+
+const DEFAULT_NOTE_TIMEOUT_MS: u32 = 10_000;
+/// Queue overflow is logged at most this often per input.
+const DROP_WARNING_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How often the dispatch thread turns bytes_received into bandwidth_bps.
+const BANDWIDTH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Clock pulses per dispatch tick under beat dispatch: 6 = sixteenth notes at 24 PPQN.
+const DEFAULT_CLOCKS_PER_TICK: u32 = 6;
+
+/// How often a beat-driven dispatch loop wakes to check for close while no
+/// clock is arriving.
+const CLOCK_WAIT_POLL: Duration = Duration::from_millis(50);
+
+/// Rust-side observer of every record an input dispatches (see
+/// add_record_tap), called on the dispatch thread before the packet goes to
+/// the callback, with the records in timestamp order.
+pub type RecordTap = Box<dyn FnMut(&[Record]) + Send>;
+
+/// Rust-side observer of every message an input receives, called with its
+/// arrival time on the shared clock before any filtering (see add_raw_tap).
+/// Runs on the backend's callback thread, so it must not block.
+pub type RawTap = Box<dyn FnMut(u64, &[u8]) + Send>;
+
+/// Source of add_record_tap and add_raw_tap tokens, unique across inputs.
+static NEXT_TAP: AtomicU64 = AtomicU64::new(1);
+
+/// Every FLAG_* an input accepts.
+const KNOWN_FLAGS: u32 = FLAG_LATENCY_SUMMARY
+    | FLAG_MONOTONIC_RAW
+    | FLAG_NOTE_DURATION
+    | FLAG_PARSE_MSC
+    | FLAG_DECODE_OSC_TUNNEL
+    | FLAG_NORMALISE_CC
+    | FLAG_PARSE_MMC;
+
+/// Highest dispatch rate: one tick every 100 µs.
+const MAX_RATE_HZ: u32 = 10_000;
+/// Largest queue an input may ask for.
+const MAX_QUEUE_CAP: usize = 1 << 20;
+
+/// Options for `midi_open_input_ex`, parsed from JSON. Missing fields (and
+/// zero queue capacities) fall back to the `midi_open_input` defaults.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InputOptions {
+    pub rate_hz: u32,
+    pub flags: u32,
+    pub raw_queue_cap: usize,
+    pub note_queue_cap: usize,
+}
+
+impl VersionedOptions for InputOptions {
+    const VERSION: u32 = 1;
+    const NAME: &'static str = "input options";
+
+    fn validate(&self) -> Result<(), Invalid> {
+        if self.rate_hz > MAX_RATE_HZ {
+            return Err(Invalid::new("rate_hz", format!("must be at most {MAX_RATE_HZ}")));
+        }
+        if self.flags & !KNOWN_FLAGS != 0 {
+            return Err(Invalid::new(
+                "flags",
+                format!("unknown flag bits {:#x}", self.flags & !KNOWN_FLAGS),
+            ));
+        }
+        if self.raw_queue_cap > MAX_QUEUE_CAP {
+            return Err(Invalid::new("raw_queue_cap", format!("must be at most {MAX_QUEUE_CAP}")));
+        }
+        if self.note_queue_cap > MAX_QUEUE_CAP {
+            return Err(Invalid::new("note_queue_cap", format!("must be at most {MAX_QUEUE_CAP}")));
+        }
+        Ok(())
+    }
+}
+
+impl Default for InputOptions {
+    fn default() -> Self {
+        Self {
+            rate_hz: 0,
+            flags: 0,
+            raw_queue_cap: RAW_QUEUE_CAP,
+            note_queue_cap: NOTE_QUEUE_CAP,
+        }
+    }
+}
+
 pub struct InputHandle {
+    shared: Arc<SharedState>,
     stop: Arc<AtomicBool>,
     callback_enabled: Arc<AtomicBool>,
+    receiver: Arc<Receiver>,
     conn: Option<MidiInputConnection<()>>,
-    coalescer_join: Option<JoinHandle<()>>,
-    dispatch_join: Option<JoinHandle<()>>,
+    coalescer: Option<Worker>,
+    dispatch: Option<Worker>,
 }
 
 impl InputHandle {
@@ -27,13 +161,245 @@ impl InputHandle {
         self.callback_enabled.store(false, Ordering::Relaxed);
         self.stop.store(true, Ordering::Relaxed);
         drop(self.conn.take());
-        let _ = self.coalescer_join.take();
-        let _ = self.dispatch_join.take();
+        let _ = self.coalescer.take();
+        let _ = self.dispatch.take();
+    }
+
+    /// `close`, then wait up to `timeout` for the input's threads to exit.
+    /// False if one is still running, e.g. a dispatch thread blocked in a
+    /// callback the host will never run; it is left to finish on its own.
+    pub fn close_and_join(mut self, timeout: Duration) -> bool {
+        let workers = [self.coalescer.take(), self.dispatch.take()];
+        self.close();
+        let deadline = Instant::now() + timeout;
+        workers
+            .into_iter()
+            .flatten()
+            .all(|w| w.join_within(deadline.saturating_duration_since(Instant::now())))
+    }
+
+    pub fn current_program(&self, channel: u8) -> u8 {
+        self.shared.state.lock().unwrap_or_else(|e| e.into_inner()).program[(channel & 0x0F) as usize]
+    }
+
+    /// Controller `cc` on `channel`, as last coalesced.
+    pub fn cc(&self, channel: u8, cc: u8) -> u8 {
+        let state = self.shared.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.cc[(channel & 0x0F) as usize][(cc & 0x7F) as usize]
+    }
+
+    /// The 14-bit value of controller pair `msb_cc` (0–31) and `msb_cc + 32`
+    /// on `channel`, as last coalesced.
+    pub fn cc14(&self, channel: u8, msb_cc: u8) -> u16 {
+        let state = self.shared.state.lock().unwrap_or_else(|e| e.into_inner());
+        let cc = &state.cc[(channel & 0x0F) as usize];
+        (cc[msb_cc as usize] as u16) << 7 | cc[msb_cc as usize + 32] as u16
+    }
+
+    pub fn add_cc_remap(&self, remap: CcRemap) {
+        let mut remaps = self.shared.cc_remaps.lock().unwrap_or_else(|e| e.into_inner());
+        remaps.retain(|r| !(r.from_channel == remap.from_channel && r.from_cc == remap.from_cc));
+        remaps.push(remap);
+    }
+
+    pub fn remove_cc_remap(&self, from_channel: u8, from_cc: u8) -> bool {
+        let mut remaps = self.shared.cc_remaps.lock().unwrap_or_else(|e| e.into_inner());
+        let before = remaps.len();
+        remaps.retain(|r| !(r.from_channel == from_channel && r.from_cc == from_cc));
+        remaps.len() != before
+    }
+
+    pub fn clear_cc_remaps(&self) {
+        self.shared.cc_remaps.lock().unwrap_or_else(|e| e.into_inner()).clear();
+    }
+
+    /// Ignore `cc` on `channel` until a value within `threshold` of `target`
+    /// arrives, then track it as usual: a knob picks up from the software's
+    /// value instead of jumping to wherever it happens to be.
+    pub fn set_cc_lock(&self, channel: u8, cc: u8, target: u8, threshold: u8) {
+        let (ch, idx) = (channel as usize, cc as usize);
+        let mut state = self.shared.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.cc_locked[ch][idx] = true;
+        state.cc_lock_target[ch][idx] = target;
+        state.cc_lock_threshold[ch][idx] = threshold;
+    }
+
+    /// Track `cc` on `channel` again; false if it was not locked.
+    pub fn release_cc_lock(&self, channel: u8, cc: u8) -> bool {
+        let mut state = self.shared.state.lock().unwrap_or_else(|e| e.into_inner());
+        std::mem::replace(&mut state.cc_locked[channel as usize][cc as usize], false)
+    }
+
+    pub fn set_quantise_grid(&self, grid: Option<QuantiseGrid>) {
+        *self.shared.quantise.lock().unwrap_or_else(|e| e.into_inner()) = grid;
+    }
+
+    /// Snap notes on `channel` (16 = every channel) to `scale`, or stop
+    /// snapping with `None`. Held notes still release the note they snapped to.
+    pub fn set_note_scale(&self, channel: u8, scale: Option<NoteScale>) {
+        let mut scales = self.shared.note_scales.lock().unwrap_or_else(|e| e.into_inner());
+        if channel as usize >= scales.scales.len() {
+            scales.scales = [scale; 16];
+        } else {
+            scales.scales[channel as usize] = scale;
+        }
+    }
+
+    /// Device id FLAG_PARSE_MSC answers to; sysex::ALL_CALL accepts any.
+    pub fn set_msc_device_id(&self, device_id: u8) {
+        self.shared.msc_device_id.store(device_id, Ordering::Relaxed);
+    }
+
+    /// Match `expression` in incoming SysEx, replacing any registration for
+    /// the same channel, note and kind.
+    pub fn register_note_expression(&self, expression: NoteExpression) {
+        let mut expressions = self.shared.note_expressions.lock().unwrap_or_else(|e| e.into_inner());
+        expressions.retain(|e| {
+            (e.channel, e.note, e.kind) != (expression.channel, expression.note, expression.kind)
+        });
+        expressions.push(expression);
+    }
+
+    pub fn clear_note_expressions(&self) {
+        self.shared.note_expressions.lock().unwrap_or_else(|e| e.into_inner()).clear();
     }
+
+    /// Call `tap` with every dispatched batch of records until removed;
+    /// returns the token for remove_record_tap.
+    pub fn add_record_tap(&self, tap: RecordTap) -> u64 {
+        let token = NEXT_TAP.fetch_add(1, Ordering::Relaxed);
+        self.shared.record_taps.lock().unwrap_or_else(|e| e.into_inner()).push((token, tap));
+        token
+    }
+
+    /// False if `token` is not a tap on this input.
+    pub fn remove_record_tap(&self, token: u64) -> bool {
+        let mut taps = self.shared.record_taps.lock().unwrap_or_else(|e| e.into_inner());
+        let before = taps.len();
+        taps.retain(|(t, _)| *t != token);
+        taps.len() != before
+    }
+
+    /// Like add_record_tap, for the messages as received.
+    pub fn add_raw_tap(&self, tap: RawTap) -> u64 {
+        let token = NEXT_TAP.fetch_add(1, Ordering::Relaxed);
+        self.shared.raw_taps.lock().unwrap_or_else(|e| e.into_inner()).push((token, tap));
+        token
+    }
+
+    pub fn remove_raw_tap(&self, token: u64) -> bool {
+        let mut taps = self.shared.raw_taps.lock().unwrap_or_else(|e| e.into_inner());
+        let before = taps.len();
+        taps.retain(|(t, _)| *t != token);
+        taps.len() != before
+    }
+
+    /// Take in `msg` as if it had just arrived from the port.
+    pub fn inject(&self, msg: &[u8]) {
+        self.receiver.receive(None, msg);
+    }
+
+    pub fn set_latency_compensation(&self, offset_us: i64) {
+        self.shared.latency_offset_us.store(offset_us, Ordering::Relaxed);
+    }
+
+    pub fn set_pipeline(&self, pipeline: Option<Arc<Pipeline>>) {
+        *self.shared.pipeline.lock().unwrap_or_else(|e| e.into_inner()) = pipeline;
+    }
+
+    pub fn pipeline(&self) -> Option<Arc<Pipeline>> {
+        self.shared.pipeline.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Route this input's RPN writes to `negotiation` (None stops).
+    pub fn set_mpe_negotiation(&self, negotiation: Option<Arc<MpeNegotiation>>) {
+        *self.shared.mpe_negotiation.lock().unwrap_or_else(|e| e.into_inner()) = negotiation;
+    }
+
+    pub fn set_note_timeout_ms(&self, ms: u32) {
+        self.shared.note_timeout_ms.store(ms, Ordering::Relaxed);
+    }
+
+    pub fn add_cc_recording(&self, recording: Arc<CcRecording>) {
+        self.shared.state.lock().unwrap_or_else(|e| e.into_inner()).cc_recordings.push(recording);
+    }
+
+    pub fn clock(&self) -> Arc<ClockPulses> {
+        self.shared.clock.clone()
+    }
+
+    /// Dispatch on MIDI clock pulses from `source` instead of the rate timer;
+    /// None returns to timer dispatch.
+    pub fn set_beat_source(&self, source: Option<Arc<ClockPulses>>) {
+        *self.shared.beat_source.lock().unwrap_or_else(|e| e.into_inner()) = source;
+    }
+
+    pub fn set_clocks_per_tick(&self, clocks: u32) {
+        self.shared.clocks_per_tick.store(clocks.max(1), Ordering::Relaxed);
+    }
+
+    pub fn epoch_us(&self) -> u64 {
+        self.shared.epoch_us.get().copied().unwrap_or(0)
+    }
+
+    pub fn stats_json(&self) -> Vec<u8> {
+        let snapshot = InputStats {
+            latency: self.shared.latency.lock().unwrap_or_else(|e| e.into_inner()).snapshot(),
+        };
+        serde_json::to_vec(&snapshot).unwrap_or_else(|_| b"{}".to_vec())
+    }
+
+    /// Counters for the runtime metrics, copied without waiting on the input's
+    /// threads.
+    pub fn metrics(&self) -> InputMetrics {
+        InputMetrics {
+            dropped_raw: self.shared.dropped_raw_total.load(Ordering::Relaxed),
+            dropped_note: self.shared.dropped_note_total.load(Ordering::Relaxed),
+            latency: metrics::sample(&self.shared.latency, LatencyStats::clone),
+            bandwidth_bps: self.bandwidth_bps(),
+            failed: self.coalescer.iter().chain(&self.dispatch).any(Worker::failed),
+        }
+    }
+
+    /// Bytes received per second over the last second.
+    pub fn bandwidth_bps(&self) -> u32 {
+        self.shared.bandwidth_bps.load(Ordering::Relaxed)
+    }
+}
+
+/// One input's share of the runtime metrics. The latency window is None if
+/// the dispatch thread was updating it when sampled.
+pub struct InputMetrics {
+    dropped_raw: u64,
+    dropped_note: u64,
+    latency: Option<LatencyStats>,
+    bandwidth_bps: u32,
+    /// One of the input's threads panicked; it no longer delivers events.
+    failed: bool,
+}
+
+impl InputMetrics {
+    /// Sorts the copied latency window, so call it after releasing table locks.
+    pub fn to_json(&self, handle: u32) -> serde_json::Value {
+        serde_json::json!({
+            "handle": handle,
+            "dropped_raw": self.dropped_raw,
+            "dropped_note": self.dropped_note,
+            "dispatch_latency": self.latency.as_ref().map(LatencyStats::snapshot),
+            "bandwidth_bps": self.bandwidth_bps,
+            "failed": self.failed,
+        })
+    }
+}
+
+#[derive(Serialize)]
+struct InputStats {
+    latency: LatencySnapshot,
 }
 
 struct RawMsg {
     ts_us: u64,
+    arrival_us: u64,
     status: u8,
     data1: u8,
     data2: u8,
@@ -42,26 +408,203 @@ struct RawMsg {
 
 struct NoteEdge {
     ts_us: u64,
+    arrival_us: u64,
     channel: u8,
     note: u8,
     velocity: u8,
     on: bool,
 }
 
+#[derive(Clone, Copy)]
+pub struct CcRemap {
+    pub from_channel: u8,
+    pub from_cc: u8,
+    pub to_channel: u8,
+    pub to_cc: u8,
+}
+
 struct SharedState {
     state: Mutex<State>,
+    cc_remaps: Mutex<Vec<CcRemap>>,
     notes: Mutex<VecDeque<NoteEdge>>,
+    /// Records parsed from SysEx in the input callback, delivered uncoalesced
+    /// like note edges and sharing their queue capacity and drop count. OSC
+    /// records carry their packet alongside.
+    sysex_records: Mutex<VecDeque<(Record, Option<Vec<u8>>)>>,
+    msc_device_id: AtomicU8,
+    note_expressions: Mutex<Vec<NoteExpression>>,
     dropped_raw: AtomicU32,
     dropped_note: AtomicU32,
+    /// Lifetime totals of the two counters above, which reset every dispatch.
+    dropped_raw_total: AtomicU64,
+    /// Every byte taken in, for bandwidth_bps.
+    bytes_received: AtomicU64,
+    /// Bytes per second over the last BANDWIDTH_INTERVAL, kept by the
+    /// dispatch thread.
+    bandwidth_bps: AtomicU32,
+    dropped_note_total: AtomicU64,
+    latency: Mutex<LatencyStats>,
+    /// `ts_us` of the first message received; origin for the quantise grid.
+    epoch_us: OnceLock<u64>,
+    /// Shared-clock time minus driver timestamp, fixed at the first message, so
+    /// record timestamps line up with fastsleep and deno_window.
+    ts_offset_us: OnceLock<i64>,
+    quantise: Mutex<Option<QuantiseGrid>>,
+    note_scales: Mutex<NoteScales>,
+    note_timeout_ms: AtomicU32,
+    note_queue_cap: usize,
+    /// 0xF8 pulses received on this input.
+    clock: Arc<ClockPulses>,
+    /// Clock that drives dispatch, if beat dispatch is enabled.
+    beat_source: Mutex<Option<Arc<ClockPulses>>>,
+    clocks_per_tick: AtomicU32,
+    /// Added to every message timestamp to cancel out interface latency.
+    latency_offset_us: AtomicI64,
+    /// Transforms applied to every message before it is coalesced.
+    pipeline: Mutex<Option<Arc<Pipeline>>>,
+    rpn: Mutex<rpn::Decoder>,
+    /// In-progress midi_mpe_negotiate listening on this input.
+    mpe_negotiation: Mutex<Option<Arc<MpeNegotiation>>>,
+    record_taps: Mutex<Vec<(u64, RecordTap)>>,
+    raw_taps: Mutex<Vec<(u64, RawTap)>>,
 }
 
-impl SharedState {
+#[derive(Clone, Copy)]
+pub struct QuantiseGrid {
+    pub interval_us: u64,
+    pub strength_percent: u8,
+}
+
+impl QuantiseGrid {
+    /// `ppqn` grid steps per quarter note at `bpm_x100` / 100 BPM.
+    pub fn new(ppqn: u32, bpm_x100: u32, strength_percent: u8) -> Option<Self> {
+        if ppqn == 0 || bpm_x100 == 0 {
+            return None;
+        }
+        let interval_us = 6_000_000_000u64 / (bpm_x100 as u64 * ppqn as u64);
+        if interval_us == 0 {
+            return None;
+        }
+        Some(Self {
+            interval_us,
+            strength_percent: strength_percent.min(100),
+        })
+    }
+
+    fn apply(&self, ts_us: u64, epoch_us: u64) -> u64 {
+        let offset = ts_us as i128 - epoch_us as i128;
+        let interval = self.interval_us as i128;
+        let nearest = (offset + interval / 2).div_euclid(interval) * interval;
+        let shift = (nearest - offset) * self.strength_percent as i128 / 100;
+        (ts_us as i128 + shift).max(0) as u64
+    }
+}
+
+/// Pitch-class set that incoming notes are snapped onto.
+#[derive(Clone, Copy)]
+pub struct NoteScale {
+    root: u8,
+    /// Bit n set = the pitch class `n` semitones above the root is in the scale.
+    mask: u16,
+}
+
+impl NoteScale {
+    pub fn new(root_note: u8, scale_mask: u16) -> Option<Self> {
+        let mask = scale_mask & 0x0FFF;
+        if mask == 0 {
+            return None;
+        }
+        Some(Self {
+            root: root_note % 12,
+            mask,
+        })
+    }
+
+    fn contains(&self, note: i16) -> bool {
+        let degree = (note - self.root as i16).rem_euclid(12);
+        self.mask & (1 << degree) != 0
+    }
+
+    /// Nearest in-scale note within 0–127; ties snap down.
+    fn snap(&self, note: u8) -> u8 {
+        let note = note as i16;
+        for distance in 0..12 {
+            for candidate in [note - distance, note + distance] {
+                if (0..=127).contains(&candidate) && self.contains(candidate) {
+                    return candidate as u8;
+                }
+            }
+        }
+        note as u8
+    }
+}
+
+/// Per-channel scales plus the note each held key was snapped to, so the
+/// note-off matches its note-on even if the scale changes in between.
+struct NoteScales {
+    scales: [Option<NoteScale>; 16],
+    held: [[u8; 128]; 16],
+}
+
+const NOT_HELD: u8 = 0xFF;
+
+impl NoteScales {
     fn new() -> Self {
+        Self {
+            scales: [None; 16],
+            held: [[NOT_HELD; 128]; 16],
+        }
+    }
+
+    fn map(&mut self, channel: u8, note: u8, on: bool) -> u8 {
+        let held = &mut self.held[channel as usize & 0x0F][note as usize & 0x7F];
+        if on {
+            let snapped = self.scales[channel as usize & 0x0F].map_or(note, |s| s.snap(note));
+            *held = snapped;
+            snapped
+        } else {
+            let snapped = if *held == NOT_HELD { note } else { *held };
+            *held = NOT_HELD;
+            snapped
+        }
+    }
+}
+
+impl SharedState {
+    fn has_note_expressions(&self) -> bool {
+        !self.note_expressions.lock().unwrap_or_else(|e| e.into_inner()).is_empty()
+    }
+
+    fn new(note_queue_cap: usize) -> Self {
         Self {
             state: Mutex::new(State::default()),
-            notes: Mutex::new(VecDeque::with_capacity(NOTE_QUEUE_CAP)),
+            cc_remaps: Mutex::new(Vec::new()),
+            notes: Mutex::new(VecDeque::with_capacity(note_queue_cap)),
+            sysex_records: Mutex::new(VecDeque::new()),
+            msc_device_id: AtomicU8::new(sysex::ALL_CALL),
+            note_expressions: Mutex::new(Vec::new()),
             dropped_raw: AtomicU32::new(0),
             dropped_note: AtomicU32::new(0),
+            dropped_raw_total: AtomicU64::new(0),
+            bytes_received: AtomicU64::new(0),
+            bandwidth_bps: AtomicU32::new(0),
+            dropped_note_total: AtomicU64::new(0),
+            latency: Mutex::new(LatencyStats::new()),
+            epoch_us: OnceLock::new(),
+            ts_offset_us: OnceLock::new(),
+            quantise: Mutex::new(None),
+            note_scales: Mutex::new(NoteScales::new()),
+            note_timeout_ms: AtomicU32::new(DEFAULT_NOTE_TIMEOUT_MS),
+            note_queue_cap,
+            clock: Arc::new(ClockPulses::new()),
+            beat_source: Mutex::new(None),
+            clocks_per_tick: AtomicU32::new(DEFAULT_CLOCKS_PER_TICK),
+            latency_offset_us: AtomicI64::new(0),
+            pipeline: Mutex::new(None),
+            rpn: Mutex::new(rpn::Decoder::new()),
+            mpe_negotiation: Mutex::new(None),
+            record_taps: Mutex::new(Vec::new()),
+            raw_taps: Mutex::new(Vec::new()),
         }
     }
 }
@@ -70,19 +613,31 @@ impl SharedState {
 struct State {
     cc: [[u8; 128]; 16],
     cc_ts: [[u64; 128]; 16],
+    cc_arrival: [[u64; 128]; 16],
+    cc_extra: [[u16; 128]; 16],
     cc_dirty: [[u64; 2]; 16],
+    /// Controllers ignoring input until it comes within `cc_lock_threshold`
+    /// of `cc_lock_target` (see set_cc_lock).
+    cc_locked: [[bool; 128]; 16],
+    cc_lock_target: [[u8; 128]; 16],
+    cc_lock_threshold: [[u8; 128]; 16],
     pb: [i16; 16],
     pb_ts: [u64; 16],
+    pb_arrival: [u64; 16],
     pb_dirty: [bool; 16],
     ch_pressure: [u8; 16],
     ch_pressure_ts: [u64; 16],
+    ch_pressure_arrival: [u64; 16],
     ch_pressure_dirty: [bool; 16],
     program: [u8; 16],
     program_ts: [u64; 16],
+    program_arrival: [u64; 16],
     program_dirty: [bool; 16],
     poly_pressure: [[u8; 128]; 16],
     poly_pressure_ts: [[u64; 128]; 16],
+    poly_pressure_arrival: [[u64; 128]; 16],
     poly_pressure_dirty: [[u64; 2]; 16],
+    cc_recordings: Vec<Arc<CcRecording>>,
 }
 
 impl Default for State {
@@ -90,110 +645,254 @@ impl Default for State {
         Self {
             cc: [[0; 128]; 16],
             cc_ts: [[0; 128]; 16],
+            cc_arrival: [[0; 128]; 16],
+            cc_extra: [[0; 128]; 16],
             cc_dirty: [[0; 2]; 16],
+            cc_locked: [[false; 128]; 16],
+            cc_lock_target: [[0; 128]; 16],
+            cc_lock_threshold: [[0; 128]; 16],
             pb: [0; 16],
             pb_ts: [0; 16],
+            pb_arrival: [0; 16],
             pb_dirty: [false; 16],
             ch_pressure: [0; 16],
             ch_pressure_ts: [0; 16],
+            ch_pressure_arrival: [0; 16],
             ch_pressure_dirty: [false; 16],
             program: [0; 16],
             program_ts: [0; 16],
+            program_arrival: [0; 16],
             program_dirty: [false; 16],
             poly_pressure: [[0; 128]; 16],
             poly_pressure_ts: [[0; 128]; 16],
+            poly_pressure_arrival: [[0; 128]; 16],
             poly_pressure_dirty: [[0; 2]; 16],
+            cc_recordings: Vec::new(),
         }
     }
 }
 
-pub fn open_input(
+pub fn open_input(port_id: &str, options: InputOptions, cb: Callback) -> Result<InputHandle, Error> {
+    open_input_with_first_packet(port_id, options, cb, None)
+}
+
+/// open_input, with `first_packet` handed to `cb` on the first dispatch tick,
+/// ahead of any data.
+pub fn open_input_with_first_packet(
     port_id: &str,
-    rate_hz: u32,
-    _flags: u32,
+    options: InputOptions,
     cb: Callback,
-) -> Result<InputHandle, String> {
+    first_packet: Option<Vec<u8>>,
+) -> Result<InputHandle, Error> {
     let mut midi_in = MidiInput::new("midi-bridge-in")
-        .map_err(|e| format!("midi input init failed: {e:?}"))?;
+        .map_err(|e| Error::new(ERR_MIDI_INIT, format!("midi input init failed: {e:?}")))?;
     midi_in.ignore(Ignore::None);
     let port = midi_in
         .find_port_by_id(port_id.to_string())
-        .ok_or_else(|| "input port not found".to_string())?;
+        .ok_or_else(|| Error::new(error::NOT_FOUND, format!("input port not found: {port_id}")))?;
+    start_input(options, cb, first_packet, |receiver| {
+        midi_in
+            .connect(
+                &port,
+                "midi-bridge-in",
+                move |ts, msg, _| receiver.receive(Some(ts), msg),
+                (),
+            )
+            .map(Some)
+            .map_err(|e| Error::new(ERR_PORT_CONNECT, format!("input connect failed: {e:?}")))
+    })
+}
 
-    let shared = Arc::new(SharedState::new());
-    let stop = Arc::new(AtomicBool::new(false));
-    let (raw_tx, raw_rx) = bounded::<RawMsg>(RAW_QUEUE_CAP);
-    let callback_enabled = Arc::new(AtomicBool::new(true));
+/// Whether the input port `port_id` still exists, for noticing that a device
+/// was unplugged. None if no MIDI client could be created to look.
+pub fn input_port_present(port_id: &str) -> Option<bool> {
+    let midi_in = MidiInput::new("midi-bridge-watch").ok()?;
+    Some(midi_in.find_port_by_id(port_id.to_string()).is_some())
+}
 
-    let cb_stop = stop.clone();
-    let cb_shared = shared.clone();
+/// An input with no port: it receives only what is injected (see
+/// InputHandle::inject), and otherwise behaves like one opened on a port.
+pub fn open_mock_input(options: InputOptions, cb: Callback) -> Result<InputHandle, Error> {
+    start_input(options, cb, None, |_| Ok(None))
+}
 
-    let conn = midi_in
-        .connect(
-            &port,
-            "midi-bridge-in",
-            move |ts, msg, _| {
-                if cb_stop.load(Ordering::Relaxed) {
-                    return;
-                }
-                if msg.is_empty() {
-                    return;
+/// The input callback's state: everything needed to take in one message.
+struct Receiver {
+    shared: Arc<SharedState>,
+    stop: Arc<AtomicBool>,
+    raw_tx: Sender<RawMsg>,
+    use_raw_clock: bool,
+    parse_msc: bool,
+    parse_mmc: bool,
+    decode_osc: bool,
+}
+
+impl Receiver {
+    /// Take in one message. `driver_ts` is the backend's µs timestamp, None
+    /// for injected messages, which are stamped on arrival.
+    fn receive(&self, driver_ts: Option<u64>, msg: &[u8]) {
+        let shared = &self.shared;
+        if self.stop.load(Ordering::Relaxed) {
+            return;
+        }
+        if msg.is_empty() {
+            return;
+        }
+        shared.bytes_received.fetch_add(msg.len() as u64, Ordering::Relaxed);
+        let arrival_us = avtools_clock::now_us();
+        {
+            let mut taps = shared.raw_taps.lock().unwrap_or_else(|e| e.into_inner());
+            for (_, tap) in taps.iter_mut() {
+                tap(arrival_us, msg);
+            }
+        }
+        let status = msg[0];
+        if status == 0xF8 {
+            shared.clock.pulse();
+            return;
+        }
+        if !(0x80..=0xF0).contains(&status) {
+            return;
+        }
+        if status == 0xF0 && !self.parse_msc && !self.parse_mmc && !self.decode_osc && !shared.has_note_expressions() {
+            return;
+        }
+        let len = msg.len();
+        let data1 = if len > 1 { msg[1] } else { 0 };
+        let data2 = if len > 2 { msg[2] } else { 0 };
+        let ts = match driver_ts {
+            None => arrival_us,
+            Some(ts) if self.use_raw_clock => avtools_clock::monotonic_raw_us().unwrap_or(ts),
+            Some(ts) => {
+                let offset = *shared.ts_offset_us.get_or_init(|| arrival_us as i64 - ts as i64);
+                ts.saturating_add_signed(offset)
+            }
+        };
+        shared.epoch_us.get_or_init(|| ts);
+        if status == 0xF0 {
+            let device_id = shared.msc_device_id.load(Ordering::Relaxed);
+            let offset = shared.latency_offset_us.load(Ordering::Relaxed);
+            let ts = ts.saturating_add_signed(offset);
+            if self.parse_msc {
+                if let Some(record) = sysex::parse_msc(msg, device_id, ts, arrival_us) {
+                    push_sysex_record(shared, record, None);
                 }
-                let status = msg[0];
-                if status < 0x80 || status >= 0xF0 {
-                    return;
+            }
+            if self.parse_mmc {
+                if let Some(record) = sysex::parse_mmc(msg, ts, arrival_us) {
+                    push_sysex_record(shared, record, None);
                 }
-                let len = msg.len();
-                let data1 = if len > 1 { msg[1] } else { 0 };
-                let data2 = if len > 2 { msg[2] } else { 0 };
-                let raw = RawMsg {
-                    ts_us: ts,
-                    status,
-                    data1,
-                    data2,
-                    len: len.min(255) as u8,
-                };
-                if raw_tx.try_send(raw).is_err() {
-                    cb_shared.dropped_raw.fetch_add(1, Ordering::Relaxed);
+            }
+            if self.decode_osc {
+                if let Some((record, packet)) = sysex::parse_osc_tunnel(msg, ts, arrival_us) {
+                    push_sysex_record(shared, record, Some(packet));
                 }
-            },
-            (),
-        )
-        .map_err(|e| format!("input connect failed: {e:?}"))?;
+            }
+            let matched: Vec<Record> = shared
+                .note_expressions
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .iter()
+                .filter_map(|e| e.parse(msg, ts, arrival_us))
+                .collect();
+            for record in matched {
+                push_sysex_record(shared, record, None);
+            }
+            return;
+        }
+        let raw = RawMsg {
+            ts_us: ts,
+            arrival_us,
+            status,
+            data1,
+            data2,
+            len: len.min(255) as u8,
+        };
+        if self.raw_tx.try_send(raw).is_err() {
+            shared.dropped_raw.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Start an input's threads. `connect` attaches the receiver to a port, if
+/// the input has one.
+fn start_input(
+    options: InputOptions,
+    cb: Callback,
+    first_packet: Option<Vec<u8>>,
+    connect: impl FnOnce(Arc<Receiver>) -> Result<Option<MidiInputConnection<()>>, Error>,
+) -> Result<InputHandle, Error> {
+    let InputOptions {
+        rate_hz,
+        flags,
+        raw_queue_cap,
+        note_queue_cap,
+    } = options;
+    let raw_queue_cap = if raw_queue_cap == 0 { RAW_QUEUE_CAP } else { raw_queue_cap };
+    let note_queue_cap = if note_queue_cap == 0 { NOTE_QUEUE_CAP } else { note_queue_cap };
+
+    let shared = Arc::new(SharedState::new(note_queue_cap));
+    let stop = Arc::new(AtomicBool::new(false));
+    let (raw_tx, raw_rx) = bounded::<RawMsg>(raw_queue_cap);
+    let callback_enabled = Arc::new(AtomicBool::new(true));
+
+    let receiver = Arc::new(Receiver {
+        shared: shared.clone(),
+        stop: stop.clone(),
+        raw_tx,
+        use_raw_clock: flags & FLAG_MONOTONIC_RAW != 0,
+        parse_msc: flags & FLAG_PARSE_MSC != 0,
+        parse_mmc: flags & FLAG_PARSE_MMC != 0,
+        decode_osc: flags & FLAG_DECODE_OSC_TUNNEL != 0,
+    });
+    let conn = connect(receiver.clone())?;
 
     let coalescer_shared = shared.clone();
     let coalescer_stop = stop.clone();
-    let coalescer_join = thread::spawn(move || coalescer_loop(raw_rx, coalescer_shared, coalescer_stop));
+    let coalescer = spawn_worker("midi-coalescer", 0, move || {
+        coalescer_loop(raw_rx, coalescer_shared, coalescer_stop)
+    })
+    .map_err(|e| Error::new(error::OS_ERROR, format!("cannot start input thread: {e}")))?;
 
     let dispatch_shared = shared.clone();
     let dispatch_stop = stop.clone();
     let dispatch_cb_enabled = callback_enabled.clone();
     let rate = if rate_hz == 0 { 250 } else { rate_hz };
-    let start = Instant::now();
-    let dispatch_join = thread::spawn(move || {
+    let dispatch = spawn_worker("midi-dispatch", 0, move || {
         dispatch_loop(
             dispatch_shared,
             dispatch_stop,
             dispatch_cb_enabled,
             cb,
-            start,
             rate,
+            flags,
+            first_packet,
         )
     });
+    let dispatch = match dispatch {
+        Ok(dispatch) => dispatch,
+        Err(e) => {
+            stop.store(true, Ordering::Relaxed);
+            return Err(Error::new(error::OS_ERROR, format!("cannot start input thread: {e}")));
+        }
+    };
 
     Ok(InputHandle {
+        shared,
         stop,
         callback_enabled,
-        conn: Some(conn),
-        coalescer_join: Some(coalescer_join),
-        dispatch_join: Some(dispatch_join),
+        receiver,
+        conn,
+        coalescer: Some(coalescer),
+        dispatch: Some(dispatch),
     })
 }
 
-fn coalescer_loop(raw_rx: Receiver<RawMsg>, shared: Arc<SharedState>, stop: Arc<AtomicBool>) {
+fn coalescer_loop(raw_rx: ChannelReceiver<RawMsg>, shared: Arc<SharedState>, stop: Arc<AtomicBool>) {
     while !stop.load(Ordering::Relaxed) {
         match raw_rx.recv_timeout(Duration::from_millis(5)) {
             Ok(raw) => {
+                let _span = avtools_trace::span!(TRACE, "coalesce_batch", queued = raw_rx.len());
                 handle_raw(raw, &shared);
                 for raw in raw_rx.try_iter() {
                     handle_raw(raw, &shared);
@@ -205,116 +904,198 @@ fn coalescer_loop(raw_rx: Receiver<RawMsg>, shared: Arc<SharedState>, stop: Arc<
     }
 }
 
-fn handle_raw(raw: RawMsg, shared: &SharedState) {
+fn handle_raw(mut raw: RawMsg, shared: &SharedState) {
+    let offset = shared.latency_offset_us.load(Ordering::Relaxed);
+    raw.ts_us = raw.ts_us.saturating_add_signed(offset);
+    let pipeline = shared.pipeline.lock().unwrap_or_else(|e| e.into_inner()).clone();
+    if let Some(pipeline) = pipeline {
+        let Some([status, data1, data2]) =
+            pipeline.process([raw.status, raw.data1, raw.data2], raw.len)
+        else {
+            return;
+        };
+        raw.status = status;
+        raw.data1 = data1;
+        raw.data2 = data2;
+    }
     let status = raw.status & 0xF0;
     let channel = raw.status & 0x0F;
     match status {
         0x80 => {
             if raw.len >= 3 {
-                push_note(shared, raw.ts_us, channel, raw.data1, raw.data2, false);
+                push_note(shared, &raw, channel, false);
             }
         }
         0x90 => {
             if raw.len >= 3 {
                 let on = raw.data2 != 0;
-                push_note(shared, raw.ts_us, channel, raw.data1, raw.data2, on);
+                push_note(shared, &raw, channel, on);
             }
         }
         0xA0 => {
             if raw.len >= 3 {
-                let mut state = shared.state.lock().unwrap();
-                update_poly_pressure(&mut state, channel, raw.data1, raw.data2, raw.ts_us);
+                let mut state = shared.state.lock().unwrap_or_else(|e| e.into_inner());
+                update_poly_pressure(&mut state, channel, raw.data1, raw.data2, &raw);
             }
         }
         0xB0 => {
             if raw.len >= 3 {
-                let mut state = shared.state.lock().unwrap();
-                update_cc(&mut state, channel, raw.data1, raw.data2, raw.ts_us);
+                observe_rpn(shared, channel, raw.data1, raw.data2);
+                let (channel, ctrl, extra) = remap_cc(shared, channel, raw.data1);
+                let mut state = shared.state.lock().unwrap_or_else(|e| e.into_inner());
+                update_cc(&mut state, channel, ctrl, raw.data2, extra, &raw);
             }
         }
         0xC0 => {
             if raw.len >= 2 {
-                let mut state = shared.state.lock().unwrap();
-                update_program(&mut state, channel, raw.data1, raw.ts_us);
+                let mut state = shared.state.lock().unwrap_or_else(|e| e.into_inner());
+                update_program(&mut state, channel, raw.data1, &raw);
             }
         }
         0xD0 => {
             if raw.len >= 2 {
-                let mut state = shared.state.lock().unwrap();
-                update_ch_pressure(&mut state, channel, raw.data1, raw.ts_us);
+                let mut state = shared.state.lock().unwrap_or_else(|e| e.into_inner());
+                update_ch_pressure(&mut state, channel, raw.data1, &raw);
             }
         }
         0xE0 => {
             if raw.len >= 3 {
-                let mut state = shared.state.lock().unwrap();
-                update_pitch_bend(&mut state, channel, raw.data1, raw.data2, raw.ts_us);
+                let mut state = shared.state.lock().unwrap_or_else(|e| e.into_inner());
+                update_pitch_bend(&mut state, channel, raw.data1, raw.data2, &raw);
             }
         }
         _ => {}
     }
 }
 
-fn push_note(shared: &SharedState, ts_us: u64, channel: u8, note: u8, velocity: u8, on: bool) {
+fn push_note(shared: &SharedState, raw: &RawMsg, channel: u8, on: bool) {
+    let grid = *shared.quantise.lock().unwrap_or_else(|e| e.into_inner());
+    let ts_us = match (grid, shared.epoch_us.get()) {
+        (Some(grid), Some(&epoch)) => grid.apply(raw.ts_us, epoch),
+        _ => raw.ts_us,
+    };
+    let note = shared.note_scales.lock().unwrap_or_else(|e| e.into_inner()).map(channel, raw.data1, on);
     let edge = NoteEdge {
         ts_us,
+        arrival_us: raw.arrival_us,
         channel,
         note,
-        velocity,
+        velocity: raw.data2,
         on,
     };
-    let mut notes = shared.notes.lock().unwrap();
-    if notes.len() >= NOTE_QUEUE_CAP {
+    let mut notes = shared.notes.lock().unwrap_or_else(|e| e.into_inner());
+    if notes.len() >= shared.note_queue_cap {
         notes.pop_front();
         shared.dropped_note.fetch_add(1, Ordering::Relaxed);
     }
     notes.push_back(edge);
 }
 
-fn update_cc(state: &mut State, channel: u8, ctrl: u8, val: u8, ts_us: u64) {
+fn push_sysex_record(shared: &SharedState, record: Record, payload: Option<Vec<u8>>) {
+    let mut records = shared.sysex_records.lock().unwrap_or_else(|e| e.into_inner());
+    if records.len() >= shared.note_queue_cap {
+        records.pop_front();
+        shared.dropped_note.fetch_add(1, Ordering::Relaxed);
+    }
+    records.push_back((record, payload));
+}
+
+fn observe_rpn(shared: &SharedState, channel: u8, ctrl: u8, val: u8) {
+    let written = shared.rpn.lock().unwrap_or_else(|e| e.into_inner()).control_change(channel, ctrl, val);
+    let Some((param, value)) = written else {
+        return;
+    };
+    if let Some(negotiation) = &*shared.mpe_negotiation.lock().unwrap_or_else(|e| e.into_inner()) {
+        negotiation.observe(channel, param, value);
+    }
+}
+
+fn remap_cc(shared: &SharedState, channel: u8, ctrl: u8) -> (u8, u8, u16) {
+    let remaps = shared.cc_remaps.lock().unwrap_or_else(|e| e.into_inner());
+    match remaps
+        .iter()
+        .find(|r| r.from_channel == channel && r.from_cc == ctrl)
+    {
+        Some(r) => (r.to_channel, r.to_cc, CC_REMAPPED | ctrl as u16),
+        None => (channel, ctrl, 0),
+    }
+}
+
+fn update_cc(state: &mut State, channel: u8, ctrl: u8, val: u8, extra: u16, raw: &RawMsg) {
     let ch = channel as usize;
     let idx = ctrl as usize;
-    if state.cc[ch][idx] != val {
+    if state.cc_locked[ch][idx] {
+        if val.abs_diff(state.cc_lock_target[ch][idx]) > state.cc_lock_threshold[ch][idx] {
+            return;
+        }
+        state.cc_locked[ch][idx] = false;
+    }
+    if !state.cc_recordings.is_empty() {
+        state.cc_recordings.retain(|r| r.is_active());
+        for recording in &state.cc_recordings {
+            if recording.channel == channel && recording.cc == ctrl {
+                recording.push(raw.ts_us, val);
+            }
+        }
+    }
+    if state.cc[ch][idx] != val || state.cc_extra[ch][idx] != extra {
         state.cc[ch][idx] = val;
-        state.cc_ts[ch][idx] = ts_us;
+        state.cc_extra[ch][idx] = extra;
+        state.cc_ts[ch][idx] = raw.ts_us;
+        state.cc_arrival[ch][idx] = raw.arrival_us;
         set_bit(&mut state.cc_dirty[ch], ctrl);
     }
 }
 
-fn update_pitch_bend(state: &mut State, channel: u8, lsb: u8, msb: u8, ts_us: u64) {
+/// A 0–127 controller value scaled to 0–65535 (rounded), as the bits of `v16`.
+fn normalise_cc(value: u8) -> i16 {
+    ((value as u32 * 65535 + 63) / 127) as u16 as i16
+}
+
+/// A 0–16383 pitch bend scaled to 0–65535 (rounded), as the bits of `v16`.
+fn normalise_pitch_bend(raw: u16) -> i16 {
+    ((raw as u32 * 65535 + 8191) / 16383) as u16 as i16
+}
+
+fn update_pitch_bend(state: &mut State, channel: u8, lsb: u8, msb: u8, raw: &RawMsg) {
     let ch = channel as usize;
-    let raw = ((msb as i16) << 7) | (lsb as i16);
-    let bend = raw - 8192;
+    let value = ((msb as i16) << 7) | (lsb as i16);
+    let bend = value - 8192;
     if state.pb[ch] != bend {
         state.pb[ch] = bend;
-        state.pb_ts[ch] = ts_us;
+        state.pb_ts[ch] = raw.ts_us;
+        state.pb_arrival[ch] = raw.arrival_us;
         state.pb_dirty[ch] = true;
     }
 }
 
-fn update_ch_pressure(state: &mut State, channel: u8, pressure: u8, ts_us: u64) {
+fn update_ch_pressure(state: &mut State, channel: u8, pressure: u8, raw: &RawMsg) {
     let ch = channel as usize;
     if state.ch_pressure[ch] != pressure {
         state.ch_pressure[ch] = pressure;
-        state.ch_pressure_ts[ch] = ts_us;
+        state.ch_pressure_ts[ch] = raw.ts_us;
+        state.ch_pressure_arrival[ch] = raw.arrival_us;
         state.ch_pressure_dirty[ch] = true;
     }
 }
 
-fn update_program(state: &mut State, channel: u8, program: u8, ts_us: u64) {
+fn update_program(state: &mut State, channel: u8, program: u8, raw: &RawMsg) {
     let ch = channel as usize;
     if state.program[ch] != program {
         state.program[ch] = program;
-        state.program_ts[ch] = ts_us;
+        state.program_ts[ch] = raw.ts_us;
+        state.program_arrival[ch] = raw.arrival_us;
         state.program_dirty[ch] = true;
     }
 }
 
-fn update_poly_pressure(state: &mut State, channel: u8, note: u8, pressure: u8, ts_us: u64) {
+fn update_poly_pressure(state: &mut State, channel: u8, note: u8, pressure: u8, raw: &RawMsg) {
     let ch = channel as usize;
     let idx = note as usize;
     if state.poly_pressure[ch][idx] != pressure {
         state.poly_pressure[ch][idx] = pressure;
-        state.poly_pressure_ts[ch][idx] = ts_us;
+        state.poly_pressure_ts[ch][idx] = raw.ts_us;
+        state.poly_pressure_arrival[ch][idx] = raw.arrival_us;
         set_bit(&mut state.poly_pressure_dirty[ch], note);
     }
 }
@@ -339,77 +1120,227 @@ fn collect_bitset(bits: [u64; 2]) -> Vec<u8> {
     indices
 }
 
+fn note_record(edge: &NoteEdge) -> Record {
+    Record {
+        ts_us: edge.ts_us,
+        kind: KIND_NOTE,
+        channel: edge.channel,
+        a: edge.note,
+        b: edge.velocity,
+        v16: 0,
+        extra: if edge.on { 1 } else { 0 },
+        arrival_us: edge.arrival_us,
+    }
+}
+
+fn complete_record(on: &NoteEdge, off_ts_us: u64, off_velocity: u8, arrival_us: u64) -> Record {
+    let duration_ms = off_ts_us.saturating_sub(on.ts_us) / 1_000;
+    Record {
+        ts_us: on.ts_us,
+        kind: KIND_NOTE_COMPLETE,
+        channel: on.channel,
+        a: on.note,
+        b: on.velocity,
+        v16: off_velocity as i16,
+        extra: duration_ms.min(u16::MAX as u64) as u16,
+        arrival_us,
+    }
+}
+
+/// Pairing buffer for FLAG_NOTE_DURATION, owned by the dispatch thread.
+struct NotePairer {
+    pending: Vec<NoteEdge>,
+}
+
+impl NotePairer {
+    fn new() -> Self {
+        Self {
+            pending: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, edge: NoteEdge, records: &mut Vec<Record>) {
+        let held = self
+            .pending
+            .iter()
+            .position(|p| p.channel == edge.channel && p.note == edge.note);
+        if edge.on {
+            // A retrigger ends the held note where the new one starts.
+            if let Some(idx) = held {
+                let prev = self.pending.swap_remove(idx);
+                records.push(complete_record(&prev, edge.ts_us, 0, edge.arrival_us));
+            }
+            self.pending.push(edge);
+        } else {
+            match held {
+                Some(idx) => {
+                    let on = self.pending.swap_remove(idx);
+                    records.push(complete_record(&on, edge.ts_us, edge.velocity, edge.arrival_us));
+                }
+                None => records.push(note_record(&edge)),
+            }
+        }
+    }
+
+    fn expire(&mut self, now_us: u64, timeout_us: u64, records: &mut Vec<Record>) {
+        self.pending.retain(|p| {
+            if now_us.saturating_sub(p.arrival_us) >= timeout_us {
+                records.push(note_record(p));
+                false
+            } else {
+                true
+            }
+        });
+    }
+}
+
 fn dispatch_loop(
     shared: Arc<SharedState>,
     stop: Arc<AtomicBool>,
     callback_enabled: Arc<AtomicBool>,
     cb: Callback,
-    start: Instant,
     rate_hz: u32,
+    flags: u32,
+    mut first_packet: Option<Vec<u8>>,
 ) {
     let rate = rate_hz.max(1);
     let period = Duration::from_secs_f64(1.0 / rate as f64);
     let mut next_tick = Instant::now() + period;
+    let pair_notes = flags & FLAG_NOTE_DURATION != 0;
+    let normalise = flags & FLAG_NORMALISE_CC != 0;
+    let mut pairer = NotePairer::new();
+    // Clock being followed under beat dispatch, and the pulse count of the next tick.
+    let mut beat: Option<(Arc<ClockPulses>, u64)> = None;
+    let mut last_drop_warning: Option<Instant> = None;
+    // When bandwidth_bps was last computed, and bytes_received then.
+    let mut bandwidth_sample = (Instant::now(), 0);
     while !stop.load(Ordering::Relaxed) {
-        let now = Instant::now();
-        if now < next_tick {
-            thread::sleep(next_tick - now);
+        let elapsed = bandwidth_sample.0.elapsed();
+        if elapsed >= BANDWIDTH_INTERVAL {
+            let received = shared.bytes_received.load(Ordering::Relaxed);
+            let rate = (received - bandwidth_sample.1) as f64 / elapsed.as_secs_f64();
+            shared.bandwidth_bps.store(rate.round() as u32, Ordering::Relaxed);
+            bandwidth_sample = (Instant::now(), received);
         }
-        let now = Instant::now();
-        if now > next_tick + period {
-            next_tick = now + period;
+        let source = shared.beat_source.lock().unwrap_or_else(|e| e.into_inner()).clone();
+        if let Some(source) = source {
+            let clocks = shared.clocks_per_tick.load(Ordering::Relaxed).max(1) as u64;
+            let target = match &beat {
+                Some((clock, target)) if Arc::ptr_eq(clock, &source) => *target,
+                _ => source.count() + clocks,
+            };
+            beat = Some((source.clone(), target));
+            let Some(count) = source.wait_for(target, CLOCK_WAIT_POLL) else {
+                continue;
+            };
+            // Stay on the pulse grid, but never queue up a burst of catch-up ticks.
+            let mut next = target + clocks;
+            while next <= count {
+                next += clocks;
+            }
+            beat = Some((source, next));
+            next_tick = Instant::now() + period;
         } else {
-            next_tick += period;
+            beat = None;
+            let now = Instant::now();
+            if now < next_tick {
+                thread::sleep(next_tick - now);
+            }
+            let now = Instant::now();
+            if now > next_tick + period {
+                next_tick = now + period;
+            } else {
+                next_tick += period;
+            }
         }
 
-        let dispatch_ts_us = start.elapsed().as_micros() as u64;
+        let _span = avtools_trace::span!(TRACE, "dispatch_tick");
+        if let Some(packet) = first_packet.take() {
+            if callback_enabled.load(Ordering::Relaxed) {
+                cb(packet.as_ptr(), packet.len() as u32);
+            }
+        }
+        let dispatch_ts_us = avtools_clock::now_us();
         let dropped_raw = shared.dropped_raw.swap(0, Ordering::Relaxed);
         let dropped_note = shared.dropped_note.swap(0, Ordering::Relaxed);
+        shared.dropped_raw_total.fetch_add(dropped_raw as u64, Ordering::Relaxed);
+        shared.dropped_note_total.fetch_add(dropped_note as u64, Ordering::Relaxed);
+        if (dropped_raw > 0 || dropped_note > 0)
+            && last_drop_warning.is_none_or(|t| t.elapsed() >= DROP_WARNING_INTERVAL)
+        {
+            avtools_log::warn!("queues full: dropped {dropped_raw} raw messages, {dropped_note} note edges");
+            last_drop_warning = Some(Instant::now());
+        }
 
         let mut records: Vec<Record> = Vec::new();
 
         {
-            let mut notes = shared.notes.lock().unwrap();
+            let mut notes = shared.notes.lock().unwrap_or_else(|e| e.into_inner());
             while let Some(edge) = notes.pop_front() {
-                records.push(Record {
-                    ts_us: edge.ts_us,
-                    kind: KIND_NOTE,
-                    channel: edge.channel,
-                    a: edge.note,
-                    b: edge.velocity,
-                    v16: 0,
-                    extra: if edge.on { 1 } else { 0 },
-                });
+                if pair_notes {
+                    pairer.push(edge, &mut records);
+                } else {
+                    records.push(note_record(&edge));
+                }
+            }
+        }
+        if pair_notes {
+            let timeout_us = shared.note_timeout_ms.load(Ordering::Relaxed) as u64 * 1_000;
+            pairer.expire(dispatch_ts_us, timeout_us, &mut records);
+        }
+        // OSC records point at their packet by index until the records are
+        // sorted; the packets then go out in record order.
+        let mut osc_packets = Vec::new();
+        for (mut record, payload) in shared.sysex_records.lock().unwrap_or_else(|e| e.into_inner()).drain(..) {
+            if let Some(payload) = payload {
+                record.v16 = osc_packets.len() as i16;
+                osc_packets.push(payload);
             }
+            records.push(record);
         }
 
         {
-            let mut state = shared.state.lock().unwrap();
+            let mut state = shared.state.lock().unwrap_or_else(|e| e.into_inner());
             for ch in 0..16 {
                 let cc_indices = collect_bitset(state.cc_dirty[ch]);
                 state.cc_dirty[ch] = [0; 2];
                 for cc in cc_indices {
                     let idx = cc as usize;
+                    let value = state.cc[ch][idx];
+                    let (v16, extra) = if normalise {
+                        (normalise_cc(value), value as u16)
+                    } else {
+                        (0, state.cc_extra[ch][idx])
+                    };
                     records.push(Record {
                         ts_us: state.cc_ts[ch][idx],
                         kind: KIND_CC,
                         channel: ch as u8,
                         a: cc,
-                        b: state.cc[ch][idx],
-                        v16: 0,
-                        extra: 0,
+                        b: value,
+                        v16,
+                        extra,
+                        arrival_us: state.cc_arrival[ch][idx],
                     });
                 }
 
                 if state.pb_dirty[ch] {
+                    let bend = state.pb[ch];
+                    let (v16, extra) = if normalise {
+                        let raw = (bend + 8192) as u16;
+                        (normalise_pitch_bend(raw), raw)
+                    } else {
+                        (bend, 0)
+                    };
                     records.push(Record {
                         ts_us: state.pb_ts[ch],
                         kind: KIND_PB,
                         channel: ch as u8,
                         a: 0,
                         b: 0,
-                        v16: state.pb[ch],
-                        extra: 0,
+                        v16,
+                        extra,
+                        arrival_us: state.pb_arrival[ch],
                     });
                     state.pb_dirty[ch] = false;
                 }
@@ -423,6 +1354,7 @@ fn dispatch_loop(
                         b: state.ch_pressure[ch],
                         v16: 0,
                         extra: 0,
+                        arrival_us: state.ch_pressure_arrival[ch],
                     });
                     state.ch_pressure_dirty[ch] = false;
                 }
@@ -436,6 +1368,7 @@ fn dispatch_loop(
                         b: state.program[ch],
                         v16: 0,
                         extra: 0,
+                        arrival_us: state.program_arrival[ch],
                     });
                     state.program_dirty[ch] = false;
                 }
@@ -452,6 +1385,7 @@ fn dispatch_loop(
                         b: state.poly_pressure[ch][idx],
                         v16: 0,
                         extra: 0,
+                        arrival_us: state.poly_pressure_arrival[ch][idx],
                     });
                 }
             }
@@ -465,8 +1399,40 @@ fn dispatch_loop(
             continue;
         }
 
+        let latencies: Vec<u64> = records
+            .iter()
+            .map(|r| dispatch_ts_us.saturating_sub(r.arrival_us))
+            .collect();
+        {
+            let mut stats = shared.latency.lock().unwrap_or_else(|e| e.into_inner());
+            for &l in &latencies {
+                stats.push(l);
+            }
+        }
+        let latency = if flags & FLAG_LATENCY_SUMMARY != 0 {
+            Some(summarize(&latencies))
+        } else {
+            None
+        };
+
         records.sort_by_key(|r| r.ts_us);
-        let packet = encode_packet(&records, dispatch_ts_us, dropped_raw, dropped_note, 0);
+        let mut osc: Vec<&[u8]> = Vec::with_capacity(osc_packets.len());
+        for record in records.iter_mut().filter(|r| r.kind == KIND_OSC) {
+            osc.push(&osc_packets[record.v16 as usize]);
+            record.v16 = 0;
+        }
+        for (_, tap) in shared.record_taps.lock().unwrap_or_else(|e| e.into_inner()).iter_mut() {
+            tap(&records);
+        }
+        let packet = encode_packet(
+            &records,
+            dispatch_ts_us,
+            dropped_raw,
+            dropped_note,
+            0,
+            latency,
+            &osc,
+        );
         if callback_enabled.load(Ordering::Relaxed) {
             cb(packet.as_ptr(), packet.len() as u32);
         }
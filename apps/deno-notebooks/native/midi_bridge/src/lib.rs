@@ -1,37 +1,175 @@
+mod automation;
+mod cc_formats;
+mod clock;
+mod handles;
 mod input;
 mod output;
 mod packet;
+mod pipeline;
+mod osc;
 mod ports;
+mod programs;
+mod rpn;
+mod stats;
+mod sysex;
 
+use automation::{CcPlayback, CcRecording};
+use clock::{CcSync, ClockGenerator};
+use avtools_ffi::shutdown::{self, Stage};
+use avtools_ffi::worker::spawn_worker;
+use avtools_ffi::{buffer, catch_panic, error, metrics, options, Error};
 use once_cell::sync::Lazy;
 use std::collections::HashMap;
-use std::sync::atomic::{AtomicU32, Ordering};
-use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
 
-use input::InputHandle;
-use output::OutputHandle;
+use handles::Kind;
+use input::{CcRemap, InputHandle, InputOptions, NoteScale, QuantiseGrid};
+use output::{OutputHandle, OutputPool};
+use packet::KIND_CONNECTED;
+use pipeline::{Pipeline, Transform, ALL_CHANNELS};
+use rpn::MpeNegotiation;
 
 pub type Callback = extern "C" fn(*const u8, u32);
 
-static NEXT_HANDLE: AtomicU32 = AtomicU32::new(1);
+pub use input::{RawTap, RecordTap};
+pub use packet::{Record, KIND_CC, KIND_NOTE};
+
 static INPUTS: Lazy<Mutex<HashMap<u32, InputHandle>>> = Lazy::new(|| Mutex::new(HashMap::new()));
 static OUTPUTS: Lazy<Mutex<HashMap<u32, OutputHandle>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+static RECORDINGS: Lazy<Mutex<HashMap<u32, Arc<CcRecording>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+static PLAYBACKS: Lazy<Mutex<HashMap<u32, CcPlayback>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+static CLOCKS: Lazy<Mutex<HashMap<u32, ClockGenerator>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+static POOLS: Lazy<Mutex<HashMap<u32, OutputPool>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+static PIPELINES: Lazy<Mutex<HashMap<u32, Arc<Pipeline>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+/// Inputs opened by midi_open_input_with_retry, keyed by the handle returned
+/// to the caller, with the flag that stops their reconnect thread. An entry
+/// stays while the handle is open; the input is also in INPUTS while it is
+/// connected. Lock before INPUTS when taking both.
+static RETRY_INPUTS: Lazy<Mutex<HashMap<u32, Arc<AtomicBool>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Longest wait between reconnect attempts.
+const MAX_BACKOFF_MS: u64 = 30_000;
+/// Granularity at which a backoff wait notices midi_close_input.
+const RETRY_CANCEL_POLL: Duration = Duration::from_millis(50);
+/// How often a connected retrying input checks that its port still exists.
+const PORT_WATCH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How long avtools_shutdown_all waits for an input's threads to exit.
+const SHUTDOWN_JOIN_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// midi_bridge's last-error codes (see avtools_ffi::error for the shared ones).
+pub(crate) const ERR_MIDI_INIT: i32 = 200;
+pub(crate) const ERR_PORT_CONNECT: i32 = 201;
+pub(crate) const ERR_SEND: i32 = 202;
+pub(crate) const ERR_TIMEOUT: i32 = 203;
+
+/// avtools_shutdown_all: stop inputs, playbacks and clocks, release held notes, then
+/// close outputs. Registered when the first handle is issued.
+pub(crate) fn register_shutdown_hooks() {
+    shutdown::register(Stage::Inputs, "inputs", shutdown_inputs);
+    shutdown::register(Stage::Inputs, "playbacks", shutdown_playbacks);
+    shutdown::register(Stage::Inputs, "clocks", shutdown_clocks);
+    shutdown::register(Stage::Outputs, "held_notes", release_held_notes);
+    shutdown::register(Stage::Outputs, "outputs", shutdown_outputs);
+}
+
+fn shutdown_inputs() -> u32 {
+    let retrying: Vec<_> = RETRY_INPUTS.lock().unwrap_or_else(|e| e.into_inner()).drain().collect();
+    let inputs: Vec<_> = INPUTS.lock().unwrap_or_else(|e| e.into_inner()).drain().collect();
+    let mut closed = inputs.len() as u32;
+    for (handle, cancelled) in &retrying {
+        cancelled.store(true, Ordering::Relaxed);
+        if !inputs.iter().any(|(h, _)| h == handle) {
+            handles::release(*handle);
+            closed += 1;
+        }
+    }
+    for (handle, input) in inputs {
+        handles::release(handle);
+        if !input.close_and_join(SHUTDOWN_JOIN_TIMEOUT) {
+            avtools_log::warn!("input {handle}: threads still running after shutdown");
+        }
+    }
+    closed
+}
+
+fn shutdown_playbacks() -> u32 {
+    let playbacks: Vec<_> = PLAYBACKS.lock().unwrap_or_else(|e| e.into_inner()).drain().collect();
+    let stopped = playbacks.len() as u32;
+    for (handle, playback) in playbacks {
+        handles::release(handle);
+        if !playback.stop_within(SHUTDOWN_JOIN_TIMEOUT) {
+            avtools_log::warn!("playback {handle}: thread still running after shutdown");
+        }
+    }
+    stopped
+}
 
-fn next_handle() -> u32 {
-    NEXT_HANDLE.fetch_add(1, Ordering::Relaxed)
+fn shutdown_clocks() -> u32 {
+    let clocks: Vec<_> = CLOCKS.lock().unwrap_or_else(|e| e.into_inner()).drain().collect();
+    let stopped = clocks.len() as u32;
+    for (handle, clock) in clocks {
+        handles::release(handle);
+        if !clock.stop_within(SHUTDOWN_JOIN_TIMEOUT) {
+            avtools_log::warn!("clock {handle}: thread still running after shutdown");
+        }
+    }
+    stopped
+}
+
+fn release_held_notes() -> u32 {
+    let mut outputs = OUTPUTS.lock().unwrap_or_else(|e| e.into_inner());
+    outputs.values_mut().map(OutputHandle::release_held_notes).sum()
+}
+
+fn shutdown_outputs() -> u32 {
+    for (handle, _) in POOLS.lock().unwrap_or_else(|e| e.into_inner()).drain() {
+        handles::release(handle);
+    }
+    let outputs: Vec<_> = OUTPUTS.lock().unwrap_or_else(|e| e.into_inner()).drain().collect();
+    for (handle, _) in &outputs {
+        handles::release(*handle);
+    }
+    outputs.len() as u32
+}
+
+/// Borrow a UTF-8 port id argument, recording INVALID_ARGUMENT if it is
+/// missing or not UTF-8.
+unsafe fn port_id_arg<'a>(ptr: *const u8, len: u32) -> Option<&'a str> {
+    if ptr.is_null() || len == 0 {
+        error::fail(error::INVALID_ARGUMENT, "port id is empty");
+        return None;
+    }
+    let bytes = std::slice::from_raw_parts(ptr, len as usize);
+    match std::str::from_utf8(bytes) {
+        Ok(s) => Some(s),
+        Err(_) => {
+            error::fail(error::INVALID_ARGUMENT, "port id is not UTF-8");
+            None
+        }
+    }
 }
 
 #[no_mangle]
+#[catch_panic]
 pub unsafe extern "C" fn midi_list_inputs(out_ptr: *mut u8, out_cap: u32) -> u32 {
     write_json_buffer(ports::list_inputs_json(), out_ptr, out_cap)
 }
 
 #[no_mangle]
+#[catch_panic]
 pub unsafe extern "C" fn midi_list_outputs(out_ptr: *mut u8, out_cap: u32) -> u32 {
     write_json_buffer(ports::list_outputs_json(), out_ptr, out_cap)
 }
 
 #[no_mangle]
+#[catch_panic]
 pub unsafe extern "C" fn midi_open_input(
     port_id_ptr: *const u8,
     port_id_len: u32,
@@ -39,83 +177,1827 @@ pub unsafe extern "C" fn midi_open_input(
     flags: u32,
     cb: Callback,
 ) -> u32 {
-    if port_id_ptr.is_null() || port_id_len == 0 {
+    let Some(port_id) = port_id_arg(port_id_ptr, port_id_len) else {
+        return 0;
+    };
+    let options = InputOptions {
+        rate_hz,
+        flags,
+        ..InputOptions::default()
+    };
+    register_input(port_id, options, cb)
+}
+
+/// Like midi_open_input, with the options given as a JSON object:
+/// `{version, rate_hz, flags, raw_queue_cap, note_queue_cap}` (version 1).
+/// Every field is optional; unknown fields and unknown flag bits are
+/// rejected. Returns 0 if the port id or options are invalid (the error names
+/// the field), or the port cannot be opened.
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn midi_open_input_ex(
+    port_id_ptr: *const u8,
+    port_id_len: u32,
+    options_json_ptr: *const u8,
+    options_json_len: u32,
+    cb: Callback,
+) -> u32 {
+    let Some(port_id) = port_id_arg(port_id_ptr, port_id_len) else {
+        return 0;
+    };
+    let Some(options) = error::record(options::parse_raw::<InputOptions>(options_json_ptr, options_json_len))
+    else {
+        return 0;
+    };
+    register_input(port_id, options, cb)
+}
+
+/// Open an input with no port, for tests and replays: it receives only what
+/// midi_mock_input_send (or avtools_replay in the combined library) feeds
+/// it, stamped on arrival, and otherwise behaves like midi_open_input_ex
+/// with the same options. Returns 0 if the options are invalid.
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn midi_open_mock_input(
+    options_json_ptr: *const u8,
+    options_json_len: u32,
+    cb: Callback,
+) -> u32 {
+    let Some(options) = error::record(options::parse_raw::<InputOptions>(options_json_ptr, options_json_len))
+    else {
         return 0;
+    };
+    register_opened("mock input", input::open_mock_input(options, cb))
+}
+
+/// Feed one complete message to an input as if it had just arrived from its
+/// port; meant for mock inputs but accepted by any. Returns 0, or -1 for an
+/// empty message or bad handle.
+///
+/// # Safety
+/// `msg_ptr` must be null or valid for `msg_len` bytes of reads.
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn midi_mock_input_send(handle: u32, msg_ptr: *const u8, msg_len: u32) -> i32 {
+    if !handles::check(handle, &[Kind::Input]) {
+        return -1;
+    }
+    if msg_ptr.is_null() || msg_len == 0 {
+        error::fail(error::INVALID_ARGUMENT, "message is empty");
+        return -1;
+    }
+    match error::record(inject(handle, std::slice::from_raw_parts(msg_ptr, msg_len as usize))) {
+        Some(()) => 0,
+        None => -1,
     }
-    let bytes = std::slice::from_raw_parts(port_id_ptr, port_id_len as usize);
-    let port_id = match std::str::from_utf8(bytes) {
-        Ok(s) => s,
-        Err(_) => return 0,
+}
+
+/// Like midi_open_input, but keeps trying in a background thread when the
+/// port cannot be opened yet, waiting `initial_backoff_ms` and doubling the
+/// wait (up to 30s) after each failure. `max_attempts` of 0 retries forever.
+///
+/// The handle is returned immediately and stays pending until the port opens;
+/// then `cb` receives a packet with a single KIND_CONNECTED record before any
+/// data. While connected, the port is checked every second; if it disappears
+/// (e.g. the device is unplugged) the handle goes back to pending and the
+/// same backoff loop reopens it, with another KIND_CONNECTED packet on
+/// success. After the last failed attempt of a round the handle is dropped.
+/// midi_close_input stops the retries. Returns 0 only for an invalid port id
+/// or a retry thread that cannot be started.
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn midi_open_input_with_retry(
+    port_id_ptr: *const u8,
+    port_id_len: u32,
+    rate_hz: u32,
+    flags: u32,
+    cb: Callback,
+    max_attempts: u32,
+    initial_backoff_ms: u32,
+) -> u32 {
+    let Some(port_id) = port_id_arg(port_id_ptr, port_id_len) else {
+        return 0;
     };
-    match input::open_input(port_id, rate_hz, flags, cb) {
+    let port_id = port_id.to_string();
+    error::clear();
+    let options = InputOptions {
+        rate_hz,
+        flags,
+        ..InputOptions::default()
+    };
+    let id = handles::issue(Kind::Input);
+    if id == 0 {
+        return 0;
+    }
+    let cancelled = Arc::new(AtomicBool::new(false));
+    RETRY_INPUTS.lock().unwrap_or_else(|e| e.into_inner()).insert(id, cancelled.clone());
+    let retry = spawn_worker("midi-input-retry", 0, move || {
+        retry_open_input(id, port_id, options, cb, max_attempts, initial_backoff_ms, cancelled)
+    });
+    if let Err(e) = retry {
+        RETRY_INPUTS.lock().unwrap_or_else(|e| e.into_inner()).remove(&id);
+        handles::release(id);
+        error::fail(error::OS_ERROR, format!("cannot start retry thread: {e}"));
+        return 0;
+    }
+    id
+}
+
+/// Keep the input behind `id` open: connect with backoff, then watch the port
+/// and start over when it disappears, until cancelled or out of attempts.
+fn retry_open_input(
+    id: u32,
+    port_id: String,
+    options: InputOptions,
+    cb: Callback,
+    max_attempts: u32,
+    initial_backoff_ms: u32,
+    cancelled: Arc<AtomicBool>,
+) {
+    loop {
+        let opened = open_with_backoff(&port_id, &options, cb, max_attempts, initial_backoff_ms, &cancelled);
+        let Some(handle) = opened else {
+            RETRY_INPUTS.lock().unwrap_or_else(|e| e.into_inner()).remove(&id);
+            return;
+        };
+        {
+            let retrying = RETRY_INPUTS.lock().unwrap_or_else(|e| e.into_inner());
+            if !retrying.contains_key(&id) {
+                drop(retrying);
+                handle.close();
+                return;
+            }
+            INPUTS.lock().unwrap_or_else(|e| e.into_inner()).insert(id, handle);
+        }
+        if !wait_for_port_loss(&port_id, &cancelled) {
+            return;
+        }
+        let retrying = RETRY_INPUTS.lock().unwrap_or_else(|e| e.into_inner());
+        if !retrying.contains_key(&id) {
+            return;
+        }
+        avtools_log::warn!("input {port_id} disconnected; reconnecting");
+        if let Some(input) = INPUTS.lock().unwrap_or_else(|e| e.into_inner()).remove(&id) {
+            input.close();
+        }
+    }
+}
+
+/// Open `port_id`, waiting `initial_backoff_ms` and doubling the wait after
+/// each failure. None once cancelled or after `max_attempts` (0 = no limit).
+fn open_with_backoff(
+    port_id: &str,
+    options: &InputOptions,
+    cb: Callback,
+    max_attempts: u32,
+    initial_backoff_ms: u32,
+    cancelled: &AtomicBool,
+) -> Option<InputHandle> {
+    let mut backoff_ms = initial_backoff_ms.max(1) as u64;
+    let mut attempt: u32 = 0;
+    loop {
+        attempt += 1;
+        // Goes out through the input's own dispatch thread, so it arrives
+        // before any data the port sends.
+        let connected = Record {
+            ts_us: 0,
+            kind: KIND_CONNECTED,
+            channel: 0,
+            a: 0,
+            b: 0,
+            v16: 0,
+            extra: attempt.min(u16::MAX as u32) as u16,
+            arrival_us: 0,
+        };
+        let packet = packet::encode_packet(&vec![connected], 0, 0, 0, 0, None, &[]);
+        match input::open_input_with_first_packet(port_id, options.clone(), cb, Some(packet)) {
+            Ok(handle) => return Some(handle),
+            Err(err) => avtools_log::debug!("retry {attempt} for input {port_id} failed: {err}"),
+        }
+        if max_attempts != 0 && attempt >= max_attempts {
+            avtools_log::warn!("giving up on input {port_id} after {attempt} attempts");
+            return None;
+        }
+        if !wait_unless_cancelled(Duration::from_millis(backoff_ms), cancelled) {
+            return None;
+        }
+        backoff_ms = (backoff_ms * 2).min(MAX_BACKOFF_MS);
+    }
+}
+
+/// Poll until `port_id` no longer exists (true) or `cancelled` (false).
+fn wait_for_port_loss(port_id: &str, cancelled: &AtomicBool) -> bool {
+    loop {
+        if !wait_unless_cancelled(PORT_WATCH_INTERVAL, cancelled) {
+            return false;
+        }
+        // A failure to look is not a loss: keep the connection and look again.
+        if input::input_port_present(port_id) == Some(false) {
+            return true;
+        }
+    }
+}
+
+/// Sleep for `wait` in RETRY_CANCEL_POLL steps; false if cancelled.
+fn wait_unless_cancelled(wait: Duration, cancelled: &AtomicBool) -> bool {
+    let mut waited = Duration::ZERO;
+    while waited < wait {
+        if cancelled.load(Ordering::Relaxed) {
+            return false;
+        }
+        let step = (wait - waited).min(RETRY_CANCEL_POLL);
+        thread::sleep(step);
+        waited += step;
+    }
+    !cancelled.load(Ordering::Relaxed)
+}
+
+/// 1 while the input behind `handle` is open, 0 while it is still retrying
+/// (including reconnecting after its port disappeared) or if the handle is
+/// unknown.
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn midi_is_handle_connected(handle: u32) -> u8 {
+    if !handles::check(handle, &[Kind::Input]) {
+        return 0;
+    }
+    INPUTS.lock().unwrap_or_else(|e| e.into_inner()).contains_key(&handle) as u8
+}
+
+fn register_input(port_id: &str, options: InputOptions, cb: Callback) -> u32 {
+    register_opened(port_id, input::open_input(port_id, options, cb))
+}
+
+fn register_opened(port_id: &str, opened: Result<InputHandle, Error>) -> u32 {
+    match opened {
         Ok(handle) => {
-            let id = next_handle();
-            INPUTS.lock().unwrap().insert(id, handle);
+            let id = handles::issue(Kind::Input);
+            if id == 0 {
+                handle.close();
+                return 0;
+            }
+            error::clear();
+            INPUTS.lock().unwrap_or_else(|e| e.into_inner()).insert(id, handle);
             id
         }
-        Err(_) => 0,
+        Err(err) => {
+            avtools_log::error!("{port_id}: {err}");
+            error::set(err);
+            0
+        }
     }
 }
 
 #[no_mangle]
+#[catch_panic]
 pub unsafe extern "C" fn midi_close_input(handle: u32) {
-    if let Some(input) = INPUTS.lock().unwrap().remove(&handle) {
+    if !handles::check(handle, &[Kind::Input]) {
+        return;
+    }
+    handles::release(handle);
+    if let Some(cancelled) = RETRY_INPUTS.lock().unwrap_or_else(|e| e.into_inner()).remove(&handle) {
+        cancelled.store(true, Ordering::Relaxed);
+    }
+    if let Some(input) = INPUTS.lock().unwrap_or_else(|e| e.into_inner()).remove(&handle) {
         input.close();
     }
 }
 
 #[no_mangle]
-pub unsafe extern "C" fn midi_open_output(port_id_ptr: *const u8, port_id_len: u32) -> u32 {
-    if port_id_ptr.is_null() || port_id_len == 0 {
+#[catch_panic]
+pub unsafe extern "C" fn midi_input_stats(handle: u32, out_ptr: *mut u8, out_cap: u32) -> u32 {
+    if !handles::check(handle, &[Kind::Input]) {
         return 0;
     }
-    let bytes = std::slice::from_raw_parts(port_id_ptr, port_id_len as usize);
-    let port_id = match std::str::from_utf8(bytes) {
-        Ok(s) => s,
-        Err(_) => return 0,
+    let json = match INPUTS.lock().unwrap_or_else(|e| e.into_inner()).get(&handle) {
+        Some(input) => input.stats_json(),
+        None => return 0,
     };
-    match OutputHandle::open(port_id) {
-        Ok(handle) => {
-            let id = next_handle();
-            OUTPUTS.lock().unwrap().insert(id, handle);
-            id
+    write_json_buffer(json, out_ptr, out_cap)
+}
+
+/// midi_bridge's section of the runtime metrics: per-input lifetime drop
+/// counts and dispatch latency (dispatch minus arrival), and how many outputs
+/// and CC playbacks are open. A table that is locked when sampled is null.
+pub fn metrics_section() -> String {
+    let inputs = metrics::sample(&INPUTS, |inputs| {
+        inputs.iter().map(|(&handle, input)| (handle, input.metrics())).collect::<Vec<_>>()
+    })
+    .map(|inputs| inputs.iter().map(|(handle, m)| m.to_json(*handle)).collect::<Vec<_>>());
+    serde_json::json!({
+        "inputs": inputs,
+        "outputs": metrics::sample(&OUTPUTS, HashMap::len),
+        "playbacks": metrics::sample(&PLAYBACKS, HashMap::len),
+        "clocks": metrics::sample(&CLOCKS, HashMap::len),
+    })
+    .to_string()
+}
+
+/// Runtime metrics as JSON, `{"schema":1,"midi":{...}}` (see
+/// `metrics_section`). Never waits on an input's threads. Returns the byte
+/// length; nothing is written if `out_cap` is too small.
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn midi_metrics_json(out_ptr: *mut u8, out_cap: u32) -> u32 {
+    let json = metrics::document(&[("midi", metrics_section())]);
+    buffer::write(json.as_bytes(), out_ptr, out_cap)
+}
+
+/// Override the display name for a program number. A null/empty name restores
+/// the General MIDI default.
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn midi_set_program_name(program: u8, name_ptr: *const u8, name_len: u32) {
+    if program > 127 {
+        return;
+    }
+    let name = if name_ptr.is_null() || name_len == 0 {
+        None
+    } else {
+        let bytes = std::slice::from_raw_parts(name_ptr, name_len as usize);
+        Some(String::from_utf8_lossy(bytes).to_string())
+    };
+    programs::set_program_name(program, name);
+}
+
+/// Set the display hint for controller `cc`: a name plus a format such as
+/// "percent", "signed_64", "signed_127", "on_off" or "hz" (empty = "raw").
+/// A null/empty name restores the General MIDI default.
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn midi_set_cc_display_format(
+    cc: u8,
+    name_ptr: *const u8,
+    name_len: u32,
+    format_ptr: *const u8,
+    format_len: u32,
+) {
+    if cc > 127 {
+        return;
+    }
+    let format = if name_ptr.is_null() || name_len == 0 {
+        None
+    } else {
+        let name = std::slice::from_raw_parts(name_ptr, name_len as usize);
+        let format = if format_ptr.is_null() || format_len == 0 {
+            "raw".to_string()
+        } else {
+            let bytes = std::slice::from_raw_parts(format_ptr, format_len as usize);
+            String::from_utf8_lossy(bytes).to_string()
+        };
+        Some(cc_formats::CcFormat {
+            name: String::from_utf8_lossy(name).to_string(),
+            format,
+        })
+    };
+    cc_formats::set_cc_format(cc, format);
+}
+
+/// Display hint for controller `cc` as `{name, format}` JSON. Returns the byte
+/// length (0 for an undefined controller with no hint set).
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn midi_get_cc_display_format(cc: u8, buf_ptr: *mut u8, buf_cap: u32) -> u32 {
+    if cc > 127 {
+        return 0;
+    }
+    match cc_formats::cc_format(cc) {
+        Some(format) => {
+            let json = serde_json::to_vec(&format).unwrap_or_default();
+            write_json_buffer(json, buf_ptr, buf_cap)
         }
-        Err(_) => 0,
+        None => 0,
     }
 }
 
+/// Name of the program currently selected on `channel` of an input. Returns
+/// the byte length of the UTF-8 name (0 if the handle/channel is unknown).
 #[no_mangle]
-pub unsafe extern "C" fn midi_close_output(handle: u32) {
-    let _ = OUTPUTS.lock().unwrap().remove(&handle);
+#[catch_panic]
+pub unsafe extern "C" fn midi_get_program_name(
+    handle: u32,
+    channel: u8,
+    buf_ptr: *mut u8,
+    buf_cap: u32,
+) -> u32 {
+    if !handles::check(handle, &[Kind::Input]) {
+        return 0;
+    }
+    if channel > 15 {
+        return 0;
+    }
+    let program = match INPUTS.lock().unwrap_or_else(|e| e.into_inner()).get(&handle) {
+        Some(input) => input.current_program(channel),
+        None => return 0,
+    };
+    match programs::program_name(program) {
+        Some(name) => write_json_buffer(name.into_bytes(), buf_ptr, buf_cap),
+        None => 0,
+    }
 }
 
+/// A 14-bit controller pair, `msb_cc` (0–31) and `msb_cc + 32`, as last
+/// received on `channel`, scaled to 0.0–1.0. Returns -1.0 for an unknown
+/// handle or an argument out of range (INVALID_ARGUMENT).
 #[no_mangle]
-pub unsafe extern "C" fn midi_send(handle: u32, bytes_ptr: *const u8, len: u32) -> i32 {
-    if bytes_ptr.is_null() || len == 0 {
+#[catch_panic]
+pub unsafe extern "C" fn midi_get_cc14(handle: u32, channel: u8, msb_cc: u8) -> f32 {
+    if !handles::check(handle, &[Kind::Input]) {
+        return -1.0;
+    }
+    if channel > 15 || msb_cc > 31 {
+        error::fail(error::INVALID_ARGUMENT, format!("channel {channel} / MSB controller {msb_cc} out of range"));
+        return -1.0;
+    }
+    match INPUTS.lock().unwrap_or_else(|e| e.into_inner()).get(&handle) {
+        Some(input) => input.cc14(channel, msb_cc) as f32 / 16383.0,
+        None => -1.0,
+    }
+}
+
+/// Route CC `from_cc` on `from_channel` to `to_cc` on `to_channel` before it is
+/// coalesced. Replaces any existing remap for the same source.
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn midi_add_cc_remap(
+    handle: u32,
+    from_channel: u8,
+    from_cc: u8,
+    to_channel: u8,
+    to_cc: u8,
+) -> i32 {
+    if !handles::check(handle, &[Kind::Input]) {
         return -1;
     }
-    let bytes = std::slice::from_raw_parts(bytes_ptr, len as usize);
-    let mut outputs = OUTPUTS.lock().unwrap();
-    let output = match outputs.get_mut(&handle) {
-        Some(o) => o,
+    if from_channel > 15 || to_channel > 15 || from_cc > 127 || to_cc > 127 {
+        return -1;
+    }
+    match INPUTS.lock().unwrap_or_else(|e| e.into_inner()).get(&handle) {
+        Some(input) => {
+            input.add_cc_remap(CcRemap {
+                from_channel,
+                from_cc,
+                to_channel,
+                to_cc,
+            });
+            0
+        }
+        None => -1,
+    }
+}
+
+/// Returns 0 if a remap was removed, 1 if none matched, -1 for a bad handle.
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn midi_remove_cc_remap(handle: u32, from_channel: u8, from_cc: u8) -> i32 {
+    if !handles::check(handle, &[Kind::Input]) {
+        return -1;
+    }
+    match INPUTS.lock().unwrap_or_else(|e| e.into_inner()).get(&handle) {
+        Some(input) => {
+            if input.remove_cc_remap(from_channel, from_cc) {
+                0
+            } else {
+                1
+            }
+        }
+        None => -1,
+    }
+}
+
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn midi_clear_cc_remaps(handle: u32) -> i32 {
+    if !handles::check(handle, &[Kind::Input]) {
+        return -1;
+    }
+    match INPUTS.lock().unwrap_or_else(|e| e.into_inner()).get(&handle) {
+        Some(input) => {
+            input.clear_cc_remaps();
+            0
+        }
+        None => -1,
+    }
+}
+
+/// `ts_us` of the first message received on an input (0 before any arrive).
+/// This is the origin of the quantise grid.
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn midi_get_epoch_us(handle: u32) -> u64 {
+    if !handles::check(handle, &[Kind::Input]) {
+        return 0;
+    }
+    match INPUTS.lock().unwrap_or_else(|e| e.into_inner()).get(&handle) {
+        Some(input) => input.epoch_us(),
+        None => 0,
+    }
+}
+
+/// Snap note timestamps to a grid of `ppqn` steps per quarter note at
+/// `bpm_x100` / 100 BPM, moving each note `strength_percent`% of the way to
+/// the nearest grid point.
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn midi_set_quantise_grid(
+    handle: u32,
+    ppqn: u32,
+    bpm_x100: u32,
+    strength_percent: u8,
+) -> i32 {
+    if !handles::check(handle, &[Kind::Input]) {
+        return -1;
+    }
+    let grid = match QuantiseGrid::new(ppqn, bpm_x100, strength_percent) {
+        Some(g) => g,
         None => return -1,
     };
-    match output.send(bytes) {
-        Ok(_) => 0,
-        Err(_) => -1,
+    match INPUTS.lock().unwrap_or_else(|e| e.into_inner()).get(&handle) {
+        Some(input) => {
+            input.set_quantise_grid(Some(grid));
+            0
+        }
+        None => -1,
     }
 }
 
-fn write_json_buffer(bytes: Vec<u8>, out_ptr: *mut u8, out_cap: u32) -> u32 {
-    let needed = bytes.len() as u32;
-    if out_ptr.is_null() || out_cap == 0 {
-        return needed;
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn midi_clear_quantise_grid(handle: u32) -> i32 {
+    if !handles::check(handle, &[Kind::Input]) {
+        return -1;
+    }
+    match INPUTS.lock().unwrap_or_else(|e| e.into_inner()).get(&handle) {
+        Some(input) => {
+            input.set_quantise_grid(None);
+            0
+        }
+        None => -1,
+    }
+}
+
+/// Snap incoming notes on `channel` (16 = every channel) to the nearest note of
+/// a scale before they are queued; ties snap down. Bit n of `scale_mask` puts
+/// the pitch class `n` semitones above `root_note` in the scale. Returns -1
+/// for an unknown handle, a channel above 16 or an empty scale.
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn midi_set_note_quantise_scale(
+    handle: u32,
+    channel: u8,
+    root_note: u8,
+    scale_mask: u16,
+) -> i32 {
+    if !handles::check(handle, &[Kind::Input]) {
+        return -1;
+    }
+    let scale = match NoteScale::new(root_note, scale_mask) {
+        Some(scale) if channel <= pipeline::ALL_CHANNELS => scale,
+        _ => return -1,
+    };
+    match INPUTS.lock().unwrap_or_else(|e| e.into_inner()).get(&handle) {
+        Some(input) => {
+            input.set_note_scale(channel, Some(scale));
+            0
+        }
+        None => -1,
+    }
+}
+
+/// Stop snapping notes on `channel` (16 = every channel).
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn midi_clear_note_quantise_scale(handle: u32, channel: u8) -> i32 {
+    if !handles::check(handle, &[Kind::Input]) {
+        return -1;
+    }
+    if channel > pipeline::ALL_CHANNELS {
+        return -1;
+    }
+    match INPUTS.lock().unwrap_or_else(|e| e.into_inner()).get(&handle) {
+        Some(input) => {
+            input.set_note_scale(channel, None);
+            0
+        }
+        None => -1,
+    }
+}
+
+/// Shift every timestamp from this input by `offset_us` to compensate for
+/// interface latency: positive moves events later, negative earlier (never
+/// below 0). Applies to messages processed after the call.
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn midi_set_latency_compensation(handle: u32, offset_us: i64) -> i32 {
+    if !handles::check(handle, &[Kind::Input]) {
+        return -1;
+    }
+    match INPUTS.lock().unwrap_or_else(|e| e.into_inner()).get(&handle) {
+        Some(input) => {
+            input.set_latency_compensation(offset_us);
+            0
+        }
+        None => -1,
+    }
+}
+
+/// Ignore CC `cc` on `channel` (after any remap) until a value within
+/// `lock_threshold` of `current_value` arrives, then track it normally, so a
+/// knob takes over from the software's value without a jump. Setting it
+/// again re-arms the lock. Returns 0, or -1 for a bad handle or argument.
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn midi_set_cc_lock_mode(
+    handle: u32,
+    channel: u8,
+    cc: u8,
+    current_value: u8,
+    lock_threshold: u8,
+) -> i32 {
+    if !handles::check(handle, &[Kind::Input]) {
+        return -1;
+    }
+    if channel > 15 || cc > 127 || current_value > 127 {
+        return -1;
+    }
+    match INPUTS.lock().unwrap_or_else(|e| e.into_inner()).get(&handle) {
+        Some(input) => {
+            input.set_cc_lock(channel, cc, current_value, lock_threshold);
+            0
+        }
+        None => -1,
+    }
+}
+
+/// Stop waiting for the lock set by midi_set_cc_lock_mode. Returns 0 if the
+/// controller was locked, 1 if it was not, -1 for a bad handle or argument.
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn midi_release_cc_lock(handle: u32, channel: u8, cc: u8) -> i32 {
+    if !handles::check(handle, &[Kind::Input]) {
+        return -1;
+    }
+    if channel > 15 || cc > 127 {
+        return -1;
+    }
+    match INPUTS.lock().unwrap_or_else(|e| e.into_inner()).get(&handle) {
+        Some(input) => {
+            if input.release_cc_lock(channel, cc) {
+                0
+            } else {
+                1
+            }
+        }
+        None => -1,
+    }
+}
+
+/// Bytes this input received per second, averaged over the last second, to
+/// spot a port near its bandwidth before raw messages start dropping. 0 for
+/// an unknown handle.
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn midi_get_bandwidth_bps(handle: u32) -> u32 {
+    if !handles::check(handle, &[Kind::Input]) {
+        return 0;
+    }
+    match INPUTS.lock().unwrap_or_else(|e| e.into_inner()).get(&handle) {
+        Some(input) => input.bandwidth_bps(),
+        None => 0,
+    }
+}
+
+/// MIDI Show Control device id (0–0x7F) this input answers to under
+/// FLAG_PARSE_MSC. 0x7F, the default, accepts messages for any device;
+/// otherwise messages for this id and all-call (0x7F) messages are parsed.
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn midi_set_msc_device_id(handle: u32, device_id: u8) -> i32 {
+    if !handles::check(handle, &[Kind::Input]) {
+        return -1;
+    }
+    if device_id > 0x7F {
+        return -1;
+    }
+    match INPUTS.lock().unwrap_or_else(|e| e.into_inner()).get(&handle) {
+        Some(input) => {
+            input.set_msc_device_id(device_id);
+            0
+        }
+        None => -1,
     }
-    if out_cap < needed {
-        return needed;
+}
+
+/// Emit a KIND_NOTE_EXP record (channel, a = `note`, b = value, extra =
+/// `kind`) for each incoming SysEx message equal to the template at
+/// `template_ptr`/`template_len` in every byte but the value at
+/// `value_offset`. The template is the whole message, F0 to F7. Registering
+/// the same channel, note and kind again replaces the template. Returns 0, or
+/// -1 with INVALID_ARGUMENT for an out-of-range argument or malformed template.
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn midi_register_note_expression(
+    handle: u32,
+    channel: u8,
+    note: u8,
+    kind: u32,
+    template_ptr: *const u8,
+    template_len: u32,
+    value_offset: u8,
+) -> i32 {
+    if !handles::check(handle, &[Kind::Input]) {
+        return -1;
+    }
+    let template = if template_ptr.is_null() {
+        &[][..]
+    } else {
+        std::slice::from_raw_parts(template_ptr, template_len as usize)
+    };
+    let expression = match sysex::NoteExpression::new(channel, note, kind, template, value_offset) {
+        Ok(expression) => expression,
+        Err(err) => {
+            error::fail(error::INVALID_ARGUMENT, err);
+            return -1;
+        }
+    };
+    match INPUTS.lock().unwrap_or_else(|e| e.into_inner()).get(&handle) {
+        Some(input) => {
+            input.register_note_expression(expression);
+            0
+        }
+        None => -1,
+    }
+}
+
+/// Forget every note expression registered on this input.
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn midi_clear_note_expressions(handle: u32) -> i32 {
+    if !handles::check(handle, &[Kind::Input]) {
+        return -1;
     }
-    unsafe {
-        std::ptr::copy_nonoverlapping(bytes.as_ptr(), out_ptr, bytes.len());
+    match INPUTS.lock().unwrap_or_else(|e| e.into_inner()).get(&handle) {
+        Some(input) => {
+            input.clear_note_expressions();
+            0
+        }
+        None => -1,
     }
-    needed
+}
+
+/// How long a note-on waits for its note-off under FLAG_NOTE_DURATION before
+/// being delivered unpaired. Defaults to 10 s.
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn midi_set_note_timeout_ms(handle: u32, timeout_ms: u32) -> i32 {
+    if !handles::check(handle, &[Kind::Input]) {
+        return -1;
+    }
+    match INPUTS.lock().unwrap_or_else(|e| e.into_inner()).get(&handle) {
+        Some(input) => {
+            input.set_note_timeout_ms(timeout_ms);
+            0
+        }
+        None => -1,
+    }
+}
+
+/// Dispatch this input on MIDI clock instead of its rate timer: one packet
+/// every N clock pulses (see midi_set_dispatch_clocks_per_tick) received on
+/// `source_clock_handle`, which may be the same input. 0 restores timer dispatch.
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn midi_set_beat_dispatch(handle: u32, source_clock_handle: u32) -> i32 {
+    if !handles::check(handle, &[Kind::Input]) {
+        return -1;
+    }
+    if source_clock_handle != 0 && !handles::check(source_clock_handle, &[Kind::Input]) {
+        return -1;
+    }
+    let inputs = INPUTS.lock().unwrap_or_else(|e| e.into_inner());
+    let source = if source_clock_handle == 0 {
+        None
+    } else {
+        match inputs.get(&source_clock_handle) {
+            Some(source) => Some(source.clock()),
+            None => return -1,
+        }
+    };
+    match inputs.get(&handle) {
+        Some(input) => {
+            input.set_beat_source(source);
+            0
+        }
+        None => -1,
+    }
+}
+
+/// Clock pulses per dispatch tick under beat dispatch (default 6, i.e. one
+/// tick per sixteenth note at 24 PPQN). 0 is treated as 1.
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn midi_set_dispatch_clocks_per_tick(handle: u32, clocks: u32) -> i32 {
+    if !handles::check(handle, &[Kind::Input]) {
+        return -1;
+    }
+    match INPUTS.lock().unwrap_or_else(|e| e.into_inner()).get(&handle) {
+        Some(input) => {
+            input.set_clocks_per_tick(clocks);
+            0
+        }
+        None => -1,
+    }
+}
+
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn midi_open_output(port_id_ptr: *const u8, port_id_len: u32) -> u32 {
+    let Some(port_id) = port_id_arg(port_id_ptr, port_id_len) else {
+        return 0;
+    };
+    match OutputHandle::open(port_id) {
+        Ok(handle) => {
+            let id = handles::issue(Kind::Output);
+            if id == 0 {
+                return 0;
+            }
+            error::clear();
+            OUTPUTS.lock().unwrap_or_else(|e| e.into_inner()).insert(id, handle);
+            id
+        }
+        Err(err) => {
+            avtools_log::error!("{port_id}: {err}");
+            error::set(err);
+            0
+        }
+    }
+}
+
+/// Open every port in a JSON array of port ids and return one pool handle.
+/// midi_send on the pool sends each call to the next member port in turn.
+/// Returns 0 if the JSON is invalid or empty, or any port fails to open (the
+/// ones already opened are closed again).
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn midi_open_output_pool(port_ids_json_ptr: *const u8, port_ids_json_len: u32) -> u32 {
+    if port_ids_json_ptr.is_null() || port_ids_json_len == 0 {
+        error::fail(error::INVALID_ARGUMENT, "port id list is empty");
+        return 0;
+    }
+    let json = std::slice::from_raw_parts(port_ids_json_ptr, port_ids_json_len as usize);
+    let port_ids: Vec<String> = match serde_json::from_slice(json) {
+        Ok(ids) => ids,
+        Err(err) => {
+            avtools_log::error!("invalid output pool port list: {err}");
+            error::fail(error::INVALID_ARGUMENT, format!("invalid output pool port list: {err}"));
+            return 0;
+        }
+    };
+    if port_ids.is_empty() {
+        error::fail(error::INVALID_ARGUMENT, "output pool needs at least one port");
+        return 0;
+    }
+    let mut opened = Vec::with_capacity(port_ids.len());
+    for port_id in &port_ids {
+        match OutputHandle::open(port_id) {
+            Ok(handle) => opened.push(handle),
+            Err(err) => {
+                avtools_log::error!("{port_id}: {err}");
+                error::set(err);
+                return 0;
+            }
+        }
+    }
+    let id = handles::issue(Kind::Pool);
+    let member_ids: Vec<u32> = opened.iter().map(|_| handles::issue(Kind::Output)).collect();
+    if id == 0 || member_ids.contains(&0) {
+        handles::release(id);
+        member_ids.iter().for_each(|&member| handles::release(member));
+        return 0;
+    }
+    let mut outputs = OUTPUTS.lock().unwrap_or_else(|e| e.into_inner());
+    let members = member_ids
+        .iter()
+        .zip(opened)
+        .map(|(&id, handle)| {
+            outputs.insert(id, handle);
+            id
+        })
+        .collect();
+    POOLS.lock().unwrap_or_else(|e| e.into_inner()).insert(id, OutputPool::new(members));
+    error::clear();
+    id
+}
+
+/// For other components of the combined library: call `tap` with every
+/// batch of records input `handle` dispatches, on its dispatch thread, until
+/// remove_record_tap. Returns the tap's token.
+pub fn add_record_tap(handle: u32, tap: RecordTap) -> Result<u64, Error> {
+    match INPUTS.lock().unwrap_or_else(|e| e.into_inner()).get(&handle) {
+        Some(input) => Ok(input.add_record_tap(tap)),
+        None => Err(Error::new(error::INVALID_HANDLE, format!("unknown input handle {handle}"))),
+    }
+}
+
+/// False if the input was closed or has no tap `token`.
+pub fn remove_record_tap(handle: u32, token: u64) -> bool {
+    INPUTS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(&handle)
+        .is_some_and(|input| input.remove_record_tap(token))
+}
+
+/// Like add_record_tap, for every message input `handle` receives, with its
+/// arrival time, before any filtering. Runs on the backend's callback thread.
+pub fn add_raw_tap(handle: u32, tap: RawTap) -> Result<u64, Error> {
+    match INPUTS.lock().unwrap_or_else(|e| e.into_inner()).get(&handle) {
+        Some(input) => Ok(input.add_raw_tap(tap)),
+        None => Err(Error::new(error::INVALID_HANDLE, format!("unknown input handle {handle}"))),
+    }
+}
+
+pub fn remove_raw_tap(handle: u32, token: u64) -> bool {
+    INPUTS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(&handle)
+        .is_some_and(|input| input.remove_raw_tap(token))
+}
+
+/// midi_mock_input_send for other components.
+pub fn inject(handle: u32, msg: &[u8]) -> Result<(), Error> {
+    match INPUTS.lock().unwrap_or_else(|e| e.into_inner()).get(&handle) {
+        Some(input) => {
+            input.inject(msg);
+            Ok(())
+        }
+        None => Err(Error::new(error::INVALID_HANDLE, format!("unknown input handle {handle}"))),
+    }
+}
+
+/// Whether `handle` is an open input, i.e. something `inject` accepts.
+pub fn is_input(handle: u32) -> bool {
+    INPUTS.lock().unwrap_or_else(|e| e.into_inner()).contains_key(&handle)
+}
+
+/// Whether `handle` is an open output or pool, i.e. something `send` accepts.
+pub fn is_output(handle: u32) -> bool {
+    OUTPUTS.lock().unwrap_or_else(|e| e.into_inner()).contains_key(&handle)
+        || POOLS.lock().unwrap_or_else(|e| e.into_inner()).contains_key(&handle)
+}
+
+/// midi_send for other components: one complete message to an output or pool.
+pub fn send(handle: u32, bytes: &[u8]) -> Result<(), Error> {
+    try_send_output(handle, bytes)
+}
+
+/// Member output handles of a pool as a JSON array. Returns the byte length
+/// (0 for an unknown pool); nothing is written if `out_cap` is too small.
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn midi_get_pool_member_handles(pool_handle: u32, out_ptr: *mut u8, out_cap: u32) -> u32 {
+    if !handles::check(pool_handle, &[Kind::Pool]) {
+        return 0;
+    }
+    let json = match POOLS.lock().unwrap_or_else(|e| e.into_inner()).get(&pool_handle) {
+        Some(pool) => serde_json::to_vec(pool.members()).unwrap_or_else(|_| b"[]".to_vec()),
+        None => return 0,
+    };
+    write_json_buffer(json, out_ptr, out_cap)
+}
+
+/// Close an output, or a pool together with all of its members.
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn midi_close_output(handle: u32) {
+    if !handles::check(handle, &[Kind::Output, Kind::Pool]) {
+        return;
+    }
+    handles::release(handle);
+    let pool = POOLS.lock().unwrap_or_else(|e| e.into_inner()).remove(&handle);
+    let mut outputs = OUTPUTS.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(pool) = pool {
+        for member in pool.members() {
+            handles::release(*member);
+            outputs.remove(member);
+        }
+    }
+    let _ = outputs.remove(&handle);
+}
+
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn midi_send(handle: u32, bytes_ptr: *const u8, len: u32) -> i32 {
+    if !handles::check(handle, &[Kind::Output, Kind::Pool]) {
+        return -1;
+    }
+    if bytes_ptr.is_null() || len == 0 {
+        error::fail(error::INVALID_ARGUMENT, "message is empty");
+        return -1;
+    }
+    let bytes = std::slice::from_raw_parts(bytes_ptr, len as usize);
+    match error::record(try_send_output(handle, bytes)) {
+        Some(()) => 0,
+        None => -1,
+    }
+}
+
+/// Send an OSC packet through MIDI as SysEx (`F0 00 21 09 <device_id>
+/// <7-bit packed OSC> F7`; see sysex::osc_tunnel_message) for an input opened
+/// with FLAG_DECODE_OSC_TUNNEL to unwrap. The packet must be valid OSC 1.0.
+/// 0 on success, -1 on failure (INVALID_ARGUMENT for bad OSC or a device id
+/// above 0x7F).
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn midi_send_osc_tunnelled(
+    output_handle: u32,
+    osc_bytes_ptr: *const u8,
+    osc_bytes_len: u32,
+    device_id: u8,
+) -> i32 {
+    if !handles::check(output_handle, &[Kind::Output, Kind::Pool]) {
+        return -1;
+    }
+    if osc_bytes_ptr.is_null() || osc_bytes_len == 0 {
+        error::fail(error::INVALID_ARGUMENT, "OSC packet is empty");
+        return -1;
+    }
+    if device_id > 0x7F {
+        error::fail(error::INVALID_ARGUMENT, format!("device id {device_id} is not 7-bit"));
+        return -1;
+    }
+    let osc = std::slice::from_raw_parts(osc_bytes_ptr, osc_bytes_len as usize);
+    if let Err(err) = osc::validate(osc) {
+        error::fail(error::INVALID_ARGUMENT, err);
+        return -1;
+    }
+    let msg = sysex::osc_tunnel_message(osc, device_id);
+    match error::record(try_send_output(output_handle, &msg)) {
+        Some(()) => 0,
+        None => -1,
+    }
+}
+
+/// Send a MIDI Machine Control transport command (`F0 7F <device_id> 06
+/// <command> F7`): stop 0x01, play 0x02, deferred play 0x03, fast forward
+/// 0x04, rewind 0x05, record strobe 0x06, record exit 0x07, pause 0x09 or
+/// eject 0x0A. `device_id` 0x7F addresses every device. 0 on success, -1 on
+/// failure (INVALID_ARGUMENT for a device id above 0x7F or another command).
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn midi_send_mmc(output_handle: u32, device_id: u8, command: u8) -> i32 {
+    if !handles::check(output_handle, &[Kind::Output, Kind::Pool]) {
+        return -1;
+    }
+    if device_id > 0x7F {
+        error::fail(error::INVALID_ARGUMENT, format!("device id {device_id} is not 7-bit"));
+        return -1;
+    }
+    if !sysex::MMC_TRANSPORT_COMMANDS.contains(&command) {
+        error::fail(error::INVALID_ARGUMENT, format!("{command:#x} is not an MMC transport command"));
+        return -1;
+    }
+    match error::record(try_send_output(output_handle, &sysex::mmc_message(device_id, command))) {
+        Some(()) => 0,
+        None => -1,
+    }
+}
+
+/// Send `value` (0.0–1.0, clamped) as a 14-bit controller pair: MSB on
+/// `msb_cc` (0–31), then LSB on `msb_cc + 32`. 0 on success, -1 on failure
+/// (INVALID_ARGUMENT for a channel, controller or value out of range).
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn midi_set_cc14(output_handle: u32, channel: u8, msb_cc: u8, value_f32: f32) -> i32 {
+    if !handles::check(output_handle, &[Kind::Output, Kind::Pool]) {
+        return -1;
+    }
+    if channel > 15 || msb_cc > 31 || value_f32.is_nan() {
+        error::fail(
+            error::INVALID_ARGUMENT,
+            format!("channel {channel} / MSB controller {msb_cc} / value {value_f32} out of range"),
+        );
+        return -1;
+    }
+    let value = (value_f32.clamp(0.0, 1.0) * 16383.0).round() as u16;
+    let status = 0xB0 | channel;
+    let msb = [status, msb_cc, (value >> 7) as u8];
+    let lsb = [status, msb_cc + 32, (value & 0x7F) as u8];
+    match error::record(try_send_messages(output_handle, &[&msb, &lsb])) {
+        Some(()) => 0,
+        None => -1,
+    }
+}
+
+/// Send on an output or pool handle. 0 on success, -1 on failure.
+pub(crate) fn send_output(handle: u32, bytes: &[u8]) -> i32 {
+    match try_send_output(handle, bytes) {
+        Ok(()) => 0,
+        Err(err) => {
+            avtools_log::warn!("{err}");
+            -1
+        }
+    }
+}
+
+fn try_send_output(handle: u32, bytes: &[u8]) -> Result<(), Error> {
+    try_send_messages(handle, &[bytes])
+}
+
+/// Send `messages` in order to one port; a pool picks one member for all.
+fn try_send_messages(handle: u32, messages: &[&[u8]]) -> Result<(), Error> {
+    let handle = match POOLS.lock().unwrap_or_else(|e| e.into_inner()).get_mut(&handle) {
+        Some(pool) => pool
+            .next_member()
+            .ok_or_else(|| Error::new(error::INVALID_HANDLE, "output pool is empty"))?,
+        None => handle,
+    };
+    let mut outputs = OUTPUTS.lock().unwrap_or_else(|e| e.into_inner());
+    let output = outputs
+        .get_mut(&handle)
+        .ok_or_else(|| Error::new(error::INVALID_HANDLE, format!("unknown output handle {handle}")))?;
+    messages.iter().try_for_each(|bytes| output.send(bytes))
+}
+
+/// Set up an MPE instrument: send RPN 0 (pitch bend range) = 48 semitones on
+/// every member channel of a full lower zone (channels 2–16) through
+/// `output_handle`, then wait up to `timeout_ms` for the instrument to echo
+/// the same setting back on `input_handle`. Returns the number of channels
+/// that acknowledged (as soon as all have), or -1 if none did (ERR_TIMEOUT)
+/// or sending failed. Blocks the calling thread.
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn midi_mpe_negotiate(input_handle: u32, output_handle: u32, timeout_ms: u32) -> i32 {
+    if !handles::check(input_handle, &[Kind::Input])
+        || !handles::check(output_handle, &[Kind::Output, Kind::Pool])
+    {
+        return -1;
+    }
+    let negotiation = Arc::new(MpeNegotiation::new(rpn::MPE_MEMBER_CHANNELS));
+    let set_listener = |listener: Option<Arc<MpeNegotiation>>| {
+        match INPUTS.lock().unwrap_or_else(|e| e.into_inner()).get(&input_handle) {
+            Some(input) => {
+                input.set_mpe_negotiation(listener);
+                true
+            }
+            None => false,
+        }
+    };
+    if !set_listener(Some(negotiation.clone())) {
+        return -1;
+    }
+    for channel in rpn::MPE_MEMBER_CHANNELS {
+        for msg in rpn::pitch_bend_range_messages(channel, rpn::MPE_MEMBER_BEND_RANGE) {
+            if error::record(try_send_output(output_handle, &msg)).is_none() {
+                set_listener(None);
+                return -1;
+            }
+        }
+    }
+    let acked = negotiation.wait(Duration::from_millis(timeout_ms as u64));
+    set_listener(None);
+    if acked == 0 {
+        error::fail(
+            ERR_TIMEOUT,
+            format!("no MPE channel acknowledged the pitch bend range within {timeout_ms} ms"),
+        );
+        return -1;
+    }
+    acked as i32
+}
+
+/// Send the SysEx `request` through `output_handle` and wait up to
+/// `timeout_ms` for a SysEx message starting with `response_prefix` (F0
+/// included; empty matches any SysEx) to arrive on `input_handle`, for
+/// instruments that answer dump and identity requests. Writes the whole
+/// response, F0 to F7, to `buf_ptr` and returns its length. -1 on timeout
+/// (ERR_TIMEOUT), a send failure, or a response longer than `buf_cap`
+/// (INVALID_ARGUMENT, the message gives its length). Blocks the calling
+/// thread; messages that arrive meanwhile are still delivered as usual.
+#[no_mangle]
+#[catch_panic]
+#[allow(clippy::too_many_arguments)]
+pub unsafe extern "C" fn midi_sysex_request_response(
+    output_handle: u32,
+    input_handle: u32,
+    request_ptr: *const u8,
+    request_len: u32,
+    response_prefix_ptr: *const u8,
+    response_prefix_len: u32,
+    timeout_ms: u32,
+    buf_ptr: *mut u8,
+    buf_cap: u32,
+) -> i32 {
+    if !handles::check(input_handle, &[Kind::Input])
+        || !handles::check(output_handle, &[Kind::Output, Kind::Pool])
+    {
+        return -1;
+    }
+    if request_ptr.is_null() || request_len == 0 {
+        error::fail(error::INVALID_ARGUMENT, "request is empty");
+        return -1;
+    }
+    let request = std::slice::from_raw_parts(request_ptr, request_len as usize);
+    let prefix = if response_prefix_ptr.is_null() {
+        &[][..]
+    } else {
+        std::slice::from_raw_parts(response_prefix_ptr, response_prefix_len as usize)
+    };
+    let matcher = Arc::new(sysex::ResponseMatcher::new(prefix));
+    let tap_matcher = matcher.clone();
+    let token = match error::record(add_raw_tap(
+        input_handle,
+        Box::new(move |_, msg| tap_matcher.observe(msg)),
+    )) {
+        Some(token) => token,
+        None => return -1,
+    };
+    if error::record(try_send_output(output_handle, request)).is_none() {
+        remove_raw_tap(input_handle, token);
+        return -1;
+    }
+    let response = matcher.wait(Duration::from_millis(timeout_ms as u64));
+    remove_raw_tap(input_handle, token);
+    let Some(response) = response else {
+        error::fail(ERR_TIMEOUT, format!("no matching SysEx response within {timeout_ms} ms"));
+        return -1;
+    };
+    if !buffer::copy_if_fits(&response, buf_ptr, buf_cap) {
+        error::fail(
+            error::INVALID_ARGUMENT,
+            format!("the {}-byte response does not fit in {buf_cap} bytes", response.len()),
+        );
+        return -1;
+    }
+    response.len() as i32
+}
+
+/// Create an empty transform pipeline. Transforms run in the order they are
+/// added, on every channel message of the attached inputs before coalescing.
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn midi_create_pipeline() -> u32 {
+    let id = handles::issue(Kind::Pipeline);
+    if id == 0 {
+        return 0;
+    }
+    PIPELINES.lock().unwrap_or_else(|e| e.into_inner()).insert(id, Arc::new(Pipeline::new()));
+    id
+}
+
+fn add_transform(pipeline: u32, transform: Transform) -> i32 {
+    match PIPELINES.lock().unwrap_or_else(|e| e.into_inner()).get(&pipeline) {
+        Some(p) => {
+            p.push(transform);
+            0
+        }
+        None => -1,
+    }
+}
+
+/// Transpose notes on `channel` (16 = all channels) by `semitones`. Notes
+/// moved outside 0–127 are dropped. Returns -1 for a bad pipeline or channel.
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn midi_pipeline_add_transpose(
+    pipeline: u32,
+    channel: u8,
+    semitones: i8,
+) -> i32 {
+    if !handles::check(pipeline, &[Kind::Pipeline]) {
+        return -1;
+    }
+    if channel > ALL_CHANNELS {
+        return -1;
+    }
+    add_transform(pipeline, Transform::Transpose { channel, semitones })
+}
+
+/// Move every message on channel `from` to channel `to`.
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn midi_pipeline_add_channel_remap(pipeline: u32, from: u8, to: u8) -> i32 {
+    if !handles::check(pipeline, &[Kind::Pipeline]) {
+        return -1;
+    }
+    if from > 15 || to > 15 {
+        return -1;
+    }
+    add_transform(pipeline, Transform::ChannelRemap { from, to })
+}
+
+/// Route CC `from_cc` on `from_channel` to `to_cc` on `to_channel`.
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn midi_pipeline_add_cc_remap(
+    pipeline: u32,
+    from_channel: u8,
+    from_cc: u8,
+    to_channel: u8,
+    to_cc: u8,
+) -> i32 {
+    if !handles::check(pipeline, &[Kind::Pipeline]) {
+        return -1;
+    }
+    if from_channel > 15 || to_channel > 15 || from_cc > 127 || to_cc > 127 {
+        return -1;
+    }
+    add_transform(
+        pipeline,
+        Transform::CcRemap {
+            from_channel,
+            from_cc,
+            to_channel,
+            to_cc,
+        },
+    )
+}
+
+/// Scale note-on velocities on `channel` (16 = all) by `percent`; results are
+/// clamped to 1–127.
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn midi_pipeline_add_velocity_scale(
+    pipeline: u32,
+    channel: u8,
+    percent: u16,
+) -> i32 {
+    if !handles::check(pipeline, &[Kind::Pipeline]) {
+        return -1;
+    }
+    if channel > ALL_CHANNELS {
+        return -1;
+    }
+    add_transform(pipeline, Transform::VelocityScale { channel, percent })
+}
+
+/// Remove every transform, keeping attachments.
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn midi_pipeline_clear(pipeline: u32) -> i32 {
+    if !handles::check(pipeline, &[Kind::Pipeline]) {
+        return -1;
+    }
+    match PIPELINES.lock().unwrap_or_else(|e| e.into_inner()).get(&pipeline) {
+        Some(p) => {
+            p.clear();
+            0
+        }
+        None => -1,
+    }
+}
+
+/// Run an input's messages through `pipeline`, replacing any pipeline it had.
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn midi_pipeline_attach_input(pipeline: u32, input_handle: u32) -> i32 {
+    if !handles::check(pipeline, &[Kind::Pipeline]) {
+        return -1;
+    }
+    if !handles::check(input_handle, &[Kind::Input]) {
+        return -1;
+    }
+    let Some(p) = PIPELINES.lock().unwrap_or_else(|e| e.into_inner()).get(&pipeline).cloned() else {
+        return -1;
+    };
+    match INPUTS.lock().unwrap_or_else(|e| e.into_inner()).get(&input_handle) {
+        Some(input) => {
+            p.add_input(input_handle);
+            input.set_pipeline(Some(p));
+            0
+        }
+        None => -1,
+    }
+}
+
+/// Echo every transformed message to an output (or pool) handle: MIDI thru.
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn midi_pipeline_attach_output(pipeline: u32, output_handle: u32) -> i32 {
+    if !handles::check(pipeline, &[Kind::Pipeline]) {
+        return -1;
+    }
+    if !handles::check(output_handle, &[Kind::Output, Kind::Pool]) {
+        return -1;
+    }
+    let Some(p) = PIPELINES.lock().unwrap_or_else(|e| e.into_inner()).get(&pipeline).cloned() else {
+        return -1;
+    };
+    let known = OUTPUTS.lock().unwrap_or_else(|e| e.into_inner()).contains_key(&output_handle)
+        || POOLS.lock().unwrap_or_else(|e| e.into_inner()).contains_key(&output_handle);
+    if !known {
+        return -1;
+    }
+    p.attach_output(output_handle);
+    0
+}
+
+/// Detach the pipeline from its inputs and free it.
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn midi_pipeline_destroy(pipeline: u32) -> i32 {
+    if !handles::check(pipeline, &[Kind::Pipeline]) {
+        return -1;
+    }
+    let Some(p) = PIPELINES.lock().unwrap_or_else(|e| e.into_inner()).remove(&pipeline) else {
+        return -1;
+    };
+    handles::release(pipeline);
+    let inputs = INPUTS.lock().unwrap_or_else(|e| e.into_inner());
+    for handle in p.inputs() {
+        if let Some(input) = inputs.get(&handle) {
+            if input.pipeline().is_some_and(|current| Arc::ptr_eq(&current, &p)) {
+                input.set_pipeline(None);
+            }
+        }
+    }
+    0
+}
+
+/// Start recording every value of CC `cc` on `channel` (after remapping)
+/// arriving on an input. Returns a recording id, or 0 for an unknown handle
+/// or a channel above 15 or cc above 127 (INVALID_ARGUMENT).
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn midi_start_cc_record(handle: u32, channel: u8, cc: u8) -> u32 {
+    if !handles::check(handle, &[Kind::Input]) || !check_channel_cc(channel, cc) {
+        return 0;
+    }
+    let recording = Arc::new(CcRecording::new(channel, cc));
+    let id = handles::issue(Kind::Recording);
+    if id == 0 {
+        return 0;
+    }
+    match INPUTS.lock().unwrap_or_else(|e| e.into_inner()).get(&handle) {
+        Some(input) => input.add_cc_recording(recording.clone()),
+        None => {
+            handles::release(id);
+            return 0;
+        }
+    }
+    RECORDINGS.lock().unwrap_or_else(|e| e.into_inner()).insert(id, recording);
+    id
+}
+
+/// Stop appending to a recording; its samples stay available for playback.
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn midi_stop_cc_record(rec_id: u32) -> i32 {
+    if !handles::check(rec_id, &[Kind::Recording]) {
+        return -1;
+    }
+    match RECORDINGS.lock().unwrap_or_else(|e| e.into_inner()).get(&rec_id) {
+        Some(recording) => {
+            recording.stop();
+            0
+        }
+        None => -1,
+    }
+}
+
+/// Stop and discard a recording.
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn midi_delete_cc_record(rec_id: u32) -> i32 {
+    if !handles::check(rec_id, &[Kind::Recording]) {
+        return -1;
+    }
+    match RECORDINGS.lock().unwrap_or_else(|e| e.into_inner()).remove(&rec_id) {
+        Some(recording) => {
+            handles::release(rec_id);
+            recording.stop();
+            0
+        }
+        None => -1,
+    }
+}
+
+/// Replay a recording as CC `cc` on `channel` to an output (or pool),
+/// interpolating linearly between recorded samples. `speed_x1000` scales
+/// time (1000 = as recorded); `loop_count` 0 loops until stopped. Plays the
+/// samples recorded so far. Returns a playback id, or 0 if the output or
+/// recording is unknown, the channel is above 15 or cc above 127
+/// (INVALID_ARGUMENT) or the playback thread cannot be started.
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn midi_playback_cc(
+    output_handle: u32,
+    rec_id: u32,
+    channel: u8,
+    cc: u8,
+    speed_x1000: u32,
+    loop_count: u32,
+) -> u32 {
+    if !handles::check(output_handle, &[Kind::Output, Kind::Pool]) {
+        return 0;
+    }
+    if !handles::check(rec_id, &[Kind::Recording]) || !check_channel_cc(channel, cc) {
+        return 0;
+    }
+    let known_output = OUTPUTS.lock().unwrap_or_else(|e| e.into_inner()).contains_key(&output_handle)
+        || POOLS.lock().unwrap_or_else(|e| e.into_inner()).contains_key(&output_handle);
+    if !known_output {
+        return 0;
+    }
+    let samples = match RECORDINGS.lock().unwrap_or_else(|e| e.into_inner()).get(&rec_id) {
+        Some(recording) => recording.snapshot(),
+        None => return 0,
+    };
+    let id = handles::issue(Kind::Playback);
+    if id == 0 {
+        return 0;
+    }
+    let playback = CcPlayback::start(samples, channel, cc, speed_x1000, loop_count, move |bytes| {
+        send_output(output_handle, bytes) == 0
+    });
+    let playback = match playback {
+        Ok(playback) => playback,
+        Err(e) => {
+            handles::release(id);
+            error::fail(error::OS_ERROR, format!("cannot start playback thread: {e}"));
+            return 0;
+        }
+    };
+    PLAYBACKS.lock().unwrap_or_else(|e| e.into_inner()).insert(id, playback);
+    id
+}
+
+/// Stop a playback; nothing more is sent once this returns. -1 for an
+/// unknown playback.
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn midi_stop_cc_playback(pb_id: u32) -> i32 {
+    if !handles::check(pb_id, &[Kind::Playback]) {
+        return -1;
+    }
+    let playback = PLAYBACKS.lock().unwrap_or_else(|e| e.into_inner()).remove(&pb_id);
+    match playback {
+        Some(playback) => {
+            handles::release(pb_id);
+            playback.stop();
+            0
+        }
+        None => -1,
+    }
+}
+
+fn check_channel_cc(channel: u8, cc: u8) -> bool {
+    if channel > 15 {
+        error::fail(error::INVALID_ARGUMENT, format!("channel {channel} is outside 0–15"));
+        return false;
+    }
+    if cc > 127 {
+        error::fail(error::INVALID_ARGUMENT, format!("cc {cc} is outside 0–127"));
+        return false;
+    }
+    true
+}
+
+fn check_bpm(bpm: f32) -> bool {
+    if !(clock::MIN_BPM..=clock::MAX_BPM).contains(&bpm) {
+        error::fail(
+            error::INVALID_ARGUMENT,
+            format!("{bpm} BPM is outside {}–{}", clock::MIN_BPM, clock::MAX_BPM),
+        );
+        return false;
+    }
+    true
+}
+
+/// Send MIDI timing clock (0xF8, 24 pulses per quarter note) at `bpm` to an
+/// output (or pool) until stopped; transport messages are left to the
+/// caller. Returns a clock id, or 0 if the output is unknown, `bpm` is
+/// outside 1–999 (INVALID_ARGUMENT) or the clock thread cannot be started.
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn midi_clock_start(output_handle: u32, bpm: f32) -> u32 {
+    if !handles::check(output_handle, &[Kind::Output, Kind::Pool]) || !check_bpm(bpm) {
+        return 0;
+    }
+    let id = handles::issue(Kind::Clock);
+    if id == 0 {
+        return 0;
+    }
+    let read_cc = |sync: &CcSync| {
+        INPUTS
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&sync.input)
+            .map(|input| input.cc(sync.channel, sync.cc))
+    };
+    let clock = match ClockGenerator::start(bpm, read_cc, move |bytes| send_output(output_handle, bytes) == 0) {
+        Ok(clock) => clock,
+        Err(e) => {
+            handles::release(id);
+            error::fail(error::OS_ERROR, format!("cannot start clock thread: {e}"));
+            return 0;
+        }
+    };
+    CLOCKS.lock().unwrap_or_else(|e| e.into_inner()).insert(id, clock);
+    id
+}
+
+/// Set a clock's tempo, replacing any CC sync. -1 for an unknown clock or a
+/// tempo outside 1–999.
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn midi_clock_set_bpm(clock_handle: u32, bpm: f32) -> i32 {
+    if !handles::check(clock_handle, &[Kind::Clock]) || !check_bpm(bpm) {
+        return -1;
+    }
+    match CLOCKS.lock().unwrap_or_else(|e| e.into_inner()).get(&clock_handle) {
+        Some(clock) => {
+            clock.set_bpm(bpm);
+            0
+        }
+        None => -1,
+    }
+}
+
+/// Drive a clock's tempo from CC `cc` on `channel` of `input_handle`: 0 maps
+/// to `min_bpm` and 127 to `max_bpm`, linearly (`max_bpm` may be the lower).
+/// The value the input last coalesced is read before every pulse, so a
+/// knob sets the tempo within one dispatch tick. If the input closes, the
+/// last tempo is kept. `input_handle` 0 removes the sync. -1 for an unknown
+/// handle or an argument out of range (INVALID_ARGUMENT).
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn midi_clock_set_cc_sync(
+    clock_handle: u32,
+    input_handle: u32,
+    channel: u8,
+    cc: u8,
+    min_bpm: f32,
+    max_bpm: f32,
+) -> i32 {
+    if !handles::check(clock_handle, &[Kind::Clock]) {
+        return -1;
+    }
+    let sync = if input_handle == 0 {
+        None
+    } else {
+        if !handles::check(input_handle, &[Kind::Input]) || !check_bpm(min_bpm) || !check_bpm(max_bpm) {
+            return -1;
+        }
+        if channel > 15 || cc > 127 {
+            error::fail(error::INVALID_ARGUMENT, format!("channel {channel} / controller {cc} out of range"));
+            return -1;
+        }
+        Some(CcSync {
+            input: input_handle,
+            channel,
+            cc,
+            min_bpm,
+            max_bpm,
+        })
+    };
+    match CLOCKS.lock().unwrap_or_else(|e| e.into_inner()).get(&clock_handle) {
+        Some(clock) => {
+            clock.set_cc_sync(sync);
+            0
+        }
+        None => -1,
+    }
+}
+
+/// A clock's current tempo, or -1.0 for an unknown clock.
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn midi_clock_get_bpm(clock_handle: u32) -> f32 {
+    if !handles::check(clock_handle, &[Kind::Clock]) {
+        return -1.0;
+    }
+    match CLOCKS.lock().unwrap_or_else(|e| e.into_inner()).get(&clock_handle) {
+        Some(clock) => clock.bpm(),
+        None => -1.0,
+    }
+}
+
+/// Stop a clock; no further pulse is sent once this returns. -1 for an
+/// unknown clock.
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn midi_clock_stop(clock_handle: u32) -> i32 {
+    if !handles::check(clock_handle, &[Kind::Clock]) {
+        return -1;
+    }
+    let clock = CLOCKS.lock().unwrap_or_else(|e| e.into_inner()).remove(&clock_handle);
+    match clock {
+        Some(clock) => {
+            handles::release(clock_handle);
+            clock.stop();
+            0
+        }
+        None => -1,
+    }
+}
+
+/// Version of the exported C ABI. Bump whenever an export is added, removed or
+/// changes signature, and refresh `abi.txt` (midi/tests/06_abi_snapshot.ts).
+pub const ABI_VERSION: u32 = 20;
+
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn midi_abi_version() -> u32 {
+    ABI_VERSION
+}
+
+/// Features available in this build as a JSON object of booleans. Returns the
+/// byte length; nothing is written if `out_cap` is too small.
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn midi_capabilities(out_ptr: *mut u8, out_cap: u32) -> u32 {
+    let caps = serde_json::json!({
+        "sysex": false,
+        "virtual_ports": false,
+        "binary_events": true,
+        "latency_summary": true,
+        "note_duration": true,
+        "monotonic_raw": cfg!(target_os = "linux"),
+        "retry_open": true,
+        "output_pools": true,
+        "pipelines": true,
+        "scale_quantise": true,
+        "cc_automation": true,
+        "log_callback": true,
+        "last_error": true,
+        "decode_frame_json": true,
+        "cc_display_formats": true,
+        "msc": true,
+        "mpe_negotiate": true,
+        "osc_tunnel": true,
+        "normalise_cc": true,
+        "shutdown_all": true,
+        "note_expression": true,
+        "mock_input": true,
+        "cc_lock": true,
+        "mmc": true,
+        "bandwidth": true,
+        "cc14": true,
+        "clock_generator": true,
+        "sysex_request": true,
+        "tracing": avtools_trace::AVAILABLE,
+    });
+    write_json_buffer(serde_json::to_vec(&caps).unwrap_or_default(), out_ptr, out_cap)
+}
+
+/// Pretty-print any avtools_frame packet (e.g. one handed to an input
+/// callback) as JSON, for debugging. Returns the byte length, 0 if the bytes
+/// are not a frame; nothing is written if `out_cap` is too small.
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn midi_decode_frame_json(
+    ptr: *const u8,
+    len: u32,
+    out_ptr: *mut u8,
+    out_cap: u32,
+) -> u32 {
+    avtools_frame::decode_json_export(ptr, len, out_ptr, out_cap, &[&packet::SCHEMA])
+}
+
+/// Code of the calling thread's last failed call (0 if the last fallible call
+/// succeeded). midi_bridge codes are 200–299; see avtools_ffi::error.
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn midi_last_error_code() -> i32 {
+    error::code()
+}
+
+/// Message for midi_last_error_code. Returns the byte length (0 if none);
+/// nothing is written if `out_cap` is too small.
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn midi_last_error_message(out_ptr: *mut u8, out_cap: u32) -> u32 {
+    error::message(out_ptr, out_cap)
+}
+
+/// Message of the last export that panicked on this thread (the library keeps
+/// working; the export returned its failure value). Unlike the last error it
+/// survives later calls. Returns the byte length, 0 if nothing has panicked.
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn midi_get_last_panic(buf_ptr: *mut u8, buf_cap: u32) -> u32 {
+    avtools_ffi::panic::last_panic(buf_ptr, buf_cap)
+}
+
+/// Route this library's log messages to `cb` instead of stderr; null restores
+/// stderr. `cb` is called from the dispatch, coalescer and retry threads, and
+/// is never called again once this returns with a different callback.
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn midi_set_log_callback(cb: Option<extern "C" fn(u32, *const u8, u32)>) {
+    avtools_log::set_callback(cb);
+}
+
+/// Most verbose level logged: 0 off, 1 error, 2 warn, 3 info (default),
+/// 4 debug. Returns -1 for an unknown level.
+#[no_mangle]
+#[catch_panic]
+pub unsafe extern "C" fn midi_set_log_level(level: u32) -> i32 {
+    avtools_log::set_level(level)
+}
+
+fn write_json_buffer(bytes: Vec<u8>, out_ptr: *mut u8, out_cap: u32) -> u32 {
+    unsafe { buffer::write(&bytes, out_ptr, out_cap) }
 }
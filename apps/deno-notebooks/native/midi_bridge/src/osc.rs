@@ -0,0 +1,163 @@
+//! Structural validation of OSC 1.0 packets, enough to reject bytes that are
+//! not OSC before they are tunnelled or delivered. Arguments are walked by
+//! their type tags but not decoded.
+
+const BUNDLE_TAG: &[u8] = b"#bundle\0";
+
+/// Bundles nested deeper than this are rejected rather than recursed into.
+const MAX_BUNDLE_DEPTH: usize = 8;
+
+/// Ok if `packet` is a well-formed OSC message or bundle; otherwise why not.
+pub fn validate(packet: &[u8]) -> Result<(), String> {
+    validate_packet(packet, 0)
+}
+
+fn validate_packet(packet: &[u8], depth: usize) -> Result<(), String> {
+    if packet.is_empty() || !packet.len().is_multiple_of(4) {
+        return Err(format!(
+            "OSC packet length {} is not a positive multiple of 4",
+            packet.len()
+        ));
+    }
+    if packet.starts_with(BUNDLE_TAG) {
+        validate_bundle(packet, depth)
+    } else {
+        validate_message(packet)
+    }
+}
+
+fn validate_bundle(packet: &[u8], depth: usize) -> Result<(), String> {
+    if depth >= MAX_BUNDLE_DEPTH {
+        return Err("OSC bundles nested too deeply".to_string());
+    }
+    // "#bundle\0" then the 8-byte time tag.
+    let mut rest = packet
+        .get(16..)
+        .ok_or_else(|| "OSC bundle is missing its time tag".to_string())?;
+    while !rest.is_empty() {
+        let (size, tail) =
+            take_u32(rest).ok_or_else(|| "truncated OSC bundle element size".to_string())?;
+        let element = tail
+            .get(..size as usize)
+            .ok_or_else(|| "OSC bundle element runs past the end".to_string())?;
+        validate_packet(element, depth + 1)?;
+        rest = &tail[size as usize..];
+    }
+    Ok(())
+}
+
+fn validate_message(packet: &[u8]) -> Result<(), String> {
+    let (address, rest) =
+        take_string(packet).ok_or_else(|| "unterminated OSC address".to_string())?;
+    if !address.starts_with(b"/") {
+        return Err("OSC address must start with '/'".to_string());
+    }
+    let (tags, mut rest) =
+        take_string(rest).ok_or_else(|| "missing OSC type tag string".to_string())?;
+    let Some((b',', tags)) = tags.split_first() else {
+        return Err("OSC type tag string must start with ','".to_string());
+    };
+    let mut array_depth = 0usize;
+    for &tag in tags {
+        rest = match tag {
+            b'i' | b'f' | b'c' | b'r' | b'm' => rest.get(4..),
+            b'h' | b'd' | b't' => rest.get(8..),
+            b's' | b'S' => take_string(rest).map(|(_, tail)| tail),
+            b'b' => take_u32(rest).and_then(|(len, tail)| tail.get(padded(len as usize)..)),
+            b'T' | b'F' | b'N' | b'I' => Some(rest),
+            b'[' => {
+                array_depth += 1;
+                Some(rest)
+            }
+            b']' if array_depth > 0 => {
+                array_depth -= 1;
+                Some(rest)
+            }
+            _ => return Err(format!("unsupported OSC type tag '{}'", tag.escape_ascii())),
+        }
+        .ok_or_else(|| format!("OSC argument '{}' runs past the end", tag.escape_ascii()))?;
+    }
+    if array_depth != 0 {
+        return Err("unclosed OSC array".to_string());
+    }
+    if !rest.is_empty() {
+        return Err(format!("{} bytes after the last OSC argument", rest.len()));
+    }
+    Ok(())
+}
+
+/// A NUL-terminated string padded to 4 bytes (without the NULs), and what
+/// follows it.
+fn take_string(bytes: &[u8]) -> Option<(&[u8], &[u8])> {
+    let len = bytes.iter().position(|&b| b == 0)?;
+    let tail = bytes.get(padded(len + 1)..)?;
+    Some((&bytes[..len], tail))
+}
+
+fn take_u32(bytes: &[u8]) -> Option<(u32, &[u8])> {
+    let (head, tail) = bytes.split_first_chunk::<4>()?;
+    Some((u32::from_be_bytes(*head), tail))
+}
+
+fn padded(len: usize) -> usize {
+    len.div_ceil(4) * 4
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn osc_string(s: &str) -> Vec<u8> {
+        let mut bytes = s.as_bytes().to_vec();
+        bytes.resize(padded(s.len() + 1), 0);
+        bytes
+    }
+
+    fn message(tags: &str, args: &[u8]) -> Vec<u8> {
+        [osc_string("/a"), osc_string(tags), args.to_vec()].concat()
+    }
+
+    fn bundle(elements: &[Vec<u8>]) -> Vec<u8> {
+        let mut bytes = [BUNDLE_TAG, &[0; 8]].concat();
+        for element in elements {
+            bytes.extend_from_slice(&(element.len() as u32).to_be_bytes());
+            bytes.extend_from_slice(element);
+        }
+        bytes
+    }
+
+    #[test]
+    fn blobs_must_fit_in_the_packet() {
+        let blob = [&5u32.to_be_bytes()[..], &[1, 2, 3, 4, 5, 0, 0, 0]].concat();
+        assert_eq!(validate(&message(",b", &blob)), Ok(()));
+
+        let truncated = [&8u32.to_be_bytes()[..], &[1, 2, 3, 4]].concat();
+        let err = validate(&message(",b", &truncated)).unwrap_err();
+        assert!(err.contains("runs past the end"), "{err}");
+    }
+
+    #[test]
+    fn arrays_must_be_closed() {
+        assert_eq!(validate(&message(",[i]", &[0; 4])), Ok(()));
+        assert_eq!(validate(&message(",[i", &[0; 4])), Err("unclosed OSC array".to_string()));
+        assert!(validate(&message(",i]", &[0; 4])).is_err());
+    }
+
+    #[test]
+    fn nested_bundles_are_walked_to_a_limit() {
+        let mut nested = bundle(&[message(",i", &[0; 4])]);
+        for _ in 1..MAX_BUNDLE_DEPTH {
+            nested = bundle(&[nested]);
+        }
+        assert_eq!(validate(&nested), Ok(()));
+        assert_eq!(validate(&bundle(&[nested])), Err("OSC bundles nested too deeply".to_string()));
+
+        // A bad message deep inside fails the whole packet.
+        let bad = bundle(&[bundle(&[message(",i", &[])])]);
+        assert!(validate(&bad).is_err());
+
+        let mut overrun = bundle(&[message(",", &[])]);
+        overrun[16..20].copy_from_slice(&64u32.to_be_bytes());
+        assert_eq!(validate(&overrun), Err("OSC bundle element runs past the end".to_string()));
+    }
+}
@@ -1,25 +1,85 @@
+use avtools_ffi::{error, Error};
 use midir::{MidiOutput, MidiOutputConnection};
 
+use crate::{ERR_MIDI_INIT, ERR_PORT_CONNECT, ERR_SEND};
+
 pub struct OutputHandle {
     conn: MidiOutputConnection,
+    /// Notes sounding per channel, as a bitset of note numbers.
+    held: [u128; 16],
 }
 
 impl OutputHandle {
-    pub fn open(port_id: &str) -> Result<Self, String> {
+    pub fn open(port_id: &str) -> Result<Self, Error> {
         let midi_out = MidiOutput::new("midi-bridge-out")
-            .map_err(|e| format!("midi output init failed: {e:?}"))?;
+            .map_err(|e| Error::new(ERR_MIDI_INIT, format!("midi output init failed: {e:?}")))?;
         let port = midi_out
             .find_port_by_id(port_id.to_string())
-            .ok_or_else(|| "output port not found".to_string())?;
+            .ok_or_else(|| Error::new(error::NOT_FOUND, format!("output port not found: {port_id}")))?;
         let conn = midi_out
             .connect(&port, "midi-bridge-out")
-            .map_err(|e| format!("output connect failed: {e:?}"))?;
-        Ok(Self { conn })
+            .map_err(|e| Error::new(ERR_PORT_CONNECT, format!("output connect failed: {e:?}")))?;
+        Ok(Self { conn, held: [0; 16] })
     }
 
-    pub fn send(&mut self, bytes: &[u8]) -> Result<(), String> {
+    pub fn send(&mut self, bytes: &[u8]) -> Result<(), Error> {
         self.conn
             .send(bytes)
-            .map_err(|e| format!("send failed: {e:?}"))
+            .map_err(|e| Error::new(ERR_SEND, format!("send failed: {e:?}")))?;
+        self.track_notes(bytes);
+        Ok(())
+    }
+
+    /// Follow note-ons and note-offs sent one message per call (as every
+    /// send path here does) so held notes can be released at shutdown.
+    fn track_notes(&mut self, bytes: &[u8]) {
+        let &[status, note, velocity] = bytes else {
+            return;
+        };
+        let held = &mut self.held[(status & 0x0F) as usize];
+        let bit = 1u128 << (note & 0x7F);
+        match status & 0xF0 {
+            0x90 if velocity != 0 => *held |= bit,
+            0x80 | 0x90 => *held &= !bit,
+            _ => {}
+        }
+    }
+
+    /// Send a note-off for every held note, then All Notes Off on every
+    /// channel. Returns the number of held notes released.
+    pub fn release_held_notes(&mut self) -> u32 {
+        let mut released = 0;
+        for channel in 0..16u8 {
+            let held = std::mem::take(&mut self.held[channel as usize]);
+            for note in (0..128u8).filter(|n| held & (1u128 << n) != 0) {
+                let _ = self.conn.send(&[0x80 | channel, note, 0]);
+                released += 1;
+            }
+            let _ = self.conn.send(&[0xB0 | channel, 123, 0]);
+        }
+        released
+    }
+}
+
+/// Several output handles behind one pool handle. Each send goes to the next
+/// member in turn.
+pub struct OutputPool {
+    members: Vec<u32>,
+    next: usize,
+}
+
+impl OutputPool {
+    pub fn new(members: Vec<u32>) -> Self {
+        Self { members, next: 0 }
+    }
+
+    pub fn members(&self) -> &[u32] {
+        &self.members
+    }
+
+    pub fn next_member(&mut self) -> Option<u32> {
+        let member = *self.members.get(self.next % self.members.len().max(1))?;
+        self.next = (self.next + 1) % self.members.len();
+        Some(member)
     }
 }
@@ -1,5 +1,10 @@
+use crate::stats::PacketLatency;
+use avtools_frame::{BlockSchema, FieldType, Fields, FrameWriter, Header, Schema};
+
 pub const MAGIC: u32 = 0x4D494452; // "MIDR"
-pub const VERSION: u16 = 1;
+/// 2 added the flag-gated blocks after the records (PACKET_FLAG_LATENCY);
+/// a packet with no flags set is laid out as in version 1.
+pub const VERSION: u16 = 2;
 
 pub const KIND_CC: u8 = 1;
 pub const KIND_PB: u8 = 2;
@@ -7,6 +12,26 @@ pub const KIND_CH_PRESS: u8 = 3;
 pub const KIND_POLY_PRESS: u8 = 4;
 pub const KIND_PROG: u8 = 5;
 pub const KIND_NOTE: u8 = 6;
+pub const KIND_NOTE_COMPLETE: u8 = 15;
+/// Sent once when a retrying input finally connects; `extra` = attempts taken.
+pub const KIND_CONNECTED: u8 = 16;
+/// MIDI Show Control message; see sysex::parse_msc.
+pub const KIND_MSC: u8 = 17;
+/// OSC packet tunnelled in SysEx; see sysex::parse_osc_tunnel. channel =
+/// device id, extra = packet length; the bytes are in a TLV_OSC extension.
+pub const KIND_OSC: u8 = 18;
+/// Per-note parameter matched by a registered note expression; see
+/// sysex::NoteExpression.
+pub const KIND_NOTE_EXP: u8 = 19;
+/// MIDI Machine Control command; see sysex::parse_mmc.
+pub const KIND_MMC: u8 = 20;
+
+/// Header flag: a latency extension block follows the records.
+pub const PACKET_FLAG_LATENCY: u16 = 0x1;
+
+/// TLV extension holding one KIND_OSC record's packet; the n-th KIND_OSC
+/// record's bytes are in the n-th TLV_OSC extension.
+pub const TLV_OSC: u16 = 1;
 
 #[derive(Clone, Copy)]
 pub struct Record {
@@ -17,50 +42,78 @@ pub struct Record {
     pub b: u8,
     pub v16: i16,
     pub extra: u16,
+    /// Arrival time on the dispatcher clock; used for latency stats, not encoded.
+    pub arrival_us: u64,
 }
 
+/// Record and latency-block layout, for midi_decode_frame_json.
+pub static SCHEMA: Schema = Schema {
+    name: "midi_bridge",
+    magic: MAGIC,
+    aux: ["dropped_raw", "dropped_note"],
+    record: &[
+        ("ts_us", FieldType::U64),
+        ("kind", FieldType::U8),
+        ("channel", FieldType::U8),
+        ("a", FieldType::U8),
+        ("b", FieldType::U8),
+        ("v16", FieldType::I16),
+        ("extra", FieldType::U16),
+    ],
+    blocks: &[BlockSchema {
+        flag: PACKET_FLAG_LATENCY,
+        name: "latency",
+        fields: &[
+            ("min_us", FieldType::U32),
+            ("max_us", FieldType::U32),
+            ("mean_us", FieldType::U32),
+            ("count", FieldType::U32),
+        ],
+    }],
+};
+
 pub fn encode_packet(
     records: &Vec<Record>,
     dispatch_ts_us: u64,
     dropped_raw: u32,
     dropped_note: u32,
     flags: u16,
+    latency: Option<PacketLatency>,
+    osc: &[&[u8]],
 ) -> Vec<u8> {
-    let mut buf = Vec::with_capacity(32 + records.len() * 16);
-    push_u32(&mut buf, MAGIC);
-    push_u16(&mut buf, VERSION);
-    push_u16(&mut buf, flags);
-    push_u64(&mut buf, dispatch_ts_us);
-    push_u32(&mut buf, dropped_raw);
-    push_u32(&mut buf, dropped_note);
-    push_u32(&mut buf, records.len() as u32);
-    push_u32(&mut buf, 0);
-
+    let header = Header {
+        magic: MAGIC,
+        version: VERSION,
+        flags,
+        ts_us: dispatch_ts_us,
+        aux: [dropped_raw, dropped_note],
+        count: 0,
+    };
+    let mut frame = FrameWriter::new(header, records.len());
     for r in records {
-        push_u64(&mut buf, r.ts_us);
-        buf.push(r.kind);
-        buf.push(r.channel);
-        buf.push(r.a);
-        buf.push(r.b);
-        push_i16(&mut buf, r.v16);
-        push_u16(&mut buf, r.extra);
+        frame.record(
+            Fields::new()
+                .u64(r.ts_us)
+                .u8(r.kind)
+                .u8(r.channel)
+                .u8(r.a)
+                .u8(r.b)
+                .i16(r.v16)
+                .u16(r.extra)
+                .build(),
+        );
     }
-
-    buf
-}
-
-fn push_u16(buf: &mut Vec<u8>, v: u16) {
-    buf.extend_from_slice(&v.to_le_bytes());
-}
-
-fn push_u32(buf: &mut Vec<u8>, v: u32) {
-    buf.extend_from_slice(&v.to_le_bytes());
-}
-
-fn push_u64(buf: &mut Vec<u8>, v: u64) {
-    buf.extend_from_slice(&v.to_le_bytes());
-}
-
-fn push_i16(buf: &mut Vec<u8>, v: i16) {
-    buf.extend_from_slice(&v.to_le_bytes());
+    if let Some(l) = latency {
+        let block = Fields::new()
+            .u32(l.min_us)
+            .u32(l.max_us)
+            .u32(l.mean_us)
+            .u32(l.count)
+            .build();
+        frame.block(PACKET_FLAG_LATENCY, block);
+    }
+    for packet in osc {
+        frame.extension(TLV_OSC, packet);
+    }
+    frame.finish()
 }
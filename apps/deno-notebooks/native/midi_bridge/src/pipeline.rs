@@ -0,0 +1,144 @@
+use std::sync::Mutex;
+
+/// Channel argument meaning "every channel" for channel-scoped transforms.
+pub const ALL_CHANNELS: u8 = 16;
+
+#[derive(Clone, Copy)]
+pub enum Transform {
+    /// Shift note numbers (note on/off, poly pressure); notes pushed outside
+    /// 0–127 are dropped.
+    Transpose {
+        channel: u8,
+        semitones: i8,
+    },
+    ChannelRemap {
+        from: u8,
+        to: u8,
+    },
+    CcRemap {
+        from_channel: u8,
+        from_cc: u8,
+        to_channel: u8,
+        to_cc: u8,
+    },
+    /// Scale note-on velocity by `percent`, clamped to 1–127 so a note-on never
+    /// turns into a note-off.
+    VelocityScale {
+        channel: u8,
+        percent: u16,
+    },
+}
+
+fn matches(filter: u8, channel: u8) -> bool {
+    filter == ALL_CHANNELS || filter == channel
+}
+
+impl Transform {
+    /// Apply to a channel voice message; None drops it.
+    fn apply(&self, msg: [u8; 3]) -> Option<[u8; 3]> {
+        let [status, data1, data2] = msg;
+        let kind = status & 0xF0;
+        let channel = status & 0x0F;
+        match *self {
+            Transform::Transpose {
+                channel: c,
+                semitones,
+            } => {
+                if !matches(c, channel) || !matches!(kind, 0x80 | 0x90 | 0xA0) {
+                    return Some(msg);
+                }
+                let note = data1 as i16 + semitones as i16;
+                if !(0..=127).contains(&note) {
+                    return None;
+                }
+                Some([status, note as u8, data2])
+            }
+            Transform::ChannelRemap { from, to } => {
+                if channel != from {
+                    return Some(msg);
+                }
+                Some([kind | to, data1, data2])
+            }
+            Transform::CcRemap {
+                from_channel,
+                from_cc,
+                to_channel,
+                to_cc,
+            } => {
+                if kind != 0xB0 || channel != from_channel || data1 != from_cc {
+                    return Some(msg);
+                }
+                Some([0xB0 | to_channel, to_cc, data2])
+            }
+            Transform::VelocityScale {
+                channel: c,
+                percent,
+            } => {
+                if !matches(c, channel) || kind != 0x90 || data2 == 0 {
+                    return Some(msg);
+                }
+                let velocity = (data2 as u32 * percent as u32 / 100).clamp(1, 127);
+                Some([status, data1, velocity as u8])
+            }
+        }
+    }
+}
+
+/// Ordered transforms applied to every channel message of the attached
+/// inputs, optionally echoed (transformed) to thru outputs.
+pub struct Pipeline {
+    transforms: Mutex<Vec<Transform>>,
+    outputs: Mutex<Vec<u32>>,
+    inputs: Mutex<Vec<u32>>,
+}
+
+impl Pipeline {
+    pub fn new() -> Self {
+        Self {
+            transforms: Mutex::new(Vec::new()),
+            outputs: Mutex::new(Vec::new()),
+            inputs: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn push(&self, transform: Transform) {
+        self.transforms.lock().unwrap_or_else(|e| e.into_inner()).push(transform);
+    }
+
+    pub fn clear(&self) {
+        self.transforms.lock().unwrap_or_else(|e| e.into_inner()).clear();
+    }
+
+    pub fn attach_output(&self, output: u32) {
+        let mut outputs = self.outputs.lock().unwrap_or_else(|e| e.into_inner());
+        if !outputs.contains(&output) {
+            outputs.push(output);
+        }
+    }
+
+    pub fn add_input(&self, input: u32) {
+        let mut inputs = self.inputs.lock().unwrap_or_else(|e| e.into_inner());
+        if !inputs.contains(&input) {
+            inputs.push(input);
+        }
+    }
+
+    pub fn inputs(&self) -> Vec<u32> {
+        self.inputs.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Run `msg` through every transform in order and send the result to the
+    /// thru outputs. None if a transform dropped it.
+    pub fn process(&self, msg: [u8; 3], len: u8) -> Option<[u8; 3]> {
+        let mut out = msg;
+        for transform in self.transforms.lock().unwrap_or_else(|e| e.into_inner()).iter() {
+            out = transform.apply(out)?;
+        }
+        let outputs = self.outputs.lock().unwrap_or_else(|e| e.into_inner()).clone();
+        let len = (len as usize).clamp(1, 3);
+        for output in outputs {
+            crate::send_output(output, &out[..len]);
+        }
+        Some(out)
+    }
+}
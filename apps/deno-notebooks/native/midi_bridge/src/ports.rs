@@ -10,7 +10,10 @@ struct PortInfo {
 pub fn list_inputs_json() -> Vec<u8> {
     let midi_in = match MidiInput::new("midi-bridge-list") {
         Ok(m) => m,
-        Err(_) => return b"[]".to_vec(),
+        Err(err) => {
+            avtools_log::error!("cannot list MIDI ports: {err:?}");
+            return b"[]".to_vec();
+        }
     };
     let ports = midi_in.ports();
     let mut infos = Vec::with_capacity(ports.len());
@@ -27,7 +30,10 @@ pub fn list_inputs_json() -> Vec<u8> {
 pub fn list_outputs_json() -> Vec<u8> {
     let midi_out = match MidiOutput::new("midi-bridge-list") {
         Ok(m) => m,
-        Err(_) => return b"[]".to_vec(),
+        Err(err) => {
+            avtools_log::error!("cannot list MIDI ports: {err:?}");
+            return b"[]".to_vec();
+        }
     };
     let ports = midi_out.ports();
     let mut infos = Vec::with_capacity(ports.len());
@@ -0,0 +1,170 @@
+//! Registered and non-registered parameter numbers: CC 101/100 (RPN) or
+//! 99/98 (NRPN) select a parameter, CC 6/38 write its value. Also the MPE
+//! pitch bend range negotiation built on the decoder.
+
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+const CC_DATA_ENTRY_MSB: u8 = 6;
+const CC_DATA_ENTRY_LSB: u8 = 38;
+const CC_NRPN_LSB: u8 = 98;
+const CC_NRPN_MSB: u8 = 99;
+const CC_RPN_LSB: u8 = 100;
+const CC_RPN_MSB: u8 = 101;
+
+/// Parameter number 127/127 deselects ("null RPN").
+const NULL_PARAM: u16 = 0x3FFF;
+
+pub const RPN_PITCH_BEND_RANGE: u16 = 0;
+
+/// Pitch bend range, in semitones, MPE requires on member channels.
+pub const MPE_MEMBER_BEND_RANGE: u8 = 48;
+
+/// Member channels of an MPE lower zone spanning every channel (master
+/// channel 1, 0-based 0).
+pub const MPE_MEMBER_CHANNELS: std::ops::RangeInclusive<u8> = 1..=15;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Param {
+    Registered(u16),
+    NonRegistered(u16),
+}
+
+#[derive(Clone, Copy, Default)]
+struct Selection {
+    registered: bool,
+    msb: Option<u8>,
+    lsb: Option<u8>,
+    value_msb: u8,
+}
+
+impl Selection {
+    fn param(&self) -> Option<Param> {
+        let number = ((self.msb? as u16) << 7) | self.lsb? as u16;
+        match (number, self.registered) {
+            (NULL_PARAM, _) => None,
+            (number, true) => Some(Param::Registered(number)),
+            (number, false) => Some(Param::NonRegistered(number)),
+        }
+    }
+
+    fn select(&mut self, registered: bool, msb: Option<u8>, lsb: Option<u8>) {
+        if self.registered != registered {
+            *self = Selection::default();
+        }
+        self.registered = registered;
+        if msb.is_some() {
+            self.msb = msb;
+        }
+        if lsb.is_some() {
+            self.lsb = lsb;
+        }
+    }
+}
+
+/// Per-channel parameter selection state of one input.
+pub struct Decoder {
+    channels: [Selection; 16],
+}
+
+impl Decoder {
+    pub fn new() -> Self {
+        Self {
+            channels: [Selection::default(); 16],
+        }
+    }
+
+    /// Feed one control change. Returns the selected parameter and its 14-bit
+    /// value (MSB << 7 | LSB) when a data entry CC writes to it; a data entry
+    /// MSB alone reports an LSB of 0.
+    pub fn control_change(&mut self, channel: u8, cc: u8, value: u8) -> Option<(Param, u16)> {
+        let sel = &mut self.channels[(channel & 0x0F) as usize];
+        match cc {
+            CC_RPN_MSB => sel.select(true, Some(value), None),
+            CC_RPN_LSB => sel.select(true, None, Some(value)),
+            CC_NRPN_MSB => sel.select(false, Some(value), None),
+            CC_NRPN_LSB => sel.select(false, None, Some(value)),
+            CC_DATA_ENTRY_MSB => {
+                sel.value_msb = value;
+                return sel.param().map(|p| (p, (value as u16) << 7));
+            }
+            CC_DATA_ENTRY_LSB => {
+                return sel
+                    .param()
+                    .map(|p| (p, ((sel.value_msb as u16) << 7) | value as u16));
+            }
+            _ => {}
+        }
+        None
+    }
+}
+
+/// CC messages that set RPN 0 (pitch bend range) to `semitones` on `channel`
+/// and then deselect it.
+pub fn pitch_bend_range_messages(channel: u8, semitones: u8) -> [[u8; 3]; 6] {
+    let status = 0xB0 | (channel & 0x0F);
+    [
+        [status, CC_RPN_MSB, 0],
+        [status, CC_RPN_LSB, 0],
+        [status, CC_DATA_ENTRY_MSB, semitones],
+        [status, CC_DATA_ENTRY_LSB, 0],
+        [status, CC_RPN_MSB, 0x7F],
+        [status, CC_RPN_LSB, 0x7F],
+    ]
+}
+
+/// Channels waiting for an instrument to answer with the MPE pitch bend
+/// range, and which have.
+pub struct MpeNegotiation {
+    pending: u16,
+    acked: Mutex<u16>,
+    changed: Condvar,
+}
+
+impl MpeNegotiation {
+    pub fn new(channels: impl IntoIterator<Item = u8>) -> Self {
+        Self {
+            pending: channels
+                .into_iter()
+                .fold(0, |mask, ch| mask | 1 << (ch & 0x0F)),
+            acked: Mutex::new(0),
+            changed: Condvar::new(),
+        }
+    }
+
+    /// A decoded parameter write from the instrument; RPN 0 set to the MPE
+    /// range on a pending channel acknowledges that channel.
+    pub fn observe(&self, channel: u8, param: Param, value: u16) {
+        let bit = 1 << (channel & 0x0F);
+        if self.pending & bit == 0
+            || param != Param::Registered(RPN_PITCH_BEND_RANGE)
+            || value >> 7 != MPE_MEMBER_BEND_RANGE as u16
+        {
+            return;
+        }
+        let mut acked = self.acked.lock().unwrap_or_else(|e| e.into_inner());
+        if *acked & bit == 0 {
+            *acked |= bit;
+            self.changed.notify_all();
+        }
+    }
+
+    /// Block until every pending channel has acknowledged or `timeout`
+    /// passes; returns how many acknowledged.
+    pub fn wait(&self, timeout: Duration) -> u32 {
+        let deadline = Instant::now() + timeout;
+        let mut acked = self.acked.lock().unwrap_or_else(|e| e.into_inner());
+        while *acked != self.pending {
+            let now = Instant::now();
+            if now >= deadline {
+                break;
+            }
+            acked = self
+                .changed
+                .wait_timeout(acked, deadline - now)
+                .unwrap_or_else(|e| e.into_inner())
+                .0;
+        }
+        acked.count_ones()
+    }
+}
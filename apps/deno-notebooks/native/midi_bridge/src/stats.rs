@@ -0,0 +1,109 @@
+use serde::Serialize;
+use std::collections::VecDeque;
+
+const LATENCY_WINDOW: usize = 4096;
+
+/// Rolling distribution of (dispatch time - arrival time) for one input.
+/// min/max cover the whole lifetime of the handle, percentiles the last
+/// `LATENCY_WINDOW` samples.
+#[derive(Clone)]
+pub struct LatencyStats {
+    window: VecDeque<u64>,
+    count: u64,
+    min_us: u64,
+    max_us: u64,
+}
+
+#[derive(Serialize)]
+pub struct LatencySnapshot {
+    pub count: u64,
+    pub min_us: u64,
+    pub median_us: u64,
+    pub p95_us: u64,
+    pub max_us: u64,
+}
+
+/// Per-packet latency summary, appended after the records when the input
+/// was opened with `FLAG_LATENCY_SUMMARY`.
+#[derive(Clone, Copy)]
+pub struct PacketLatency {
+    pub min_us: u32,
+    pub max_us: u32,
+    pub mean_us: u32,
+    pub count: u32,
+}
+
+impl LatencyStats {
+    pub fn new() -> Self {
+        Self {
+            window: VecDeque::with_capacity(LATENCY_WINDOW),
+            count: 0,
+            min_us: u64::MAX,
+            max_us: 0,
+        }
+    }
+
+    pub fn push(&mut self, latency_us: u64) {
+        if self.window.len() >= LATENCY_WINDOW {
+            self.window.pop_front();
+        }
+        self.window.push_back(latency_us);
+        self.count += 1;
+        self.min_us = self.min_us.min(latency_us);
+        self.max_us = self.max_us.max(latency_us);
+    }
+
+    pub fn snapshot(&self) -> LatencySnapshot {
+        if self.count == 0 {
+            return LatencySnapshot {
+                count: 0,
+                min_us: 0,
+                median_us: 0,
+                p95_us: 0,
+                max_us: 0,
+            };
+        }
+        let mut sorted: Vec<u64> = self.window.iter().copied().collect();
+        sorted.sort_unstable();
+        LatencySnapshot {
+            count: self.count,
+            min_us: self.min_us,
+            median_us: percentile(&sorted, 50),
+            p95_us: percentile(&sorted, 95),
+            max_us: self.max_us,
+        }
+    }
+}
+
+pub fn summarize(latencies: &[u64]) -> PacketLatency {
+    if latencies.is_empty() {
+        return PacketLatency {
+            min_us: 0,
+            max_us: 0,
+            mean_us: 0,
+            count: 0,
+        };
+    }
+    let min = latencies.iter().copied().min().unwrap_or(0);
+    let max = latencies.iter().copied().max().unwrap_or(0);
+    let sum: u64 = latencies.iter().sum();
+    let mean = sum / latencies.len() as u64;
+    PacketLatency {
+        min_us: clamp_u32(min),
+        max_us: clamp_u32(max),
+        mean_us: clamp_u32(mean),
+        count: latencies.len().min(u32::MAX as usize) as u32,
+    }
+}
+
+fn percentile(sorted: &[u64], pct: usize) -> u64 {
+    if sorted.is_empty() {
+        return 0;
+    }
+    let idx = (sorted.len() - 1) * pct / 100;
+    sorted[idx]
+}
+
+fn clamp_u32(v: u64) -> u32 {
+    v.min(u32::MAX as u64) as u32
+}
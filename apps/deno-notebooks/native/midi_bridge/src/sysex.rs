@@ -0,0 +1,269 @@
+//! Universal Real Time SysEx protocols (MIDI Show Control and MIDI Machine
+//! Control, `F0 7F <device> <sub-id> ...`) parsed into and built from
+//! records, the OSC tunnel carried in manufacturer SysEx,
+//! registered per-note expression messages, and waiting for the reply to a
+//! SysEx request.
+
+use crate::osc;
+use crate::packet::{Record, KIND_MMC, KIND_MSC, KIND_NOTE_EXP, KIND_OSC};
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+const UNIVERSAL_REAL_TIME: u8 = 0x7F;
+const SUB_ID_MSC: u8 = 0x02;
+const SUB_ID_MMC_COMMAND: u8 = 0x06;
+
+/// MMC transport commands, for midi_send_mmc and KIND_MMC records' `a`.
+pub const MMC_STOP: u8 = 0x01;
+pub const MMC_PLAY: u8 = 0x02;
+pub const MMC_DEFERRED_PLAY: u8 = 0x03;
+pub const MMC_FAST_FORWARD: u8 = 0x04;
+pub const MMC_REWIND: u8 = 0x05;
+pub const MMC_RECORD_STROBE: u8 = 0x06;
+pub const MMC_RECORD_EXIT: u8 = 0x07;
+pub const MMC_PAUSE: u8 = 0x09;
+pub const MMC_EJECT: u8 = 0x0A;
+
+/// The commands midi_send_mmc sends.
+pub const MMC_TRANSPORT_COMMANDS: [u8; 9] = [
+    MMC_STOP,
+    MMC_PLAY,
+    MMC_DEFERRED_PLAY,
+    MMC_FAST_FORWARD,
+    MMC_REWIND,
+    MMC_RECORD_STROBE,
+    MMC_RECORD_EXIT,
+    MMC_PAUSE,
+    MMC_EJECT,
+];
+
+/// Manufacturer id the OSC tunnel is sent under.
+const OSC_TUNNEL_ID: [u8; 3] = [0x00, 0x21, 0x09];
+
+/// Device id that addresses every device ("all-call"). As our own id it
+/// accepts messages for any device.
+pub const ALL_CALL: u8 = 0x7F;
+
+/// MIDI Show Control: `F0 7F <device> 02 <command_format> <command> <data> F7`.
+/// Returns a KIND_MSC record (channel = device id, a = command format,
+/// b = command, extra = number of data bytes) if `msg` is MSC addressed to
+/// `device_id` or to all-call.
+pub fn parse_msc(msg: &[u8], device_id: u8, ts_us: u64, arrival_us: u64) -> Option<Record> {
+    let [0xF0, UNIVERSAL_REAL_TIME, device, SUB_ID_MSC, format, command, data @ ..] = msg else {
+        return None;
+    };
+    if device_id != ALL_CALL && *device != device_id && *device != ALL_CALL {
+        return None;
+    }
+    let data = data.strip_suffix(&[0xF7]).unwrap_or(data);
+    Some(Record {
+        ts_us,
+        kind: KIND_MSC,
+        channel: *device,
+        a: *format,
+        b: *command,
+        v16: 0,
+        extra: data.len().min(u16::MAX as usize) as u16,
+        arrival_us,
+    })
+}
+
+/// MIDI Machine Control command: `F0 7F <device> 06 <command> <data> F7`.
+/// Returns a KIND_MMC record (channel = device id, a = command, extra =
+/// number of data bytes, for commands such as LOCATE that carry some) from
+/// any device.
+pub fn parse_mmc(msg: &[u8], ts_us: u64, arrival_us: u64) -> Option<Record> {
+    let [0xF0, UNIVERSAL_REAL_TIME, device, SUB_ID_MMC_COMMAND, command, data @ ..] = msg else {
+        return None;
+    };
+    let data = data.strip_suffix(&[0xF7]).unwrap_or(data);
+    Some(Record {
+        ts_us,
+        kind: KIND_MMC,
+        channel: *device,
+        a: *command,
+        b: 0,
+        v16: 0,
+        extra: data.len().min(u16::MAX as usize) as u16,
+        arrival_us,
+    })
+}
+
+/// A data-less MMC command for `device_id` (ALL_CALL for every device).
+pub fn mmc_message(device_id: u8, command: u8) -> [u8; 6] {
+    [0xF0, UNIVERSAL_REAL_TIME, device_id & 0x7F, SUB_ID_MMC_COMMAND, command & 0x7F, 0xF7]
+}
+
+/// Wrap an OSC packet for the tunnel: `F0 00 21 09 <device> <data> F7`. SysEx
+/// data bytes are 7-bit, so the OSC bytes are packed in groups of up to 7,
+/// each group preceded by a byte holding their top bits (bit n for byte n).
+pub fn osc_tunnel_message(osc: &[u8], device_id: u8) -> Vec<u8> {
+    let mut msg = Vec::with_capacity(6 + osc.len() + osc.len().div_ceil(7));
+    msg.push(0xF0);
+    msg.extend_from_slice(&OSC_TUNNEL_ID);
+    msg.push(device_id & 0x7F);
+    for group in osc.chunks(7) {
+        let high_bits = group
+            .iter()
+            .enumerate()
+            .fold(0, |bits, (i, &b)| bits | ((b >> 7) << i));
+        msg.push(high_bits);
+        msg.extend(group.iter().map(|&b| b & 0x7F));
+    }
+    msg.push(0xF7);
+    msg
+}
+
+/// A KIND_OSC record (channel = device id, extra = packet length) and the
+/// OSC packet, if `msg` is a tunnelled message (see `osc_tunnel_message`)
+/// that unpacks to valid OSC.
+pub fn parse_osc_tunnel(msg: &[u8], ts_us: u64, arrival_us: u64) -> Option<(Record, Vec<u8>)> {
+    let [0xF0, m0, m1, m2, device, data @ ..] = msg else {
+        return None;
+    };
+    if [*m0, *m1, *m2] != OSC_TUNNEL_ID {
+        return None;
+    }
+    let data = data.strip_suffix(&[0xF7]).unwrap_or(data);
+    let mut packet = Vec::with_capacity(data.len());
+    for group in data.chunks(8) {
+        let (&high_bits, bytes) = group.split_first()?;
+        for (i, &b) in bytes.iter().enumerate() {
+            packet.push(b | (((high_bits >> i) & 1) << 7));
+        }
+    }
+    if packet.len() > u16::MAX as usize {
+        return None;
+    }
+    if let Err(err) = osc::validate(&packet) {
+        avtools_log::debug!("dropping tunnelled OSC from device {device}: {err}");
+        return None;
+    }
+    let record = Record {
+        ts_us,
+        kind: KIND_OSC,
+        channel: *device,
+        a: 0,
+        b: 0,
+        v16: 0,
+        extra: packet.len() as u16,
+        arrival_us,
+    };
+    Some((record, packet))
+}
+
+/// A per-note parameter a synth sends as SysEx (e.g. VST3 Note Expression
+/// bridged to MIDI), registered with midi_register_note_expression. Messages
+/// equal to `template` in every byte but the one at `value_offset` become
+/// KIND_NOTE_EXP records: channel and a = the registered channel and note,
+/// b = the byte at `value_offset`, extra = `kind`.
+pub struct NoteExpression {
+    pub channel: u8,
+    pub note: u8,
+    pub kind: u16,
+    template: Vec<u8>,
+    value_offset: usize,
+}
+
+impl NoteExpression {
+    /// `template` is the whole message, F0 to F7; the value byte must lie
+    /// between them.
+    pub fn new(channel: u8, note: u8, kind: u32, template: &[u8], value_offset: u8) -> Result<Self, String> {
+        if channel > 15 || note > 127 {
+            return Err(format!("channel {channel} / note {note} out of range"));
+        }
+        let kind = u16::try_from(kind).map_err(|_| format!("kind {kind} does not fit in 16 bits"))?;
+        let [0xF0, data @ .., 0xF7] = template else {
+            return Err("template must start with F0 and end with F7".to_string());
+        };
+        if data.iter().any(|&b| b > 0x7F) {
+            return Err("template data bytes must be 7-bit".to_string());
+        }
+        let value_offset = value_offset as usize;
+        if value_offset == 0 || value_offset > data.len() {
+            return Err(format!(
+                "value offset {value_offset} is not a data byte of the {}-byte template",
+                template.len()
+            ));
+        }
+        Ok(Self {
+            channel,
+            note,
+            kind,
+            template: template.to_vec(),
+            value_offset,
+        })
+    }
+
+    pub fn parse(&self, msg: &[u8], ts_us: u64, arrival_us: u64) -> Option<Record> {
+        if msg.len() != self.template.len() {
+            return None;
+        }
+        let differs = msg
+            .iter()
+            .zip(&self.template)
+            .enumerate()
+            .any(|(i, (a, b))| i != self.value_offset && a != b);
+        if differs {
+            return None;
+        }
+        Some(Record {
+            ts_us,
+            kind: KIND_NOTE_EXP,
+            channel: self.channel,
+            a: self.note,
+            b: msg[self.value_offset],
+            v16: 0,
+            extra: self.kind,
+            arrival_us,
+        })
+    }
+}
+
+/// The first SysEx message starting with `prefix` to arrive after the
+/// matcher was created, for midi_sysex_request_response.
+pub struct ResponseMatcher {
+    prefix: Vec<u8>,
+    response: Mutex<Option<Vec<u8>>>,
+    arrived: Condvar,
+}
+
+impl ResponseMatcher {
+    pub fn new(prefix: &[u8]) -> Self {
+        Self {
+            prefix: prefix.to_vec(),
+            response: Mutex::new(None),
+            arrived: Condvar::new(),
+        }
+    }
+
+    /// A message the input received; keeps it if it is the first match.
+    pub fn observe(&self, msg: &[u8]) {
+        if msg.first() != Some(&0xF0) || !msg.starts_with(&self.prefix) {
+            return;
+        }
+        let mut response = self.response.lock().unwrap_or_else(|e| e.into_inner());
+        if response.is_none() {
+            *response = Some(msg.to_vec());
+            self.arrived.notify_all();
+        }
+    }
+
+    /// Block until a match arrives or `timeout` passes.
+    pub fn wait(&self, timeout: Duration) -> Option<Vec<u8>> {
+        let deadline = Instant::now() + timeout;
+        let mut response = self.response.lock().unwrap_or_else(|e| e.into_inner());
+        while response.is_none() {
+            let now = Instant::now();
+            if now >= deadline {
+                break;
+            }
+            response = self
+                .arrived
+                .wait_timeout(response, deadline - now)
+                .unwrap_or_else(|e| e.into_inner())
+                .0;
+        }
+        response.take()
+    }
+}
@@ -17,4 +17,4 @@ case "$uname_out" in
  esac
 
 lib_name="libmidi_bridge.${ext}"
-echo "Built $root_dir/native/midi_bridge/target/release/$lib_name"
+echo "Built $root_dir/native/target/release/$lib_name"
@@ -1,8 +1,20 @@
 // fast_sleep.ts
-type Strategy = "default" | "yield" | "spin";
+import { readSizedBuffer } from "./native_buffer.ts";
+import { lastNativePanic, nativeError } from "./native_error.ts";
+import { readMetrics, type RuntimeMetrics } from "./native_metrics.ts";
+import { combinedLibraryHint, findCombinedLibrary } from "./native_library.ts";
+import { onUnload, type ShutdownReport, shutdownAll } from "./native_shutdown.ts";
+import { type LogHandler, type LogLevel, logLevelId, NativeLogSink } from "./native_log.ts";
+import { startTracing, type TraceLevel } from "./native_tracing.ts";
+
+/**
+ * "busy": "spin" with nativeAccuracyUs capped at 1000; that last window
+ * before a deadline is a pure spin.
+ */
+type Strategy = "default" | "yield" | "spin" | "busy";
 
 function dylibUrl(): URL {
-  const base = new URL("../native/fastsleep/target/release/", import.meta.url);
+  const base = new URL("../native/target/release/", import.meta.url);
 
   // Cargo output names differ slightly on Windows; try both.
   const os = Deno.build.os;
@@ -24,19 +36,98 @@ function dylibUrl(): URL {
       // try next
     }
   }
+  const combined = findCombinedLibrary(FFI_SYMBOLS);
+  if (combined) return combined;
   throw new Error(
-    `Could not find native fastsleep library in ${base.toString()} (tried ${candidates.join(", ")})`,
+    `Could not find native fastsleep library in ${base.toString()} (tried ${candidates.join(", ")}), ` +
+      combinedLibraryHint(),
   );
 }
 
-const lib = Deno.dlopen(dylibUrl(), {
+export const FFI_SYMBOLS = {
   fast_sleep_init: { parameters: ["u32", "u32"], result: "i32" },
+  fast_sleep_configure: { parameters: ["u32", "u32"], result: "i32" },
+  fast_sleep_get_config: { parameters: ["pointer", "pointer", "pointer"], result: "void" },
   fast_sleep_us: {
     parameters: ["u32"],
     result: "void",
     nonblocking: true, // runs on blocking thread; returns Promise<undefined> :contentReference[oaicite:2]{index=2}
   },
-} as const);
+  fast_sleep_ns: { parameters: ["u64"], result: "void", nonblocking: true },
+  fast_sleeper_create: { parameters: ["u32", "u32"], result: "u32" },
+  fast_sleep_us_with: { parameters: ["u32", "u32", "u32"], result: "i32", nonblocking: true },
+  fast_sleep_ns_with: { parameters: ["u64", "u32", "u32"], result: "i32", nonblocking: true },
+  fast_sleep_until_us_with: { parameters: ["u64", "u32", "u32"], result: "i32", nonblocking: true },
+  fast_sleeper_sleep_us: { parameters: ["u32", "u32"], result: "i32", nonblocking: true },
+  fast_sleeper_sleep_until_us: { parameters: ["u32", "u64"], result: "i64", nonblocking: true },
+  fast_sleeper_destroy: { parameters: ["u32"], result: "i32" },
+  fast_sleep_token_create: { parameters: [], result: "u32" },
+  fast_sleep_cancellable_us: { parameters: ["u32", "u32"], result: "i32", nonblocking: true },
+  fast_sleep_cancel: { parameters: ["u32"], result: "i32" },
+  fast_sleep_token_reset: { parameters: ["u32"], result: "i32" },
+  fast_sleep_token_destroy: { parameters: ["u32"], result: "i32" },
+  fast_ticker_create: { parameters: ["u32", "u32"], result: "u32" },
+  fast_ticker_create_ex: { parameters: ["buffer", "u32"], result: "u32" },
+  fast_ticker_wait: { parameters: ["u32"], result: "i64", nonblocking: true },
+  fast_ticker_skipped: { parameters: ["u32"], result: "i64" },
+  fast_ticker_set_period: { parameters: ["u32", "u32"], result: "i32" },
+  fast_ticker_align: { parameters: ["u32", "u64"], result: "i32" },
+  fast_ticker_info: { parameters: ["u32", "buffer", "u32"], result: "u32" },
+  fast_ticker_destroy: { parameters: ["u32"], result: "i32" },
+  fast_loop_start: { parameters: ["u32", "function", "u32"], result: "u32" },
+  fast_loop_stop: { parameters: ["u32"], result: "i32", nonblocking: true },
+  fast_timer_schedule: { parameters: ["u32", "function"], result: "u32" },
+  fast_timer_cancel: { parameters: ["u32"], result: "i32" },
+  fast_schedule_batch: { parameters: ["buffer", "u32", "function"], result: "u32" },
+  fast_schedule_batch_cancel: { parameters: ["u32"], result: "i32" },
+  fast_sleep_set_windows_timer_resolution: { parameters: ["u32"], result: "i32" },
+  fast_sleep_reset_windows_timer_resolution: { parameters: [], result: "i32" },
+  fast_sleep_set_timer_resolution: { parameters: ["u32"], result: "i32" },
+  fast_sleep_clear_timer_resolution: { parameters: ["u32"], result: "i32" },
+  fast_sleep_calibrate: { parameters: ["u32", "u32"], result: "u32", nonblocking: true },
+  fast_sleep_calibration_report: { parameters: ["buffer", "u32"], result: "u32" },
+  fast_sleep_selftest: { parameters: ["buffer", "u32"], result: "u32", nonblocking: true },
+  fast_sleep_stats_enable: { parameters: ["u8"], result: "void" },
+  fast_sleep_stats_reset: { parameters: [], result: "void" },
+  fast_sleep_stats: { parameters: ["buffer", "u32"], result: "u32" },
+  fast_sleep_us_measured: { parameters: ["u32"], result: "u64", nonblocking: true },
+  fast_sleep_ns_measured: { parameters: ["u64"], result: "u64", nonblocking: true },
+  fast_set_current_thread_priority: { parameters: ["u32"], result: "i32", nonblocking: true },
+  fast_get_current_thread_priority: { parameters: [], result: "i32", nonblocking: true },
+  fast_set_current_thread_name: { parameters: ["buffer", "u32"], result: "i32", nonblocking: true },
+  fast_sleep_until_any: { parameters: ["buffer", "u32", "u32"], result: "i32", nonblocking: true },
+  fast_wait_flag_until_us: { parameters: ["pointer", "u32", "u64"], result: "i32", nonblocking: true },
+  fast_sleep_until_signal: { parameters: ["pointer", "u64"], result: "u8", nonblocking: true },
+  fast_set_wake_flag: { parameters: ["pointer", "u32"], result: "i32" },
+  fast_sleep_clock_ns: { parameters: [], result: "u64" },
+  fast_set_miss_handler: { parameters: ["u32", "function"], result: "void" },
+  fast_sleep_set_power_mode: { parameters: ["u32"], result: "i32" },
+  fast_sleep_get_power_mode: { parameters: [], result: "u32" },
+  fast_clock_info: { parameters: ["buffer", "u32"], result: "u32" },
+  fast_now_us: { parameters: [], result: "u64" },
+  avtools_clock_now_us: { parameters: [], result: "u64" },
+  avtools_clock_offset: { parameters: ["u32", "u32"], result: "i64" },
+  avtools_tracing_start: { parameters: ["buffer", "u32", "u32"], result: "i32" },
+  avtools_tracing_stop: { parameters: [], result: "i32" },
+  av_alloc: { parameters: ["u32"], result: "pointer" },
+  av_free: { parameters: ["pointer", "u32"], result: "void" },
+  fast_sleep_enable_mock: { parameters: ["f64"], result: "i32" },
+  fast_sleep_get_virtual_time_ns: { parameters: [], result: "u64" },
+  fast_sleep_abi_version: { parameters: [], result: "u32" },
+  fast_sleep_capabilities: { parameters: ["buffer", "u32"], result: "u32" },
+  fast_sleep_last_error_code: { parameters: [], result: "i32" },
+  fast_sleep_last_error_message: { parameters: ["buffer", "u32"], result: "u32" },
+  fast_sleep_get_last_panic: { parameters: ["buffer", "u32"], result: "u32" },
+  fast_sleep_metrics_json: { parameters: ["buffer", "u32"], result: "u32" },
+  fast_sleep_set_log_callback: { parameters: ["function"], result: "void", nonblocking: true },
+  fast_sleep_set_log_level: { parameters: ["u32"], result: "i32" },
+  fast_sleep_until_us: { parameters: ["u64"], result: "i64", nonblocking: true },
+  fast_sleep_until_ns: { parameters: ["u64"], result: "i64", nonblocking: true },
+  avtools_shutdown_all: { parameters: ["buffer", "u32"], result: "u32" },
+} as const;
+
+const lib = Deno.dlopen(dylibUrl(), FFI_SYMBOLS);
+onUnload(() => shutdownFastSleep());
 
 function strategyToInt(s: Strategy): number {
   switch (s) {
@@ -46,18 +137,175 @@ function strategyToInt(s: Strategy): number {
       return 1;
     case "spin":
       return 2;
+    case "busy":
+      return 3;
   }
 }
 
-/** Optional one-time config. Call early (before fastSleep). */
+function intToStrategy(n: number): Strategy {
+  switch (n) {
+    case 1:
+      return "yield";
+    case 2:
+      return "spin";
+    case 3:
+      return "busy";
+    default:
+      return "default";
+  }
+}
+
+/** Configure the global sleeper. Can be called at any time; applies to subsequent sleeps. */
 export function configureFastSleep(
   opts: { nativeAccuracyUs?: number; strategy?: Strategy } = {},
 ) {
   const nativeAccuracyUs = Math.max(0, Math.floor(opts.nativeAccuracyUs ?? 200));
   const strategy = strategyToInt(opts.strategy ?? "default");
-  const rc = lib.symbols.fast_sleep_init(nativeAccuracyUs >>> 0, strategy);
-  if (rc === -1) throw new Error("fast_sleep_init: invalid strategy");
-  // rc=1 just means it was already initialized; ignore.
+  const rc = lib.symbols.fast_sleep_configure(nativeAccuracyUs >>> 0, strategy);
+  if (rc !== 0) throw lastError("fast_sleep_configure");
+}
+
+export type PowerMode = "configured" | "low-power";
+
+/**
+ * "low-power": native sleep only (no spin tail) everywhere, and ticker/timer
+ * deadlines rounded up to whole milliseconds. Switchable at any time.
+ */
+export function setFastSleepPowerMode(mode: PowerMode) {
+  lib.symbols.fast_sleep_set_power_mode(mode === "low-power" ? 1 : 0);
+}
+
+/** Current global sleeper configuration. */
+export function getFastSleepConfig(): {
+  nativeAccuracyUs: number;
+  strategy: Strategy;
+  powerMode: PowerMode;
+} {
+  const out = new Uint32Array(3);
+  lib.symbols.fast_sleep_get_config(
+    Deno.UnsafePointer.of(out.subarray(0, 1)),
+    Deno.UnsafePointer.of(out.subarray(1, 2)),
+    Deno.UnsafePointer.of(out.subarray(2, 3)),
+  );
+  return {
+    nativeAccuracyUs: out[0],
+    strategy: intToStrategy(out[1]),
+    powerMode: out[2] === 1 ? "low-power" : "configured",
+  };
+}
+
+/**
+ * Measure this machine's native sleep overshoot and apply the overshoot at
+ * `percentile` as the native accuracy. Resolves to the applied accuracy in µs,
+ * capped at 1000 with the BusySpin strategy (3).
+ */
+export async function calibrateFastSleep(sampleCount = 500, percentile = 99): Promise<number> {
+  return await lib.symbols.fast_sleep_calibrate(
+    Math.max(0, Math.floor(sampleCount)) >>> 0,
+    Math.min(100, Math.max(0, Math.floor(percentile))) >>> 0,
+  );
+}
+
+/** Measurement summary from the last calibrateFastSleep run, or null. */
+export function fastSleepCalibrationReport(): Record<string, unknown> | null {
+  let buf = new Uint8Array(512);
+  let len = lib.symbols.fast_sleep_calibration_report(buf, buf.length);
+  if (len > buf.length) {
+    buf = new Uint8Array(len);
+    len = lib.symbols.fast_sleep_calibration_report(buf, buf.length);
+  }
+  return JSON.parse(new TextDecoder().decode(buf.subarray(0, len)));
+}
+
+export type SelfTestResult = {
+  requested_us: number;
+  method: "thread_sleep" | "spin_sleeper";
+  samples: number;
+  mean_overshoot_us: number;
+  p95_overshoot_us: number;
+  max_overshoot_us: number;
+};
+
+export type SelfTestReport = {
+  duration_ms: number;
+  os_timer_granularity_us: number;
+  timer_resolution_us: number | null;
+  native_accuracy_us: number;
+  results: SelfTestResult[];
+};
+
+/**
+ * Compare plain OS sleeps with the configured sleeper at 100µs, 1ms, 5ms and
+ * 16.7ms. Runs natively for about three seconds and changes no settings.
+ */
+export async function fastSleepSelfTest(): Promise<SelfTestReport> {
+  const buf = new Uint8Array(4096);
+  const len = await lib.symbols.fast_sleep_selftest(buf, buf.length);
+  if (len > buf.length) throw new Error("fast_sleep_selftest: report larger than buffer");
+  return JSON.parse(new TextDecoder().decode(buf.subarray(0, len)));
+}
+
+export type FastSleepStats = {
+  enabled: boolean;
+  power_mode: number;
+  count: number;
+  mean_error_us: number;
+  p95_us: number;
+  p99_us: number;
+  max_us: number;
+  buckets: Record<string, number>;
+};
+
+/** Record requested-vs-actual error for every sleep (off by default). */
+export function enableFastSleepStats(enabled = true) {
+  lib.symbols.fast_sleep_stats_enable(enabled ? 1 : 0);
+}
+
+export function resetFastSleepStats() {
+  lib.symbols.fast_sleep_stats_reset();
+}
+
+/** Jitter histogram; percentiles are resolved to the decade bucket bounds. */
+export function fastSleepStats(): FastSleepStats {
+  const buf = new Uint8Array(512);
+  const len = lib.symbols.fast_sleep_stats(buf, buf.length);
+  return JSON.parse(new TextDecoder().decode(buf.subarray(0, len)));
+}
+
+/**
+ * Windows only: raise the system timer rate via timeBeginPeriod so native
+ * sleeps are accurate to ~`periodMs`. Undone by resetWindowsTimerResolution
+ * or at process exit. No-op on other platforms.
+ */
+export function setWindowsTimerResolution(periodMs = 1) {
+  const rc = lib.symbols.fast_sleep_set_windows_timer_resolution(Math.max(1, Math.floor(periodMs)) >>> 0);
+  if (rc !== 0) throw new Error("fast_sleep_set_windows_timer_resolution failed");
+}
+
+/**
+ * Refcounted variant of setWindowsTimerResolution: returns a function that
+ * undoes exactly this call, so independent users can nest. The first element
+ * is false on non-Windows platforms, where this is a no-op.
+ */
+export function acquireTimerResolution(periodMs = 1): [boolean, () => void] {
+  const ms = Math.max(1, Math.floor(periodMs)) >>> 0;
+  const rc = lib.symbols.fast_sleep_set_timer_resolution(ms);
+  if (rc === -1) throw new Error("fast_sleep_set_timer_resolution failed");
+  if (rc === 1) return [false, () => {}];
+  let released = false;
+  return [true, () => {
+    if (released) return;
+    released = true;
+    lib.symbols.fast_sleep_clear_timer_resolution(ms);
+  }];
+}
+
+/**
+ * End all periods begun by setWindowsTimerResolution; those held through
+ * acquireTimerResolution stay.
+ */
+export function resetWindowsTimerResolution() {
+  lib.symbols.fast_sleep_reset_windows_timer_resolution();
 }
 
 /** Sleep for microseconds (Promise-based, nonblocking FFI). */
@@ -69,6 +317,165 @@ export function fastSleepUs(us: number): Promise<void> {
   return lib.symbols.fast_sleep_us(clamped >>> 0) as unknown as Promise<void>;
 }
 
+/** Sleep for nanoseconds; not capped at ~71 minutes like fastSleepUs. */
+export function fastSleepNs(ns: number | bigint): Promise<void> {
+  const n = typeof ns === "bigint" ? ns : BigInt(Math.max(0, Math.floor(Number.isFinite(ns) ? ns : 0)));
+  return lib.symbols.fast_sleep_ns(n < 0n ? 0n : n) as unknown as Promise<void>;
+}
+
+type SleepOverride = { nativeAccuracyUs?: number; strategy?: Strategy };
+
+function checkWithResult(rc: number, name: string) {
+  if (rc === -1) throw new Error(`${name}: invalid strategy`);
+  if (rc === -2) throw new Error(`${name}: busy-spin window above 1000µs`);
+}
+
+/**
+ * fastSleepUs with a one-off accuracy/strategy for this call only; the global
+ * configuration is left untouched.
+ */
+export async function fastSleepUsWith(us: number, opts: SleepOverride): Promise<void> {
+  if (!Number.isFinite(us)) us = 0;
+  const clamped = Math.min(Math.max(0, Math.floor(us)), 0xFFFF_FFFF);
+  const rc = await lib.symbols.fast_sleep_us_with(
+    clamped >>> 0,
+    (opts.nativeAccuracyUs ?? 200) >>> 0,
+    strategyToInt(opts.strategy ?? "default"),
+  );
+  checkWithResult(rc, "fast_sleep_us_with");
+}
+
+/** Nanosecond variant of fastSleepUsWith. */
+export async function fastSleepNsWith(ns: number | bigint, opts: SleepOverride): Promise<void> {
+  const n = typeof ns === "bigint" ? ns : BigInt(Math.max(0, Math.floor(Number.isFinite(ns) ? ns : 0)));
+  const rc = await lib.symbols.fast_sleep_ns_with(
+    n < 0n ? 0n : n,
+    (opts.nativeAccuracyUs ?? 200) >>> 0,
+    strategyToInt(opts.strategy ?? "default"),
+  );
+  checkWithResult(rc, "fast_sleep_ns_with");
+}
+
+/** Sleep until an absolute fastNowUs() deadline with a one-off accuracy/strategy. */
+export async function fastSleepUntilUsWith(deadlineUs: number, opts: SleepOverride): Promise<void> {
+  const deadline = BigInt(Math.max(0, Math.floor(Number.isFinite(deadlineUs) ? deadlineUs : 0)));
+  const rc = await lib.symbols.fast_sleep_until_us_with(
+    deadline,
+    (opts.nativeAccuracyUs ?? 200) >>> 0,
+    strategyToInt(opts.strategy ?? "default"),
+  );
+  checkWithResult(rc, "fast_sleep_until_us_with");
+}
+
+/** Like fastSleepUs; resolves to the natively measured elapsed time in ns. */
+export async function fastSleepUsMeasured(us: number): Promise<number> {
+  if (!Number.isFinite(us)) us = 0;
+  const clamped = Math.min(Math.max(0, Math.floor(us)), 0xFFFF_FFFF);
+  return Number(await lib.symbols.fast_sleep_us_measured(clamped >>> 0));
+}
+
+/** Like fastSleepNs; resolves to the natively measured elapsed time in ns. */
+export async function fastSleepNsMeasured(ns: number | bigint): Promise<number> {
+  const n = typeof ns === "bigint" ? ns : BigInt(Math.max(0, Math.floor(Number.isFinite(ns) ? ns : 0)));
+  return Number(await lib.symbols.fast_sleep_ns_measured(n < 0n ? 0n : n));
+}
+
+export type ThreadPriority = "normal" | "above-normal" | "time-critical";
+const THREAD_PRIORITIES: ThreadPriority[] = ["normal", "above-normal", "time-critical"];
+
+/**
+ * Elevate the native thread that runs this (nonblocking) FFI call, i.e. a
+ * Deno blocking-pool thread. Throws if the OS refuses or the platform is unsupported.
+ */
+export async function setThreadPriority(level: ThreadPriority): Promise<void> {
+  const rc = await lib.symbols.fast_set_current_thread_priority(THREAD_PRIORITIES.indexOf(level) >>> 0);
+  if (rc === -2) throw new Error("setThreadPriority: insufficient privileges");
+  if (rc === -3) throw new Error("setThreadPriority: unsupported platform");
+  if (rc !== 0) throw new Error(`setThreadPriority: failed (${rc})`);
+}
+
+/** Effective priority of the pool thread running this call, or null if unsupported. */
+export async function getThreadPriority(): Promise<ThreadPriority | null> {
+  const level = await lib.symbols.fast_get_current_thread_priority();
+  return THREAD_PRIORITIES[level] ?? null;
+}
+
+/** Name the pool thread running this call, for profilers. Returns false if refused. */
+export async function setThreadName(name: string): Promise<boolean> {
+  const bytes = new TextEncoder().encode(name);
+  return (await lib.symbols.fast_set_current_thread_name(bytes, bytes.length)) === 0;
+}
+
+/** Raw platform monotonic clock in ns (for intervals; origin is platform-defined). */
+export function fastClockNs(): bigint {
+  return BigInt(lib.symbols.fast_sleep_clock_ns());
+}
+
+export type ClockInfo = {
+  monotonic_us: number;
+  unix_ms: number;
+  anchor_monotonic_us: number;
+  anchor_unix_ms: number;
+  error_bound_us: number;
+};
+
+/** Mapping from fastNowUs() to Unix time, fixed for the process lifetime. */
+export function fastClockInfo(): ClockInfo {
+  const buf = new Uint8Array(256);
+  const len = lib.symbols.fast_clock_info(buf, buf.length);
+  return JSON.parse(new TextDecoder().decode(buf.subarray(0, len)));
+}
+
+/** Convert a fastNowUs() value to Unix epoch milliseconds. */
+export function fastNowUsToUnixMs(monotonicUs: number, info: ClockInfo = fastClockInfo()): number {
+  return info.anchor_unix_ms + (monotonicUs - info.anchor_monotonic_us) / 1000;
+}
+
+/** Microseconds on the process-wide monotonic clock used for deadlines. */
+export function fastNowUs(): number {
+  return Number(lib.symbols.fast_now_us());
+}
+
+/**
+ * The shared avtools clock as read through this library's own export. MIDI
+ * `tsUs` and window event `ts_us` are on the same timeline; comparing the
+ * `avtools_clock_now_us` of each library checks they agree.
+ */
+export function avtoolsClockNowUs(): number {
+  return Number(lib.symbols.avtools_clock_now_us());
+}
+
+/** Timestamp domains for `clockOffsetUs`, as numbered by `avtools_clock_offset`. */
+export const ClockDomain = {
+  shared: 0,
+  /** MIDI inputs opened without the raw monotonic clock flag. */
+  midi: 1,
+  window: 2,
+  /** `fastNowUs` and sleep deadlines. */
+  fastsleep: 3,
+  /** MIDI inputs opened with the raw monotonic clock flag (Linux only). */
+  midiRaw: 4,
+} as const;
+export type ClockDomain = typeof ClockDomain[keyof typeof ClockDomain];
+
+/**
+ * How many µs domain `b`'s timestamps run ahead of domain `a`'s for the same
+ * instant. 0 (apart from rounding) for every domain on the shared clock; any
+ * other value is an offset to subtract before comparing timestamps.
+ */
+export function clockOffsetUs(a: ClockDomain, b: ClockDomain): number {
+  const offset = Number(lib.symbols.avtools_clock_offset(a, b));
+  if (lib.symbols.fast_sleep_last_error_code() !== 0) throw lastError("avtools_clock_offset");
+  return offset;
+}
+
+/** Sleep until an absolute `fastNowUs()` deadline; resolves to lateness in µs. */
+export async function fastSleepUntilUs(deadlineUs: number): Promise<number> {
+  if (!Number.isFinite(deadlineUs)) deadlineUs = 0;
+  const deadline = BigInt(Math.max(0, Math.floor(deadlineUs)));
+  return Number(await lib.symbols.fast_sleep_until_us(deadline));
+}
+
 /** Sleep for milliseconds (fractional ms allowed). */
 export function fastSleep(ms: number): Promise<void> {
   if (!Number.isFinite(ms)) ms = 0;
@@ -100,3 +507,425 @@ export function clearFastTimeout(id: number) {
   if (rec) rec.canceled = true;
   pending.delete(id);
 }
+
+/** Independent sleeper with its own accuracy/strategy, separate from the global one. */
+export class FastSleeper {
+  #handle: number;
+
+  private constructor(handle: number) {
+    this.#handle = handle;
+  }
+
+  static create(opts: { nativeAccuracyUs?: number; strategy?: Strategy } = {}): FastSleeper {
+    const nativeAccuracyUs = Math.max(0, Math.floor(opts.nativeAccuracyUs ?? 200));
+    const handle = lib.symbols.fast_sleeper_create(
+      nativeAccuracyUs >>> 0,
+      strategyToInt(opts.strategy ?? "default"),
+    );
+    if (handle === 0) throw lastError("fast_sleeper_create");
+    return new FastSleeper(handle);
+  }
+
+  async sleepUs(us: number): Promise<void> {
+    const clamped = Math.min(Math.max(0, Math.floor(us)), 0xFFFF_FFFF);
+    const rc = await lib.symbols.fast_sleeper_sleep_us(this.#handle, clamped >>> 0);
+    if (rc !== 0) throw new Error("fast_sleeper_sleep_us: invalid handle");
+  }
+
+  async sleepUntilUs(deadlineUs: number): Promise<number> {
+    const deadline = BigInt(Math.max(0, Math.floor(deadlineUs)));
+    const late = await lib.symbols.fast_sleeper_sleep_until_us(this.#handle, deadline);
+    if (BigInt(late) === -(2n ** 63n)) throw new Error("fast_sleeper_sleep_until_us: invalid handle");
+    return Number(late);
+  }
+
+  destroy() {
+    lib.symbols.fast_sleeper_destroy(this.#handle);
+  }
+}
+
+/** Token that lets another part of the program wake sleeps early (e.g. on stop). */
+async function sleepUntilAny(deadlinesUs: number[], token: number): Promise<number> {
+  if (deadlinesUs.length === 0) throw new Error("fastSleepUntilAny: no deadlines");
+  const deadlines = BigUint64Array.from(
+    deadlinesUs,
+    (d) => BigInt(Math.max(0, Math.floor(Number.isFinite(d) ? d : 0))),
+  );
+  const rc = await lib.symbols.fast_sleep_until_any(deadlines, deadlines.length, token);
+  if (rc === -4) throw new Error("fast_sleep_until_any: invalid token");
+  if (rc < -1) throw new Error(`fast_sleep_until_any: failed (${rc})`);
+  return rc;
+}
+
+/**
+ * Sleep until the earliest of several absolute `fastNowUs()` deadlines;
+ * resolves to the index of the one that expired.
+ */
+export function fastSleepUntilAny(deadlinesUs: number[]): Promise<number> {
+  return sleepUntilAny(deadlinesUs, 0);
+}
+
+function flagPointer(flags: Int32Array | Uint32Array, index: number): Deno.PointerValue {
+  if (!Number.isInteger(index) || index < 0 || index >= flags.length) {
+    throw new RangeError(`flag index ${index} out of range`);
+  }
+  return Deno.UnsafePointer.offset(Deno.UnsafePointer.of(flags)!, index * 4);
+}
+
+/**
+ * Wait until `flags[index]` becomes nonzero (or equals `expected` when given)
+ * or the absolute `fastNowUs()` deadline passes. Back `flags` with a
+ * SharedArrayBuffer so another thread can set it, and keep it alive until the
+ * promise settles. Resolves to true if the flag was set.
+ */
+export async function waitFlagUntilUs(
+  flags: Int32Array | Uint32Array,
+  index: number,
+  deadlineUs: number,
+  expected = 0,
+): Promise<boolean> {
+  const deadline = BigInt(Math.max(0, Math.floor(Number.isFinite(deadlineUs) ? deadlineUs : 0)));
+  const rc = await lib.symbols.fast_wait_flag_until_us(flagPointer(flags, index), expected >>> 0, deadline);
+  if (rc < 0) throw new Error("fast_wait_flag_until_us: flag must be 4-byte aligned");
+  return rc === 1;
+}
+
+/** Store `value` into `flags[index]` and wake any native waiter on it at once. */
+export function setWakeFlag(flags: Int32Array | Uint32Array, index: number, value = 1) {
+  lib.symbols.fast_set_wake_flag(flagPointer(flags, index), value >>> 0);
+}
+
+/**
+ * Wait until `signals[index]` becomes nonzero or `timeoutUs` passes, e.g. for
+ * a frame-ready byte another worker sets with `Atomics.store`. Back `signals`
+ * with a SharedArrayBuffer and keep it alive until the promise settles.
+ * Resolves to true if signalled.
+ */
+export async function waitSignal(signals: Uint8Array, index: number, timeoutUs: number): Promise<boolean> {
+  if (!Number.isInteger(index) || index < 0 || index >= signals.length) {
+    throw new RangeError(`signal index ${index} out of range`);
+  }
+  const ptr = Deno.UnsafePointer.offset(Deno.UnsafePointer.of(signals)!, index);
+  const timeout = BigInt(Math.max(0, Math.floor(Number.isFinite(timeoutUs) ? timeoutUs : 0)));
+  return (await lib.symbols.fast_sleep_until_signal(ptr, timeout)) === 1;
+}
+
+export class SleepToken {
+  #handle: number;
+
+  constructor() {
+    this.#handle = lib.symbols.fast_sleep_token_create();
+  }
+
+  /** Resolves to true if the sleep was cancelled, false if it completed. */
+  async sleepUs(us: number): Promise<boolean> {
+    const clamped = Math.min(Math.max(0, Math.floor(us)), 0xFFFF_FFFF);
+    const rc = await lib.symbols.fast_sleep_cancellable_us(this.#handle, clamped >>> 0);
+    if (rc === -1) throw new Error("fast_sleep_cancellable_us: invalid token");
+    return rc === 1;
+  }
+
+  /** fastSleepUntilAny, cancellable by this token; resolves to -1 if cancelled. */
+  sleepUntilAny(deadlinesUs: number[]): Promise<number> {
+    return sleepUntilAny(deadlinesUs, this.#handle);
+  }
+
+  cancel() {
+    lib.symbols.fast_sleep_cancel(this.#handle);
+  }
+
+  reset() {
+    lib.symbols.fast_sleep_token_reset(this.#handle);
+  }
+
+  destroy() {
+    lib.symbols.fast_sleep_token_destroy(this.#handle);
+  }
+}
+
+/** Drift-free periodic ticker on absolute boundaries. */
+export type TickerInfo = {
+  period_us: number;
+  anchor_us: number;
+  ticks_fired: number;
+  ticks_skipped: number;
+  lateness: { count: number; min_us: number; max_us: number; mean_us: number; last_us: number };
+};
+
+export class FastTicker {
+  #handle: number;
+
+  constructor(periodUs: number, opts: { catchUp?: boolean } = {}) {
+    const period = Math.min(Math.max(1, Math.floor(periodUs)), 0xFFFF_FFFF);
+    const options = new TextEncoder().encode(JSON.stringify({
+      version: 1,
+      period_us: period,
+      catch_up: opts.catchUp ?? false,
+    }));
+    this.#handle = lib.symbols.fast_ticker_create_ex(options, options.length);
+    if (this.#handle === 0) throw lastError("fast_ticker_create_ex");
+  }
+
+  /** Resolves to the wakeup lateness in µs; negative when ticks were skipped. */
+  async wait(): Promise<number> {
+    return Number(await lib.symbols.fast_ticker_wait(this.#handle));
+  }
+
+  /** Ticks skipped by the most recent wait(). */
+  skipped(): number {
+    return Number(lib.symbols.fast_ticker_skipped(this.#handle));
+  }
+
+  setPeriodUs(periodUs: number) {
+    const period = Math.min(Math.max(1, Math.floor(periodUs)), 0xFFFF_FFFF);
+    lib.symbols.fast_ticker_set_period(this.#handle, period >>> 0);
+  }
+
+  /** Put ticks on the grid `anchorUs + n * period` (fastNowUs clock) from the next tick. */
+  alignTo(anchorUs: number) {
+    const anchor = BigInt(Math.max(0, Math.floor(anchorUs)));
+    if (lib.symbols.fast_ticker_align(this.#handle, anchor) !== 0) {
+      throw new Error("fast_ticker_align failed");
+    }
+  }
+
+  info(): TickerInfo {
+    const buf = new Uint8Array(512);
+    const len = lib.symbols.fast_ticker_info(this.#handle, buf, buf.length);
+    if (len === 0 || len > buf.length) throw new Error("fast_ticker_info failed");
+    return JSON.parse(new TextDecoder().decode(buf.subarray(0, len)));
+  }
+
+  destroy() {
+    lib.symbols.fast_ticker_destroy(this.#handle);
+  }
+}
+
+const LOOP_CALLBACK_DEF = { parameters: ["u64", "i64"], result: "void" } as const;
+
+/**
+ * Native-driven periodic loop: the callback runs once per tick without a
+ * per-tick FFI sleep call. `tick` jumps by more than one when ticks were skipped.
+ * `priority` sets the loop thread's priority (stepped down if the OS refuses).
+ */
+export class FastLoop {
+  #handle: number;
+  #callback: Deno.UnsafeCallback<typeof LOOP_CALLBACK_DEF>;
+
+  constructor(
+    periodUs: number,
+    fn: (tick: number, lateUs: number) => void,
+    priority: ThreadPriority = "normal",
+  ) {
+    this.#callback = Deno.UnsafeCallback.threadSafe(
+      LOOP_CALLBACK_DEF,
+      (tick, lateUs) => fn(Number(tick), Number(lateUs)),
+    );
+    const period = Math.min(Math.max(1, Math.floor(periodUs)), 0xFFFF_FFFF);
+    const flags = THREAD_PRIORITIES.indexOf(priority);
+    this.#handle = lib.symbols.fast_loop_start(period >>> 0, this.#callback.pointer, flags >>> 0);
+    if (this.#handle === 0) {
+      this.#callback.close();
+      throw new Error("fast_loop_start failed");
+    }
+  }
+
+  /** Resolves once no further callbacks will run. */
+  async stop(): Promise<void> {
+    await lib.symbols.fast_loop_stop(this.#handle);
+    this.#callback.close();
+  }
+}
+
+const MISS_CALLBACK_DEF = { parameters: ["u64", "u64"], result: "void" } as const;
+let missCallback: Deno.UnsafeCallback<typeof MISS_CALLBACK_DEF> | null = null;
+// A waking thread may still be calling a replaced handler, so replaced
+// callbacks are kept alive rather than closed.
+const retiredMissCallbacks: Deno.UnsafeCallback<typeof MISS_CALLBACK_DEF>[] = [];
+
+/**
+ * Call `fn(requestedUs, actualUs)` whenever a native sleep, ticker, loop or
+ * timer wakes more than `thresholdUs` late. Pass null to remove the handler.
+ */
+export function setMissHandler(
+  thresholdUs: number,
+  fn: ((requestedUs: number, actualUs: number) => void) | null,
+) {
+  if (missCallback) retiredMissCallbacks.push(missCallback);
+  missCallback = fn
+    ? Deno.UnsafeCallback.threadSafe(
+      MISS_CALLBACK_DEF,
+      (requestedUs, actualUs) => fn(Number(requestedUs), Number(actualUs)),
+    )
+    : null;
+  missCallback?.unref();
+  const threshold = Math.min(Math.max(0, Math.floor(thresholdUs)), 0xFFFF_FFFF);
+  lib.symbols.fast_set_miss_handler(threshold >>> 0, missCallback?.pointer ?? null);
+}
+
+const TIMER_CALLBACK_DEF = { parameters: ["u32", "i64"], result: "void" } as const;
+
+const pendingTimers = new Map<number, (lateUs: number) => void>();
+let timerCallback: Deno.UnsafeCallback<typeof TIMER_CALLBACK_DEF> | null = null;
+
+function sharedTimerCallback(): Deno.UnsafeCallback<typeof TIMER_CALLBACK_DEF> {
+  if (!timerCallback) {
+    timerCallback = Deno.UnsafeCallback.threadSafe(TIMER_CALLBACK_DEF, (id, lateUs) => {
+      const fn = pendingTimers.get(id);
+      pendingTimers.delete(id);
+      if (pendingTimers.size === 0) timerCallback?.unref();
+      fn?.(Number(lateUs));
+    });
+    timerCallback.unref();
+  }
+  return timerCallback;
+}
+
+/**
+ * One-shot native timer: `fn` runs once, `delayUs` from now, unless cancelled
+ * first. Returns the timer id for `cancelFastTimer`.
+ */
+export function scheduleFastTimer(delayUs: number, fn: (lateUs: number) => void): number {
+  const callback = sharedTimerCallback();
+  const delay = Math.min(Math.max(0, Math.floor(delayUs)), 0xFFFF_FFFF);
+  if (pendingTimers.size === 0) callback.ref();
+  const id = lib.symbols.fast_timer_schedule(delay >>> 0, callback.pointer);
+  pendingTimers.set(id, fn);
+  return id;
+}
+
+/** True if the timer was cancelled before firing; its callback will never run. */
+export function cancelFastTimer(id: number): boolean {
+  if (lib.symbols.fast_timer_cancel(id) !== 0) return false;
+  pendingTimers.delete(id);
+  if (pendingTimers.size === 0) timerCallback?.unref();
+  return true;
+}
+
+type BatchCallback = Deno.UnsafeCallback<typeof TIMER_CALLBACK_DEF>;
+
+const batchCallbacks = new Map<number, BatchCallback>();
+// A cancelled batch may still have one element in flight on the worker, so its
+// callback is kept alive rather than closed.
+const retiredBatchCallbacks: BatchCallback[] = [];
+
+/**
+ * Schedule `fn(index, lateUs)` at each absolute deadline (µs on the fastNowUs
+ * clock) under one native handle. Deadlines need not be sorted; equal ones
+ * fire in index order. Returns the handle for `cancelFastBatch`.
+ */
+export function scheduleFastBatch(
+  deadlinesUs: ArrayLike<number | bigint>,
+  fn: (index: number, lateUs: number) => void,
+): number {
+  const deadlines = BigUint64Array.from(deadlinesUs, (d) => BigInt(Math.max(0, Math.floor(Number(d)))));
+  if (deadlines.length === 0) return 0;
+  let remaining = deadlines.length;
+  let handle = 0;
+  const callback: BatchCallback = Deno.UnsafeCallback.threadSafe(TIMER_CALLBACK_DEF, (index, lateUs) => {
+    if (--remaining === 0) {
+      batchCallbacks.delete(handle);
+      setTimeout(() => callback.close(), 0);
+    }
+    fn(index, Number(lateUs));
+  });
+  handle = lib.symbols.fast_schedule_batch(deadlines, deadlines.length, callback.pointer);
+  if (handle === 0) {
+    callback.close();
+    return 0;
+  }
+  batchCallbacks.set(handle, callback);
+  return handle;
+}
+
+/** Cancel every element of a batch that has not fired yet. */
+export function cancelFastBatch(handle: number): boolean {
+  const cancelled = lib.symbols.fast_schedule_batch_cancel(handle) === 0;
+  const callback = batchCallbacks.get(handle);
+  if (cancelled && callback) {
+    batchCallbacks.delete(handle);
+    callback.unref();
+    retiredBatchCallbacks.push(callback);
+  }
+  return cancelled;
+}
+
+/**
+ * Test mode for CI: sleeps return at once and advance a virtual clock by
+ * `requested / scaleFactor` (0 = by the requested time). Call before the
+ * first sleep; it cannot be turned off.
+ */
+export function enableFastSleepMock(scaleFactor = 0) {
+  if (lib.symbols.fast_sleep_enable_mock(scaleFactor) !== 0) {
+    throw lastError("fast_sleep_enable_mock");
+  }
+}
+
+/** Nanoseconds slept so far in mock mode. */
+export function fastSleepVirtualTimeNs(): number {
+  return Number(lib.symbols.fast_sleep_get_virtual_time_ns());
+}
+
+/** ABI version of the loaded fastsleep library. */
+export function fastSleepAbiVersion(): number {
+  return lib.symbols.fast_sleep_abi_version();
+}
+
+/** Feature flags of the loaded library, e.g. `{ timer_resolution: false }`. */
+export function fastSleepCapabilities(): Record<string, boolean> {
+  const buf = readSizedBuffer((out, cap) => lib.symbols.fast_sleep_capabilities(out, cap));
+  return JSON.parse(new TextDecoder().decode(buf));
+}
+
+/**
+ * Write a span per timer fire to `path` until stopFastSleepTracing (see
+ * tools/native_tracing.ts). Needs a library built with the `tracing` feature.
+ */
+export function startFastSleepTracing(path: string, level: TraceLevel = "debug") {
+  if (!startTracing(lib.symbols.avtools_tracing_start, path, level)) {
+    throw lastError("Failed to start tracing");
+  }
+}
+
+/** Finish the trace file. */
+export function stopFastSleepTracing() {
+  if (lib.symbols.avtools_tracing_stop() !== 0) throw lastError("Failed to stop tracing");
+}
+
+/**
+ * Stop every tick loop still running. Also runs on unload; returns what was
+ * stopped so far.
+ */
+export function shutdownFastSleep(): ShutdownReport {
+  return shutdownAll((buf, cap) => lib.symbols.avtools_shutdown_all(buf, cap));
+}
+
+/** fastsleep's runtime metrics: jitter stats and open tickers and loops. */
+export function fastSleepMetrics(): RuntimeMetrics {
+  return readMetrics((buf, cap) => lib.symbols.fast_sleep_metrics_json(buf, cap));
+}
+
+/** Message of the last fastsleep export that panicked on this thread, if any. */
+export function fastSleepLastPanic(): string | null {
+  return lastNativePanic((buf, cap) => lib.symbols.fast_sleep_get_last_panic(buf, cap));
+}
+
+/** NativeError for the last failed synchronous fastsleep call. */
+function lastError(fallback: string) {
+  return nativeError({
+    code: () => lib.symbols.fast_sleep_last_error_code(),
+    message: (buf, cap) => lib.symbols.fast_sleep_last_error_message(buf, cap),
+  }, fallback);
+}
+
+const logSink = new NativeLogSink((cb) => lib.symbols.fast_sleep_set_log_callback(cb));
+
+/** Receive fastsleep's log lines instead of stderr; null restores stderr. */
+export function setFastSleepLogHandler(fn: LogHandler | null): Promise<void> {
+  return logSink.setHandler(fn);
+}
+
+/** Most verbose level fastsleep logs (default "info"). */
+export function setFastSleepLogLevel(level: LogLevel) {
+  lib.symbols.fast_sleep_set_log_level(logLevelId(level));
+}
@@ -0,0 +1,98 @@
+// native_bridge.ts
+// Window input forwarded as MIDI and MIDI forwarded as window input inside
+// the combined avtools_native library (`avtools_map_*`), without a round
+// trip through Deno. Window-to-MIDI rules run in the window pump; MIDI-to-
+// window rules run on the input's dispatch thread and their events come back
+// from the window's next `pollEvents`. The window and the MIDI ports must
+// come from the same combined library this opens, so the native handles are
+// shared. Mapping the same pair again replaces its rules.
+
+import { findCombinedLibrary } from "./native_library.ts";
+import { lastNativeError } from "./native_error.ts";
+
+const SYMBOLS = {
+  avtools_map_window_to_midi: { parameters: ["pointer", "u32", "buffer", "u32"], result: "u32" },
+  avtools_map_midi_to_window: { parameters: ["u32", "pointer", "buffer", "u32"], result: "u32" },
+  avtools_map_destroy: { parameters: ["u32"], result: "i32" },
+  midi_last_error_code: { parameters: [], result: "i32" },
+  midi_last_error_message: { parameters: ["buffer", "u32"], result: "u32" },
+} as const;
+
+export type WindowInput = "mouse_x" | "mouse_y" | "key" | "mouse_button";
+
+/**
+ * One window event to MIDI. Mouse positions need `cc`: `min` at the left or
+ * top edge to `max` at the other. Keys and buttons send note on/off with
+ * `note`, or `max`/`min` on press/release with `cc`.
+ */
+export type WindowToMidiRule = {
+  event: WindowInput;
+  /** For "key": the key as window events name it, e.g. "a" or "Space". */
+  key?: string;
+  /** For "mouse_button": 0 left, 1 right, 2 middle. */
+  button?: number;
+  /** 0–15 (default 0). */
+  channel?: number;
+  cc?: number;
+  note?: number;
+  /** Note-on velocity (default 100). */
+  velocity?: number;
+  /** Defaults 0 and 127; min above max inverts. */
+  min?: number;
+  max?: number;
+};
+
+/**
+ * One MIDI controller or note to window events. A CC moves the cursor across
+ * the window ("mouse_x"/"mouse_y") or holds a key or button while 64 or more;
+ * a note holds a key or button while on.
+ */
+export type MidiToWindowRule = {
+  /** 0–15; any channel if omitted. */
+  channel?: number;
+  cc?: number;
+  note?: number;
+  event: WindowInput;
+  key?: string;
+  button?: number;
+};
+
+export class NativeBridge {
+  #lib: Deno.DynamicLibrary<typeof SYMBOLS>;
+
+  constructor(libPath?: string | URL) {
+    const path = libPath ?? findCombinedLibrary(SYMBOLS);
+    if (!path) throw new Error("native bridge needs a combined avtools_native build with window and midi");
+    this.#lib = Deno.dlopen(path, SYMBOLS);
+  }
+
+  /** Map a window (`GpuWindow.nativeState`) to an output or pool handle; returns the mapping id. */
+  mapWindowToMidi(windowState: Deno.PointerValue, outputHandle: number, rules: WindowToMidiRule[]): number {
+    const json = new TextEncoder().encode(JSON.stringify({ version: 1, mappings: rules }));
+    return this.#check(this.#lib.symbols.avtools_map_window_to_midi(windowState, outputHandle, json, json.length));
+  }
+
+  /** Map an input handle to a window (`GpuWindow.nativeState`); returns the mapping id. */
+  mapMidiToWindow(inputHandle: number, windowState: Deno.PointerValue, rules: MidiToWindowRule[]): number {
+    const json = new TextEncoder().encode(JSON.stringify({ version: 1, mappings: rules }));
+    return this.#check(this.#lib.symbols.avtools_map_midi_to_window(inputHandle, windowState, json, json.length));
+  }
+
+  /** Stop a mapping; false if it was already gone. */
+  unmap(mapping: number): boolean {
+    return this.#lib.symbols.avtools_map_destroy(mapping) === 0;
+  }
+
+  close() {
+    this.#lib.close();
+  }
+
+  #check(mapping: number): number {
+    if (mapping !== 0) return mapping;
+    const err = lastNativeError({
+      code: () => this.#lib.symbols.midi_last_error_code(),
+      message: (buf, cap) => this.#lib.symbols.midi_last_error_message(buf, cap),
+    });
+    throw new Error(`mapping failed: ${err?.message ?? "unknown error"}`);
+  }
+}
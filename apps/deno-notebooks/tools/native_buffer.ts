@@ -0,0 +1,33 @@
+// native_buffer.ts
+// Readers for the two buffer contracts of the native libraries (see
+// avtools_ffi::buffer): sized buffers, called once for the length and again
+// to fill, and owned buffers returned as pointer + length and released with
+// the same library's `av_free`.
+
+/** Call `fill(null, 0)` for the length, then fill a buffer of that size. */
+export function readSizedBuffer(fill: (buf: Uint8Array | null, cap: number) => number): Uint8Array {
+  const len = fill(null, 0);
+  if (len === 0) return new Uint8Array(0);
+  const buf = new Uint8Array(len);
+  const written = fill(buf, buf.length);
+  return buf.subarray(0, Math.min(written, len));
+}
+
+/**
+ * Copy an owned buffer into JS memory and release it. `call` receives a
+ * length out-parameter and returns the buffer pointer.
+ */
+export function takeOwnedBuffer(
+  call: (outLen: Uint32Array) => Deno.PointerValue,
+  free: (ptr: Deno.PointerValue, len: number) => void,
+): Uint8Array {
+  const outLen = new Uint32Array(1);
+  const ptr = call(outLen);
+  const len = outLen[0];
+  if (ptr === null || len === 0) return new Uint8Array(0);
+  try {
+    return new Uint8Array(new Deno.UnsafePointerView(ptr).getArrayBuffer(len)).slice();
+  } finally {
+    free(ptr, len);
+  }
+}
@@ -0,0 +1,59 @@
+// native_error.ts
+// Shared reader for the `*_last_error_code` / `*_last_error_message` exports of
+// the native libraries. The record is thread-local on the native side, so it
+// only describes synchronous (non-`nonblocking`) calls.
+
+import { readSizedBuffer } from "./native_buffer.ts";
+
+/** Codes shared by every library; 100–199 deno_window, 200–299 midi_bridge, 300–399 fastsleep. */
+export const NativeErrorCode = {
+  ok: 0,
+  invalidArgument: 1,
+  invalidHandle: 2,
+  notFound: 3,
+  osError: 4,
+  unsupported: 5,
+  busy: 6,
+  /** The export panicked; the library stays usable. */
+  panic: 7,
+} as const;
+
+export class NativeError extends Error {
+  readonly code: number;
+
+  constructor(message: string, code: number) {
+    super(message);
+    this.name = "NativeError";
+    this.code = code;
+  }
+}
+
+export type LastErrorSymbols = {
+  code: () => number;
+  message: (buf: Uint8Array | null, cap: number) => number;
+};
+
+/** The library's last error, or null if its last fallible call succeeded. */
+export function lastNativeError(symbols: LastErrorSymbols): { code: number; message: string } | null {
+  const code = symbols.code();
+  if (code === NativeErrorCode.ok) return null;
+  return { code, message: new TextDecoder().decode(readSizedBuffer(symbols.message)) };
+}
+
+/**
+ * Message of the last export that panicked on the calling thread, or null.
+ * Unlike the last error it is kept until the next panic, so it can be read
+ * after later calls have succeeded.
+ */
+export function lastNativePanic(read: (buf: Uint8Array | null, cap: number) => number): string | null {
+  const buf = readSizedBuffer(read);
+  return buf.length === 0 ? null : new TextDecoder().decode(buf);
+}
+
+/** A NativeError for a failed call, using the native message when there is one. */
+export function nativeError(symbols: LastErrorSymbols, fallback: string): NativeError {
+  const last = lastNativeError(symbols);
+  return last
+    ? new NativeError(`${fallback}: ${last.message}`, last.code)
+    : new NativeError(fallback, NativeErrorCode.ok);
+}
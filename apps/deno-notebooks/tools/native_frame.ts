@@ -0,0 +1,76 @@
+// native_frame.ts
+// Shared layout of the binary frames built by the native libraries'
+// avtools_frame crate: a 32-byte header, fixed 16-byte records, then optional
+// fixed blocks and TLV extensions. Each library decodes its own records.
+
+import { readSizedBuffer } from "./native_buffer.ts";
+
+export const FRAME_HEADER_LEN = 32;
+export const FRAME_RECORD_LEN = 16;
+
+/** Header flag: TLV extensions follow the records and fixed blocks. */
+export const FRAME_FLAG_TLV = 0x8000;
+
+export type FrameHeader = {
+  magic: number;
+  version: number;
+  flags: number;
+  tsUs: number;
+  aux: [number, number];
+  count: number;
+};
+
+export function readFrameHeader(view: DataView): FrameHeader {
+  if (view.byteLength < FRAME_HEADER_LEN) {
+    throw new Error("Packet too small");
+  }
+  return {
+    magic: view.getUint32(0, true),
+    version: view.getUint16(4, true),
+    flags: view.getUint16(6, true),
+    tsUs: Number(view.getBigUint64(8, true)),
+    aux: [view.getUint32(16, true), view.getUint32(20, true)],
+    count: view.getUint32(24, true),
+  };
+}
+
+/**
+ * The TLV extensions of a frame with `recordCount` records and `blockCount`
+ * fixed blocks, in order; empty unless FRAME_FLAG_TLV is set. Payloads are
+ * views into `bytes`.
+ */
+export function readFrameExtensions(
+  bytes: Uint8Array,
+  recordCount: number,
+  blockCount: number,
+): { tag: number; payload: Uint8Array }[] {
+  const view = new DataView(bytes.buffer, bytes.byteOffset, bytes.byteLength);
+  if ((readFrameHeader(view).flags & FRAME_FLAG_TLV) === 0) return [];
+  const extensions = [];
+  let offset = FRAME_HEADER_LEN + (recordCount + blockCount) * FRAME_RECORD_LEN;
+  while (offset + 4 <= bytes.length) {
+    const tag = view.getUint16(offset, true);
+    const len = view.getUint16(offset + 2, true);
+    if (offset + 4 + len > bytes.length) break;
+    extensions.push({ tag, payload: bytes.subarray(offset + 4, offset + 4 + len) });
+    offset += 4 + len;
+  }
+  return extensions;
+}
+
+/** Signature of each library's `<prefix>_decode_frame_json` export. */
+export type DecodeFrameJson = (
+  ptr: Deno.PointerValue,
+  len: number,
+  out: Deno.PointerValue,
+  cap: number,
+) => number;
+
+/** Pretty-printed JSON for a frame, or null if the bytes are not a frame. */
+export function describeFrame(decode: DecodeFrameJson, bytes: Uint8Array): string | null {
+  const ptr = Deno.UnsafePointer.of(bytes);
+  const buf = readSizedBuffer((out, cap) =>
+    decode(ptr, bytes.length, out && Deno.UnsafePointer.of(out), cap)
+  );
+  return buf.length === 0 ? null : new TextDecoder().decode(buf);
+}
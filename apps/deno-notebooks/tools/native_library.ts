@@ -0,0 +1,35 @@
+// native_library.ts
+// The combined avtools_native library exports every component's symbols from
+// one file (see native/avtools_native). Each component's loader falls back to
+// it when its own library is not built, so building just avtools_native is
+// enough. A slim build without the component fails the probe and is skipped.
+
+const COMBINED_BASE = new URL("../native/target/release/", import.meta.url);
+
+function combinedCandidates(): string[] {
+  const os = Deno.build.os;
+  return os === "windows"
+    ? ["avtools_native.dll", "libavtools_native.dll"]
+    : os === "darwin"
+    ? ["libavtools_native.dylib"]
+    : ["libavtools_native.so"];
+}
+
+/** URL of a built avtools_native that exports all of `symbols`, or null. */
+export function findCombinedLibrary(symbols: Deno.ForeignLibraryInterface): URL | null {
+  for (const name of combinedCandidates()) {
+    const u = new URL(name, COMBINED_BASE);
+    try {
+      Deno.dlopen(u, symbols).close();
+      return u;
+    } catch {
+      // try next
+    }
+  }
+  return null;
+}
+
+/** Appended to a loader's "could not find" error. */
+export function combinedLibraryHint(): string {
+  return `or a combined avtools_native build in ${COMBINED_BASE.toString()}`;
+}
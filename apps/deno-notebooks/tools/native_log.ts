@@ -0,0 +1,48 @@
+// native_log.ts
+// Shared glue for the `*_set_log_callback` / `*_set_log_level` exports of the
+// native libraries (fastsleep, midi_bridge, deno_window).
+
+export const LOG_CALLBACK_DEF = { parameters: ["u32", "pointer", "u32"], result: "void" } as const;
+
+export type LogLevel = "off" | "error" | "warn" | "info" | "debug";
+export type LogHandler = (level: Exclude<LogLevel, "off">, message: string) => void;
+
+const LEVELS: LogLevel[] = ["off", "error", "warn", "info", "debug"];
+const decoder = new TextDecoder();
+
+export function logLevelId(level: LogLevel): number {
+  return LEVELS.indexOf(level);
+}
+
+/**
+ * Owns the callback installed in one library. The setter must be bound as a
+ * nonblocking symbol: native worker threads wait for the JS thread while the
+ * callback runs, and clearing waits for in-flight calls, so a blocking clear
+ * could deadlock.
+ */
+export class NativeLogSink {
+  #set: (cb: Deno.PointerValue) => Promise<void>;
+  #current: Deno.UnsafeCallback<typeof LOG_CALLBACK_DEF> | null = null;
+
+  constructor(set: (cb: Deno.PointerValue) => Promise<void>) {
+    this.#set = set;
+  }
+
+  /** Route native log lines to `fn`; null falls back to stderr. */
+  async setHandler(fn: LogHandler | null): Promise<void> {
+    const next = fn
+      ? Deno.UnsafeCallback.threadSafe(LOG_CALLBACK_DEF, (level, ptr, len) => {
+        const message = ptr && len > 0
+          ? decoder.decode(new Uint8Array(Deno.UnsafePointerView.getArrayBuffer(ptr, len)))
+          : "";
+        fn((LEVELS[level] ?? "debug") as Exclude<LogLevel, "off">, message);
+      })
+      : null;
+    next?.unref();
+    const previous = this.#current;
+    this.#current = next;
+    await this.#set(next?.pointer ?? null);
+    // The native side never calls a replaced callback after the setter returns.
+    previous?.close();
+  }
+}
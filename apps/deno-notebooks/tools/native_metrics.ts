@@ -0,0 +1,73 @@
+// native_metrics.ts
+// Runtime metrics for a performance HUD. Each library reports its own section
+// (`midi_metrics_json`, `window_metrics_json`, `fast_sleep_metrics_json`) and
+// the combined library all of them (`avtools_metrics_json`); mergeMetrics
+// puts per-library documents together so the result looks the same either
+// way. Sampling never waits on a realtime thread: anything that was locked at
+// that moment is null.
+
+import { readSizedBuffer } from "./native_buffer.ts";
+
+export type LatencySnapshot = {
+  count: number;
+  min_us: number;
+  median_us: number;
+  p95_us: number;
+  max_us: number;
+};
+
+export type MidiInputMetrics = {
+  handle: number;
+  /** Lifetime totals, unlike the per-packet counts in the frame header. */
+  dropped_raw: number;
+  dropped_note: number;
+  /** Dispatch minus arrival time. */
+  dispatch_latency: LatencySnapshot | null;
+  /** Bytes received per second over the last second. */
+  bandwidth_bps: number;
+  /** One of the input's threads panicked; it no longer delivers events. */
+  failed: boolean;
+};
+
+export type WindowMetrics = {
+  /** Native state pointer value, as from `Deno.UnsafePointer.value`. */
+  state: number;
+  queue_depth: number;
+  max_queue_depth: number;
+  frames: number;
+  pumps: number;
+  last_pump_us: number;
+  max_pump_us: number;
+  mean_pump_us: number;
+};
+
+export type RuntimeMetrics = {
+  /** Bumped when a field changes meaning or is removed. */
+  schema: number;
+  midi?: {
+    inputs: MidiInputMetrics[] | null;
+    outputs: number | null;
+    playbacks: number | null;
+    clocks: number | null;
+  };
+  window?: { windows: WindowMetrics[] | null };
+  fastsleep?: {
+    /** Same shape as `fastSleepStats()`. */
+    stats: Record<string, unknown>;
+    tickers: number | null;
+    loops: number | null;
+    /** Open loops whose thread panicked. */
+    failed_loops: number | null;
+  };
+};
+
+/** Call a library's `*_metrics_json` and parse the document. */
+export function readMetrics(fill: (buf: Uint8Array | null, cap: number) => number): RuntimeMetrics {
+  return JSON.parse(new TextDecoder().decode(readSizedBuffer(fill)));
+}
+
+/** One document with every library's section; the lowest schema wins. */
+export function mergeMetrics(...docs: RuntimeMetrics[]): RuntimeMetrics {
+  const schema = Math.min(...docs.map((d) => d.schema));
+  return Object.assign({}, ...docs, { schema });
+}
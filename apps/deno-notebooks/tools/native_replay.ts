@@ -0,0 +1,139 @@
+// native_replay.ts
+// Capture and deterministic replay of window and MIDI input inside the
+// combined avtools_native library (`avtools_capture_*`, `avtools_replay*`).
+// A capture records a window's key and mouse events and every message a MIDI
+// input receives, on the shared clock, into one JSON-lines file; a replay
+// plays it back on a native thread with the original relative timing, into a
+// window (`GpuWindow.nativeState`, events come back from `pollEvents`) and a
+// MIDI input, usually one from `MidiAccess.openMockInput`. As with
+// native_bridge.ts, the window and input must come from the library this
+// opens.
+
+import { findCombinedLibrary } from "./native_library.ts";
+import { lastNativeError } from "./native_error.ts";
+
+const SYMBOLS = {
+  avtools_capture_start: { parameters: ["pointer", "u32", "buffer", "u32"], result: "i32" },
+  avtools_capture_stop: { parameters: [], result: "i32" },
+  avtools_replay: { parameters: ["buffer", "u32", "f64", "u32"], result: "u32" },
+  avtools_replay_set_targets: { parameters: ["u32", "pointer", "u32"], result: "i32" },
+  avtools_replay_pause: { parameters: ["u32", "u8"], result: "i32" },
+  avtools_replay_seek: { parameters: ["u32", "u64"], result: "i32" },
+  avtools_replay_status: { parameters: ["u32", "buffer", "u32"], result: "u32" },
+  avtools_replay_destroy: { parameters: ["u32"], result: "i32" },
+  midi_last_error_code: { parameters: [], result: "i32" },
+  midi_last_error_message: { parameters: ["buffer", "u32"], result: "u32" },
+} as const;
+
+/** avtools_replay: start paused. */
+const REPLAY_PAUSED = 0x1;
+
+export type ReplayStatus = {
+  /** Position in the capture, µs from its start. */
+  position_us: number;
+  duration_us: number;
+  paused: boolean;
+  finished: boolean;
+  injected: number;
+  /** Entries that came due with no target set for them. */
+  skipped: number;
+  /** How late injections were against the capture's timing, in µs. */
+  drift: { last_us: number; mean_us: number; max_us: number };
+};
+
+export type ReplayTargets = {
+  /** `GpuWindow.nativeState`. */
+  window?: Deno.PointerValue;
+  /** Input handle, e.g. from `MidiAccess.openMockInput`. */
+  input?: number;
+};
+
+type Lib = Deno.DynamicLibrary<typeof SYMBOLS>;
+
+export class NativeReplay {
+  #lib: Lib;
+
+  constructor(libPath?: string | URL) {
+    const path = libPath ?? findCombinedLibrary(SYMBOLS);
+    if (!path) throw new Error("native replay needs a combined avtools_native build with window and midi");
+    this.#lib = Deno.dlopen(path, SYMBOLS);
+  }
+
+  /** Start capturing either or both sources to `path`; only one capture runs at a time. */
+  startCapture(path: string, sources: ReplayTargets) {
+    const bytes = new TextEncoder().encode(path);
+    const ok = this.#lib.symbols.avtools_capture_start(sources.window ?? null, sources.input ?? 0, bytes, bytes.length);
+    if (ok !== 0) this.#fail("capture failed");
+  }
+
+  /** Stop the capture and finish its file. */
+  stopCapture() {
+    if (this.#lib.symbols.avtools_capture_stop() !== 0) this.#fail("capture stop failed");
+  }
+
+  /**
+   * Load a capture and start replaying it at `speed` times the original pace
+   * once `targets` are set. With `paused`, nothing plays until `resume()`.
+   */
+  replay(path: string, targets: ReplayTargets, options: { speed?: number; paused?: boolean } = {}): Replay {
+    const bytes = new TextEncoder().encode(path);
+    const id = this.#lib.symbols.avtools_replay(bytes, bytes.length, options.speed ?? 1, REPLAY_PAUSED);
+    if (id === 0) this.#fail("replay failed");
+    const replay = new Replay(this.#lib, id);
+    try {
+      const ok = this.#lib.symbols.avtools_replay_set_targets(id, targets.window ?? null, targets.input ?? 0);
+      if (ok !== 0) this.#fail("replay targets failed");
+    } catch (err) {
+      replay.destroy();
+      throw err;
+    }
+    if (!options.paused) replay.resume();
+    return replay;
+  }
+
+  close() {
+    this.#lib.close();
+  }
+
+  #fail(what: string): never {
+    const err = lastNativeError({
+      code: () => this.#lib.symbols.midi_last_error_code(),
+      message: (buf, cap) => this.#lib.symbols.midi_last_error_message(buf, cap),
+    });
+    throw new Error(`${what}: ${err?.message ?? "unknown error"}`);
+  }
+}
+
+export class Replay {
+  #lib: Lib;
+  #id: number;
+
+  constructor(lib: Lib, id: number) {
+    this.#lib = lib;
+    this.#id = id;
+  }
+
+  pause() {
+    this.#lib.symbols.avtools_replay_pause(this.#id, 1);
+  }
+
+  resume() {
+    this.#lib.symbols.avtools_replay_pause(this.#id, 0);
+  }
+
+  /** Continue from `positionUs` into the capture; earlier entries are not replayed. */
+  seek(positionUs: number) {
+    this.#lib.symbols.avtools_replay_seek(this.#id, BigInt(Math.max(0, Math.round(positionUs))));
+  }
+
+  status(): ReplayStatus | null {
+    const buf = new Uint8Array(512);
+    const len = this.#lib.symbols.avtools_replay_status(this.#id, buf, buf.length);
+    if (len === 0 || len > buf.length) return null;
+    return JSON.parse(new TextDecoder().decode(buf.subarray(0, len)));
+  }
+
+  destroy() {
+    this.#lib.symbols.avtools_replay_destroy(this.#id);
+  }
+}
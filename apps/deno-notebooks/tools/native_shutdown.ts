@@ -0,0 +1,23 @@
+// native_shutdown.ts
+// `avtools_shutdown_all`: every native library can close whatever it still
+// has open (MIDI inputs and their threads, held notes, outputs, tick loops,
+// windows) and report how many of each it closed. Idempotent, so it is safe
+// from both an explicit close and the unload handler.
+
+import { readSizedBuffer } from "./native_buffer.ts";
+
+/** Resources closed so far by kind, e.g. `{ inputs: 1, held_notes: 3 }`. */
+export type ShutdownReport = Record<string, number>;
+
+/** Call a library's `avtools_shutdown_all` and parse its report. */
+export function shutdownAll(fill: (buf: Uint8Array | null, cap: number) => number): ShutdownReport {
+  const text = new TextDecoder().decode(readSizedBuffer(fill));
+  return text ? JSON.parse(text) : {};
+}
+
+/** Run `fn` when the script unloads. Returns a function that cancels it. */
+export function onUnload(fn: () => void): () => void {
+  const listener = () => fn();
+  globalThis.addEventListener("unload", listener);
+  return () => globalThis.removeEventListener("unload", listener);
+}
@@ -0,0 +1,29 @@
+// native_tracing.ts
+// `avtools_tracing_start` / `avtools_tracing_stop`: a native library built
+// with its `tracing` cargo feature writes timing spans of its hot paths
+// (window pump, MIDI coalescer and dispatch ticks, timer fires) to a file.
+// Paths ending in `.jsonl` get one JSON object per line; anything else gets
+// the Chrome trace format, which loads in Perfetto or chrome://tracing.
+// Builds without the feature fail to start with `unsupported`.
+
+export type TraceLevel = "error" | "warn" | "info" | "debug" | "trace";
+
+const LEVELS: TraceLevel[] = ["error", "warn", "info", "debug", "trace"];
+
+/** Native level id (1–5, as avtools_log numbers them). */
+export function traceLevelId(level: TraceLevel): number {
+  return LEVELS.indexOf(level) + 1;
+}
+
+/**
+ * Call a library's `avtools_tracing_start`; false if it failed (the
+ * library's last error says why).
+ */
+export function startTracing(
+  start: (path: Uint8Array, len: number, level: number) => number,
+  path: string,
+  level: TraceLevel,
+): boolean {
+  const bytes = new TextEncoder().encode(path);
+  return start(bytes, bytes.length, traceLevelId(level)) === 0;
+}
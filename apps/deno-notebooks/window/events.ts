@@ -1,7 +1,43 @@
-export type WindowEvent =
-  | { type: "key"; key: string; down: boolean }
+/**
+ * `ts_us` is when the event was queued, on the shared avtools clock (see
+ * fastNowUs). `window_token` is 0 for the window itself, else the token
+ * `openAdditionalWindow` returned for the window it came from.
+ */
+export type WindowEvent = { ts_us: number; window_token: number } & (
+  // `key` names the logical key ("a", "Space", "ArrowLeft"); `text` is what a
+  // press types (layout and modifiers applied), null for releases and keys
+  // that type nothing. Use `text` for text input.
+  | { type: "key"; key: string; text: string | null; down: boolean }
   | { type: "mouse_move"; x: number; y: number }
   | { type: "mouse_button"; button: number; down: boolean; x: number; y: number }
   | { type: "scroll"; dx: number; dy: number }
   | { type: "resize"; width: number; height: number }
-  | { type: "close" };
+  | { type: "close" }
+  // Keyboard focus gained or lost (alt-tab, clicking another window).
+  | { type: "focus"; gained: boolean }
+  // A file dropped on the window, one event per file.
+  | { type: "file_drop"; path: string }
+  // A file dragged over the window; `active` false when the drag leaves
+  // without dropping. A drop also ends the hover.
+  | { type: "file_hover"; path: string; active: boolean }
+  | { type: "frame_stats"; count: number; fps: number }
+  // Bytes passed to `sendUserEvent`, base64-encoded.
+  | { type: "user"; data: string }
+);
+
+/** Bits for `pollEvents(mask)`; events whose bit is clear are discarded. */
+export const EventMask = {
+  key: 0x1,
+  mouse_move: 0x2,
+  mouse_button: 0x4,
+  scroll: 0x8,
+  resize: 0x10,
+  close: 0x20,
+  frame_stats: 0x40,
+  user: 0x80,
+  focus: 0x100,
+  file_drop: 0x200,
+  file_hover: 0x400,
+} as const satisfies Record<WindowEvent["type"], number>;
+
+export const EVENT_MASK_ALL = 0x7ff;
@@ -1,12 +1,76 @@
+import { readSizedBuffer } from "../tools/native_buffer.ts";
+import { combinedLibraryHint, findCombinedLibrary } from "../tools/native_library.ts";
+
 export const FFI_SYMBOLS = {
   create_window: { parameters: ["u32", "u32", "pointer", "u32"], result: "pointer" },
+  create_window_ex: { parameters: ["buffer", "u32"], result: "pointer" },
+  create_child_window: {
+    parameters: ["pointer", "u32", "u32", "u32", "u32"],
+    result: "pointer",
+  },
+  create_additional_window: { parameters: ["pointer", "u32", "u32", "buffer", "u32"], result: "u32" },
+  wait_for_window_ready: { parameters: ["pointer", "u32"], result: "i32" },
   get_raw_window_handle: { parameters: ["pointer"], result: "usize" },
   get_raw_display_handle: { parameters: ["pointer"], result: "usize" },
   get_window_system: { parameters: ["pointer"], result: "u32" },
   poll_events: { parameters: ["pointer", "pointer", "u32"], result: "u32" },
   resize_window: { parameters: ["pointer", "u32", "u32"], result: "void" },
   get_window_size: { parameters: ["pointer", "pointer", "pointer"], result: "void" },
+  set_window_position: { parameters: ["pointer", "i32", "i32"], result: "i32" },
+  get_window_position: { parameters: ["pointer", "buffer", "buffer"], result: "i32" },
+  get_window_size_by_token: { parameters: ["pointer", "u32", "buffer", "buffer"], result: "i32" },
+  get_raw_window_handle_by_token: { parameters: ["pointer", "u32"], result: "usize" },
+  resize_window_by_token: { parameters: ["pointer", "u32", "u32", "u32"], result: "i32" },
+  destroy_window_by_token: { parameters: ["pointer", "u32"], result: "i32" },
+  set_window_corner_preference: { parameters: ["pointer", "u32"], result: "i32" },
+  set_hdr_hint: { parameters: ["pointer", "u8"], result: "i32" },
+  set_gpu_preference: { parameters: ["pointer", "u32"], result: "i32" },
+  get_gpu_preference: { parameters: ["pointer"], result: "u32" },
+  get_display_color_space: { parameters: ["pointer", "pointer", "u32"], result: "u32" },
+  list_monitors: { parameters: ["pointer", "pointer", "u32"], result: "u32" },
+  get_display_refresh_rate: { parameters: ["pointer"], result: "f64" },
+  get_vsync_interval_us: { parameters: ["pointer"], result: "u64" },
+  poll_events_filtered: { parameters: ["pointer", "u64", "pointer", "u32"], result: "u32" },
+  poll_events_msgpack: { parameters: ["pointer", "pointer", "u32"], result: "u32" },
+  set_event_format: { parameters: ["pointer", "u8"], result: "i32" },
+  set_accessibility_label: { parameters: ["pointer", "pointer", "u32"], result: "i32" },
+  set_accessibility_role: { parameters: ["pointer", "u32"], result: "i32" },
+  set_content_protection: { parameters: ["pointer", "u8"], result: "i32" },
+  set_taskbar_thumbnail_clip: { parameters: ["pointer", "u32", "u32", "u32", "u32"], result: "i32" },
+  clear_taskbar_thumbnail_clip: { parameters: ["pointer"], result: "i32" },
+  send_window_user_event: { parameters: ["pointer", "pointer", "u32"], result: "i32" },
+  set_pointer_capture: { parameters: ["pointer", "u8"], result: "i32" },
+  set_auto_capture_on_drag: { parameters: ["pointer", "u8"], result: "i32" },
+  begin_window_drag_move: { parameters: ["pointer"], result: "i32" },
+  begin_window_drag_resize: { parameters: ["pointer", "u32"], result: "i32" },
+  set_hit_test_callback: { parameters: ["pointer", "function"], result: "i32" },
+  set_before_destroy_callback: { parameters: ["pointer", "function"], result: "i32" },
+  subscribe_events: { parameters: ["pointer", "u64", "function"], result: "i32" },
+  unsubscribe_events: { parameters: ["pointer"], result: "i32" },
+  set_parent_window_handle: { parameters: ["pointer", "usize", "usize", "u32"], result: "i32" },
+  capture_window_pixels: {
+    parameters: ["pointer", "pointer", "u32", "pointer", "pointer"],
+    result: "i32",
+  },
+  get_frame_count: { parameters: ["pointer"], result: "u64" },
+  get_frames_per_second: { parameters: ["pointer"], result: "f64" },
+  avtools_clock_now_us: { parameters: [], result: "u64" },
+  avtools_clock_offset: { parameters: ["u32", "u32"], result: "i64" },
+  avtools_tracing_start: { parameters: ["buffer", "u32", "u32"], result: "i32" },
+  avtools_tracing_stop: { parameters: [], result: "i32" },
+  av_alloc: { parameters: ["u32"], result: "pointer" },
+  av_free: { parameters: ["pointer", "u32"], result: "void" },
+  window_abi_version: { parameters: [], result: "u32" },
+  window_capabilities: { parameters: ["pointer", "u32"], result: "u32" },
+  window_decode_frame_json: { parameters: ["pointer", "u32", "pointer", "u32"], result: "u32" },
+  window_last_error_code: { parameters: [], result: "i32" },
+  window_last_error_message: { parameters: ["buffer", "u32"], result: "u32" },
+  window_get_last_panic: { parameters: ["buffer", "u32"], result: "u32" },
+  window_metrics_json: { parameters: ["buffer", "u32"], result: "u32" },
+  window_set_log_callback: { parameters: ["function"], result: "void", nonblocking: true },
+  window_set_log_level: { parameters: ["u32"], result: "i32" },
   destroy_window: { parameters: ["pointer"], result: "void" },
+  avtools_shutdown_all: { parameters: ["buffer", "u32"], result: "u32" },
 } as const;
 
 export type WindowSymbols = typeof FFI_SYMBOLS;
@@ -15,7 +79,7 @@ export type WindowLibrary = Deno.DynamicLibrary<WindowSymbols>;
 const textEncoder = new TextEncoder();
 
 function defaultLibUrl(): URL {
-  const base = new URL("../native/deno_window/target/release/", import.meta.url);
+  const base = new URL("../native/target/release/", import.meta.url);
   const os = Deno.build.os;
   const candidates =
     os === "windows"
@@ -34,9 +98,12 @@ function defaultLibUrl(): URL {
       // try next
     }
   }
+  const combined = findCombinedLibrary(FFI_SYMBOLS);
+  if (combined) return combined;
 
   throw new Error(
-    `Could not find native deno_window library in ${base.toString()} (tried ${candidates.join(", ")})`,
+    `Could not find native deno_window library in ${base.toString()} (tried ${candidates.join(", ")}), ` +
+      combinedLibraryHint(),
   );
 }
 
@@ -50,3 +117,11 @@ export function encodeTitle(title: string): { ptr: Deno.PointerValue; len: numbe
   const ptr = Deno.UnsafePointer.of(bytes);
   return { ptr, len: bytes.length };
 }
+
+/** Feature flags of a loaded deno_window library. */
+export function readCapabilities(lib: WindowLibrary): Record<string, boolean> {
+  const buf = readSizedBuffer((out, cap) =>
+    lib.symbols.window_capabilities(out && Deno.UnsafePointer.of(out), cap)
+  );
+  return JSON.parse(new TextDecoder().decode(buf));
+}
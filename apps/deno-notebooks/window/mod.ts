@@ -1,4 +1,4 @@
 export { createGpuWindow, type GpuWindow, type WindowOptions } from "./window.ts";
 export { createBlitPipeline, blit, type BlitPipeline } from "./blit.ts";
 export { startRenderLoop, type RenderLoopOptions } from "./render_loop.ts";
-export type { WindowEvent } from "./events.ts";
+export { EVENT_MASK_ALL, EventMask, type WindowEvent } from "./events.ts";
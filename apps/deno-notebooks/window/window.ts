@@ -1,13 +1,20 @@
 /// <reference lib="dom" />
 
-import { encodeTitle, openLibrary } from "./ffi.ts";
-import type { WindowEvent } from "./events.ts";
+import { openLibrary } from "./ffi.ts";
+import { EventMask, type WindowEvent } from "./events.ts";
+import { nativeError } from "../tools/native_error.ts";
+import { onUnload, shutdownAll } from "../tools/native_shutdown.ts";
+import { readMetrics, type RuntimeMetrics } from "../tools/native_metrics.ts";
 
 export interface WindowOptions {
   width: number;
   height: number;
   title?: string;
+  /** Open the window centred on the primary monitor. */
+  center?: boolean;
   libPath?: string;
+  /** How long to wait for the native window to appear (default 2000 ms). */
+  readyTimeoutMs?: number;
 }
 
 export interface GpuWindow {
@@ -18,11 +25,117 @@ export interface GpuWindow {
   width: number;
   height: number;
   closed: boolean;
-  pollEvents(): WindowEvent[];
+  /**
+   * The native window state, for exports of the same library that take a
+   * window (e.g. tools/native_bridge.ts); null once closed.
+   */
+  readonly nativeState: Deno.PointerValue;
+  /**
+   * Drain queued events. With `mask` (OR of `EventMask` bits) only matching
+   * events are returned; the rest are dropped. Resize and close are always
+   * tracked internally regardless of the mask.
+   */
+  pollEvents(mask?: number): WindowEvent[];
+  /**
+   * Call `fn` with each event matching `mask` (OR of `EventMask` bits) as the
+   * native side handles it, while pollEvents pumps the window, instead of
+   * returning it from pollEvents. Resize and close still come from
+   * pollEvents. Replaces any previous subscription; returns an unsubscribe.
+   */
+  subscribeEvents(mask: number, fn: (event: WindowEvent) => void): () => void;
+  /**
+   * Run `fn` when the native window is about to be destroyed (by `close`, a
+   * parent window's close or the shutdown on unload), while its surface is
+   * still valid: the place to wait for the GPU and unconfigure the context.
+   * Null removes it.
+   */
+  onBeforeDestroy(fn: (() => void) | null): void;
+  /**
+   * Queue `data` on the window's event loop; it comes back from pollEvents as
+   * a "user" event. Returns false once the loop has exited.
+   */
+  sendUserEvent(data: Uint8Array): boolean;
+  /** Confine the cursor to the window; returns false if the platform refused. */
+  setPointerCapture(captured: boolean): boolean;
+  /** Capture the pointer while the left button is held (off by default). */
+  setAutoCaptureOnDrag(enabled: boolean): void;
+  /**
+   * Let the window manager move the window, from a left-button-down on a
+   * custom title bar. Returns false where unsupported (some Wayland
+   * compositors).
+   */
+  beginDragMove(): boolean;
+  /** Like beginDragMove, resizing from an edge or corner; "center" moves. */
+  beginDragResize(direction: DragDirection): boolean;
+  /**
+   * Make parts of the window click-through: `hitTest(x, y)` (physical client
+   * pixels) returns false where clicks should pass to whatever is below.
+   * Called while events are polled; null captures clicks everywhere again.
+   * Throws on Wayland.
+   */
+  setHitTest(hitTest: ((x: number, y: number) => boolean) | null): void;
+  /**
+   * Embed the window in another application's window: an X11 window id, an
+   * HWND or an NSView*, for the same window system as this window. `display`
+   * is the host's Xlib Display* if it shares this process, else this
+   * window's connection is used. Throws on Wayland.
+   */
+  setParentWindow(handle: bigint, display?: bigint): void;
+  /**
+   * Open another top-level window on this window's event loop. Its events
+   * come from pollEvents with `window_token` set to the returned token; it
+   * closes when the user closes it (after its close event), with
+   * closeAdditionalWindow or with this window. It has no surface of its own.
+   */
+  openAdditionalWindow(width: number, height: number, title?: string): number;
+  /**
+   * Outer top-left corner in physical screen pixels; null where the
+   * platform cannot report it (Wayland).
+   */
+  position(): [number, number] | null;
+  /** Move the window (physical screen pixels); throws on Wayland. */
+  setPosition(x: number, y: number): void;
+  /** Physical size of this window (token 0) or an additional one, or null. */
+  windowSize(token: number): [number, number] | null;
+  /**
+   * Native handle of an additional window (as `get_raw_window_handle`), e.g.
+   * for `Deno.UnsafeWindowSurface` with this window's `system` and display.
+   */
+  additionalWindowHandle(token: number): bigint;
+  resizeAdditionalWindow(token: number, width: number, height: number): void;
+  closeAdditionalWindow(token: number): void;
+  /**
+   * Read back the window's client area as RGBA8 rows, top row first. Throws
+   * where the platform cannot capture (Wayland, or a failed OS call); on macOS
+   * the process needs Screen Recording permission.
+   */
+  capturePixels(): { width: number; height: number; pixels: Uint8Array };
+  /**
+   * GPU tier to prefer on dual-GPU machines; stored on the native window so
+   * adapter selection can read it back with `gpuPreference`.
+   */
+  setGpuPreference(pref: GPUPowerPreference | "default"): void;
+  /** The stored preference, ready for `requestAdapter({ powerPreference })`. */
+  readonly gpuPreference: GPUPowerPreference | undefined;
+  /**
+   * Queue depth, frame count and pump timings of every window of the loaded
+   * library (see tools/native_metrics.ts). Throws once closed.
+   */
+  metrics(): RuntimeMetrics;
   present(): void;
   close(): void;
 }
 
+/** poll_events: the queued events do not fit in the buffer. */
+const ERR_EVENTS_TOO_LARGE = 104;
+
+/** Indexed by the native begin_window_drag_resize direction. */
+const DRAG_DIRECTIONS = ["center", "n", "ne", "e", "se", "s", "sw", "w", "nw"] as const;
+export type DragDirection = typeof DRAG_DIRECTIONS[number];
+
+/** Indexed by the native GPU preference id. */
+const GPU_PREFERENCES = ["default", "low-power", "high-performance"] as const;
+
 function systemFromId(id: number): "cocoa" | "x11" | "wayland" {
   if (id === 1) return "x11";
   if (id === 2) return "wayland";
@@ -33,12 +146,32 @@ export async function createGpuWindow(device: GPUDevice, options: WindowOptions)
   const debug = Deno.env.get("DENO_WINDOW_DEBUG") !== undefined;
   const lib = openLibrary(options.libPath);
   const title = options.title ?? "raw-webgpu";
-  const { ptr, len } = encodeTitle(title);
+  const optionsJson = new TextEncoder().encode(JSON.stringify({
+    version: 1,
+    width: options.width,
+    height: options.height,
+    title,
+    center: options.center ?? false,
+  }));
 
-  const state = lib.symbols.create_window(options.width, options.height, ptr, len);
+  const state = lib.symbols.create_window_ex(optionsJson, optionsJson.length);
   if (!state) {
+    const err = nativeError({
+      code: () => lib.symbols.window_last_error_code(),
+      message: (buf, cap) => lib.symbols.window_last_error_message(buf, cap),
+    }, "Failed to create native window");
     lib.close();
-    throw new Error("Failed to create native window");
+    throw err;
+  }
+  // Blocks the JS thread: the event loop has to be pumped from this thread.
+  if (lib.symbols.wait_for_window_ready(state, options.readyTimeoutMs ?? 2000) !== 0) {
+    const err = nativeError({
+      code: () => lib.symbols.window_last_error_code(),
+      message: (buf, cap) => lib.symbols.window_last_error_message(buf, cap),
+    }, "Native window did not appear");
+    lib.symbols.destroy_window(state);
+    lib.close();
+    throw err;
   }
 
   let windowHandle = 0n;
@@ -134,6 +267,11 @@ export async function createGpuWindow(device: GPUDevice, options: WindowOptions)
   let height = initialHeight;
   let closed = false;
   let pendingCloseEvent = false;
+  // If the script exits without close(), destroy every window still open in
+  // the library (all GpuWindows share it).
+  const cancelUnload = onUnload(() => {
+    shutdownAll((buf, cap) => lib.symbols.avtools_shutdown_all(buf, cap));
+  });
 
   const markClosed = () => {
     if (!closed) {
@@ -142,26 +280,53 @@ export async function createGpuWindow(device: GPUDevice, options: WindowOptions)
     }
   };
 
-  const pollEvents = (): WindowEvent[] => {
+  // Synthetic close (surface lost) stamped on the same clock as native events.
+  const closeEvent = (): WindowEvent => ({
+    ts_us: Number(lib.symbols.avtools_clock_now_us()),
+    window_token: 0,
+    type: "close",
+  });
+
+  let eventBufferSize = 65536;
+  const pollEvents = (mask?: number): WindowEvent[] => {
     if (closed) {
       if (pendingCloseEvent) {
         pendingCloseEvent = false;
-        return [{ type: "close" }];
+        return [closeEvent()];
       }
       return [];
     }
-    const buf = new Uint8Array(65536);
-    const written = lib.symbols.poll_events(state, Deno.UnsafePointer.of(buf), buf.length);
+    let buf = new Uint8Array(eventBufferSize);
+    // Resize and close drive the surface and `closed`, so always fetch them.
+    const poll = () =>
+      mask === undefined
+        ? lib.symbols.poll_events(state, Deno.UnsafePointer.of(buf), buf.length)
+        : lib.symbols.poll_events_filtered(
+          state,
+          BigInt(mask | EventMask.resize | EventMask.close),
+          Deno.UnsafePointer.of(buf),
+          buf.length,
+        );
+    let written = poll();
+    // Too much to fit (long dropped file paths): the events stay queued.
+    while (!written && lib.symbols.window_last_error_code() === ERR_EVENTS_TOO_LARGE) {
+      eventBufferSize *= 4;
+      buf = new Uint8Array(eventBufferSize);
+      written = poll();
+    }
     if (!written) {
       if (pendingCloseEvent) {
         pendingCloseEvent = false;
-        return [{ type: "close" }];
+        return [closeEvent()];
       }
       return [];
     }
     const text = new TextDecoder().decode(buf.subarray(0, written));
     const events = JSON.parse(text) as WindowEvent[];
     for (const ev of events) {
+      if (ev.window_token !== 0) {
+        continue;
+      }
       if (ev.type === "resize") {
         width = ev.width;
         height = ev.height;
@@ -170,9 +335,260 @@ export async function createGpuWindow(device: GPUDevice, options: WindowOptions)
         markClosed();
       }
     }
+    if (mask !== undefined) {
+      return events.filter((ev) => (EventMask[ev.type] & mask) !== 0);
+    }
     return events;
   };
 
+  let eventCallback: Deno.UnsafeCallback<{ parameters: ["pointer", "u32"]; result: "void" }> | null = null;
+  const releaseEventCallback = () => {
+    eventCallback?.close();
+    eventCallback = null;
+  };
+
+  const subscribeEvents = (mask: number, fn: (event: WindowEvent) => void) => {
+    if (closed) {
+      throw new Error("Window is closed");
+    }
+    const callback = new Deno.UnsafeCallback(
+      { parameters: ["pointer", "u32"], result: "void" } as const,
+      (ptr, len) => {
+        if (ptr === null) return;
+        const bytes = new Uint8Array(len);
+        Deno.UnsafePointerView.copyInto(ptr, bytes);
+        fn(JSON.parse(new TextDecoder().decode(bytes)) as WindowEvent);
+      },
+    );
+    // pollEvents tracks resize and close itself.
+    const nativeMask = BigInt(mask & ~(EventMask.resize | EventMask.close));
+    if (lib.symbols.subscribe_events(state, nativeMask, callback.pointer) !== 0) {
+      callback.close();
+      throw nativeError({
+        code: () => lib.symbols.window_last_error_code(),
+        message: (buf, cap) => lib.symbols.window_last_error_message(buf, cap),
+      }, "Failed to subscribe to events");
+    }
+    releaseEventCallback();
+    eventCallback = callback;
+    return () => {
+      if (eventCallback !== callback) return;
+      if (!closed) lib.symbols.unsubscribe_events(state);
+      releaseEventCallback();
+    };
+  };
+
+  let beforeDestroyCallback: Deno.UnsafeCallback<{ parameters: []; result: "void" }> | null = null;
+  const releaseBeforeDestroy = () => {
+    beforeDestroyCallback?.close();
+    beforeDestroyCallback = null;
+  };
+
+  const onBeforeDestroy = (fn: (() => void) | null) => {
+    if (closed) {
+      return;
+    }
+    const callback = fn ? new Deno.UnsafeCallback({ parameters: [], result: "void" } as const, fn) : null;
+    if (lib.symbols.set_before_destroy_callback(state, callback?.pointer ?? null) !== 0) {
+      callback?.close();
+      throw new Error("set_before_destroy_callback failed");
+    }
+    releaseBeforeDestroy();
+    beforeDestroyCallback = callback;
+  };
+
+  const sendUserEvent = (data: Uint8Array): boolean => {
+    if (closed) {
+      return false;
+    }
+    return lib.symbols.send_window_user_event(state, Deno.UnsafePointer.of(data), data.length) === 0;
+  };
+
+  const setPointerCapture = (captured: boolean): boolean => {
+    if (closed) {
+      return false;
+    }
+    return lib.symbols.set_pointer_capture(state, captured ? 1 : 0) === 0;
+  };
+
+  const beginDragMove = (): boolean => {
+    if (closed) {
+      return false;
+    }
+    return lib.symbols.begin_window_drag_move(state) === 0;
+  };
+
+  const beginDragResize = (direction: DragDirection): boolean => {
+    if (closed) {
+      return false;
+    }
+    return lib.symbols.begin_window_drag_resize(state, DRAG_DIRECTIONS.indexOf(direction)) === 0;
+  };
+
+  const setAutoCaptureOnDrag = (enabled: boolean) => {
+    if (!closed) {
+      lib.symbols.set_auto_capture_on_drag(state, enabled ? 1 : 0);
+    }
+  };
+
+  let hitTestCallback: Deno.UnsafeCallback<{ parameters: ["f64", "f64"]; result: "u8" }> | null = null;
+  const releaseHitTest = () => {
+    hitTestCallback?.close();
+    hitTestCallback = null;
+  };
+
+  const setHitTest = (hitTest: ((x: number, y: number) => boolean) | null) => {
+    if (closed) {
+      return;
+    }
+    const callback = hitTest
+      ? new Deno.UnsafeCallback(
+        { parameters: ["f64", "f64"], result: "u8" } as const,
+        (x, y) => (hitTest(x, y) ? 1 : 0),
+      )
+      : null;
+    if (lib.symbols.set_hit_test_callback(state, callback?.pointer ?? null) !== 0) {
+      callback?.close();
+      throw nativeError({
+        code: () => lib.symbols.window_last_error_code(),
+        message: (buf, cap) => lib.symbols.window_last_error_message(buf, cap),
+      }, "Failed to set hit test");
+    }
+    // The native side now holds the new callback (or none).
+    releaseHitTest();
+    hitTestCallback = callback;
+  };
+
+  const setParentWindow = (handle: bigint, display = 0n) => {
+    if (closed) {
+      throw new Error("Window is closed");
+    }
+    if (lib.symbols.set_parent_window_handle(state, handle, display, systemId) !== 0) {
+      throw nativeError({
+        code: () => lib.symbols.window_last_error_code(),
+        message: (buf, cap) => lib.symbols.window_last_error_message(buf, cap),
+      }, "Failed to set parent window");
+    }
+  };
+
+  const position = (): [number, number] | null => {
+    if (closed) {
+      return null;
+    }
+    const x = new Int32Array(1);
+    const y = new Int32Array(1);
+    if (lib.symbols.get_window_position(state, x, y) !== 0) {
+      return null;
+    }
+    return [x[0], y[0]];
+  };
+
+  const setPosition = (x: number, y: number) => {
+    if (closed) {
+      throw new Error("Window is closed");
+    }
+    if (lib.symbols.set_window_position(state, Math.round(x), Math.round(y)) !== 0) {
+      throw nativeError({
+        code: () => lib.symbols.window_last_error_code(),
+        message: (buf, cap) => lib.symbols.window_last_error_message(buf, cap),
+      }, "Failed to set window position");
+    }
+  };
+
+  const openAdditionalWindow = (width: number, height: number, title = ""): number => {
+    if (closed) {
+      throw new Error("Window is closed");
+    }
+    const bytes = new TextEncoder().encode(title);
+    const token = lib.symbols.create_additional_window(state, width, height, bytes, bytes.length);
+    if (token === 0) {
+      throw nativeError({
+        code: () => lib.symbols.window_last_error_code(),
+        message: (buf, cap) => lib.symbols.window_last_error_message(buf, cap),
+      }, "Failed to open additional window");
+    }
+    return token;
+  };
+
+  const windowSize = (token: number): [number, number] | null => {
+    if (closed) {
+      return null;
+    }
+    const w = new Uint32Array(1);
+    const h = new Uint32Array(1);
+    if (lib.symbols.get_window_size_by_token(state, token, w, h) !== 0) {
+      return null;
+    }
+    return [w[0], h[0]];
+  };
+
+  const additionalWindowHandle = (token: number): bigint => {
+    if (closed) {
+      return 0n;
+    }
+    return BigInt(lib.symbols.get_raw_window_handle_by_token(state, token));
+  };
+
+  const resizeAdditionalWindow = (token: number, width: number, height: number) => {
+    if (!closed) {
+      lib.symbols.resize_window_by_token(state, token, width, height);
+    }
+  };
+
+  const closeAdditionalWindow = (token: number) => {
+    if (!closed) {
+      lib.symbols.destroy_window_by_token(state, token);
+    }
+  };
+
+  const metrics = () => {
+    if (closed) {
+      throw new Error("Window is closed");
+    }
+    return readMetrics((buf, cap) => lib.symbols.window_metrics_json(buf, cap));
+  };
+
+  const capturePixels = () => {
+    if (closed) {
+      throw new Error("Window is closed");
+    }
+    const outWidth = new Uint32Array(1);
+    const outHeight = new Uint32Array(1);
+    const capture = (buf: Uint8Array | null) =>
+      lib.symbols.capture_window_pixels(
+        state,
+        buf ? Deno.UnsafePointer.of(buf) : null,
+        buf?.length ?? 0,
+        Deno.UnsafePointer.of(outWidth),
+        Deno.UnsafePointer.of(outHeight),
+      );
+    // The window can resize between the sizing call and the copy; retry once.
+    for (let attempt = 0; attempt < 2; attempt++) {
+      const needed = capture(null);
+      if (needed < 0) break;
+      const pixels = new Uint8Array(needed);
+      const written = capture(pixels);
+      if (written < 0) break;
+      if (written <= pixels.length) {
+        return { width: outWidth[0], height: outHeight[0], pixels };
+      }
+    }
+    throw nativeError({
+      code: () => lib.symbols.window_last_error_code(),
+      message: (buf, cap) => lib.symbols.window_last_error_message(buf, cap),
+    }, "Failed to capture window pixels");
+  };
+
+  const setGpuPreference = (pref: GPUPowerPreference | "default") => {
+    if (closed) {
+      return;
+    }
+    const id = GPU_PREFERENCES.indexOf(pref);
+    if (id < 0 || lib.symbols.set_gpu_preference(state, id) !== 0) {
+      throw new Error(`Invalid GPU preference: ${pref}`);
+    }
+  };
+
   const present = () => {
     if (closed) {
       return;
@@ -193,9 +609,13 @@ export async function createGpuWindow(device: GPUDevice, options: WindowOptions)
     }
     closed = true;
     pendingCloseEvent = false;
+    cancelUnload();
     try {
       lib.symbols.destroy_window(state);
     } finally {
+      releaseHitTest();
+      releaseEventCallback();
+      releaseBeforeDestroy();
       lib.close();
     }
   };
@@ -214,7 +634,36 @@ export async function createGpuWindow(device: GPUDevice, options: WindowOptions)
     get closed() {
       return closed;
     },
+    get nativeState() {
+      return closed ? null : state;
+    },
+    get gpuPreference() {
+      if (closed) {
+        return undefined;
+      }
+      const pref = GPU_PREFERENCES[lib.symbols.get_gpu_preference(state)];
+      return pref === "default" ? undefined : pref;
+    },
     pollEvents,
+    subscribeEvents,
+    onBeforeDestroy,
+    sendUserEvent,
+    setPointerCapture,
+    setAutoCaptureOnDrag,
+    beginDragMove,
+    beginDragResize,
+    setHitTest,
+    setParentWindow,
+    position,
+    setPosition,
+    openAdditionalWindow,
+    windowSize,
+    additionalWindowHandle,
+    resizeAdditionalWindow,
+    closeAdditionalWindow,
+    capturePixels,
+    setGpuPreference,
+    metrics,
     present,
     close,
   };